@@ -0,0 +1,88 @@
+//! Benchmarks for the editor's hot loops: typing with many cursors, search
+//! over large buffers and syntax highlighting. Run with `cargo bench`.
+
+use std::hint::black_box;
+use std::path::Path;
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use lux_edit::editor::Editor;
+use lux_edit::rope_search::SearchOptions;
+use lux_edit::syntax::SyntaxHighlighter;
+
+const SOURCE_LINE: &str = "    let value = compute(index, &mut buffer); // update the running total\n";
+
+/// A buffer of `lines` lines of Rust-like source.
+fn source(lines: usize) -> String {
+    SOURCE_LINE.repeat(lines)
+}
+
+/// An editor over `lines` lines with a cursor at the start of each of the
+/// first `cursors` lines.
+fn editor_with_cursors(lines: usize, cursors: usize) -> Editor {
+    let mut editor = Editor::from_text(None, &source(lines));
+    for line in 1..cursors {
+        editor.add_cursor_at(line, 4);
+    }
+    editor
+}
+
+fn typing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("typing");
+    for cursors in [1, 100, 1000] {
+        group.bench_with_input(BenchmarkId::new("insert_text", cursors), &cursors, |b, &cursors| {
+            b.iter_batched_ref(
+                || editor_with_cursors(10_000, cursors),
+                |editor| editor.insert_text("x"),
+                BatchSize::LargeInput,
+            )
+        });
+        group.bench_with_input(BenchmarkId::new("backspace", cursors), &cursors, |b, &cursors| {
+            b.iter_batched_ref(
+                || editor_with_cursors(10_000, cursors),
+                |editor| editor.backspace(),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn search(c: &mut Criterion) {
+    let mut group = c.benchmark_group("search");
+    group.sample_size(20);
+    // About 70 MB
+    let editor = Editor::from_text(None, &source(1_000_000));
+    let cases = [
+        ("literal", "running total", SearchOptions::default()),
+        ("ignore_case", "RUNNING TOTAL", SearchOptions { ignore_case: true, ..Default::default() }),
+        ("whole_word", "value", SearchOptions { whole_word: true, ..Default::default() }),
+        ("regex", r"compute\(\w+", SearchOptions { regex: true, ..Default::default() }),
+    ];
+    for (name, query, options) in cases {
+        group.bench_function(name, |b| {
+            b.iter(|| editor.search_iter(black_box(query), options).unwrap().count())
+        });
+    }
+    group.bench_function("no_match", |b| {
+        b.iter(|| editor.search_iter(black_box("not in the buffer"), SearchOptions::default()).unwrap().count())
+    });
+    group.finish();
+}
+
+fn highlighting(c: &mut Criterion) {
+    let mut group = c.benchmark_group("highlight");
+    let highlighter = SyntaxHighlighter::new();
+    let editor = Editor::from_text(None, &source(20_000));
+    let path = Some(Path::new("bench.rs"));
+    // A screenful at the top, and deep in the file where the lines above
+    // the viewport have to be parsed first: the work a highlight cache saves
+    for first in [0, 1_000, 10_000] {
+        group.bench_with_input(BenchmarkId::new("viewport", first), &first, |b, &first| {
+            b.iter(|| highlighter.highlight_lines(&editor.rope, path, first, first + 60))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, typing, search, highlighting);
+criterion_main!(benches);
@@ -0,0 +1,151 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use eframe::egui;
+
+/// Background work in progress, shown in the status bar. Subsystems
+/// register what they start and get an [`ActivityHandle`]; the entry stays
+/// listed until the handle is dropped. Cheap to clone and share with worker
+/// threads.
+#[derive(Clone)]
+pub struct Activities {
+    inner: Arc<Mutex<Inner>>,
+    ctx: egui::Context,
+}
+
+#[derive(Default)]
+struct Inner {
+    next_id: u64,
+    running: Vec<Entry>,
+}
+
+struct Entry {
+    id: u64,
+    label: String,
+    /// Present when the work can be stopped early.
+    cancel: Option<Arc<AtomicBool>>,
+}
+
+/// One running activity, as the status bar shows it.
+#[derive(Clone, Debug)]
+pub struct Activity {
+    pub id: u64,
+    pub label: String,
+    pub cancellable: bool,
+}
+
+impl Activities {
+    pub fn new(ctx: egui::Context) -> Self {
+        Self {
+            inner: Arc::default(),
+            ctx,
+        }
+    }
+
+    /// Register work that runs until the returned handle is dropped.
+    pub fn start(&self, label: impl Into<String>) -> ActivityHandle {
+        self.register(label.into(), None)
+    }
+
+    /// Like [`start`](Self::start), with a cancel button. The worker checks
+    /// [`ActivityHandle::is_cancelled`] and stops when it can; the entry
+    /// disappears from the list right away.
+    pub fn start_cancellable(&self, label: impl Into<String>) -> ActivityHandle {
+        self.register(label.into(), Some(Arc::new(AtomicBool::new(false))))
+    }
+
+    fn register(&self, label: String, cancel: Option<Arc<AtomicBool>>) -> ActivityHandle {
+        let mut inner = self.lock();
+        inner.next_id += 1;
+        let id = inner.next_id;
+        tracing::debug!(id, label = %label, "activity started");
+        inner.running.push(Entry { id, label, cancel: cancel.clone() });
+        self.ctx.request_repaint();
+        ActivityHandle {
+            id,
+            cancelled: cancel.unwrap_or_default(),
+            activities: self.clone(),
+        }
+    }
+
+    /// Running activities that haven't been cancelled, oldest first.
+    pub fn running(&self) -> Vec<Activity> {
+        let inner = self.lock();
+        inner
+            .running
+            .iter()
+            .filter(|e| !e.cancel.as_ref().is_some_and(|c| c.load(Ordering::Relaxed)))
+            .map(|e| Activity {
+                id: e.id,
+                label: e.label.clone(),
+                cancellable: e.cancel.is_some(),
+            })
+            .collect()
+    }
+
+    /// Ask a cancellable activity to stop.
+    pub fn cancel(&self, id: u64) {
+        let inner = self.lock();
+        if let Some(cancel) = inner.running.iter().find(|e| e.id == id).and_then(|e| e.cancel.as_ref()) {
+            tracing::debug!(id, "activity cancelled");
+            cancel.store(true, Ordering::Relaxed);
+        }
+    }
+
+    fn label(&self, id: u64) -> Option<String> {
+        self.lock().running.iter().find(|e| e.id == id).map(|e| e.label.clone())
+    }
+
+    fn set_label(&self, id: u64, label: String) {
+        let mut inner = self.lock();
+        if let Some(entry) = inner.running.iter_mut().find(|e| e.id == id) {
+            entry.label = label;
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        // A panicked worker leaves the list itself intact
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn finish(&self, id: u64) {
+        let mut inner = self.lock();
+        inner.running.retain(|e| e.id != id);
+        self.ctx.request_repaint();
+    }
+}
+
+/// Held by whoever does the work; dropping it ends the activity.
+pub struct ActivityHandle {
+    id: u64,
+    cancelled: Arc<AtomicBool>,
+    activities: Activities,
+}
+
+impl ActivityHandle {
+    /// Identifies the activity, for [`Activities::cancel`].
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// True once the user cancelled the activity.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// The text shown for the activity.
+    pub fn label(&self) -> String {
+        self.activities.label(self.id).unwrap_or_default()
+    }
+
+    /// Update the text shown for the activity, e.g. with a count so far.
+    pub fn set_label(&self, label: impl Into<String>) {
+        self.activities.set_label(self.id, label.into());
+    }
+}
+
+impl Drop for ActivityHandle {
+    fn drop(&mut self) {
+        self.activities.finish(self.id);
+    }
+}
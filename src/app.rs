@@ -1,42 +1,596 @@
 use arboard::Clipboard;
 use eframe::egui;
 
-use crate::editor::Editor;
-use crate::syntax::SyntaxHighlighter;
-use crate::ui::command_palette::{CommandId, CommandPalette};
+use crate::activity::Activities;
+use crate::calc;
+use crate::collab::{Presence, Session, SessionEvent};
+use crate::commands::{self, CommandId};
+use crate::config::{self, Chrome, ColorScheme, ConfigError, ConfigWatcher, Settings, Theme, DEFAULT_DATE_FORMAT};
+use crate::crash;
+use crate::debugger::DebugSession;
+use crate::diagnostics::Diagnostics;
+use crate::editor::{Editor, Indent, Position};
+use crate::edits::{self, CodeAction, EditReport, WorkspaceEdit};
+use crate::elevated;
+use crate::encryption::{self, Encryption};
+use crate::file_ops;
+use crate::git::{self, RemoteOutput};
+use crate::index::FileIndex;
+use crate::jobs::{JobPanic, Jobs};
+use crate::local_history;
+use crate::logging;
+use crate::lsp::{LanguageServers, LspEvent};
+use crate::markers::{self, MarkerType, ProjectMarkers};
+use crate::path_refs::{self, ReferenceEdit};
+use crate::reveal;
+use crate::rope_search::{SearchError, SearchOptions};
+use crate::runner::{self, Run};
+use crate::scratch;
+use crate::scripting::{self, Hook, ScriptHost};
+use crate::search_history::{self, Recall, SearchHistory};
+use crate::syntax::{self, IndentPolicy, SyntaxHighlighter};
+use crate::todos::{self, TodoIndex, TodoSource, WorkspaceTodos};
+use crate::ui::collab_window::{CollabRequest, CollabWindow};
+use crate::ui::command_palette::{self, CommandPalette, PaletteContext};
+use crate::ui::debug_panel;
+use crate::ui::diff_view::DiffView;
+use crate::ui::editor_view::{self, ViewOptions};
+use crate::ui::frame_times::FrameTimes;
+use crate::ui::input::{self, Layer};
+use crate::ui::file_info::FileInfoWindow;
+use crate::ui::local_history::LocalHistoryWindow;
+use crate::ui::output_panel::OutputPanel;
+use crate::ui::rename::RenameWindow;
+use crate::ui::script_console::ScriptConsole;
+use crate::ui::settings_window::{SavedConfig, SettingsWindow};
+use crate::ui::source_control::{ScmAction, SourceControl};
+use crate::ui::problems;
+use crate::ui::statistics;
+use crate::ui::toasts::Toasts;
+use crate::ui::todo_list;
+use crate::ui::undo_history;
+use crate::undo;
+use crate::wait::{self, WaitedFile};
+use crate::workspace::{LintOutput, TaskOutput, Workspace};
+use crate::workspace_search;
+use std::path::{Path, PathBuf};
 
 pub struct LuxApp {
     pub editors: Vec<Editor>,
     pub active_tab: usize,
+    /// The tabs were wider than the tab bar last frame, so the open files
+    /// dropdown is shown beside them.
+    tabs_overflow: bool,
+    /// Directory of the file last picked in an Open or Save dialog.
+    last_dialog_dir: Option<PathBuf>,
+    /// The tab last scrolled into view, to scroll again when it changes.
+    revealed_tab: Option<usize>,
+    /// Filter typed in the open files dropdown.
+    tab_filter: String,
     pub command_palette: CommandPalette,
+    /// Activity of the palette's running `#` search.
+    palette_search: Option<u64>,
     pub show_search: bool,
     pub show_replace: bool,
+    /// Move keyboard focus to the search field on the next frame, so keys
+    /// typed after Ctrl+F go to the query instead of the buffer.
+    focus_search: bool,
     pub search_input: String,
+    pub search_options: SearchOptions,
+    /// Why the last search failed, e.g. an invalid regex.
+    pub search_error: Option<String>,
     pub replace_input: String,
+    search_history: SearchHistory,
+    /// Up/Down position in the search history, one per field.
+    search_recall: Recall,
+    replace_recall: Recall,
     pub show_goto_line: bool,
+    pub show_undo_history: bool,
+    pub show_problems: bool,
+    pub show_todos: bool,
+    /// Bookmarks and breakpoints of the open project's files, saved as
+    /// they change.
+    markers: ProjectMarkers,
+    /// Language servers of the open files.
+    servers: LanguageServers,
+    /// Buffer version and cursor position code actions were last asked for.
+    code_actions_asked: Option<(u64, Position)>,
+    /// The fixes the palette is offering, by index.
+    offered_actions: Vec<CodeAction>,
+    /// The debug session, kept after it ends to show its output.
+    debug: Option<DebugSession>,
+    pub show_debug: bool,
+    /// TODO comments for the TODOs panel, scanned while it is open.
+    todos: TodoIndex,
+    pub show_statistics: bool,
+    output_panel: OutputPanel,
+    source_control: SourceControl,
+    /// The collaboration session on the shared tab, if any.
+    collab: Option<Session>,
+    collab_window: CollabWindow,
     pub goto_line_input: String,
+    /// Go to Line couldn't make sense of what was entered.
+    goto_line_invalid: bool,
+    /// The format being edited while the Insert Date/Time bar is open.
+    insert_date_format: Option<String>,
     pub clipboard: Option<Clipboard>,
     pub highlighter: SyntaxHighlighter,
     /// If Some, show a "save before closing?" dialog for this tab index.
     pub confirm_close_tab: Option<usize>,
+    /// If Some, show the unsaved-changes dialog for a multi-tab close.
+    pub pending_bulk_close: Option<BulkClose>,
+    /// Set once the user confirmed quitting, so the next close request passes.
+    pub allow_quit: bool,
+    /// True after Ctrl+K, waiting for the second key of a chord.
+    pub chord_pending: bool,
+    /// References to a just-moved file, awaiting confirmation (with per-edit toggles).
+    pub pending_ref_rewrite: Option<Vec<(bool, ReferenceEdit)>>,
+    /// A save refused for lack of permission, offered again as administrator.
+    pub pending_elevated_save: Option<(usize, PathBuf)>,
+    /// A Save As to a file another tab has open, awaiting confirmation to
+    /// close that tab.
+    pub pending_save_conflict: Option<(usize, PathBuf)>,
+    /// Buffer version last checked for lines too long to highlight, and
+    /// whether it had any.
+    long_lines: (u64, bool),
+    /// Files whose long-line notice was closed.
+    long_lines_dismissed: Vec<Option<PathBuf>>,
+    /// An encrypted file being opened, waiting for its passphrase.
+    passphrase_prompt: Option<PassphrasePrompt>,
+    pub toasts: Toasts,
+    frame_times: FrameTimes,
+    /// When open files were last checked for deletion on disk, and the
+    /// index generation at the time.
+    last_disk_check: (f64, u64),
+    /// The font was zoomed or the theme reloaded: measure cell widths again.
+    metrics_stale: bool,
+    /// Pinch or Ctrl+wheel zoom of the editor font over its setting, kept
+    /// for this session only.
+    font_zoom: f32,
+    /// The interface scale last applied.
+    ui_scale: f32,
+    /// Points to pixels last frame; it changes with the display's scaling,
+    /// e.g. when the window moves to another screen.
+    pixels_per_point: f32,
+    /// Buffers left behind by a crash, waiting for the user to restore or discard.
+    pub offer_recovery: bool,
+    /// The window is unfocused or minimized; background work should back off
+    /// and nothing animates until input arrives.
+    pub idle: bool,
+    /// User preferences from `settings.toml`; view toggles change them in memory.
+    pub settings: Settings,
+    /// Editor colors in use, for the current color scheme.
+    pub theme: Theme,
+    /// Colors from `theme.toml`, used by the dark scheme.
+    user_theme: Theme,
+    /// The scheme `theme` and the egui visuals were last set up for; `None`
+    /// sets them up again on the next frame.
+    scheme: Option<ColorScheme>,
+    chrome: Chrome,
+    pub settings_window: SettingsWindow,
+    pub local_history: LocalHistoryWindow,
+    pub file_info: FileInfoWindow,
+    pub diff_view: DiffView,
+    /// Started with --diff: quit when the comparison is closed, unless a
+    /// file was opened from it.
+    diff_only: bool,
+    /// Started with --wait: the file whose tab closing quits the editor.
+    wait_for: Option<WaitedFile>,
+    pub rename: RenameWindow,
+    /// Rhai engine for the console and the init script's hooks.
+    scripts: ScriptHost,
+    script_console: ScriptConsole,
+    /// Reloads settings, theme, keymap and init script when their files change.
+    config_watcher: Option<ConfigWatcher>,
+    /// Folder opened with "Open Folder", if any.
+    pub workspace: Option<Workspace>,
+    /// Background file and word index of the workspace.
+    pub index: Option<FileIndex>,
+    /// Window title last sent to the viewport.
+    window_title: String,
+    /// Fills each buffer's diagnostics from the registered providers.
+    diagnostics: Diagnostics,
+    /// Woken by background work that finishes between input events.
+    egui_ctx: egui::Context,
+    /// Background work listed in the status bar.
+    activities: Activities,
+    /// Tasks, linters, git remotes, elevated saves and decryption.
+    jobs: Jobs<JobDone>,
+}
+
+/// Passphrase entry for an encrypted file.
+struct PassphrasePrompt {
+    path: PathBuf,
+    kind: encryption::Kind,
+    input: String,
+    /// Why the last attempt failed, e.g. a wrong passphrase.
+    error: Option<String>,
+    /// Decryption is running.
+    busy: bool,
+}
+
+/// Result of a background job, handled on the UI thread.
+enum JobDone {
+    Task(TaskOutput),
+    Lint(LintOutput),
+    Remote(RemoteOutput),
+    Elevated(ElevatedSave),
+    Decrypted(Decrypted),
+    /// A scan of the workspace at this root for the TODOs panel.
+    Todos(PathBuf, WorkspaceTodos),
+    Rename(RenameHits),
+    /// Lines referring to a renamed file, to offer rewriting.
+    References(Vec<ReferenceEdit>),
+    /// A palette `#` search for this query finished with this many hits;
+    /// the palette already has them.
+    TextSearched(String, usize),
+    Panicked(JobPanic),
+}
+
+impl From<JobPanic> for JobDone {
+    fn from(panic: JobPanic) -> Self {
+        JobDone::Panicked(panic)
+    }
+}
+
+/// Occurrences of a word found for Rename Symbol.
+struct RenameHits {
+    word: String,
+    hits: Vec<workspace_search::WordHit>,
+    /// More were found than are listed.
+    truncated: bool,
+    /// Paths are shown relative to this.
+    root: Option<PathBuf>,
+}
+
+/// Outcome of decrypting a file in the background.
+struct Decrypted {
+    path: PathBuf,
+    result: std::io::Result<(String, Encryption)>,
+}
+
+/// Outcome of a save run through the elevated helper.
+struct ElevatedSave {
+    path: PathBuf,
+    /// Buffer version that was written; identifies the tab, and whether it
+    /// has been edited since.
+    version: u64,
+    result: std::io::Result<()>,
+}
+
+/// Seconds after the last edit before a scratch buffer is written out.
+const SCRATCH_SAVE_DELAY: f64 = 1.0;
+
+/// Seconds between checks that open files still exist on disk.
+const DISK_CHECK_INTERVAL: f64 = 2.0;
+
+/// Rename Symbol lists at most this many occurrences.
+const MAX_RENAME_HITS: usize = 2000;
+
+/// Index words offered per completion request.
+const MAX_INDEX_COMPLETIONS: usize = 50;
+
+/// How the file at `path` is encrypted, judging by its first bytes.
+fn encryption_of(path: &std::path::Path) -> Option<encryption::Kind> {
+    use std::io::Read;
+    let mut head = Vec::new();
+    std::fs::File::open(path).ok()?.take(64).read_to_end(&mut head).ok()?;
+    encryption::detect(path, &head)
+}
+
+fn file_name(path: &std::path::Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+/// User indentation, then what the file itself uses, then any project
+/// override, and last what the language requires.
+fn apply_indent(settings: &Settings, workspace: Option<&Workspace>, editor: &mut Editor) {
+    editor.indent.width = settings.tab_width.max(1);
+    editor.indent.use_tabs = settings.use_tabs;
+    editor.indent_detected = false;
+    if settings.detect_indentation {
+        if let Some(detected) = Indent::detect(&editor.rope) {
+            editor.indent.use_tabs = detected.use_tabs;
+            // A tab's width is a display preference, not in the file
+            if !detected.use_tabs {
+                editor.indent.width = detected.width;
+            }
+            editor.indent_detected = true;
+        }
+    }
+    if let Some(ws) = workspace {
+        ws.apply_to(editor);
+    }
+    match syntax::indent_policy_for(editor.file_path.as_deref()) {
+        Some(IndentPolicy::Tabs) => editor.indent.use_tabs = true,
+        Some(IndentPolicy::Spaces) => editor.indent.use_tabs = false,
+        None => {}
+    }
+}
+
+/// Whitespace clean-up the user asked for on every save.
+/// Tab titles: the file name, followed by as much of its directory as
+/// tells it apart from other open files with the same name, e.g.
+/// `mod.rs — parser/`.
+fn tab_titles(editors: &[Editor]) -> Vec<String> {
+    // Parent directory names, innermost first
+    let dirs = |path: &std::path::Path| -> Vec<String> {
+        let parent = path.parent().into_iter().flat_map(|p| p.components().rev());
+        parent
+            .filter_map(|c| match c {
+                std::path::Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+                _ => None,
+            })
+            .collect()
+    };
+    editors
+        .iter()
+        .enumerate()
+        .map(|(i, editor)| {
+            let Some(path) = &editor.file_path else {
+                return editor.title.clone();
+            };
+            let others: Vec<Vec<String>> = editors
+                .iter()
+                .enumerate()
+                .filter(|&(j, _)| j != i)
+                .filter_map(|(_, other)| other.file_path.as_deref())
+                .filter(|other| other.file_name() == path.file_name())
+                .map(dirs)
+                .collect();
+            let own = dirs(path);
+            if others.is_empty() || own.is_empty() {
+                return editor.title.clone();
+            }
+            let depth = (1..=own.len())
+                .find(|&depth| others.iter().all(|other| other.get(..depth) != Some(&own[..depth])))
+                .unwrap_or(own.len());
+            let hint: Vec<&str> = own[..depth].iter().rev().map(String::as_str).collect();
+            format!("{} \u{2014} {}/", editor.title, hint.join("/"))
+        })
+        .collect()
+}
+
+fn prepare_save(settings: &Settings, editor: &mut Editor) {
+    if !settings.trim_trailing_whitespace_on_save && !settings.ensure_final_newline {
+        return;
+    }
+    editor.labeled_edit("Format on Save", |editor| {
+        if settings.trim_trailing_whitespace_on_save {
+            editor.trim_trailing_whitespace();
+        }
+        if settings.ensure_final_newline {
+            editor.ensure_final_newline();
+        }
+    });
+}
+
+/// Keep a copy of what was just saved in the local history. Encrypted
+/// files are left out, since history is stored in plain text.
+fn record_history(path: &std::path::Path, editor: &Editor) {
+    if editor.encryption.is_some() {
+        return;
+    }
+    if let Err(e) = local_history::record(path, &editor.rope.to_string()) {
+        tracing::warn!(path = %path.display(), error = %e, "failed to record local history");
+    }
+}
+
+/// A close operation spanning several tabs that may need confirmation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BulkClose {
+    /// Close every unpinned tab.
+    CloseAllTabs,
+    /// Close the application window.
+    Quit,
 }
 
 impl LuxApp {
-    pub fn new(_cc: &eframe::CreationContext<'_>) -> Self {
-        Self {
+    pub fn new(
+        cc: &eframe::CreationContext<'_>,
+        recover: bool,
+        merge: Option<PathBuf>,
+        diff: Option<(PathBuf, PathBuf)>,
+        wait: Option<PathBuf>,
+    ) -> Self {
+        let mut app = Self {
             editors: vec![Editor::new()],
             active_tab: 0,
+            tabs_overflow: false,
+            last_dialog_dir: None,
+            revealed_tab: None,
+            tab_filter: String::new(),
             command_palette: CommandPalette::new(),
+            palette_search: None,
             show_search: false,
             show_replace: false,
+            focus_search: false,
             search_input: String::new(),
+            search_options: SearchOptions::default(),
+            search_error: None,
             replace_input: String::new(),
+            search_history: SearchHistory::load(),
+            search_recall: Recall::default(),
+            replace_recall: Recall::default(),
             show_goto_line: false,
+            show_undo_history: false,
+            show_problems: false,
+            show_todos: false,
+            todos: TodoIndex::default(),
+            servers: LanguageServers::default(),
+            code_actions_asked: None,
+            offered_actions: Vec::new(),
+            debug: None,
+            show_debug: false,
+            show_statistics: false,
+            output_panel: OutputPanel::default(),
+            source_control: SourceControl::default(),
+            collab: None,
+            collab_window: CollabWindow::default(),
             goto_line_input: String::new(),
+            goto_line_invalid: false,
+            insert_date_format: None,
             clipboard: Clipboard::new().ok(),
             highlighter: SyntaxHighlighter::new(),
             confirm_close_tab: None,
+            pending_bulk_close: None,
+            allow_quit: false,
+            chord_pending: false,
+            pending_ref_rewrite: None,
+            pending_elevated_save: None,
+            pending_save_conflict: None,
+            long_lines: (0, false),
+            long_lines_dismissed: Vec::new(),
+            passphrase_prompt: None,
+            toasts: Toasts::default(),
+            frame_times: FrameTimes::default(),
+            last_disk_check: (0.0, 0),
+            metrics_stale: false,
+            font_zoom: 1.0,
+            ui_scale: 0.0,
+            pixels_per_point: 0.0,
+            offer_recovery: false,
+            idle: false,
+            settings: Settings::default(),
+            theme: Theme::default(),
+            user_theme: Theme::default(),
+            scheme: None,
+            chrome: Chrome::default(),
+            settings_window: SettingsWindow::new(),
+            local_history: LocalHistoryWindow::new(),
+            file_info: FileInfoWindow::new(),
+            diff_view: DiffView::new(),
+            diff_only: false,
+            wait_for: None,
+            rename: RenameWindow::new(),
+            scripts: ScriptHost::new(),
+            markers: ProjectMarkers::load(None),
+            script_console: ScriptConsole::default(),
+            config_watcher: ConfigWatcher::start(cc.egui_ctx.clone()),
+            workspace: None,
+            index: None,
+            window_title: String::new(),
+            diagnostics: Diagnostics::new(),
+            egui_ctx: cc.egui_ctx.clone(),
+            activities: Activities::new(cc.egui_ctx.clone()),
+            jobs: Jobs::new(cc.egui_ctx.clone()),
+        };
+        for file in [config::SETTINGS_FILE, config::THEME_FILE, config::KEYMAP_FILE, scripting::INIT_SCRIPT] {
+            app.reload_config(file);
+        }
+        if crash::has_recovery() {
+            if recover {
+                app.restore_recovered();
+            } else {
+                app.offer_recovery = true;
+            }
+        }
+        if let Some(path) = merge {
+            app.open_merge(path);
+        }
+        if let Some((old, new)) = diff {
+            app.diff_view.open_for(old, new);
+            app.diff_only = true;
+        }
+        if let Some(path) = wait {
+            app.open_wait(path);
+        }
+        app
+    }
+
+    /// Open the file given with --wait in place of the empty startup tab.
+    /// A file that doesn't exist yet opens empty, and saving creates it.
+    fn open_wait(&mut self, path: PathBuf) {
+        let startup = &self.editors[0];
+        let pristine = self.editors.len() == 1 && startup.file_path.is_none() && !startup.modified;
+        let opened = if path.exists() {
+            self.open_path(path.clone())
+        } else {
+            self.add_opened(Editor::for_new_file(path.clone()));
+            true
+        };
+        if !opened {
+            // Nothing to wait for; quitting reports the failure
+            wait::set_failed(true);
+            return;
+        }
+        if pristine {
+            self.editors.remove(0);
+            self.active_tab = self.editors.len() - 1;
+        }
+        tracing::info!(path = %path.display(), "waiting for file to be closed");
+        self.toasts.info(format!("Save and close {} when done", file_name(&path)));
+        self.wait_for = Some(WaitedFile::new(path, &self.editors[self.active_tab]));
+    }
+
+    fn is_wait_tab(&self, idx: usize) -> bool {
+        self.wait_for.as_ref().is_some_and(|waited| waited.is_tab(&self.editors[idx]))
+    }
+
+    /// Note whether the file given with --wait holds its tab's text, and
+    /// quit once the tab is closed.
+    fn check_wait_tab(&mut self, ctx: &egui::Context) {
+        let Some(waited) = &mut self.wait_for else {
+            return;
+        };
+        let open = waited.observe(&self.editors);
+        wait::set_failed(waited.discarded());
+        if !open {
+            tracing::info!(path = %waited.path().display(), discarded = waited.discarded(), "waited-for file closed");
+            self.wait_for = None;
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+        }
+    }
+
+    /// Open a file with merge conflicts in place of the empty startup tab,
+    /// at its first conflict.
+    fn open_merge(&mut self, path: PathBuf) {
+        let startup = &self.editors[0];
+        let pristine = self.editors.len() == 1 && startup.file_path.is_none() && !startup.modified;
+        if !self.open_path(path.clone()) {
+            return;
+        }
+        if pristine {
+            self.editors.remove(0);
+            self.active_tab = self.editors.len() - 1;
         }
+        let editor = self.active_editor();
+        let count = editor.conflicts().len();
+        editor.goto_conflict(true);
+        tracing::info!(path = %path.display(), count, "opened file to merge");
+        if count == 0 {
+            self.toasts.info(format!("No conflicts left in {}", file_name(&path)));
+        } else {
+            self.toasts.info(format!("{} conflict(s) in {}; save and close when resolved", count, file_name(&path)));
+        }
+    }
+
+    /// Reopen the buffers saved by the crash handler, replacing the empty
+    /// startup tab.
+    fn restore_recovered(&mut self) {
+        let buffers = crash::load_recovery();
+        crash::clear_recovery();
+        if buffers.is_empty() {
+            return;
+        }
+        let count = buffers.len();
+        self.editors = buffers
+            .into_iter()
+            .map(|b| {
+                let mut editor = Editor::from_text(b.path, &b.text);
+                editor.modified = b.modified;
+                editor.goto_line(b.line + 1);
+                let line = editor.cursors[0].pos.line;
+                editor.cursors[0].pos.col = b.col.min(editor.line_text(line).chars().count());
+                editor.cursors[0].desired_col = editor.cursors[0].pos.col;
+                editor
+            })
+            .collect();
+        self.active_tab = 0;
+        tracing::info!(count, "restored buffers after a crash");
+        self.toasts.info(format!("Restored {} buffer(s) after a crash", count));
     }
 
     fn active_editor(&mut self) -> &mut Editor {
@@ -44,8 +598,224 @@ impl LuxApp {
     }
 
     fn new_tab(&mut self) {
-        self.editors.push(Editor::new());
+        let mut editor = Editor::new();
+        editor.line_ending = self.settings.default_line_ending;
+        apply_indent(&self.settings, self.workspace.as_ref(), &mut editor);
+        self.editors.push(editor);
+        self.active_tab = self.editors.len() - 1;
+    }
+
+    /// Switch to the scratch buffer `name`, loading or creating it.
+    fn open_scratch(&mut self, name: String) {
+        if let Some(idx) = self.editors.iter().position(|e| e.scratch.as_ref() == Some(&name)) {
+            self.active_tab = idx;
+            return;
+        }
+        let text = match scratch::load(&name) {
+            Ok(text) => text,
+            Err(e) => {
+                tracing::error!(scratch = %name, error = %e, "failed to load scratch buffer");
+                self.toasts.error(format!("Failed to open scratch buffer {}", name), e.to_string());
+                return;
+            }
+        };
+        let mut editor = Editor::scratch(name, &text);
+        apply_indent(&self.settings, self.workspace.as_ref(), &mut editor);
+        self.editors.push(editor);
         self.active_tab = self.editors.len() - 1;
+        // Write it out right away so quick-open lists it
+        self.save_scratch(self.active_tab);
+    }
+
+    /// Write a scratch buffer to the app data directory.
+    fn save_scratch(&mut self, idx: usize) -> bool {
+        let editor = &mut self.editors[idx];
+        let Some(name) = editor.scratch.clone() else {
+            return false;
+        };
+        match scratch::save(&name, &editor.rope.to_string()) {
+            Ok(()) => {
+                editor.modified = false;
+                true
+            }
+            Err(e) => {
+                tracing::error!(scratch = %name, error = %e, "failed to save scratch buffer");
+                self.toasts.error(format!("Failed to save scratch buffer {}", name), e.to_string());
+                false
+            }
+        }
+    }
+
+    /// Persist scratch buffers edited more than `delay` seconds ago.
+    fn save_scratches(&mut self, now: f64, delay: f64) {
+        for idx in 0..self.editors.len() {
+            let editor = &self.editors[idx];
+            if editor.scratch.is_some() && editor.modified && now - editor.last_edit_time >= delay {
+                self.save_scratch(idx);
+            }
+        }
+    }
+
+    /// Mark tabs whose file was deleted or moved away by something else.
+    fn check_files_on_disk(&mut self) {
+        for editor in &mut self.editors {
+            if let Some(path) = &editor.file_path {
+                editor.deleted_on_disk = !path.exists();
+            }
+        }
+    }
+
+    /// Move tab `idx`'s file to the trash. The tab closes unless it has
+    /// unsaved changes, which stay open marked as deleted.
+    fn delete_file(&mut self, idx: usize, path: &std::path::Path) {
+        if let Err(e) = file_ops::trash(path) {
+            tracing::error!(path = %path.display(), error = %e, "failed to move file to trash");
+            self.toasts.error(format!("Failed to delete {}", file_name(path)), e.to_string());
+            return;
+        }
+        tracing::info!(path = %path.display(), "moved file to trash");
+        self.toasts.info(format!("Moved {} to the trash", file_name(path)));
+        self.editors[idx].deleted_on_disk = true;
+        if !self.editors[idx].modified && self.editors.len() > 1 {
+            self.force_close_tab(idx);
+        }
+    }
+
+    /// Close the active scratch buffer and delete what was persisted of it.
+    fn delete_active_scratch(&mut self) {
+        let Some(name) = self.editors[self.active_tab].scratch.clone() else {
+            self.toasts.info("The active tab is not a scratch buffer");
+            return;
+        };
+        if let Err(e) = scratch::delete(&name) {
+            tracing::error!(scratch = %name, error = %e, "failed to delete scratch buffer");
+            self.toasts.error(format!("Failed to delete scratch buffer {}", name), e.to_string());
+            return;
+        }
+        if self.editors.len() > 1 {
+            self.force_close_tab(self.active_tab);
+        } else {
+            self.editors[0] = Editor::new();
+        }
+        self.toasts.info(format!("Deleted scratch buffer {}", name));
+    }
+
+    /// Run the scripts' `hook` functions on a tab, reporting failures.
+    fn run_script_hooks(&mut self, hook: Hook, idx: usize) {
+        if !self.scripts.has_hooks(hook) {
+            return;
+        }
+        let scripts = &mut self.scripts;
+        let result = self.editors[idx].labeled_edit("Script Hooks", |editor| scripts.run_hooks(hook, editor));
+        if let Err(e) = result {
+            tracing::warn!(error = %e, "script hook failed");
+            self.toasts.error("A script hook failed", e);
+        }
+    }
+
+    /// Load one config file and apply it. A file that fails to parse leaves
+    /// the previous values in place and shows where the problem is.
+    fn reload_config(&mut self, file: &str) {
+        let result = match file {
+            config::SETTINGS_FILE => config::load_settings().map(|settings| {
+                self.highlighter.palette = settings.syntax_palette;
+                undo::set_memory_budget(settings.undo_memory_mb.saturating_mul(1024 * 1024));
+                self.settings = settings;
+                self.scheme = None;
+                for editor in &mut self.editors {
+                    apply_indent(&self.settings, self.workspace.as_ref(), editor);
+                }
+            }),
+            config::THEME_FILE => config::load_theme().map(|theme| {
+                self.user_theme = theme;
+                self.scheme = None;
+            }),
+            config::KEYMAP_FILE => config::load_keymap().map(|keymap| {
+                commands::set_keymap(keymap);
+                self.command_palette.refresh_shortcuts();
+            }),
+            scripting::INIT_SCRIPT => self.scripts.load_init(),
+            _ => Ok(()),
+        };
+        match result {
+            Ok(()) => tracing::debug!(file, "config loaded"),
+            Err(e) => self.report_config_error(file, e),
+        }
+    }
+
+    /// Switch the editor, syntax and egui colors over to `scheme`.
+    fn apply_scheme(&mut self, ctx: &egui::Context, scheme: ColorScheme) {
+        tracing::debug!(?scheme, "applying color scheme");
+        self.theme = match scheme {
+            ColorScheme::Light => Theme::light(),
+            ColorScheme::HighContrast => Theme::high_contrast(),
+            _ => self.user_theme.clone(),
+        };
+        self.chrome = scheme.chrome();
+        self.highlighter.background = self.theme.background;
+        self.highlighter.light = scheme.is_light();
+        self.highlighter.min_contrast = match scheme {
+            // Syntax colors are held to the AAA ratio against pure black
+            ColorScheme::HighContrast => Some(self.settings.min_contrast.unwrap_or(0.0).max(7.0)),
+            _ => self.settings.min_contrast,
+        };
+        // egui keeps visuals per theme; set the one it will draw with
+        ctx.set_theme(if scheme.is_light() { egui::Theme::Light } else { egui::Theme::Dark });
+        ctx.set_visuals(scheme.visuals());
+        self.scheme = Some(scheme);
+        self.metrics_stale = true;
+    }
+
+    /// Scale the interface by `ui_scale` over the display's own scaling, and
+    /// measure the editor again whenever the result changes.
+    fn apply_ui_scale(&mut self, ctx: &egui::Context) {
+        let scale = self.settings.ui_scale.clamp(*config::UI_SCALE_RANGE.start(), *config::UI_SCALE_RANGE.end());
+        if scale != self.ui_scale {
+            ctx.set_zoom_factor(scale);
+            self.ui_scale = scale;
+        } else if ctx.zoom_factor() != scale {
+            // Ctrl+Plus / Ctrl+Minus zoomed the interface for this session
+            self.ui_scale = ctx.zoom_factor();
+            self.settings.ui_scale = self.ui_scale;
+        }
+        let pixels_per_point = ctx.pixels_per_point();
+        if pixels_per_point != self.pixels_per_point {
+            tracing::info!(pixels_per_point, native = ?ctx.native_pixels_per_point(), "display scale changed");
+            self.pixels_per_point = pixels_per_point;
+            self.metrics_stale = true;
+        }
+    }
+
+    /// Write the settings window's changes and apply them like any other
+    /// config file edit.
+    fn save_config(&mut self, saved: SavedConfig) {
+        let results = [
+            (config::SETTINGS_FILE, config::save_settings(&saved.settings)),
+            (config::THEME_FILE, config::save_theme(&saved.theme)),
+            (config::KEYMAP_FILE, config::save_keymap(&saved.keymap)),
+        ];
+        for (file, result) in results {
+            match result {
+                Ok(true) => self.reload_config(file),
+                Ok(false) => {}
+                Err(e) => {
+                    tracing::error!(file, error = %e, "failed to write config file");
+                    self.toasts.error(
+                        format!("Failed to save {}", file),
+                        format!("{}\n{}", config::config_dir().join(file).display(), e),
+                    );
+                }
+            }
+        }
+    }
+
+    fn report_config_error(&mut self, file: &str, e: ConfigError) {
+        tracing::warn!(error = %e, "config file not applied");
+        let message = match e.location {
+            Some((line, col)) => format!("{} has an error at line {}, column {}", file, line, col),
+            None => format!("{} could not be loaded", file),
+        };
+        self.toasts.error(message, e);
     }
 
     fn close_tab(&mut self) {
@@ -53,9 +823,13 @@ impl LuxApp {
     }
 
     fn close_tab_idx(&mut self, idx: usize) {
-        if self.editors.len() <= 1 {
+        // The last tab stays open, unless closing it is what --wait waits for
+        if self.editors.len() <= 1 && !self.is_wait_tab(idx) {
             return;
         }
+        if self.editors[idx].scratch.is_some() {
+            self.save_scratch(idx);
+        }
         if self.editors[idx].modified {
             self.confirm_close_tab = Some(idx);
         } else {
@@ -64,145 +838,1644 @@ impl LuxApp {
     }
 
     fn force_close_tab(&mut self, idx: usize) {
+        if let Some(waited) = &mut self.wait_for {
+            waited.observe(&self.editors);
+        }
         if self.editors.len() > 1 {
             self.editors.remove(idx);
             if self.active_tab >= self.editors.len() {
                 self.active_tab = self.editors.len() - 1;
             }
+        } else if self.is_wait_tab(idx) {
+            self.editors[idx] = Editor::new();
         }
         self.confirm_close_tab = None;
     }
 
+    /// Pin or unpin a tab, moving it to the boundary of the pinned group so
+    /// pinned tabs always stay at the left of the tab strip.
+    fn toggle_pin_tab(&mut self, idx: usize) {
+        let pinned = !self.editors[idx].pinned;
+        self.editors[idx].pinned = pinned;
+        self.editors[idx].preview = false;
+        let pinned_count = self.editors.iter().filter(|e| e.pinned).count();
+        let target = if pinned { pinned_count - 1 } else { pinned_count };
+        self.move_tab(idx, target);
+    }
+
+    fn move_tab(&mut self, from: usize, to: usize) {
+        if from == to {
+            return;
+        }
+        let editor = self.editors.remove(from);
+        self.editors.insert(to, editor);
+        if self.active_tab == from {
+            self.active_tab = to;
+        } else if from < self.active_tab && to >= self.active_tab {
+            self.active_tab -= 1;
+        } else if from > self.active_tab && to <= self.active_tab {
+            self.active_tab += 1;
+        }
+    }
+
     fn open_file(&mut self) {
-        if let Some(path) = rfd::FileDialog::new().pick_file() {
-            match Editor::from_file(path) {
-                Ok(editor) => {
-                    self.editors.push(editor);
-                    self.active_tab = self.editors.len() - 1;
+        if let Some(path) = self.file_dialog(self.active_tab, false).pick_file() {
+            self.last_dialog_dir = path.parent().map(Path::to_path_buf);
+            self.open_path(path);
+        }
+    }
+
+    /// Where dialogs for tab `idx` start: its file's directory, else where
+    /// the last one picked a file, else the workspace root.
+    fn dialog_directory(&self, idx: usize) -> Option<PathBuf> {
+        self.editors[idx]
+            .file_path
+            .as_deref()
+            .and_then(Path::parent)
+            .map(Path::to_path_buf)
+            .or_else(|| self.last_dialog_dir.clone())
+            .or_else(|| self.workspace.as_ref().map(|ws| ws.root.clone()))
+    }
+
+    /// A file dialog for tab `idx` offering a filter for each language the
+    /// highlighter knows. "All files" comes first when opening; when
+    /// `saving`, the tab's own language comes before it.
+    fn file_dialog(&self, idx: usize, saving: bool) -> rfd::FileDialog {
+        let mut dialog = rfd::FileDialog::new();
+        if let Some(dir) = self.dialog_directory(idx) {
+            dialog = dialog.set_directory(dir);
+        }
+        let own = self.editors[idx].file_path.as_deref().filter(|_| saving);
+        let mut types = self.highlighter.file_types(own).into_iter();
+        if own.is_some() {
+            if let Some((name, extensions)) = types.next() {
+                dialog = dialog.add_filter(name, &extensions);
+            }
+        }
+        dialog = dialog.add_filter("All files", &["*"]);
+        for (name, extensions) in types {
+            dialog = dialog.add_filter(name, &extensions);
+        }
+        dialog
+    }
+
+    /// Open `path` in a new tab, or switch to the tab already showing it.
+    /// Returns false if the file could not be read, or is encrypted and
+    /// waits for its passphrase.
+    fn open_path(&mut self, path: std::path::PathBuf) -> bool {
+        if let Some(idx) = self.editors.iter().position(|e| e.file_path.as_ref() == Some(&path)) {
+            self.active_tab = idx;
+            self.editors[idx].preview = false;
+            return true;
+        }
+        if let Some(kind) = encryption_of(&path) {
+            tracing::info!(path = %path.display(), kind = kind.name(), "opening encrypted file");
+            self.passphrase_prompt = Some(PassphrasePrompt {
+                path,
+                kind,
+                input: String::new(),
+                error: None,
+                busy: false,
+            });
+            return false;
+        }
+        match Editor::from_file(path.clone()) {
+            Ok(editor) => {
+                tracing::info!(path = %path.display(), "opened file");
+                self.add_opened(editor);
+                true
+            }
+            Err(e) => {
+                tracing::error!(path = %path.display(), error = %e, "failed to open file");
+                self.toasts.error(
+                    format!("Failed to open {}", file_name(&path)),
+                    format!("{}\n{}", path.display(), e),
+                );
+                false
+            }
+        }
+    }
+
+    /// Open `path` in the preview tab, in place of the file previewed
+    /// there. A file already open is just switched to.
+    fn open_preview(&mut self, path: PathBuf) -> bool {
+        if !self.settings.preview_tabs {
+            return self.open_path(path);
+        }
+        if let Some(idx) = self.editors.iter().position(|e| e.file_path.as_ref() == Some(&path)) {
+            self.active_tab = idx;
+            return true;
+        }
+        let previous = self.editors.iter().position(|e| e.preview && !e.modified);
+        if !self.open_path(path) {
+            return false;
+        }
+        let opened = self.editors.len() - 1;
+        self.editors[opened].preview = true;
+        if let Some(previous) = previous {
+            // Take the old preview's place in the tab strip
+            let editor = self.editors.remove(opened);
+            self.editors[previous] = editor;
+            self.active_tab = previous;
+        }
+        true
+    }
+
+    fn add_opened(&mut self, mut editor: Editor) {
+        apply_indent(&self.settings, self.workspace.as_ref(), &mut editor);
+        self.diagnostics.refresh(&mut editor);
+        if let Some(path) = &editor.file_path {
+            editor.set_markers(self.markers.get(path));
+        }
+        self.editors.push(editor);
+        self.active_tab = self.editors.len() - 1;
+        self.run_script_hooks(Hook::Open, self.active_tab);
+    }
+
+    fn show_passphrase_prompt(&mut self, ctx: &egui::Context) {
+        let Some(prompt) = &mut self.passphrase_prompt else {
+            return;
+        };
+        let mut submit = false;
+        let mut cancel = false;
+        egui::Window::new("Encrypted File")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "\"{}\" is encrypted with {}. Enter its passphrase:",
+                    file_name(&prompt.path),
+                    prompt.kind.name()
+                ));
+                let response = ui.add_enabled(
+                    !prompt.busy,
+                    egui::TextEdit::singleline(&mut prompt.input).password(true).desired_width(280.0),
+                );
+                if !prompt.busy && !response.has_focus() && !response.lost_focus() {
+                    response.request_focus();
                 }
-                Err(e) => {
-                    eprintln!("Failed to open file: {}", e);
+                submit = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                if let Some(error) = &prompt.error {
+                    ui.colored_label(egui::Color32::from_rgb(240, 110, 100), error);
                 }
+                ui.add_space(8.0);
+                ui.horizontal(|ui| {
+                    if prompt.busy {
+                        ui.spinner();
+                        ui.label("Decrypting\u{2026}");
+                    } else if ui.button("Open").clicked() {
+                        submit = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        cancel = true;
+                    }
+                });
+            });
+        if cancel {
+            self.passphrase_prompt = None;
+        } else if submit && !prompt.busy {
+            prompt.busy = true;
+            prompt.error = None;
+            let passphrase = std::mem::take(&mut prompt.input).into();
+            let (path, kind) = (prompt.path.clone(), prompt.kind);
+            // Key derivation takes a moment; keep drawing meanwhile
+            let activity = self.activities.start(format!("Decrypting {}", file_name(&path)));
+            self.jobs.spawn(activity, move |_| {
+                let result = std::fs::read(&path).and_then(|bytes| encryption::decrypt(&path, &bytes, kind, passphrase));
+                JobDone::Decrypted(Decrypted { path, result })
+            });
+        }
+    }
+
+    fn decrypted(&mut self, decrypted: Decrypted) {
+        let path = decrypted.path;
+        // Cancelled while decrypting: the plaintext is dropped
+        let Some(prompt) = self.passphrase_prompt.as_mut().filter(|p| p.path == path) else {
+            return;
+        };
+        match decrypted.result {
+            Ok((text, encryption)) => {
+                tracing::info!(path = %path.display(), "opened encrypted file");
+                self.passphrase_prompt = None;
+                self.add_opened(Editor::from_decrypted(path, &text, encryption));
+            }
+            Err(e) => {
+                tracing::warn!(path = %path.display(), error = %e, "failed to decrypt file");
+                prompt.busy = false;
+                prompt.error = Some(e.to_string());
             }
         }
     }
 
-    fn save_file(&mut self) {
-        let editor = &mut self.editors[self.active_tab];
-        if editor.file_path.is_some() {
-            if let Err(e) = editor.save() {
-                eprintln!("Failed to save: {}", e);
+    fn open_folder(&mut self) {
+        let mut dialog = rfd::FileDialog::new();
+        if let Some(dir) = self.dialog_directory(self.active_tab) {
+            dialog = dialog.set_directory(dir);
+        }
+        if let Some(root) = dialog.pick_folder() {
+            self.open_workspace(root);
+        }
+    }
+
+    /// Open the checkout the active file belongs to, or its directory, as
+    /// the workspace.
+    fn open_containing_folder(&mut self) {
+        let Some(path) = self.editors[self.active_tab].file_path.clone() else {
+            self.toasts.info("The buffer hasn't been saved to a file");
+            return;
+        };
+        let Some(root) = path_refs::workspace_root_for(&path) else {
+            return;
+        };
+        if self.workspace.as_ref().is_some_and(|ws| ws.root == root) {
+            return;
+        }
+        self.open_workspace(root);
+    }
+
+    /// Make `root` the workspace and apply its project settings.
+    fn open_workspace(&mut self, root: std::path::PathBuf) {
+        match Workspace::open(root.clone()) {
+            Ok(ws) => {
+                tracing::info!(root = %root.display(), tasks = ws.settings.tasks.len(), "opened workspace");
+                for editor in &mut self.editors {
+                    ws.apply_to(editor);
+                }
+                self.command_palette
+                    .set_tasks(ws.settings.tasks.iter().map(|t| t.name.as_str()));
+                self.command_palette
+                    .set_debug_targets(ws.settings.debug.iter().map(|t| t.name.as_str()));
+                self.toasts.info(format!("Opened folder {}", ws.name()));
+                self.index = Some(FileIndex::start(ws.root.clone(), self.egui_ctx.clone(), self.activities.clone()));
+                self.todos.forget_workspace();
+                self.load_markers(Some(&ws.root));
+                if self.source_control.open {
+                    self.source_control.open_for(Some(&ws.root));
+                }
+                self.workspace = Some(ws);
+            }
+            Err(e) => {
+                tracing::error!(root = %root.display(), error = %e, "failed to open workspace");
+                self.toasts.error(format!("Invalid project settings in {}", file_name(&root)), e);
             }
-        } else {
-            self.save_file_as();
         }
     }
 
-    fn save_file_as(&mut self) {
-        if let Some(path) = rfd::FileDialog::new().save_file() {
-            if let Err(e) = self.editors[self.active_tab].save_as(path) {
-                eprintln!("Failed to save: {}", e);
+    fn close_folder(&mut self) {
+        self.workspace = None;
+        self.index = None;
+        self.todos.forget_workspace();
+        self.load_markers(None);
+        self.source_control.close();
+        self.command_palette.set_tasks([]);
+        self.command_palette.set_debug_targets([]);
+    }
+
+    /// Switch to the markers saved for the project at `root` and show them
+    /// in the open files.
+    fn load_markers(&mut self, root: Option<&std::path::Path>) {
+        self.save_markers();
+        self.markers = ProjectMarkers::load(root);
+        for editor in &mut self.editors {
+            if let Some(path) = &editor.file_path {
+                editor.set_markers(self.markers.get(path));
             }
         }
     }
 
-    fn handle_command(&mut self, cmd: CommandId) {
-        match cmd {
-            CommandId::NewTab => self.new_tab(),
-            CommandId::OpenFile => self.open_file(),
-            CommandId::SaveFile => self.save_file(),
-            CommandId::SaveFileAs => self.save_file_as(),
-            CommandId::CloseTab => self.close_tab(),
-            CommandId::Find => {
-                self.show_search = true;
-                self.show_goto_line = false;
+    /// Save the markers of files whose markers changed, and give the debug
+    /// session their new breakpoints.
+    fn save_markers(&mut self) {
+        for editor in &mut self.editors {
+            if !std::mem::take(&mut editor.markers_changed) {
+                continue;
             }
-            CommandId::GoToLine => {
-                self.show_goto_line = true;
-                self.show_search = false;
+            if let Some(path) = &editor.file_path {
+                if let Err(e) = self.markers.set(path, &editor.markers) {
+                    tracing::warn!(path = %path.display(), error = %e, "failed to save bookmarks");
+                }
+                if let Some(session) = &mut self.debug {
+                    session.set_breakpoints(path, markers::lines_of(&editor.markers, MarkerType::Breakpoint));
+                }
+            }
+        }
+    }
+
+    /// Start language servers for newly opened files, send them the
+    /// buffers' changes and act on what they send back.
+    fn sync_language_servers(&mut self) {
+        if !self.settings.language_servers {
+            if !self.servers.is_empty() {
+                self.servers.stop_all();
+            }
+            return;
+        }
+        for (name, e) in self.servers.sync(&self.editors, &self.egui_ctx) {
+            self.toasts.error(format!("Could not start {}", name), e);
+        }
+        for event in self.servers.poll() {
+            match event {
+                LspEvent::Diagnostics(path, diagnostics) => {
+                    self.diagnostics.server_published(&path, &diagnostics);
+                    for editor in self.editors.iter_mut().filter(|e| e.file_path.as_ref() == Some(&path)) {
+                        self.diagnostics.refresh(editor);
+                    }
+                }
+                LspEvent::Completions { path, version, items } => {
+                    // Dropped if the buffer changed or the tab was left meanwhile
+                    let editor = &self.editors[self.active_tab];
+                    if editor.file_path.as_ref() != Some(&path) || editor.version != version {
+                        continue;
+                    }
+                    let prefix = editor.word_prefix_at_cursor().to_lowercase();
+                    let mut items: Vec<String> =
+                        items.into_iter().filter(|item| item.to_lowercase().starts_with(&prefix)).collect();
+                    match items.len() {
+                        0 => self.offer_word_completions(),
+                        1 => self.active_editor().complete_word(&items.remove(0)),
+                        _ => self.command_palette.open_completions(items),
+                    }
+                }
+                LspEvent::Definition(locations) => match locations.into_iter().next() {
+                    Some((path, pos)) => {
+                        if self.open_path(path) {
+                            self.active_editor().goto_line_col(pos.line + 1, pos.col + 1);
+                        }
+                    }
+                    None => self.toasts.info("No definition found"),
+                },
+                LspEvent::CodeActions { path, version, pos, actions } => {
+                    if let Some(editor) = self.editors.iter_mut().find(|e| e.file_path.as_ref() == Some(&path)) {
+                        editor.code_actions = Some((version, pos, actions));
+                    }
+                }
+                LspEvent::Failed { server, message } => {
+                    self.toasts.error(format!("{} exited", server), message);
+                }
+            }
+        }
+        // Ask what can be done at the cursor whenever it or the text moves
+        let editor = &self.editors[self.active_tab];
+        let at = (editor.version, editor.cursors[0].pos);
+        if self.code_actions_asked != Some(at) {
+            let server = editor.file_path.as_deref().and_then(|p| self.servers.for_path(p));
+            if server.is_some_and(|server| server.code_actions(editor)) {
+                self.code_actions_asked = Some(at);
+            }
+        }
+    }
+
+    /// Offer the fixes at the active editor's cursor in the palette.
+    fn show_code_actions(&mut self) {
+        self.offered_actions = self.editors[self.active_tab].code_actions_at_cursor();
+        if self.offered_actions.is_empty() {
+            self.toasts.info("No fixes available here");
+            return;
+        }
+        let titles = self.offered_actions.iter().map(|a| format!("{}  ({})", a.title, a.source)).collect();
+        self.command_palette.open_code_actions(titles);
+    }
+
+    fn apply_code_action(&mut self, index: usize) {
+        let Some(action) = self.offered_actions.get(index).cloned() else {
+            return;
+        };
+        self.offered_actions.clear();
+        self.apply_workspace_edit(&action.title, &action.edit);
+    }
+
+    /// Find the word at the cursor across the workspace and ask what to
+    /// rename it to.
+    fn rename_symbol(&mut self) {
+        let editor = &self.editors[self.active_tab];
+        let Some((start, end)) = editor.word_under_cursor() else {
+            self.toasts.info("Put the cursor on a word to rename it");
+            return;
+        };
+        let word: String = editor.line_text(start.line).chars().skip(start.col).take(end.col - start.col).collect();
+        let (root, indexed) = self.searched_files();
+        // Open buffers are searched as they are, not as saved
+        let open: Vec<(PathBuf, ropey::Rope)> = self
+            .editors
+            .iter()
+            .filter_map(|e| Some((e.file_path.clone()?, e.rope.clone())))
+            .collect();
+        let activity = self.activities.start_cancellable(format!("Finding '{}'", word));
+        self.jobs.spawn(activity, move |_| {
+            let mut files = indexed.unwrap_or_else(|| {
+                let mut files = Vec::new();
+                if let Some(root) = &root {
+                    path_refs::collect_files(root, &mut files);
+                    files.sort();
+                }
+                files
+            });
+            for (path, _) in &open {
+                if !files.contains(path) {
+                    files.push(path.clone());
+                }
+            }
+            let mut hits = workspace_search::find_word(&files, &open, &word, MAX_RENAME_HITS + 1);
+            let truncated = hits.len() > MAX_RENAME_HITS;
+            hits.truncate(MAX_RENAME_HITS);
+            tracing::debug!(word = %word, hits = hits.len(), files = files.len(), "searched for symbol to rename");
+            JobDone::Rename(RenameHits { word, hits, truncated, root })
+        });
+    }
+
+    /// Start the palette's `#` search on the job pool, stopping the one it
+    /// replaces, and stop it if the palette closed.
+    fn run_palette_search(&mut self) {
+        if let Some(search) = self.command_palette.take_search_request() {
+            if let Some(previous) = self.palette_search.take() {
+                self.activities.cancel(previous);
+            }
+            let activity = self.activities.start_cancellable(format!("Searching for '{}'", search.query()));
+            self.palette_search = Some(activity.id());
+            let ctx = self.egui_ctx.clone();
+            self.jobs.spawn(activity, move |activity| {
+                let query = search.query().to_string();
+                JobDone::TextSearched(query, search.run(activity, &ctx))
+            });
+        } else if !self.command_palette.visible {
+            if let Some(previous) = self.palette_search.take() {
+                self.activities.cancel(previous);
+            }
+        }
+    }
+
+    /// The folder a workspace-wide search covers and, when they are already
+    /// known, its files: the indexed workspace files. Without a workspace it
+    /// is the active file's checkout, whose files the search collects.
+    fn searched_files(&self) -> (Option<PathBuf>, Option<Vec<PathBuf>>) {
+        if let (Some(ws), Some(index)) = (&self.workspace, &self.index) {
+            let files = index.files().iter().filter(|f| !ws.is_excluded(f)).cloned().collect();
+            return (Some(ws.root.clone()), Some(files));
+        }
+        let root = self.editors[self.active_tab].file_path.as_deref().and_then(path_refs::workspace_root_for);
+        (root, None)
+    }
+
+    /// Apply edits across files, open or not, as one undo step named
+    /// `label` in each open buffer, and say what changed. Returns None if
+    /// nothing was applied.
+    fn apply_workspace_edit(&mut self, label: &str, edit: &WorkspaceEdit) -> Option<EditReport> {
+        match edits::apply(edit, label, &mut self.editors) {
+            Ok(report) => {
+                tracing::info!(label, buffers = report.buffers.len(), files = report.files.len(), "applied edits");
+                if report.files.len() + report.buffers.len() > 1 {
+                    self.toasts.info(report.summary());
+                }
+                if !report.files.is_empty() && self.source_control.open {
+                    self.source_control.refresh();
+                }
+                Some(report)
+            }
+            Err(e) => {
+                tracing::warn!(label, error = %e, "failed to apply edits");
+                self.toasts.error(format!("Could not apply {}", label), e);
+                None
+            }
+        }
+    }
+
+    fn goto_definition(&mut self) {
+        let editor = &self.editors[self.active_tab];
+        let server = editor.file_path.as_deref().and_then(|p| self.servers.for_path(p));
+        let asked = server.is_some_and(|server| server.definition(editor));
+        if !asked {
+            self.toasts.info("No language server is ready for this file");
+        }
+    }
+
+    /// Start a debug session for the workspace debug target `name`,
+    /// replacing (and stopping) the last one.
+    fn start_debugging(&mut self, name: &str) {
+        let Some(ws) = &self.workspace else {
+            return;
+        };
+        let Some(target) = ws.settings.debug.iter().find(|t| t.name == name).cloned() else {
+            return;
+        };
+        let root = ws.root.clone();
+        self.save_markers();
+        if let Some(mut session) = self.debug.take() {
+            session.stop();
+        }
+        let breakpoints = self.markers.lines(MarkerType::Breakpoint);
+        match DebugSession::start(target, &root, breakpoints, self.egui_ctx.clone()) {
+            Ok(session) => {
+                self.debug = Some(session);
+                self.show_debug = true;
+            }
+            Err(e) => {
+                tracing::warn!(target = %name, error = %e, "failed to start debug adapter");
+                self.toasts.error(format!("Could not start debugging {}", name), e);
+            }
+        }
+    }
+
+    /// Take in debug adapter messages, show where the program stopped and
+    /// mark the execution line in the open files.
+    fn sync_debugger(&mut self) {
+        let Some(session) = &mut self.debug else {
+            return;
+        };
+        session.poll();
+        if let Some((path, line)) = session.reveal.take() {
+            if self.open_path(path) {
+                self.active_editor().goto_line(line + 1);
+            }
+        }
+        let at = self.debug.as_ref().and_then(|s| s.execution_line());
+        for editor in &mut self.editors {
+            editor.execution_line = at.filter(|(path, _)| editor.file_path.as_deref() == Some(*path)).map(|(_, line)| line);
+        }
+    }
+
+    /// Run `action` on the debug session, if there is one.
+    fn with_debugger(&mut self, action: fn(&mut DebugSession)) {
+        match &mut self.debug {
+            Some(session) => action(session),
+            None => self.toasts.info("No debug session; run Debug: Start from the palette"),
+        }
+    }
+
+    /// Scan the workspace for the TODOs panel again if its files changed
+    /// since the last scan. Unchanged files keep their earlier results.
+    fn refresh_workspace_todos(&mut self) {
+        let (Some(ws), Some(index)) = (&self.workspace, &self.index) else {
+            return;
+        };
+        let generation = index.generation();
+        if !self.todos.include_workspace || self.todos.scanning || !index.is_ready() || self.todos.scanned_generation == Some(generation) {
+            return;
+        }
+        self.todos.scanned_generation = Some(generation);
+        self.todos.scanning = true;
+        let (root, files, previous) = (ws.root.clone(), index.files(), self.todos.workspace.clone());
+        let activity = self.activities.start("Scanning for TODOs");
+        self.jobs.spawn(activity, move |_| JobDone::Todos(root, todos::scan_workspace(&files, &previous)));
+    }
+
+    fn todos_scanned(&mut self, root: PathBuf, found: WorkspaceTodos) {
+        self.todos.scanning = false;
+        // A scan of a folder that has since been closed
+        if self.workspace.as_ref().is_some_and(|ws| ws.root == root) {
+            tracing::debug!(files = found.len(), "scanned workspace for TODOs");
+            self.todos.workspace = std::sync::Arc::new(found);
+        }
+    }
+
+    /// Run a workspace task on a background thread; the result arrives as a toast.
+    fn run_task(&mut self, name: &str) {
+        let Some(ws) = &self.workspace else {
+            return;
+        };
+        let Some(task) = ws.settings.tasks.iter().find(|t| t.name == name).cloned() else {
+            return;
+        };
+        let root = ws.root.clone();
+        tracing::info!(task = %task.name, command = %task.command, "running task");
+        self.toasts.info(format!("Running task {}", task.name));
+        let activity = self.activities.start(format!("Running task {}", task.name));
+        self.jobs.spawn(activity, move |_| JobDone::Task(task.run(&root)));
+    }
+
+    fn task_finished(&mut self, result: TaskOutput) {
+        tracing::info!(task = %result.name, success = result.success, "task finished");
+        if let Some(ws) = &self.workspace {
+            self.diagnostics.task_finished(&ws.root, &result);
+            for editor in &mut self.editors {
+                self.diagnostics.refresh(editor);
+            }
+        }
+        if result.success {
+            self.toasts.info(format!("Task {} finished", result.name));
+        } else {
+            self.toasts.error(format!("Task {} failed", result.name), result.output);
+        }
+    }
+
+    /// Bring everything that follows files on disk up to date after `path`
+    /// was saved.
+    fn saved(&mut self, path: &std::path::Path) {
+        self.run_linters(path);
+        self.servers.did_save(path);
+        if self.source_control.open {
+            self.source_control.refresh();
+        }
+    }
+
+    /// Run the workspace linters covering `path` in the background; their
+    /// problems arrive as diagnostics.
+    fn run_linters(&mut self, path: &std::path::Path) {
+        let Some(ws) = &self.workspace else {
+            return;
+        };
+        for linter in ws.settings.linters.iter().filter(|l| l.matches(&ws.root, path)) {
+            let linter = linter.clone();
+            let root = ws.root.clone();
+            let path = path.to_path_buf();
+            tracing::debug!(linter = %linter.name, path = %path.display(), "running linter");
+            let activity = self.activities.start(format!("Linting with {}", linter.name));
+            self.jobs.spawn(activity, move |_| JobDone::Lint(linter.run(&root, &path)));
+        }
+    }
+
+    fn lint_finished(&mut self, result: LintOutput) {
+        tracing::info!(linter = %result.linter, success = result.success, "linter finished");
+        self.diagnostics.lint_finished(&result);
+        for editor in &mut self.editors {
+            self.diagnostics.refresh(editor);
+        }
+        // Linters exit non-zero when they find problems; only complain
+        // when there was nothing to show for it
+        let reported = self.diagnostics.problems().iter().any(|p| p.source == result.linter);
+        if !result.success && !reported {
+            let details = if result.errors.trim().is_empty() { result.output } else { result.errors };
+            self.toasts.error(format!("Linter {} failed", result.linter), details);
+        }
+    }
+
+    /// Save the active file and run it with the command configured for its
+    /// extension, showing the output in the output panel.
+    fn run_current_file(&mut self) {
+        let Some(path) = self.editors[self.active_tab].file_path.clone() else {
+            self.toasts.info("Save the file before running it");
+            return;
+        };
+        let Some(command) = runner::command_for(&self.settings.run, &path) else {
+            let ext = path.extension().map(|e| e.to_string_lossy().into_owned()).unwrap_or_default();
+            self.toasts.info(format!("No run command for .{} files; add one under [run] in settings.toml", ext));
+            return;
+        };
+        if self.editors[self.active_tab].modified && !self.save_tab(self.active_tab) {
+            return;
+        }
+        let cwd = match &self.workspace {
+            Some(ws) if path.starts_with(&ws.root) => ws.root.clone(),
+            _ => path.parent().map(PathBuf::from).unwrap_or_default(),
+        };
+        self.output_panel.start(Run::start(command, cwd, self.egui_ctx.clone()));
+    }
+
+    fn handle_scm_action(&mut self, action: ScmAction) {
+        match action {
+            ScmAction::Open(path) => {
+                self.open_path(path);
+            }
+            ScmAction::Committed(id) => self.toasts.info(format!("Committed {}", id)),
+            ScmAction::Remote(remote) => {
+                let Some(ws) = &self.workspace else {
+                    return;
+                };
+                let root = ws.root.clone();
+                tracing::info!(remote = remote.name(), "running git remote command");
+                self.source_control.busy = true;
+                let activity = self.activities.start(format!("Running git {}", remote.name().to_lowercase()));
+                self.jobs.spawn(activity, move |_| JobDone::Remote(git::run_remote(&root, remote)));
+            }
+        }
+    }
+
+    fn handle_collab_request(&mut self, request: CollabRequest) {
+        match request {
+            CollabRequest::Host { name, port, public } => {
+                if self.editors[self.active_tab].encryption.is_some() {
+                    self.toasts.info("Encrypted files can't be shared");
+                    return;
+                }
+                let ctx = self.egui_ctx.clone();
+                match Session::host(name, port, public, &self.editors[self.active_tab], ctx) {
+                    Ok(session) => {
+                        self.active_editor().shared = true;
+                        self.collab = Some(session);
+                        self.toasts.info(format!("Hosting on port {}", port));
+                    }
+                    Err(e) => self.toasts.error(format!("Could not host on port {}", port), e),
+                }
+            }
+            CollabRequest::Join { name, url } => {
+                self.new_tab();
+                let editor = self.active_editor();
+                editor.shared = true;
+                editor.title = "Shared".into();
+                self.collab = Some(Session::join(name, url, self.egui_ctx.clone()));
+            }
+            CollabRequest::Follow(peer) => {
+                if let Some(session) = &mut self.collab {
+                    session.following = peer;
+                }
+            }
+            CollabRequest::Leave => self.leave_collab(),
+        }
+    }
+
+    fn follow_next_collaborator(&mut self) {
+        let Some(session) = &mut self.collab else {
+            self.toasts.info("Not in a collaboration session");
+            return;
+        };
+        let message = match session.follow_next() {
+            Some(presence) => format!("Following {}", presence.name),
+            None => "Stopped following".to_string(),
+        };
+        self.toasts.info(message);
+        if let Some(idx) = self.editors.iter().position(|e| e.shared) {
+            self.active_tab = idx;
+        }
+    }
+
+    fn leave_collab(&mut self) {
+        if self.collab.take().is_some() {
+            tracing::info!("left collaboration session");
+        }
+        for editor in &mut self.editors {
+            editor.shared = false;
+        }
+    }
+
+    /// Trade edits and cursors with the session. Closing the shared tab
+    /// leaves it.
+    fn sync_collab(&mut self) {
+        let Some(session) = &mut self.collab else {
+            return;
+        };
+        let Some(editor) = self.editors.iter_mut().find(|e| e.shared) else {
+            self.leave_collab();
+            return;
+        };
+        let (changed, events) = session.update(editor);
+        if changed {
+            self.egui_ctx.request_repaint();
+        }
+        for event in events {
+            match event {
+                SessionEvent::Joined(name) => self.toasts.info(format!("{} joined", name)),
+                SessionEvent::Left(name) => self.toasts.info(format!("{} left", name)),
+                SessionEvent::Ended(reason) => {
+                    self.toasts.error("Collaboration session ended", reason);
+                    self.leave_collab();
+                }
+            }
+        }
+    }
+
+    fn remote_finished(&mut self, result: RemoteOutput) {
+        tracing::info!(remote = result.remote.name(), success = result.success, "git remote command finished");
+        self.source_control.busy = false;
+        self.source_control.refresh();
+        if result.success {
+            self.toasts.info(format!("{} finished", result.remote.name()));
+        } else {
+            self.toasts.error(format!("{} failed", result.remote.name()), result.output);
+        }
+    }
+
+    fn save_file(&mut self) {
+        self.save_tab(self.active_tab);
+    }
+
+    fn save_file_as(&mut self) {
+        self.save_tab_as(self.active_tab);
+    }
+
+    /// Save a tab, asking for a path if it has none. Returns true on success.
+    fn save_tab(&mut self, idx: usize) -> bool {
+        if self.editors[idx].scratch.is_some() {
+            return self.save_scratch(idx);
+        }
+        if let Some(path) = self.editors[idx].file_path.clone() {
+            self.run_script_hooks(Hook::Save, idx);
+            let editor = &mut self.editors[idx];
+            if editor.deleted_on_disk && path.parent().is_some_and(|dir| !dir.exists()) {
+                // Its folder is gone too; ask where it should go now
+                return self.save_tab_as(idx);
+            }
+            let recreated = editor.deleted_on_disk;
+            prepare_save(&self.settings, editor);
+            match editor.save() {
+                Ok(()) => {
+                    tracing::debug!(path = %path.display(), "saved file");
+                    if recreated {
+                        self.toasts.info(format!("Recreated {}", file_name(&path)));
+                    }
+                    record_history(&path, editor);
+                    self.diagnostics.refresh(editor);
+                    self.saved(&path);
+                    true
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+                    tracing::warn!(path = %path.display(), "permission denied saving file");
+                    self.pending_elevated_save = Some((idx, path));
+                    false
+                }
+                Err(e) => {
+                    tracing::error!(path = %path.display(), error = %e, "failed to save file");
+                    self.toasts.error(
+                        format!("Failed to save {}", file_name(&path)),
+                        format!("{}\n{}", path.display(), e),
+                    );
+                    false
+                }
+            }
+        } else {
+            self.save_tab_as(idx)
+        }
+    }
+
+    /// Ask where to save a tab, suggesting its current name, and save it
+    /// there. Returns true on success; a path another tab has open waits
+    /// for confirmation first.
+    fn save_tab_as(&mut self, idx: usize) -> bool {
+        let editor = &self.editors[idx];
+        let name = match &editor.file_path {
+            Some(path) => file_name(path),
+            None => editor.title.clone(),
+        };
+        let Some(path) = self
+            .file_dialog(idx, true)
+            .set_title(format!("Save \"{}\"", editor.title))
+            .set_file_name(name)
+            .save_file()
+        else {
+            return false;
+        };
+        self.last_dialog_dir = path.parent().map(Path::to_path_buf);
+        let taken = self.editors.iter().enumerate().any(|(i, e)| i != idx && e.file_path.as_ref() == Some(&path));
+        if taken {
+            self.pending_save_conflict = Some((idx, path));
+            return false;
+        }
+        self.save_tab_to(idx, path)
+    }
+
+    /// Save a tab under a new path. Returns true on success.
+    fn save_tab_to(&mut self, idx: usize, path: PathBuf) -> bool {
+        self.run_script_hooks(Hook::Save, idx);
+        prepare_save(&self.settings, &mut self.editors[idx]);
+        match self.editors[idx].save_as(path.clone()) {
+            Ok(()) => {
+                tracing::debug!(path = %path.display(), "saved file");
+                record_history(&path, &self.editors[idx]);
+                // The new name may be a language with its own indentation
+                apply_indent(&self.settings, self.workspace.as_ref(), &mut self.editors[idx]);
+                self.diagnostics.refresh(&mut self.editors[idx]);
+                self.saved(&path);
+                true
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+                tracing::warn!(path = %path.display(), "permission denied saving file");
+                self.pending_elevated_save = Some((idx, path));
+                false
+            }
+            Err(e) => {
+                tracing::error!(path = %path.display(), error = %e, "failed to save file");
+                self.toasts.error(
+                    format!("Failed to save {}", file_name(&path)),
+                    format!("{}\n{}", path.display(), e),
+                );
+                false
+            }
+        }
+    }
+
+    /// Write a tab through the elevated helper on a background thread, so the
+    /// window keeps drawing while the system asks for a password.
+    fn save_tab_elevated(&mut self, idx: usize, path: PathBuf) {
+        self.run_script_hooks(Hook::Save, idx);
+        let editor = &mut self.editors[idx];
+        prepare_save(&self.settings, editor);
+        let contents = match editor.file_contents() {
+            Ok(contents) => contents,
+            Err(e) => {
+                self.toasts.error(format!("Failed to encrypt {}", file_name(&path)), e);
+                return;
+            }
+        };
+        let version = editor.version;
+        tracing::info!(path = %path.display(), "saving file as administrator");
+        let activity = self.activities.start(format!("Saving {} as administrator", file_name(&path)));
+        self.jobs.spawn(activity, move |_| {
+            let result = elevated::write(&path, &contents);
+            JobDone::Elevated(ElevatedSave { path, version, result })
+        });
+    }
+
+    fn elevated_save_finished(&mut self, save: ElevatedSave) {
+        let path = save.path;
+        if let Err(e) = save.result {
+            tracing::error!(path = %path.display(), error = %e, "elevated save failed");
+            self.toasts.error(
+                format!("Failed to save {} as administrator", file_name(&path)),
+                format!("{}\n{}", path.display(), e),
+            );
+            return;
+        }
+        tracing::debug!(path = %path.display(), "saved file as administrator");
+        self.toasts.info(format!("Saved {} as administrator", file_name(&path)));
+        // A tab edited while the prompt was up stays modified
+        if let Some(editor) = self.editors.iter_mut().find(|e| e.version == save.version) {
+            editor.mark_saved(path.clone());
+            record_history(&path, editor);
+            self.diagnostics.refresh(editor);
+            self.saved(&path);
+        }
+    }
+
+    /// Hand the results of finished background jobs to their features.
+    fn handle_finished_jobs(&mut self) {
+        for done in self.jobs.finished() {
+            match done {
+                JobDone::Task(result) => self.task_finished(result),
+                JobDone::Lint(result) => self.lint_finished(result),
+                JobDone::Remote(result) => self.remote_finished(result),
+                JobDone::Elevated(save) => self.elevated_save_finished(save),
+                JobDone::Decrypted(decrypted) => self.decrypted(decrypted),
+                JobDone::Todos(root, found) => self.todos_scanned(root, found),
+                JobDone::Rename(found) => self.rename.open_for(found.word, found.hits, found.truncated, found.root),
+                JobDone::References(edits) if !edits.is_empty() => {
+                    self.pending_ref_rewrite = Some(edits.into_iter().map(|e| (true, e)).collect());
+                }
+                JobDone::References(_) => {}
+                JobDone::TextSearched(query, hits) => tracing::debug!(query = %query, hits, "searched workspace text"),
+                JobDone::Panicked(panic) => self.job_panicked(panic),
+            }
+        }
+    }
+
+    fn job_panicked(&mut self, panic: JobPanic) {
+        tracing::error!(job = %panic.label, message = %panic.message, "background job panicked");
+        // No result is coming for it; don't leave its view waiting forever
+        self.todos.scanning = false;
+        self.source_control.busy = false;
+        if let Some(prompt) = &mut self.passphrase_prompt {
+            prompt.busy = false;
+        }
+        self.toasts.error(format!("{} failed", panic.label), panic.message);
+    }
+
+    fn show_elevated_save_dialog(&mut self, ctx: &egui::Context) {
+        let Some((idx, path)) = self.pending_elevated_save.clone() else {
+            return;
+        };
+        let mut choice = None;
+        egui::Window::new("Permission Denied")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(format!("You don't have permission to write \"{}\".", path.display()));
+                ui.add_space(8.0);
+                ui.horizontal(|ui| {
+                    if ui.button("Save as Administrator").clicked() {
+                        choice = Some(true);
+                    }
+                    if ui.button("Cancel").clicked() {
+                        choice = Some(false);
+                    }
+                });
+            });
+        match choice {
+            Some(true) => {
+                self.pending_elevated_save = None;
+                if idx < self.editors.len() {
+                    self.save_tab_elevated(idx, path);
+                }
+            }
+            Some(false) => self.pending_elevated_save = None,
+            None => {}
+        }
+    }
+
+    /// Confirm a Save As over a file open in another tab. The saved tab
+    /// takes the other one's place, so the file stays open only once.
+    fn show_save_conflict_dialog(&mut self, ctx: &egui::Context) {
+        let Some((idx, path)) = self.pending_save_conflict.clone() else {
+            return;
+        };
+        let other = self.editors.iter().enumerate().position(|(i, e)| i != idx && e.file_path.as_ref() == Some(&path));
+        let (Some(other), true) = (other, idx < self.editors.len()) else {
+            self.pending_save_conflict = None;
+            return;
+        };
+        let mut choice = None;
+        egui::Window::new("File Already Open")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(format!("\"{}\" is open in another tab.", file_name(&path)));
+                if self.editors[other].modified {
+                    ui.label("That tab has unsaved changes, which will be lost.");
+                }
+                ui.add_space(8.0);
+                ui.horizontal(|ui| {
+                    if ui.button("Save and Close Other Tab").clicked() {
+                        choice = Some(true);
+                    }
+                    if ui.button("Cancel").clicked() {
+                        choice = Some(false);
+                    }
+                });
+            });
+        match choice {
+            Some(true) => {
+                self.pending_save_conflict = None;
+                if self.save_tab_to(idx, path) {
+                    let pinned = self.editors.remove(other).pinned;
+                    let idx = if other < idx { idx - 1 } else { idx };
+                    self.active_tab = idx;
+                    // A pinned tab stays among the pinned ones
+                    if pinned || !self.editors[idx].pinned {
+                        self.editors[idx].pinned = pinned;
+                        self.move_tab(idx, other.min(self.editors.len() - 1));
+                    }
+                }
+            }
+            Some(false) => self.pending_save_conflict = None,
+            None => {}
+        }
+    }
+
+    /// Rename or move the active file, then offer to rewrite workspace
+    /// references that pointed at its old location.
+    fn rename_active_file(&mut self) {
+        let idx = self.active_tab;
+        let Some(old) = self.editors[idx].file_path.clone() else {
+            // Nothing on disk to move yet
+            self.save_file_as();
+            return;
+        };
+        let mut dialog = rfd::FileDialog::new().set_title("Rename/Move File");
+        if let Some(dir) = old.parent() {
+            dialog = dialog.set_directory(dir);
+        }
+        if let Some(name) = old.file_name() {
+            dialog = dialog.set_file_name(name.to_string_lossy());
+        }
+        let Some(new) = dialog.save_file() else {
+            return;
+        };
+        if new == old {
+            return;
+        }
+        if let Err(e) = self.editors[idx].rename_file(new.clone()) {
+            tracing::error!(from = %old.display(), to = %new.display(), error = %e, "failed to rename file");
+            self.toasts.error(
+                format!("Failed to rename {}", file_name(&old)),
+                format!("{} -> {}\n{}", old.display(), new.display(), e),
+            );
+            return;
+        }
+
+        tracing::info!(from = %old.display(), to = %new.display(), "renamed file");
+
+        let Some(root) = path_refs::workspace_root_for(&old) else {
+            return;
+        };
+        // Open buffers are searched as they are, not as saved
+        let open: Vec<(PathBuf, ropey::Rope)> = self
+            .editors
+            .iter()
+            .filter_map(|e| Some((e.file_path.clone()?, e.rope.clone())))
+            .collect();
+        let activity = self.activities.start_cancellable(format!("Finding references to {}", file_name(&old)));
+        self.jobs.spawn(activity, move |_| {
+            let mut files = Vec::new();
+            path_refs::collect_files(&root, &mut files);
+            files.sort();
+            let edits = path_refs::find_references(&files, &open, &old, &new);
+            tracing::debug!(root = %root.display(), count = edits.len(), "found references to moved file");
+            JobDone::References(edits)
+        });
+    }
+
+    /// Apply the chosen reference rewrites, open buffers as one undo step
+    /// each and other files on disk, all or none of them.
+    fn apply_reference_rewrite(&mut self, edits: &[ReferenceEdit]) {
+        let edit = path_refs::workspace_edit(edits);
+        // Several files already get the report's summary
+        if let Some(report) = self.apply_workspace_edit("Update References", &edit) {
+            if report.buffers.len() + report.files.len() == 1 {
+                self.toasts.info(format!("Updated {} reference(s)", report.edit_count()));
+            }
+        }
+    }
+
+    /// Save every modified tab. Stops at the first failure or cancelled
+    /// dialog and returns false.
+    fn save_all(&mut self) -> bool {
+        for idx in 0..self.editors.len() {
+            if self.editors[idx].modified && !self.save_tab(idx) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Tabs affected by a bulk close.
+    fn bulk_close_targets(&self, op: BulkClose) -> Vec<usize> {
+        (0..self.editors.len())
+            .filter(|&i| op == BulkClose::Quit || !self.editors[i].pinned)
+            .collect()
+    }
+
+    fn close_all_tabs(&mut self) {
+        let targets = self.bulk_close_targets(BulkClose::CloseAllTabs);
+        if targets.iter().any(|&i| self.editors[i].modified) {
+            self.pending_bulk_close = Some(BulkClose::CloseAllTabs);
+        } else {
+            self.force_close_all_tabs();
+        }
+    }
+
+    /// Remove every unpinned tab, keeping at least one editor open.
+    fn force_close_all_tabs(&mut self) {
+        if let Some(waited) = &mut self.wait_for {
+            waited.observe(&self.editors);
+        }
+        self.editors.retain(|e| e.pinned);
+        if self.editors.is_empty() {
+            self.editors.push(Editor::new());
+        }
+        self.active_tab = self.active_tab.min(self.editors.len() - 1);
+    }
+
+    /// Carry out a confirmed bulk close.
+    fn finish_bulk_close(&mut self, ctx: &egui::Context, op: BulkClose) {
+        self.pending_bulk_close = None;
+        match op {
+            BulkClose::CloseAllTabs => self.force_close_all_tabs(),
+            BulkClose::Quit => {
+                self.allow_quit = true;
+                ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+            }
+        }
+    }
+
+    fn handle_command(&mut self, cmd: CommandId) {
+        match cmd {
+            CommandId::NewTab => self.new_tab(),
+            CommandId::NewScratch => self.command_palette.open_scratch_name(),
+            CommandId::DeleteScratch => self.delete_active_scratch(),
+            CommandId::OpenScratch(name) => self.open_scratch(name),
+            CommandId::OpenFile => self.open_file(),
+            CommandId::OpenFolder => self.open_folder(),
+            CommandId::OpenContainingFolder => self.open_containing_folder(),
+            CommandId::CloseFolder => self.close_folder(),
+            CommandId::RunTask(name) => self.run_task(&name),
+            CommandId::StartDebugging(name) => self.start_debugging(&name),
+            CommandId::DebugContinue => self.with_debugger(DebugSession::resume),
+            CommandId::DebugPause => self.with_debugger(DebugSession::pause),
+            CommandId::DebugStepOver => self.with_debugger(DebugSession::step_over),
+            CommandId::DebugStepInto => self.with_debugger(DebugSession::step_into),
+            CommandId::DebugStepOut => self.with_debugger(DebugSession::step_out),
+            CommandId::DebugStop => self.with_debugger(DebugSession::stop),
+            CommandId::ShowDebug => self.show_debug = !self.show_debug,
+            CommandId::SaveFile => self.save_file(),
+            CommandId::SaveFileAs => self.save_file_as(),
+            CommandId::CloseTab => self.close_tab(),
+            CommandId::NextTab => {
+                self.active_tab = (self.active_tab + 1) % self.editors.len();
+            }
+            CommandId::PrevTab => {
+                self.active_tab = self.active_tab.checked_sub(1).unwrap_or(self.editors.len() - 1);
+            }
+            CommandId::ShowCommandPalette => self.command_palette.toggle(),
+            CommandId::QuickOpen => self.command_palette.open_files(),
+            CommandId::CompleteWord => self.offer_completions(),
+            CommandId::OpenPath(path) => {
+                self.open_preview(path);
+            }
+            CommandId::InsertCompletion(word) => self.active_editor().complete_word(&word),
+            CommandId::Find => {
+                self.show_search = !self.show_search;
+                self.show_replace = false;
+                self.show_goto_line = false;
+                self.focus_search = self.show_search;
+            }
+            CommandId::Replace => {
+                self.show_search = true;
+                self.show_replace = !self.show_replace;
+                self.show_goto_line = false;
+                self.focus_search = true;
+            }
+            CommandId::ShowUndoHistory => self.show_undo_history = !self.show_undo_history,
+            CommandId::ShowProblems => self.show_problems = !self.show_problems,
+            CommandId::ShowTodos => self.show_todos = !self.show_todos,
+            CommandId::RunFile => self.run_current_file(),
+            CommandId::ToggleSourceControl => {
+                if self.source_control.open {
+                    self.source_control.open = false;
+                } else {
+                    self.source_control.open_for(self.workspace.as_ref().map(|ws| ws.root.as_path()));
+                }
+            }
+            CommandId::PrettyPrintJson => self.pretty_print_json(),
+            CommandId::ShowCollaboration => self.collab_window.open = true,
+            CommandId::FollowCollaborator => self.follow_next_collaborator(),
+            CommandId::LeaveCollaboration => self.leave_collab(),
+            CommandId::StopRun => self.output_panel.stop(),
+            CommandId::ToggleOutputPanel => self.output_panel.open = !self.output_panel.open,
+            CommandId::GoToDefinition => self.goto_definition(),
+            CommandId::ShowCodeActions => self.show_code_actions(),
+            CommandId::RenameSymbol => self.rename_symbol(),
+            CommandId::ApplyCodeAction(index) => self.apply_code_action(index),
+            CommandId::GoToLine => {
+                self.show_goto_line = !self.show_goto_line;
+                self.goto_line_invalid = false;
+                self.show_search = false;
+            }
+            CommandId::ReflowParagraph => {
+                let width = self.settings.text_width.max(1);
+                self.active_editor().labeled_edit("Reflow Paragraph", |editor| editor.reflow_paragraphs(width));
+            }
+            CommandId::InsertDateTime => self.insert_date_format = Some(self.settings.date_format.clone()),
+            CommandId::TogglePinTab => self.toggle_pin_tab(self.active_tab),
+            CommandId::SaveAll => {
+                self.save_all();
+            }
+            CommandId::CloseAllTabs => self.close_all_tabs(),
+            CommandId::SaveFileAsAdmin => match self.editors[self.active_tab].file_path.clone() {
+                Some(path) => self.save_tab_elevated(self.active_tab, path),
+                None => self.save_file_as(),
+            },
+            CommandId::RenameFile => self.rename_active_file(),
+            CommandId::ShowLocalHistory => match self.editors[self.active_tab].file_path.clone() {
+                Some(path) => self.local_history.open_for(path),
+                None => self.toasts.info("Local history starts once the file is saved"),
+            },
+            CommandId::ShowFileInfo => match self.editors[self.active_tab].file_path.clone() {
+                Some(path) => self.file_info.open_for(path),
+                None => self.toasts.info("File info is available once the file is saved"),
+            },
+            CommandId::RepeatLastCommand => {
+                if let Some(last) = self.command_palette.last_command() {
+                    self.handle_command(last);
+                }
+            }
+            CommandId::SetLogLevel(level) => {
+                logging::set_level(level);
+                self.toasts.info(format!(
+                    "Log level: {} (logs in {})",
+                    level,
+                    logging::log_dir().display()
+                ));
+            }
+            CommandId::JumpToLine(line) => self.active_editor().goto_line(line),
+            CommandId::OpenLocation(path, line) => {
+                if self.open_preview(path) {
+                    self.active_editor().goto_line(line);
+                }
+            }
+            CommandId::CopyWithHighlighting => self.copy_with_highlighting(),
+            CommandId::CopyEvaluation => self.copy_evaluation(),
+            CommandId::RevealInFileManager
+            | CommandId::DeleteFile
+            | CommandId::CopyAbsolutePath
+            | CommandId::CopyRelativePath
+            | CommandId::CopyFileName => self.file_command(self.active_tab, cmd),
+            CommandId::CopyText(text) => self.copy_to_clipboard(text),
+            CommandId::ToggleLineNumbers => self.settings.line_numbers = !self.settings.line_numbers,
+            CommandId::ToggleStatusBar => self.settings.status_bar = !self.settings.status_bar,
+            CommandId::ShowStatistics => self.show_statistics = !self.show_statistics,
+            CommandId::ToggleFrameTimes => self.frame_times.toggle(),
+            CommandId::ToggleScriptConsole => self.script_console.toggle(),
+            CommandId::ReloadInitScript => self.reload_config(scripting::INIT_SCRIPT),
+            CommandId::OpenSettings => self.settings_window.open_with(&self.settings, &self.user_theme),
+            CommandId::SetColorScheme(scheme) => self.settings.color_scheme = scheme,
+            CommandId::SetSyntaxPalette(palette) => self.highlighter.palette = palette,
+            CommandId::ToggleMinContrast => {
+                self.settings.min_contrast = match self.settings.min_contrast {
+                    Some(_) => None,
+                    None => Some(syntax::DEFAULT_MIN_CONTRAST),
+                };
+                self.scheme = None;
+            }
+            // Everything else edits the active buffer
+            editor_cmd => {
+                editor_view::run_command(&mut self.editors[self.active_tab], &mut self.clipboard, &editor_cmd);
+            }
+        }
+    }
+
+    /// Put the selections on the clipboard as colored HTML, with the plain
+    /// text as a fallback for apps that don't take HTML.
+    fn copy_with_highlighting(&mut self) {
+        let editor = &self.editors[self.active_tab];
+        let mut lines = Vec::new();
+        for (start, end) in editor.copy_ranges() {
+            let tokens = self.highlighter.highlight_lines(
+                &editor.rope,
+                editor.file_path.as_deref(),
+                start.line,
+                end.line + 1,
+            );
+            lines.extend(syntax::slice_tokens(tokens, start.col, end.col));
+        }
+        let html = syntax::tokens_to_html(&lines, self.theme.background, self.theme.text);
+        let content = editor.copy_content();
+        content.remember();
+        let plain = content.text();
+        if let Some(cb) = self.clipboard.as_mut() {
+            if let Err(e) = cb.set_html(html, Some(plain)) {
+                tracing::warn!(error = %e, "failed to copy highlighted text");
+            }
+        }
+    }
+
+    /// Evaluate each selection (or the line of a cursor without one) and
+    /// copy the results, one per line.
+    fn copy_evaluation(&mut self) {
+        let editor = &self.editors[self.active_tab];
+        let mut results = Vec::new();
+        for (start, end) in editor.copy_ranges() {
+            let text = editor.text_between(start, end);
+            let expr = text.trim();
+            let expr = expr.strip_suffix('=').unwrap_or(expr);
+            match calc::evaluate(expr) {
+                Ok(value) => results.push(calc::format(value)),
+                Err(e) => {
+                    self.toasts.error(format!("Can't evaluate \"{}\"", expr.chars().take(40).collect::<String>()), e);
+                    return;
+                }
+            }
+        }
+        self.copy_to_clipboard(results.join("\n"));
+    }
+
+    /// Reveal, delete or copy the path or name of tab `idx`'s file.
+    fn file_command(&mut self, idx: usize, cmd: CommandId) {
+        let Some(path) = self.editors[idx].file_path.clone() else {
+            self.toasts.info("The buffer hasn't been saved to a file");
+            return;
+        };
+        match cmd {
+            CommandId::DeleteFile => self.delete_file(idx, &path),
+            CommandId::RevealInFileManager => {
+                if let Err(e) = reveal::reveal(&path) {
+                    tracing::warn!(path = %path.display(), error = %e, "failed to reveal file");
+                    self.toasts.error(format!("Failed to reveal {}", file_name(&path)), e.to_string());
+                }
+            }
+            CommandId::CopyAbsolutePath => self.copy_to_clipboard(path.display().to_string()),
+            CommandId::CopyRelativePath => {
+                // Relative to the open folder, else the file's own folder
+                let root = match &self.workspace {
+                    Some(ws) if path.starts_with(&ws.root) => ws.root.clone(),
+                    _ => path.parent().map(|p| p.to_path_buf()).unwrap_or_default(),
+                };
+                let relative = path.strip_prefix(&root).unwrap_or(&path);
+                self.copy_to_clipboard(relative.display().to_string());
+            }
+            CommandId::CopyFileName => self.copy_to_clipboard(file_name(&path)),
+            _ => {}
+        }
+    }
+
+    fn copy_to_clipboard(&mut self, text: String) {
+        let Some(cb) = self.clipboard.as_mut() else {
+            return;
+        };
+        match cb.set_text(text.clone()) {
+            Ok(()) => self.toasts.info(format!("Copied {}", text)),
+            Err(e) => self.toasts.error("Failed to copy", e.to_string()),
+        }
+    }
+
+    /// Complete from the file's language server, or else from words in
+    /// the buffer and the workspace.
+    fn offer_completions(&mut self) {
+        let editor = &self.editors[self.active_tab];
+        if let Some(server) = editor.file_path.as_deref().and_then(|p| self.servers.for_path(p)) {
+            if server.complete(editor) {
+                return;
+            }
+        }
+        self.offer_word_completions();
+    }
+
+    fn offer_word_completions(&mut self) {
+        let editor = &self.editors[self.active_tab];
+        let prefix = editor.word_prefix_at_cursor();
+        if prefix.is_empty() {
+            return;
+        }
+        let mut words = editor.words_with_prefix(&prefix);
+        if let Some(index) = &self.index {
+            for word in index.complete(&prefix, MAX_INDEX_COMPLETIONS) {
+                if !words.contains(&word) {
+                    words.push(word);
+                }
+            }
+        }
+        match words.len() {
+            0 => {}
+            1 => self.active_editor().complete_word(&words[0]),
+            _ => self.command_palette.open_completions(words),
+        }
+    }
+
+    /// What is drawn over the editor and gets the keyboard first.
+    fn top_layer(&self) -> Layer {
+        if self.command_palette.visible {
+            Layer::Palette
+        } else if self.passphrase_prompt.is_some()
+            || self.confirm_close_tab.is_some()
+            || self.pending_bulk_close.is_some()
+            || self.pending_ref_rewrite.is_some()
+            || self.rename.open
+            || self.pending_elevated_save.is_some()
+            || self.pending_save_conflict.is_some()
+            || self.offer_recovery
+        {
+            Layer::Dialog
+        } else if self.show_search || self.show_goto_line || self.insert_date_format.is_some() {
+            Layer::Bar
+        } else {
+            Layer::Editor
+        }
+    }
+
+    fn route_input(&mut self, ctx: &egui::Context) {
+        let layer = self.top_layer();
+        // Escape closes one layer per press. With nothing open it is left
+        // for the editor, which clears extra cursors with it.
+        if layer != Layer::Editor && input::take_key(ctx, egui::Key::Escape) {
+            self.dismiss_layer(layer);
+        }
+        // The palette and dialogs keep shortcuts from acting behind them
+        if matches!(layer, Layer::Bar | Layer::Editor) {
+            self.handle_global_shortcuts(ctx);
+        }
+    }
+
+    /// Close the topmost part of `layer`, as its Cancel button would.
+    fn dismiss_layer(&mut self, layer: Layer) {
+        match layer {
+            Layer::Palette => self.command_palette.close(),
+            Layer::Dialog => {
+                if self.passphrase_prompt.is_some() {
+                    self.passphrase_prompt = None;
+                } else if self.confirm_close_tab.is_some() {
+                    self.confirm_close_tab = None;
+                } else if self.pending_bulk_close.is_some() {
+                    self.pending_bulk_close = None;
+                } else if self.pending_ref_rewrite.is_some() {
+                    self.pending_ref_rewrite = None;
+                } else if self.rename.open {
+                    self.rename.close();
+                } else if self.pending_elevated_save.is_some() {
+                    self.pending_elevated_save = None;
+                } else if self.pending_save_conflict.is_some() {
+                    self.pending_save_conflict = None;
+                }
+                // Recovery has no Cancel: restore or discard must be picked
             }
-            CommandId::SelectAll => {
-                self.active_editor().select_all();
+            Layer::Bar => {
+                if self.insert_date_format.is_some() {
+                    self.insert_date_format = None;
+                } else if self.show_goto_line {
+                    self.show_goto_line = false;
+                } else {
+                    self.show_search = false;
+                    self.show_replace = false;
+                }
             }
-            CommandId::Undo => self.active_editor().undo(),
-            CommandId::Redo => self.active_editor().redo(),
+            Layer::Editor => {}
         }
     }
 
     fn handle_global_shortcuts(&mut self, ctx: &egui::Context) {
-        ctx.input(|i| {
-            let ctrl = i.modifiers.command;
-            let shift = i.modifiers.shift;
-
-            if ctrl && shift && i.key_pressed(egui::Key::P) {
-                self.command_palette.toggle();
-            } else if ctrl && i.key_pressed(egui::Key::N) {
-                self.new_tab();
-            } else if ctrl && i.key_pressed(egui::Key::O) {
-                // Defer file dialog to avoid borrow issues
-            } else if ctrl && i.key_pressed(egui::Key::S) {
-                if shift {
-                    // save as - defer
-                } else {
-                    // save - defer
+        // Second key of a Ctrl+K chord
+        if self.chord_pending {
+            // Taken so it doesn't reach the editor as text as well
+            if let Some(key) = input::take_chord_key(ctx) {
+                self.chord_pending = false;
+                if let Some(cmd) = commands::lookup_chord(key) {
+                    self.handle_command(cmd);
                 }
-            } else if ctrl && i.key_pressed(egui::Key::W) {
-                self.close_tab();
-            } else if ctrl && i.key_pressed(egui::Key::F) {
-                self.show_search = !self.show_search;
-                self.show_replace = false;
-                self.show_goto_line = false;
-            } else if ctrl && i.key_pressed(egui::Key::H) {
-                self.show_search = true;
-                self.show_replace = !self.show_replace;
-                self.show_goto_line = false;
-            } else if ctrl && i.key_pressed(egui::Key::G) {
-                self.show_goto_line = !self.show_goto_line;
-                self.show_search = false;
+                return;
             }
-        });
-
-        // Handle open/save outside of input closure to avoid borrow issues
-        let should_open = ctx.input(|i| i.modifiers.command && i.key_pressed(egui::Key::O));
-        let should_save = ctx.input(|i| i.modifiers.command && !i.modifiers.shift && i.key_pressed(egui::Key::S));
-        let should_save_as = ctx.input(|i| i.modifiers.command && i.modifiers.shift && i.key_pressed(egui::Key::S));
-
-        if should_open {
-            self.open_file();
         }
-        if should_save {
-            self.save_file();
+
+        // Collect first, run afterwards: commands may open dialogs or borrow self
+        let (triggered, chord_started) = input::take_shortcuts(ctx);
+        if chord_started {
+            self.chord_pending = true;
         }
-        if should_save_as {
-            self.save_file_as();
+        for cmd in triggered {
+            self.handle_command(cmd);
         }
     }
 
+    /// The tabs, scrolling sideways when they don't fit, with a dropdown
+    /// listing every open file beside them then.
     fn show_tab_bar(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.style_mut().spacing.item_spacing.x = 0.0;
+            let dropdown_width = if self.tabs_overflow { 26.0 } else { 0.0 };
+            let output = egui::ScrollArea::horizontal()
+                .id_salt("tab_strip")
+                .max_width(ui.available_width() - dropdown_width)
+                .scroll_bar_visibility(egui::scroll_area::ScrollBarVisibility::AlwaysHidden)
+                .show(ui, |ui| self.show_tabs(ui));
+            self.tabs_overflow = output.content_size.x > output.inner_rect.width() + 1.0;
+            if self.tabs_overflow {
+                self.show_open_files_dropdown(ui);
+            }
+        });
+    }
+
+    /// Button opening a filterable list of all open files, for picking a
+    /// tab scrolled out of sight.
+    fn show_open_files_dropdown(&mut self, ui: &mut egui::Ui) {
+        let button = egui::Button::new(egui::RichText::new(" \u{25BE} ").color(self.chrome.weak).size(12.0))
+            .fill(self.chrome.panel)
+            .rounding(egui::Rounding { nw: 4.0, ne: 4.0, sw: 0.0, se: 0.0 });
+        let response = ui.add(button).on_hover_text("All Open Files");
+        let popup_id = ui.make_persistent_id("open_files_dropdown");
+        if response.clicked() {
+            self.tab_filter.clear();
+            ui.memory_mut(|m| m.toggle_popup(popup_id));
+        }
+        let titles = tab_titles(&self.editors);
+        let mut picked = None;
+        egui::popup_below_widget(ui, popup_id, &response, egui::PopupCloseBehavior::CloseOnClickOutside, |ui| {
+            ui.set_min_width(280.0);
+            let filter = ui.add(egui::TextEdit::singleline(&mut self.tab_filter).hint_text("Filter open files..."));
+            filter.request_focus();
+            let query = self.tab_filter.to_lowercase();
+            let mut matches: Vec<(usize, usize)> = titles
+                .iter()
+                .enumerate()
+                .filter_map(|(i, title)| command_palette::fuzzy_rank(title, &query).map(|rank| (rank, i)))
+                .collect();
+            matches.sort();
+            if filter.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                picked = matches.first().map(|&(_, i)| i);
+            }
+            if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                ui.memory_mut(|m| m.close_popup());
+            }
+            egui::ScrollArea::vertical().max_height(360.0).show(ui, |ui| {
+                for &(_, i) in &matches {
+                    let editor = &self.editors[i];
+                    let mut text = titles[i].clone();
+                    if editor.modified {
+                        text.push_str(" \u{25CF}");
+                    }
+                    let response = ui.selectable_label(i == self.active_tab, text);
+                    let response = match &editor.file_path {
+                        Some(path) => response.on_hover_text(path.display().to_string()),
+                        None => response,
+                    };
+                    if response.clicked() {
+                        picked = Some(i);
+                    }
+                }
+                if matches.is_empty() {
+                    ui.weak("No matching files");
+                }
+            });
+        });
+        if let Some(i) = picked {
+            self.active_tab = i;
+            ui.memory_mut(|m| m.close_popup());
+        }
+    }
+
+    fn show_tabs(&mut self, ui: &mut egui::Ui) {
         ui.horizontal(|ui| {
             ui.style_mut().spacing.item_spacing.x = 0.0;
 
-            for i in 0..self.editors.len() {
-                let title = &self.editors[i].title;
+            let titles = tab_titles(&self.editors);
+            for (i, title) in titles.iter().enumerate() {
                 let modified = self.editors[i].modified;
+                let pinned = self.editors[i].pinned;
+                let deleted = self.editors[i].deleted_on_disk;
                 let is_active = i == self.active_tab;
 
-                let label = if modified {
+                let label = if pinned {
+                    // Compact: just the first few characters of the title
+                    let short: String = title.chars().take(3).collect();
+                    if modified {
+                        format!(" {} \u{25CF}", short)
+                    } else {
+                        format!(" {} ", short)
+                    }
+                } else if modified {
                     format!(" {} \u{25CF}", title) // ● dot for modified
                 } else {
                     format!(" {}", title)
                 };
+                let full_title = title.clone();
 
-                let bg = if is_active {
-                    egui::Color32::from_rgb(30, 30, 30)
-                } else {
-                    egui::Color32::from_rgb(45, 45, 45)
-                };
-                let text_color = if is_active {
-                    egui::Color32::WHITE
-                } else {
-                    egui::Color32::from_rgb(160, 160, 160)
-                };
+                let bg = if is_active { self.theme.background } else { self.chrome.panel };
+                let text_color = if is_active { self.chrome.text } else { self.chrome.weak };
 
                 let tab_rounding = egui::Rounding {
                     nw: 4.0,
@@ -210,34 +2483,84 @@ impl LuxApp {
                     sw: 0.0,
                     se: 0.0,
                 };
-                let tab_stroke = egui::Stroke::new(1.0, egui::Color32::from_rgb(60, 60, 60));
+                let tab_stroke = egui::Stroke::new(1.0, self.chrome.border);
+
+                let mut text = egui::RichText::new(&label).color(text_color).size(12.0);
+                if deleted {
+                    text = text.strikethrough();
+                }
+                if self.editors[i].preview {
+                    text = text.italics();
+                }
 
                 // Tab label button
-                let response = ui.add(
-                    egui::Button::new(
-                        egui::RichText::new(&label)
-                            .color(text_color)
-                            .size(12.0),
-                    )
+                let mut response = ui.add(
+                    egui::Button::new(text)
                     .fill(bg)
                     .rounding(tab_rounding)
                     .stroke(tab_stroke),
                 );
+                if pinned {
+                    response = response.on_hover_text(&full_title);
+                }
+                if is_active && self.revealed_tab != Some(i) {
+                    response.scroll_to_me(None);
+                    self.revealed_tab = Some(i);
+                }
+                if deleted {
+                    response = response.on_hover_text("Deleted on disk; saving writes it again");
+                }
 
                 if response.clicked() {
                     self.active_tab = i;
                 }
-                if response.middle_clicked() && self.editors.len() > 1 {
+                if response.double_clicked() {
+                    self.editors[i].preview = false;
+                }
+                if response.middle_clicked() && !pinned && self.editors.len() > 1 {
                     self.close_tab_idx(i);
                     break;
                 }
 
-                // Close "x" button (only if more than 1 tab)
-                if self.editors.len() > 1 {
+                let mut toggle_pin = false;
+                let mut file_command = None;
+                let has_file = self.editors[i].file_path.is_some();
+                response.context_menu(|ui| {
+                    let text = if pinned { "Unpin Tab" } else { "Pin Tab" };
+                    if ui.button(text).clicked() {
+                        toggle_pin = true;
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    ui.add_enabled_ui(has_file, |ui| {
+                        for (cmd, text) in [
+                            (CommandId::RevealInFileManager, reveal::LABEL),
+                            (CommandId::CopyAbsolutePath, "Copy Absolute Path"),
+                            (CommandId::CopyRelativePath, "Copy Relative Path"),
+                            (CommandId::CopyFileName, "Copy File Name"),
+                            (CommandId::DeleteFile, "Delete File"),
+                        ] {
+                            if ui.button(text).clicked() {
+                                file_command = Some(cmd);
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                });
+                if toggle_pin {
+                    self.toggle_pin_tab(i);
+                    break;
+                }
+                if let Some(cmd) = file_command {
+                    self.file_command(i, cmd);
+                }
+
+                // Close "x" button (only if more than 1 tab, never on pinned tabs)
+                if self.editors.len() > 1 && !pinned {
                     let x_resp = ui.add(
                         egui::Button::new(
                             egui::RichText::new("\u{00D7}") // ×
-                                .color(egui::Color32::from_rgb(140, 140, 140))
+                                .color(self.chrome.weak)
                                 .size(12.0),
                         )
                         .fill(bg)
@@ -258,10 +2581,10 @@ impl LuxApp {
                 .add(
                     egui::Button::new(
                         egui::RichText::new(" + ")
-                            .color(egui::Color32::from_rgb(160, 160, 160))
+                            .color(self.chrome.weak)
                             .size(12.0),
                     )
-                    .fill(egui::Color32::from_rgb(45, 45, 45))
+                    .fill(self.chrome.panel)
                     .rounding(egui::Rounding {
                         nw: 4.0,
                         ne: 4.0,
@@ -276,6 +2599,37 @@ impl LuxApp {
         });
     }
 
+    fn search_query(&self) -> (String, String, SearchOptions) {
+        (self.search_input.clone(), self.replace_input.clone(), self.search_options)
+    }
+
+    /// Add the current inputs to the search history, saving it when it
+    /// changed.
+    fn remember_search(&mut self, with_replacement: bool) {
+        let mut changed = search_history::push(&mut self.search_history.searches, &self.search_input);
+        if with_replacement {
+            changed |= search_history::push(&mut self.search_history.replacements, &self.replace_input);
+        }
+        self.search_recall.reset();
+        self.replace_recall.reset();
+        if changed {
+            if let Err(e) = self.search_history.save() {
+                tracing::warn!(error = %e, "failed to save search history");
+            }
+        }
+    }
+
+    fn find_next(&mut self) {
+        self.remember_search(false);
+        let (query, _, options) = self.search_query();
+        let result = self.active_editor().find_and_select(&query, options);
+        self.set_search_result(result);
+    }
+
+    fn set_search_result(&mut self, result: Result<(), SearchError>) {
+        self.search_error = result.err().map(|e| e.message);
+    }
+
     fn show_search_bar(&mut self, ui: &mut egui::Ui) {
         if !self.show_search {
             return;
@@ -284,37 +2638,59 @@ impl LuxApp {
         ui.horizontal(|ui| {
             ui.label(
                 egui::RichText::new("Find:")
-                    .color(egui::Color32::from_rgb(200, 200, 200))
+                    .color(self.chrome.label)
                     .size(13.0),
             );
 
+            let id = egui::Id::new("search_input");
+            recall_history(ui, id, &mut self.search_recall, &self.search_history.searches, &mut self.search_input);
             let response = ui.add(
                 egui::TextEdit::singleline(&mut self.search_input)
+                    .id(id)
                     .desired_width(250.0)
                     .font(egui::FontId::monospace(13.0))
-                    .text_color(egui::Color32::WHITE)
+                    .text_color(self.chrome.text)
                     .hint_text("Search..."),
             );
+            if std::mem::take(&mut self.focus_search) {
+                response.request_focus();
+            }
+
+            if response.changed() {
+                self.search_error = None;
+                self.search_recall.reset();
+            }
+
+            if let Some(query) = history_menu(ui, &self.search_history.searches) {
+                self.search_input = query;
+                self.find_next();
+            }
 
             if response.lost_focus()
                 && ui.input(|i| i.key_pressed(egui::Key::Enter))
             {
-                let query = self.search_input.clone();
-                self.active_editor().find_and_select(&query);
+                self.find_next();
                 response.request_focus();
             }
 
+            let options = &mut self.search_options;
+            ui.toggle_value(&mut options.ignore_case, egui::RichText::new("Aa").size(12.0))
+                .on_hover_text("Ignore case");
+            ui.toggle_value(&mut options.whole_word, egui::RichText::new("\\b").size(12.0))
+                .on_hover_text("Whole word");
+            ui.toggle_value(&mut options.regex, egui::RichText::new(".*").size(12.0))
+                .on_hover_text("Regular expression");
+
             if ui
                 .add(egui::Button::new(egui::RichText::new("Next").size(12.0)))
                 .clicked()
             {
-                let query = self.search_input.clone();
-                self.active_editor().find_and_select(&query);
+                self.find_next();
             }
 
-            if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
-                self.show_search = false;
-                self.show_replace = false;
+            if let Some(error) = &self.search_error {
+                ui.label(egui::RichText::new("Invalid pattern").color(egui::Color32::from_rgb(230, 110, 110)).size(12.0))
+                    .on_hover_text(error);
             }
 
             if ui
@@ -331,39 +2707,131 @@ impl LuxApp {
             ui.horizontal(|ui| {
                 ui.label(
                     egui::RichText::new("Replace:")
-                        .color(egui::Color32::from_rgb(200, 200, 200))
+                        .color(self.chrome.label)
                         .size(13.0),
                 );
 
-                ui.add(
+                let id = egui::Id::new("replace_input");
+                recall_history(ui, id, &mut self.replace_recall, &self.search_history.replacements, &mut self.replace_input);
+                let response = ui.add(
                     egui::TextEdit::singleline(&mut self.replace_input)
+                        .id(id)
                         .desired_width(250.0)
                         .font(egui::FontId::monospace(13.0))
-                        .text_color(egui::Color32::WHITE)
+                        .text_color(self.chrome.text)
                         .hint_text("Replace with..."),
                 );
 
+                if response.changed() {
+                    self.replace_recall.reset();
+                }
+
+                if let Some(replacement) = history_menu(ui, &self.search_history.replacements) {
+                    self.replace_input = replacement;
+                }
+
                 if ui
                     .add(egui::Button::new(egui::RichText::new("Replace").size(12.0)))
                     .clicked()
                 {
-                    let find = self.search_input.clone();
-                    let replace = self.replace_input.clone();
-                    self.active_editor().replace_next(&find, &replace);
+                    self.remember_search(true);
+                    let (find, replace, options) = self.search_query();
+                    let result = self.active_editor().replace_next(&find, &replace, options);
+                    self.set_search_result(result);
                 }
 
                 if ui
                     .add(egui::Button::new(egui::RichText::new("Replace All").size(12.0)))
                     .clicked()
                 {
-                    let find = self.search_input.clone();
-                    let replace = self.replace_input.clone();
-                    self.active_editor().replace_all(&find, &replace);
+                    self.remember_search(true);
+                    let (find, replace, options) = self.search_query();
+                    let result = self
+                        .active_editor()
+                        .labeled_edit("Replace All", |editor| editor.replace_all(&find, &replace, options));
+                    self.set_search_result(result);
                 }
             });
         }
     }
 
+    fn pretty_print_json(&mut self) {
+        if let Err(e) = self.active_editor().labeled_edit("Pretty Print JSON", Editor::pretty_print_json) {
+            self.toasts.error("Not valid JSON", e);
+        }
+    }
+
+    /// Notice over a file with lines too long to highlight, offering to
+    /// pretty print it when it looks like JSON.
+    fn show_long_line_bar(&mut self, ui: &mut egui::Ui) {
+        let editor = &self.editors[self.active_tab];
+        if self.long_lines.0 != editor.version {
+            self.long_lines = (editor.version, editor.has_long_lines());
+        }
+        if !self.long_lines.1 || self.long_lines_dismissed.contains(&editor.file_path) {
+            return;
+        }
+        let json = editor.file_path.as_ref().is_some_and(|p| p.extension().is_some_and(|e| e == "json"))
+            || editor.rope.chars().find(|c| !c.is_whitespace()).is_some_and(|c| c == '{' || c == '[');
+        ui.horizontal(|ui| {
+            ui.label(
+                egui::RichText::new("This file has very long lines; they are shown without syntax highlighting.")
+                    .color(self.chrome.label)
+                    .size(13.0),
+            );
+            if json && ui.button("Pretty Print JSON").clicked() {
+                self.pretty_print_json();
+            }
+            if ui.small_button("\u{2715}").on_hover_text("Dismiss").clicked() {
+                self.long_lines_dismissed.push(self.editors[self.active_tab].file_path.clone());
+            }
+        });
+    }
+
+    /// Format for Insert Date/Time, with a preview of the current time.
+    /// Enter inserts at every cursor and keeps the format for next time.
+    fn show_insert_date_bar(&mut self, ui: &mut egui::Ui) {
+        let Some(format) = &mut self.insert_date_format else {
+            return;
+        };
+        let mut insert = None;
+        ui.horizontal(|ui| {
+            ui.label(
+                egui::RichText::new("Insert Date/Time:")
+                    .color(self.chrome.label)
+                    .size(13.0),
+            );
+
+            let response = ui.add(
+                egui::TextEdit::singleline(format)
+                    .desired_width(180.0)
+                    .font(egui::FontId::monospace(13.0))
+                    .text_color(self.chrome.text)
+                    .hint_text(DEFAULT_DATE_FORMAT),
+            );
+            if !response.has_focus() && !response.lost_focus() {
+                response.request_focus();
+            }
+
+            let now = format_now(format);
+            match &now {
+                Some(text) => ui.label(egui::RichText::new(text).color(self.chrome.weak).size(12.0)),
+                None => ui.label(egui::RichText::new("Invalid format").color(egui::Color32::from_rgb(230, 110, 110)).size(12.0)),
+            }
+            .on_hover_text("strftime syntax, e.g. %Y-%m-%d %H:%M:%S or %a %b %e %Y");
+
+            if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                insert = now;
+            }
+        });
+        if let Some(text) = insert {
+            if let Some(format) = self.insert_date_format.take() {
+                self.settings.date_format = format;
+            }
+            self.active_editor().insert_each(|_| text.clone());
+        }
+    }
+
     fn show_goto_line_bar(&mut self, ui: &mut egui::Ui) {
         if !self.show_goto_line {
             return;
@@ -372,54 +2840,353 @@ impl LuxApp {
         ui.horizontal(|ui| {
             ui.label(
                 egui::RichText::new("Go to Line:")
-                    .color(egui::Color32::from_rgb(200, 200, 200))
+                    .color(self.chrome.label)
                     .size(13.0),
             );
 
             let response = ui.add(
                 egui::TextEdit::singleline(&mut self.goto_line_input)
-                    .desired_width(100.0)
+                    .desired_width(200.0)
                     .font(egui::FontId::monospace(13.0))
-                    .text_color(egui::Color32::WHITE)
-                    .hint_text("Line number"),
+                    .text_color(self.chrome.text)
+                    .hint_text("Line[:Column], +N, -N or N%"),
             );
 
+            if response.changed() {
+                self.goto_line_invalid = false;
+            }
             if response.lost_focus()
                 && ui.input(|i| i.key_pressed(egui::Key::Enter))
             {
-                if let Ok(line) = self.goto_line_input.trim().parse::<usize>() {
-                    self.active_editor().goto_line(line);
+                let input = self.goto_line_input.clone();
+                if self.active_editor().goto(&input) {
+                    self.show_goto_line = false;
+                } else {
+                    // Stay open to fix it
+                    self.goto_line_invalid = true;
+                    response.request_focus();
                 }
-                self.show_goto_line = false;
             }
-
-            if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
-                self.show_goto_line = false;
+            if self.goto_line_invalid {
+                ui.label(egui::RichText::new("Invalid line").color(egui::Color32::from_rgb(230, 110, 110)).size(12.0));
             }
         });
     }
 }
 
+impl LuxApp {
+    /// Save All / Discard All / Cancel dialog for closing several tabs at once.
+    fn show_bulk_close_dialog(&mut self, ctx: &egui::Context, op: BulkClose) {
+        let unsaved: Vec<String> = self
+            .bulk_close_targets(op)
+            .into_iter()
+            .filter(|&i| self.editors[i].modified)
+            .map(|i| self.editors[i].title.clone())
+            .collect();
+        if unsaved.is_empty() {
+            self.finish_bulk_close(ctx, op);
+            return;
+        }
+
+        let mut action: Option<bool> = None;
+        egui::Window::new("Unsaved Changes")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(format!("{} file(s) have unsaved changes:", unsaved.len()));
+                for title in &unsaved {
+                    ui.label(format!("  \u{2022} {}", title));
+                }
+                ui.add_space(8.0);
+                ui.horizontal(|ui| {
+                    if ui.button("Save All").clicked() {
+                        action = Some(true);
+                    }
+                    if ui.button("Discard All").clicked() {
+                        action = Some(false);
+                    }
+                    if ui.button("Cancel").clicked() {
+                        self.pending_bulk_close = None;
+                    }
+                });
+            });
+
+        match action {
+            Some(true) => {
+                let targets = self.bulk_close_targets(op);
+                let saved = targets
+                    .into_iter()
+                    .all(|i| !self.editors[i].modified || self.save_tab(i));
+                // A failed or cancelled save keeps the dialog open
+                if saved {
+                    self.finish_bulk_close(ctx, op);
+                }
+            }
+            Some(false) => self.finish_bulk_close(ctx, op),
+            None => {}
+        }
+    }
+}
+
+impl LuxApp {
+    /// Preview of reference rewrites after a file move.
+    fn show_ref_rewrite_dialog(&mut self, ctx: &egui::Context) {
+        let Some(edits) = self.pending_ref_rewrite.as_mut() else {
+            return;
+        };
+        let mut apply = None;
+
+        egui::Window::new("Update References")
+            .collapsible(false)
+            .resizable(true)
+            .default_width(600.0)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "{} reference(s) point at the file's old location:",
+                    edits.len()
+                ));
+                ui.add_space(4.0);
+                egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                    for (enabled, edit) in edits.iter_mut() {
+                        ui.checkbox(
+                            enabled,
+                            format!("{}:{}", edit.path.display(), edit.line + 1),
+                        );
+                        ui.label(
+                            egui::RichText::new(format!("- {}", edit.old_line.trim()))
+                                .monospace()
+                                .color(egui::Color32::from_rgb(220, 110, 110)),
+                        );
+                        ui.label(
+                            egui::RichText::new(format!("+ {}", edit.new_line.trim()))
+                                .monospace()
+                                .color(egui::Color32::from_rgb(110, 200, 110)),
+                        );
+                        ui.add_space(4.0);
+                    }
+                });
+                ui.add_space(8.0);
+                ui.horizontal(|ui| {
+                    if ui.button("Apply Selected").clicked() {
+                        apply = Some(true);
+                    }
+                    if ui.button("Skip").clicked() {
+                        apply = Some(false);
+                    }
+                });
+            });
+
+        match apply {
+            Some(true) => {
+                let selected: Vec<ReferenceEdit> = self
+                    .pending_ref_rewrite
+                    .take()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|(enabled, _)| *enabled)
+                    .map(|(_, e)| e)
+                    .collect();
+                self.apply_reference_rewrite(&selected);
+            }
+            Some(false) => self.pending_ref_rewrite = None,
+            None => {}
+        }
+    }
+}
+
+impl LuxApp {
+    fn show_recovery_dialog(&mut self, ctx: &egui::Context) {
+        egui::Window::new("Recover Unsaved Work")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label("Lux Editor closed unexpectedly last time.");
+                ui.label("Restore the buffers that were open?");
+                ui.add_space(8.0);
+                ui.horizontal(|ui| {
+                    if ui.button("Restore").clicked() {
+                        self.offer_recovery = false;
+                        self.restore_recovered();
+                    }
+                    if ui.button("Discard").clicked() {
+                        self.offer_recovery = false;
+                        crash::clear_recovery();
+                    }
+                });
+            });
+    }
+}
+
 impl eframe::App for LuxApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        // Dark theme
-        ctx.set_visuals(egui::Visuals::dark());
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        self.frame_times.record(frame);
 
-        // Global shortcuts (handled before UI to avoid conflicts)
-        if !self.command_palette.visible {
-            self.handle_global_shortcuts(ctx);
+        // The OS preference is checked every frame, so `system` follows it
+        // when it changes while the editor is open
+        let scheme = self.settings.color_scheme.resolve(ctx.system_theme());
+        if self.scheme != Some(scheme) {
+            self.apply_scheme(ctx, scheme);
+        }
+
+        // Focus and minimize changes arrive as input, so resuming is immediate
+        let was_idle = self.idle;
+        self.idle = ctx.input(|i| {
+            let viewport = i.viewport();
+            viewport.minimized.unwrap_or(false) || !viewport.focused.unwrap_or(true)
+        });
+        if let Some(index) = &self.index {
+            index.set_paused(self.idle);
+        }
+
+        // Scratch buffers save themselves, and all at once on quit
+        let now = ctx.input(|i| i.time);
+        let closing = ctx.input(|i| i.viewport().close_requested());
+        self.save_scratches(now, if closing { 0.0 } else { SCRATCH_SAVE_DELAY });
+        if self.editors.iter().any(|e| e.scratch.is_some() && e.modified) {
+            ctx.request_repaint_after(std::time::Duration::from_secs_f64(SCRATCH_SAVE_DELAY));
+        }
+        // Look again right away when the window comes back or the workspace
+        // watcher saw files come and go
+        let generation = self.index.as_ref().map_or(0, |index| index.generation());
+        let (checked_at, checked_generation) = self.last_disk_check;
+        if !self.idle
+            && (was_idle || generation != checked_generation || now - checked_at >= DISK_CHECK_INTERVAL)
+        {
+            self.last_disk_check = (now, generation);
+            self.check_files_on_disk();
         }
 
+        // Intercept window close while there are unsaved changes
+        if closing
+            && !self.allow_quit
+            && self.editors.iter().any(|e| e.modified)
+        {
+            ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+            self.pending_bulk_close = Some(BulkClose::Quit);
+        }
+
+        // Pinch or Ctrl+wheel zooms the editor font for this session
+        let zoom = ctx.input(|i| i.zoom_delta());
+        if zoom != 1.0 {
+            let size = self.settings.font_size.max(1.0);
+            self.font_zoom = (self.font_zoom * zoom)
+                .clamp(*config::FONT_SIZE_RANGE.start() / size, *config::FONT_SIZE_RANGE.end() / size);
+            self.metrics_stale = true;
+        }
+
+        let changed = self.config_watcher.as_ref().map(|w| w.changed()).unwrap_or_default();
+        for file in changed {
+            self.reload_config(file);
+        }
+        self.apply_ui_scale(ctx);
+
+        // Keys are routed before any widget runs, topmost layer first
+        self.route_input(ctx);
+
         // Command palette (rendered as overlay)
-        if let Some(cmd) = self.command_palette.show(ctx) {
+        let palette_cx = PaletteContext {
+            editor: &self.editors[self.active_tab],
+            workspace: self.workspace.as_ref(),
+            index: self.index.as_ref(),
+            chrome: self.chrome,
+        };
+        if let Some(cmd) = self.command_palette.show(ctx, &palette_cx) {
             self.handle_command(cmd);
         }
+        self.run_palette_search();
+
+        if let Some(saved) = self.settings_window.show(ctx) {
+            self.save_config(saved);
+        }
+        if std::mem::take(&mut self.metrics_stale) {
+            editor_view::forget_metrics(ctx);
+        }
+
+        if self.show_undo_history {
+            undo_history::show(ctx, &mut self.show_undo_history, &mut self.editors[self.active_tab]);
+        }
+
+        if self.show_statistics {
+            statistics::show(ctx, &mut self.show_statistics, &self.editors[self.active_tab]);
+        }
+
+        if self.show_problems {
+            let root = self.workspace.as_ref().map(|ws| ws.root.as_path());
+            let problems = self.diagnostics.problems();
+            if let Some((path, line, col)) = problems::show(ctx, &mut self.show_problems, &problems, root) {
+                if self.open_path(path) {
+                    self.active_editor().goto_line_col(line + 1, col + 1);
+                }
+            }
+        }
+
+        if self.show_debug {
+            let root = self.workspace.as_ref().map(|ws| ws.root.as_path());
+            debug_panel::show(ctx, &mut self.show_debug, self.debug.as_mut(), root);
+        }
+
+        if self.show_todos {
+            self.refresh_workspace_todos();
+            let root = self.workspace.as_ref().map(|ws| ws.root.as_path());
+            let groups = self.todos.groups(&self.editors, root);
+            let todos = &mut self.todos;
+            if let Some((source, line, col)) = todo_list::show(ctx, &mut self.show_todos, &groups, &mut todos.include_workspace, todos.scanning) {
+                let opened = match source {
+                    TodoSource::Tab(i) if i < self.editors.len() => {
+                        self.active_tab = i;
+                        true
+                    }
+                    TodoSource::Tab(_) => false,
+                    TodoSource::File(path) => self.open_path(path),
+                };
+                if opened {
+                    self.active_editor().goto_line_col(line + 1, col + 1);
+                }
+            }
+        }
+
+        if let Some(text) = self.local_history.show(ctx, &self.editors[self.active_tab]) {
+            self.active_editor().set_text(&text);
+        }
+
+        if let Some(text) = self.file_info.show(ctx, &self.editors[self.active_tab]) {
+            self.copy_to_clipboard(text);
+        }
+
+        if let Some(path) = self.diff_view.show(ctx) {
+            self.diff_only = false;
+            self.open_path(path);
+        }
+        if self.diff_only && !self.diff_view.open {
+            self.diff_only = false;
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+        }
+
+        if let Some(edit) = self.rename.show(ctx) {
+            self.apply_workspace_edit("Rename Symbol", &edit);
+        }
+
+        if self.script_console.open {
+            self.script_console.show(ctx, &mut self.scripts, &mut self.editors[self.active_tab]);
+        }
+
+        if let Some(request) = self.collab_window.show(ctx, self.collab.as_ref()) {
+            self.handle_collab_request(request);
+        }
+
+        self.output_panel.show(ctx);
+        if let Some(action) = self.source_control.show(ctx) {
+            self.handle_scm_action(action);
+        }
 
         // Main panel
         egui::CentralPanel::default()
             .frame(
                 egui::Frame::none()
-                    .fill(egui::Color32::from_rgb(45, 45, 45))
+                    .fill(self.chrome.panel)
                     .inner_margin(egui::Margin::same(0.0)),
             )
             .show(ctx, |ui| {
@@ -429,11 +3196,13 @@ impl eframe::App for LuxApp {
                 // Search / goto line bar
                 self.show_search_bar(ui);
                 self.show_goto_line_bar(ui);
+                self.show_insert_date_bar(ui);
+                self.show_long_line_bar(ui);
 
                 ui.add_space(0.0);
 
                 // Editor area (takes remaining space minus status bar)
-                let status_bar_height = 24.0;
+                let status_bar_height = if self.settings.status_bar { 24.0 } else { 0.0 };
                 let available = ui.available_rect_before_wrap();
                 let editor_rect = egui::Rect::from_min_max(
                     available.min,
@@ -441,11 +3210,48 @@ impl eframe::App for LuxApp {
                 );
 
                 let mut editor_ui = ui.new_child(egui::UiBuilder::new().max_rect(editor_rect).layout(egui::Layout::top_down(egui::Align::LEFT)));
-                let auto_focus = !self.show_search && !self.show_goto_line && !self.command_palette.visible && self.confirm_close_tab.is_none();
-                crate::ui::editor_view::show(&mut editor_ui, &mut self.editors[self.active_tab], &mut self.clipboard, &self.highlighter, auto_focus);
+                let auto_focus = self.top_layer() == Layer::Editor && !self.settings_window.open && !self.script_console.open && !self.source_control.wants_keyboard() && !self.file_info.wants_keyboard() && !self.collab_window.open && !ui.memory(|m| m.any_popup_open());
+                let shared = self.collab.as_ref().filter(|_| self.editors[self.active_tab].shared);
+                let collaborators: Vec<Presence> = shared.map(|s| s.collaborators.values().cloned().collect()).unwrap_or_default();
+                let follow = shared.and_then(Session::followed_cursor);
+                let view = ViewOptions {
+                    auto_focus,
+                    idle: self.idle,
+                    line_numbers: self.settings.line_numbers,
+                    sticky_scroll: self.settings.sticky_scroll,
+                    rulers: &self.settings.rulers,
+                    scroll_past_end: self.settings.scroll_past_end,
+                    scroll_margin: self.settings.scroll_margin,
+                    active_line_focus_only: self.settings.active_line_focus_only,
+                    show_line_ends: self.settings.show_line_ends,
+                    color_swatches: self.settings.color_swatches,
+                    font_size: (self.settings.font_size * self.font_zoom)
+                        .clamp(*config::FONT_SIZE_RANGE.start(), *config::FONT_SIZE_RANGE.end()),
+                    continue_comments: self.settings.continue_comments,
+                    reindent_paste: self.settings.reindent_paste,
+                    emmet: self.settings.emmet,
+                    search: (self.show_search && !self.search_input.is_empty())
+                        .then_some((self.search_input.as_str(), self.search_options)),
+                    collaborators: &collaborators,
+                    follow,
+                    theme: &self.theme,
+                };
+                let editor = &mut self.editors[self.active_tab];
+                let (before, version) = (editor.cursors[0].pos, editor.version);
+                editor_view::show(&mut editor_ui, editor, &mut self.clipboard, &self.highlighter, &view);
+                // Moving or typing takes the view back
+                if follow.is_some() && (editor.cursors[0].pos != before || editor.version != version) {
+                    if let Some(session) = &mut self.collab {
+                        session.following = None;
+                    }
+                }
 
                 // Status bar
-                crate::ui::status_bar::show(ui, &self.editors[self.active_tab]);
+                if self.settings.status_bar {
+                    if let Some(cmd) = crate::ui::status_bar::show(ui, &mut self.editors[self.active_tab], self.settings.status_statistics, &self.activities) {
+                        self.handle_command(cmd);
+                    }
+                }
             });
 
         // Unsaved changes confirmation dialog
@@ -477,9 +3283,12 @@ impl eframe::App for LuxApp {
 
             match close_action {
                 Some(true) => {
-                    // Save then close
-                    let _ = self.editors[tab_idx].save();
-                    self.force_close_tab(tab_idx);
+                    // Save then close; a failed or cancelled save keeps the tab
+                    if self.save_tab(tab_idx) {
+                        self.force_close_tab(tab_idx);
+                    } else {
+                        self.confirm_close_tab = None;
+                    }
                 }
                 Some(false) => {
                     self.force_close_tab(tab_idx);
@@ -488,6 +3297,106 @@ impl eframe::App for LuxApp {
             }
         }
 
-        ctx.request_repaint();
+        if let Some(op) = self.pending_bulk_close {
+            self.show_bulk_close_dialog(ctx, op);
+        }
+
+        self.show_ref_rewrite_dialog(ctx);
+        self.show_elevated_save_dialog(ctx);
+        self.show_save_conflict_dialog(ctx);
+        self.show_passphrase_prompt(ctx);
+
+        if self.offer_recovery {
+            self.show_recovery_dialog(ctx);
+        }
+
+        self.handle_finished_jobs();
+        self.sync_collab();
+        self.save_markers();
+        self.sync_debugger();
+        self.sync_language_servers();
+        if std::mem::take(&mut self.editors[self.active_tab].show_code_actions) {
+            self.show_code_actions();
+        }
+        // An edited preview is kept
+        for editor in self.editors.iter_mut().filter(|e| e.preview && e.modified) {
+            editor.preview = false;
+        }
+        // Say what an undo or redo of a command's change took back
+        if let Some(notice) = self.editors[self.active_tab].undo_notice.take() {
+            self.toasts.info(notice);
+        }
+        self.toasts.show(ctx);
+        self.frame_times.show(ctx);
+
+        let tab = tab_titles(&self.editors).swap_remove(self.active_tab);
+        let title = match &self.workspace {
+            Some(ws) => format!("{} - {} - Lux Editor", tab, ws.name()),
+            None => format!("{} - Lux Editor", tab),
+        };
+        if title != self.window_title {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Title(title.clone()));
+            self.window_title = title;
+        }
+
+        // Last, so a save made while quitting counts
+        self.check_wait_tab(ctx);
+        crash::update_snapshot(&self.editors);
+    }
+}
+
+/// The local time in strftime `format`, or None if the format is invalid.
+fn format_now(format: &str) -> Option<String> {
+    use std::fmt::Write;
+
+    let items = chrono::format::StrftimeItems::new(format);
+    let mut text = String::new();
+    write!(text, "{}", chrono::Local::now().format_with_items(items)).ok()?;
+    Some(text)
+}
+
+/// Step through `entries` with Up and Down while the search bar field `id`
+/// has focus. The keys are taken before the field sees them.
+fn recall_history(ui: &mut egui::Ui, id: egui::Id, recall: &mut Recall, entries: &[String], input: &mut String) {
+    if !ui.memory(|m| m.has_focus(id)) {
+        return;
+    }
+    let older = ui.input_mut(|i| {
+        if i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp) {
+            Some(true)
+        } else if i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown) {
+            Some(false)
+        } else {
+            None
+        }
+    });
+    let Some(older) = older else {
+        return;
+    };
+    recall.step(entries, input, older);
+    if let Some(mut state) = egui::TextEdit::load_state(ui.ctx(), id) {
+        let end = egui::text::CCursor::new(input.chars().count());
+        state.cursor.set_char_range(Some(egui::text::CCursorRange::one(end)));
+        state.store(ui.ctx(), id);
     }
 }
+
+/// Dropdown of recent entries for a search bar field, returning the one
+/// picked.
+fn history_menu(ui: &mut egui::Ui, entries: &[String]) -> Option<String> {
+    let mut picked = None;
+    ui.add_enabled_ui(!entries.is_empty(), |ui| {
+        ui.menu_button(egui::RichText::new("\u{23f7}").size(12.0), |ui| {
+            for entry in entries {
+                let label: String = entry.chars().take(60).collect();
+                if ui.button(egui::RichText::new(label).monospace()).on_hover_text(entry).clicked() {
+                    picked = Some(entry.clone());
+                    ui.close_menu();
+                }
+            }
+        })
+        .response
+        .on_hover_text("Recent");
+    });
+    picked
+}
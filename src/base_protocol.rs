@@ -0,0 +1,80 @@
+//! The framing the Debug Adapter Protocol and the Language Server Protocol
+//! share: each message is a JSON body after a `Content-Length` header,
+//! exchanged over a child process's standard input and output.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::sync::mpsc::Sender;
+
+use eframe::egui;
+use serde_json::Value;
+
+/// Something read from the child process.
+pub enum Incoming {
+    Message(Value),
+    /// A line the process wrote to standard error.
+    Stderr(String),
+    /// The process closed its output, which means it exited.
+    Closed,
+}
+
+/// Write one message with its header.
+pub fn write_message(out: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(out, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    out.flush()
+}
+
+/// Send each message the process writes until it closes its output, then
+/// [`Incoming::Closed`]. Every message wakes `ctx`.
+pub fn read_messages(stream: impl Read + Send + 'static, tx: Sender<Incoming>, ctx: egui::Context) {
+    std::thread::spawn(move || {
+        let mut reader = BufReader::new(stream);
+        while let Some(message) = read_message(&mut reader) {
+            if tx.send(Incoming::Message(message)).is_err() {
+                return;
+            }
+            ctx.request_repaint();
+        }
+        let _ = tx.send(Incoming::Closed);
+        ctx.request_repaint();
+    });
+}
+
+/// Pass on what the process logs to standard error, line by line.
+pub fn read_stderr(stream: impl Read + Send + 'static, tx: Sender<Incoming>, ctx: egui::Context) {
+    std::thread::spawn(move || {
+        let reader = BufReader::new(stream);
+        for line in reader.lines().map_while(Result::ok) {
+            if tx.send(Incoming::Stderr(line)).is_err() {
+                return;
+            }
+            ctx.request_repaint();
+        }
+    });
+}
+
+/// One message: headers including `Content-Length`, a blank line, then
+/// that many bytes of JSON. None at the end of the stream or when the
+/// framing is broken, after which nothing more can be read.
+fn read_message(reader: &mut impl BufRead) -> Option<Value> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).ok()? == 0 {
+            return None;
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse().ok();
+            }
+        }
+    }
+    let mut body = vec![0; length?];
+    reader.read_exact(&mut body).ok()?;
+    // A message that isn't JSON is skipped rather than ending the session
+    Some(serde_json::from_slice(&body).unwrap_or_default())
+}
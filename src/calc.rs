@@ -0,0 +1,287 @@
+use std::f64::consts::{E, PI, TAU};
+
+/// Deepest nesting of parentheses, signs and powers evaluated, so a long
+/// run of `(` or `-` fails instead of overflowing the stack.
+const MAX_DEPTH: usize = 256;
+
+/// Evaluate an arithmetic expression: numbers (`42`, `1.5e3`, `0xff`,
+/// `0b101`), `+ - * / %`, `^` or `**` for powers, parentheses, the
+/// constants `pi`, `tau` and `e`, and functions such as `sqrt(2)` or
+/// `max(1, 2)`.
+pub fn evaluate(expr: &str) -> Result<f64, String> {
+    let mut parser = Parser { chars: expr.chars().collect(), pos: 0, depth: 0 };
+    let value = parser.expr()?;
+    parser.skip_whitespace();
+    if let Some(c) = parser.peek() {
+        return Err(format!("unexpected '{}'", c));
+    }
+    if !value.is_finite() {
+        return Err("the result is not a finite number".to_string());
+    }
+    Ok(value)
+}
+
+/// `value` the way a person would write it: integers without a decimal
+/// point, and others rounded to 12 significant digits so float noise like
+/// `0.30000000000000004` doesn't show.
+pub fn format(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        return format!("{}", value as i64);
+    }
+    let rounded: f64 = format!("{:.11e}", value).parse().unwrap_or(value);
+    format!("{}", rounded)
+}
+
+/// What Evaluate Selection puts in place of `text`: the result, or for
+/// text ending in `=` the text followed by the result. None if `text` isn't
+/// an expression.
+pub fn substitute(text: &str) -> Option<String> {
+    let trimmed = text.trim_end();
+    match trimmed.strip_suffix('=') {
+        Some(expr) => {
+            let value = evaluate(expr).ok()?;
+            let space = if expr.ends_with(char::is_whitespace) { " " } else { "" };
+            Some(format!("{}{}{}", trimmed, space, format(value)))
+        }
+        None => evaluate(text).ok().map(format),
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    /// How many `unary` calls are in progress; every nesting goes
+    /// through one.
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    /// Skip whitespace and consume `c` if it comes next.
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expr(&mut self) -> Result<f64, String> {
+        let mut value = self.term()?;
+        loop {
+            if self.eat('+') {
+                value += self.term()?;
+            } else if self.eat('-') {
+                value -= self.term()?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn term(&mut self) -> Result<f64, String> {
+        let mut value = self.unary()?;
+        loop {
+            self.skip_whitespace();
+            // `**` is a power, handled below
+            if self.peek() == Some('*') && self.chars.get(self.pos + 1) != Some(&'*') {
+                self.pos += 1;
+                value *= self.unary()?;
+            } else if self.eat('/') {
+                let divisor = self.unary()?;
+                if divisor == 0.0 {
+                    return Err("division by zero".to_string());
+                }
+                value /= divisor;
+            } else if self.eat('%') {
+                let divisor = self.unary()?;
+                if divisor == 0.0 {
+                    return Err("division by zero".to_string());
+                }
+                value %= divisor;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    /// Signs bind looser than powers, so `-2^2` is -4.
+    fn unary(&mut self) -> Result<f64, String> {
+        if self.depth == MAX_DEPTH {
+            return Err("the expression is nested too deeply".to_string());
+        }
+        self.depth += 1;
+        let value = if self.eat('-') {
+            self.unary().map(|v| -v)
+        } else if self.eat('+') {
+            self.unary()
+        } else {
+            self.power()
+        };
+        self.depth -= 1;
+        value
+    }
+
+    fn power(&mut self) -> Result<f64, String> {
+        let base = self.atom()?;
+        self.skip_whitespace();
+        let is_power = match self.peek() {
+            Some('^') => {
+                self.pos += 1;
+                true
+            }
+            Some('*') if self.chars.get(self.pos + 1) == Some(&'*') => {
+                self.pos += 2;
+                true
+            }
+            _ => false,
+        };
+        if is_power {
+            // Right associative: 2^3^2 is 2^9
+            Ok(base.powf(self.unary()?))
+        } else {
+            Ok(base)
+        }
+    }
+
+    fn atom(&mut self) -> Result<f64, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('(') => {
+                self.pos += 1;
+                let value = self.expr()?;
+                if !self.eat(')') {
+                    return Err("missing ')'".to_string());
+                }
+                Ok(value)
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => self.number(),
+            Some(c) if c.is_alphabetic() => self.name(),
+            Some(c) => Err(format!("unexpected '{}'", c)),
+            None => Err("unexpected end of expression".to_string()),
+        }
+    }
+
+    fn number(&mut self) -> Result<f64, String> {
+        let start = self.pos;
+        if self.peek() == Some('0') {
+            let radix = match self.chars.get(self.pos + 1) {
+                Some('x' | 'X') => 16,
+                Some('b' | 'B') => 2,
+                Some('o' | 'O') => 8,
+                _ => 10,
+            };
+            if radix != 10 {
+                self.pos += 2;
+                let digits_start = self.pos;
+                while self.peek().is_some_and(|c| c.is_digit(radix)) {
+                    self.pos += 1;
+                }
+                let digits: String = self.chars[digits_start..self.pos].iter().collect();
+                return u64::from_str_radix(&digits, radix)
+                    .map(|n| n as f64)
+                    .map_err(|_| format!("invalid number '{}'", self.chars[start..self.pos].iter().collect::<String>()));
+            }
+        }
+        while self.peek().is_some_and(|c| c.is_ascii_digit() || c == '.') {
+            self.pos += 1;
+        }
+        // An exponent only when digits follow the `e`
+        if matches!(self.peek(), Some('e' | 'E')) {
+            let mut end = self.pos + 1;
+            if matches!(self.chars.get(end), Some('+' | '-')) {
+                end += 1;
+            }
+            if self.chars.get(end).is_some_and(char::is_ascii_digit) {
+                self.pos = end;
+                while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                    self.pos += 1;
+                }
+            }
+        }
+        let text: String = self.chars[start..self.pos].iter().collect();
+        text.parse().map_err(|_| format!("invalid number '{}'", text))
+    }
+
+    /// A constant, or a function call.
+    fn name(&mut self) -> Result<f64, String> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_alphanumeric() || c == '_') {
+            self.pos += 1;
+        }
+        let name: String = self.chars[start..self.pos].iter().collect::<String>().to_lowercase();
+        if !self.eat('(') {
+            return match name.as_str() {
+                "pi" => Ok(PI),
+                "tau" => Ok(TAU),
+                "e" => Ok(E),
+                _ => Err(format!("unknown name '{}'", name)),
+            };
+        }
+        let mut args = Vec::new();
+        if !self.eat(')') {
+            loop {
+                args.push(self.expr()?);
+                if self.eat(')') {
+                    break;
+                }
+                if !self.eat(',') {
+                    return Err("missing ')'".to_string());
+                }
+            }
+        }
+        call(&name, &args)
+    }
+}
+
+fn call(name: &str, args: &[f64]) -> Result<f64, String> {
+    let unary: Option<fn(f64) -> f64> = match name {
+        "sqrt" => Some(f64::sqrt),
+        "cbrt" => Some(f64::cbrt),
+        "abs" => Some(f64::abs),
+        "floor" => Some(f64::floor),
+        "ceil" => Some(f64::ceil),
+        "round" => Some(f64::round),
+        "trunc" => Some(f64::trunc),
+        "exp" => Some(f64::exp),
+        "ln" => Some(f64::ln),
+        "log" | "log10" => Some(f64::log10),
+        "log2" => Some(f64::log2),
+        "sin" => Some(f64::sin),
+        "cos" => Some(f64::cos),
+        "tan" => Some(f64::tan),
+        "asin" => Some(f64::asin),
+        "acos" => Some(f64::acos),
+        "atan" => Some(f64::atan),
+        _ => None,
+    };
+    if let Some(f) = unary {
+        return match args {
+            [x] => Ok(f(*x)),
+            _ => Err(format!("{}() takes one argument", name)),
+        };
+    }
+    match name {
+        "min" | "max" if !args.is_empty() => {
+            let pick = if name == "min" { f64::min } else { f64::max };
+            Ok(args.iter().copied().fold(args[0], pick))
+        }
+        "min" | "max" => Err(format!("{}() needs at least one argument", name)),
+        "pow" => match args {
+            [base, exp] => Ok(base.powf(*exp)),
+            _ => Err("pow() takes two arguments".to_string()),
+        },
+        _ => Err(format!("unknown function '{}'", name)),
+    }
+}
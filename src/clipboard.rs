@@ -0,0 +1,50 @@
+use std::sync::Mutex;
+
+/// What a copy or cut took, so that pasting it here puts it back the same
+/// way rather than as plain text.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClipboardContent {
+    /// One piece per cursor, in document order.
+    pub parts: Vec<String>,
+    /// Whole lines, copied from cursors without a selection, each ending in
+    /// a newline. They paste above the cursor's line rather than mid-line.
+    pub linewise: bool,
+}
+
+/// What the app last put on the system clipboard, to recognize it when it
+/// is pasted back.
+static LAST_COPY: Mutex<Option<ClipboardContent>> = Mutex::new(None);
+
+impl ClipboardContent {
+    /// Text from another program, pasted as it is.
+    pub fn plain(text: &str) -> Self {
+        Self { parts: vec![text.to_string()], linewise: false }
+    }
+
+    /// The text for the system clipboard: whole lines one after another,
+    /// other pieces one per line.
+    pub fn text(&self) -> String {
+        if self.linewise {
+            self.parts.concat()
+        } else {
+            self.parts.join("\n")
+        }
+    }
+
+    /// Remember this as the app's latest copy, as its text goes on the
+    /// system clipboard.
+    pub fn remember(&self) {
+        if let Ok(mut last) = LAST_COPY.lock() {
+            *last = Some(self.clone());
+        }
+    }
+
+    /// What pasting `text` from the system clipboard pastes: the app's
+    /// latest copy while the clipboard still holds it, else plain text.
+    pub fn recall(text: &str) -> Self {
+        // The rope is kept LF-only
+        let text = text.replace("\r\n", "\n");
+        let last = LAST_COPY.lock().ok().and_then(|last| last.clone());
+        last.filter(|content| content.text() == text).unwrap_or_else(|| Self::plain(&text))
+    }
+}
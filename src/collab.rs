@@ -0,0 +1,603 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::time::Duration;
+
+use automerge::sync::{self, SyncDoc};
+use automerge::transaction::Transactable;
+use automerge::{AutoCommit, ObjId, ObjType, ReadDoc, TextEncoding, ROOT};
+use eframe::egui::{self, Color32};
+use ropey::Rope;
+use serde::{Deserialize, Serialize};
+use tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tungstenite::http::StatusCode;
+use tungstenite::{Message, WebSocket};
+
+use crate::editor::{Editor, Position};
+use crate::undo;
+
+pub const DEFAULT_PORT: u16 = 7878;
+/// How long a connection waits for input before sending what's queued.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+/// Longest a connecting guest may take over the WebSocket handshake.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+/// Most handshakes in progress at once; more connections are dropped.
+const MAX_HANDSHAKES: usize = 8;
+/// The host's peer id; guests are numbered from 1 as they join.
+const HOST: u64 = 0;
+/// Key of the shared text in the document root.
+const TEXT_KEY: &str = "text";
+
+const TAG_SYNC: u8 = 0;
+const TAG_CONTROL: u8 = 1;
+
+/// Colors for collaborators' cursors, picked by peer id.
+const COLORS: [Color32; 6] = [
+    Color32::from_rgb(230, 120, 90),
+    Color32::from_rgb(110, 190, 110),
+    Color32::from_rgb(100, 150, 240),
+    Color32::from_rgb(220, 170, 60),
+    Color32::from_rgb(190, 110, 220),
+    Color32::from_rgb(70, 190, 200),
+];
+
+/// A collaborator's cursor, as 0-based line and column.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SharedCursor {
+    pub pos: (usize, usize),
+    pub anchor: Option<(usize, usize)>,
+}
+
+impl SharedCursor {
+    pub fn pos(&self) -> Position {
+        Position::new(self.pos.0, self.pos.1)
+    }
+
+    pub fn selection(&self) -> Option<(Position, Position)> {
+        let anchor = Position::new(self.anchor?.0, self.anchor?.1);
+        Some((anchor.min(self.pos()), anchor.max(self.pos())))
+    }
+}
+
+/// Who is in the session and where their cursors are.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Presence {
+    pub peer: u64,
+    pub name: String,
+    pub cursors: Vec<SharedCursor>,
+}
+
+impl Presence {
+    pub fn color(&self) -> Color32 {
+        COLORS[self.peer as usize % COLORS.len()]
+    }
+}
+
+/// Messages besides document sync, sent as JSON.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+enum Control {
+    /// Host to a guest that just connected: the guest's peer id.
+    Welcome { peer: u64 },
+    Presence(Presence),
+    Left { peer: u64 },
+}
+
+/// What a connection thread reports.
+enum NetEvent {
+    Connected(u64, Sender<Vec<u8>>),
+    Received(u64, Vec<u8>),
+    Disconnected(u64, Option<String>),
+}
+
+/// Changes worth telling the user about.
+pub enum SessionEvent {
+    Joined(String),
+    Left(String),
+    /// The session is over (the host went away); the reason.
+    Ended(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Role {
+    /// Guests must give `secret` as the path of the URL they join with.
+    /// Unless `public`, only this computer can connect.
+    Host { port: u16, secret: String, public: bool },
+    Guest { url: String },
+}
+
+impl Role {
+    /// The URL guests join with, given the host's address.
+    pub fn join_url(&self, address: &str) -> Option<String> {
+        match self {
+            Role::Host { port, secret, .. } => Some(format!("ws://{}:{}/{}", address, port, secret)),
+            Role::Guest { .. } => None,
+        }
+    }
+}
+
+struct Peer {
+    out: Sender<Vec<u8>>,
+    sync: sync::State,
+}
+
+/// A live collaboration session on one buffer. Edits are kept in an
+/// Automerge document that every peer holds a copy of; guests sync it
+/// with the host, which relays changes and cursors between them.
+pub struct Session {
+    pub role: Role,
+    pub name: String,
+    peer: u64,
+    doc: AutoCommit,
+    text: Option<ObjId>,
+    /// The buffer as of the last sync with the document.
+    synced: Rope,
+    synced_version: Option<u64>,
+    peers: HashMap<u64, Peer>,
+    /// Everyone else in the session, by peer id.
+    pub collaborators: BTreeMap<u64, Presence>,
+    /// The collaborator whose cursor the view keeps in sight.
+    pub following: Option<u64>,
+    sent_cursors: Vec<SharedCursor>,
+    /// Sync messages arrived since the buffer was last brought up to date.
+    received: bool,
+    events: Receiver<NetEvent>,
+    stop: Arc<AtomicBool>,
+    /// A guest has reached the host.
+    pub connected: bool,
+}
+
+impl Session {
+    /// Share `editor` with whoever connects to `port` with the session's
+    /// secret: from this computer only, or with `public` from any.
+    pub fn host(name: String, port: u16, public: bool, editor: &Editor, ctx: egui::Context) -> io::Result<Self> {
+        let address = if public { Ipv4Addr::UNSPECIFIED } else { Ipv4Addr::LOCALHOST };
+        let listener = TcpListener::bind((address, port))?;
+        listener.set_nonblocking(true)?;
+        let mut doc = AutoCommit::new_with_encoding(TextEncoding::UnicodeCodePoint);
+        let text = doc.put_object(ROOT, TEXT_KEY, ObjType::Text).map_err(io::Error::other)?;
+        doc.splice_text(&text, 0, 0, &editor.rope.to_string()).map_err(io::Error::other)?;
+
+        let (tx, events) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let secret = uuid::Uuid::new_v4().simple().to_string();
+        accept(listener, secret.clone(), tx, stop.clone(), ctx);
+        tracing::info!(port, public, "hosting collaboration session");
+        Ok(Self {
+            role: Role::Host { port, secret, public },
+            name,
+            peer: HOST,
+            doc,
+            text: Some(text),
+            synced: editor.rope.clone(),
+            synced_version: Some(editor.version),
+            peers: HashMap::new(),
+            collaborators: BTreeMap::new(),
+            following: None,
+            sent_cursors: Vec::new(),
+            received: false,
+            events,
+            stop,
+            connected: true,
+        })
+    }
+
+    /// Connect to the session at `url`, e.g. `ws://192.168.1.20:7878`. The
+    /// buffer fills in once the host's document arrives.
+    pub fn join(name: String, url: String, ctx: egui::Context) -> Self {
+        let (tx, events) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        connect(url.clone(), tx, stop.clone(), ctx);
+        tracing::info!(url = %url, "joining collaboration session");
+        Self {
+            role: Role::Guest { url },
+            name,
+            peer: HOST,
+            doc: AutoCommit::new_with_encoding(TextEncoding::UnicodeCodePoint),
+            text: None,
+            synced: Rope::new(),
+            synced_version: None,
+            peers: HashMap::new(),
+            collaborators: BTreeMap::new(),
+            following: None,
+            sent_cursors: Vec::new(),
+            received: false,
+            events,
+            stop,
+            connected: false,
+        }
+    }
+
+    /// The followed collaborator's primary cursor.
+    pub fn followed_cursor(&self) -> Option<Position> {
+        let presence = self.collaborators.get(&self.following?)?;
+        presence.cursors.first().map(SharedCursor::pos)
+    }
+
+    /// Follow the next collaborator after the current one, or stop after
+    /// the last. Returns who is followed now.
+    pub fn follow_next(&mut self) -> Option<&Presence> {
+        let next = match self.following {
+            Some(current) => self.collaborators.range(current + 1..).next(),
+            None => self.collaborators.iter().next(),
+        };
+        self.following = next.map(|(&peer, _)| peer);
+        next.map(|(_, presence)| presence)
+    }
+
+    fn forget(&mut self, peer: u64) -> Option<Presence> {
+        if self.following == Some(peer) {
+            self.following = None;
+        }
+        self.collaborators.remove(&peer)
+    }
+
+    fn is_host(&self) -> bool {
+        self.peer == HOST && matches!(self.role, Role::Host { .. })
+    }
+
+    /// Exchange changes with the other peers: send `editor`'s edits and
+    /// cursors, and apply everyone else's. Also returns whether the buffer
+    /// changed.
+    pub fn update(&mut self, editor: &mut Editor) -> (bool, Vec<SessionEvent>) {
+        let mut out = Vec::new();
+        self.send_local_edits(editor);
+        while let Ok(event) = self.events.try_recv() {
+            self.handle(event, &mut out);
+        }
+        let changed = self.apply_remote_edits(editor);
+        self.send_presence(editor);
+        (changed, out)
+    }
+
+    fn send_local_edits(&mut self, editor: &Editor) {
+        let Some(text) = &self.text else {
+            return;
+        };
+        if self.synced_version == Some(editor.version) {
+            return;
+        }
+        let (start, old_end, new_end) = undo::changed_chars(&self.synced, &editor.rope);
+        if start < old_end || start < new_end {
+            let inserted = editor.rope.slice(start..new_end).to_string();
+            if let Err(e) = self.doc.splice_text(text, start, (old_end - start) as isize, &inserted) {
+                tracing::error!(error = %e, "failed to record edit in the shared document");
+            }
+            self.sync_all(None);
+        }
+        self.synced = editor.rope.clone();
+        self.synced_version = Some(editor.version);
+    }
+
+    /// Bring the buffer up to date with the document after remote changes.
+    fn apply_remote_edits(&mut self, editor: &mut Editor) -> bool {
+        if !std::mem::take(&mut self.received) {
+            return false;
+        }
+        let text = match &self.text {
+            Some(text) => text.clone(),
+            // A guest waits for the host's document to arrive
+            None => match self.doc.get(ROOT, TEXT_KEY) {
+                Ok(Some((_, id))) => {
+                    let Ok(initial) = self.doc.text(&id) else {
+                        return false;
+                    };
+                    editor.set_text(&initial);
+                    editor.modified = false;
+                    self.text = Some(id);
+                    self.synced = editor.rope.clone();
+                    self.synced_version = Some(editor.version);
+                    return true;
+                }
+                _ => return false,
+            },
+        };
+        let Ok(current) = self.doc.text(&text) else {
+            return false;
+        };
+        let current = Rope::from_str(&current);
+        let (start, old_end, new_end) = undo::changed_chars(&self.synced, &current);
+        if start == old_end && start == new_end {
+            return false;
+        }
+        // Local edits were sent first, so the buffer matches `synced`
+        editor.apply_remote_edit(start, old_end, &current.slice(start..new_end).to_string());
+        self.synced = editor.rope.clone();
+        self.synced_version = Some(editor.version);
+        true
+    }
+
+    fn send_presence(&mut self, editor: &Editor) {
+        let cursors: Vec<SharedCursor> = editor
+            .cursors
+            .iter()
+            .map(|c| SharedCursor {
+                pos: (c.pos.line, c.pos.col),
+                anchor: c.anchor.map(|a| (a.line, a.col)),
+            })
+            .collect();
+        if cursors == self.sent_cursors || !self.connected || self.text.is_none() {
+            return;
+        }
+        self.sent_cursors = cursors.clone();
+        let presence = Presence { peer: self.peer, name: self.name.clone(), cursors };
+        self.broadcast(&Control::Presence(presence), None);
+    }
+
+    fn handle(&mut self, event: NetEvent, out: &mut Vec<SessionEvent>) {
+        match event {
+            NetEvent::Connected(peer, sender) => {
+                self.peers.insert(peer, Peer { out: sender, sync: sync::State::new() });
+                if self.is_host() {
+                    tracing::info!(peer, "collaborator connected");
+                    self.send_to(peer, &Control::Welcome { peer });
+                    let mut known: Vec<Presence> = self.collaborators.values().cloned().collect();
+                    known.push(Presence {
+                        peer: self.peer,
+                        name: self.name.clone(),
+                        cursors: self.sent_cursors.clone(),
+                    });
+                    for presence in known {
+                        self.send_to(peer, &Control::Presence(presence));
+                    }
+                } else {
+                    self.connected = true;
+                }
+                self.sync_all(None);
+            }
+            NetEvent::Received(from, bytes) => match bytes.split_first() {
+                Some((&TAG_SYNC, message)) => self.receive_sync(from, message),
+                Some((&TAG_CONTROL, json)) => match serde_json::from_slice(json) {
+                    Ok(control) => self.receive_control(from, control, out),
+                    Err(e) => tracing::warn!(peer = from, error = %e, "bad control message"),
+                },
+                _ => tracing::warn!(peer = from, "unknown message"),
+            },
+            NetEvent::Disconnected(peer, error) => {
+                self.peers.remove(&peer);
+                if self.is_host() {
+                    tracing::info!(peer, error = ?error, "collaborator disconnected");
+                    if let Some(presence) = self.forget(peer) {
+                        out.push(SessionEvent::Left(presence.name));
+                    }
+                    self.broadcast(&Control::Left { peer }, None);
+                } else {
+                    tracing::info!(error = ?error, "disconnected from host");
+                    self.collaborators.clear();
+                    self.following = None;
+                    let reason = match error {
+                        Some(e) if !self.connected => format!("Could not connect: {}", e),
+                        Some(e) => format!("Lost the connection: {}", e),
+                        None => "The host ended the session".to_string(),
+                    };
+                    out.push(SessionEvent::Ended(reason));
+                }
+            }
+        }
+    }
+
+    fn receive_sync(&mut self, from: u64, message: &[u8]) {
+        let Some(peer) = self.peers.get_mut(&from) else {
+            return;
+        };
+        let message = match sync::Message::decode(message) {
+            Ok(message) => message,
+            Err(e) => return tracing::warn!(peer = from, error = %e, "bad sync message"),
+        };
+        if let Err(e) = self.doc.sync().receive_sync_message(&mut peer.sync, message) {
+            tracing::warn!(peer = from, error = %e, "failed to apply sync message");
+        }
+        self.received = true;
+        // Answer the sender, and pass anything new on to everyone else
+        self.sync_all(None);
+    }
+
+    fn receive_control(&mut self, from: u64, control: Control, out: &mut Vec<SessionEvent>) {
+        match control {
+            Control::Welcome { peer } if !self.is_host() => {
+                self.peer = peer;
+                // Our cursors go out again under the new id
+                self.sent_cursors.clear();
+            }
+            Control::Presence(mut presence) => {
+                // A guest speaks only for itself, whatever id it claims
+                if self.is_host() {
+                    presence.peer = from;
+                }
+                if presence.peer == self.peer {
+                    return;
+                }
+                if self.is_host() {
+                    self.broadcast(&Control::Presence(presence.clone()), Some(from));
+                }
+                if !self.collaborators.contains_key(&presence.peer) {
+                    out.push(SessionEvent::Joined(presence.name.clone()));
+                }
+                self.collaborators.insert(presence.peer, presence);
+            }
+            Control::Left { peer } if !self.is_host() => {
+                if let Some(presence) = self.forget(peer) {
+                    out.push(SessionEvent::Left(presence.name));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Send each peer (but `except`) whatever sync message it needs next.
+    fn sync_all(&mut self, except: Option<u64>) {
+        for (&id, peer) in self.peers.iter_mut().filter(|(&id, _)| Some(id) != except) {
+            if let Some(message) = self.doc.sync().generate_sync_message(&mut peer.sync) {
+                let mut bytes = vec![TAG_SYNC];
+                bytes.extend(message.encode());
+                if peer.out.send(bytes).is_err() {
+                    tracing::debug!(peer = id, "sync message for a closed connection");
+                }
+            }
+        }
+    }
+
+    fn send_to(&self, peer: u64, control: &Control) {
+        if let Some(peer) = self.peers.get(&peer) {
+            let _ = peer.out.send(encode_control(control));
+        }
+    }
+
+    fn broadcast(&self, control: &Control, except: Option<u64>) {
+        let bytes = encode_control(control);
+        for (_, peer) in self.peers.iter().filter(|(&id, _)| Some(id) != except) {
+            let _ = peer.out.send(bytes.clone());
+        }
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+fn encode_control(control: &Control) -> Vec<u8> {
+    let mut bytes = vec![TAG_CONTROL];
+    bytes.extend(serde_json::to_vec(control).unwrap_or_default());
+    bytes
+}
+
+/// Accept guests that know `secret` until the session stops, each on its
+/// own thread.
+fn accept(listener: TcpListener, secret: String, events: Sender<NetEvent>, stop: Arc<AtomicBool>, ctx: egui::Context) {
+    let next_peer = AtomicU64::new(HOST + 1);
+    let handshakes = Arc::new(AtomicUsize::new(0));
+    std::thread::spawn(move || {
+        while !stop.load(Ordering::Relaxed) {
+            let stream = match listener.accept() {
+                Ok((stream, _)) => stream,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                    std::thread::sleep(POLL_INTERVAL * 5);
+                    continue;
+                }
+                Err(e) => {
+                    tracing::warn!(error = %e, "failed to accept a collaborator");
+                    continue;
+                }
+            };
+            if handshakes.fetch_add(1, Ordering::SeqCst) >= MAX_HANDSHAKES {
+                handshakes.fetch_sub(1, Ordering::SeqCst);
+                tracing::warn!("too many connections at once; dropped one");
+                continue;
+            }
+            let peer = next_peer.fetch_add(1, Ordering::Relaxed);
+            let (secret, handshakes) = (secret.clone(), handshakes.clone());
+            let (events, stop, ctx) = (events.clone(), stop.clone(), ctx.clone());
+            std::thread::spawn(move || {
+                let socket = handshake(stream, &secret);
+                handshakes.fetch_sub(1, Ordering::SeqCst);
+                match socket {
+                    Ok(socket) => {
+                        let _ = socket.get_ref().set_read_timeout(Some(POLL_INTERVAL));
+                        let _ = socket.get_ref().set_write_timeout(None);
+                        pump(socket, peer, events, stop, ctx);
+                    }
+                    Err(e) => tracing::warn!(error = %e, "websocket handshake failed"),
+                }
+            });
+        }
+    });
+}
+
+/// Complete a guest's WebSocket handshake, refusing it unless the URL's
+/// path is the session's secret, and giving up after `HANDSHAKE_TIMEOUT`.
+// The refusal's type is tungstenite's
+#[allow(clippy::result_large_err)]
+fn handshake(stream: TcpStream, secret: &str) -> Result<WebSocket<TcpStream>, String> {
+    stream.set_nonblocking(false).map_err(|e| e.to_string())?;
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT)).map_err(|e| e.to_string())?;
+    stream.set_write_timeout(Some(HANDSHAKE_TIMEOUT)).map_err(|e| e.to_string())?;
+    let check = |request: &Request, response: Response| {
+        if request.uri().path().trim_start_matches('/') == secret {
+            return Ok(response);
+        }
+        let mut refused = ErrorResponse::new(Some("Wrong session link".to_string()));
+        *refused.status_mut() = StatusCode::FORBIDDEN;
+        Err(refused)
+    };
+    tungstenite::accept_hdr(stream, check).map_err(|e| e.to_string())
+}
+
+/// Connect to a host and pump its connection.
+fn connect(url: String, events: Sender<NetEvent>, stop: Arc<AtomicBool>, ctx: egui::Context) {
+    std::thread::spawn(move || match tungstenite::connect(url.as_str()) {
+        Ok((socket, _)) => {
+            if let tungstenite::stream::MaybeTlsStream::Plain(stream) = socket.get_ref() {
+                let _ = stream.set_read_timeout(Some(POLL_INTERVAL));
+            }
+            pump(socket, HOST, events, stop, ctx);
+        }
+        Err(e) => {
+            let _ = events.send(NetEvent::Disconnected(HOST, Some(e.to_string())));
+            ctx.request_repaint();
+        }
+    });
+}
+
+/// Move messages between a socket and the session until either side
+/// closes. The socket's read timeout keeps outgoing messages flowing.
+fn pump<S: Read + Write>(
+    mut socket: WebSocket<S>,
+    peer: u64,
+    events: Sender<NetEvent>,
+    stop: Arc<AtomicBool>,
+    ctx: egui::Context,
+) {
+    let (out, outgoing) = mpsc::channel::<Vec<u8>>();
+    if events.send(NetEvent::Connected(peer, out)).is_err() {
+        return;
+    }
+    ctx.request_repaint();
+    let error = loop {
+        if stop.load(Ordering::Relaxed) {
+            let _ = socket.close(None);
+            let _ = socket.flush();
+            break None;
+        }
+        let mut failed = None;
+        for bytes in outgoing.try_iter() {
+            if let Err(e) = socket.write(Message::Binary(bytes)) {
+                failed = Some(e);
+                break;
+            }
+        }
+        if let Some(e) = failed.or_else(|| socket.flush().err().filter(|e| !would_block(e))) {
+            break Some(e.to_string());
+        }
+        match socket.read() {
+            Ok(Message::Binary(bytes)) => {
+                if events.send(NetEvent::Received(peer, bytes)).is_err() {
+                    break None;
+                }
+                ctx.request_repaint();
+            }
+            Ok(Message::Close(_)) => break None,
+            Ok(_) => {}
+            Err(e) if would_block(&e) => {}
+            Err(tungstenite::Error::ConnectionClosed) => break None,
+            Err(e) => break Some(e.to_string()),
+        }
+    };
+    let _ = events.send(NetEvent::Disconnected(peer, error));
+    ctx.request_repaint();
+}
+
+fn would_block(e: &tungstenite::Error) -> bool {
+    matches!(e, tungstenite::Error::Io(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut))
+}
+
+/// A name to show to collaborators: the user's login, or "Guest".
+pub fn default_name() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "Guest".to_string())
+}
@@ -0,0 +1,333 @@
+use std::path::Path;
+
+use eframe::egui::Color32;
+
+/// A color written in the text: `#rrggbb`, `rgb(…)`/`rgba(…)`, or in
+/// stylesheets also `#rgb` and a CSS color name. Columns count chars.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColorLiteral {
+    pub start: usize,
+    pub end: usize,
+    pub color: Color32,
+    form: Form,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Form {
+    /// Hex digits, how many and whether they were upper case.
+    Hex { digits: usize, upper: bool },
+    /// `rgb(` or `rgba(` as written.
+    Function { alpha: bool },
+    Named,
+}
+
+/// Files whose short hex codes and bare color names are colors rather than
+/// issue numbers and English words.
+const STYLESHEET_EXTENSIONS: &[&str] = &["css", "scss", "sass", "less", "styl", "html", "htm", "svg", "vue", "svelte"];
+
+/// Whether `path` is a stylesheet or markup file, where every form is
+/// recognized.
+pub fn is_stylesheet(path: Option<&Path>) -> bool {
+    path.and_then(|p| p.extension())
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| STYLESHEET_EXTENSIONS.iter().any(|s| e.eq_ignore_ascii_case(s)))
+}
+
+/// The color literals on one line, in order.
+pub fn find(line: &str, stylesheet: bool) -> Vec<ColorLiteral> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut found = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let before = i.checked_sub(1).map(|j| chars[j]);
+        let literal = if chars[i] == '#' && !before.is_some_and(|c| c.is_alphanumeric() || c == '&' || c == '#') {
+            hex_at(&chars, i, stylesheet)
+        } else if chars[i].is_ascii_alphabetic() && !before.is_some_and(|c| is_word_char(c) || c == '-' || c == '#') {
+            // `.red` is a class selector, not a color
+            function_at(&chars, i).or_else(|| if stylesheet && before != Some('.') { named_at(&chars, i) } else { None })
+        } else {
+            None
+        };
+        match literal {
+            Some(literal) => {
+                i = literal.end;
+                found.push(literal);
+            }
+            None => i += 1,
+        }
+    }
+    found
+}
+
+impl ColorLiteral {
+    /// `color` written the way this literal was: hex stays hex (short if it
+    /// was short and still can be), `rgb()` stays a function, and a name
+    /// becomes hex.
+    pub fn rewrite(&self, color: Color32) -> String {
+        let [r, g, b, a] = color.to_srgba_unmultiplied();
+        match self.form {
+            Form::Hex { digits, upper } => {
+                let short = matches!(digits, 3 | 4) && [r, g, b, a].iter().all(|c| c >> 4 == c & 0xf);
+                let keep_alpha = a != 255 || matches!(digits, 4 | 8);
+                let text = match (short, keep_alpha) {
+                    (true, false) => format!("#{:x}{:x}{:x}", r & 0xf, g & 0xf, b & 0xf),
+                    (true, true) => format!("#{:x}{:x}{:x}{:x}", r & 0xf, g & 0xf, b & 0xf, a & 0xf),
+                    (false, false) => format!("#{:02x}{:02x}{:02x}", r, g, b),
+                    (false, true) => format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a),
+                };
+                if upper {
+                    text.to_uppercase()
+                } else {
+                    text
+                }
+            }
+            Form::Function { alpha } if alpha || a != 255 => {
+                let a = (a as f32 / 255.0 * 100.0).round() / 100.0;
+                format!("rgba({}, {}, {}, {})", r, g, b, a)
+            }
+            Form::Function { .. } => format!("rgb({}, {}, {})", r, g, b),
+            Form::Named if a != 255 => format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a),
+            Form::Named => format!("#{:02x}{:02x}{:02x}", r, g, b),
+        }
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn hex_at(chars: &[char], start: usize, stylesheet: bool) -> Option<ColorLiteral> {
+    let digits = chars[start + 1..].iter().take_while(|c| c.is_ascii_hexdigit()).count();
+    let end = start + 1 + digits;
+    if chars.get(end).is_some_and(|&c| is_word_char(c)) {
+        return None;
+    }
+    match digits {
+        6 | 8 => {}
+        3 | 4 if stylesheet => {}
+        _ => return None,
+    }
+    let hex: String = chars[start + 1..end].iter().collect();
+    let nibble = |i: usize| u8::from_str_radix(&hex[i..i + 1], 16).ok().map(|n| n * 17);
+    let byte = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    let [r, g, b, a] = match digits {
+        3 => [nibble(0)?, nibble(1)?, nibble(2)?, 255],
+        4 => [nibble(0)?, nibble(1)?, nibble(2)?, nibble(3)?],
+        6 => [byte(0)?, byte(2)?, byte(4)?, 255],
+        _ => [byte(0)?, byte(2)?, byte(4)?, byte(6)?],
+    };
+    Some(ColorLiteral {
+        start,
+        end,
+        color: Color32::from_rgba_unmultiplied(r, g, b, a),
+        form: Form::Hex { digits, upper: hex.chars().any(|c| c.is_ascii_uppercase()) },
+    })
+}
+
+/// `rgb(255, 0, 0)`, `rgba(255, 0, 0, 0.5)` or the space-separated
+/// `rgb(255 0 0 / 50%)`, with channels as numbers or percentages.
+fn function_at(chars: &[char], start: usize) -> Option<ColorLiteral> {
+    let name: String = chars[start..].iter().take(5).collect::<String>().to_ascii_lowercase();
+    let alpha = name.starts_with("rgba(");
+    if !alpha && !name.starts_with("rgb(") {
+        return None;
+    }
+    let open = start + if alpha { 5 } else { 4 };
+    let close = open + chars[open..].iter().position(|&c| c == ')')?;
+    let args: String = chars[open..close].iter().collect();
+    let parts: Vec<&str> = args.split(|c: char| c == ',' || c == '/' || c.is_whitespace()).filter(|p| !p.is_empty()).collect();
+    if !matches!(parts.len(), 3 | 4) {
+        return None;
+    }
+    let channel = |text: &str| -> Option<u8> {
+        let value = match text.strip_suffix('%') {
+            Some(percent) => percent.parse::<f32>().ok()? * 2.55,
+            None => text.parse::<f32>().ok()?,
+        };
+        Some(value.round().clamp(0.0, 255.0) as u8)
+    };
+    let opacity = |text: &str| -> Option<u8> {
+        let value = match text.strip_suffix('%') {
+            Some(percent) => percent.parse::<f32>().ok()? / 100.0,
+            None => text.parse::<f32>().ok()?,
+        };
+        Some((value.clamp(0.0, 1.0) * 255.0).round() as u8)
+    };
+    let a = match parts.get(3) {
+        Some(text) => opacity(text)?,
+        None => 255,
+    };
+    Some(ColorLiteral {
+        start,
+        end: close + 1,
+        color: Color32::from_rgba_unmultiplied(channel(parts[0])?, channel(parts[1])?, channel(parts[2])?, a),
+        form: Form::Function { alpha },
+    })
+}
+
+fn named_at(chars: &[char], start: usize) -> Option<ColorLiteral> {
+    let len = chars[start..].iter().take_while(|c| c.is_ascii_alphabetic()).count();
+    if chars.get(start + len).is_some_and(|&c| is_word_char(c) || c == '-' || c == '(') {
+        return None;
+    }
+    let word: String = chars[start..start + len].iter().collect::<String>().to_ascii_lowercase();
+    let &(_, rgb) = NAMED_COLORS.iter().find(|(name, _)| *name == word)?;
+    Some(ColorLiteral {
+        start,
+        end: start + len,
+        color: Color32::from_rgb((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8),
+        form: Form::Named,
+    })
+}
+
+/// The CSS named colors.
+const NAMED_COLORS: &[(&str, u32)] = &[
+    ("aliceblue", 0xf0f8ff),
+    ("antiquewhite", 0xfaebd7),
+    ("aqua", 0x00ffff),
+    ("aquamarine", 0x7fffd4),
+    ("azure", 0xf0ffff),
+    ("beige", 0xf5f5dc),
+    ("bisque", 0xffe4c4),
+    ("black", 0x000000),
+    ("blanchedalmond", 0xffebcd),
+    ("blue", 0x0000ff),
+    ("blueviolet", 0x8a2be2),
+    ("brown", 0xa52a2a),
+    ("burlywood", 0xdeb887),
+    ("cadetblue", 0x5f9ea0),
+    ("chartreuse", 0x7fff00),
+    ("chocolate", 0xd2691e),
+    ("coral", 0xff7f50),
+    ("cornflowerblue", 0x6495ed),
+    ("cornsilk", 0xfff8dc),
+    ("crimson", 0xdc143c),
+    ("cyan", 0x00ffff),
+    ("darkblue", 0x00008b),
+    ("darkcyan", 0x008b8b),
+    ("darkgoldenrod", 0xb8860b),
+    ("darkgray", 0xa9a9a9),
+    ("darkgreen", 0x006400),
+    ("darkgrey", 0xa9a9a9),
+    ("darkkhaki", 0xbdb76b),
+    ("darkmagenta", 0x8b008b),
+    ("darkolivegreen", 0x556b2f),
+    ("darkorange", 0xff8c00),
+    ("darkorchid", 0x9932cc),
+    ("darkred", 0x8b0000),
+    ("darksalmon", 0xe9967a),
+    ("darkseagreen", 0x8fbc8f),
+    ("darkslateblue", 0x483d8b),
+    ("darkslategray", 0x2f4f4f),
+    ("darkslategrey", 0x2f4f4f),
+    ("darkturquoise", 0x00ced1),
+    ("darkviolet", 0x9400d3),
+    ("deeppink", 0xff1493),
+    ("deepskyblue", 0x00bfff),
+    ("dimgray", 0x696969),
+    ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1e90ff),
+    ("firebrick", 0xb22222),
+    ("floralwhite", 0xfffaf0),
+    ("forestgreen", 0x228b22),
+    ("fuchsia", 0xff00ff),
+    ("gainsboro", 0xdcdcdc),
+    ("ghostwhite", 0xf8f8ff),
+    ("gold", 0xffd700),
+    ("goldenrod", 0xdaa520),
+    ("gray", 0x808080),
+    ("green", 0x008000),
+    ("greenyellow", 0xadff2f),
+    ("grey", 0x808080),
+    ("honeydew", 0xf0fff0),
+    ("hotpink", 0xff69b4),
+    ("indianred", 0xcd5c5c),
+    ("indigo", 0x4b0082),
+    ("ivory", 0xfffff0),
+    ("khaki", 0xf0e68c),
+    ("lavender", 0xe6e6fa),
+    ("lavenderblush", 0xfff0f5),
+    ("lawngreen", 0x7cfc00),
+    ("lemonchiffon", 0xfffacd),
+    ("lightblue", 0xadd8e6),
+    ("lightcoral", 0xf08080),
+    ("lightcyan", 0xe0ffff),
+    ("lightgoldenrodyellow", 0xfafad2),
+    ("lightgray", 0xd3d3d3),
+    ("lightgreen", 0x90ee90),
+    ("lightgrey", 0xd3d3d3),
+    ("lightpink", 0xffb6c1),
+    ("lightsalmon", 0xffa07a),
+    ("lightseagreen", 0x20b2aa),
+    ("lightskyblue", 0x87cefa),
+    ("lightslategray", 0x778899),
+    ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xb0c4de),
+    ("lightyellow", 0xffffe0),
+    ("lime", 0x00ff00),
+    ("limegreen", 0x32cd32),
+    ("linen", 0xfaf0e6),
+    ("magenta", 0xff00ff),
+    ("maroon", 0x800000),
+    ("mediumaquamarine", 0x66cdaa),
+    ("mediumblue", 0x0000cd),
+    ("mediumorchid", 0xba55d3),
+    ("mediumpurple", 0x9370db),
+    ("mediumseagreen", 0x3cb371),
+    ("mediumslateblue", 0x7b68ee),
+    ("mediumspringgreen", 0x00fa9a),
+    ("mediumturquoise", 0x48d1cc),
+    ("mediumvioletred", 0xc71585),
+    ("midnightblue", 0x191970),
+    ("mintcream", 0xf5fffa),
+    ("mistyrose", 0xffe4e1),
+    ("moccasin", 0xffe4b5),
+    ("navajowhite", 0xffdead),
+    ("navy", 0x000080),
+    ("oldlace", 0xfdf5e6),
+    ("olive", 0x808000),
+    ("olivedrab", 0x6b8e23),
+    ("orange", 0xffa500),
+    ("orangered", 0xff4500),
+    ("orchid", 0xda70d6),
+    ("palegoldenrod", 0xeee8aa),
+    ("palegreen", 0x98fb98),
+    ("paleturquoise", 0xafeeee),
+    ("palevioletred", 0xdb7093),
+    ("papayawhip", 0xffefd5),
+    ("peachpuff", 0xffdab9),
+    ("peru", 0xcd853f),
+    ("pink", 0xffc0cb),
+    ("plum", 0xdda0dd),
+    ("powderblue", 0xb0e0e6),
+    ("purple", 0x800080),
+    ("rebeccapurple", 0x663399),
+    ("red", 0xff0000),
+    ("rosybrown", 0xbc8f8f),
+    ("royalblue", 0x4169e1),
+    ("saddlebrown", 0x8b4513),
+    ("salmon", 0xfa8072),
+    ("sandybrown", 0xf4a460),
+    ("seagreen", 0x2e8b57),
+    ("seashell", 0xfff5ee),
+    ("sienna", 0xa0522d),
+    ("silver", 0xc0c0c0),
+    ("skyblue", 0x87ceeb),
+    ("slateblue", 0x6a5acd),
+    ("slategray", 0x708090),
+    ("slategrey", 0x708090),
+    ("snow", 0xfffafa),
+    ("springgreen", 0x00ff7f),
+    ("steelblue", 0x4682b4),
+    ("tan", 0xd2b48c),
+    ("teal", 0x008080),
+    ("thistle", 0xd8bfd8),
+    ("tomato", 0xff6347),
+    ("turquoise", 0x40e0d0),
+    ("violet", 0xee82ee),
+    ("wheat", 0xf5deb3),
+    ("white", 0xffffff),
+    ("whitesmoke", 0xf5f5f5),
+    ("yellow", 0xffff00),
+    ("yellowgreen", 0x9acd32),
+];
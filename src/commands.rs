@@ -0,0 +1,578 @@
+use std::path::PathBuf;
+use std::sync::{OnceLock, RwLock};
+
+use eframe::egui::{Key, Modifiers};
+use tracing_subscriber::filter::LevelFilter;
+
+use crate::config::{ColorScheme, Keymap};
+use crate::logging;
+use crate::reveal;
+use crate::syntax::SyntaxPalette;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CommandId {
+    // Files and tabs
+    NewTab,
+    NewScratch,
+    DeleteScratch,
+    OpenFile,
+    OpenFolder,
+    CloseFolder,
+    OpenContainingFolder,
+    SaveFile,
+    SaveFileAs,
+    SaveAll,
+    SaveFileAsAdmin,
+    RenameFile,
+    ShowLocalHistory,
+    ShowFileInfo,
+    RevealInFileManager,
+    DeleteFile,
+    CopyAbsolutePath,
+    CopyRelativePath,
+    CopyFileName,
+    CloseTab,
+    CloseAllTabs,
+    TogglePinTab,
+    NextTab,
+    PrevTab,
+    // Navigation and overlays
+    ShowCommandPalette,
+    QuickOpen,
+    RepeatLastCommand,
+    CompleteWord,
+    Find,
+    Replace,
+    GoToLine,
+    GoToDefinition,
+    ShowCodeActions,
+    RenameSymbol,
+    // Running
+    RunFile,
+    StopRun,
+    ToggleOutputPanel,
+    // Editing
+    Undo,
+    Redo,
+    ShowUndoHistory,
+    ShowProblems,
+    ShowTodos,
+    ToggleSourceControl,
+    ShowCollaboration,
+    FollowCollaborator,
+    LeaveCollaboration,
+    Copy,
+    CopyWithHighlighting,
+    Cut,
+    Paste,
+    DeleteWordBackward,
+    DeleteWordForward,
+    DeleteSubwordBackward,
+    DeleteSubwordForward,
+    ToggleOverwrite,
+    // View
+    CenterCursor,
+    ScrollLineUp,
+    ScrollLineDown,
+    CursorToViewTop,
+    CursorToViewBottom,
+    // Lines
+    DuplicateLine,
+    CopyLineUp,
+    CopyLineDown,
+    DeleteLine,
+    SelectLine,
+    ToggleComment,
+    MoveLineUp,
+    MoveLineDown,
+    Indent,
+    Outdent,
+    JoinLines,
+    ReflowParagraph,
+    InsertDateTime,
+    InsertUuid,
+    InsertNumbers,
+    // Transforms
+    UpperCase,
+    LowerCase,
+    TitleCase,
+    Base64Encode,
+    Base64Decode,
+    UrlEncode,
+    UrlDecode,
+    HtmlEscape,
+    HtmlUnescape,
+    JsonEscape,
+    EvaluateSelection,
+    CopyEvaluation,
+    SortLines,
+    ReverseLines,
+    UniqueLines,
+    TrimTrailingWhitespace,
+    PrettyPrintJson,
+    // Cursors and selection
+    SelectAll,
+    SelectParagraph,
+    SelectNextOccurrence,
+    SelectAllOccurrences,
+    SplitSelectionIntoLines,
+    AddCursorAbove,
+    AddCursorBelow,
+    ClearExtraCursors,
+    SwapSelectionEnds,
+    Reselect,
+    JumpToChar,
+    // Merge conflicts
+    AcceptCurrentChange,
+    AcceptIncomingChange,
+    AcceptBothChanges,
+    NextConflict,
+    PrevConflict,
+    // Bookmarks
+    ToggleBookmark,
+    NextBookmark,
+    PrevBookmark,
+    ClearBookmarks,
+    // Debugging
+    ToggleBreakpoint,
+    DebugContinue,
+    DebugPause,
+    DebugStepOver,
+    DebugStepInto,
+    DebugStepOut,
+    DebugStop,
+    ShowDebug,
+    // View
+    ToggleLineNumbers,
+    ToggleStatusBar,
+    ShowStatistics,
+    ToggleFrameTimes,
+    OpenSettings,
+    SetColorScheme(ColorScheme),
+    // Scripting
+    ToggleScriptConsole,
+    ReloadInitScript,
+    SetSyntaxPalette(SyntaxPalette),
+    ToggleMinContrast,
+    SetLogLevel(LevelFilter),
+    // Palette results and workspace tasks; not in the registry
+    /// Jump to a 1-based line in the active buffer.
+    JumpToLine(usize),
+    /// Open (or switch to) a file and jump to a 1-based line.
+    OpenLocation(PathBuf, usize),
+    /// Open (or switch to) a file without moving its cursor.
+    OpenPath(PathBuf),
+    /// Open (or create) the scratch buffer with this name.
+    OpenScratch(String),
+    /// Replace the word before each cursor with this completion.
+    InsertCompletion(String),
+    /// Put this text on the clipboard, e.g. a palette calculation.
+    CopyText(String),
+    /// Run the workspace task with this name.
+    RunTask(String),
+    /// Start debugging the workspace debug target with this name.
+    StartDebugging(String),
+    /// Apply the fix the palette offered at this index.
+    ApplyCodeAction(usize),
+}
+
+/// Where a command's key bindings are active.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Scope {
+    /// Handled by the app whenever no overlay has the keyboard.
+    Global,
+    /// Handled by the editor view while it has focus.
+    Editor,
+}
+
+/// A key plus modifiers. Modifiers must match exactly, so Ctrl+Z and
+/// Ctrl+Shift+Z can be bound to different commands.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KeyBinding {
+    pub key: Key,
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+}
+
+impl KeyBinding {
+    pub const fn new(key: Key) -> Self {
+        Self {
+            key,
+            ctrl: false,
+            shift: false,
+            alt: false,
+        }
+    }
+
+    pub const fn ctrl(key: Key) -> Self {
+        Self {
+            ctrl: true,
+            ..Self::new(key)
+        }
+    }
+
+    pub const fn shift(key: Key) -> Self {
+        Self {
+            shift: true,
+            ..Self::new(key)
+        }
+    }
+
+    pub const fn ctrl_shift(key: Key) -> Self {
+        Self {
+            shift: true,
+            ..Self::ctrl(key)
+        }
+    }
+
+    pub const fn alt(key: Key) -> Self {
+        Self {
+            alt: true,
+            ..Self::new(key)
+        }
+    }
+
+    pub const fn alt_shift(key: Key) -> Self {
+        Self {
+            shift: true,
+            ..Self::alt(key)
+        }
+    }
+
+    pub const fn ctrl_alt(key: Key) -> Self {
+        Self {
+            alt: true,
+            ..Self::ctrl(key)
+        }
+    }
+
+    /// Parse a shortcut such as `"Ctrl+Shift+D"`, `"Alt+Up"` or `"Ctrl+/"`.
+    /// `Cmd` is accepted as a synonym for `Ctrl`.
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        // A trailing "++" means the plus key itself
+        let (mods, key) = match text.strip_suffix("++") {
+            Some(mods) => (mods, "+"),
+            None => text.rsplit_once('+').unwrap_or(("", text)),
+        };
+        let key = key.trim();
+        let key = Key::from_name(key).or_else(|| Key::from_name(&key.to_uppercase()))?;
+        let mut binding = Self::new(key);
+        for m in mods.split('+').map(str::trim).filter(|m| !m.is_empty()) {
+            match m.to_lowercase().as_str() {
+                "ctrl" | "cmd" | "control" | "command" => binding.ctrl = true,
+                "shift" => binding.shift = true,
+                "alt" | "option" => binding.alt = true,
+                _ => return None,
+            }
+        }
+        Some(binding)
+    }
+
+    pub fn matches(&self, key: Key, modifiers: Modifiers) -> bool {
+        self.key == key
+            && self.ctrl == modifiers.command
+            && self.shift == modifiers.shift
+            && self.alt == modifiers.alt
+    }
+
+    pub fn label(&self) -> String {
+        let mut label = String::new();
+        if self.ctrl {
+            label.push_str("Ctrl+");
+        }
+        if self.alt {
+            label.push_str("Alt+");
+        }
+        if self.shift {
+            label.push_str("Shift+");
+        }
+        label.push_str(key_label(self.key));
+        label
+    }
+}
+
+/// Arrows read better as names than as the arrow glyphs egui uses.
+fn key_label(key: Key) -> &'static str {
+    match key {
+        Key::ArrowUp | Key::ArrowDown | Key::ArrowLeft | Key::ArrowRight => key.name(),
+        _ => key.symbol_or_name(),
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Command {
+    pub id: CommandId,
+    pub name: String,
+    pub scope: Scope,
+    pub bindings: Vec<KeyBinding>,
+    /// Second key of a `Ctrl+K` chord, if the command has one.
+    pub chord: Option<Key>,
+}
+
+impl Command {
+    fn new(id: CommandId, name: impl Into<String>, scope: Scope) -> Self {
+        Self {
+            id,
+            name: name.into(),
+            scope,
+            bindings: Vec::new(),
+            chord: None,
+        }
+    }
+
+    fn bind(mut self, binding: KeyBinding) -> Self {
+        self.bindings.push(binding);
+        self
+    }
+
+    fn chord(mut self, key: Key) -> Self {
+        self.chord = Some(key);
+        self
+    }
+
+    /// Name used for the command in `keymap.toml`, e.g. `DuplicateLine`.
+    pub fn keymap_name(&self) -> String {
+        format!("{:?}", self.id)
+    }
+
+    /// Bindings in effect: the user's keymap override if any, else the defaults.
+    pub fn active_bindings(&self) -> Vec<KeyBinding> {
+        let keymap = KEYMAP.read().ok();
+        match keymap.as_ref().and_then(|k| k.as_ref()?.get(&self.keymap_name())) {
+            Some(bindings) => bindings.clone(),
+            None => self.bindings.clone(),
+        }
+    }
+
+    /// Shortcut text shown next to the command, e.g. "Ctrl+Shift+P".
+    pub fn shortcut_label(&self) -> String {
+        if let Some(key) = self.chord {
+            return format!("Ctrl+K {}", key_label(key));
+        }
+        self.active_bindings().first().map(|b| b.label()).unwrap_or_default()
+    }
+}
+
+/// Every command the editor knows about. The palette lists these and the
+/// keyboard handlers resolve key presses against their bindings.
+pub fn all() -> &'static [Command] {
+    static COMMANDS: OnceLock<Vec<Command>> = OnceLock::new();
+    COMMANDS.get_or_init(build)
+}
+
+/// User overrides from `keymap.toml`, replacing a command's default bindings.
+static KEYMAP: RwLock<Option<Keymap>> = RwLock::new(None);
+
+/// Install the user's keymap overrides.
+pub fn set_keymap(keymap: Keymap) {
+    if let Ok(mut current) = KEYMAP.write() {
+        *current = Some(keymap);
+    }
+}
+
+/// The user's current keymap overrides.
+pub fn keymap() -> Keymap {
+    KEYMAP
+        .read()
+        .ok()
+        .and_then(|k| k.clone())
+        .unwrap_or_default()
+}
+
+/// The command bound to a key press in the given scope.
+pub fn lookup(scope: Scope, key: Key, modifiers: Modifiers) -> Option<CommandId> {
+    all()
+        .iter()
+        .filter(|c| c.scope == scope)
+        .find(|c| c.active_bindings().iter().any(|b| b.matches(key, modifiers)))
+        .map(|c| c.id.clone())
+}
+
+/// The command completing a `Ctrl+K` chord with `key`.
+pub fn lookup_chord(key: Key) -> Option<CommandId> {
+    all().iter().find(|c| c.chord == Some(key)).map(|c| c.id.clone())
+}
+
+fn build() -> Vec<Command> {
+    use CommandId as C;
+    use KeyBinding as K;
+    use Scope::{Editor, Global};
+
+    let mut commands = vec![
+        Command::new(C::NewTab, "New Tab", Global).bind(K::ctrl(Key::N)),
+        Command::new(C::NewScratch, "Scratch: New Scratch Buffer...", Global),
+        Command::new(C::DeleteScratch, "Scratch: Delete Scratch Buffer", Global),
+        Command::new(C::OpenFile, "Open File", Global).bind(K::ctrl(Key::O)),
+        Command::new(C::OpenFolder, "Open Folder...", Global),
+        Command::new(C::CloseFolder, "Close Folder", Global),
+        Command::new(C::OpenContainingFolder, "Open Project of Current File", Global),
+        Command::new(C::SaveFile, "Save File", Global).bind(K::ctrl(Key::S)),
+        Command::new(C::SaveFileAs, "Save File As...", Global).bind(K::ctrl_shift(Key::S)),
+        Command::new(C::SaveFileAsAdmin, "Save as Administrator", Global),
+        Command::new(C::RenameFile, "Rename/Move File...", Global),
+        Command::new(C::ShowLocalHistory, "Local History: Show File History", Global),
+        Command::new(C::ShowFileInfo, "File Info", Global),
+        Command::new(C::RevealInFileManager, reveal::LABEL, Global),
+        Command::new(C::DeleteFile, "Delete File (Move to Trash)", Global),
+        Command::new(C::CopyAbsolutePath, "Copy Absolute Path", Global),
+        Command::new(C::CopyRelativePath, "Copy Relative Path", Global),
+        Command::new(C::CopyFileName, "Copy File Name", Global),
+        Command::new(C::SaveAll, "Save All", Global).chord(Key::S),
+        Command::new(C::CloseTab, "Close Tab", Global).bind(K::ctrl(Key::W)),
+        Command::new(C::CloseAllTabs, "Close All Tabs", Global),
+        Command::new(C::TogglePinTab, "Pin/Unpin Tab", Global),
+        Command::new(C::NextTab, "Next Tab", Global).bind(K::ctrl(Key::Tab)),
+        Command::new(C::PrevTab, "Previous Tab", Global).bind(K::ctrl_shift(Key::Tab)),
+        Command::new(C::ShowCommandPalette, "Show Command Palette", Global)
+            .bind(K::ctrl_shift(Key::P)),
+        Command::new(C::QuickOpen, "Go to File...", Global).bind(K::ctrl(Key::P)),
+        Command::new(C::CompleteWord, "Complete Word", Global).bind(K::ctrl(Key::Space)),
+        Command::new(C::RepeatLastCommand, "Repeat Last Command", Global)
+            .bind(K::ctrl_shift(Key::R)),
+        Command::new(C::Find, "Find", Global).bind(K::ctrl(Key::F)),
+        Command::new(C::Replace, "Find and Replace", Global).bind(K::ctrl(Key::H)),
+        Command::new(C::GoToLine, "Go to Line", Global).bind(K::ctrl(Key::G)),
+        // Asks the file's language server, so it runs at the app level
+        Command::new(C::GoToDefinition, "Go to Definition", Global).bind(K::new(Key::F12)),
+        Command::new(C::ShowCodeActions, "Show Fixes...", Global).bind(K::ctrl(Key::Period)),
+        // Searches the workspace, so it runs at the app level
+        Command::new(C::RenameSymbol, "Rename Symbol...", Global).bind(K::shift(Key::F6)),
+        Command::new(C::RunFile, "Run: Run Current File", Global)
+            .bind(K::ctrl(Key::R))
+            .bind(K::new(Key::F5)),
+        Command::new(C::StopRun, "Run: Stop", Global),
+        Command::new(C::ToggleOutputPanel, "View: Toggle Output Panel", Global),
+        Command::new(C::Undo, "Undo", Editor).bind(K::ctrl(Key::Z)),
+        Command::new(C::Redo, "Redo", Editor)
+            .bind(K::ctrl(Key::Y))
+            .bind(K::ctrl_shift(Key::Z)),
+        Command::new(C::ShowUndoHistory, "Show Undo History", Global),
+        Command::new(C::ShowProblems, "Problems: Toggle Panel", Global).bind(K::ctrl_shift(Key::M)),
+        Command::new(C::ShowTodos, "TODOs: Toggle Panel", Global),
+        Command::new(C::ToggleSourceControl, "Source Control: Toggle Panel", Global)
+            .bind(K::ctrl_shift(Key::G)),
+        Command::new(C::ShowCollaboration, "Collaboration: Host or Join Session...", Global),
+        Command::new(C::FollowCollaborator, "Collaboration: Follow Next Collaborator", Global),
+        Command::new(C::LeaveCollaboration, "Collaboration: Leave Session", Global),
+        // egui turns these into clipboard events; the bindings label them
+        Command::new(C::Copy, "Copy", Editor).bind(K::ctrl(Key::C)),
+        Command::new(C::Cut, "Cut", Editor).bind(K::ctrl(Key::X)),
+        // Needs the highlighter, so it runs at the app level
+        Command::new(C::CopyWithHighlighting, "Copy with Syntax Highlighting", Global),
+        Command::new(C::Paste, "Paste", Editor).bind(K::ctrl(Key::V)),
+        Command::new(C::DeleteWordBackward, "Delete Word Backward", Editor)
+            .bind(K::ctrl(Key::Backspace)),
+        Command::new(C::DeleteWordForward, "Delete Word Forward", Editor)
+            .bind(K::ctrl(Key::Delete)),
+        Command::new(C::DeleteSubwordBackward, "Delete Sub-word Backward", Editor)
+            .bind(K::alt(Key::Backspace)),
+        Command::new(C::DeleteSubwordForward, "Delete Sub-word Forward", Editor)
+            .bind(K::alt(Key::Delete)),
+        Command::new(C::ToggleOverwrite, "Toggle Overwrite Mode", Editor).bind(K::new(Key::Insert)),
+        Command::new(C::DuplicateLine, "Duplicate Line", Editor).bind(K::ctrl_shift(Key::D)),
+        Command::new(C::CopyLineUp, "Copy Line Up", Editor).bind(K::alt_shift(Key::ArrowUp)),
+        Command::new(C::CopyLineDown, "Copy Line Down", Editor).bind(K::alt_shift(Key::ArrowDown)),
+        Command::new(C::DeleteLine, "Delete Line", Editor).bind(K::ctrl_shift(Key::K)),
+        Command::new(C::SelectLine, "Select Line", Editor).bind(K::ctrl(Key::L)),
+        Command::new(C::CenterCursor, "View: Center Cursor", Editor).bind(K::ctrl_alt(Key::L)),
+        Command::new(C::ScrollLineUp, "View: Scroll Line Up", Editor).bind(K::alt(Key::PageUp)),
+        Command::new(C::ScrollLineDown, "View: Scroll Line Down", Editor).bind(K::alt(Key::PageDown)),
+        Command::new(C::CursorToViewTop, "View: Cursor to Top of View", Editor).bind(K::alt(Key::Home)),
+        Command::new(C::CursorToViewBottom, "View: Cursor to Bottom of View", Editor).bind(K::alt(Key::End)),
+        Command::new(C::ToggleComment, "Toggle Line Comment", Editor).bind(K::ctrl(Key::Slash)),
+        Command::new(C::MoveLineUp, "Move Line Up", Editor).bind(K::alt(Key::ArrowUp)),
+        Command::new(C::MoveLineDown, "Move Line Down", Editor).bind(K::alt(Key::ArrowDown)),
+        Command::new(C::Indent, "Indent Line", Editor).bind(K::ctrl(Key::CloseBracket)),
+        Command::new(C::Outdent, "Outdent Line", Editor).bind(K::ctrl(Key::OpenBracket)),
+        Command::new(C::JoinLines, "Join Lines", Editor).bind(K::ctrl(Key::J)),
+        // Wraps at the `text_width` setting, so it runs at the app level
+        Command::new(C::ReflowParagraph, "Reflow Paragraph", Global).bind(K::alt(Key::Q)),
+        // Asks for a format first, so it runs at the app level
+        Command::new(C::InsertDateTime, "Insert Date/Time", Global),
+        Command::new(C::InsertUuid, "Insert UUID", Editor),
+        Command::new(C::InsertNumbers, "Insert Incrementing Numbers", Editor),
+        Command::new(C::UpperCase, "Transform to Uppercase", Editor),
+        Command::new(C::LowerCase, "Transform to Lowercase", Editor),
+        Command::new(C::TitleCase, "Transform to Title Case", Editor),
+        Command::new(C::Base64Encode, "Transform to Base64", Editor),
+        Command::new(C::Base64Decode, "Transform from Base64", Editor),
+        Command::new(C::UrlEncode, "Transform to URL Encoding", Editor),
+        Command::new(C::UrlDecode, "Transform from URL Encoding", Editor),
+        Command::new(C::HtmlEscape, "Transform to HTML Entities", Editor),
+        Command::new(C::HtmlUnescape, "Transform from HTML Entities", Editor),
+        Command::new(C::JsonEscape, "Transform to JSON String", Editor),
+        Command::new(C::EvaluateSelection, "Evaluate Selection", Editor),
+        // Reports the result or the error, so it runs at the app level
+        Command::new(C::CopyEvaluation, "Evaluate Selection and Copy Result", Global),
+        Command::new(C::SortLines, "Sort Lines", Editor),
+        Command::new(C::ReverseLines, "Reverse Lines", Editor),
+        Command::new(C::UniqueLines, "Remove Duplicate Lines", Editor),
+        Command::new(C::TrimTrailingWhitespace, "Trim Trailing Whitespace", Editor),
+        // Reports invalid JSON, so it runs at the app level
+        Command::new(C::PrettyPrintJson, "Pretty Print JSON", Global),
+        Command::new(C::SelectAll, "Select All", Editor).bind(K::ctrl(Key::A)),
+        Command::new(C::SelectParagraph, "Select Paragraph", Editor),
+        Command::new(C::SelectNextOccurrence, "Add Next Occurrence to Selection", Editor)
+            .bind(K::ctrl(Key::D)),
+        Command::new(C::SelectAllOccurrences, "Select All Occurrences", Editor)
+            .bind(K::alt(Key::F3)),
+        Command::new(C::SplitSelectionIntoLines, "Split Selection into Lines", Editor)
+            .bind(K::ctrl_shift(Key::L)),
+        Command::new(C::AddCursorAbove, "Add Cursor Above", Editor).bind(K::ctrl_alt(Key::ArrowUp)),
+        Command::new(C::AddCursorBelow, "Add Cursor Below", Editor)
+            .bind(K::ctrl_alt(Key::ArrowDown)),
+        Command::new(C::ClearExtraCursors, "Clear Extra Cursors", Editor).bind(K::new(Key::Escape)),
+        Command::new(C::SwapSelectionEnds, "Swap Selection Anchor and Cursor", Editor).chord(Key::X),
+        Command::new(C::Reselect, "Restore Last Selection", Editor).chord(Key::U),
+        Command::new(C::JumpToChar, "Jump to Character", Editor).bind(K::ctrl(Key::Semicolon)),
+        Command::new(C::AcceptCurrentChange, "Merge: Accept Current Change", Editor),
+        Command::new(C::AcceptIncomingChange, "Merge: Accept Incoming Change", Editor),
+        Command::new(C::AcceptBothChanges, "Merge: Accept Both Changes", Editor),
+        Command::new(C::NextConflict, "Merge: Next Conflict", Editor).bind(K::alt(Key::F8)),
+        Command::new(C::PrevConflict, "Merge: Previous Conflict", Editor).bind(K::alt_shift(Key::F8)),
+        Command::new(C::ToggleBookmark, "Bookmarks: Toggle Bookmark", Editor).bind(K::ctrl(Key::F2)),
+        Command::new(C::NextBookmark, "Bookmarks: Next Bookmark", Editor).bind(K::new(Key::F2)),
+        Command::new(C::PrevBookmark, "Bookmarks: Previous Bookmark", Editor).bind(K::shift(Key::F2)),
+        Command::new(C::ClearBookmarks, "Bookmarks: Clear Bookmarks", Editor),
+        Command::new(C::ToggleBreakpoint, "Debug: Toggle Breakpoint", Editor).bind(K::new(Key::F9)),
+        Command::new(C::DebugContinue, "Debug: Continue", Global).bind(K::new(Key::F8)),
+        Command::new(C::DebugPause, "Debug: Pause", Global).bind(K::new(Key::F6)),
+        Command::new(C::DebugStepOver, "Debug: Step Over", Global).bind(K::new(Key::F10)),
+        Command::new(C::DebugStepInto, "Debug: Step Into", Global).bind(K::new(Key::F11)),
+        Command::new(C::DebugStepOut, "Debug: Step Out", Global).bind(K::shift(Key::F11)),
+        Command::new(C::DebugStop, "Debug: Stop", Global).bind(K::shift(Key::F5)),
+        Command::new(C::ShowDebug, "Debug: Toggle Panel", Global),
+        Command::new(C::ToggleLineNumbers, "View: Toggle Line Numbers", Global),
+        Command::new(C::ToggleStatusBar, "View: Toggle Status Bar", Global),
+        Command::new(C::ShowStatistics, "Document Statistics", Global),
+        Command::new(C::ToggleFrameTimes, "Developer: Toggle Frame Time Overlay", Global),
+        Command::new(C::OpenSettings, "Preferences: Open Settings", Global).bind(K::ctrl(Key::Comma)),
+        Command::new(C::ToggleScriptConsole, "Scripting: Toggle Console", Global),
+        Command::new(C::ReloadInitScript, "Scripting: Reload Init Script", Global),
+        Command::new(C::ToggleMinContrast, "Accessibility: Toggle Minimum Contrast", Global),
+    ];
+    for scheme in ColorScheme::ALL {
+        commands.push(Command::new(
+            C::SetColorScheme(scheme),
+            format!("Preferences: Color Scheme: {}", scheme.name()),
+            Global,
+        ));
+    }
+    for p in SyntaxPalette::ALL {
+        commands.push(Command::new(
+            C::SetSyntaxPalette(p),
+            format!("Accessibility: Syntax Palette: {}", p.name()),
+            Global,
+        ));
+    }
+    for level in logging::LEVELS {
+        commands.push(Command::new(
+            C::SetLogLevel(level),
+            format!("Developer: Set Log Level: {}", level),
+            Global,
+        ));
+    }
+    commands
+}
@@ -0,0 +1,646 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+
+use eframe::egui::{self, Color32};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::commands::KeyBinding;
+use crate::editor::LineEnding;
+use crate::scripting;
+use crate::syntax::SyntaxPalette;
+use crate::undo;
+
+pub const SETTINGS_FILE: &str = "settings.toml";
+pub const THEME_FILE: &str = "theme.toml";
+pub const KEYMAP_FILE: &str = "keymap.toml";
+
+pub const DEFAULT_FONT_SIZE: f32 = 14.0;
+pub const FONT_SIZE_RANGE: std::ops::RangeInclusive<f32> = 8.0..=32.0;
+pub const UI_SCALE_RANGE: std::ops::RangeInclusive<f32> = 0.5..=3.0;
+pub const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+/// Directory holding the user's `settings.toml`, `theme.toml` and `keymap.toml`.
+pub fn config_dir() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("lux-edit")
+}
+
+/// A config file that could not be read or parsed.
+#[derive(Clone, Debug)]
+pub struct ConfigError {
+    pub path: PathBuf,
+    /// 1-based position of the problem, when the parser reports one.
+    pub location: Option<(usize, usize)>,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path.display())?;
+        if let Some((line, col)) = self.location {
+            write!(f, ":{}:{}", line, col)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+/// Editor preferences from `settings.toml`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    pub font_size: f32,
+    /// Size of the whole interface, on top of the display's own scaling.
+    pub ui_scale: f32,
+    pub tab_width: usize,
+    pub use_tabs: bool,
+    /// Follow the indentation a file already uses instead of the two above.
+    pub detect_indentation: bool,
+    pub line_numbers: bool,
+    pub status_bar: bool,
+    /// Show word, character and line counts in the status bar for every
+    /// file, instead of only a word count for prose.
+    pub status_statistics: bool,
+    /// Pin the enclosing definitions at the top of the editor while scrolling.
+    pub sticky_scroll: bool,
+    /// Highlight the cursor's line only while the editor has keyboard focus.
+    pub active_line_focus_only: bool,
+    /// Draw a `¶` where each line ends.
+    pub show_line_ends: bool,
+    /// Draw a swatch after colors like `#ff8800` or `rgb(0, 128, 255)`.
+    pub color_swatches: bool,
+    /// Columns at which to draw vertical rulers, e.g. `[80, 100, 120]`.
+    pub rulers: Vec<usize>,
+    /// Let the last line scroll up to the top of the editor.
+    pub scroll_past_end: bool,
+    /// Lines of context kept above and below the cursor as it moves.
+    pub scroll_margin: usize,
+    /// Start the line after a comment line with the same comment prefix.
+    pub continue_comments: bool,
+    /// Re-indent pasted lines to match the indentation where they land.
+    pub reindent_paste: bool,
+    /// Tab expands Emmet abbreviations like `ul>li*3` in HTML and CSS files.
+    pub emmet: bool,
+    /// Start built-in language servers, such as rust-analyzer in Cargo
+    /// projects, for the files they cover.
+    pub language_servers: bool,
+    /// Files opened from Go to File or a search open in a preview tab,
+    /// which the next one replaces.
+    pub preview_tabs: bool,
+    /// Colors for the editor and its panels; `system` follows the OS.
+    pub color_scheme: ColorScheme,
+    pub syntax_palette: SyntaxPalette,
+    /// Minimum contrast ratio for syntax colors; absent means off.
+    pub min_contrast: Option<f32>,
+    pub trim_trailing_whitespace_on_save: bool,
+    pub ensure_final_newline: bool,
+    /// Line ending for new, untitled buffers.
+    pub default_line_ending: LineEnding,
+    /// Memory each buffer's undo history may use, in megabytes.
+    pub undo_memory_mb: usize,
+    /// Column Reflow Paragraph wraps text at.
+    pub text_width: usize,
+    /// `strftime`-style format Insert Date/Time starts with.
+    pub date_format: String,
+    /// Command that runs a file, by extension; `%f` is the file's path and
+    /// `%d` its directory.
+    pub run: BTreeMap<String, String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            font_size: DEFAULT_FONT_SIZE,
+            ui_scale: 1.0,
+            tab_width: 4,
+            use_tabs: false,
+            detect_indentation: true,
+            line_numbers: true,
+            status_bar: true,
+            status_statistics: false,
+            sticky_scroll: true,
+            active_line_focus_only: false,
+            show_line_ends: false,
+            color_swatches: true,
+            rulers: Vec::new(),
+            scroll_past_end: false,
+            scroll_margin: 0,
+            continue_comments: true,
+            reindent_paste: true,
+            emmet: true,
+            language_servers: true,
+            preview_tabs: true,
+            color_scheme: ColorScheme::System,
+            syntax_palette: SyntaxPalette::Default,
+            min_contrast: None,
+            trim_trailing_whitespace_on_save: false,
+            ensure_final_newline: false,
+            default_line_ending: LineEnding::Lf,
+            undo_memory_mb: undo::DEFAULT_MEMORY_BUDGET / (1024 * 1024),
+            text_width: 80,
+            date_format: DEFAULT_DATE_FORMAT.to_string(),
+            run: default_run_commands(),
+        }
+    }
+}
+
+fn default_run_commands() -> BTreeMap<String, String> {
+    [
+        ("go", "go run %f"),
+        ("js", "node %f"),
+        ("py", "python %f"),
+        ("rs", "cargo run"),
+        ("sh", "sh %f"),
+    ]
+    .into_iter()
+    .map(|(ext, command)| (ext.to_string(), command.to_string()))
+    .collect()
+}
+
+/// Editor colors from `theme.toml`, written as `"#rrggbb"` or `"#rrggbbaa"`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    #[serde(deserialize_with = "hex_color", serialize_with = "to_hex_color")]
+    pub background: Color32,
+    #[serde(deserialize_with = "hex_color", serialize_with = "to_hex_color")]
+    pub text: Color32,
+    #[serde(deserialize_with = "hex_color", serialize_with = "to_hex_color")]
+    pub cursor: Color32,
+    #[serde(deserialize_with = "hex_color", serialize_with = "to_hex_color")]
+    pub selection: Color32,
+    #[serde(deserialize_with = "hex_color", serialize_with = "to_hex_color")]
+    pub gutter: Color32,
+    #[serde(deserialize_with = "hex_color", serialize_with = "to_hex_color")]
+    pub line_number: Color32,
+    #[serde(deserialize_with = "hex_color", serialize_with = "to_hex_color")]
+    pub line_number_active: Color32,
+    #[serde(deserialize_with = "hex_color", serialize_with = "to_hex_color")]
+    pub active_line: Color32,
+    /// Other occurrences of the word under the cursor.
+    #[serde(deserialize_with = "hex_color", serialize_with = "to_hex_color")]
+    pub word_highlight: Color32,
+    /// Matches of the find bar's query.
+    #[serde(deserialize_with = "hex_color", serialize_with = "to_hex_color")]
+    pub search_match: Color32,
+    /// Placeholders for control, BiDi and zero-width characters.
+    #[serde(deserialize_with = "hex_color", serialize_with = "to_hex_color")]
+    pub control_char: Color32,
+    #[serde(deserialize_with = "hex_color", serialize_with = "to_hex_color")]
+    pub ruler: Color32,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            background: Color32::from_rgb(30, 30, 30),
+            text: Color32::from_rgb(212, 212, 212),
+            cursor: Color32::from_rgb(248, 248, 240),
+            selection: Color32::from_rgba_premultiplied(60, 100, 150, 120),
+            gutter: Color32::from_rgb(37, 37, 37),
+            line_number: Color32::from_rgb(90, 90, 90),
+            line_number_active: Color32::from_rgb(180, 180, 180),
+            active_line: Color32::from_rgb(40, 40, 40),
+            word_highlight: Color32::from_rgba_unmultiplied(255, 255, 255, 28),
+            search_match: Color32::from_rgba_unmultiplied(230, 170, 50, 70),
+            control_char: Color32::from_rgb(230, 110, 90),
+            ruler: Color32::from_rgb(52, 52, 52),
+        }
+    }
+}
+
+impl Theme {
+    /// Editor colors for the light scheme.
+    pub fn light() -> Self {
+        Self {
+            background: Color32::from_rgb(250, 250, 250),
+            text: Color32::from_rgb(36, 36, 36),
+            cursor: Color32::from_rgb(20, 20, 20),
+            selection: Color32::from_rgba_unmultiplied(70, 130, 220, 70),
+            gutter: Color32::from_rgb(240, 240, 240),
+            line_number: Color32::from_rgb(150, 150, 150),
+            line_number_active: Color32::from_rgb(50, 50, 50),
+            active_line: Color32::from_rgb(238, 240, 244),
+            word_highlight: Color32::from_rgba_unmultiplied(0, 0, 0, 22),
+            search_match: Color32::from_rgba_unmultiplied(240, 180, 40, 110),
+            control_char: Color32::from_rgb(200, 60, 40),
+            ruler: Color32::from_rgb(225, 225, 225),
+        }
+    }
+
+    /// Editor colors for the high-contrast scheme: pure black and white,
+    /// with selection and matches strong enough to see at a glance.
+    pub fn high_contrast() -> Self {
+        Self {
+            background: Color32::BLACK,
+            text: Color32::WHITE,
+            cursor: Color32::from_rgb(255, 255, 0),
+            selection: Color32::from_rgb(0, 70, 160),
+            gutter: Color32::BLACK,
+            line_number: Color32::from_rgb(190, 190, 190),
+            line_number_active: Color32::WHITE,
+            active_line: Color32::from_rgb(28, 28, 28),
+            word_highlight: Color32::from_rgba_unmultiplied(255, 255, 255, 50),
+            search_match: Color32::from_rgba_unmultiplied(255, 200, 0, 110),
+            control_char: Color32::from_rgb(255, 120, 90),
+            ruler: Color32::from_rgb(110, 110, 110),
+        }
+    }
+}
+
+/// Which set of colors the editor uses.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ColorScheme {
+    /// Light or dark to match the OS.
+    System,
+    /// The colors from `theme.toml`.
+    Dark,
+    Light,
+    HighContrast,
+}
+
+impl ColorScheme {
+    pub const ALL: [ColorScheme; 4] = [
+        ColorScheme::System,
+        ColorScheme::Dark,
+        ColorScheme::Light,
+        ColorScheme::HighContrast,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ColorScheme::System => "System",
+            ColorScheme::Dark => "Dark",
+            ColorScheme::Light => "Light",
+            ColorScheme::HighContrast => "High Contrast",
+        }
+    }
+
+    /// The scheme to draw with, given the OS preference if it reports one.
+    /// `System` falls back to dark.
+    pub fn resolve(self, system: Option<egui::Theme>) -> ColorScheme {
+        match (self, system) {
+            (ColorScheme::System, Some(egui::Theme::Light)) => ColorScheme::Light,
+            (ColorScheme::System, _) => ColorScheme::Dark,
+            (scheme, _) => scheme,
+        }
+    }
+
+    pub fn is_light(self) -> bool {
+        self == ColorScheme::Light
+    }
+
+    /// egui's widget colors for the scheme.
+    pub fn visuals(self) -> egui::Visuals {
+        match self {
+            ColorScheme::Light => egui::Visuals::light(),
+            ColorScheme::HighContrast => {
+                let mut visuals = egui::Visuals::dark();
+                visuals.override_text_color = Some(Color32::WHITE);
+                visuals.panel_fill = Color32::BLACK;
+                visuals.window_fill = Color32::BLACK;
+                visuals.extreme_bg_color = Color32::BLACK;
+                visuals.faint_bg_color = Color32::from_gray(20);
+                visuals.window_stroke = egui::Stroke::new(1.0, Color32::WHITE);
+                visuals.hyperlink_color = Color32::from_rgb(120, 200, 255);
+                visuals.selection.bg_fill = Color32::from_rgb(0, 70, 160);
+                visuals.selection.stroke = egui::Stroke::new(1.0, Color32::WHITE);
+                for widget in [
+                    &mut visuals.widgets.noninteractive,
+                    &mut visuals.widgets.inactive,
+                    &mut visuals.widgets.hovered,
+                    &mut visuals.widgets.active,
+                    &mut visuals.widgets.open,
+                ] {
+                    widget.fg_stroke.color = Color32::WHITE;
+                }
+                visuals.widgets.noninteractive.bg_stroke = egui::Stroke::new(1.0, Color32::from_gray(160));
+                visuals.widgets.inactive.bg_stroke = egui::Stroke::new(1.0, Color32::from_gray(200));
+                visuals.widgets.hovered.bg_stroke = egui::Stroke::new(1.5, Color32::WHITE);
+                visuals.widgets.active.bg_stroke = egui::Stroke::new(2.0, Color32::from_rgb(255, 255, 0));
+                visuals
+            }
+            _ => egui::Visuals::dark(),
+        }
+    }
+
+    /// Colors for the tab bar, find bars and command palette.
+    pub fn chrome(self) -> Chrome {
+        match self {
+            ColorScheme::Light => Chrome {
+                panel: Color32::from_gray(236),
+                popup: Color32::from_gray(248),
+                border: Color32::from_gray(200),
+                text: Color32::from_gray(20),
+                label: Color32::from_gray(60),
+                weak: Color32::from_gray(110),
+                selected: Color32::from_rgb(210, 222, 244),
+                accent: Color32::from_rgb(30, 100, 180),
+            },
+            ColorScheme::HighContrast => Chrome {
+                panel: Color32::BLACK,
+                popup: Color32::BLACK,
+                border: Color32::WHITE,
+                text: Color32::WHITE,
+                label: Color32::WHITE,
+                weak: Color32::from_gray(200),
+                selected: Color32::from_rgb(0, 70, 160),
+                accent: Color32::from_rgb(255, 255, 0),
+            },
+            _ => Chrome::default(),
+        }
+    }
+}
+
+/// Colors for the parts of the window drawn by hand rather than by egui
+/// widgets.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Chrome {
+    /// Behind the tab bar and the editor area.
+    pub panel: Color32,
+    /// The command palette's background.
+    pub popup: Color32,
+    pub border: Color32,
+    /// Input text and the active tab's title.
+    pub text: Color32,
+    /// Labels in the find and go to line bars.
+    pub label: Color32,
+    /// Inactive tabs, shortcuts and other secondary text.
+    pub weak: Color32,
+    /// The highlighted palette entry.
+    pub selected: Color32,
+    pub accent: Color32,
+}
+
+impl Default for Chrome {
+    fn default() -> Self {
+        Self {
+            panel: Color32::from_gray(45),
+            popup: Color32::from_gray(40),
+            border: Color32::from_gray(70),
+            text: Color32::WHITE,
+            label: Color32::from_gray(200),
+            weak: Color32::from_gray(140),
+            selected: Color32::from_rgb(55, 55, 75),
+            accent: Color32::from_rgb(100, 140, 180),
+        }
+    }
+}
+
+fn hex_color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color32, D::Error> {
+    let text = String::deserialize(deserializer)?;
+    parse_hex_color(&text).ok_or_else(|| {
+        serde::de::Error::custom(format!("invalid color {:?}, expected \"#rrggbb\" or \"#rrggbbaa\"", text))
+    })
+}
+
+fn to_hex_color<S: Serializer>(color: &Color32, serializer: S) -> Result<S::Ok, S::Error> {
+    let [r, g, b, a] = color.to_srgba_unmultiplied();
+    let hex = if a == 255 {
+        format!("#{:02x}{:02x}{:02x}", r, g, b)
+    } else {
+        format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a)
+    };
+    serializer.serialize_str(&hex)
+}
+
+fn parse_hex_color(text: &str) -> Option<Color32> {
+    let hex = text.strip_prefix('#')?;
+    let byte = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    match hex.len() {
+        6 => Some(Color32::from_rgb(byte(0)?, byte(2)?, byte(4)?)),
+        8 => Some(Color32::from_rgba_unmultiplied(byte(0)?, byte(2)?, byte(4)?, byte(6)?)),
+        _ => None,
+    }
+}
+
+/// Key binding overrides from `keymap.toml`: command name to one or more
+/// shortcuts, e.g. `DuplicateLine = "Ctrl+Shift+D"`. An empty list unbinds.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct KeymapFile {
+    #[serde(default)]
+    bindings: HashMap<String, OneOrMany>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+enum OneOrMany {
+    One(String),
+    Many(Vec<String>),
+}
+
+pub type Keymap = HashMap<String, Vec<KeyBinding>>;
+
+fn read_toml<T: for<'de> Deserialize<'de> + Default>(path: &Path) -> Result<T, ConfigError> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(T::default()),
+        Err(e) => {
+            return Err(ConfigError {
+                path: path.to_path_buf(),
+                location: None,
+                message: e.to_string(),
+            })
+        }
+    };
+    toml::from_str(&text).map_err(|e| ConfigError {
+        path: path.to_path_buf(),
+        location: e.span().map(|span| line_col(&text, span.start)),
+        message: e.message().to_string(),
+    })
+}
+
+/// 1-based line and column of a byte offset.
+fn line_col(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset.min(text.len())];
+    let line = before.matches('\n').count() + 1;
+    let col = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+    (line, col)
+}
+
+pub fn load_settings() -> Result<Settings, ConfigError> {
+    read_toml(&config_dir().join(SETTINGS_FILE))
+}
+
+pub fn load_theme() -> Result<Theme, ConfigError> {
+    read_toml(&config_dir().join(THEME_FILE))
+}
+
+pub fn load_keymap() -> Result<Keymap, ConfigError> {
+    let path = config_dir().join(KEYMAP_FILE);
+    let file: KeymapFile = read_toml(&path)?;
+    let mut keymap = Keymap::new();
+    for (command, keys) in file.bindings {
+        let keys = match keys {
+            OneOrMany::One(k) if k.is_empty() => Vec::new(),
+            OneOrMany::One(k) => vec![k],
+            OneOrMany::Many(ks) => ks,
+        };
+        let mut bindings = Vec::new();
+        for key in keys {
+            let binding = KeyBinding::parse(&key).ok_or_else(|| ConfigError {
+                path: path.clone(),
+                location: None,
+                message: format!("invalid shortcut {:?} for {}", key, command),
+            })?;
+            bindings.push(binding);
+        }
+        keymap.insert(command, bindings);
+    }
+    Ok(keymap)
+}
+
+/// Write `value` to the config file `file`, editing what is already there
+/// so comments, formatting and keys this version doesn't know survive. Only
+/// the values that differ are replaced. Top-level keys `value` leaves out
+/// are kept unless listed in `optional_keys`, the ones it omits when unset.
+/// Returns whether the file changed.
+fn write_toml(file: &str, value: &impl Serialize, optional_keys: &[&str]) -> std::io::Result<bool> {
+    let text = toml::to_string_pretty(value)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    let dir = config_dir();
+    let path = dir.join(file);
+    let old_text = match fs::read_to_string(&path) {
+        Ok(text) => Some(text),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e),
+    };
+    let new_text = match old_text.as_deref().map(str::parse::<toml_edit::DocumentMut>) {
+        Some(Ok(mut doc)) => {
+            let new: toml_edit::DocumentMut = text
+                .parse()
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+            doc.retain(|key, _| new.contains_key(key) || !optional_keys.contains(&key));
+            merge_table(doc.as_table_mut(), new.as_table());
+            doc.to_string()
+        }
+        // Nothing there yet, or nothing that can be kept
+        _ => text,
+    };
+    if old_text.as_deref() == Some(new_text.as_str()) {
+        return Ok(false);
+    }
+    fs::create_dir_all(&dir)?;
+    fs::write(path, new_text)?;
+    Ok(true)
+}
+
+/// Make `old` hold what `new` does. Values that are already equal keep
+/// their formatting; tables nested in the file are replaced wholesale in
+/// what they hold, so entries removed from them go.
+fn merge_table(old: &mut dyn toml_edit::TableLike, new: &dyn toml_edit::TableLike) {
+    for (key, item) in new.iter() {
+        match old.get_mut(key) {
+            Some(existing) => merge_item(existing, item),
+            None => {
+                old.insert(key, item.clone());
+            }
+        }
+    }
+}
+
+fn merge_item(old: &mut toml_edit::Item, new: &toml_edit::Item) {
+    if let (Some(old_table), Some(new_table)) = (old.as_table_like_mut(), new.as_table_like()) {
+        let removed: Vec<String> = old_table
+            .iter()
+            .map(|(key, _)| key.to_string())
+            .filter(|key| !new_table.contains_key(key))
+            .collect();
+        for key in removed {
+            old_table.remove(&key);
+        }
+        merge_table(old_table, new_table);
+        return;
+    }
+    match (old.as_value_mut(), new.as_value()) {
+        (Some(old_value), Some(new_value)) => {
+            if value_of(old_value) != value_of(new_value) {
+                // Keep the spacing and any comment after the value
+                let decor = old_value.decor().clone();
+                *old_value = new_value.clone();
+                *old_value.decor_mut() = decor;
+            }
+        }
+        _ => *old = new.clone(),
+    }
+}
+
+/// What a TOML value means, apart from how it is written.
+fn value_of(value: &toml_edit::Value) -> Option<toml::Value> {
+    let mut bare = value.clone();
+    bare.decor_mut().clear();
+    toml::from_str::<toml::Table>(&format!("v = {}", bare)).ok()?.remove("v")
+}
+
+/// Write settings over those in `settings.toml`. Returns whether the file
+/// changed.
+pub fn save_settings(settings: &Settings) -> std::io::Result<bool> {
+    write_toml(SETTINGS_FILE, settings, &["min_contrast"])
+}
+
+pub fn save_theme(theme: &Theme) -> std::io::Result<bool> {
+    write_toml(THEME_FILE, theme, &[])
+}
+
+/// Write keymap overrides; commands without an entry keep their defaults.
+pub fn save_keymap(keymap: &Keymap) -> std::io::Result<bool> {
+    #[derive(Serialize)]
+    struct Out {
+        bindings: std::collections::BTreeMap<String, Vec<String>>,
+    }
+    let bindings = keymap
+        .iter()
+        .map(|(command, keys)| (command.clone(), keys.iter().map(|k| k.label()).collect()))
+        .collect();
+    write_toml(KEYMAP_FILE, &Out { bindings }, &[])
+}
+
+/// Watches the config directory and reports which config files changed.
+pub struct ConfigWatcher {
+    rx: Receiver<PathBuf>,
+    _watcher: RecommendedWatcher,
+}
+
+impl ConfigWatcher {
+    /// Start watching. `ctx` is woken on changes so edits apply even while idle.
+    pub fn start(ctx: egui::Context) -> Option<Self> {
+        let dir = config_dir();
+        fs::create_dir_all(&dir).ok()?;
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            if let Ok(event) = event {
+                for path in event.paths {
+                    let _ = tx.send(path);
+                }
+                ctx.request_repaint();
+            }
+        })
+        .ok()?;
+        // Editors often save by replacing the file, so watch the directory
+        watcher.watch(&dir, RecursiveMode::NonRecursive).ok()?;
+        Some(Self {
+            rx,
+            _watcher: watcher,
+        })
+    }
+
+    /// Names of config files changed since the last call, without duplicates.
+    pub fn changed(&self) -> Vec<&'static str> {
+        let mut changed = Vec::new();
+        while let Ok(path) = self.rx.try_recv() {
+            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            for file in [SETTINGS_FILE, THEME_FILE, KEYMAP_FILE, scripting::INIT_SCRIPT] {
+                if name == file && !changed.contains(&file) {
+                    changed.push(file);
+                }
+            }
+        }
+        changed
+    }
+}
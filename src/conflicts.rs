@@ -0,0 +1,138 @@
+use ropey::Rope;
+
+/// Command-line flag that opens a file to resolve its conflicts, e.g. as a
+/// git mergetool.
+pub const MERGE_FLAG: &str = "--merge";
+
+/// A merge conflict left in a file by git, as the line numbers of its
+/// markers:
+///
+/// ```text
+/// <<<<<<< HEAD          start
+/// current side
+/// ||||||| base          base (diff3 style only)
+/// common ancestor
+/// =======               separator
+/// incoming side
+/// >>>>>>> branch        end
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Conflict {
+    pub start: usize,
+    pub base: Option<usize>,
+    pub separator: usize,
+    pub end: usize,
+}
+
+/// Which side of a conflict to keep.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Resolution {
+    Current,
+    Incoming,
+    Both,
+}
+
+impl Resolution {
+    pub const ALL: [Resolution; 3] = [Resolution::Current, Resolution::Incoming, Resolution::Both];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Resolution::Current => "Accept Current",
+            Resolution::Incoming => "Accept Incoming",
+            Resolution::Both => "Accept Both",
+        }
+    }
+}
+
+impl Conflict {
+    pub fn contains(&self, line: usize) -> bool {
+        self.start <= line && line <= self.end
+    }
+
+    /// Lines of our side, between the start marker and the base or separator.
+    pub fn current(&self) -> std::ops::Range<usize> {
+        self.start + 1..self.base.unwrap_or(self.separator)
+    }
+
+    pub fn incoming(&self) -> std::ops::Range<usize> {
+        self.separator + 1..self.end
+    }
+
+    /// The text that replaces the whole block, markers included, when it is
+    /// resolved with `resolution`.
+    pub fn resolved_text(&self, rope: &Rope, resolution: Resolution) -> String {
+        let lines = |range: std::ops::Range<usize>| -> String { range.map(|l| rope.line(l).to_string()).collect() };
+        let mut text = match resolution {
+            Resolution::Current => lines(self.current()),
+            Resolution::Incoming => lines(self.incoming()),
+            Resolution::Both => format!("{}{}", lines(self.current()), lines(self.incoming())),
+        };
+        // The block ended the file without a line break; so does the result
+        if rope.line(self.end).chars().last() != Some('\n') && text.ends_with('\n') {
+            text.pop();
+            if text.ends_with('\r') {
+                text.pop();
+            }
+        }
+        text
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Marker {
+    Start,
+    Base,
+    Separator,
+    End,
+}
+
+/// The conflict marker a line starts with: seven marker characters followed
+/// by the end of the line or a space.
+fn marker(rope: &Rope, line: usize) -> Option<Marker> {
+    let line = rope.line(line);
+    let first = line.get_char(0)?;
+    let kind = match first {
+        '<' => Marker::Start,
+        '|' => Marker::Base,
+        '=' => Marker::Separator,
+        '>' => Marker::End,
+        _ => return None,
+    };
+    let run = line.chars().take_while(|&c| c == first).count();
+    let after = line.get_char(run);
+    let ends = after.is_none_or(|c| c == ' ' || c == '\n' || c == '\r');
+    (run == 7 && ends).then_some(kind)
+}
+
+/// Every complete conflict block in `rope`, top to bottom. Stray or
+/// unbalanced markers are ignored.
+pub fn find(rope: &Rope) -> Vec<Conflict> {
+    let mut found = Vec::new();
+    let mut start = None;
+    let mut base = None;
+    let mut separator = None;
+    for line in 0..rope.len_lines() {
+        match (marker(rope, line), start, separator) {
+            (Some(Marker::Start), _, _) => {
+                start = Some(line);
+                base = None;
+                separator = None;
+            }
+            (Some(Marker::Base), Some(_), None) if base.is_none() => base = Some(line),
+            (Some(Marker::Separator), Some(_), None) => separator = Some(line),
+            (Some(Marker::End), Some(start_line), Some(separator_line)) => {
+                found.push(Conflict {
+                    start: start_line,
+                    base,
+                    separator: separator_line,
+                    end: line,
+                });
+                start = None;
+                base = None;
+                separator = None;
+            }
+            _ => {}
+        }
+    }
+    found
+}
@@ -0,0 +1,237 @@
+use ropey::Rope;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use std::thread::ThreadId;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::editor::Editor;
+
+/// Command-line flag used when relaunching after a crash.
+pub const RECOVER_FLAG: &str = "--recover";
+
+/// Cheap copy of a buffer's state; `Rope` clones share their chunks.
+struct BufferSnapshot {
+    path: Option<PathBuf>,
+    rope: Rope,
+    modified: bool,
+    line: usize,
+    col: usize,
+}
+
+/// A buffer read back from the recovery directory.
+pub struct RecoveredBuffer {
+    pub path: Option<PathBuf>,
+    pub text: String,
+    pub modified: bool,
+    pub line: usize,
+    pub col: usize,
+}
+
+static SNAPSHOT: Mutex<Vec<BufferSnapshot>> = Mutex::new(Vec::new());
+
+/// The thread that installed the panic hook and runs the UI.
+static MAIN_THREAD: OnceLock<ThreadId> = OnceLock::new();
+
+/// Per-user data directory for recovery files, crash reports and history.
+pub fn app_data_dir() -> PathBuf {
+    dirs::data_local_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("lux-edit")
+}
+
+fn recovery_dir() -> PathBuf {
+    app_data_dir().join("recovery")
+}
+
+fn crash_dir() -> PathBuf {
+    app_data_dir().join("crashes")
+}
+
+/// Record the current buffers so the panic hook can save them.
+pub fn update_snapshot(editors: &[Editor]) {
+    let Ok(mut snapshot) = SNAPSHOT.lock() else {
+        return;
+    };
+    snapshot.clear();
+    // Encrypted buffers would be recovered in plain text; they are left out
+    snapshot.extend(editors.iter().filter(|e| e.encryption.is_none()).map(|e| BufferSnapshot {
+        path: e.file_path.clone(),
+        rope: e.rope.clone(),
+        modified: e.modified,
+        line: e.cursors[0].pos.line,
+        col: e.cursors[0].pos.col,
+    }));
+}
+
+/// Install a panic hook that saves open buffers and a crash report to the
+/// local data directory (nothing is sent anywhere), then offers a restart.
+/// The default hook still runs so the panic is printed as usual.
+///
+/// Must be called on the main thread. Panics on other threads only unwind
+/// that thread, with the editor still running; they are logged, and left
+/// for whoever owns the thread to report.
+pub fn install_panic_hook() {
+    let _ = MAIN_THREAD.set(std::thread::current().id());
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if MAIN_THREAD.get() != Some(&std::thread::current().id()) {
+            tracing::error!(
+                panic = %info,
+                thread = std::thread::current().name().unwrap_or("<unnamed>"),
+                "background thread panicked"
+            );
+            default_hook(info);
+            return;
+        }
+        let report = write_crash_report(info);
+        let recovered = write_recovery();
+        tracing::error!(
+            panic = %info,
+            report = ?report,
+            recovered,
+            "editor panicked"
+        );
+        default_hook(info);
+
+        if recovered {
+            offer_restart(report);
+        }
+    }));
+}
+
+fn write_crash_report(info: &std::panic::PanicHookInfo<'_>) -> Option<PathBuf> {
+    let dir = crash_dir();
+    fs::create_dir_all(&dir).ok()?;
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let path = dir.join(format!("crash-{}.txt", secs));
+
+    let message = panic_message(info.payload());
+    let location = info
+        .location()
+        .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()))
+        .unwrap_or_else(|| "<unknown>".into());
+    let thread = std::thread::current();
+
+    let mut file = fs::File::create(&path).ok()?;
+    let _ = writeln!(file, "Lux Editor {} crash report", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(file, "Time: {} (unix)", secs);
+    let _ = writeln!(file, "Platform: {} {}", std::env::consts::OS, std::env::consts::ARCH);
+    let _ = writeln!(file, "Thread: {}", thread.name().unwrap_or("<unnamed>"));
+    let _ = writeln!(file, "Panic: {}", message);
+    let _ = writeln!(file, "Location: {}", location);
+    let _ = writeln!(file, "\nBacktrace:\n{}", std::backtrace::Backtrace::force_capture());
+    Some(path)
+}
+
+/// The message a panic was raised with.
+pub fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "<non-string panic payload>".into())
+}
+
+/// Write every buffer to the recovery directory. Returns true if anything
+/// worth restoring was saved.
+fn write_recovery() -> bool {
+    // The panic may have happened while the snapshot was locked
+    let snapshot = match SNAPSHOT.try_lock() {
+        Ok(s) => s,
+        Err(std::sync::TryLockError::Poisoned(p)) => p.into_inner(),
+        Err(std::sync::TryLockError::WouldBlock) => return false,
+    };
+    if !snapshot.iter().any(|b| b.modified) {
+        return false;
+    }
+
+    let dir = recovery_dir();
+    let _ = fs::remove_dir_all(&dir);
+    if fs::create_dir_all(&dir).is_err() {
+        return false;
+    }
+
+    // session: one buffer per line, "index\tmodified\tline\tcol\tpath"
+    let mut session = String::new();
+    for (i, buf) in snapshot.iter().enumerate() {
+        let Ok(file) = fs::File::create(dir.join(format!("buffer-{}.txt", i))) else {
+            continue;
+        };
+        if buf.rope.write_to(std::io::BufWriter::new(file)).is_err() {
+            continue;
+        }
+        let path = buf
+            .path
+            .as_ref()
+            .map(|p| p.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        session.push_str(&format!(
+            "{}\t{}\t{}\t{}\t{}\n",
+            i, buf.modified as u8, buf.line, buf.col, path
+        ));
+    }
+    fs::write(dir.join("session"), session).is_ok()
+}
+
+fn offer_restart(report: Option<PathBuf>) {
+    let mut description = String::from(
+        "Lux Editor crashed. Your unsaved buffers were saved for recovery.\n\n\
+         Restart now and restore the session?",
+    );
+    if let Some(report) = report {
+        description.push_str(&format!("\n\nCrash report: {}", report.display()));
+    }
+    let answer = rfd::MessageDialog::new()
+        .set_level(rfd::MessageLevel::Error)
+        .set_title("Lux Editor crashed")
+        .set_description(description)
+        .set_buttons(rfd::MessageButtons::YesNo)
+        .show();
+    if answer == rfd::MessageDialogResult::Yes {
+        if let Ok(exe) = std::env::current_exe() {
+            let _ = std::process::Command::new(exe).arg(RECOVER_FLAG).spawn();
+        }
+    }
+}
+
+/// True if a previous crash left buffers behind.
+pub fn has_recovery() -> bool {
+    recovery_dir().join("session").exists()
+}
+
+/// Read back the buffers saved by the panic hook.
+pub fn load_recovery() -> Vec<RecoveredBuffer> {
+    let dir = recovery_dir();
+    let Ok(session) = fs::read_to_string(dir.join("session")) else {
+        return Vec::new();
+    };
+    session
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(5, '\t');
+            let index = fields.next()?;
+            let modified = fields.next()? == "1";
+            let line_no = fields.next()?.parse().ok()?;
+            let col = fields.next()?.parse().ok()?;
+            let path = fields.next().filter(|p| !p.is_empty()).map(PathBuf::from);
+            let text = fs::read_to_string(dir.join(format!("buffer-{}.txt", index))).ok()?;
+            Some(RecoveredBuffer {
+                path,
+                text,
+                modified,
+                line: line_no,
+                col,
+            })
+        })
+        .collect()
+}
+
+/// Remove recovery data once it has been restored or declined.
+pub fn clear_recovery() {
+    let _ = fs::remove_dir_all(recovery_dir());
+}
@@ -0,0 +1,481 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Stdio};
+use std::sync::mpsc::{self, Receiver};
+
+use eframe::egui;
+use serde_json::{json, Value};
+
+use crate::base_protocol::{self, Incoming};
+use crate::workspace::{shell, DebugRequest, DebugTarget};
+
+/// Lines of program output kept; older ones scroll away.
+const MAX_OUTPUT_LINES: usize = 2_000;
+
+/// What the debugged program is doing.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DebugState {
+    /// The adapter is starting the program or attaching to it.
+    Starting,
+    Running,
+    /// Stopped in a thread, at a breakpoint, after a step or on request.
+    Stopped { thread: i64, reason: String },
+    /// The session is over, with why.
+    Ended(String),
+}
+
+/// One frame of the stopped thread's call stack. Line and column are 0-based.
+#[derive(Clone, Debug)]
+pub struct StackFrame {
+    pub id: i64,
+    pub name: String,
+    pub path: Option<PathBuf>,
+    pub line: usize,
+    pub col: usize,
+}
+
+/// A group of variables in a frame, such as its locals.
+#[derive(Clone, Debug)]
+pub struct Scope {
+    pub name: String,
+    pub reference: i64,
+}
+
+/// A variable, with its value as the adapter formats it. A non-zero
+/// `reference` means it has children, such as the fields of a struct.
+#[derive(Clone, Debug)]
+pub struct Variable {
+    pub name: String,
+    pub value: String,
+    pub type_name: Option<String>,
+    pub reference: i64,
+}
+
+/// What a request was for, to know what to do with its response.
+enum Pending {
+    Initialize,
+    /// The launch or attach request.
+    Start,
+    ConfigurationDone,
+    Threads,
+    StackTrace,
+    Scopes,
+    Variables(i64),
+    Other,
+}
+
+/// A debug session: a debug adapter started for a [`DebugTarget`], spoken
+/// to over the Debug Adapter Protocol on its standard input and output.
+/// Messages are read on a background thread and handled in [`Self::poll`].
+pub struct DebugSession {
+    pub name: String,
+    pub state: DebugState,
+    /// Call stack of the stopped thread, innermost frame first.
+    pub frames: Vec<StackFrame>,
+    pub selected_frame: usize,
+    /// Scopes of the selected frame.
+    pub scopes: Vec<Scope>,
+    /// Children by reference, for the scopes and the variables expanded.
+    pub variables: HashMap<i64, Vec<Variable>>,
+    /// Output of the program and messages from the adapter.
+    pub output: VecDeque<String>,
+    /// A file and 0-based line the app should show, after the program
+    /// stopped or a frame was selected.
+    pub reveal: Option<(PathBuf, usize)>,
+    target: DebugTarget,
+    child: Child,
+    stdin: ChildStdin,
+    events: Receiver<Incoming>,
+    seq: i64,
+    pending: HashMap<i64, Pending>,
+    /// 0-based breakpoint lines by file.
+    breakpoints: BTreeMap<PathBuf, Vec<usize>>,
+    /// The adapter has been given the breakpoints, so changes to them are
+    /// sent as they happen.
+    configured: bool,
+    /// The thread to pause: the last one that stopped, or the first listed.
+    thread: Option<i64>,
+}
+
+impl DebugSession {
+    /// Start the target's adapter in `root` (or the target's `cwd` under
+    /// it) and ask it to launch or attach, with `breakpoints` set before
+    /// the program runs. Every message wakes `ctx`.
+    pub fn start(
+        target: DebugTarget,
+        root: &Path,
+        breakpoints: BTreeMap<PathBuf, Vec<usize>>,
+        ctx: egui::Context,
+    ) -> io::Result<Self> {
+        let cwd = match &target.cwd {
+            Some(dir) => root.join(dir),
+            None => root.to_path_buf(),
+        };
+        let mut child = shell(&target.adapter)
+            .current_dir(&cwd)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let (Some(stdin), Some(stdout), Some(stderr)) = (child.stdin.take(), child.stdout.take(), child.stderr.take())
+        else {
+            let _ = child.kill();
+            return Err(io::Error::other("the debug adapter has no standard streams"));
+        };
+        tracing::info!(target = %target.name, adapter = %target.adapter, cwd = %cwd.display(), "started debug adapter");
+
+        let (tx, events) = mpsc::channel();
+        base_protocol::read_messages(stdout, tx.clone(), ctx.clone());
+        base_protocol::read_stderr(stderr, tx, ctx);
+        let mut session = Self {
+            name: target.name.clone(),
+            state: DebugState::Starting,
+            frames: Vec::new(),
+            selected_frame: 0,
+            scopes: Vec::new(),
+            variables: HashMap::new(),
+            output: VecDeque::new(),
+            reveal: None,
+            target,
+            child,
+            stdin,
+            events,
+            seq: 0,
+            pending: HashMap::new(),
+            breakpoints,
+            configured: false,
+            thread: None,
+        };
+        session.request(
+            Pending::Initialize,
+            "initialize",
+            json!({
+                "clientID": "lux-edit",
+                "clientName": "Lux Editor",
+                "adapterID": session.name,
+                "pathFormat": "path",
+                "linesStartAt1": true,
+                "columnsStartAt1": true,
+                "supportsVariableType": true,
+            }),
+        );
+        Ok(session)
+    }
+
+    pub fn is_ended(&self) -> bool {
+        matches!(self.state, DebugState::Ended(_))
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        matches!(self.state, DebugState::Stopped { .. })
+    }
+
+    /// The file and 0-based line of the selected frame while stopped.
+    pub fn execution_line(&self) -> Option<(&Path, usize)> {
+        if !self.is_stopped() {
+            return None;
+        }
+        let frame = self.frames.get(self.selected_frame)?;
+        Some((frame.path.as_deref()?, frame.line))
+    }
+
+    /// Handle the messages that arrived since the last call.
+    pub fn poll(&mut self) {
+        let incoming: Vec<Incoming> = self.events.try_iter().collect();
+        for item in incoming {
+            match item {
+                Incoming::Message(message) => self.handle(message),
+                Incoming::Stderr(line) => self.push_output(line),
+                Incoming::Closed => {
+                    if !self.is_ended() {
+                        self.end("the debug adapter exited");
+                    }
+                }
+            }
+        }
+    }
+
+    /// Replace a file's breakpoints, sending them to the adapter once it
+    /// has been configured.
+    pub fn set_breakpoints(&mut self, file: &Path, lines: Vec<usize>) {
+        if self.breakpoints.get(file) == Some(&lines) {
+            return;
+        }
+        self.breakpoints.insert(file.to_path_buf(), lines);
+        if self.configured && !self.is_ended() {
+            self.send_breakpoints(file);
+        }
+    }
+
+    pub fn resume(&mut self) {
+        self.step("continue");
+    }
+
+    pub fn step_over(&mut self) {
+        self.step("next");
+    }
+
+    pub fn step_into(&mut self) {
+        self.step("stepIn");
+    }
+
+    pub fn step_out(&mut self) {
+        self.step("stepOut");
+    }
+
+    pub fn pause(&mut self) {
+        if self.state != DebugState::Running {
+            return;
+        }
+        if let Some(thread) = self.thread {
+            self.request(Pending::Other, "pause", json!({ "threadId": thread }));
+        }
+    }
+
+    /// End the session, terminating a launched program.
+    pub fn stop(&mut self) {
+        if self.is_ended() {
+            return;
+        }
+        let terminate = self.target.request == DebugRequest::Launch;
+        self.request(Pending::Other, "disconnect", json!({ "terminateDebuggee": terminate }));
+        self.end("stopped");
+    }
+
+    /// Show a frame's location and variables.
+    pub fn select_frame(&mut self, index: usize) {
+        let Some(frame) = self.frames.get(index) else {
+            return;
+        };
+        self.selected_frame = index;
+        self.reveal = frame.path.clone().map(|path| (path, frame.line));
+        let id = frame.id;
+        self.scopes.clear();
+        self.variables.clear();
+        self.request(Pending::Scopes, "scopes", json!({ "frameId": id }));
+    }
+
+    /// Fetch the children of a scope or variable unless they are already
+    /// known or on their way.
+    pub fn expand(&mut self, reference: i64) {
+        let requested = self.pending.values().any(|p| matches!(p, Pending::Variables(r) if *r == reference));
+        if reference > 0 && !requested && !self.variables.contains_key(&reference) {
+            self.request(Pending::Variables(reference), "variables", json!({ "variablesReference": reference }));
+        }
+    }
+
+    /// Run the stopped thread on with `command`.
+    fn step(&mut self, command: &str) {
+        let DebugState::Stopped { thread, .. } = self.state else {
+            return;
+        };
+        self.request(Pending::Other, command, json!({ "threadId": thread }));
+        self.state = DebugState::Running;
+        self.clear_stack();
+    }
+
+    fn end(&mut self, reason: &str) {
+        tracing::info!(target = %self.name, reason, "debug session ended");
+        self.state = DebugState::Ended(reason.to_string());
+        self.configured = false;
+        self.clear_stack();
+    }
+
+    fn clear_stack(&mut self) {
+        self.frames.clear();
+        self.selected_frame = 0;
+        self.scopes.clear();
+        self.variables.clear();
+    }
+
+    fn push_output(&mut self, line: String) {
+        if self.output.len() == MAX_OUTPUT_LINES {
+            self.output.pop_front();
+        }
+        self.output.push_back(line);
+    }
+
+    fn send_breakpoints(&mut self, file: &Path) {
+        let lines = self.breakpoints.get(file).cloned().unwrap_or_default();
+        let breakpoints: Vec<Value> = lines.iter().map(|line| json!({ "line": line + 1 })).collect();
+        let name = file.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        self.request(
+            Pending::Other,
+            "setBreakpoints",
+            json!({ "source": { "name": name, "path": file }, "breakpoints": breakpoints }),
+        );
+    }
+
+    fn request(&mut self, pending: Pending, command: &str, arguments: Value) {
+        self.seq += 1;
+        self.pending.insert(self.seq, pending);
+        self.send(json!({ "seq": self.seq, "type": "request", "command": command, "arguments": arguments }));
+    }
+
+    fn send(&mut self, message: Value) {
+        if let Err(e) = base_protocol::write_message(&mut self.stdin, &message) {
+            tracing::warn!(target = %self.name, error = %e, "failed to write to debug adapter");
+        }
+    }
+
+    fn handle(&mut self, message: Value) {
+        match message["type"].as_str() {
+            Some("response") => {
+                let pending = message["request_seq"].as_i64().and_then(|seq| self.pending.remove(&seq));
+                let Some(pending) = pending else {
+                    return;
+                };
+                if message["success"].as_bool() != Some(true) {
+                    let error = message["body"]["error"]["format"]
+                        .as_str()
+                        .or(message["message"].as_str())
+                        .unwrap_or("failed");
+                    let command = message["command"].as_str().unwrap_or("request");
+                    self.push_output(format!("{}: {}", command, error));
+                    if matches!(pending, Pending::Initialize | Pending::Start) {
+                        self.end(error);
+                    }
+                    return;
+                }
+                self.responded(pending, &message["body"]);
+            }
+            Some("event") => self.event(message["event"].as_str().unwrap_or_default(), &message["body"]),
+            Some("request") => {
+                // Reverse requests such as runInTerminal aren't supported
+                self.seq += 1;
+                self.send(json!({
+                    "seq": self.seq,
+                    "type": "response",
+                    "request_seq": message["seq"],
+                    "command": message["command"],
+                    "success": false,
+                    "message": "not supported by Lux Editor",
+                }));
+            }
+            _ => {}
+        }
+    }
+
+    fn responded(&mut self, pending: Pending, body: &Value) {
+        match pending {
+            Pending::Initialize => {
+                let command = match self.target.request {
+                    DebugRequest::Launch => "launch",
+                    DebugRequest::Attach => "attach",
+                };
+                let arguments = serde_json::to_value(&self.target.arguments).unwrap_or_default();
+                self.request(Pending::Start, command, arguments);
+            }
+            Pending::Start => {
+                if self.state == DebugState::Starting {
+                    self.state = DebugState::Running;
+                }
+            }
+            Pending::ConfigurationDone => self.request(Pending::Threads, "threads", json!({})),
+            Pending::Threads => {
+                if self.thread.is_none() {
+                    self.thread = body["threads"][0]["id"].as_i64();
+                }
+            }
+            Pending::StackTrace => {
+                self.frames = list(body, "stackFrames").iter().map(parse_frame).collect();
+                self.select_frame(0);
+            }
+            Pending::Scopes => {
+                self.scopes = list(body, "scopes")
+                    .iter()
+                    .map(|scope| Scope {
+                        name: scope["name"].as_str().unwrap_or_default().to_string(),
+                        reference: scope["variablesReference"].as_i64().unwrap_or_default(),
+                    })
+                    .collect();
+                // Expensive scopes, such as all globals, wait until expanded
+                let cheap: Vec<i64> = list(body, "scopes")
+                    .iter()
+                    .filter(|scope| scope["expensive"].as_bool() != Some(true))
+                    .filter_map(|scope| scope["variablesReference"].as_i64())
+                    .collect();
+                for reference in cheap {
+                    self.expand(reference);
+                }
+            }
+            Pending::Variables(reference) => {
+                let variables = list(body, "variables")
+                    .iter()
+                    .map(|var| Variable {
+                        name: var["name"].as_str().unwrap_or_default().to_string(),
+                        value: var["value"].as_str().unwrap_or_default().to_string(),
+                        type_name: var["type"].as_str().filter(|t| !t.is_empty()).map(str::to_string),
+                        reference: var["variablesReference"].as_i64().unwrap_or_default(),
+                    })
+                    .collect();
+                self.variables.insert(reference, variables);
+            }
+            Pending::Other => {}
+        }
+    }
+
+    fn event(&mut self, event: &str, body: &Value) {
+        match event {
+            "initialized" => {
+                let files: Vec<PathBuf> = self.breakpoints.keys().cloned().collect();
+                for file in files {
+                    self.send_breakpoints(&file);
+                }
+                self.configured = true;
+                self.request(Pending::ConfigurationDone, "configurationDone", json!({}));
+            }
+            "stopped" => {
+                let thread = body["threadId"].as_i64().or(self.thread).unwrap_or(1);
+                self.thread = Some(thread);
+                let reason = body["reason"].as_str().unwrap_or("paused").to_string();
+                self.state = DebugState::Stopped { thread, reason };
+                self.clear_stack();
+                self.request(Pending::StackTrace, "stackTrace", json!({ "threadId": thread }));
+            }
+            "continued" => {
+                self.state = DebugState::Running;
+                self.clear_stack();
+            }
+            "output" if body["category"].as_str() != Some("telemetry") => {
+                let text = body["output"].as_str().unwrap_or_default().to_string();
+                for line in text.lines() {
+                    self.push_output(line.to_string());
+                }
+            }
+            "exited" => {
+                let code = body["exitCode"].as_i64().unwrap_or_default();
+                self.push_output(format!("Program exited with code {}", code));
+            }
+            "terminated" => {
+                // Let the adapter exit too
+                self.request(Pending::Other, "disconnect", json!({}));
+                self.end("the program ended");
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Drop for DebugSession {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn list<'a>(body: &'a Value, key: &str) -> &'a [Value] {
+    body[key].as_array().map(Vec::as_slice).unwrap_or_default()
+}
+
+fn parse_frame(frame: &Value) -> StackFrame {
+    let position = |key: &str| frame[key].as_u64().unwrap_or(1).saturating_sub(1) as usize;
+    StackFrame {
+        id: frame["id"].as_i64().unwrap_or_default(),
+        name: frame["name"].as_str().unwrap_or_default().to_string(),
+        path: frame["source"]["path"].as_str().map(PathBuf::from),
+        line: position("line"),
+        col: position("column"),
+    }
+}
@@ -0,0 +1,602 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use eframe::egui::Color32;
+
+use crate::edits::{self, CodeAction, TextEdit, WorkspaceEdit};
+use crate::editor::{Editor, Position};
+use crate::workspace::{LintFormat, LintOutput, TaskOutput};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Hint,
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub fn color(self) -> Color32 {
+        match self {
+            Severity::Error => Color32::from_rgb(235, 85, 85),
+            Severity::Warning => Color32::from_rgb(225, 180, 60),
+            Severity::Info => Color32::from_rgb(85, 165, 235),
+            Severity::Hint => Color32::from_rgb(150, 150, 150),
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "info",
+            Severity::Hint => "hint",
+        }
+    }
+}
+
+/// A message attached to a range of a buffer.
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub start: Position,
+    pub end: Position,
+    pub severity: Severity,
+    pub message: String,
+    /// Provider name, shown next to the message.
+    pub source: String,
+    /// Fixes the tool suggested, for the text it checked.
+    pub fixes: Vec<CodeAction>,
+}
+
+impl Diagnostic {
+    pub fn touches_line(&self, line: usize) -> bool {
+        self.start.line <= line && line <= self.end.line
+    }
+}
+
+/// A diagnostic in any file, open or not, for the problems panel.
+#[derive(Clone, Debug)]
+pub struct Problem {
+    pub path: PathBuf,
+    /// 0-based.
+    pub line: usize,
+    /// 0-based; 0 when the tool gave no column.
+    pub col: usize,
+    pub severity: Severity,
+    pub message: String,
+    pub source: String,
+}
+
+/// A source of diagnostics for open buffers: build output, a spellchecker,
+/// a language server.
+pub trait DiagnosticsProvider {
+    fn name(&self) -> &str;
+
+    /// Diagnostics for one buffer's current text.
+    fn diagnose(&self, path: Option<&Path>, text: &str) -> Vec<Diagnostic>;
+
+    /// Called when a workspace task finishes, for providers that read build
+    /// output.
+    fn task_finished(&mut self, _root: &Path, _output: &TaskOutput) {}
+
+    /// Called when a linter run finishes.
+    fn lint_finished(&mut self, _output: &LintOutput) {}
+
+    /// Called when a language server publishes a file's diagnostics,
+    /// replacing what it published for that file before.
+    fn server_published(&mut self, _path: &Path, _diagnostics: &[Diagnostic]) {}
+
+    /// Everything the provider knows about in files on disk, for providers
+    /// that report on more than the open buffers.
+    fn problems(&self) -> Vec<Problem> {
+        Vec::new()
+    }
+}
+
+/// The registered providers; fills each editor's `diagnostics`.
+pub struct Diagnostics {
+    providers: Vec<Box<dyn DiagnosticsProvider>>,
+}
+
+impl Default for Diagnostics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Diagnostics {
+    pub fn new() -> Self {
+        Self {
+            providers: vec![
+                Box::new(BuildOutput::default()),
+                Box::new(Linters::default()),
+                Box::new(LanguageServers::default()),
+            ],
+        }
+    }
+
+    pub fn task_finished(&mut self, root: &Path, output: &TaskOutput) {
+        for provider in &mut self.providers {
+            provider.task_finished(root, output);
+        }
+    }
+
+    pub fn lint_finished(&mut self, output: &LintOutput) {
+        for provider in &mut self.providers {
+            provider.lint_finished(output);
+        }
+    }
+
+    pub fn server_published(&mut self, path: &Path, diagnostics: &[Diagnostic]) {
+        for provider in &mut self.providers {
+            provider.server_published(path, diagnostics);
+        }
+    }
+
+    /// Problems from every provider, by file and position.
+    pub fn problems(&self) -> Vec<Problem> {
+        let mut problems: Vec<Problem> = self.providers.iter().flat_map(|p| p.problems()).collect();
+        problems.sort_by(|a, b| (&a.path, a.line, a.col).cmp(&(&b.path, b.line, b.col)));
+        problems
+    }
+
+    /// Recompute an editor's diagnostics from every provider.
+    pub fn refresh(&self, editor: &mut Editor) {
+        let text = editor.rope.to_string();
+        let path = editor.file_path.as_deref();
+        let mut diagnostics: Vec<Diagnostic> = self
+            .providers
+            .iter()
+            .flat_map(|p| p.diagnose(path, &text))
+            .collect();
+        diagnostics.sort_by_key(|d| (d.start.line, d.start.col));
+        editor.diagnostics = diagnostics;
+    }
+}
+
+/// A problem reported at a file position, before it is mapped onto a buffer.
+struct Reported {
+    line: usize,
+    col: Option<usize>,
+    /// Exclusive end line and column, when the tool gives one.
+    end: Option<(usize, usize)>,
+    severity: Severity,
+    message: String,
+    fixes: Vec<CodeAction>,
+}
+
+/// Problems by file, with paths normalized so they match buffers.
+type ByFile = HashMap<PathBuf, Vec<Reported>>;
+
+/// Map reported problems onto a buffer's text.
+fn diagnostics_for(reported: &[Reported], text: &str, source: &str) -> Vec<Diagnostic> {
+    let lines: Vec<&str> = text.split('\n').collect();
+    reported
+        .iter()
+        .filter(|r| r.line < lines.len())
+        .map(|r| {
+            let line_text = lines[r.line];
+            let (start, end) = match r.col {
+                Some(col) => (col, word_end(line_text, col)),
+                // No column: the whole line, minus indentation
+                None => (
+                    line_text.chars().take_while(|c| c.is_whitespace()).count(),
+                    line_text.chars().count(),
+                ),
+            };
+            let start = Position::new(r.line, start);
+            let end = match r.end {
+                Some((line, col)) if (line, col) > (start.line, start.col) => Position::new(line, col),
+                _ => Position::new(r.line, end.max(start.col + 1)),
+            };
+            Diagnostic {
+                start,
+                end,
+                severity: r.severity,
+                message: r.message.clone(),
+                source: source.to_string(),
+                fixes: r.fixes.clone(),
+            }
+        })
+        .collect()
+}
+
+fn problems_in(by_file: &ByFile, source: &str) -> Vec<Problem> {
+    by_file
+        .iter()
+        .flat_map(|(path, reported)| {
+            reported.iter().map(|r| Problem {
+                path: path.clone(),
+                line: r.line,
+                col: r.col.unwrap_or(0),
+                severity: r.severity,
+                message: r.message.clone(),
+                source: source.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Errors and warnings parsed from the output of the last workspace task,
+/// in rustc/cargo (`--> file:line:col`) or gcc/clang/tsc
+/// (`file:line:col: error: message`) style.
+#[derive(Default)]
+struct BuildOutput {
+    by_file: ByFile,
+}
+
+impl DiagnosticsProvider for BuildOutput {
+    fn name(&self) -> &str {
+        "build"
+    }
+
+    fn diagnose(&self, path: Option<&Path>, text: &str) -> Vec<Diagnostic> {
+        match path.and_then(|p| self.by_file.get(&normalize(p))) {
+            Some(reported) => diagnostics_for(reported, text, self.name()),
+            None => Vec::new(),
+        }
+    }
+
+    fn task_finished(&mut self, root: &Path, output: &TaskOutput) {
+        // A new run supersedes the previous one
+        self.by_file = parse_text_output(root, &output.output);
+        tracing::debug!(files = self.by_file.len(), "parsed build output");
+    }
+
+    fn problems(&self) -> Vec<Problem> {
+        problems_in(&self.by_file, self.name())
+    }
+}
+
+/// Results of each linter's last run.
+#[derive(Default)]
+struct Linters {
+    by_linter: HashMap<String, ByFile>,
+}
+
+impl DiagnosticsProvider for Linters {
+    fn name(&self) -> &str {
+        "lint"
+    }
+
+    fn diagnose(&self, path: Option<&Path>, text: &str) -> Vec<Diagnostic> {
+        let Some(path) = path.map(normalize) else {
+            return Vec::new();
+        };
+        self.by_linter
+            .iter()
+            .filter_map(|(linter, by_file)| Some(diagnostics_for(by_file.get(&path)?, text, linter)))
+            .flatten()
+            .collect()
+    }
+
+    fn lint_finished(&mut self, output: &LintOutput) {
+        let found = match output.format {
+            LintFormat::Text => {
+                parse_text_output(&output.cwd, &format!("{}\n{}", output.output, output.errors))
+            }
+            LintFormat::CargoJson => parse_cargo_json(&output.cwd, &output.output),
+            LintFormat::EslintJson => parse_eslint_json(&output.cwd, &output.output),
+        };
+        tracing::debug!(linter = %output.linter, files = found.len(), "parsed linter output");
+        let results = self.by_linter.entry(output.linter.clone()).or_default();
+        match &output.file {
+            // Only the saved file was checked, so other files keep theirs
+            Some(file) => {
+                results.remove(&normalize(file));
+                results.extend(found);
+            }
+            None => *results = found,
+        }
+    }
+
+    fn problems(&self) -> Vec<Problem> {
+        self.by_linter
+            .iter()
+            .flat_map(|(linter, by_file)| problems_in(by_file, linter))
+            .collect()
+    }
+}
+
+/// What the language servers last published for each file. Positions
+/// are already in buffer terms, so they only need to fit the text.
+#[derive(Default)]
+struct LanguageServers {
+    by_file: HashMap<PathBuf, Vec<Diagnostic>>,
+}
+
+impl DiagnosticsProvider for LanguageServers {
+    fn name(&self) -> &str {
+        "language server"
+    }
+
+    fn diagnose(&self, path: Option<&Path>, text: &str) -> Vec<Diagnostic> {
+        let Some(published) = path.and_then(|p| self.by_file.get(&normalize(p))) else {
+            return Vec::new();
+        };
+        let lines = text.split('\n').count();
+        published.iter().filter(|d| d.start.line < lines).cloned().collect()
+    }
+
+    fn server_published(&mut self, path: &Path, diagnostics: &[Diagnostic]) {
+        if diagnostics.is_empty() {
+            self.by_file.remove(&normalize(path));
+        } else {
+            self.by_file.insert(normalize(path), diagnostics.to_vec());
+        }
+    }
+
+    fn problems(&self) -> Vec<Problem> {
+        self.by_file
+            .iter()
+            .flat_map(|(path, diagnostics)| {
+                diagnostics.iter().map(|d| Problem {
+                    path: path.clone(),
+                    line: d.start.line,
+                    col: d.start.col,
+                    severity: d.severity,
+                    message: d.message.clone(),
+                    source: d.source.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// rustc-style or `file:line:col: message` lines; numbers are 1-based and
+/// relative paths start at `root`.
+fn parse_text_output(root: &Path, output: &str) -> ByFile {
+    let mut by_file = ByFile::new();
+    let mut pending: Option<(Severity, String)> = None;
+    for line in output.lines() {
+        let trimmed = line.trim_start();
+        if let Some(location) = trimmed.strip_prefix("--> ") {
+            // rustc: the message came on the line above
+            if let (Some((severity, message)), Some((file, line, col))) =
+                (pending.take(), parse_location(location))
+            {
+                add(&mut by_file, root, file, line, col, severity, message);
+            }
+        } else if let Some((severity, message)) = parse_rustc_header(trimmed) {
+            pending = Some((severity, message));
+        } else if let Some((location, rest)) = split_gcc_line(line) {
+            if let Some((file, line, col)) = parse_location(location) {
+                let (severity, message) = parse_severity(rest);
+                add(&mut by_file, root, file, line, col, severity, message);
+            }
+        }
+    }
+    by_file
+}
+
+fn add(by_file: &mut ByFile, root: &Path, file: &str, line: usize, col: Option<usize>, severity: Severity, message: String) {
+    by_file.entry(resolve(root, file)).or_default().push(Reported {
+        line: line.saturating_sub(1),
+        col: col.map(|c| c.saturating_sub(1)),
+        end: None,
+        severity,
+        message,
+        fixes: Vec::new(),
+    });
+}
+
+fn resolve(root: &Path, file: &str) -> PathBuf {
+    let path = Path::new(file);
+    normalize(&if path.is_absolute() { path.to_path_buf() } else { root.join(path) })
+}
+
+/// One JSON message per line from `cargo --message-format=json`; only the
+/// compiler messages with a primary span count.
+fn parse_cargo_json(root: &Path, output: &str) -> ByFile {
+    let mut by_file = ByFile::new();
+    for line in output.lines() {
+        let Ok(value) = serde_json::from_str::<serde_json::Value>(line) else {
+            continue;
+        };
+        if value["reason"] != "compiler-message" {
+            continue;
+        }
+        let message = &value["message"];
+        let severity = match message["level"].as_str() {
+            Some("error") => Severity::Error,
+            Some("warning") => Severity::Warning,
+            Some("note") => Severity::Info,
+            Some("help") => Severity::Hint,
+            _ => continue,
+        };
+        let spans = message["spans"].as_array().map(Vec::as_slice).unwrap_or_default();
+        let Some(span) = spans.iter().find(|s| s["is_primary"] == true) else {
+            continue;
+        };
+        let (Some(file), Some(line), Some(col)) = (
+            span["file_name"].as_str(),
+            span["line_start"].as_u64(),
+            span["column_start"].as_u64(),
+        ) else {
+            continue;
+        };
+        let end = span["line_end"].as_u64().zip(span["column_end"].as_u64());
+        let text = message["message"].as_str().unwrap_or_default();
+        let text = match message["code"]["code"].as_str() {
+            Some(code) => format!("{} ({})", text, code),
+            None => text.to_string(),
+        };
+        let children = message["children"].as_array().map(Vec::as_slice).unwrap_or_default();
+        let fixes = children.iter().filter_map(|child| cargo_fix(root, child)).collect();
+        by_file.entry(resolve(root, file)).or_default().push(Reported {
+            line: (line as usize).saturating_sub(1),
+            col: Some((col as usize).saturating_sub(1)),
+            end: end.map(|(l, c)| ((l as usize).saturating_sub(1), (c as usize).saturating_sub(1))),
+            severity,
+            message: text,
+            fixes,
+        });
+    }
+    by_file
+}
+
+/// The replacements a `help:` child message suggests, as a fix named after
+/// it. Suggestions with placeholders to fill in are left out.
+fn cargo_fix(root: &Path, child: &serde_json::Value) -> Option<CodeAction> {
+    let mut edit = WorkspaceEdit::new();
+    let spans = child["spans"].as_array().map(Vec::as_slice).unwrap_or_default();
+    for span in spans {
+        let Some(text) = span["suggested_replacement"].as_str() else {
+            continue;
+        };
+        if span["suggestion_applicability"] == "HasPlaceholders" {
+            return None;
+        }
+        let position = |line: &str, col: &str| {
+            let line = span[line].as_u64()? as usize;
+            let col = span[col].as_u64()? as usize;
+            Some(Position::new(line.saturating_sub(1), col.saturating_sub(1)))
+        };
+        let (Some(file), Some(start), Some(end)) = (
+            span["file_name"].as_str(),
+            position("line_start", "column_start"),
+            position("line_end", "column_end"),
+        ) else {
+            continue;
+        };
+        edit.entry(resolve(root, file)).or_default().push(TextEdit { start, end, text: text.to_string(), old: None });
+    }
+    let edits: Vec<&TextEdit> = edit.values().flatten().collect();
+    let message = child["message"].as_str().unwrap_or("Apply suggestion");
+    let title = match edits.as_slice() {
+        [only] if !only.text.is_empty() && !message.contains(&only.text) => format!("{}: `{}`", message, only.text),
+        [] => return None,
+        _ => message.to_string(),
+    };
+    Some(CodeAction { title, edit, source: "cargo".to_string() })
+}
+
+/// The array printed by `eslint -f json`: one entry per file, each with
+/// its messages.
+fn parse_eslint_json(root: &Path, output: &str) -> ByFile {
+    let mut by_file = ByFile::new();
+    let Ok(serde_json::Value::Array(files)) = serde_json::from_str(output.trim()) else {
+        return by_file;
+    };
+    for file in &files {
+        let Some(path) = file["filePath"].as_str() else {
+            continue;
+        };
+        let path = resolve(root, path);
+        // Fixes give character offsets into the file as it was checked
+        let mut source: Option<String> = None;
+        let reported = by_file.entry(path.clone()).or_default();
+        for message in file["messages"].as_array().map(Vec::as_slice).unwrap_or_default() {
+            let severity = match message["severity"].as_u64() {
+                Some(2) => Severity::Error,
+                Some(1) => Severity::Warning,
+                _ => Severity::Info,
+            };
+            let line = message["line"].as_u64().unwrap_or(1) as usize;
+            let col = message["column"].as_u64().map(|c| (c as usize).saturating_sub(1));
+            let end = message["endLine"].as_u64().zip(message["endColumn"].as_u64());
+            let text = message["message"].as_str().unwrap_or_default();
+            let text = match message["ruleId"].as_str() {
+                Some(rule) => format!("{} ({})", text, rule),
+                None => text.to_string(),
+            };
+            let suggestions = message["suggestions"].as_array().map(Vec::as_slice).unwrap_or_default();
+            let fix_title = format!("Fix: {}", text);
+            let fixes = [(fix_title.as_str(), &message["fix"])]
+                .into_iter()
+                .chain(suggestions.iter().map(|s| (s["desc"].as_str().unwrap_or("Apply suggestion"), &s["fix"])))
+                .filter(|(_, fix)| fix.is_object())
+                .filter_map(|(title, fix)| {
+                    let source = source.get_or_insert_with(|| fs::read_to_string(&path).unwrap_or_default());
+                    eslint_fix(&path, source, title, fix)
+                })
+                .collect();
+            reported.push(Reported {
+                line: line.saturating_sub(1),
+                col,
+                end: end.map(|(l, c)| ((l as usize).saturating_sub(1), (c as usize).saturating_sub(1))),
+                severity,
+                message: text,
+                fixes,
+            });
+        }
+    }
+    by_file.retain(|_, reported| !reported.is_empty());
+    by_file
+}
+
+/// An eslint `{ range: [from, to], text }` fix.
+fn eslint_fix(path: &Path, source: &str, title: &str, fix: &serde_json::Value) -> Option<CodeAction> {
+    let from = fix["range"][0].as_u64()? as usize;
+    let to = fix["range"][1].as_u64()? as usize;
+    let edit = TextEdit {
+        start: edits::offset_to_position(source, from),
+        end: edits::offset_to_position(source, to),
+        text: fix["text"].as_str()?.to_string(),
+        // Buffers hold line endings as `\n`
+        old: Some(source.chars().skip(from).take(to.saturating_sub(from)).filter(|&c| c != '\r').collect()),
+    };
+    Some(CodeAction {
+        title: title.to_string(),
+        edit: WorkspaceEdit::from([(path.to_path_buf(), vec![edit])]),
+        source: "eslint".to_string(),
+    })
+}
+
+fn normalize(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// `error[E0425]: message` or `warning: message`.
+fn parse_rustc_header(line: &str) -> Option<(Severity, String)> {
+    let (head, message) = line.split_once(": ")?;
+    let kind = head.split('[').next()?;
+    let severity = match kind {
+        "error" => Severity::Error,
+        "warning" => Severity::Warning,
+        "note" => Severity::Info,
+        "help" => Severity::Hint,
+        _ => return None,
+    };
+    Some((severity, message.to_string()))
+}
+
+/// Split `file:line:col: rest` into the location and the rest.
+fn split_gcc_line(line: &str) -> Option<(&str, &str)> {
+    // Skip a Windows drive letter so its colon isn't taken as a separator
+    let skip = if line.as_bytes().get(1) == Some(&b':') { 2 } else { 0 };
+    let idx = line[skip..].find(": ")? + skip;
+    Some((&line[..idx], &line[idx + 2..]))
+}
+
+/// `file:line` or `file:line:col`, with 1-based numbers.
+fn parse_location(location: &str) -> Option<(&str, usize, Option<usize>)> {
+    let mut parts = location.trim().rsplitn(3, ':');
+    let last = parts.next()?.parse::<usize>().ok()?;
+    let middle = parts.next()?;
+    match (middle.parse::<usize>(), parts.next()) {
+        (Ok(line), Some(file)) if !file.is_empty() => Some((file, line, Some(last))),
+        _ => {
+            let file = location.trim().rsplit_once(':')?.0;
+            (!file.is_empty()).then_some((file, last, None))
+        }
+    }
+}
+
+/// `error: message`, `warning: message`, or just a message (an error).
+fn parse_severity(rest: &str) -> (Severity, String) {
+    match parse_rustc_header(rest.trim_start()) {
+        Some(found) => found,
+        None => (Severity::Error, rest.trim().to_string()),
+    }
+}
+
+/// End column of the word starting at `col`, or `col` if there is none.
+fn word_end(line: &str, col: usize) -> usize {
+    col + line
+        .chars()
+        .skip(col)
+        .take_while(|c| c.is_alphanumeric() || *c == '_')
+        .count()
+}
@@ -0,0 +1,127 @@
+/// Command-line flag that compares two files side by side, e.g. as a git
+/// difftool.
+pub const DIFF_FLAG: &str = "--diff";
+
+/// One line of a line-by-line comparison.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DiffLine {
+    Same(String),
+    Removed(String),
+    Added(String),
+}
+
+/// Largest middle section (lines × lines) compared exactly; anything bigger
+/// is shown as a block removal followed by a block addition.
+const MAX_CELLS: usize = 4_000_000;
+
+/// Line diff of `old` against `new`. Common leading and trailing lines are
+/// matched first, then the rest by longest common subsequence.
+pub fn line_diff(old: &str, new: &str) -> Vec<DiffLine> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (a, b) = (&old[prefix..old.len() - suffix], &new[prefix..new.len() - suffix]);
+
+    let mut out: Vec<DiffLine> = old[..prefix].iter().map(|l| DiffLine::Same(l.to_string())).collect();
+    if a.len().saturating_mul(b.len()) > MAX_CELLS {
+        out.extend(a.iter().map(|l| DiffLine::Removed(l.to_string())));
+        out.extend(b.iter().map(|l| DiffLine::Added(l.to_string())));
+    } else {
+        out.extend(lcs_diff(a, b));
+    }
+    out.extend(old[old.len() - suffix..].iter().map(|l| DiffLine::Same(l.to_string())));
+    out
+}
+
+/// One row of a side-by-side comparison: a line of each file with its
+/// 0-based number, or None where the other file has lines this one lacks.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiffRow {
+    pub old: Option<(usize, String)>,
+    pub new: Option<(usize, String)>,
+    pub changed: bool,
+}
+
+/// `diff` as rows of two columns, pairing each run of removed lines with
+/// the added lines that replace it.
+pub fn side_by_side(diff: &[DiffLine]) -> Vec<DiffRow> {
+    let mut rows = Vec::new();
+    let (mut old_line, mut new_line) = (0, 0);
+    let mut i = 0;
+    while i < diff.len() {
+        if let DiffLine::Same(text) = &diff[i] {
+            rows.push(DiffRow {
+                old: Some((old_line, text.clone())),
+                new: Some((new_line, text.clone())),
+                changed: false,
+            });
+            old_line += 1;
+            new_line += 1;
+            i += 1;
+            continue;
+        }
+        let mut removed = Vec::new();
+        let mut added = Vec::new();
+        while let Some(line) = diff.get(i) {
+            match line {
+                DiffLine::Removed(text) => removed.push(text.clone()),
+                DiffLine::Added(text) => added.push(text.clone()),
+                DiffLine::Same(_) => break,
+            }
+            i += 1;
+        }
+        let mut removed = removed.into_iter();
+        let mut added = added.into_iter();
+        loop {
+            let old = removed.next().map(|text| (old_line, text));
+            let new = added.next().map(|text| (new_line, text));
+            if old.is_none() && new.is_none() {
+                break;
+            }
+            old_line += usize::from(old.is_some());
+            new_line += usize::from(new.is_some());
+            rows.push(DiffRow { old, new, changed: true });
+        }
+    }
+    rows
+}
+
+fn lcs_diff(a: &[&str], b: &[&str]) -> Vec<DiffLine> {
+    // lengths[i][j] = LCS length of a[i..] and b[j..]
+    let width = b.len() + 1;
+    let mut lengths = vec![0u32; (a.len() + 1) * width];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lengths[i * width + j] = if a[i] == b[j] {
+                lengths[(i + 1) * width + j + 1] + 1
+            } else {
+                lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+            };
+        }
+    }
+    let mut out = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            out.push(DiffLine::Same(a[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if lengths[(i + 1) * width + j] >= lengths[i * width + j + 1] {
+            out.push(DiffLine::Removed(a[i].to_string()));
+            i += 1;
+        } else {
+            out.push(DiffLine::Added(b[j].to_string()));
+            j += 1;
+        }
+    }
+    out.extend(a[i..].iter().map(|l| DiffLine::Removed(l.to_string())));
+    out.extend(b[j..].iter().map(|l| DiffLine::Added(l.to_string())));
+    out
+}
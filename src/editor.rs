@@ -1,9 +1,198 @@
 use ropey::Rope;
+use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
-
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::clipboard::ClipboardContent;
+use crate::conflicts::{self, Conflict, Resolution};
+use crate::diagnostics::Diagnostic;
+use crate::diff;
+use crate::edits::{self, CodeAction, TextEdit};
+use crate::emmet;
+use crate::encryption::Encryption;
+use crate::file_ops;
+use crate::json;
+use crate::markers::{self, LineMarker, MarkerType};
+use crate::reflow;
+use crate::rope_search::{self, SearchError, SearchIter, SearchOptions};
+use crate::stats::Stats;
+use crate::syntax;
+use crate::undo::{Snapshot, UndoTree};
+
+/// Line height at the default font size.
 pub const LINE_HEIGHT: f32 = 20.0;
 
+static NEXT_VERSION: AtomicU64 = AtomicU64::new(1);
+
+/// Globally unique content version, so caches can key on it across editors.
+fn next_version() -> u64 {
+    NEXT_VERSION.fetch_add(1, Ordering::Relaxed)
+}
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+// --- Buffer format ---
+
+/// Lines read to detect a file's indentation.
+const DETECT_INDENT_LINES: usize = 10_000;
+/// How far back a typed closing bracket looks for its opener.
+const MATCH_BRACKET_CHARS: usize = 100_000;
+
+/// Indentation inserted by Tab and auto-indent.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Indent {
+    pub use_tabs: bool,
+    pub width: usize,
+}
+
+impl Default for Indent {
+    fn default() -> Self {
+        Self {
+            use_tabs: false,
+            width: 4,
+        }
+    }
+}
+
+impl Indent {
+    /// Text for one indentation level.
+    pub fn unit(&self) -> String {
+        if self.use_tabs {
+            "\t".into()
+        } else {
+            " ".repeat(self.width)
+        }
+    }
+
+    /// The indentation `rope` is written with: tabs if more lines start
+    /// with a tab than with spaces, otherwise the most common step between
+    /// consecutive space-indented lines. None if no line is indented.
+    pub fn detect(rope: &Rope) -> Option<Indent> {
+        let mut tab_lines = 0;
+        let mut space_lines = 0;
+        // How often each step from 1 to 8 spaces occurs
+        let mut steps = [0usize; 9];
+        let mut previous = 0;
+        for line in rope.lines().take(DETECT_INDENT_LINES) {
+            let mut chars = line.chars().peekable();
+            if chars.peek() == Some(&'\t') {
+                tab_lines += 1;
+                continue;
+            }
+            let spaces = chars.by_ref().take_while(|&c| c == ' ').count();
+            if line.chars().all(char::is_whitespace) {
+                continue;
+            }
+            if spaces > 0 {
+                space_lines += 1;
+            }
+            let step = spaces.abs_diff(previous);
+            // One-space steps are usually alignment, like ` * ` in comments
+            if (2..steps.len()).contains(&step) {
+                steps[step] += 1;
+            }
+            previous = spaces;
+        }
+        if tab_lines == 0 && space_lines == 0 {
+            return None;
+        }
+        let use_tabs = tab_lines > space_lines;
+        let width = (2..steps.len()).rev().max_by_key(|&step| steps[step]).filter(|&step| steps[step] > 0);
+        Some(Indent { use_tabs, width: width.unwrap_or(Indent::default().width) })
+    }
+
+    pub fn label(&self) -> String {
+        if self.use_tabs {
+            format!("Tab Size: {}", self.width)
+        } else {
+            format!("Spaces: {}", self.width)
+        }
+    }
+}
+
+/// Line ending written on save. The rope itself always uses `\n`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+}
+
+impl LineEnding {
+    pub fn label(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "LF",
+            LineEnding::CrLf => "CRLF",
+        }
+    }
+}
+
+/// Line endings of a file that mixes LF and CRLF, kept line by line: lines
+/// still as they were read keep their ending on save, and new ones get the
+/// buffer's `line_ending`.
+#[derive(Clone, Debug)]
+struct MixedEndings {
+    /// The text as read, with `\n` endings.
+    text: String,
+    /// Whether each of its lines ended in CRLF.
+    crlf: Vec<bool>,
+}
+
+impl MixedEndings {
+    /// The endings of `content`, or None if it uses just one kind.
+    fn detect(content: &str) -> Option<Self> {
+        let crlf: Vec<bool> = content.split_inclusive('\n').filter(|l| l.ends_with('\n')).map(|l| l.ends_with("\r\n")).collect();
+        if crlf.iter().all(|&c| c) || !crlf.iter().any(|&c| c) {
+            return None;
+        }
+        Some(Self { text: content.replace("\r\n", "\n"), crlf })
+    }
+
+    /// Whether most lines end in CRLF.
+    fn mostly_crlf(&self) -> bool {
+        self.crlf.iter().filter(|&&c| c).count() * 2 >= self.crlf.len()
+    }
+
+    /// `text` with each line that was there when the file was read, or
+    /// that was edited from one, ending as that line did; new lines end
+    /// in `ending`.
+    fn restore(&self, text: &str, ending: LineEnding) -> String {
+        let crlf: Vec<Option<bool>> = diff::side_by_side(&diff::line_diff(&self.text, text))
+            .into_iter()
+            .filter(|row| row.new.is_some())
+            .map(|row| row.old.and_then(|(line, _)| self.crlf.get(line).copied()))
+            .collect();
+        let mut out = String::with_capacity(text.len() + crlf.len());
+        for (i, line) in text.split_inclusive('\n').enumerate() {
+            match line.strip_suffix('\n') {
+                Some(line) if crlf.get(i).copied().flatten().unwrap_or(ending == LineEnding::CrLf) => {
+                    out.push_str(line);
+                    out.push_str("\r\n");
+                }
+                _ => out.push_str(line),
+            }
+        }
+        out
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Encoding {
+    Utf8,
+    /// UTF-8 with a leading byte-order mark, preserved on save.
+    Utf8Bom,
+}
+
+impl Encoding {
+    pub fn label(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf8Bom => "UTF-8 with BOM",
+        }
+    }
+}
+
 // --- Position & Cursor ---
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -49,9 +238,9 @@ impl Cursor {
     pub fn selection_ordered(&self) -> Option<(Position, Position)> {
         self.anchor.as_ref().map(|anchor| {
             if self.pos <= *anchor {
-                (self.pos.clone(), anchor.clone())
+                (self.pos, *anchor)
             } else {
-                (anchor.clone(), self.pos.clone())
+                (*anchor, self.pos)
             }
         })
     }
@@ -74,75 +263,429 @@ fn line_len_chars(rope: &Rope, line: usize) -> usize {
 }
 
 fn pos_to_char_idx(rope: &Rope, pos: &Position) -> usize {
+    if pos.line >= rope.len_lines() {
+        return rope.len_chars();
+    }
     let line_start = rope.line_to_char(pos.line);
     let max_col = line_len_chars(rope, pos.line);
     line_start + pos.col.min(max_col)
 }
 
-// --- Undo snapshot ---
+/// `text` with its lines moved to `indent`, keeping their indentation
+/// relative to the least indented line. A first line without leading
+/// whitespace was likely copied from mid-line, so it doesn't count toward
+/// that, and is left as it is unless `indent_first`. Blank lines are
+/// emptied.
+fn reindent(text: &str, indent: &str, indent_first: bool) -> String {
+    let lines: Vec<&str> = text.split('\n').collect();
+    let width = |line: &str| line.len() - line.trim_start_matches([' ', '\t']).len();
+    let first_counts = lines[0].starts_with([' ', '\t']);
+    let base = lines
+        .iter()
+        .enumerate()
+        .filter(|(i, line)| !line.trim().is_empty() && (*i > 0 || first_counts))
+        .map(|(_, line)| width(line))
+        .min()
+        .unwrap_or(0);
+    let reindented: Vec<String> = lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
+            if i == 0 && !indent_first {
+                line.to_string()
+            } else if line.trim().is_empty() {
+                String::new()
+            } else {
+                format!("{}{}", indent, &line[width(line).min(base)..])
+            }
+        })
+        .collect();
+    reindented.join("\n")
+}
+
+/// `pos` moved inside the text: a column past the end of its line goes to
+/// the line end, a line past the end of the buffer to the buffer end.
+fn clamp_pos(rope: &Rope, pos: Position) -> Position {
+    char_idx_to_pos(rope, pos_to_char_idx(rope, &pos))
+}
+
+/// First line of a cursor's selection, or its line without one.
+fn selection_start_line(cursor: &Cursor) -> usize {
+    cursor.selection_ordered().map_or(cursor.pos.line, |(start, _)| start.line)
+}
+
+fn char_idx_to_pos(rope: &Rope, idx: usize) -> Position {
+    let line = rope.char_to_line(idx);
+    Position::new(line, idx - rope.line_to_char(line))
+}
+
+/// The last word of `line`, before any block parameters like Ruby's
+/// `do |item|`.
+/// Whether `content`, a line without its indentation, has just completed a
+/// block-closing word: the word followed by one other character, or for
+/// rules with a `closer_end`, a statement from the word up to that end.
+fn completes_closer(rules: &syntax::IndentRules, content: &str) -> bool {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let word_end = content.find(|c: char| !is_word(c)).unwrap_or(content.len());
+    let (word, rest) = content.split_at(word_end);
+    if !rules.closers.contains(&word) {
+        return false;
+    }
+    match rules.closer_end {
+        Some(end) => rest.ends_with(end),
+        None => rest.chars().count() == 1,
+    }
+}
+
+fn last_word(line: &str) -> &str {
+    let mut line = line.trim_end();
+    if let Some(rest) = line.strip_suffix('|') {
+        if let Some(start) = rest.rfind('|') {
+            line = rest[..start].trim_end();
+        }
+    }
+    line.rsplit(char::is_whitespace).next().unwrap_or("")
+}
+
+/// How Enter carries on the comment a line starts with.
+enum CommentContinuation {
+    /// Start the new line with this after the indentation, like `// ` or
+    /// ` * ` inside a block comment.
+    Prefix(String),
+    /// The comment line is empty: remove its prefix instead.
+    End,
+}
 
-#[derive(Clone)]
-struct Snapshot {
-    rope: Rope,
-    cursors: Vec<Cursor>,
+/// The comment continuation for a line split into the text `before` and
+/// `after` the cursor, or None if the line doesn't start with a comment.
+/// Doc comment markers like `///` and `//!` carry on as they are.
+fn comment_continuation(before: &str, after: &str, line_comment: &str) -> Option<CommentContinuation> {
+    let content = before.trim_start();
+    let (marker_len, prefix) = if let Some(rest) = content.strip_prefix("/*") {
+        (2 + rest.chars().take_while(|&c| c == '*' || c == '!').count(), None)
+    } else if content.starts_with('*') && !content.starts_with("*/") {
+        (1, Some("*"))
+    } else if let Some(rest) = content.strip_prefix(line_comment) {
+        let extra: usize = rest
+            .chars()
+            .take_while(|&c| line_comment.contains(c) || c == '!')
+            .map(char::len_utf8)
+            .sum();
+        let len = line_comment.len() + extra;
+        (len, Some(&content[..len]))
+    } else {
+        return None;
+    };
+    let rest = &content[marker_len..];
+    let Some(marker) = prefix else {
+        // The line that opens a block comment
+        return Some(CommentContinuation::Prefix(" * ".to_string()));
+    };
+    if rest.trim().is_empty() && after.trim().is_empty() {
+        return Some(CommentContinuation::End);
+    }
+    let gap: String = rest.chars().take_while(|c| c.is_whitespace()).collect();
+    Some(CommentContinuation::Prefix(format!("{}{}", marker, gap)))
+}
+
+/// Cursor selecting the char range `range`, with the cursor at its end.
+fn selection_cursor(rope: &Rope, range: std::ops::Range<usize>) -> Cursor {
+    let end = char_idx_to_pos(rope, range.end);
+    let mut cursor = Cursor::new(end.line, end.col);
+    cursor.anchor = Some(char_idx_to_pos(rope, range.start));
+    cursor
+}
+
+fn title_for(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "Untitled".into())
 }
 
 // --- Editor ---
 
 pub struct Editor {
+    /// Identifies the tab for its lifetime, whatever file it holds.
+    pub id: u64,
     pub rope: Rope,
     pub cursors: Vec<Cursor>,
     pub file_path: Option<PathBuf>,
+    /// Name of the scratch buffer this is, if any. Scratch buffers have no
+    /// file and are persisted to the app data directory automatically.
+    pub scratch: Option<String>,
     pub modified: bool,
+    /// The file was deleted or moved away on disk; saving writes it again.
+    pub deleted_on_disk: bool,
     pub scroll_y: f32,
     pub scroll_x: f32,
+    /// Pixel height of a line as last laid out by the view.
+    pub line_height: f32,
+    /// Pixel height of the text area as last laid out by the view.
+    pub view_height: f32,
+    /// Where Center Cursor last put the cursor's line, to go on to the
+    /// next place when repeated: center, top, then bottom.
+    recentered: Option<(Position, f32, usize)>,
     pub title: String,
-    undo_stack: Vec<Snapshot>,
-    redo_stack: Vec<Snapshot>,
+    /// Pinned tabs render compact, stay left of the tab strip, and ignore
+    /// bulk or middle-click closing.
+    pub pinned: bool,
+    /// Opened for a quick look: the next preview replaces it unless it is
+    /// edited or its tab double-clicked first.
+    pub preview: bool,
+    /// The buffer is synced with a collaboration session.
+    pub shared: bool,
+    /// The file is encrypted; it is decrypted only in memory and encrypted
+    /// again on save.
+    pub encryption: Option<Encryption>,
+    undo: UndoTree,
+    /// The next edit extends the last undo step instead of starting one.
+    join_undo: bool,
+    /// Inside `labeled_edit`: every edit goes into its one undo step.
+    group_undo: bool,
+    /// "Undid Replace All" after undoing or redoing a command's change, for
+    /// the app to show and clear.
+    pub undo_notice: Option<String>,
     /// Timestamp of last edit/keystroke (seconds since epoch via std::time)
     pub last_edit_time: f64,
+    /// Changes whenever the content changes; unique across editors.
+    pub version: u64,
+    pub indent: Indent,
+    /// The indentation was detected from the content rather than set.
+    pub indent_detected: bool,
+    pub line_ending: LineEnding,
+    /// Set when the file mixes line endings, until one is chosen for it.
+    mixed_endings: Option<MixedEndings>,
+    pub encoding: Encoding,
+    /// Typed characters replace the character under the cursor (Insert key).
+    pub overwrite: bool,
+    /// Re-indent multi-line pastes to match the cursor's line; the editor
+    /// view keeps it in step with the setting.
+    pub reindent_paste: bool,
+    /// Problems reported by diagnostics providers, sorted by position.
+    pub diagnostics: Vec<Diagnostic>,
+    /// Bookmarks and other markers placed on lines, sorted by line.
+    pub markers: Vec<LineMarker>,
+    /// Buffer version the markers were last moved to follow edits at.
+    markers_version: u64,
+    /// The markers changed since the app last saved them.
+    pub markers_changed: bool,
+    /// Line the debugger is stopped at in this file, set by the app.
+    pub execution_line: Option<usize>,
+    /// Actions a language server offered for the buffer version and cursor
+    /// position they were asked for.
+    pub code_actions: Option<(u64, Position, Vec<CodeAction>)>,
+    /// The fixes lightbulb was clicked, for the app to open the menu.
+    pub show_code_actions: bool,
+    /// Characters typed so far in jump-to-character mode, when it is on.
+    pub jump_query: Option<String>,
+    /// The most recent cursors that had a selection, for Reselect.
+    last_selection: Option<Vec<Cursor>>,
+    /// Text an input method is composing at the primary cursor; it reaches
+    /// the buffer only when committed.
+    pub ime_preedit: Option<String>,
+}
+
+impl Default for Editor {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Editor {
     pub fn new() -> Self {
         Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
             rope: Rope::new(),
             cursors: vec![Cursor::new(0, 0)],
             file_path: None,
+            scratch: None,
             modified: false,
+            deleted_on_disk: false,
             scroll_y: 0.0,
             scroll_x: 0.0,
+            line_height: LINE_HEIGHT,
+            view_height: 0.0,
+            recentered: None,
             title: "Untitled".into(),
-            undo_stack: Vec::new(),
-            redo_stack: Vec::new(),
+            pinned: false,
+            preview: false,
+            shared: false,
+            encryption: None,
+            undo: UndoTree::new(Snapshot {
+                rope: Rope::new(),
+                cursors: vec![Cursor::new(0, 0)],
+            }),
+            join_undo: false,
+            group_undo: false,
+            undo_notice: None,
             last_edit_time: 0.0,
+            version: next_version(),
+            indent: Indent::default(),
+            indent_detected: false,
+            line_ending: LineEnding::Lf,
+            mixed_endings: None,
+            encoding: Encoding::Utf8,
+            overwrite: false,
+            reindent_paste: true,
+            diagnostics: Vec::new(),
+            markers: Vec::new(),
+            markers_version: 0,
+            markers_changed: false,
+            execution_line: None,
+            code_actions: None,
+            show_code_actions: false,
+            jump_query: None,
+            last_selection: None,
+            ime_preedit: None,
         }
     }
 
     pub fn from_file(path: PathBuf) -> Result<Self, std::io::Error> {
         let content = fs::read_to_string(&path)?;
-        let title = path
-            .file_name()
-            .map(|n| n.to_string_lossy().into_owned())
-            .unwrap_or_else(|| "Untitled".into());
-        Ok(Self {
-            rope: Rope::from_str(&content),
+        Ok(Self::from_content(path, &content))
+    }
+
+    /// Empty buffer for `path`, which doesn't exist yet; saving creates it.
+    pub fn for_new_file(path: PathBuf) -> Self {
+        Self::from_content(path, "")
+    }
+
+    /// Buffer for an encrypted file, with its decrypted `text`.
+    pub fn from_decrypted(path: PathBuf, text: &str, encryption: Encryption) -> Self {
+        let mut editor = Self::from_content(path, text);
+        editor.encryption = Some(encryption);
+        editor
+    }
+
+    /// Buffer for `path` holding `content` as read from it.
+    fn from_content(path: PathBuf, content: &str) -> Self {
+        let title = title_for(&path);
+
+        let (content, encoding) = match content.strip_prefix('\u{FEFF}') {
+            Some(rest) => (rest, Encoding::Utf8Bom),
+            None => (content, Encoding::Utf8),
+        };
+        // Keep the rope LF-only; the original endings are restored on save
+        let mixed_endings = MixedEndings::detect(content);
+        let (rope, line_ending) = if content.contains("\r\n") {
+            let ending = match &mixed_endings {
+                Some(mixed) if !mixed.mostly_crlf() => LineEnding::Lf,
+                _ => LineEnding::CrLf,
+            };
+            (Rope::from_str(&content.replace("\r\n", "\n")), ending)
+        } else {
+            (Rope::from_str(content), LineEnding::Lf)
+        };
+
+        Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            rope: rope.clone(),
             cursors: vec![Cursor::new(0, 0)],
             file_path: Some(path),
+            scratch: None,
             modified: false,
+            deleted_on_disk: false,
             scroll_y: 0.0,
             scroll_x: 0.0,
-            undo_stack: Vec::new(),
-            redo_stack: Vec::new(),
+            line_height: LINE_HEIGHT,
+            view_height: 0.0,
+            recentered: None,
+            undo: UndoTree::new(Snapshot {
+                rope: rope.clone(),
+                cursors: vec![Cursor::new(0, 0)],
+            }),
+            join_undo: false,
+            group_undo: false,
+            undo_notice: None,
             last_edit_time: 0.0,
             title,
-        })
+            pinned: false,
+            preview: false,
+            shared: false,
+            encryption: None,
+            version: next_version(),
+            indent: Indent::default(),
+            indent_detected: false,
+            line_ending,
+            mixed_endings,
+            encoding,
+            overwrite: false,
+            reindent_paste: true,
+            diagnostics: Vec::new(),
+            markers: Vec::new(),
+            markers_version: 0,
+            markers_changed: false,
+            execution_line: None,
+            code_actions: None,
+            show_code_actions: false,
+            jump_query: None,
+            last_selection: None,
+            ime_preedit: None,
+        }
+    }
+
+    /// Buffer with the given content, e.g. restored from crash recovery.
+    /// `path` is only remembered for saving; it is not read.
+    pub fn from_text(path: Option<PathBuf>, text: &str) -> Self {
+        let mut editor = Self::new();
+        editor.rope = Rope::from_str(text);
+        editor.undo = UndoTree::new(editor.snapshot());
+        if let Some(path) = path {
+            editor.title = title_for(&path);
+            editor.file_path = Some(path);
+        }
+        editor
+    }
+
+    /// Scratch buffer `name` with its persisted contents.
+    pub fn scratch(name: String, text: &str) -> Self {
+        let mut editor = Self::from_text(None, text);
+        editor.title = name.clone();
+        editor.scratch = Some(name);
+        editor
+    }
+
+    /// File contents as written to disk, with the buffer's line ending and BOM.
+    pub fn serialized(&self) -> String {
+        let mut out = String::with_capacity(self.rope.len_bytes() + 3);
+        if self.encoding == Encoding::Utf8Bom {
+            out.push('\u{FEFF}');
+        }
+        match (&self.mixed_endings, self.line_ending) {
+            (Some(mixed), ending) => out.push_str(&mixed.restore(&self.rope.to_string(), ending)),
+            (None, LineEnding::Lf) => out.push_str(&self.rope.to_string()),
+            (None, LineEnding::CrLf) => out.push_str(&self.rope.to_string().replace('\n', "\r\n")),
+        }
+        out
+    }
+
+    /// The file mixes LF and CRLF line endings, which saving keeps.
+    pub fn has_mixed_line_endings(&self) -> bool {
+        self.mixed_endings.is_some()
+    }
+
+    /// End every line with `ending` from the next save on.
+    pub fn set_line_ending(&mut self, ending: LineEnding) {
+        if self.line_ending != ending || self.mixed_endings.is_some() {
+            self.line_ending = ending;
+            self.mixed_endings = None;
+            self.modified = true;
+        }
+    }
+
+    /// The bytes to write to the file: `serialized`, encrypted if the file is.
+    pub fn file_contents(&self) -> Result<Vec<u8>, std::io::Error> {
+        match &self.encryption {
+            Some(encryption) => encryption.encrypt(&self.serialized()),
+            None => Ok(self.serialized().into_bytes()),
+        }
     }
 
     pub fn save(&mut self) -> Result<(), std::io::Error> {
         if let Some(path) = &self.file_path {
-            fs::write(path, self.rope.to_string())?;
+            fs::write(path, self.file_contents()?)?;
             self.modified = false;
+            self.deleted_on_disk = false;
             Ok(())
         } else {
             Err(std::io::Error::new(
@@ -153,54 +696,132 @@ impl Editor {
     }
 
     pub fn save_as(&mut self, path: PathBuf) -> Result<(), std::io::Error> {
-        fs::write(&path, self.rope.to_string())?;
-        self.title = path
-            .file_name()
-            .map(|n| n.to_string_lossy().into_owned())
-            .unwrap_or_else(|| "Untitled".into());
+        fs::write(&path, self.file_contents()?)?;
+        self.title = title_for(&path);
         self.file_path = Some(path);
+        // A saved scratch buffer becomes an ordinary file
+        self.scratch = None;
         self.modified = false;
+        self.deleted_on_disk = false;
+        Ok(())
+    }
+
+    /// Point the buffer at `path` after its contents were written there by
+    /// something other than `save`, such as an elevated helper.
+    pub fn mark_saved(&mut self, path: PathBuf) {
+        self.title = title_for(&path);
+        self.file_path = Some(path);
+        self.scratch = None;
+        self.modified = false;
+        self.deleted_on_disk = false;
+    }
+
+    /// Move the underlying file on disk and point this buffer at the new
+    /// path. A different file already there goes to the trash.
+    pub fn rename_file(&mut self, new_path: PathBuf) -> Result<(), std::io::Error> {
+        if let Some(old) = &self.file_path {
+            file_ops::rename(old, &new_path)?;
+        }
+        self.title = title_for(&new_path);
+        self.file_path = Some(new_path);
         Ok(())
     }
 
     // --- Undo/Redo ---
 
-    fn save_undo(&mut self) {
-        self.undo_stack.push(Snapshot {
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
             rope: self.rope.clone(),
             cursors: self.cursors.clone(),
-        });
-        // Cap at 500 entries
-        if self.undo_stack.len() > 500 {
-            self.undo_stack.remove(0);
         }
-        self.redo_stack.clear();
+    }
+
+    fn restore(&mut self, snap: Snapshot) {
+        self.rope = snap.rope;
+        self.cursors = snap.cursors;
+        self.modified = true;
+        self.version = next_version();
+    }
+
+    fn save_undo(&mut self) {
+        self.version = next_version();
+        if self.group_undo || std::mem::take(&mut self.join_undo) {
+            return;
+        }
+        let state = self.snapshot();
+        self.undo.record(state);
+    }
+
+    /// Run `f` as a single undo step named `label`, however many edits it
+    /// makes, so undoing it doesn't step through intermediate states.
+    pub fn labeled_edit<T>(&mut self, label: &str, f: impl FnOnce(&mut Self) -> T) -> T {
+        if self.group_undo {
+            return f(self);
+        }
+        self.save_undo();
+        self.undo.label_pending(label);
+        self.group_undo = true;
+        let result = f(self);
+        self.group_undo = false;
+        self.undo.drop_unchanged(&self.rope);
+        result
+    }
+
+    /// Name the change just made, while it is still the pending undo step.
+    pub fn label_last_edit(&mut self, label: &str) {
+        self.undo.label_pending(label);
+    }
+
+    /// Name of the change undo would revert, if a command made it.
+    pub fn undo_label(&self) -> Option<&str> {
+        self.undo.undo_label()
+    }
+
+    /// Name of the change redo would make again, if a command made it.
+    pub fn redo_label(&self) -> Option<&str> {
+        self.undo.redo_label()
+    }
+
+    /// Fold the next edit into the current undo step, so a held key undoes
+    /// in one go rather than one repeat at a time.
+    pub fn join_next_undo(&mut self) {
+        self.join_undo = self.undo.has_pending_edit();
     }
 
     pub fn undo(&mut self) {
-        if let Some(snap) = self.undo_stack.pop() {
-            self.redo_stack.push(Snapshot {
-                rope: self.rope.clone(),
-                cursors: self.cursors.clone(),
-            });
-            self.rope = snap.rope;
-            self.cursors = snap.cursors;
-            self.modified = true;
+        let label = self.undo.undo_label().map(|l| format!("Undid {}", l));
+        if let Some(snap) = self.undo.undo(self.snapshot()) {
+            self.restore(snap);
+            self.undo_notice = label;
         }
     }
 
     pub fn redo(&mut self) {
-        if let Some(snap) = self.redo_stack.pop() {
-            self.undo_stack.push(Snapshot {
-                rope: self.rope.clone(),
-                cursors: self.cursors.clone(),
-            });
-            self.rope = snap.rope;
-            self.cursors = snap.cursors;
-            self.modified = true;
+        let label = self.undo.redo_label().map(|l| format!("Redid {}", l));
+        if let Some(snap) = self.undo.redo() {
+            self.restore(snap);
+            self.undo_notice = label;
+        }
+    }
+
+    pub fn undo_history(&self) -> &UndoTree {
+        &self.undo
+    }
+
+    /// Restore any state from the undo history, including undone branches.
+    pub fn restore_undo_state(&mut self, id: usize) {
+        if let Some(snap) = self.undo.jump_to(id, self.snapshot()) {
+            self.restore(snap);
         }
     }
 
+    /// Text between two positions, clamped to the buffer.
+    pub fn text_between(&self, start: Position, end: Position) -> String {
+        let start = pos_to_char_idx(&self.rope, &start);
+        let end = pos_to_char_idx(&self.rope, &end).max(start);
+        self.rope.slice(start..end).to_string()
+    }
+
     pub fn line_count(&self) -> usize {
         self.rope.len_lines()
     }
@@ -219,6 +840,173 @@ impl Editor {
         s
     }
 
+    /// Replace the text of one line (without its line ending) as an undoable edit.
+    pub fn set_line_text(&mut self, line: usize, text: &str) {
+        if line >= self.rope.len_lines() {
+            return;
+        }
+        self.save_undo();
+        let start = self.rope.line_to_char(line);
+        // line_text() excludes a CRLF's '\r', so it survives the replacement
+        let end = start + self.line_text(line).chars().count();
+        self.rope.remove(start..end);
+        self.rope.insert(start, text);
+        let ll = line_len_chars(&self.rope, line);
+        for cursor in self.cursors.iter_mut().filter(|c| c.pos.line == line) {
+            cursor.pos.col = cursor.pos.col.min(ll);
+        }
+        self.modified = true;
+    }
+
+    /// Put a marker of `kind` on `line`, or take it off.
+    pub fn toggle_marker(&mut self, line: usize, kind: MarkerType) {
+        if line < self.line_count() {
+            markers::toggle(&mut self.markers, &self.rope, line, kind);
+            self.markers_changed = true;
+        }
+    }
+
+    /// Toggle a marker of `kind` on each cursor's line.
+    fn toggle_cursor_markers(&mut self, kind: MarkerType) {
+        let mut lines: Vec<usize> = self.cursors.iter().map(|c| c.pos.line).collect();
+        lines.dedup();
+        for line in lines {
+            self.toggle_marker(line, kind);
+        }
+    }
+
+    pub fn toggle_bookmarks(&mut self) {
+        self.toggle_cursor_markers(MarkerType::Bookmark);
+    }
+
+    pub fn toggle_breakpoints(&mut self) {
+        self.toggle_cursor_markers(MarkerType::Breakpoint);
+    }
+
+    /// Move to the next (or previous) bookmark, wrapping around the file.
+    /// Returns false if there are none.
+    pub fn goto_bookmark(&mut self, forward: bool) -> bool {
+        match markers::next(&self.markers, MarkerType::Bookmark, self.cursors[0].pos.line, forward) {
+            Some(line) => {
+                self.goto_line(line + 1);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn clear_bookmarks(&mut self) {
+        self.markers.retain(|m| m.kind != MarkerType::Bookmark);
+        self.markers_changed = true;
+    }
+
+    /// Replace the markers with saved ones, which are checked against the
+    /// text before they are next drawn.
+    pub fn set_markers(&mut self, markers: Vec<LineMarker>) {
+        self.markers = markers;
+        self.markers_version = 0;
+        self.markers_changed = false;
+    }
+
+    /// Move the markers to follow the lines they were on, once per change.
+    pub fn relocate_markers(&mut self) {
+        if self.markers_version != self.version {
+            self.markers_version = self.version;
+            self.markers_changed |= markers::relocate(&mut self.markers, &self.rope);
+        }
+    }
+
+    /// Replace the text between two positions as an undoable edit, moving
+    /// cursors after it along with the text.
+    pub fn replace_span(&mut self, start: Position, end: Position, text: &str) {
+        self.save_undo();
+        self.replace_range(start, end, text);
+        self.sync_desired_cols();
+        self.modified = true;
+    }
+
+    /// Apply edits that all refer to the current text, as one undo step
+    /// named `label`. Positions past the end of a line or the buffer are
+    /// moved inside it.
+    pub fn apply_edits(&mut self, label: &str, edits: &[TextEdit]) {
+        let mut edits: Vec<&TextEdit> = edits.iter().collect();
+        edits.sort_by_key(|e| e.start);
+        self.labeled_edit(label, |ed| {
+            // From the end, so earlier positions stay valid
+            for edit in edits.into_iter().rev() {
+                let start = clamp_pos(&ed.rope, edit.start);
+                let end = clamp_pos(&ed.rope, edit.end).max(start);
+                ed.replace_span(start, end, &edit.text.replace("\r\n", "\n"));
+            }
+        });
+    }
+
+    /// Fixes for problems on the primary cursor's line, while the text is
+    /// what was checked, then what a language server offered there.
+    pub fn code_actions_at_cursor(&self) -> Vec<CodeAction> {
+        let pos = self.cursors[0].pos;
+        let mut actions: Vec<CodeAction> = Vec::new();
+        if !self.modified {
+            let fixes = self.diagnostics.iter().filter(|d| d.touches_line(pos.line)).flat_map(|d| &d.fixes);
+            actions.extend(fixes.cloned());
+        }
+        if let Some((version, at, offered)) = &self.code_actions {
+            if *version == self.version && *at == pos {
+                for action in offered {
+                    if !actions.contains(action) {
+                        actions.push(action.clone());
+                    }
+                }
+            }
+        }
+        actions
+    }
+
+    pub fn has_code_actions(&self) -> bool {
+        let line = self.cursors[0].pos.line;
+        (!self.modified && self.diagnostics.iter().any(|d| d.touches_line(line) && !d.fixes.is_empty()))
+            || self.code_actions.as_ref().is_some_and(|(version, at, offered)| {
+                *version == self.version && *at == self.cursors[0].pos && !offered.is_empty()
+            })
+    }
+
+    /// Number of selections and the total characters and lines they span,
+    /// or None when nothing is selected.
+    pub fn selection_stats(&self) -> Option<(usize, usize, usize)> {
+        let mut count = 0;
+        let mut chars = 0;
+        let mut lines = 0;
+        for cursor in &self.cursors {
+            if let Some((start, end)) = cursor.selection_ordered() {
+                if start == end {
+                    continue;
+                }
+                count += 1;
+                chars += pos_to_char_idx(&self.rope, &end) - pos_to_char_idx(&self.rope, &start);
+                lines += end.line - start.line + 1;
+            }
+        }
+        (count > 0).then_some((count, chars, lines))
+    }
+
+    pub fn statistics(&self) -> Stats {
+        Stats::of(self.rope.chars())
+    }
+
+    /// Statistics of all selections together; None without a selection.
+    pub fn selection_statistics(&self) -> Option<Stats> {
+        let mut total = None;
+        for cursor in &self.cursors {
+            if let Some((start, end)) = cursor.selection_ordered() {
+                let (s, e) = (pos_to_char_idx(&self.rope, &start), pos_to_char_idx(&self.rope, &end));
+                if s < e {
+                    total.get_or_insert_with(Stats::default).add(&Stats::of(self.rope.slice(s..e).chars()));
+                }
+            }
+        }
+        total
+    }
+
     // --- Editing operations ---
 
     /// Indices sorted in reverse document order for safe multi-cursor edits.
@@ -235,11 +1023,8 @@ impl Editor {
     fn delete_selection_at(&mut self, idx: usize) -> bool {
         let sel = self.cursors[idx].selection_ordered();
         if let Some((start, end)) = sel {
-            let start_ci = pos_to_char_idx(&self.rope, &start);
-            let end_ci = pos_to_char_idx(&self.rope, &end);
-            if start_ci < end_ci {
-                self.rope.remove(start_ci..end_ci);
-            }
+            let (start, end) = (clamp_pos(&self.rope, start), clamp_pos(&self.rope, end));
+            self.replace_range(start, end, "");
             self.cursors[idx].pos = start;
             self.cursors[idx].anchor = None;
             self.cursors[idx].desired_col = start.col;
@@ -251,21 +1036,145 @@ impl Editor {
 
     pub fn insert_text(&mut self, text: &str) {
         self.save_undo();
+        self.insert_at_cursors(text);
+    }
+
+    /// Paste `content`. With a piece for each of several cursors, each
+    /// cursor gets its own; otherwise every cursor gets all of it. Whole
+    /// lines go above a cursor's line, other text replaces its selection.
+    pub fn paste(&mut self, content: &ClipboardContent) {
+        let selection = self.cursors.iter().any(|c| c.selection_ordered().is_some());
+        let per_cursor = self.cursors.len() > 1 && content.parts.len() == self.cursors.len();
+        if content.linewise && !selection && (per_cursor || self.cursors.len() > 1) {
+            let text = content.text();
+            self.labeled_edit("Paste", |ed| {
+                let order = ed.sorted_cursor_indices_rev();
+                let count = order.len();
+                for (i, &idx) in order.iter().enumerate() {
+                    let lines = if per_cursor { &content.parts[count - 1 - i] } else { &text };
+                    let pos = clamp_pos(&ed.rope, ed.cursors[idx].pos);
+                    let start = Position::new(pos.line, 0);
+                    ed.replace_span(start, start, lines);
+                    ed.cursors[idx].pos = Position::new(pos.line + lines.matches('\n').count(), pos.col);
+                }
+            });
+        } else if per_cursor {
+            // A selection takes a whole line's text, without its newline
+            let parts: Vec<&str> = content
+                .parts
+                .iter()
+                .map(|part| if content.linewise { part.strip_suffix('\n').unwrap_or(part) } else { part })
+                .collect();
+            self.insert_each(|n| parts[n].to_string());
+        } else {
+            self.paste_text(&content.text(), content.linewise && !selection);
+        }
+    }
+
+    /// Paste `text` at the cursors, or above the lone cursor's line when it
+    /// is `linewise`. A multi-line paste at a lone cursor is re-indented to
+    /// the cursor's line when `reindent_paste` is on, keeping the lines'
+    /// indentation relative to each other; whole lines, ending in a
+    /// newline, go above the line when the cursor is in its indentation.
+    fn paste_text(&mut self, text: &str, linewise: bool) {
+        if !linewise && (!self.reindent_paste || !text.contains('\n') || self.cursors.len() > 1) {
+            self.insert_text(text);
+            return;
+        }
+        self.labeled_edit("Paste", |ed| {
+            ed.delete_selection_at(0);
+            let pos = clamp_pos(&ed.rope, ed.cursors[0].pos);
+            let line = ed.line_text(pos.line);
+            let before: String = line.chars().take(pos.col).collect();
+            let target: String = if line.trim().is_empty() {
+                before.clone()
+            } else {
+                line.chars().take_while(|c| *c == ' ' || *c == '\t').collect()
+            };
+            let in_indent = before.trim().is_empty();
+            let above = linewise || (in_indent && text.ends_with('\n'));
+            let (start, end) = if above {
+                (Position::new(pos.line, 0), Position::new(pos.line, 0))
+            } else if !in_indent {
+                (pos, pos)
+            } else {
+                // Over the line's indentation, so the last line joins its text
+                let indent_end = target.chars().count().max(pos.col);
+                (Position::new(pos.line, 0), Position::new(pos.line, indent_end))
+            };
+            let pasted = if ed.reindent_paste { reindent(text, &target, in_indent || above) } else { text.to_string() };
+            ed.replace_span(start, end, &pasted);
+            let lines = pasted.matches('\n').count();
+            let cursor = if above {
+                // The cursor stays on its line, now below the pasted ones
+                Position::new(pos.line + lines, pos.col)
+            } else {
+                let last = pasted.rsplit('\n').next().unwrap_or("").chars().count();
+                Position::new(start.line + lines, last)
+            };
+            ed.cursors = vec![Cursor::new(cursor.line, cursor.col)];
+        });
+    }
+
+    /// Typed input: inserts, or in overwrite mode replaces the characters
+    /// under each cursor (never past the end of the line). A typed closing
+    /// bracket or block word alone on its line is dedented.
+    pub fn type_text(&mut self, text: &str) {
+        if !self.overwrite || text.contains('\n') {
+            self.insert_text(text);
+            self.auto_dedent();
+            return;
+        }
+        self.save_undo();
+        let n = text.chars().count();
         let order = self.sorted_cursor_indices_rev();
         for &idx in &order {
+            if self.delete_selection_at(idx) {
+                continue;
+            }
+            let pos = clamp_pos(&self.rope, self.cursors[idx].pos);
+            let remaining = line_len_chars(&self.rope, pos.line) - pos.col;
+            let remove = n.min(remaining);
+            if remove > 0 {
+                self.replace_range(pos, Position::new(pos.line, pos.col + remove), "");
+            }
+        }
+        self.insert_at_cursors(text);
+        self.auto_dedent();
+    }
+
+    /// Insert `text_for(n)` at each cursor, replacing its selection, where
+    /// `n` counts the cursors from 0 in document order.
+    pub fn insert_each(&mut self, text_for: impl FnMut(usize) -> String) {
+        self.save_undo();
+        self.insert_at_cursors_with(text_for);
+    }
+
+    fn insert_at_cursors(&mut self, text: &str) {
+        self.insert_at_cursors_with(|_| text.to_string());
+    }
+
+    fn insert_at_cursors_with(&mut self, mut text_for: impl FnMut(usize) -> String) {
+        let order = self.sorted_cursor_indices_rev();
+        let count = order.len();
+        for (i, &idx) in order.iter().enumerate() {
+            let text = text_for(count - 1 - i);
+            let text = text.as_str();
             self.delete_selection_at(idx);
-            let ci = pos_to_char_idx(&self.rope, &self.cursors[idx].pos);
-            self.rope.insert(ci, text);
+            let pos = clamp_pos(&self.rope, self.cursors[idx].pos);
+            self.replace_range(pos, pos, text);
 
             let newlines: usize = text.chars().filter(|&c| c == '\n').count();
+            let cursor = &mut self.cursors[idx];
+            cursor.pos = pos;
             if newlines > 0 {
-                self.cursors[idx].pos.line += newlines;
+                cursor.pos.line += newlines;
                 let last_segment = text.rsplit('\n').next().unwrap_or("");
-                self.cursors[idx].pos.col = last_segment.chars().count();
+                cursor.pos.col = last_segment.chars().count();
             } else {
-                self.cursors[idx].pos.col += text.chars().count();
+                cursor.pos.col += text.chars().count();
             }
-            self.cursors[idx].desired_col = self.cursors[idx].pos.col;
+            cursor.desired_col = cursor.pos.col;
         }
         self.modified = true;
     }
@@ -277,23 +1186,14 @@ impl Editor {
             if self.delete_selection_at(idx) {
                 continue;
             }
-            let pos = &self.cursors[idx].pos;
-            if pos.line == 0 && pos.col == 0 {
-                continue;
-            }
-            let ci = pos_to_char_idx(&self.rope, pos);
+            let ci = pos_to_char_idx(&self.rope, &self.cursors[idx].pos);
             if ci == 0 {
                 continue;
             }
-            self.rope.remove(ci - 1..ci);
-
-            if self.cursors[idx].pos.col == 0 {
-                self.cursors[idx].pos.line -= 1;
-                self.cursors[idx].pos.col = line_len_chars(&self.rope, self.cursors[idx].pos.line);
-            } else {
-                self.cursors[idx].pos.col -= 1;
-            }
-            self.cursors[idx].desired_col = self.cursors[idx].pos.col;
+            let start = char_idx_to_pos(&self.rope, ci - 1);
+            self.replace_range(start, char_idx_to_pos(&self.rope, ci), "");
+            self.cursors[idx].pos = start;
+            self.cursors[idx].desired_col = start.col;
         }
         self.modified = true;
     }
@@ -309,36 +1209,235 @@ impl Editor {
             if ci >= self.rope.len_chars() {
                 continue;
             }
-            self.rope.remove(ci..ci + 1);
+            let start = char_idx_to_pos(&self.rope, ci);
+            self.replace_range(start, char_idx_to_pos(&self.rope, ci + 1), "");
         }
         self.modified = true;
     }
 
-    pub fn insert_newline(&mut self) {
-        // Auto-indent: match previous line indentation and add extra for openers
-        let line = self.cursors[0].pos.line;
-        let line_text = self.line_text(line);
-        let indent: String = line_text.chars().take_while(|c| c.is_whitespace()).collect();
-
-        let trimmed = line_text.trim_end();
-        let extra_indent = if trimmed.ends_with('{')
-            || trimmed.ends_with('(')
-            || trimmed.ends_with('[')
-            || trimmed.ends_with(':')
-        {
-            "    "
+    /// Break the line at each cursor, indenting the new line like the text
+    /// before the cursor: a level deeper after a block opener, a level
+    /// shallower after a statement that ends its block (like Python's
+    /// `return`). Between a bracket pair, like `{|}`, the cursor gets an
+    /// indented empty line and the closer a line of its own at the opener's
+    /// indentation.
+    pub fn insert_newline(&mut self) {
+        self.insert_newline_continuing(|_, _| false);
+    }
+
+    /// `insert_newline`, except that a cursor on a comment line, where
+    /// `in_comment` says the text is a comment, carries the comment on to
+    /// the new line. Enter on a comment line with nothing written after the
+    /// prefix removes the prefix instead, ending the comment.
+    pub fn insert_newline_continuing(&mut self, in_comment: impl Fn(&Rope, Position) -> bool) {
+        self.save_undo();
+        let rules = syntax::indent_rules_for(self.file_path.as_deref());
+        let line_comment = syntax::line_comment_for(self.file_path.as_deref());
+        let unit = self.indent.unit();
+        for idx in self.sorted_cursor_indices_rev() {
+            self.delete_selection_at(idx);
+            let mut pos = clamp_pos(&self.rope, self.cursors[idx].pos);
+            let line_text = self.line_text(pos.line);
+            let mut before: String = line_text.chars().take(pos.col).collect();
+            let after: String = line_text.chars().skip(pos.col).collect();
+            let mut indent: String = before.chars().take_while(|c| c.is_whitespace()).collect();
+
+            let continuation = comment_continuation(&before, &after, line_comment)
+                .filter(|_| in_comment(&self.rope, pos));
+            if let Some(continuation) = continuation {
+                let indent_len = indent.chars().count();
+                let (start, end, text, col) = match continuation {
+                    CommentContinuation::Prefix(prefix) => {
+                        let text = format!("\n{}{}", indent, prefix);
+                        (pos, pos, text, indent_len + prefix.chars().count())
+                    }
+                    CommentContinuation::End => {
+                        let end = Position::new(pos.line, line_text.chars().count());
+                        (Position::new(pos.line, indent_len), end, String::new(), indent_len)
+                    }
+                };
+                self.replace_range(start, end, &text);
+                let line = pos.line + text.contains('\n') as usize;
+                let cursor = &mut self.cursors[idx];
+                cursor.pos = Position::new(line, col);
+                cursor.desired_col = col;
+                continue;
+            }
+
+            // Enter completes a block-closing word typed alone on its line
+            let closer = rules.closer_end.is_none() && rules.closers.contains(&before.trim());
+            if let Some(target) = self.closer_indent(pos.line, &rules).filter(|t| closer && t.len() < indent.len()) {
+                self.cursors[idx].pos = pos;
+                self.reindent_line(idx, &indent, &target);
+                pos = self.cursors[idx].pos;
+                before = format!("{}{}", target, before.trim_start());
+                indent = target;
+            }
+            let trimmed = before.trim_end();
+            let last_word = last_word(trimmed);
+            let first_word = trimmed.split(|c: char| !c.is_alphanumeric() && c != '_').find(|w| !w.is_empty());
+            let opens = trimmed.ends_with(['{', '(', '[', ':']) || rules.openers.contains(&last_word);
+            let mut new_indent = indent.clone();
+            if opens {
+                new_indent.push_str(&unit);
+            } else if first_word.is_some_and(|w| rules.block_enders.contains(&w)) {
+                new_indent = self.dedent(&indent);
+            }
+
+            let mut text = format!("\n{}", new_indent);
+            let closer = match trimmed.chars().last() {
+                Some('{') => Some('}'),
+                Some('(') => Some(')'),
+                Some('[') => Some(']'),
+                _ => None,
+            };
+            // Spaces around the cursor would be left trailing the opener
+            // and indenting the closer
+            let mut start = pos;
+            let mut end = pos;
+            if closer.is_some_and(|c| after.trim_start().starts_with(c)) {
+                text.push('\n');
+                text.push_str(&indent);
+                start.col = trimmed.chars().count();
+                end.col += after.chars().take_while(|c| c.is_whitespace()).count();
+            }
+            self.replace_range(start, end, &text);
+            let cursor = &mut self.cursors[idx];
+            cursor.pos = Position::new(pos.line + 1, new_indent.chars().count());
+            cursor.desired_col = cursor.pos.col;
+        }
+        self.modified = true;
+    }
+
+    /// `indent` one level shallower.
+    fn dedent(&self, indent: &str) -> String {
+        let unit = self.indent.unit();
+        if let Some(rest) = indent.strip_suffix(unit.as_str()) {
+            return rest.to_string();
+        }
+        if let Some(rest) = indent.strip_suffix('\t') {
+            return rest.to_string();
+        }
+        let spaces = indent.chars().rev().take(self.indent.width).take_while(|&c| c == ' ').count();
+        indent[..indent.len() - spaces].to_string()
+    }
+
+    /// After typing, move a line back when it now holds only a closing
+    /// bracket (to its opener's indentation) or a block-closing word like
+    /// `end` or `else` just completed (a level under the line before).
+    fn auto_dedent(&mut self) {
+        let rules = syntax::indent_rules_for(self.file_path.as_deref());
+        for idx in 0..self.cursors.len() {
+            let pos = self.cursors[idx].pos;
+            let line_text = self.line_text(pos.line);
+            let indent: String = line_text.chars().take_while(|c| c.is_whitespace()).collect();
+            let content = &line_text[indent.len()..];
+            if pos.line == 0 || indent.is_empty() || pos.col != line_text.chars().count() {
+                continue;
+            }
+            let target = match content {
+                "}" | ")" | "]" => {
+                    let closer_idx = pos_to_char_idx(&self.rope, &pos) - 1;
+                    match self.matching_opener_line(closer_idx) {
+                        Some(line) => self.line_text(line).chars().take_while(|c| c.is_whitespace()).collect(),
+                        None => continue,
+                    }
+                }
+                _ if completes_closer(&rules, content) => match self.closer_indent(pos.line, &rules) {
+                    Some(target) => target,
+                    None => continue,
+                },
+                _ => continue,
+            };
+            self.reindent_line(idx, &indent, &target);
+        }
+    }
+
+    /// The indentation for a block-closing word on `line`: a level under
+    /// the nearest non-blank line above, or level with it when that line
+    /// opens a block.
+    fn closer_indent(&self, line: usize, rules: &syntax::IndentRules) -> Option<String> {
+        let prev = (0..line).rev().map(|l| self.line_text(l)).find(|t| !t.trim().is_empty())?;
+        let prev_indent: String = prev.chars().take_while(|c| c.is_whitespace()).collect();
+        let prev = prev.trim_end();
+        if prev.ends_with(['{', '(', '[', ':']) || rules.openers.contains(&last_word(prev)) {
+            Some(prev_indent)
+        } else {
+            Some(self.dedent(&prev_indent))
+        }
+    }
+
+    /// Replace the leading `indent` of the line under cursor `idx` with
+    /// `target` if that moves it back, keeping the cursor on its text.
+    fn reindent_line(&mut self, idx: usize, indent: &str, target: &str) {
+        let pos = self.cursors[idx].pos;
+        let (old_len, new_len) = (indent.chars().count(), target.chars().count());
+        if new_len >= old_len {
+            return;
+        }
+        self.replace_range(Position::new(pos.line, 0), Position::new(pos.line, old_len), target);
+        let cursor = &mut self.cursors[idx];
+        cursor.pos.col = pos.col.saturating_sub(old_len) + new_len;
+        cursor.desired_col = cursor.pos.col;
+    }
+
+
+    /// Line of the bracket that the closing bracket at char `closer` pairs
+    /// with, looking back a limited distance.
+    fn matching_opener_line(&self, closer: usize) -> Option<usize> {
+        let close = self.rope.char(closer);
+        let open = match close {
+            '}' => '{',
+            ')' => '(',
+            ']' => '[',
+            _ => return None,
+        };
+        let mut depth = 0usize;
+        let start = closer.saturating_sub(MATCH_BRACKET_CHARS);
+        for idx in (start..closer).rev() {
+            let c = self.rope.char(idx);
+            if c == close {
+                depth += 1;
+            } else if c == open {
+                if depth == 0 {
+                    return Some(self.rope.char_to_line(idx));
+                }
+                depth -= 1;
+            }
+        }
+        None
+    }
+
+    pub fn insert_tab(&mut self) {
+        let unit = self.indent.unit();
+        self.insert_text(&unit);
+    }
+
+    /// Expand the Emmet abbreviation before a lone cursor in an HTML or CSS
+    /// file, putting the cursor at the first place to type. False when
+    /// there is none, so Tab can indent instead.
+    pub fn expand_emmet(&mut self) -> bool {
+        let Some(syntax) = emmet::syntax_for(self.file_path.as_deref()) else {
+            return false;
+        };
+        if self.cursors.len() > 1 || self.cursors[0].anchor.is_some() {
+            return false;
+        }
+        let pos = self.cursors[0].pos;
+        let line = self.line_text(pos.line);
+        let Some(expansion) = emmet::expand(syntax, &line, pos.col, &self.indent.unit()) else {
+            return false;
+        };
+        let start = Position::new(pos.line, expansion.start);
+        self.labeled_edit("Expand Abbreviation", |ed| ed.replace_span(start, pos, &expansion.text));
+        let offset = edits::offset_to_position(&expansion.text, expansion.cursor);
+        let cursor = if offset.line == 0 {
+            Position::new(pos.line, expansion.start + offset.col)
         } else {
-            ""
+            Position::new(pos.line + offset.line, offset.col)
         };
-
-        let mut newline = String::from("\n");
-        newline.push_str(&indent);
-        newline.push_str(extra_indent);
-        self.insert_text(&newline);
-    }
-
-    pub fn insert_tab(&mut self) {
-        self.insert_text("    ");
+        self.cursors = vec![Cursor::new(cursor.line, cursor.col)];
+        true
     }
 
     // --- Cursor movement ---
@@ -347,11 +1446,11 @@ impl Editor {
         let rope = &self.rope;
         for cursor in &mut self.cursors {
             if select && cursor.anchor.is_none() {
-                cursor.anchor = Some(cursor.pos.clone());
+                cursor.anchor = Some(cursor.pos);
             } else if !select {
                 // If there's a selection and not extending, collapse to start
                 if let Some(anchor) = cursor.anchor.take() {
-                    cursor.pos = cursor.pos.clone().min(anchor);
+                    cursor.pos = cursor.pos.min(anchor);
                     cursor.desired_col = cursor.pos.col;
                     continue;
                 }
@@ -371,10 +1470,10 @@ impl Editor {
         let rope = &self.rope;
         for cursor in &mut self.cursors {
             if select && cursor.anchor.is_none() {
-                cursor.anchor = Some(cursor.pos.clone());
+                cursor.anchor = Some(cursor.pos);
             } else if !select {
                 if let Some(anchor) = cursor.anchor.take() {
-                    cursor.pos = cursor.pos.clone().max(anchor);
+                    cursor.pos = cursor.pos.max(anchor);
                     cursor.desired_col = cursor.pos.col;
                     continue;
                 }
@@ -395,7 +1494,7 @@ impl Editor {
         let rope = &self.rope;
         for cursor in &mut self.cursors {
             if select && cursor.anchor.is_none() {
-                cursor.anchor = Some(cursor.pos.clone());
+                cursor.anchor = Some(cursor.pos);
             } else if !select {
                 cursor.anchor = None;
             }
@@ -412,7 +1511,7 @@ impl Editor {
         let rope = &self.rope;
         for cursor in &mut self.cursors {
             if select && cursor.anchor.is_none() {
-                cursor.anchor = Some(cursor.pos.clone());
+                cursor.anchor = Some(cursor.pos);
             } else if !select {
                 cursor.anchor = None;
             }
@@ -428,7 +1527,7 @@ impl Editor {
     pub fn move_home(&mut self, select: bool) {
         for cursor in &mut self.cursors {
             if select && cursor.anchor.is_none() {
-                cursor.anchor = Some(cursor.pos.clone());
+                cursor.anchor = Some(cursor.pos);
             } else if !select {
                 cursor.anchor = None;
             }
@@ -441,7 +1540,7 @@ impl Editor {
         let rope = &self.rope;
         for cursor in &mut self.cursors {
             if select && cursor.anchor.is_none() {
-                cursor.anchor = Some(cursor.pos.clone());
+                cursor.anchor = Some(cursor.pos);
             } else if !select {
                 cursor.anchor = None;
             }
@@ -479,6 +1578,49 @@ impl Editor {
         }
     }
 
+    /// Scroll the cursor's line to the middle of the view; repeated without
+    /// moving the cursor, to the top and then the bottom.
+    pub fn center_cursor(&mut self) {
+        let pos = self.cursors[0].pos;
+        let step = match self.recentered {
+            Some((at, scroll, step)) if at == pos && scroll == self.scroll_y => (step + 1) % 3,
+            _ => 0,
+        };
+        let top = pos.line as f32 * self.line_height;
+        let room = (self.view_height - self.line_height).max(0.0);
+        self.scroll_y = match step {
+            0 => top - room / 2.0,
+            1 => top,
+            _ => top - room,
+        }
+        .max(0.0);
+        self.recentered = Some((pos, self.scroll_y, step));
+    }
+
+    /// Scroll the view by whole lines, up for negative `lines`, leaving the
+    /// cursors where they are.
+    pub fn scroll_lines(&mut self, lines: isize) {
+        let first = (self.scroll_y / self.line_height).round() as isize;
+        let last = self.line_count().saturating_sub(1) as isize;
+        self.scroll_y = (first + lines).clamp(0, last) as f32 * self.line_height;
+    }
+
+    /// Move the cursor to the first (or last) line wholly in view.
+    pub fn move_to_view_edge(&mut self, top: bool, select: bool) {
+        let first = (self.scroll_y / self.line_height).ceil() as usize;
+        let last = ((self.scroll_y + self.view_height) / self.line_height).floor() as usize;
+        let line = if top { first } else { last.saturating_sub(1).max(first) };
+        let line = line.min(self.line_count().saturating_sub(1));
+        self.cursors.truncate(1);
+        let cursor = &mut self.cursors[0];
+        if select && cursor.anchor.is_none() {
+            cursor.anchor = Some(cursor.pos);
+        } else if !select {
+            cursor.anchor = None;
+        }
+        cursor.pos = Position::new(line, cursor.desired_col.min(line_len_chars(&self.rope, line)));
+    }
+
     pub fn move_to_start(&mut self, select: bool) {
         for cursor in &mut self.cursors {
             if select && cursor.anchor.is_none() {
@@ -527,11 +1669,11 @@ impl Editor {
                 }
             } else {
                 // Skip whitespace backwards
-                while col > 0 && chars.get(col - 1).map_or(false, |c| !c.is_alphanumeric() && *c != '_') {
+                while col > 0 && chars.get(col - 1).is_some_and(|c| !c.is_alphanumeric() && *c != '_') {
                     col -= 1;
                 }
                 // Skip word chars backwards
-                while col > 0 && chars.get(col - 1).map_or(false, |c| c.is_alphanumeric() || *c == '_') {
+                while col > 0 && chars.get(col - 1).is_some_and(|c| c.is_alphanumeric() || *c == '_') {
                     col -= 1;
                 }
                 cursor.pos.col = col;
@@ -567,83 +1709,822 @@ impl Editor {
                 while col < chars.len() && !chars[col].is_alphanumeric() && chars[col] != '_' {
                     col += 1;
                 }
-                cursor.pos.col = col.min(ll);
+                cursor.pos.col = col.min(ll);
+            }
+            cursor.desired_col = cursor.pos.col;
+        }
+    }
+
+    // --- Sub-word movement ---
+
+    /// Start of the camelCase hump or snake_case part before `pos`, like
+    /// `foo|Bar` or `foo_|bar`. `HTTPServer` splits as `HTTP|Server`.
+    fn subword_start_before(&self, pos: Position) -> Position {
+        if pos.col == 0 {
+            return if pos.line > 0 { self.line_end(pos.line - 1) } else { pos };
+        }
+        let chars: Vec<char> = self.line_text(pos.line).chars().collect();
+        let mut col = pos.col.min(chars.len());
+        while col > 0 && !chars[col - 1].is_alphanumeric() {
+            col -= 1;
+        }
+        let end = col;
+        while col > 0 && (chars[col - 1].is_lowercase() || chars[col - 1].is_numeric()) {
+            col -= 1;
+        }
+        if col == end {
+            // An all-caps run
+            while col > 0 && chars[col - 1].is_uppercase() {
+                col -= 1;
+            }
+        } else if col > 0 && chars[col - 1].is_uppercase() {
+            col -= 1;
+        }
+        // Letters without case, as in CJK text
+        if col == end {
+            while col > 0 && chars[col - 1].is_alphanumeric() {
+                col -= 1;
+            }
+        }
+        Position::new(pos.line, col)
+    }
+
+    /// Start of the camelCase hump or snake_case part after `pos`.
+    fn subword_start_after(&self, pos: Position) -> Position {
+        let chars: Vec<char> = self.line_text(pos.line).chars().collect();
+        if pos.col >= chars.len() {
+            return if pos.line + 1 < self.rope.len_lines() { Position::new(pos.line + 1, 0) } else { pos };
+        }
+        let mut col = pos.col;
+        let start = col;
+        while col < chars.len() && chars[col].is_uppercase() {
+            col += 1;
+        }
+        // The last capital of `HTTPServer` starts the next hump
+        if col - start > 1 && chars.get(col).is_some_and(|c| c.is_lowercase()) {
+            col -= 1;
+        } else {
+            while col < chars.len() && (chars[col].is_lowercase() || chars[col].is_numeric()) {
+                col += 1;
+            }
+        }
+        if col == start {
+            while col < chars.len() && chars[col].is_alphanumeric() && !chars[col].is_uppercase() {
+                col += 1;
+            }
+        }
+        while col < chars.len() && !chars[col].is_alphanumeric() {
+            col += 1;
+        }
+        Position::new(pos.line, col)
+    }
+
+    pub fn move_subword_left(&mut self, select: bool) {
+        self.move_cursors_to(select, Self::subword_start_before);
+    }
+
+    pub fn move_subword_right(&mut self, select: bool) {
+        self.move_cursors_to(select, Self::subword_start_after);
+    }
+
+    pub fn delete_subword_backward(&mut self) {
+        self.delete_to(Self::subword_start_before);
+    }
+
+    pub fn delete_subword_forward(&mut self) {
+        self.delete_to(Self::subword_start_after);
+    }
+
+    /// Delete each selection, or for cursors without one, the text between
+    /// the cursor and `target` of its position.
+    fn delete_to(&mut self, target: impl Fn(&Self, Position) -> Position) {
+        self.save_undo();
+        for idx in self.sorted_cursor_indices_rev() {
+            if self.delete_selection_at(idx) {
+                continue;
+            }
+            let pos = clamp_pos(&self.rope, self.cursors[idx].pos);
+            let other = target(self, pos);
+            let (start, end) = if other < pos { (other, pos) } else { (pos, other) };
+            self.replace_range(start, end, "");
+            self.cursors[idx].pos = start;
+            self.cursors[idx].desired_col = start.col;
+        }
+        self.modified = true;
+    }
+
+    // --- Paragraph and sentence movement ---
+
+    fn is_blank_line(&self, line: usize) -> bool {
+        self.rope.line(line).chars().all(char::is_whitespace)
+    }
+
+    /// Start of the paragraph before `pos`: of its own paragraph if `pos`
+    /// is inside one, else of the previous one.
+    fn paragraph_start_before(&self, pos: Position) -> Position {
+        let mut line = pos.line;
+        if pos.col == 0 || self.is_blank_line(line) {
+            if line == 0 {
+                return Position::new(0, 0);
+            }
+            line -= 1;
+        }
+        while line > 0 && self.is_blank_line(line) {
+            line -= 1;
+        }
+        while line > 0 && !self.is_blank_line(line - 1) {
+            line -= 1;
+        }
+        Position::new(line, 0)
+    }
+
+    /// Start of the next paragraph after `pos`, or the end of the buffer.
+    fn paragraph_start_after(&self, pos: Position) -> Position {
+        let lines = self.rope.len_lines();
+        let mut line = pos.line;
+        while line < lines && !self.is_blank_line(line) {
+            line += 1;
+        }
+        while line < lines && self.is_blank_line(line) {
+            line += 1;
+        }
+        if line < lines {
+            Position::new(line, 0)
+        } else {
+            char_idx_to_pos(&self.rope, self.rope.len_chars())
+        }
+    }
+
+    /// Move each cursor to the start of its paragraph, or of the previous
+    /// one when already there. Paragraphs are separated by blank lines.
+    pub fn move_paragraph_up(&mut self, select: bool) {
+        self.move_cursors_to(select, |editor, pos| editor.paragraph_start_before(pos));
+    }
+
+    pub fn move_paragraph_down(&mut self, select: bool) {
+        self.move_cursors_to(select, |editor, pos| editor.paragraph_start_after(pos));
+    }
+
+    /// Whether a sentence starts at char `idx`: the first text after a
+    /// sentence-ending `.`, `!` or `?` (and closing quotes) and a space, a
+    /// CJK full stop, a blank line or the start of the buffer.
+    fn is_sentence_start(&self, idx: usize) -> bool {
+        let rope = &self.rope;
+        let c = rope.char(idx);
+        if c.is_whitespace() {
+            return false;
+        }
+        let mut before = idx;
+        let mut newlines = 0;
+        while before > 0 && rope.char(before - 1).is_whitespace() {
+            newlines += (rope.char(before - 1) == '\n') as usize;
+            before -= 1;
+        }
+        let spaced = before < idx;
+        if before == 0 || newlines >= 2 {
+            return true;
+        }
+        let is_closer = |c: char| matches!(c, '"' | '\'' | ')' | ']' | '\u{201d}' | '\u{2019}' | '\u{300d}' | '\u{300f}');
+        if !spaced && is_closer(c) {
+            return false;
+        }
+        while before > 0 && is_closer(rope.char(before - 1)) {
+            before -= 1;
+        }
+        match before.checked_sub(1).map(|i| rope.char(i)) {
+            Some('.' | '!' | '?' | '\u{2026}') => spaced,
+            Some('\u{3002}' | '\u{ff01}' | '\u{ff1f}') => true,
+            _ => false,
+        }
+    }
+
+    fn sentence_start_before(&self, pos: Position) -> Position {
+        let mut idx = pos_to_char_idx(&self.rope, &pos);
+        while idx > 0 {
+            idx -= 1;
+            if self.is_sentence_start(idx) {
+                break;
+            }
+        }
+        char_idx_to_pos(&self.rope, idx)
+    }
+
+    fn sentence_start_after(&self, pos: Position) -> Position {
+        let len = self.rope.len_chars();
+        let mut idx = pos_to_char_idx(&self.rope, &pos);
+        while idx < len {
+            idx += 1;
+            if idx < len && self.is_sentence_start(idx) {
+                break;
+            }
+        }
+        char_idx_to_pos(&self.rope, idx)
+    }
+
+    /// Move each cursor to the start of its sentence, or of the previous
+    /// one when already there.
+    pub fn move_sentence_backward(&mut self, select: bool) {
+        self.move_cursors_to(select, |editor, pos| editor.sentence_start_before(pos));
+    }
+
+    pub fn move_sentence_forward(&mut self, select: bool) {
+        self.move_cursors_to(select, |editor, pos| editor.sentence_start_after(pos));
+    }
+
+    /// Move every cursor to `target(pos)`, extending its selection or
+    /// dropping it.
+    fn move_cursors_to(&mut self, select: bool, target: impl Fn(&Self, Position) -> Position) {
+        for idx in 0..self.cursors.len() {
+            let pos = target(self, self.cursors[idx].pos);
+            let cursor = &mut self.cursors[idx];
+            if select && cursor.anchor.is_none() {
+                cursor.anchor = Some(cursor.pos);
+            } else if !select {
+                cursor.anchor = None;
+            }
+            cursor.pos = pos;
+            cursor.desired_col = pos.col;
+        }
+    }
+
+    /// Select the paragraph around each cursor, through the newline after
+    /// it. Cursors on blank lines are left alone.
+    pub fn select_paragraph(&mut self) {
+        for idx in 0..self.cursors.len() {
+            let line = self.cursors[idx].pos.line;
+            if self.is_blank_line(line) {
+                continue;
+            }
+            let mut first = line;
+            while first > 0 && !self.is_blank_line(first - 1) {
+                first -= 1;
+            }
+            let mut end = line + 1;
+            while end < self.rope.len_lines() && !self.is_blank_line(end) {
+                end += 1;
+            }
+            let end = if end < self.rope.len_lines() {
+                Position::new(end, 0)
+            } else {
+                char_idx_to_pos(&self.rope, self.rope.len_chars())
+            };
+            let cursor = &mut self.cursors[idx];
+            cursor.anchor = Some(Position::new(first, 0));
+            cursor.pos = end;
+            cursor.desired_col = end.col;
+        }
+        // Cursors in the same paragraph now select the same text
+        let mut seen = Vec::new();
+        self.cursors.retain(|c| {
+            let key = (c.anchor, c.pos);
+            let new = !seen.contains(&key);
+            seen.push(key);
+            new
+        });
+    }
+
+    pub fn delete_word_backward(&mut self) {
+        self.save_undo();
+        let order = self.sorted_cursor_indices_rev();
+        for &idx in &order {
+            if self.delete_selection_at(idx) {
+                continue;
+            }
+            let pos = clamp_pos(&self.rope, self.cursors[idx].pos);
+            if pos.line == 0 && pos.col == 0 {
+                continue;
+            }
+            let start = if pos.col == 0 {
+                // Merge with previous line
+                self.line_end(pos.line - 1)
+            } else {
+                // Find word start
+                let chars: Vec<char> = self.line_text(pos.line).chars().collect();
+                let mut col = pos.col;
+                while col > 0 && chars.get(col - 1).is_some_and(|c| !c.is_alphanumeric() && *c != '_') {
+                    col -= 1;
+                }
+                while col > 0 && chars.get(col - 1).is_some_and(|c| c.is_alphanumeric() || *c == '_') {
+                    col -= 1;
+                }
+                Position::new(pos.line, col)
+            };
+            self.replace_range(start, pos, "");
+            self.cursors[idx].pos = start;
+            self.cursors[idx].desired_col = start.col;
+        }
+        self.modified = true;
+    }
+
+    pub fn delete_word_forward(&mut self) {
+        self.save_undo();
+        let order = self.sorted_cursor_indices_rev();
+        for &idx in &order {
+            if self.delete_selection_at(idx) {
+                continue;
+            }
+            let pos = clamp_pos(&self.rope, self.cursors[idx].pos);
+            let ll = line_len_chars(&self.rope, pos.line);
+            let end = if pos.col >= ll {
+                // Merge with next line
+                if pos.line + 1 >= self.rope.len_lines() {
+                    continue;
+                }
+                Position::new(pos.line + 1, 0)
+            } else {
+                let chars: Vec<char> = self.line_text(pos.line).chars().collect();
+                let mut col = pos.col;
+                while col < chars.len() && (chars[col].is_alphanumeric() || chars[col] == '_') {
+                    col += 1;
+                }
+                while col < chars.len() && !chars[col].is_alphanumeric() && chars[col] != '_' {
+                    col += 1;
+                }
+                Position::new(pos.line, col)
+            };
+            self.replace_range(pos, end, "");
+        }
+        self.modified = true;
+    }
+
+
+    // --- Range editing ---
+
+    /// Replace the text between two positions and shift every cursor and
+    /// anchor that sits after the range. Does not record undo.
+    fn replace_range(&mut self, start: Position, end: Position, text: &str) {
+        let start_ci = pos_to_char_idx(&self.rope, &start);
+        let end_ci = pos_to_char_idx(&self.rope, &end);
+        if start_ci < end_ci {
+            self.rope.remove(start_ci..end_ci);
+        }
+        self.rope.insert(start_ci, text);
+
+        let inserted_lines = text.matches('\n').count();
+        let new_end = if inserted_lines == 0 {
+            Position::new(start.line, start.col + text.chars().count())
+        } else {
+            let last = text.rsplit('\n').next().unwrap_or("");
+            Position::new(start.line + inserted_lines, last.chars().count())
+        };
+
+        let map = |p: Position| -> Position {
+            if p <= start {
+                p
+            } else if p < end {
+                p.min(new_end)
+            } else if p.line == end.line {
+                Position::new(new_end.line, new_end.col + (p.col - end.col))
+            } else {
+                Position::new(p.line + new_end.line - end.line, p.col)
+            }
+        };
+        for cursor in &mut self.cursors {
+            cursor.pos = map(cursor.pos);
+            cursor.anchor = cursor.anchor.map(map);
+        }
+    }
+
+    /// Line ranges touched by the cursors, sorted and merged. A selection that
+    /// ends at column 0 does not include that last line. Adjacent ranges are
+    /// merged so line moves never interleave.
+    fn cursor_line_ranges(&self) -> Vec<(usize, usize)> {
+        let mut ranges: Vec<(usize, usize)> = self
+            .cursors
+            .iter()
+            .map(|c| match c.selection_ordered() {
+                Some((s, e)) if e.line > s.line && e.col == 0 => (s.line, e.line - 1),
+                Some((s, e)) => (s.line, e.line),
+                None => (c.pos.line, c.pos.line),
+            })
+            .collect();
+        ranges.sort();
+        let mut merged: Vec<(usize, usize)> = Vec::new();
+        for (first, last) in ranges {
+            match merged.last_mut() {
+                Some(prev) if first <= prev.1 + 1 => prev.1 = prev.1.max(last),
+                _ => merged.push((first, last)),
+            }
+        }
+        merged
+    }
+
+    fn line_end(&self, line: usize) -> Position {
+        Position::new(line, line_len_chars(&self.rope, line))
+    }
+
+    fn sync_desired_cols(&mut self) {
+        for cursor in &mut self.cursors {
+            cursor.desired_col = cursor.pos.col;
+        }
+    }
+
+    // --- Line operations ---
+
+    /// Copy each cursor's lines below themselves, moving the cursors down
+    /// onto the copy.
+    pub fn duplicate_lines(&mut self) {
+        self.copy_lines(true);
+    }
+
+    /// Copy each cursor's lines above themselves; the cursors stay on the
+    /// upper copy.
+    pub fn copy_lines_up(&mut self) {
+        self.copy_lines(false);
+    }
+
+    fn copy_lines(&mut self, move_down: bool) {
+        self.save_undo();
+        for (first, last) in self.cursor_line_ranges().into_iter().rev() {
+            let block: Vec<String> = (first..=last).map(|l| self.line_text(l)).collect();
+            let count = last - first + 1;
+            let end = self.line_end(last);
+            // Insert "\n<block>" at the end of the range, then move the
+            // range's cursors down onto the copy.
+            // Positions after the range are shifted by the insert itself
+            let in_range = |p: &Position| move_down && p.line >= first && p.line <= last;
+            let moved: Vec<(bool, bool)> = self
+                .cursors
+                .iter()
+                .map(|c| (in_range(&c.pos), c.anchor.as_ref().is_some_and(in_range)))
+                .collect();
+            self.replace_range(end, end, &format!("\n{}", block.join("\n")));
+            for (cursor, (pos, anchor)) in self.cursors.iter_mut().zip(moved) {
+                if pos {
+                    cursor.pos.line += count;
+                }
+                if let Some(a) = cursor.anchor.as_mut().filter(|_| anchor) {
+                    a.line += count;
+                }
+            }
+        }
+        self.sync_desired_cols();
+        self.modified = true;
+    }
+
+    /// Delete each cursor's lines (every line a selection touches), in one
+    /// undo step. Cursors keep their column on the line that moves up.
+    pub fn delete_lines(&mut self) {
+        self.save_undo();
+        for (first, last) in self.cursor_line_ranges().into_iter().rev() {
+            let (start, end) = if last + 1 < self.rope.len_lines() {
+                (Position::new(first, 0), Position::new(last + 1, 0))
+            } else if first > 0 {
+                // The last line has no newline of its own; take the one before
+                (self.line_end(first - 1), self.line_end(last))
+            } else {
+                (Position::new(0, 0), self.line_end(last))
+            };
+            self.replace_range(start, end, "");
+        }
+        let mut seen = Vec::new();
+        self.cursors.retain_mut(|cursor| {
+            cursor.anchor = None;
+            cursor.pos.col = cursor.desired_col;
+            let new = !seen.contains(&cursor.pos.line);
+            seen.push(cursor.pos.line);
+            new
+        });
+        for cursor in &mut self.cursors {
+            cursor.pos.col = cursor.pos.col.min(line_len_chars(&self.rope, cursor.pos.line));
+        }
+        self.modified = true;
+    }
+
+    /// Select each cursor's whole line, through its newline. Repeating
+    /// extends a selection of whole lines by the next line.
+    pub fn select_lines(&mut self) {
+        let last_line = self.rope.len_lines() - 1;
+        for cursor in &mut self.cursors {
+            // A selection of whole lines ends at the start of the next line,
+            // so this takes one more
+            let (start, end) = cursor.selection_ordered().unwrap_or((cursor.pos, cursor.pos));
+            let next = end.line + 1;
+            cursor.anchor = Some(Position::new(start.line, 0));
+            cursor.pos = if next <= last_line {
+                Position::new(next, 0)
+            } else {
+                Position::new(last_line, line_len_chars(&self.rope, last_line))
+            };
+            cursor.desired_col = cursor.pos.col;
+        }
+    }
+
+    pub fn move_lines_up(&mut self) {
+        let ranges = self.cursor_line_ranges();
+        if ranges.first().is_none_or(|r| r.0 == 0) {
+            return;
+        }
+        self.save_undo();
+        for (first, last) in ranges {
+            let above = self.line_text(first - 1);
+            let mut block: Vec<String> = (first..=last).map(|l| self.line_text(l)).collect();
+            block.push(above);
+            let saved: Vec<Cursor> = self.cursors.clone();
+            self.replace_range(Position::new(first - 1, 0), self.line_end(last), &block.join("\n"));
+            // Text changed in place; restore cursors shifted up by one line
+            self.cursors = saved;
+            for cursor in &mut self.cursors {
+                if (first..=last).contains(&selection_start_line(cursor)) {
+                    cursor.pos.line -= 1;
+                    if let Some(a) = cursor.anchor.as_mut() {
+                        a.line -= 1;
+                    }
+                }
+            }
+        }
+        self.modified = true;
+    }
+
+    pub fn move_lines_down(&mut self) {
+        let ranges = self.cursor_line_ranges();
+        let last_line = self.rope.len_lines().saturating_sub(1);
+        if ranges.last().is_none_or(|r| r.1 >= last_line) {
+            return;
+        }
+        self.save_undo();
+        for (first, last) in ranges.into_iter().rev() {
+            let below = self.line_text(last + 1);
+            let mut block = vec![below];
+            block.extend((first..=last).map(|l| self.line_text(l)));
+            let saved: Vec<Cursor> = self.cursors.clone();
+            self.replace_range(Position::new(first, 0), self.line_end(last + 1), &block.join("\n"));
+            self.cursors = saved;
+            // A selection ending at the start of the last line now ends past
+            // the buffer; clamp it to the end
+            let rope = &self.rope;
+            let down = |p: Position| clamp_pos(rope, Position::new(p.line + 1, p.col));
+            for cursor in &mut self.cursors {
+                if (first..=last).contains(&selection_start_line(cursor)) {
+                    cursor.pos = down(cursor.pos);
+                    cursor.anchor = cursor.anchor.map(down);
+                }
+            }
+        }
+        self.modified = true;
+    }
+
+    /// Indent every non-empty line touched by a cursor by one level.
+    pub fn indent_lines(&mut self) {
+        self.save_undo();
+        let unit = self.indent.unit();
+        for (first, last) in self.cursor_line_ranges().into_iter().rev() {
+            for line in (first..=last).rev() {
+                if !self.line_text(line).is_empty() {
+                    let p = Position::new(line, 0);
+                    self.replace_range(p, p, &unit);
+                    // Cursors at column 0 move with the text
+                    for cursor in &mut self.cursors {
+                        if cursor.pos == p {
+                            cursor.pos.col += unit.chars().count();
+                        }
+                    }
+                }
             }
-            cursor.desired_col = cursor.pos.col;
         }
+        self.sync_desired_cols();
+        self.modified = true;
     }
 
-    pub fn delete_word_backward(&mut self) {
+    /// Remove one level of indentation (a tab or up to `indent.width` spaces).
+    pub fn outdent_lines(&mut self) {
         self.save_undo();
-        let order = self.sorted_cursor_indices_rev();
-        for &idx in &order {
-            if self.delete_selection_at(idx) {
-                continue;
+        for (first, last) in self.cursor_line_ranges().into_iter().rev() {
+            for line in (first..=last).rev() {
+                let text = self.line_text(line);
+                let remove = if text.starts_with('\t') {
+                    1
+                } else {
+                    text.chars().take(self.indent.width).take_while(|&c| c == ' ').count()
+                };
+                if remove > 0 {
+                    self.replace_range(Position::new(line, 0), Position::new(line, remove), "");
+                }
             }
-            let pos = self.cursors[idx].pos;
-            if pos.line == 0 && pos.col == 0 {
+        }
+        self.sync_desired_cols();
+        self.modified = true;
+    }
+
+    /// Comment or uncomment the lines under each cursor with a line-comment
+    /// prefix such as `//` or `#`. A range is uncommented only if every
+    /// non-blank line in it is already commented.
+    pub fn toggle_line_comment(&mut self, prefix: &str) {
+        self.save_undo();
+        let with_space = format!("{} ", prefix);
+        for (first, last) in self.cursor_line_ranges().into_iter().rev() {
+            let lines: Vec<(usize, String)> = (first..=last)
+                .map(|l| (l, self.line_text(l)))
+                .filter(|(_, t)| !t.trim().is_empty())
+                .collect();
+            if lines.is_empty() {
                 continue;
             }
-            // Find word start
-            let line_text = self.line_text(pos.line);
-            let chars: Vec<char> = line_text.chars().collect();
-            let mut col = pos.col;
-            if col == 0 {
-                // Merge with previous line
-                let ci = pos_to_char_idx(&self.rope, &pos);
-                if ci > 0 {
-                    self.rope.remove(ci - 1..ci);
-                    self.cursors[idx].pos.line -= 1;
-                    self.cursors[idx].pos.col = line_len_chars(&self.rope, self.cursors[idx].pos.line);
-                }
-            } else {
-                let start_col = col;
-                while col > 0 && chars.get(col - 1).map_or(false, |c| !c.is_alphanumeric() && *c != '_') {
-                    col -= 1;
+            let commented = lines.iter().all(|(_, t)| t.trim_start().starts_with(prefix));
+            let indent_col = lines
+                .iter()
+                .map(|(_, t)| t.chars().take_while(|c| c.is_whitespace()).count())
+                .min()
+                .unwrap_or(0);
+
+            for (line, text) in lines.into_iter().rev() {
+                if commented {
+                    let col = text.chars().take_while(|c| c.is_whitespace()).count();
+                    let rest: String = text.chars().skip(col).collect();
+                    let len = if rest.starts_with(&with_space) {
+                        with_space.chars().count()
+                    } else {
+                        prefix.chars().count()
+                    };
+                    self.replace_range(Position::new(line, col), Position::new(line, col + len), "");
+                } else {
+                    let p = Position::new(line, indent_col);
+                    self.replace_range(p, p, &with_space);
                 }
-                while col > 0 && chars.get(col - 1).map_or(false, |c| c.is_alphanumeric() || *c == '_') {
-                    col -= 1;
+            }
+        }
+        self.sync_desired_cols();
+        self.modified = true;
+    }
+
+    /// Rewrap the paragraph under each cursor, or the selected lines, to
+    /// `width` columns, keeping comment and quote prefixes. Each cursor ends
+    /// up after its reflowed text.
+    pub fn reflow_paragraphs(&mut self, width: usize) {
+        let mut ranges: Vec<(usize, usize)> = Vec::new();
+        for cursor in &self.cursors {
+            let range = match cursor.selection_ordered() {
+                Some((start, end)) if end.col == 0 && end.line > start.line => (start.line, end.line - 1),
+                Some((start, end)) => (start.line, end.line),
+                None => {
+                    let line = self.line_text(cursor.pos.line);
+                    let prefix = reflow::prefix(&line);
+                    if !reflow::continues(&line, prefix) {
+                        continue;
+                    }
+                    let (mut first, mut last) = (cursor.pos.line, cursor.pos.line);
+                    while first > 0 && reflow::continues(&self.line_text(first - 1), prefix) {
+                        first -= 1;
+                    }
+                    while last + 1 < self.rope.len_lines() && reflow::continues(&self.line_text(last + 1), prefix) {
+                        last += 1;
+                    }
+                    (first, last)
                 }
-                let start_ci = self.rope.line_to_char(pos.line) + col;
-                let end_ci = self.rope.line_to_char(pos.line) + start_col;
-                self.rope.remove(start_ci..end_ci);
-                self.cursors[idx].pos.col = col;
+            };
+            ranges.push(range);
+        }
+        ranges.sort();
+        ranges.dedup_by(|next, prev| {
+            let overlaps = next.0 <= prev.1;
+            if overlaps {
+                prev.1 = prev.1.max(next.1);
+            }
+            overlaps
+        });
+        if ranges.is_empty() {
+            return;
+        }
+
+        self.save_undo();
+        let mut cursors = Vec::new();
+        // Lines added (or removed) by the ranges reflowed so far
+        let mut shift = 0isize;
+        for (first, last) in ranges {
+            let (first, last) = ((first as isize + shift) as usize, (last as isize + shift) as usize);
+            let old: Vec<String> = (first..=last).map(|line| self.line_text(line)).collect();
+            let lines = reflow::reflow(&old.iter().map(String::as_str).collect::<Vec<_>>(), width);
+            let end = Position::new(first + lines.len() - 1, lines.last().map_or(0, |l| l.chars().count()));
+            if lines != old {
+                let old_end = Position::new(last, line_len_chars(&self.rope, last));
+                self.replace_range(Position::new(first, 0), old_end, &lines.join("\n"));
+                self.modified = true;
+            }
+            shift += lines.len() as isize - old.len() as isize;
+            cursors.push(Cursor::new(end.line, end.col));
+        }
+        self.cursors = cursors;
+    }
+
+    /// Join each cursor's line with the next one (or all selected lines),
+    /// collapsing the indentation at each join into a single space.
+    pub fn join_lines(&mut self) {
+        self.save_undo();
+        let last_line = self.rope.len_lines().saturating_sub(1);
+        for (first, last) in self.cursor_line_ranges().into_iter().rev() {
+            let last = if last == first { (first + 1).min(last_line) } else { last };
+            for line in (first..last).rev() {
+                let next = self.line_text(line + 1);
+                let skip = next.chars().take_while(|c| c.is_whitespace()).count();
+                let current = self.line_text(line);
+                let sep = if current.trim().is_empty() || next.trim().is_empty() { "" } else { " " };
+                let trimmed_len = current.trim_end().chars().count();
+                self.replace_range(
+                    Position::new(line, trimmed_len),
+                    Position::new(line + 1, skip),
+                    sep,
+                );
             }
-            self.cursors[idx].desired_col = self.cursors[idx].pos.col;
         }
+        self.sync_desired_cols();
         self.modified = true;
     }
 
-    pub fn delete_word_forward(&mut self) {
+    /// Apply a transformation to blocks of whole lines: the selected lines, or
+    /// the whole document when nothing is selected.
+    pub fn transform_lines(&mut self, f: impl Fn(Vec<String>) -> Vec<String>) {
+        let ranges = if self.cursors.iter().any(|c| c.anchor.is_some()) {
+            self.cursor_line_ranges()
+        } else {
+            vec![(0, self.rope.len_lines().saturating_sub(1))]
+        };
         self.save_undo();
-        let order = self.sorted_cursor_indices_rev();
-        for &idx in &order {
-            if self.delete_selection_at(idx) {
+        for (first, last) in ranges.into_iter().rev() {
+            let lines: Vec<String> = (first..=last).map(|l| self.line_text(l)).collect();
+            let new_lines = f(lines);
+            self.replace_range(Position::new(first, 0), self.line_end(last), &new_lines.join("\n"));
+        }
+        self.sync_desired_cols();
+        self.modified = true;
+    }
+
+    /// Make sure a non-empty buffer ends with a newline.
+    pub fn ensure_final_newline(&mut self) {
+        let len = self.rope.len_chars();
+        if len == 0 || self.rope.char(len - 1) == '\n' {
+            return;
+        }
+        self.save_undo();
+        self.rope.insert_char(len, '\n');
+        self.modified = true;
+    }
+
+    /// True if any line is too long to highlight, like minified JSON or JS.
+    pub fn has_long_lines(&self) -> bool {
+        self.rope.lines().any(|l| l.len_chars() > syntax::MAX_HIGHLIGHT_CHARS)
+    }
+
+    /// Reformat the whole buffer as indented JSON, as one undoable edit.
+    pub fn pretty_print_json(&mut self) -> Result<(), String> {
+        let text = json::pretty_print(&self.rope.to_string(), &self.indent.unit())?;
+        self.set_text(&text);
+        self.cursors = vec![Cursor::new(0, 0)];
+        self.scroll_x = 0.0;
+        self.scroll_y = 0.0;
+        Ok(())
+    }
+
+    /// Remove trailing whitespace from every line.
+    pub fn trim_trailing_whitespace(&mut self) {
+        let dirty = (0..self.rope.len_lines()).any(|l| {
+            let text = self.line_text(l);
+            text.trim_end().len() < text.len()
+        });
+        if !dirty {
+            return;
+        }
+        self.save_undo();
+        for line in (0..self.rope.len_lines()).rev() {
+            let text = self.line_text(line);
+            let trimmed = text.trim_end().chars().count();
+            let len = text.chars().count();
+            if trimmed < len {
+                self.replace_range(Position::new(line, trimmed), Position::new(line, len), "");
+            }
+        }
+        self.sync_desired_cols();
+        self.modified = true;
+    }
+
+    /// Replace each selection (or the word under each cursor) with `f(text)`,
+    /// keeping it selected.
+    pub fn transform_selections(&mut self, f: impl Fn(&str) -> String) {
+        self.save_undo();
+        for idx in self.sorted_cursor_indices_rev() {
+            let (start, end) = match self.cursors[idx].selection_ordered() {
+                Some(sel) => sel,
+                None => self.word_bounds_at_cursor(&self.cursors[idx]),
+            };
+            let start_ci = pos_to_char_idx(&self.rope, &start);
+            let end_ci = pos_to_char_idx(&self.rope, &end);
+            if start_ci >= end_ci {
                 continue;
             }
-            let pos = self.cursors[idx].pos;
-            let ll = line_len_chars(&self.rope, pos.line);
-            if pos.col >= ll {
-                // Merge with next line
-                let ci = pos_to_char_idx(&self.rope, &pos);
-                if ci < self.rope.len_chars() {
-                    self.rope.remove(ci..ci + 1);
-                }
-            } else {
-                let line_text = self.line_text(pos.line);
-                let chars: Vec<char> = line_text.chars().collect();
-                let mut col = pos.col;
-                while col < chars.len() && (chars[col].is_alphanumeric() || chars[col] == '_') {
-                    col += 1;
-                }
-                while col < chars.len() && !chars[col].is_alphanumeric() && chars[col] != '_' {
-                    col += 1;
-                }
-                let start_ci = self.rope.line_to_char(pos.line) + pos.col;
-                let end_ci = self.rope.line_to_char(pos.line) + col;
-                self.rope.remove(start_ci..end_ci);
+            let text = self.rope.slice(start_ci..end_ci).to_string();
+            let replaced = f(&text);
+            if replaced == text {
+                continue;
             }
+            self.replace_range(start, end, &replaced);
+            let new_end = self.rope.char_to_line(start_ci + replaced.chars().count());
+            let new_end = Position::new(
+                new_end,
+                start_ci + replaced.chars().count() - self.rope.line_to_char(new_end),
+            );
+            self.cursors[idx].anchor = Some(start);
+            self.cursors[idx].pos = new_end;
         }
+        self.sync_desired_cols();
         self.modified = true;
     }
 
@@ -691,21 +2572,104 @@ impl Editor {
             .max_by_key(|c| (c.pos.line, c.pos.col))
             .unwrap();
         let start_ci = pos_to_char_idx(&self.rope, &last_cursor.pos);
-        let full_text = self.rope.to_string();
 
-        if let Some(offset) = full_text[start_ci..].find(&search_text) {
-            let match_start_ci = start_ci + offset;
-            let match_end_ci = match_start_ci + search_text.len();
+        let found = self
+            .search_iter_from(&search_text, SearchOptions::default(), start_ci)
+            .ok()
+            .and_then(|mut matches| matches.next());
+        if let Some(range) = found {
+            self.cursors.push(selection_cursor(&self.rope, range));
+        }
+    }
+
+    /// The word characters immediately before the primary cursor.
+    pub fn word_prefix_at_cursor(&self) -> String {
+        let pos = self.cursors[0].pos;
+        let before: Vec<char> = self.line_text(pos.line).chars().take(pos.col).collect();
+        let start = before
+            .iter()
+            .rposition(|c| !(c.is_alphanumeric() || *c == '_'))
+            .map_or(0, |i| i + 1);
+        before[start..].iter().collect()
+    }
+
+    /// Distinct words in the buffer that extend `prefix`, in order of appearance.
+    pub fn words_with_prefix(&self, prefix: &str) -> Vec<String> {
+        let mut seen = std::collections::HashSet::new();
+        let mut words = Vec::new();
+        for line in self.rope.lines() {
+            let line = line.to_string();
+            for word in line.split(|c: char| !(c.is_alphanumeric() || c == '_')) {
+                if word.len() > prefix.len() && word.starts_with(prefix) && seen.insert(word.to_string()) {
+                    words.push(word.to_string());
+                }
+            }
+        }
+        words
+    }
+
+    /// Replace the word prefix before every cursor with `word`.
+    pub fn complete_word(&mut self, word: &str) {
+        self.save_undo();
+        for idx in self.sorted_cursor_indices_rev() {
+            let pos = self.cursors[idx].pos;
+            let before: Vec<char> = self.line_text(pos.line).chars().take(pos.col).collect();
+            let start = before
+                .iter()
+                .rposition(|c| !(c.is_alphanumeric() || *c == '_'))
+                .map_or(0, |i| i + 1);
+            self.cursors[idx].anchor = None;
+            self.replace_range(Position::new(pos.line, start), pos, word);
+        }
+        self.sync_desired_cols();
+        self.modified = true;
+    }
+
+    /// The word the primary cursor is in or just after, unless it has a selection.
+    pub fn word_under_cursor(&self) -> Option<(Position, Position)> {
+        let cursor = &self.cursors[0];
+        if cursor.selection_ordered().is_some() {
+            return None;
+        }
+        let chars: Vec<char> = self.line_text(cursor.pos.line).chars().collect();
+        let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+        let col = cursor.pos.col.min(chars.len());
+        let mut start = col;
+        while start > 0 && is_word_char(chars[start - 1]) {
+            start -= 1;
+        }
+        let mut end = col;
+        while end < chars.len() && is_word_char(chars[end]) {
+            end += 1;
+        }
+        (start < end).then(|| (Position::new(cursor.pos.line, start), Position::new(cursor.pos.line, end)))
+    }
 
-            let start_line = self.rope.char_to_line(match_start_ci);
-            let start_col = match_start_ci - self.rope.line_to_char(start_line);
-            let end_line = self.rope.char_to_line(match_end_ci);
-            let end_col = match_end_ci - self.rope.line_to_char(end_line);
+    /// The word at `pos`, or the single non-word character there.
+    pub fn word_bounds_at(&self, pos: Position) -> (Position, Position) {
+        self.word_bounds_at_cursor(&Cursor::new(pos.line, pos.col))
+    }
 
-            let mut new_cursor = Cursor::new(end_line, end_col);
-            new_cursor.anchor = Some(Position::new(start_line, start_col));
-            self.cursors.push(new_cursor);
+    /// Mouse counterpart of `select_next_occurrence`: when the primary
+    /// cursor selects a whole word and `pos` is on the same word elsewhere,
+    /// select that one too with a new cursor. Returns false if it isn't.
+    pub fn add_occurrence_at(&mut self, pos: Position) -> bool {
+        let Some((start, end)) = self.cursors[0].selection_ordered() else {
+            return false;
+        };
+        if self.word_bounds_at(start) != (start, end) {
+            return false;
+        }
+        let (word_start, word_end) = self.word_bounds_at(clamp_pos(&self.rope, pos));
+        if word_start == word_end || self.text_between(word_start, word_end) != self.text_between(start, end) {
+            return false;
+        }
+        if !self.cursors.iter().any(|c| c.selection_ordered() == Some((word_start, word_end))) {
+            let mut cursor = Cursor::new(word_end.line, word_end.col);
+            cursor.anchor = Some(word_start);
+            self.cursors.push(cursor);
         }
+        true
     }
 
     fn word_at_cursor(&self, cursor: &Cursor) -> String {
@@ -725,13 +2689,13 @@ impl Editor {
         let col = cursor.pos.col.min(chars.len());
 
         if chars.is_empty() || col >= chars.len() {
-            return (cursor.pos.clone(), cursor.pos.clone());
+            return (cursor.pos, cursor.pos);
         }
 
         let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
 
         if !is_word_char(chars[col]) {
-            return (cursor.pos.clone(), Position::new(cursor.pos.line, col + 1));
+            return (cursor.pos, Position::new(cursor.pos.line, col + 1));
         }
 
         let mut start = col;
@@ -749,6 +2713,77 @@ impl Editor {
         )
     }
 
+    /// Add a cursor on the line above (or below) every existing cursor,
+    /// at the same desired column.
+    pub fn add_cursor_vertical(&mut self, below: bool) {
+        let max_line = self.rope.len_lines().saturating_sub(1);
+        let mut new_cursors = Vec::new();
+        for cursor in &self.cursors {
+            let line = if below {
+                if cursor.pos.line >= max_line {
+                    continue;
+                }
+                cursor.pos.line + 1
+            } else {
+                match cursor.pos.line.checked_sub(1) {
+                    Some(l) => l,
+                    None => continue,
+                }
+            };
+            let col = cursor.desired_col.min(line_len_chars(&self.rope, line));
+            let mut c = Cursor::new(line, col);
+            c.desired_col = cursor.desired_col;
+            new_cursors.push(c);
+        }
+        for c in new_cursors {
+            if !self.cursors.iter().any(|e| e.pos == c.pos) {
+                self.cursors.push(c);
+            }
+        }
+    }
+
+    /// Select every occurrence of the primary selection (or word under it).
+    pub fn select_all_occurrences(&mut self) {
+        let primary = &self.cursors[0];
+        let (start, end) = match primary.selection_ordered() {
+            Some(sel) => sel,
+            None => self.word_bounds_at_cursor(primary),
+        };
+        let start_ci = pos_to_char_idx(&self.rope, &start);
+        let end_ci = pos_to_char_idx(&self.rope, &end);
+        if start_ci >= end_ci {
+            return;
+        }
+        let needle = self.rope.slice(start_ci..end_ci).to_string();
+        let cursors: Vec<Cursor> = match self.search_iter(&needle, SearchOptions::default()) {
+            Ok(matches) => matches.map(|range| selection_cursor(&self.rope, range)).collect(),
+            Err(_) => Vec::new(),
+        };
+        if !cursors.is_empty() {
+            self.cursors = cursors;
+        }
+    }
+
+    /// Turn each multi-line selection into one selection per line.
+    pub fn split_selection_into_lines(&mut self) {
+        let mut cursors = Vec::new();
+        for cursor in &self.cursors {
+            match cursor.selection_ordered() {
+                Some((s, e)) if s.line != e.line => {
+                    for line in s.line..=e.line {
+                        let from = if line == s.line { s.col } else { 0 };
+                        let to = if line == e.line { e.col } else { line_len_chars(&self.rope, line) };
+                        let mut c = Cursor::new(line, to);
+                        c.anchor = Some(Position::new(line, from));
+                        cursors.push(c);
+                    }
+                }
+                _ => cursors.push(cursor.clone()),
+            }
+        }
+        self.cursors = cursors;
+    }
+
     pub fn clear_extra_cursors(&mut self) {
         self.cursors.truncate(1);
         self.cursors[0].anchor = None;
@@ -756,6 +2791,39 @@ impl Editor {
 
     // --- Selection helpers ---
 
+    /// Remember the cursors if any of them selects text, so Reselect can
+    /// bring the selection back after it is collapsed.
+    pub fn note_selection(&mut self) {
+        if self.cursors.iter().any(|c| c.selection_ordered().is_some_and(|(s, e)| s != e)) {
+            self.last_selection = Some(self.cursors.clone());
+        }
+    }
+
+    /// Restore the last selection, moved inside the text if it has shrunk.
+    pub fn reselect(&mut self) {
+        let Some(cursors) = self.last_selection.clone() else {
+            return;
+        };
+        self.cursors = cursors;
+        for cursor in &mut self.cursors {
+            cursor.pos = clamp_pos(&self.rope, cursor.pos);
+            cursor.anchor = cursor.anchor.map(|a| clamp_pos(&self.rope, a));
+            cursor.desired_col = cursor.pos.col;
+        }
+    }
+
+    /// Swap each selection's anchor and cursor, so extending it moves the
+    /// other end.
+    pub fn swap_selection_ends(&mut self) {
+        for cursor in &mut self.cursors {
+            if let Some(anchor) = cursor.anchor {
+                cursor.anchor = Some(cursor.pos);
+                cursor.pos = anchor;
+                cursor.desired_col = anchor.col;
+            }
+        }
+    }
+
     pub fn select_all(&mut self) {
         let last_line = self.rope.len_lines().saturating_sub(1);
         let last_col = line_len_chars(&self.rope, last_line);
@@ -765,40 +2833,62 @@ impl Editor {
         self.cursors[0].desired_col = last_col;
     }
 
-    pub fn selected_text(&self) -> String {
-        if let Some((start, end)) = self.cursors[0].selection_ordered() {
-            let start_ci = pos_to_char_idx(&self.rope, &start);
-            let end_ci = pos_to_char_idx(&self.rope, &end);
-            self.rope.slice(start_ci..end_ci).to_string()
-        } else {
-            String::new()
-        }
+    /// What Copy takes: each cursor's selection, in document order, or
+    /// when no cursor has one, each cursor's whole line.
+    pub fn copy_content(&self) -> ClipboardContent {
+        let linewise = self.cursors.iter().all(|c| c.selection_ordered().is_none());
+        let mut order = self.sorted_cursor_indices_rev();
+        order.reverse();
+        let parts = order
+            .into_iter()
+            .map(|idx| match self.cursors[idx].selection_ordered() {
+                Some((start, end)) => self.text_between(start, end),
+                None => {
+                    let mut line = self.line_text(self.cursors[idx].pos.line);
+                    if linewise {
+                        line.push('\n');
+                    }
+                    line
+                }
+            })
+            .collect();
+        ClipboardContent { parts, linewise }
     }
 
-    /// Copy: returns selected text (or current line if no selection).
+    /// The text Copy puts on the clipboard.
     pub fn copy_text(&self) -> String {
-        let mut parts: Vec<String> = Vec::new();
-        for cursor in &self.cursors {
-            if let Some((start, end)) = cursor.selection_ordered() {
-                let s = pos_to_char_idx(&self.rope, &start);
-                let e = pos_to_char_idx(&self.rope, &end);
-                parts.push(self.rope.slice(s..e).to_string());
-            } else {
-                // No selection: copy entire line
-                let mut line = self.line_text(cursor.pos.line);
-                line.push('\n');
-                parts.push(line);
-            }
-        }
-        parts.join("")
+        self.copy_content().text()
+    }
+
+    /// The ranges `copy_text` copies, in document order: each selection,
+    /// or the whole line (through its newline) for a cursor without one.
+    pub fn copy_ranges(&self) -> Vec<(Position, Position)> {
+        let mut order = self.sorted_cursor_indices_rev();
+        order.reverse();
+        order
+            .into_iter()
+            .map(|idx| &self.cursors[idx])
+            .map(|cursor| match cursor.selection_ordered() {
+                Some(range) => range,
+                None => {
+                    let line = cursor.pos.line;
+                    let end = if line + 1 < self.line_count() {
+                        Position::new(line + 1, 0)
+                    } else {
+                        Position::new(line, self.line_text(line).chars().count())
+                    };
+                    (Position::new(line, 0), end)
+                }
+            })
+            .collect()
     }
 
-    /// Cut: returns selected text and deletes it (or cuts current line).
-    pub fn cut_text(&mut self) -> String {
+    /// Cut: deletes the selections and returns what they held, or without
+    /// any, cuts the primary cursor's line.
+    pub fn cut_content(&mut self) -> ClipboardContent {
         self.save_undo();
-        let text = self.copy_text();
-        let has_selection = self.cursors.iter().any(|c| c.anchor.is_some());
-        if has_selection {
+        let mut content = self.copy_content();
+        if !content.linewise {
             // Delete all selections
             let order = self.sorted_cursor_indices_rev();
             for &idx in &order {
@@ -808,6 +2898,7 @@ impl Editor {
         } else {
             // Delete entire current line
             let line = self.cursors[0].pos.line;
+            content = ClipboardContent { parts: vec![format!("{}\n", self.line_text(line))], linewise: true };
             let line_start = self.rope.line_to_char(line);
             let line_end = if line + 1 < self.rope.len_lines() {
                 self.rope.line_to_char(line + 1)
@@ -824,71 +2915,117 @@ impl Editor {
             self.cursors[0].desired_col = 0;
             self.modified = true;
         }
-        text
+        content
     }
 
     // --- Search ---
 
-    pub fn find_and_select(&mut self, query: &str) {
+    /// Iterate over the matches of `query` in the buffer as char ranges.
+    pub fn search_iter<'a>(&'a self, query: &'a str, options: SearchOptions) -> Result<SearchIter<'a>, SearchError> {
+        self.search_iter_from(query, options, 0)
+    }
+
+    /// Like `search_iter`, starting from char index `from`.
+    pub fn search_iter_from<'a>(
+        &'a self,
+        query: &'a str,
+        options: SearchOptions,
+        from: usize,
+    ) -> Result<SearchIter<'a>, SearchError> {
+        rope_search::search(&self.rope, query, options, from)
+    }
+
+    /// Select the next match of `query` after the primary cursor, wrapping
+    /// around at the end of the buffer.
+    pub fn find_and_select(&mut self, query: &str, options: SearchOptions) -> Result<(), SearchError> {
         if query.is_empty() {
-            return;
+            return Ok(());
         }
-        let full = self.rope.to_string();
         let primary_ci = pos_to_char_idx(&self.rope, &self.cursors[0].pos);
+        let found = match self.search_iter_from(query, options, primary_ci)?.next() {
+            Some(range) => Some(range),
+            None => self.search_iter(query, options)?.next(),
+        };
 
-        // Search forward from cursor
-        let found = full[primary_ci..]
-            .find(query)
-            .map(|o| primary_ci + o)
-            .or_else(|| full[..primary_ci].find(query)); // Wrap around
-
-        if let Some(match_start) = found {
-            let match_end = match_start + query.len();
-            let start_line = self.rope.char_to_line(match_start);
-            let start_col = match_start - self.rope.line_to_char(start_line);
-            let end_line = self.rope.char_to_line(match_end);
-            let end_col = match_end - self.rope.line_to_char(end_line);
-
-            self.cursors.truncate(1);
-            self.cursors[0].anchor = Some(Position::new(start_line, start_col));
-            self.cursors[0].pos = Position::new(end_line, end_col);
-            self.cursors[0].desired_col = end_col;
+        if let Some(range) = found {
+            let cursor = selection_cursor(&self.rope, range);
+            let start_line = cursor.anchor.map_or(cursor.pos.line, |a| a.line);
+            self.cursors = vec![cursor];
 
             // Scroll to match
-            self.scroll_y = (start_line as f32 * LINE_HEIGHT).max(0.0);
+            self.scroll_y = (start_line as f32 * self.line_height).max(0.0);
         }
+        Ok(())
     }
 
     /// Replace the current selection (if it matches query) and find the next match.
-    pub fn replace_next(&mut self, find: &str, replace: &str) {
+    pub fn replace_next(&mut self, find: &str, replace: &str, options: SearchOptions) -> Result<(), SearchError> {
         if find.is_empty() {
-            return;
+            return Ok(());
         }
-        // If current selection matches find, replace it
-        let selected = self.selected_text();
-        if selected == find {
-            self.save_undo();
-            // Delete selection and insert replacement
-            self.delete_selection_at(0);
-            let ci = pos_to_char_idx(&self.rope, &self.cursors[0].pos);
-            self.rope.insert(ci, replace);
-            self.cursors[0].pos.col += replace.chars().count();
-            self.cursors[0].desired_col = self.cursors[0].pos.col;
-            self.modified = true;
+        // If the current selection is a match, replace it
+        if let Some((start, end)) = self.cursors[0].selection_ordered() {
+            let range = pos_to_char_idx(&self.rope, &start)..pos_to_char_idx(&self.rope, &end);
+            if self.search_iter_from(find, options, range.start)?.next() == Some(range.clone()) {
+                self.save_undo();
+                self.rope.remove(range.clone());
+                self.rope.insert(range.start, replace);
+                let pos = char_idx_to_pos(&self.rope, range.start + replace.chars().count());
+                self.cursors[0] = Cursor::new(pos.line, pos.col);
+                self.modified = true;
+            }
         }
         // Find next occurrence
-        self.find_and_select(find);
+        self.find_and_select(find, options)
     }
 
     /// Replace all occurrences in the document.
-    pub fn replace_all(&mut self, find: &str, replace: &str) {
+    pub fn replace_all(&mut self, find: &str, replace: &str, options: SearchOptions) -> Result<(), SearchError> {
         if find.is_empty() {
-            return;
+            return Ok(());
+        }
+        let matches: Vec<_> = self.search_iter(find, options)?.collect();
+        if matches.is_empty() {
+            return Ok(());
+        }
+        self.save_undo();
+        if options == SearchOptions::default() {
+            self.rope = rope_search::replace_all(&self.rope, find, replace);
+        } else {
+            for range in matches.into_iter().rev() {
+                self.rope.remove(range.clone());
+                self.rope.insert(range.start, replace);
+            }
         }
+        self.clamp_cursors();
+        self.modified = true;
+        Ok(())
+    }
+
+    /// Replace the whole buffer as one undoable edit.
+    pub fn set_text(&mut self, text: &str) {
+        self.save_undo();
+        self.rope = Rope::from_str(text);
+        self.clamp_cursors();
+        self.modified = true;
+    }
+
+    /// Apply an edit made elsewhere (by a collaborator): replace the chars
+    /// `start..end` with `text`, moving the cursors along with it.
+    pub fn apply_remote_edit(&mut self, start: usize, end: usize, text: &str) {
         self.save_undo();
-        let content = self.rope.to_string().replace(find, replace);
-        self.rope = Rope::from_str(&content);
-        // Reset cursors to safe position
+        let to_pos = |rope: &Rope, idx: usize| {
+            let line = rope.char_to_line(idx);
+            Position::new(line, idx - rope.line_to_char(line))
+        };
+        let (start, end) = (to_pos(&self.rope, start), to_pos(&self.rope, end));
+        self.replace_range(start, end, text);
+        self.modified = true;
+    }
+
+    /// Move cursors back inside the text and drop selections, after the
+    /// content was replaced wholesale.
+    fn clamp_cursors(&mut self) {
         let max_line = self.rope.len_lines().saturating_sub(1);
         for cursor in &mut self.cursors {
             cursor.pos.line = cursor.pos.line.min(max_line);
@@ -897,17 +3034,123 @@ impl Editor {
             cursor.desired_col = cursor.pos.col;
             cursor.anchor = None;
         }
-        self.modified = true;
     }
 
     // --- Go to line ---
 
+    /// Go to the start of a 1-based line, scrolled to the middle of the view.
     pub fn goto_line(&mut self, line_number: usize) {
         let line = line_number.saturating_sub(1).min(self.rope.len_lines().saturating_sub(1));
         self.cursors.truncate(1);
         self.cursors[0].pos = Position::new(line, 0);
         self.cursors[0].anchor = None;
         self.cursors[0].desired_col = 0;
-        self.scroll_y = (line as f32 * LINE_HEIGHT).max(0.0);
+        let room = (self.view_height - self.line_height).max(0.0);
+        self.scroll_y = (line as f32 * self.line_height - room / 2.0).max(0.0);
+    }
+
+    /// Go to a 1-based line and column; the column is clamped to the line.
+    pub fn goto_line_col(&mut self, line_number: usize, col_number: usize) {
+        self.goto_line(line_number);
+        let line = self.cursors[0].pos.line;
+        let col = col_number.saturating_sub(1).min(self.line_text(line).chars().count());
+        self.cursors[0].pos.col = col;
+        self.cursors[0].desired_col = col;
+    }
+
+    /// Go to a place typed into Go to Line: `42`, `42:7` for a line and
+    /// column, `+10` or `-10` for lines below or above the cursor, or `50%`
+    /// of the way through the file. Returns false if `target` is none of
+    /// these.
+    pub fn goto(&mut self, target: &str) -> bool {
+        let target = target.trim();
+        if let Some(percent) = target.strip_suffix('%') {
+            let Some(percent) = percent.trim().parse::<f64>().ok().filter(|p| p.is_finite()) else {
+                return false;
+            };
+            let last = self.line_count().saturating_sub(1) as f64;
+            self.goto_line((percent.clamp(0.0, 100.0) / 100.0 * last).round() as usize + 1);
+            return true;
+        }
+        // Plain digits only: `parse` would also take a sign, letting `++2` through
+        let number = |text: &str| {
+            let text = text.trim();
+            let digits = !text.is_empty() && text.bytes().all(|b| b.is_ascii_digit());
+            digits.then(|| text.parse::<usize>().ok()).flatten()
+        };
+        let (line, col) = match target.split_once(':') {
+            Some((line, col)) => match number(col) {
+                Some(col) => (line.trim(), Some(col)),
+                None => return false,
+            },
+            None => (target, None),
+        };
+        let current = self.cursors[0].pos.line + 1;
+        let line_number = if let Some(below) = line.strip_prefix('+') {
+            number(below).map(|n| current.saturating_add(n))
+        } else if let Some(above) = line.strip_prefix('-') {
+            number(above).map(|n| current.saturating_sub(n).max(1))
+        } else {
+            number(line)
+        };
+        let Some(line_number) = line_number else {
+            return false;
+        };
+        match col {
+            Some(col) => self.goto_line_col(line_number, col),
+            None => self.goto_line(line_number),
+        }
+        true
+    }
+
+    // --- Merge conflicts ---
+
+    pub fn conflicts(&self) -> Vec<Conflict> {
+        conflicts::find(&self.rope)
+    }
+
+    /// Replace a conflict block, markers and all, with the side(s) to keep.
+    pub fn resolve_conflict(&mut self, conflict: &Conflict, resolution: Resolution) {
+        self.save_undo();
+        let text = conflict.resolved_text(&self.rope, resolution);
+        self.replace_range(Position::new(conflict.start, 0), Position::new(conflict.end + 1, 0), &text);
+        for cursor in &mut self.cursors {
+            cursor.pos = clamp_pos(&self.rope, cursor.pos);
+            cursor.anchor = cursor.anchor.map(|a| clamp_pos(&self.rope, a));
+        }
+        self.sync_desired_cols();
+        self.modified = true;
+    }
+
+    /// Resolve the conflict containing the primary cursor. Returns false if
+    /// the cursor is not in one.
+    pub fn resolve_conflict_at_cursor(&mut self, resolution: Resolution) -> bool {
+        let line = self.cursors[0].pos.line;
+        match self.conflicts().into_iter().find(|c| c.contains(line)) {
+            Some(conflict) => {
+                self.resolve_conflict(&conflict, resolution);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Move to the start of the next (or previous) conflict, wrapping around
+    /// the file. Returns false if there are none.
+    pub fn goto_conflict(&mut self, forward: bool) -> bool {
+        let line = self.cursors[0].pos.line;
+        let starts: Vec<usize> = self.conflicts().iter().map(|c| c.start).collect();
+        let target = if forward {
+            starts.iter().find(|&&s| s > line).or(starts.first())
+        } else {
+            starts.iter().rev().find(|&&s| s < line).or(starts.last())
+        };
+        match target {
+            Some(&start) => {
+                self.goto_line(start + 1);
+                true
+            }
+            None => false,
+        }
     }
 }
@@ -0,0 +1,185 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::editor::{Editor, Position};
+use crate::encryption;
+
+/// Replace the text between two positions (0-based, end exclusive).
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextEdit {
+    pub start: Position,
+    pub end: Position,
+    pub text: String,
+    /// The text the edit was made against between `start` and `end`, when
+    /// its maker knows it. If the file has anything else there now, the
+    /// edit is refused rather than overwriting it.
+    pub old: Option<String>,
+}
+
+/// Edits to any number of files. Each file's edits must not overlap; they
+/// all refer to the text as it was before any of them.
+pub type WorkspaceEdit = BTreeMap<PathBuf, Vec<TextEdit>>;
+
+/// A fix or refactoring offered at a position, from a linter's suggestion
+/// or a language server.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CodeAction {
+    pub title: String,
+    pub edit: WorkspaceEdit,
+    /// Who offered it, e.g. the linter's or the server's name.
+    pub source: String,
+}
+
+/// The position of a character offset into `text`.
+pub fn offset_to_position(text: &str, offset: usize) -> Position {
+    let mut pos = Position::new(0, 0);
+    for c in text.chars().take(offset) {
+        if c == '\n' {
+            pos = Position::new(pos.line + 1, 0);
+        } else {
+            pos.col += 1;
+        }
+    }
+    pos
+}
+
+/// What applying a workspace edit changed.
+#[derive(Clone, Debug, Default)]
+pub struct EditReport {
+    /// Open buffers edited, with how many edits each got. They are left
+    /// modified, for the user to review and save.
+    pub buffers: Vec<(PathBuf, usize)>,
+    /// Files rewritten on disk, with how many edits each got.
+    pub files: Vec<(PathBuf, usize)>,
+}
+
+impl EditReport {
+    pub fn edit_count(&self) -> usize {
+        self.buffers.iter().chain(&self.files).map(|(_, n)| n).sum()
+    }
+
+    /// "Made 5 changes in 3 files (2 open, unsaved)".
+    pub fn summary(&self) -> String {
+        let files = self.buffers.len() + self.files.len();
+        let mut text = format!(
+            "Made {} change{} in {} file{}",
+            self.edit_count(),
+            if self.edit_count() == 1 { "" } else { "s" },
+            files,
+            if files == 1 { "" } else { "s" },
+        );
+        if !self.buffers.is_empty() && !self.files.is_empty() {
+            text.push_str(&format!(" ({} open, unsaved)", self.buffers.len()));
+        } else if !self.buffers.is_empty() {
+            text.push_str(" (unsaved)");
+        }
+        text
+    }
+}
+
+/// Why a workspace edit was not applied. Nothing was changed.
+#[derive(Clone, Debug)]
+pub struct EditError {
+    pub path: PathBuf,
+    pub message: String,
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.message)
+    }
+}
+
+/// Apply `edit` to every file it names: open buffers (by `editors`) as one
+/// undo step named `label` each, other files on disk. Either every file is
+/// changed or, if any edit doesn't fit its file or a file can't be read or
+/// written, none is.
+pub fn apply(edit: &WorkspaceEdit, label: &str, editors: &mut [Editor]) -> Result<EditReport, EditError> {
+    let mut report = EditReport::default();
+    let mut open = Vec::new();
+    let mut rewrites = Vec::new();
+    for (path, edits) in edit.iter().filter(|(_, edits)| !edits.is_empty()) {
+        let error = |message: String| EditError { path: path.clone(), message };
+        match editors.iter().position(|e| e.file_path.as_ref() == Some(path)) {
+            Some(index) => {
+                check(&editors[index], edits).map_err(error)?;
+                open.push((index, edits));
+                report.buffers.push((path.clone(), edits.len()));
+            }
+            None => {
+                let before = fs::read(path).map_err(|e| error(e.to_string()))?;
+                // Its text can't be changed without the passphrase
+                if encryption::detect(path, &before).is_some() {
+                    return Err(error("the file is encrypted; open it to edit it".to_string()));
+                }
+                let mut editor = Editor::from_file(path.clone()).map_err(|e| error(e.to_string()))?;
+                check(&editor, edits).map_err(error)?;
+                editor.apply_edits(label, edits);
+                let after = editor.file_contents().map_err(|e| error(e.to_string()))?;
+                rewrites.push((path, before, after));
+                report.files.push((path.clone(), edits.len()));
+            }
+        }
+    }
+    write_all(&rewrites)?;
+    for (index, edits) in open {
+        editors[index].apply_edits(label, edits);
+    }
+    Ok(report)
+}
+
+/// Check that `edits` lie inside the editor's text, replace the text they
+/// were made against and don't overlap.
+fn check(editor: &Editor, edits: &[TextEdit]) -> Result<(), String> {
+    let inside = |p: Position| p.line < editor.line_count() && p.col <= editor.line_text(p.line).chars().count();
+    let mut sorted: Vec<&TextEdit> = edits.iter().collect();
+    sorted.sort_by_key(|e| e.start);
+    for edit in &sorted {
+        let fits = inside(edit.start) && inside(edit.end) && edit.start <= edit.end;
+        if !fits || edit.old.as_ref().is_some_and(|old| *old != editor.text_between(edit.start, edit.end)) {
+            return Err(format!("line {} has changed since the edit was made", edit.start.line + 1));
+        }
+    }
+    if let Some(pair) = sorted.windows(2).find(|pair| pair[0].end > pair[1].start) {
+        return Err(format!("edits overlap on line {}", pair[1].start.line + 1));
+    }
+    Ok(())
+}
+
+/// Write new contents to each file: all into temporary files beside them
+/// first, then over them. A failure puts back the files already replaced.
+fn write_all(rewrites: &[(&PathBuf, Vec<u8>, Vec<u8>)]) -> Result<(), EditError> {
+    let temp = |path: &Path| {
+        let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        path.with_file_name(format!(".{}.lux-edit-tmp", name))
+    };
+    let mut written: Vec<PathBuf> = Vec::new();
+    for (path, _, after) in rewrites {
+        if let Err(e) = fs::write(temp(path), after) {
+            for done in &written {
+                let _ = fs::remove_file(done);
+            }
+            let _ = fs::remove_file(temp(path));
+            return Err(EditError { path: path.to_path_buf(), message: e.to_string() });
+        }
+        written.push(temp(path));
+    }
+    for (i, (path, _, _)) in rewrites.iter().enumerate() {
+        // Keep the file's permissions, which the temporary file doesn't have
+        if let Ok(metadata) = fs::metadata(path) {
+            let _ = fs::set_permissions(temp(path), metadata.permissions());
+        }
+        if let Err(e) = fs::rename(temp(path), path) {
+            for (done, before, _) in &rewrites[..i] {
+                let _ = fs::write(done, before);
+            }
+            for (left, _, _) in &rewrites[i..] {
+                let _ = fs::remove_file(temp(left));
+            }
+            return Err(EditError { path: path.to_path_buf(), message: e.to_string() });
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,97 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Write `contents` to `path` with administrator rights, asking the user to
+/// authenticate through the system's own prompt: polkit (or sudo with an
+/// askpass helper) on Linux, an authorization dialog on macOS and UAC on
+/// Windows. Blocks until the prompt is answered.
+pub fn write(path: &Path, contents: &[u8]) -> io::Result<()> {
+    if cfg!(windows) {
+        write_windows(path, contents)
+    } else if cfg!(target_os = "macos") {
+        write_macos(path, contents)
+    } else {
+        write_unix(path, contents)
+    }
+}
+
+/// Pipe the contents through `tee` run as root.
+fn write_unix(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let spawn = |program: &str, args: &[&str]| {
+        Command::new(program)
+            .args(args)
+            .arg("tee")
+            .arg("--")
+            .arg(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+    };
+    let mut child = match spawn("pkexec", &[]) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => spawn("sudo", &["-A"])?,
+        other => other?,
+    };
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(contents)?;
+    }
+    check(child.wait_with_output()?)
+}
+
+fn write_macos(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let temp = staged_copy(contents)?;
+    let script = format!(
+        "do shell script \"cat {} > {}\" with administrator privileges",
+        applescript_escape(&shell_quote(&temp)),
+        applescript_escape(&shell_quote(path)),
+    );
+    let result = Command::new("osascript").arg("-e").arg(script).output();
+    let _ = fs::remove_file(&temp);
+    check(result?)
+}
+
+fn write_windows(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let temp = staged_copy(contents)?;
+    // Windows paths cannot contain double quotes, so quoting them for cmd
+    // is enough; the PowerShell string escapes single quotes by doubling
+    let copy = format!("/c copy /y \"{}\" \"{}\"", temp.display(), path.display());
+    let script = format!(
+        "$p = Start-Process -FilePath cmd -ArgumentList '{}' -Verb RunAs -WindowStyle Hidden -Wait -PassThru; exit $p.ExitCode",
+        copy.replace('\'', "''"),
+    );
+    let result = Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+        .output();
+    let _ = fs::remove_file(&temp);
+    check(result?)
+}
+
+/// Copy of the contents the elevated helper can read.
+fn staged_copy(contents: &[u8]) -> io::Result<std::path::PathBuf> {
+    let temp = std::env::temp_dir().join(format!("lux-edit-save-{}.tmp", std::process::id()));
+    fs::write(&temp, contents)?;
+    Ok(temp)
+}
+
+fn shell_quote(path: &Path) -> String {
+    format!("'{}'", path.to_string_lossy().replace('\'', "'\\''"))
+}
+
+fn applescript_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Turn a failed helper into an error carrying what it printed.
+fn check(output: std::process::Output) -> io::Result<()> {
+    if output.status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let message = match stderr.trim() {
+        "" => format!("elevated save failed ({})", output.status),
+        msg => msg.to_string(),
+    };
+    Err(io::Error::new(io::ErrorKind::PermissionDenied, message))
+}
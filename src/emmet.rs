@@ -0,0 +1,709 @@
+use std::path::Path;
+
+/// Which kind of abbreviations a file takes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Syntax {
+    /// `ul>li.item*3`, `a[href=#]{Home}`, `!` for a whole document.
+    Html,
+    /// `m10-20`, `w50p`, `c#f`, `dn`.
+    Css,
+}
+
+const HTML_EXTENSIONS: &[&str] = &["html", "htm", "xhtml", "vue", "svelte"];
+const CSS_EXTENSIONS: &[&str] = &["css", "scss", "less"];
+
+/// The abbreviation syntax for a file, by extension; None for files that
+/// take neither.
+pub fn syntax_for(path: Option<&Path>) -> Option<Syntax> {
+    let ext = path?.extension()?.to_str()?.to_lowercase();
+    if HTML_EXTENSIONS.contains(&ext.as_str()) {
+        Some(Syntax::Html)
+    } else if CSS_EXTENSIONS.contains(&ext.as_str()) {
+        Some(Syntax::Css)
+    } else {
+        None
+    }
+}
+
+/// An expanded abbreviation, which replaces the line's text from char
+/// column `start` up to the cursor.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Expansion {
+    pub start: usize,
+    pub text: String,
+    /// Where the cursor goes, as a char offset into `text`: the first empty
+    /// attribute value or element, or the end.
+    pub cursor: usize,
+}
+
+/// Expand the abbreviation that ends at char column `col` of `line`. Later
+/// lines of the expansion keep the line's indentation and nest by `unit`.
+/// None when there is no abbreviation there, so Tab can indent instead.
+pub fn expand(syntax: Syntax, line: &str, col: usize, unit: &str) -> Option<Expansion> {
+    let before: Vec<char> = line.chars().take(col).collect();
+    let indent: String = line.chars().take_while(|c| *c == ' ' || *c == '\t').collect();
+    let (start, text, cursor) = match syntax {
+        Syntax::Html => {
+            let start = html_abbreviation(&before)?;
+            let abbr: String = before[start..].iter().collect();
+            let (text, cursor) = expand_html(&abbr, &indent, unit)?;
+            (start, text, cursor)
+        }
+        Syntax::Css => {
+            let start = css_abbreviation(&before)?;
+            let abbr: String = before[start..].iter().collect();
+            let (text, cursor) = expand_css(&abbr)?;
+            (start, text, cursor)
+        }
+    };
+    Some(Expansion { start, text, cursor })
+}
+
+// --- HTML ---
+
+fn is_abbreviation_char(c: char) -> bool {
+    c.is_alphanumeric() || "-_:.#>+^*$!()[]{}".contains(c)
+}
+
+/// The column where the HTML abbreviation before the cursor starts. It runs
+/// back to whitespace, but attribute lists and text in braces may hold
+/// anything; after a tag like `<p>` it starts past the tag.
+fn html_abbreviation(before: &[char]) -> Option<usize> {
+    let mut depth = 0;
+    let mut start = before.len();
+    while start > 0 {
+        let c = before[start - 1];
+        if depth == 0 && (c.is_whitespace() || c == '<' || !is_abbreviation_char(c)) {
+            break;
+        }
+        match c {
+            ']' | '}' => depth += 1,
+            '[' | '{' if depth == 0 => return None,
+            '[' | '{' => depth -= 1,
+            _ => {}
+        }
+        start -= 1;
+    }
+    if start > 0 && !before[start - 1].is_whitespace() {
+        // Right after markup, as in `<p>ul>li`: skip the end of the tag
+        let close = before[start..].iter().position(|c| *c == '>')?;
+        start += close + 1;
+    }
+    (start < before.len()).then_some(start)
+}
+
+/// Elements expanded from a bare name; other words are left alone, as they
+/// are more likely text than tags.
+const ELEMENTS: &[&str] = &[
+    "a", "abbr", "address", "article", "aside", "audio", "b", "blockquote", "body", "br", "button",
+    "canvas", "caption", "code", "col", "dd", "details", "dialog", "div", "dl", "dt", "em", "fieldset",
+    "figcaption", "figure", "footer", "form", "h1", "h2", "h3", "h4", "h5", "h6", "head", "header", "hr",
+    "html", "i", "iframe", "img", "input", "label", "legend", "li", "link", "main", "meta", "nav", "ol",
+    "option", "p", "pre", "script", "section", "select", "small", "source", "span", "strong", "style",
+    "sub", "summary", "sup", "table", "tbody", "td", "template", "textarea", "tfoot", "th", "thead",
+    "title", "tr", "u", "ul", "video",
+];
+
+/// Elements without content or a closing tag.
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track", "wbr",
+];
+
+/// Attributes an element gets unless the abbreviation gives them; empty
+/// ones are left for the user to fill in.
+fn default_attributes(name: &str) -> &'static [(&'static str, &'static str)] {
+    match name {
+        "a" => &[("href", "")],
+        "img" => &[("src", ""), ("alt", "")],
+        "input" => &[("type", "text")],
+        "link" => &[("rel", "stylesheet"), ("href", "")],
+        "form" => &[("action", "")],
+        "label" => &[("for", "")],
+        "iframe" => &[("src", "")],
+        _ => &[],
+    }
+}
+
+/// The element a name-less abbreviation like `.item` makes inside `parent`.
+fn implicit_name(parent: &str) -> &'static str {
+    match parent {
+        "ul" | "ol" => "li",
+        "table" | "thead" | "tbody" | "tfoot" => "tr",
+        "tr" => "td",
+        "select" | "optgroup" => "option",
+        _ => "div",
+    }
+}
+
+#[derive(Debug, Default)]
+struct Element {
+    name: String,
+    id: Option<String>,
+    classes: Vec<String>,
+    attributes: Vec<(String, String)>,
+    text: Option<String>,
+}
+
+#[derive(Debug)]
+enum Kind {
+    Element(Element),
+    Group(Vec<Node>),
+}
+
+#[derive(Debug)]
+struct Node {
+    kind: Kind,
+    count: usize,
+    children: Vec<Node>,
+}
+
+/// Where `>` puts children of the last of `nodes`: for a group, in its
+/// last element.
+fn last_children(nodes: &mut [Node]) -> Option<&mut Vec<Node>> {
+    let last = nodes.last_mut()?;
+    match &mut last.kind {
+        Kind::Group(inner) if !inner.is_empty() => last_children(inner),
+        _ => Some(&mut last.children),
+    }
+}
+
+/// Most copies `*` makes, so a typo can't produce a huge expansion.
+const MAX_COUNT: usize = 1000;
+
+/// Most elements one expansion writes, since nested counts multiply.
+const MAX_ELEMENTS: usize = 5000;
+
+/// How many elements `nodes` expand to, or None if that is more than
+/// [`MAX_ELEMENTS`].
+fn element_count(nodes: &[Node]) -> Option<usize> {
+    nodes.iter().try_fold(0usize, |total, node| {
+        let own = match &node.kind {
+            Kind::Group(inner) => element_count(inner)?,
+            Kind::Element(_) => 1,
+        };
+        let each = own.checked_add(element_count(&node.children)?)?;
+        let total = total.checked_add(each.checked_mul(node.count)?)?;
+        (total <= MAX_ELEMENTS).then_some(total)
+    })
+}
+
+/// Deepest nesting of `>` children and `(` groups parsed, so a long run of
+/// either fails instead of overflowing the stack.
+const MAX_DEPTH: usize = 256;
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    /// Sequences open around the one being parsed.
+    nesting: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let found = self.peek() == Some(c);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn take_while(&mut self, keep: impl Fn(char) -> bool) -> String {
+        let start = self.pos;
+        while self.peek().is_some_and(&keep) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    /// Siblings joined by `+`, with `>` nesting and `^` climbing back out.
+    /// Returns them with how many levels a `^` still has to climb past
+    /// this one; `depth` is how deep this list is.
+    fn sequence(&mut self, depth: usize) -> Option<(Vec<Node>, usize)> {
+        let mut nodes = Vec::new();
+        loop {
+            nodes.push(self.term()?);
+            match self.peek() {
+                Some('+') => self.pos += 1,
+                Some('>') => {
+                    self.pos += 1;
+                    let (children, climb) = self.nested_sequence(depth + 1)?;
+                    *last_children(&mut nodes)? = children;
+                    match climb {
+                        0 => return Some((nodes, 0)),
+                        1 => {}
+                        _ => return Some((nodes, climb - 1)),
+                    }
+                }
+                Some('^') => {
+                    let mut climb = 0;
+                    while self.eat('^') {
+                        climb += 1;
+                    }
+                    // Climbing past the top adds a sibling there
+                    if depth > 0 {
+                        return Some((nodes, climb.min(depth)));
+                    }
+                }
+                _ => return Some((nodes, 0)),
+            }
+        }
+    }
+
+    /// `sequence` inside another, unless that nests deeper than
+    /// [`MAX_DEPTH`].
+    fn nested_sequence(&mut self, depth: usize) -> Option<(Vec<Node>, usize)> {
+        if self.nesting == MAX_DEPTH {
+            return None;
+        }
+        self.nesting += 1;
+        let nodes = self.sequence(depth);
+        self.nesting -= 1;
+        nodes
+    }
+
+    /// An element or a parenthesized group, with its `*count`.
+    fn term(&mut self) -> Option<Node> {
+        let kind = if self.eat('(') {
+            let (nodes, _) = self.nested_sequence(0)?;
+            if !self.eat(')') {
+                return None;
+            }
+            Kind::Group(nodes)
+        } else {
+            Kind::Element(self.element()?)
+        };
+        let count = if self.eat('*') {
+            let digits = self.take_while(|c| c.is_ascii_digit());
+            digits.parse().ok().filter(|n| (1..=MAX_COUNT).contains(n))?
+        } else {
+            1
+        };
+        Some(Node { kind, count, children: Vec::new() })
+    }
+
+    fn element(&mut self) -> Option<Element> {
+        let mut element = Element {
+            name: self.take_while(|c| c.is_alphanumeric() || c == '-' || c == '_' || c == ':'),
+            ..Element::default()
+        };
+        loop {
+            match self.peek() {
+                Some('#') => {
+                    self.pos += 1;
+                    element.id = Some(self.word()?);
+                }
+                Some('.') => {
+                    self.pos += 1;
+                    element.classes.push(self.word()?);
+                }
+                Some('[') => {
+                    self.pos += 1;
+                    self.attributes(&mut element)?;
+                }
+                Some('{') => {
+                    self.pos += 1;
+                    element.text = Some(self.take_while(|c| c != '}'));
+                    if !self.eat('}') {
+                        return None;
+                    }
+                }
+                _ => break,
+            }
+        }
+        let empty = element.name.is_empty()
+            && element.id.is_none()
+            && element.classes.is_empty()
+            && element.attributes.is_empty()
+            && element.text.is_none();
+        (!empty).then_some(element)
+    }
+
+    /// An id or class name, which may hold `$` for numbering.
+    fn word(&mut self) -> Option<String> {
+        let word = self.take_while(|c| c.is_alphanumeric() || c == '-' || c == '_' || c == '$');
+        (!word.is_empty()).then_some(word)
+    }
+
+    /// `name=value name="quoted value" flag]`, after the `[`.
+    fn attributes(&mut self, element: &mut Element) -> Option<()> {
+        loop {
+            self.take_while(char::is_whitespace);
+            if self.eat(']') {
+                return Some(());
+            }
+            let name = self.take_while(|c| !c.is_whitespace() && c != '=' && c != ']');
+            if name.is_empty() {
+                return None;
+            }
+            let value = if !self.eat('=') {
+                String::new()
+            } else if let Some(quote) = self.peek().filter(|c| *c == '"' || *c == '\'') {
+                self.pos += 1;
+                let value = self.take_while(|c| c != quote);
+                if !self.eat(quote) {
+                    return None;
+                }
+                value
+            } else {
+                self.take_while(|c| !c.is_whitespace() && c != ']')
+            };
+            element.attributes.push((name, value));
+        }
+    }
+}
+
+/// Replace each run of `$` with `index`, zero-padded to the run's length.
+fn number(text: &str, index: usize) -> String {
+    let mut out = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '$' {
+            out.push(c);
+            continue;
+        }
+        let mut width = 1;
+        while chars.next_if_eq(&'$').is_some() {
+            width += 1;
+        }
+        out.push_str(&format!("{:0width$}", index, width = width));
+    }
+    out
+}
+
+/// Writes expanded elements one per line, remembering the first place to
+/// type into.
+struct Writer<'a> {
+    indent: &'a str,
+    unit: &'a str,
+    out: String,
+    cursor: Option<usize>,
+}
+
+impl Writer<'_> {
+    fn line(&mut self, level: usize) {
+        if !self.out.is_empty() {
+            self.out.push('\n');
+            self.out.push_str(self.indent);
+        }
+        for _ in 0..level {
+            self.out.push_str(self.unit);
+        }
+    }
+
+    fn stop(&mut self) {
+        if self.cursor.is_none() {
+            self.cursor = Some(self.out.chars().count());
+        }
+    }
+
+    fn nodes(&mut self, nodes: &[Node], parent: &str, level: usize, index: usize) {
+        for node in nodes {
+            for i in 1..=node.count {
+                let index = if node.count > 1 { i } else { index };
+                match &node.kind {
+                    Kind::Group(inner) => self.nodes(inner, parent, level, index),
+                    Kind::Element(element) => self.element(element, &node.children, parent, level, index),
+                }
+            }
+        }
+    }
+
+    fn element(&mut self, element: &Element, children: &[Node], parent: &str, level: usize, index: usize) {
+        let text = element.text.as_deref().map(|t| number(t, index));
+        let only_text = element.name.is_empty()
+            && element.id.is_none()
+            && element.classes.is_empty()
+            && element.attributes.is_empty();
+        if only_text {
+            self.line(level);
+            self.out.push_str(&text.unwrap_or_default());
+            return;
+        }
+        let name = match element.name.as_str() {
+            "" => implicit_name(parent).to_string(),
+            name => name.to_string(),
+        };
+        let mut attributes: Vec<(String, String)> = Vec::new();
+        if let Some(id) = &element.id {
+            attributes.push(("id".into(), number(id, index)));
+        }
+        if !element.classes.is_empty() {
+            let classes: Vec<String> = element.classes.iter().map(|c| number(c, index)).collect();
+            attributes.push(("class".into(), classes.join(" ")));
+        }
+        for (key, value) in &element.attributes {
+            attributes.push((key.clone(), number(value, index)));
+        }
+        for (key, value) in default_attributes(&name) {
+            if !attributes.iter().any(|(k, _)| k == key) {
+                attributes.push((key.to_string(), value.to_string()));
+            }
+        }
+
+        self.line(level);
+        self.out.push('<');
+        self.out.push_str(&name);
+        for (key, value) in &attributes {
+            self.out.push_str(&format!(" {}=\"", key));
+            if value.is_empty() {
+                self.stop();
+            }
+            self.out.push_str(value);
+            self.out.push('"');
+        }
+        self.out.push('>');
+        if VOID_ELEMENTS.contains(&name.as_str()) {
+            return;
+        }
+        match text {
+            Some(text) => self.out.push_str(&text),
+            None if children.is_empty() => self.stop(),
+            None => {}
+        }
+        if !children.is_empty() {
+            self.nodes(children, &name, level + 1, index);
+            self.line(level);
+        }
+        self.out.push_str(&format!("</{}>", name));
+    }
+}
+
+/// An HTML5 document, for `!`.
+fn document(writer: &mut Writer) {
+    for (level, line) in [
+        (0, "<!DOCTYPE html>"),
+        (0, "<html lang=\"en\">"),
+        (0, "<head>"),
+        (1, "<meta charset=\"UTF-8\">"),
+        (1, "<meta name=\"viewport\" content=\"width=device-width, initial-scale=1.0\">"),
+        (1, "<title>Document</title>"),
+        (0, "</head>"),
+        (0, "<body>"),
+        (1, ""),
+        (0, "</body>"),
+        (0, "</html>"),
+    ] {
+        writer.line(level);
+        if line.is_empty() {
+            writer.stop();
+        }
+        writer.out.push_str(line);
+    }
+}
+
+fn expand_html(abbr: &str, indent: &str, unit: &str) -> Option<(String, usize)> {
+    let mut writer = Writer { indent, unit, out: String::new(), cursor: None };
+    if abbr == "!" {
+        document(&mut writer);
+    } else {
+        let bare_word = abbr.chars().all(|c| c.is_ascii_alphanumeric());
+        if bare_word && !ELEMENTS.contains(&abbr) {
+            return None;
+        }
+        let mut parser = Parser { chars: abbr.chars().collect(), pos: 0, nesting: 0 };
+        let (nodes, _) = parser.sequence(0)?;
+        if parser.pos != parser.chars.len() {
+            return None;
+        }
+        if element_count(&nodes).is_none() {
+            tracing::debug!(abbreviation = abbr, "abbreviation expands to too many elements");
+            return None;
+        }
+        writer.nodes(&nodes, "", 0, 1);
+    }
+    let cursor = writer.cursor.unwrap_or_else(|| writer.out.chars().count());
+    Some((writer.out, cursor))
+}
+
+// --- CSS ---
+
+/// The column where the CSS abbreviation before the cursor starts: the
+/// start of a declaration, after whitespace, `{` or `;`.
+fn css_abbreviation(before: &[char]) -> Option<usize> {
+    let start = before
+        .iter()
+        .rposition(|c| c.is_whitespace() || matches!(c, '{' | '}' | ';'))
+        .map_or(0, |i| i + 1);
+    let preceding: String = before[..start].iter().collect();
+    let preceding = preceding.trim_end();
+    let declaration = preceding.is_empty() || preceding.ends_with('{') || preceding.ends_with(';');
+    (declaration && start < before.len()).then_some(start)
+}
+
+/// Property abbreviations, which take values as in `m10-auto`.
+const PROPERTIES: &[(&str, &str)] = &[
+    ("m", "margin"),
+    ("mt", "margin-top"),
+    ("mr", "margin-right"),
+    ("mb", "margin-bottom"),
+    ("ml", "margin-left"),
+    ("p", "padding"),
+    ("pt", "padding-top"),
+    ("pr", "padding-right"),
+    ("pb", "padding-bottom"),
+    ("pl", "padding-left"),
+    ("w", "width"),
+    ("h", "height"),
+    ("maw", "max-width"),
+    ("mah", "max-height"),
+    ("miw", "min-width"),
+    ("mih", "min-height"),
+    ("t", "top"),
+    ("r", "right"),
+    ("b", "bottom"),
+    ("l", "left"),
+    ("z", "z-index"),
+    ("op", "opacity"),
+    ("c", "color"),
+    ("bg", "background"),
+    ("bgc", "background-color"),
+    ("bd", "border"),
+    ("bdrs", "border-radius"),
+    ("bxsh", "box-shadow"),
+    ("ff", "font-family"),
+    ("fz", "font-size"),
+    ("fw", "font-weight"),
+    ("lh", "line-height"),
+    ("lts", "letter-spacing"),
+    ("ti", "text-indent"),
+    ("gap", "gap"),
+    ("fx", "flex"),
+    ("fxg", "flex-grow"),
+    ("fxs", "flex-shrink"),
+    ("ord", "order"),
+    ("trf", "transform"),
+    ("trs", "transition"),
+];
+
+/// Whole declarations, for properties usually set to a keyword.
+const KEYWORDS: &[(&str, &str)] = &[
+    ("db", "display: block"),
+    ("di", "display: inline"),
+    ("dib", "display: inline-block"),
+    ("df", "display: flex"),
+    ("dg", "display: grid"),
+    ("dn", "display: none"),
+    ("posr", "position: relative"),
+    ("posa", "position: absolute"),
+    ("posf", "position: fixed"),
+    ("poss", "position: sticky"),
+    ("fll", "float: left"),
+    ("flr", "float: right"),
+    ("fln", "float: none"),
+    ("tal", "text-align: left"),
+    ("tac", "text-align: center"),
+    ("tar", "text-align: right"),
+    ("tdn", "text-decoration: none"),
+    ("tdu", "text-decoration: underline"),
+    ("ttu", "text-transform: uppercase"),
+    ("fwb", "font-weight: bold"),
+    ("fwn", "font-weight: normal"),
+    ("fsi", "font-style: italic"),
+    ("ovh", "overflow: hidden"),
+    ("ova", "overflow: auto"),
+    ("curp", "cursor: pointer"),
+    ("wsnw", "white-space: nowrap"),
+    ("vam", "vertical-align: middle"),
+    ("jcc", "justify-content: center"),
+    ("jcsb", "justify-content: space-between"),
+    ("jcfs", "justify-content: flex-start"),
+    ("jcfe", "justify-content: flex-end"),
+    ("aic", "align-items: center"),
+    ("aifs", "align-items: flex-start"),
+    ("aife", "align-items: flex-end"),
+    ("fxdc", "flex-direction: column"),
+    ("fxdr", "flex-direction: row"),
+    ("fxww", "flex-wrap: wrap"),
+    ("bxzbb", "box-sizing: border-box"),
+    ("bdn", "border: none"),
+];
+
+/// Properties whose numbers take no unit.
+const UNITLESS: &[&str] = &["z-index", "opacity", "font-weight", "line-height", "flex", "flex-grow", "flex-shrink", "order"];
+
+fn expand_css(abbr: &str) -> Option<(String, usize)> {
+    let (abbr, important) = match abbr.strip_suffix('!') {
+        Some(rest) => (rest, " !important"),
+        None => (abbr, ""),
+    };
+    if let Some((_, declaration)) = KEYWORDS.iter().find(|(k, _)| *k == abbr) {
+        let text = format!("{}{};", declaration, important);
+        return Some((text.clone(), text.chars().count()));
+    }
+    let name_len = abbr.find(|c: char| !c.is_ascii_lowercase()).unwrap_or(abbr.len());
+    let (name, values) = abbr.split_at(name_len);
+    let (_, property) = PROPERTIES.iter().find(|(k, _)| *k == name)?;
+    if values.is_empty() {
+        // Leave the cursor where the value goes
+        let text = format!("{}: {};", property, important);
+        let cursor = property.chars().count() + 2;
+        return Some((text, cursor));
+    }
+    let value = if let Some(hex) = values.strip_prefix('#') {
+        color(hex)?
+    } else {
+        numbers(values, UNITLESS.contains(property))?
+    };
+    let text = format!("{}: {}{};", property, value, important);
+    Some((text.clone(), text.chars().count()))
+}
+
+/// `#f` as `#fff`, `#e0` as `#e0e0e0`; three and six digits stay as typed.
+fn color(hex: &str) -> Option<String> {
+    if hex.is_empty() || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    match hex.len() {
+        1 | 2 => Some(format!("#{}", hex.repeat(3))),
+        3 | 6 => Some(format!("#{}", hex)),
+        _ => None,
+    }
+}
+
+/// `10-20` as `10px 20px`, `50p` as `50%`, `1.5e` as `1.5em`, `10--5` as
+/// `10px -5px`. Plain numbers get `px` unless the property is unitless.
+fn numbers(values: &str, unitless: bool) -> Option<String> {
+    let chars: Vec<char> = values.chars().collect();
+    let mut out = Vec::new();
+    let mut i = 0;
+    loop {
+        let start = i;
+        if chars.get(i) == Some(&'-') {
+            i += 1;
+        }
+        let digits = i;
+        while chars.get(i).is_some_and(|c| c.is_ascii_digit() || *c == '.') {
+            i += 1;
+        }
+        if !chars[digits..i].iter().any(char::is_ascii_digit) {
+            return None;
+        }
+        let number: String = chars[start..i].iter().collect();
+        let unit_start = i;
+        while chars.get(i).is_some_and(|c| c.is_ascii_lowercase() || *c == '%') {
+            i += 1;
+        }
+        let unit: String = chars[unit_start..i].iter().collect();
+        let unit = match unit.as_str() {
+            "" if unitless || number.parse::<f64>().ok() == Some(0.0) => "",
+            "" => "px",
+            "p" => "%",
+            "e" => "em",
+            "r" => "rem",
+            "x" => "ex",
+            other => other,
+        };
+        out.push(format!("{}{}", number, unit));
+        if i == chars.len() {
+            return Some(out.join(" "));
+        }
+        if chars[i] != '-' {
+            return None;
+        }
+        i += 1;
+    }
+}
@@ -0,0 +1,188 @@
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use age::armor::{ArmoredReader, ArmoredWriter, Format};
+use age::secrecy::{ExposeSecret, SecretString};
+
+const AGE_HEADER: &[u8] = b"age-encryption.org/";
+const AGE_ARMOR: &[u8] = b"-----BEGIN AGE ENCRYPTED FILE-----";
+const PGP_ARMOR: &[u8] = b"-----BEGIN PGP MESSAGE-----";
+
+/// Which tool a file is encrypted with.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    /// age, with a passphrase.
+    Age { armored: bool },
+    /// OpenPGP, through the `gpg` command.
+    Gpg { armored: bool },
+}
+
+impl Kind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Kind::Age { .. } => "age",
+            Kind::Gpg { .. } => "GPG",
+        }
+    }
+}
+
+/// How an open buffer's file is encrypted, so it can be encrypted the same
+/// way on save. The passphrase only ever lives in memory.
+pub struct Encryption {
+    pub kind: Kind,
+    /// Key ids a GPG file is encrypted to; empty when it uses a passphrase.
+    pub recipients: Vec<String>,
+    passphrase: SecretString,
+}
+
+/// The encryption `bytes`, read from `path`, are in, if any. GPG's binary
+/// format has no magic number, so it is only recognized by extension.
+pub fn detect(path: &Path, bytes: &[u8]) -> Option<Kind> {
+    let text = bytes.trim_ascii_start();
+    if bytes.starts_with(AGE_HEADER) {
+        Some(Kind::Age { armored: false })
+    } else if text.starts_with(AGE_ARMOR) {
+        Some(Kind::Age { armored: true })
+    } else if text.starts_with(PGP_ARMOR) {
+        Some(Kind::Gpg { armored: true })
+    } else {
+        let ext = path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase);
+        let packet = bytes.first().is_some_and(|b| b & 0x80 != 0);
+        (matches!(ext.as_deref(), Some("gpg" | "pgp")) && packet).then_some(Kind::Gpg { armored: false })
+    }
+}
+
+/// Decrypt the contents of `path` into memory. Nothing is written to disk.
+pub fn decrypt(path: &Path, bytes: &[u8], kind: Kind, passphrase: SecretString) -> io::Result<(String, Encryption)> {
+    let (plaintext, recipients) = match kind {
+        Kind::Age { .. } => (decrypt_age(bytes, &passphrase)?, Vec::new()),
+        Kind::Gpg { .. } => (gpg_decrypt(path, &passphrase)?, gpg_recipients(path)?),
+    };
+    let text = String::from_utf8(plaintext)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "the decrypted file is not UTF-8 text"))?;
+    Ok((text, Encryption { kind, recipients, passphrase }))
+}
+
+impl Encryption {
+    /// `plaintext` encrypted the way the file was.
+    pub fn encrypt(&self, plaintext: &str) -> io::Result<Vec<u8>> {
+        match self.kind {
+            Kind::Age { armored } => {
+                let encryptor = age::Encryptor::with_user_passphrase(self.passphrase.clone());
+                let format = if armored { Format::AsciiArmor } else { Format::Binary };
+                let mut out = Vec::new();
+                let armor = ArmoredWriter::wrap_output(&mut out, format)?;
+                let mut writer = encryptor.wrap_output(armor).map_err(io::Error::other)?;
+                writer.write_all(plaintext.as_bytes())?;
+                writer.finish()?.finish()?;
+                Ok(out)
+            }
+            Kind::Gpg { armored } => self.gpg_encrypt(plaintext, armored),
+        }
+    }
+
+    fn gpg_encrypt(&self, plaintext: &str, armored: bool) -> io::Result<Vec<u8>> {
+        let mut command = gpg();
+        if armored {
+            command.arg("--armor");
+        }
+        let mut input = Vec::new();
+        if self.recipients.is_empty() {
+            // The passphrase is the first line of the input, then the text
+            command.args(["--passphrase-fd", "0", "--symmetric"]);
+            input.extend(self.passphrase.expose_secret().as_bytes());
+            input.push(b'\n');
+        } else {
+            command.args(["--trust-model", "always", "--encrypt"]);
+            for recipient in &self.recipients {
+                command.args(["--recipient", recipient]);
+            }
+        }
+        input.extend(plaintext.as_bytes());
+        run_gpg(command, &input)
+    }
+}
+
+fn decrypt_age(bytes: &[u8], passphrase: &SecretString) -> io::Result<Vec<u8>> {
+    let decryptor = age::Decryptor::new(ArmoredReader::new(bytes)).map_err(age_error)?;
+    if !decryptor.is_scrypt() {
+        return Err(io::Error::other("only passphrase-encrypted age files are supported"));
+    }
+    let identity = age::scrypt::Identity::new(passphrase.clone());
+    let mut reader = decryptor
+        .decrypt(std::iter::once(&identity as &dyn age::Identity))
+        .map_err(age_error)?;
+    let mut plaintext = Vec::new();
+    reader.read_to_end(&mut plaintext)?;
+    Ok(plaintext)
+}
+
+fn age_error(e: age::DecryptError) -> io::Error {
+    match e {
+        age::DecryptError::DecryptionFailed | age::DecryptError::NoMatchingKeys => {
+            io::Error::new(io::ErrorKind::PermissionDenied, "wrong passphrase")
+        }
+        age::DecryptError::Io(e) => e,
+        e => io::Error::new(io::ErrorKind::InvalidData, e.to_string()),
+    }
+}
+
+/// `gpg` for unattended use: the passphrase comes from us, never a pinentry
+/// dialog or the agent's cache.
+fn gpg() -> Command {
+    let mut command = Command::new("gpg");
+    command.args(["--batch", "--yes", "--quiet", "--no-symkey-cache", "--pinentry-mode", "loopback"]);
+    command
+}
+
+fn gpg_decrypt(path: &Path, passphrase: &SecretString) -> io::Result<Vec<u8>> {
+    let mut command = gpg();
+    command.args(["--passphrase-fd", "0", "--decrypt"]).arg(path);
+    let mut input = passphrase.expose_secret().as_bytes().to_vec();
+    input.push(b'\n');
+    run_gpg(command, &input)
+}
+
+/// Key ids the file is encrypted to, read without decrypting it.
+fn gpg_recipients(path: &Path) -> io::Result<Vec<String>> {
+    let output = Command::new("gpg")
+        .args(["--batch", "--list-only", "--list-packets"])
+        .arg(path)
+        .stderr(Stdio::null())
+        .output()?;
+    // ":pubkey enc packet: version 3, algo 1, keyid 02DE97B0CAD469B2"
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| line.starts_with(":pubkey enc packet:"))
+        .filter_map(|line| line.rsplit_once("keyid ").map(|(_, id)| id.trim().to_string()))
+        .collect())
+}
+
+/// Run `command` with `input` on stdin; its stdout, or its stderr as the
+/// error.
+fn run_gpg(mut command: Command, input: &[u8]) -> io::Result<Vec<u8>> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| io::Error::new(e.kind(), format!("failed to run gpg: {}", e)))?;
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let input = input.to_vec();
+    // Written from a thread so a full stdout pipe can't deadlock us
+    let writer = std::thread::spawn(move || stdin.write_all(&input));
+    let output = child.wait_with_output()?;
+    let _ = writer.join();
+    if output.status.success() {
+        Ok(output.stdout)
+    } else {
+        let message = String::from_utf8_lossy(&output.stderr).trim().trim_start_matches("gpg: ").to_string();
+        let kind = if message.contains("Bad session key") || message.contains("Bad passphrase") {
+            io::ErrorKind::PermissionDenied
+        } else {
+            io::ErrorKind::Other
+        };
+        Err(io::Error::new(kind, message))
+    }
+}
@@ -0,0 +1,185 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Rename `from` to `to`, moving a different file already at `to` aside to
+/// the trash. Nothing is ever overwritten in place, and `to` is only
+/// trashed once `from` has been moved in next to it, so a move that can't
+/// be made leaves both where they were.
+pub fn rename(from: &Path, to: &Path) -> io::Result<()> {
+    if !to.exists() || same_file(from, to) {
+        return move_path(from, to);
+    }
+    let staged = staging_path(to)?;
+    move_path(from, &staged)?;
+    if let Err(e) = trash(to) {
+        let _ = move_path(&staged, from);
+        return Err(e);
+    }
+    // Same directory, so this can't cross devices
+    fs::rename(&staged, to)
+}
+
+/// An unused hidden name in `path`'s directory to stage a move under.
+fn staging_path(path: &Path) -> io::Result<PathBuf> {
+    let name = path.file_name().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "No file name"))?;
+    Ok(path.with_file_name(format!(".{}.{}.tmp", name.to_string_lossy(), uuid::Uuid::new_v4().simple())))
+}
+
+/// `fs::rename`, falling back to copying and removing when `to` is on
+/// another file system.
+fn move_path(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            if let Err(e) = copy_all(from, to) {
+                // Leave no half copy behind
+                let _ = if to.is_dir() { fs::remove_dir_all(to) } else { fs::remove_file(to) };
+                return Err(e);
+            }
+            if from.is_dir() {
+                fs::remove_dir_all(from)
+            } else {
+                fs::remove_file(from)
+            }
+        }
+        result => result,
+    }
+}
+
+/// Copy a file, or a directory and everything in it.
+fn copy_all(from: &Path, to: &Path) -> io::Result<()> {
+    let meta = fs::symlink_metadata(from)?;
+    if meta.is_dir() {
+        fs::create_dir(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy_all(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        fs::set_permissions(to, meta.permissions())
+    } else if meta.file_type().is_symlink() {
+        copy_symlink(&fs::read_link(from)?, to)
+    } else {
+        fs::copy(from, to).map(|_| ())
+    }
+}
+
+#[cfg(unix)]
+fn copy_symlink(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+fn copy_symlink(target: &Path, link: &Path) -> io::Result<()> {
+    if target.is_dir() {
+        std::os::windows::fs::symlink_dir(target, link)
+    } else {
+        std::os::windows::fs::symlink_file(target, link)
+    }
+}
+
+/// Whether two paths name the same file, e.g. a case-only rename on a
+/// case-insensitive file system.
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// `path` with ` n` added to its file stem, e.g. `notes 2.txt`.
+#[cfg(not(any(target_os = "macos", windows)))]
+fn numbered(path: &Path, n: usize) -> PathBuf {
+    let stem = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    let extension = path.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+    path.with_file_name(format!("{} {}{}", stem, n, extension))
+}
+
+/// Move `path` to the Trash through Finder.
+#[cfg(target_os = "macos")]
+pub fn trash(path: &Path) -> io::Result<()> {
+    let path = fs::canonicalize(path)?;
+    let quoted = path.to_string_lossy().replace('\\', "\\\\").replace('"', "\\\"");
+    let script = format!("tell application \"Finder\" to delete POSIX file \"{}\"", quoted);
+    check(Command::new("osascript").arg("-e").arg(script).output()?)
+}
+
+/// Move `path` to the Recycle Bin.
+#[cfg(windows)]
+pub fn trash(path: &Path) -> io::Result<()> {
+    let path = fs::canonicalize(path)?;
+    let method = if path.is_dir() { "DeleteDirectory" } else { "DeleteFile" };
+    // The PowerShell string escapes single quotes by doubling
+    let script = format!(
+        "Add-Type -AssemblyName Microsoft.VisualBasic; [Microsoft.VisualBasic.FileIO.FileSystem]::{}('{}', 'OnlyErrorDialogs', 'SendToRecycleBin')",
+        method,
+        path.display().to_string().replace('\'', "''"),
+    );
+    check(
+        Command::new("powershell")
+            .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+            .output()?,
+    )
+}
+
+/// Move `path` to the freedesktop home trash, where file managers can
+/// restore it from. Files on another file system go through `gio trash`,
+/// which knows about per-volume trash directories.
+#[cfg(not(any(target_os = "macos", windows)))]
+pub fn trash(path: &Path) -> io::Result<()> {
+    use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+    use std::io::Write;
+
+    const PATH_ESCAPES: &AsciiSet = &CONTROLS.add(b' ').add(b'%').add(b'\n');
+
+    let path = fs::canonicalize(path)?;
+    let trash = dirs::data_dir()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No data directory"))?
+        .join("Trash");
+    fs::create_dir_all(trash.join("files"))?;
+    fs::create_dir_all(trash.join("info"))?;
+
+    // Claim a name by creating its info file, as the spec asks
+    let name = path.file_name().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "No file name"))?;
+    let (trashed, info_path, mut info) = (1..)
+        .map(|n| match n {
+            1 => trash.join("files").join(name),
+            n => numbered(&trash.join("files").join(name), n),
+        })
+        .map(|trashed| {
+            let file_name = trashed.file_name().unwrap_or(name).to_string_lossy().into_owned();
+            let info_path = trash.join("info").join(format!("{}.trashinfo", file_name));
+            (trashed, info_path)
+        })
+        .filter(|(trashed, _)| !trashed.exists())
+        .find_map(|(trashed, info_path)| {
+            match fs::OpenOptions::new().write(true).create_new(true).open(&info_path) {
+                Ok(file) => Some(Ok((trashed, info_path, file))),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => None,
+                Err(e) => Some(Err(e)),
+            }
+        })
+        .unwrap_or_else(|| Err(io::Error::other("No free name in the trash")))?;
+    write!(
+        info,
+        "[Trash Info]\nPath={}\nDeletionDate={}\n",
+        utf8_percent_encode(&path.to_string_lossy(), PATH_ESCAPES),
+        chrono::Local::now().format("%Y-%m-%dT%H:%M:%S"),
+    )?;
+    if let Err(e) = fs::rename(&path, &trashed) {
+        let _ = fs::remove_file(&info_path);
+        if e.kind() != io::ErrorKind::CrossesDevices {
+            return Err(e);
+        }
+        return check(Command::new("gio").arg("trash").arg(&path).output()?);
+    }
+    Ok(())
+}
+
+fn check(output: std::process::Output) -> io::Result<()> {
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(String::from_utf8_lossy(&output.stderr).trim().to_string()))
+    }
+}
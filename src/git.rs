@@ -0,0 +1,288 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use git2::{ErrorCode, IndexAddOption, ObjectType, Repository, Status, StatusOptions};
+
+/// What happened to a file, on one side of the index.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Change {
+    Added,
+    Modified,
+    Deleted,
+    Renamed,
+    TypeChange,
+    Untracked,
+    Conflicted,
+}
+
+impl Change {
+    /// One-letter code, as `git status --short` shows it.
+    pub fn letter(self) -> &'static str {
+        match self {
+            Change::Added => "A",
+            Change::Modified => "M",
+            Change::Deleted => "D",
+            Change::Renamed => "R",
+            Change::TypeChange => "T",
+            Change::Untracked => "U",
+            Change::Conflicted => "!",
+        }
+    }
+}
+
+/// A changed file; `staged` compares the index with HEAD and `unstaged`
+/// the working tree with the index.
+#[derive(Clone, Debug)]
+pub struct FileStatus {
+    /// Relative to the repository root.
+    pub path: PathBuf,
+    pub staged: Option<Change>,
+    pub unstaged: Option<Change>,
+}
+
+fn staged_change(s: Status) -> Option<Change> {
+    if s.is_conflicted() {
+        None
+    } else if s.is_index_new() {
+        Some(Change::Added)
+    } else if s.is_index_modified() {
+        Some(Change::Modified)
+    } else if s.is_index_deleted() {
+        Some(Change::Deleted)
+    } else if s.is_index_renamed() {
+        Some(Change::Renamed)
+    } else if s.is_index_typechange() {
+        Some(Change::TypeChange)
+    } else {
+        None
+    }
+}
+
+fn unstaged_change(s: Status) -> Option<Change> {
+    if s.is_conflicted() {
+        Some(Change::Conflicted)
+    } else if s.is_wt_new() {
+        Some(Change::Untracked)
+    } else if s.is_wt_modified() {
+        Some(Change::Modified)
+    } else if s.is_wt_deleted() {
+        Some(Change::Deleted)
+    } else if s.is_wt_renamed() {
+        Some(Change::Renamed)
+    } else if s.is_wt_typechange() {
+        Some(Change::TypeChange)
+    } else {
+        None
+    }
+}
+
+/// The repository containing a workspace.
+pub struct Repo {
+    repo: Repository,
+    pub root: PathBuf,
+}
+
+impl Repo {
+    /// The repository `path` is in, if any.
+    pub fn discover(path: &Path) -> Result<Self, git2::Error> {
+        let repo = Repository::discover(path)?;
+        let root = repo
+            .workdir()
+            .map(Path::to_path_buf)
+            .ok_or_else(|| git2::Error::from_str("bare repositories have no working tree"))?;
+        Ok(Self { repo, root })
+    }
+
+    /// Current branch name, or a short commit id when HEAD is detached.
+    pub fn branch(&self) -> Option<String> {
+        match self.repo.head() {
+            Ok(head) if head.is_branch() => head.shorthand().map(str::to_string),
+            Ok(head) => head.target().map(|id| id.to_string()[..7].to_string()),
+            // A new repository: HEAD names a branch with no commits yet
+            Err(_) => self
+                .repo
+                .find_reference("HEAD")
+                .ok()
+                .and_then(|r| r.symbolic_target().map(|t| t.trim_start_matches("refs/heads/").to_string())),
+        }
+    }
+
+    /// Changed and untracked files, sorted by path.
+    pub fn status(&self) -> Result<Vec<FileStatus>, git2::Error> {
+        let mut options = StatusOptions::new();
+        options.include_untracked(true).recurse_untracked_dirs(true).renames_head_to_index(true);
+        let statuses = self.repo.statuses(Some(&mut options))?;
+        let mut files: Vec<FileStatus> = statuses
+            .iter()
+            .filter_map(|entry| {
+                let status = entry.status();
+                Some(FileStatus {
+                    path: PathBuf::from(entry.path()?),
+                    staged: staged_change(status),
+                    unstaged: unstaged_change(status),
+                })
+            })
+            .filter(|f| f.staged.is_some() || f.unstaged.is_some())
+            .collect();
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(files)
+    }
+
+    /// How one file differs from HEAD: "unmodified", "ignored", or its
+    /// staged and unstaged changes as in the source control panel.
+    pub fn describe_file(&self, path: &Path) -> Result<String, git2::Error> {
+        let relative = path.strip_prefix(&self.root).map_err(|_| git2::Error::from_str("outside the repository"))?;
+        let status = self.repo.status_file(relative)?;
+        if status.is_ignored() {
+            return Ok("ignored".to_string());
+        }
+        if unstaged_change(status) == Some(Change::Untracked) {
+            return Ok("untracked".to_string());
+        }
+        let parts: Vec<String> = [("staged", staged_change(status)), ("unstaged", unstaged_change(status))]
+            .into_iter()
+            .filter_map(|(side, change)| change.map(|c| format!("{} {:?}", side, c).to_lowercase()))
+            .collect();
+        Ok(if parts.is_empty() { "unmodified".to_string() } else { parts.join(", ") })
+    }
+
+    /// Add the working tree version of `path` to the index; a deleted file
+    /// is removed from it.
+    pub fn stage(&self, path: &Path) -> Result<(), git2::Error> {
+        let mut index = self.repo.index()?;
+        if self.root.join(path).exists() {
+            index.add_all([path], IndexAddOption::DEFAULT, None)?;
+        } else {
+            index.remove_path(path)?;
+        }
+        index.write()
+    }
+
+    /// Put the HEAD version of `path` back in the index, keeping the
+    /// working tree as it is.
+    pub fn unstage(&self, path: &Path) -> Result<(), git2::Error> {
+        match self.repo.head().and_then(|h| h.peel(ObjectType::Commit)) {
+            Ok(head) => self.repo.reset_default(Some(&head), [path]),
+            // Nothing committed yet: unstaging removes it from the index
+            Err(e) if e.code() == ErrorCode::UnbornBranch => {
+                let mut index = self.repo.index()?;
+                index.remove_path(path)?;
+                index.write()
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Commit the index with `message`, as the user configured in git.
+    /// Returns the new commit's short id.
+    pub fn commit(&self, message: &str) -> Result<String, git2::Error> {
+        let signature = self.repo.signature()?;
+        let mut index = self.repo.index()?;
+        let tree = self.repo.find_tree(index.write_tree()?)?;
+        let parent = match self.repo.head() {
+            Ok(head) => Some(head.peel_to_commit()?),
+            Err(e) if e.code() == ErrorCode::UnbornBranch => None,
+            Err(e) => return Err(e),
+        };
+        if let Some(parent) = &parent {
+            if parent.tree_id() == tree.id() {
+                return Err(git2::Error::from_str("nothing is staged"));
+            }
+        }
+        let parents: Vec<_> = parent.iter().collect();
+        let id = self.repo.commit(Some("HEAD"), &signature, &signature, message, &tree, &parents)?;
+        Ok(id.to_string()[..7].to_string())
+    }
+
+    /// The two sides of a file's change: HEAD and the index when `staged`,
+    /// otherwise the index and the working tree. Missing sides are empty.
+    pub fn diff_texts(&self, path: &Path, staged: bool) -> Result<(String, String), git2::Error> {
+        let index = self.repo.index()?;
+        let in_index = || -> Result<String, git2::Error> {
+            match index.get_path(path, 0) {
+                Some(entry) => Ok(blob_text(&self.repo.find_blob(entry.id)?)),
+                None => Ok(String::new()),
+            }
+        };
+        if staged {
+            let in_head = match self.repo.head().and_then(|h| h.peel_to_tree()) {
+                Ok(tree) => match tree.get_path(path) {
+                    Ok(entry) => blob_text(&self.repo.find_blob(entry.id())?),
+                    Err(e) if e.code() == ErrorCode::NotFound => String::new(),
+                    Err(e) => return Err(e),
+                },
+                Err(e) if e.code() == ErrorCode::UnbornBranch => String::new(),
+                Err(e) => return Err(e),
+            };
+            Ok((in_head, in_index()?))
+        } else {
+            let on_disk = std::fs::read(self.root.join(path))
+                .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+                .unwrap_or_default();
+            Ok((in_index()?, on_disk))
+        }
+    }
+}
+
+fn blob_text(blob: &git2::Blob) -> String {
+    if blob.is_binary() {
+        "(binary file)".to_string()
+    } else {
+        String::from_utf8_lossy(blob.content()).into_owned()
+    }
+}
+
+/// Push or pull with the `git` command, so remotes use the user's own
+/// credential helpers and SSH setup.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Remote {
+    Push,
+    Pull,
+}
+
+impl Remote {
+    pub fn name(self) -> &'static str {
+        match self {
+            Remote::Push => "Push",
+            Remote::Pull => "Pull",
+        }
+    }
+}
+
+/// Outcome of a push or pull.
+pub struct RemoteOutput {
+    pub remote: Remote,
+    pub success: bool,
+    /// Everything git printed.
+    pub output: String,
+}
+
+/// Run `git push` or `git pull` in `root` and wait for it to finish.
+pub fn run_remote(root: &Path, remote: Remote) -> RemoteOutput {
+    let arg = match remote {
+        Remote::Push => "push",
+        Remote::Pull => "pull",
+    };
+    let result = Command::new("git")
+        .arg(arg)
+        .current_dir(root)
+        // Never wait on a password prompt nobody can see
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .output();
+    match result {
+        Ok(out) => RemoteOutput {
+            remote,
+            success: out.status.success(),
+            output: format!(
+                "{}{}",
+                String::from_utf8_lossy(&out.stdout),
+                String::from_utf8_lossy(&out.stderr)
+            ),
+        },
+        Err(e) => RemoteOutput {
+            remote,
+            success: false,
+            output: format!("failed to run git: {}", e),
+        },
+    }
+}
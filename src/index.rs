@@ -0,0 +1,361 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use eframe::egui;
+use ignore::gitignore::Gitignore;
+use ignore::{Match, WalkBuilder};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::activity::Activities;
+
+/// Scanned files between updates of the status bar's count.
+const PROGRESS_STEP: usize = 100;
+/// Files larger than this are listed but not scanned for words.
+const MAX_WORD_SCAN_BYTES: u64 = 1024 * 1024;
+/// Shortest word worth offering as a completion.
+const MIN_WORD_LEN: usize = 3;
+/// File events are batched until none arrive for this long...
+const DEBOUNCE: Duration = Duration::from_millis(200);
+/// ...or until the oldest has waited this long, so the steady churn of a
+/// long build doesn't hold back every other change.
+const MAX_DELAY: Duration = Duration::from_secs(2);
+
+enum Message {
+    Changed(Vec<PathBuf>),
+    Stop,
+}
+
+#[derive(Default)]
+struct IndexState {
+    /// Sorted file list, rebuilt after each batch of changes.
+    files: Arc<Vec<PathBuf>>,
+    /// Words per file, so a changed file's old words can be removed.
+    file_words: HashMap<PathBuf, HashSet<String>>,
+    /// Every indexed word with the number of files containing it.
+    words: BTreeMap<String, usize>,
+    /// Counts rebuilds of the file list.
+    generation: u64,
+    ready: bool,
+}
+
+impl IndexState {
+    fn remove_file(&mut self, path: &Path) {
+        if let Some(words) = self.file_words.remove(path) {
+            for word in words {
+                if let Some(count) = self.words.get_mut(&word) {
+                    *count -= 1;
+                    if *count == 0 {
+                        self.words.remove(&word);
+                    }
+                }
+            }
+        }
+    }
+
+    fn add_file(&mut self, path: PathBuf, words: HashSet<String>) {
+        self.remove_file(&path);
+        for word in &words {
+            *self.words.entry(word.clone()).or_insert(0) += 1;
+        }
+        self.file_words.insert(path, words);
+    }
+
+    fn rebuild_file_list(&mut self) {
+        let mut files: Vec<PathBuf> = self.file_words.keys().cloned().collect();
+        files.sort();
+        self.files = Arc::new(files);
+        self.generation += 1;
+    }
+}
+
+/// Background index of a workspace: the file list (honoring `.gitignore` and
+/// hidden-file rules) and the words in those files. Shared by quick-open,
+/// project search and word completion, and kept current by a file watcher.
+pub struct FileIndex {
+    state: Arc<RwLock<IndexState>>,
+    paused: Arc<AtomicBool>,
+    tx: Sender<Message>,
+    _watcher: Option<RecommendedWatcher>,
+}
+
+impl FileIndex {
+    /// Start indexing `root` on a background thread. `ctx` is woken when
+    /// the initial scan completes; until then it is listed in `activities`.
+    pub fn start(root: PathBuf, ctx: egui::Context, activities: Activities) -> Self {
+        let state = Arc::new(RwLock::new(IndexState::default()));
+        let paused = Arc::new(AtomicBool::new(false));
+        let (tx, rx) = mpsc::channel();
+
+        let watch_tx = tx.clone();
+        let watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            if let Ok(event) = event {
+                let _ = watch_tx.send(Message::Changed(event.paths));
+            }
+        })
+        .and_then(|mut w| w.watch(&root, RecursiveMode::Recursive).map(|_| w));
+        let watcher = match watcher {
+            Ok(w) => Some(w),
+            Err(e) => {
+                tracing::warn!(root = %root.display(), error = %e, "file watcher unavailable; index will not refresh");
+                None
+            }
+        };
+
+        let worker = Worker {
+            root,
+            state: state.clone(),
+            paused: paused.clone(),
+            ignores: HashMap::new(),
+            ctx,
+            activities,
+        };
+        std::thread::Builder::new()
+            .name("file-index".into())
+            .spawn(move || worker.run(rx))
+            .ok();
+
+        Self {
+            state,
+            paused,
+            tx,
+            _watcher: watcher,
+        }
+    }
+
+    /// Hold back incremental updates, e.g. while the window is idle. Events
+    /// keep queuing and are applied on resume.
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Relaxed);
+    }
+
+    /// True once the initial scan has finished.
+    pub fn is_ready(&self) -> bool {
+        self.state.read().is_ok_and(|s| s.ready)
+    }
+
+    /// Changes whenever files were added or removed (or may have been), so
+    /// callers can notice without comparing lists.
+    pub fn generation(&self) -> u64 {
+        self.state.read().map(|s| s.generation).unwrap_or_default()
+    }
+
+    /// Indexed files, sorted.
+    pub fn files(&self) -> Arc<Vec<PathBuf>> {
+        self.state
+            .read()
+            .map(|s| s.files.clone())
+            .unwrap_or_default()
+    }
+
+    /// Indexed words starting with `prefix`, most widespread first.
+    pub fn complete(&self, prefix: &str, limit: usize) -> Vec<String> {
+        let Ok(state) = self.state.read() else {
+            return Vec::new();
+        };
+        let mut matches: Vec<(&String, usize)> = state
+            .words
+            .range(prefix.to_string()..)
+            .take_while(|(w, _)| w.starts_with(prefix))
+            .filter(|(w, _)| w.as_str() != prefix)
+            .map(|(w, c)| (w, *c))
+            .collect();
+        matches.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        matches.into_iter().take(limit).map(|(w, _)| w.clone()).collect()
+    }
+}
+
+impl Drop for FileIndex {
+    fn drop(&mut self) {
+        let _ = self.tx.send(Message::Stop);
+    }
+}
+
+struct Worker {
+    root: PathBuf,
+    state: Arc<RwLock<IndexState>>,
+    paused: Arc<AtomicBool>,
+    /// Parsed `.gitignore` per directory, for checking single changed paths.
+    ignores: HashMap<PathBuf, Option<Gitignore>>,
+    ctx: egui::Context,
+    activities: Activities,
+}
+
+impl Worker {
+    fn run(mut self, rx: Receiver<Message>) {
+        let started = std::time::Instant::now();
+        self.rescan();
+        if let Ok(state) = self.state.read() {
+            tracing::info!(
+                root = %self.root.display(),
+                files = state.files.len(),
+                words = state.words.len(),
+                elapsed_ms = started.elapsed().as_millis() as u64,
+                "workspace indexed"
+            );
+        }
+
+        let mut pending: BTreeSet<PathBuf> = BTreeSet::new();
+        let mut oldest: Option<Instant> = None;
+        loop {
+            match rx.recv_timeout(DEBOUNCE) {
+                Ok(Message::Changed(paths)) => {
+                    pending.extend(paths);
+                    if oldest.get_or_insert_with(Instant::now).elapsed() < MAX_DELAY {
+                        continue;
+                    }
+                }
+                Ok(Message::Stop) | Err(RecvTimeoutError::Disconnected) => return,
+                Err(RecvTimeoutError::Timeout) => {}
+            }
+            if pending.is_empty() || self.paused.load(Ordering::Relaxed) {
+                continue;
+            }
+            let changed = std::mem::take(&mut pending);
+            oldest = None;
+            // A changed ignore file can hide or reveal anything below it
+            if changed.iter().any(|p| p.file_name().is_some_and(|n| n == ".gitignore")) {
+                self.ignores.clear();
+                self.rescan();
+                tracing::debug!("ignore rules changed; workspace re-indexed");
+                continue;
+            }
+            for path in changed {
+                self.update_path(&path);
+            }
+            if let Ok(mut state) = self.state.write() {
+                state.rebuild_file_list();
+            }
+            // Refresh quick-open if it is showing
+            self.ctx.request_repaint();
+        }
+    }
+
+    fn rescan(&mut self) {
+        let activity = self.activities.start_cancellable("Indexing workspace");
+        let mut scanned = Vec::new();
+        for entry in WalkBuilder::new(&self.root).require_git(false).build().flatten() {
+            // Cancelled: keep what was found so far, and let file events
+            // fill in the rest as they come
+            if activity.is_cancelled() {
+                tracing::info!(files = scanned.len(), "workspace indexing cancelled");
+                break;
+            }
+            if entry.file_type().is_some_and(|t| t.is_file()) {
+                let path = entry.into_path();
+                let words = scan_words(&path);
+                scanned.push((path, words));
+                if scanned.len() % PROGRESS_STEP == 0 {
+                    activity.set_label(format!("Indexing workspace ({} files)", scanned.len()));
+                }
+            }
+        }
+        if let Ok(mut state) = self.state.write() {
+            state.file_words.clear();
+            state.words.clear();
+            for (path, words) in scanned {
+                state.add_file(path, words);
+            }
+            state.rebuild_file_list();
+            state.ready = true;
+        }
+        self.ctx.request_repaint();
+    }
+
+    /// Re-read, add or remove a single path after a file-system event.
+    fn update_path(&mut self, path: &Path) {
+        let Ok(meta) = fs::metadata(path) else {
+            // Build output comes and goes constantly; none of it is indexed
+            if self.is_ignored(path, false) {
+                return;
+            }
+            // Deleted: drop the path and anything that was under it
+            if let Ok(mut state) = self.state.write() {
+                let gone: Vec<PathBuf> = state
+                    .file_words
+                    .keys()
+                    .filter(|p| p.starts_with(path))
+                    .cloned()
+                    .collect();
+                for p in gone {
+                    state.remove_file(&p);
+                }
+            }
+            return;
+        };
+        if self.is_ignored(path, meta.is_dir()) {
+            return;
+        }
+        if meta.is_dir() {
+            // A directory moved in; walk it (parent ignore files still apply)
+            for entry in WalkBuilder::new(path).require_git(false).build().flatten() {
+                if entry.file_type().is_some_and(|t| t.is_file()) {
+                    let file = entry.into_path();
+                    let words = scan_words(&file);
+                    if let Ok(mut state) = self.state.write() {
+                        state.add_file(file, words);
+                    }
+                }
+            }
+        } else if meta.is_file() {
+            let words = scan_words(path);
+            if let Ok(mut state) = self.state.write() {
+                state.add_file(path.to_path_buf(), words);
+            }
+        }
+    }
+
+    /// Same rules as the initial walk: hidden components and `.gitignore`
+    /// files from the root down to the path, nearest first.
+    fn is_ignored(&mut self, path: &Path, is_dir: bool) -> bool {
+        let Ok(rel) = path.strip_prefix(&self.root) else {
+            return true;
+        };
+        if rel
+            .components()
+            .any(|c| c.as_os_str().to_string_lossy().starts_with('.'))
+        {
+            return true;
+        }
+        let mut dir = path.parent();
+        while let Some(d) = dir {
+            if !d.starts_with(&self.root) {
+                break;
+            }
+            let gitignore = self
+                .ignores
+                .entry(d.to_path_buf())
+                .or_insert_with(|| {
+                    let file = d.join(".gitignore");
+                    file.exists().then(|| Gitignore::new(&file).0)
+                });
+            if let Some(gi) = gitignore {
+                match gi.matched_path_or_any_parents(path, is_dir) {
+                    Match::Ignore(_) => return true,
+                    Match::Whitelist(_) => return false,
+                    Match::None => {}
+                }
+            }
+            dir = d.parent();
+        }
+        false
+    }
+}
+
+/// Distinct identifier-like words in a text file; empty for large or binary files.
+fn scan_words(path: &Path) -> HashSet<String> {
+    if fs::metadata(path).map_or(true, |m| m.len() > MAX_WORD_SCAN_BYTES) {
+        return HashSet::new();
+    }
+    let Ok(text) = fs::read_to_string(path) else {
+        return HashSet::new();
+    };
+    text.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|w| w.chars().count() >= MIN_WORD_LEN && !w.starts_with(|c: char| c.is_ascii_digit()))
+        .map(str::to_string)
+        .collect()
+}
@@ -0,0 +1,119 @@
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicIsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+use eframe::egui;
+
+use crate::activity::ActivityHandle;
+use crate::crash;
+
+/// Most worker threads kept. Jobs that find every worker busy start another
+/// up to this many, so a long build task doesn't hold up a quick save.
+const MAX_WORKERS: usize = 16;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// A job that panicked, delivered in place of its result.
+#[derive(Debug)]
+pub struct JobPanic {
+    /// What the job was listed as in the status bar.
+    pub label: String,
+    pub message: String,
+}
+
+/// Thread pool for one-off background work, instead of a thread and a
+/// channel per feature. Each job returns a message `M` that the UI thread
+/// collects with [`finished`](Self::finished) on its next frame.
+///
+/// A job carries the [`ActivityHandle`] it is listed under in the status
+/// bar, which is also its cancellation token: a job cancelled before it
+/// starts is skipped, and the result of one cancelled while running is
+/// dropped. Long jobs can check it themselves to stop early.
+///
+/// The pool runs work that starts, produces one result and ends: tasks,
+/// linters, git remote commands, elevated saves, decryption, the TODO scan,
+/// Rename Symbol's word search and the palette's `#` search. Threads that
+/// live as long as what they serve keep their own: the file index watcher,
+/// collab sessions, run and debug output readers and language server pipes.
+/// Opening files and highlighting stay on the UI thread.
+pub struct Jobs<M> {
+    queue: Sender<Job>,
+    shared: Arc<Mutex<Receiver<Job>>>,
+    /// Idle workers minus queued jobs; below zero means jobs are waiting.
+    available: Arc<AtomicIsize>,
+    workers: usize,
+    done_tx: Sender<M>,
+    done_rx: Receiver<M>,
+    ctx: egui::Context,
+}
+
+impl<M: From<JobPanic> + Send + 'static> Jobs<M> {
+    /// An empty pool; workers start with the first jobs. `ctx` is woken
+    /// whenever a result arrives.
+    pub fn new(ctx: egui::Context) -> Self {
+        let (queue, jobs) = mpsc::channel();
+        let (done_tx, done_rx) = mpsc::channel();
+        Self {
+            queue,
+            shared: Arc::new(Mutex::new(jobs)),
+            available: Arc::default(),
+            workers: 0,
+            done_tx,
+            done_rx,
+            ctx,
+        }
+    }
+
+    /// Run `work` on a worker thread and deliver its result, unless
+    /// `activity` is cancelled first. If `work` panics, a [`JobPanic`] is
+    /// delivered instead and the worker carries on.
+    pub fn spawn(&mut self, activity: ActivityHandle, work: impl FnOnce(&ActivityHandle) -> M + Send + 'static) {
+        let done = self.done_tx.clone();
+        let ctx = self.ctx.clone();
+        let job: Job = Box::new(move || {
+            if activity.is_cancelled() {
+                return;
+            }
+            let result = std::panic::catch_unwind(AssertUnwindSafe(|| work(&activity))).unwrap_or_else(|payload| {
+                M::from(JobPanic {
+                    label: activity.label(),
+                    message: crash::panic_message(&*payload),
+                })
+            });
+            if !activity.is_cancelled() {
+                let _ = done.send(result);
+                ctx.request_repaint();
+            }
+        });
+        if self.available.fetch_sub(1, Ordering::SeqCst) <= 0 && self.workers < MAX_WORKERS {
+            self.start_worker();
+        }
+        let _ = self.queue.send(job);
+    }
+
+    fn start_worker(&mut self) {
+        self.workers += 1;
+        let jobs = self.shared.clone();
+        let available = self.available.clone();
+        let spawned = std::thread::Builder::new()
+            .name(format!("job-{}", self.workers))
+            .spawn(move || loop {
+                available.fetch_add(1, Ordering::SeqCst);
+                // Dropping the pool closes the queue and ends the worker
+                let Ok(job) = jobs.lock().unwrap_or_else(|e| e.into_inner()).recv() else {
+                    return;
+                };
+                job();
+            });
+        if let Err(e) = spawned {
+            tracing::error!(error = %e, "failed to start a job worker");
+            self.workers -= 1;
+        }
+    }
+
+    /// Results of the jobs that finished since the last call, in order.
+    pub fn finished(&self) -> Vec<M> {
+        self.done_rx.try_iter().collect()
+    }
+}
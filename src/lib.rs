@@ -0,0 +1,47 @@
+pub mod activity;
+pub mod app;
+pub mod base_protocol;
+pub mod calc;
+pub mod clipboard;
+pub mod codec;
+pub mod collab;
+pub mod color_literals;
+pub mod commands;
+pub mod config;
+pub mod conflicts;
+pub mod crash;
+pub mod debugger;
+pub mod diagnostics;
+pub mod diff;
+pub mod editor;
+pub mod edits;
+pub mod elevated;
+pub mod emmet;
+pub mod encryption;
+pub mod file_ops;
+pub mod git;
+pub mod index;
+pub mod jobs;
+pub mod json;
+pub mod local_history;
+pub mod logging;
+pub mod lsp;
+pub mod markers;
+pub mod path_refs;
+pub mod reflow;
+pub mod reveal;
+pub mod rope_search;
+pub mod runner;
+pub mod scratch;
+pub mod scripting;
+pub mod search_history;
+pub mod stats;
+pub mod symbols;
+pub mod syntax;
+pub mod todos;
+pub mod ui;
+pub mod undo;
+pub mod wait;
+pub mod workspace;
+pub mod workspace_search;
+
@@ -0,0 +1,78 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::crash;
+
+/// Saved versions kept per file; older ones are deleted.
+const MAX_VERSIONS: usize = 50;
+/// Records the original path inside each file's history directory.
+const SOURCE_FILE: &str = "source";
+
+/// A previous save of a file.
+#[derive(Clone, Debug)]
+pub struct Version {
+    /// Copy of the contents in the history directory.
+    pub path: PathBuf,
+    pub time: SystemTime,
+    pub size: u64,
+}
+
+fn history_dir() -> PathBuf {
+    crash::app_data_dir().join("history")
+}
+
+/// History directory for one file, named by a stable hash of its path.
+fn file_dir(file: &Path) -> PathBuf {
+    let file = file.canonicalize().unwrap_or_else(|_| file.to_path_buf());
+    // FNV-1a, so the name is the same across runs and builds
+    let hash = file
+        .to_string_lossy()
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325u64, |h, b| (h ^ b as u64).wrapping_mul(0x0100_0000_01b3));
+    history_dir().join(format!("{:016x}", hash))
+}
+
+/// Keep a copy of `text` as the newest saved version of `file`, unless it
+/// matches the newest version already kept.
+pub fn record(file: &Path, text: &str) -> std::io::Result<()> {
+    let dir = file_dir(file);
+    if let Some(latest) = versions(file).first() {
+        if fs::read_to_string(&latest.path).is_ok_and(|t| t == text) {
+            return Ok(());
+        }
+    }
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join(SOURCE_FILE), file.to_string_lossy().as_bytes())?;
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    fs::write(dir.join(format!("{}.txt", millis)), text)?;
+
+    for old in versions(file).iter().skip(MAX_VERSIONS) {
+        let _ = fs::remove_file(&old.path);
+    }
+    Ok(())
+}
+
+/// Saved versions of `file`, newest first.
+pub fn versions(file: &Path) -> Vec<Version> {
+    let Ok(entries) = fs::read_dir(file_dir(file)) else {
+        return Vec::new();
+    };
+    let mut versions: Vec<Version> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let millis: u64 = path.file_stem()?.to_str()?.parse().ok()?;
+            Some(Version {
+                time: UNIX_EPOCH + Duration::from_millis(millis),
+                size: entry.metadata().ok()?.len(),
+                path,
+            })
+        })
+        .collect();
+    versions.sort_by_key(|v| std::cmp::Reverse(v.time));
+    versions
+}
@@ -0,0 +1,68 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{fmt, reload, Registry};
+
+/// Environment variable overriding the startup log level (e.g. `debug`).
+const LEVEL_ENV: &str = "LUX_EDIT_LOG";
+/// Daily log files kept before the oldest is deleted.
+const MAX_LOG_FILES: usize = 7;
+
+pub const LEVELS: [LevelFilter; 5] = [
+    LevelFilter::ERROR,
+    LevelFilter::WARN,
+    LevelFilter::INFO,
+    LevelFilter::DEBUG,
+    LevelFilter::TRACE,
+];
+
+static LEVEL_HANDLE: OnceLock<reload::Handle<LevelFilter, Registry>> = OnceLock::new();
+
+/// Directory holding the rotating `lux-edit.log.*` files.
+pub fn log_dir() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("lux-edit")
+        .join("logs")
+}
+
+/// Install the global subscriber: a rotating log file plus stderr, both
+/// behind a level filter that can be changed at runtime.
+pub fn init() {
+    let level = std::env::var(LEVEL_ENV)
+        .ok()
+        .and_then(|v| LevelFilter::from_str(&v).ok())
+        .unwrap_or(LevelFilter::INFO);
+    let (filter, handle) = reload::Layer::new(level);
+
+    let file_layer = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix("lux-edit")
+        .filename_suffix("log")
+        .max_log_files(MAX_LOG_FILES)
+        .build(log_dir())
+        .ok()
+        .map(|appender| fmt::layer().with_writer(appender).with_ansi(false));
+
+    let result = tracing_subscriber::registry()
+        .with(filter)
+        .with(file_layer)
+        .with(fmt::layer().with_writer(std::io::stderr))
+        .try_init();
+    if result.is_ok() {
+        let _ = LEVEL_HANDLE.set(handle);
+    }
+}
+
+/// Change the active log level.
+pub fn set_level(level: LevelFilter) {
+    if let Some(handle) = LEVEL_HANDLE.get() {
+        if handle.reload(level).is_ok() {
+            tracing::info!(%level, "log level changed");
+        }
+    }
+}
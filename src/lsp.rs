@@ -0,0 +1,759 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+
+use eframe::egui;
+use ropey::{Rope, RopeSlice};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
+use serde_json::{json, Value};
+
+use crate::base_protocol::{self, Incoming};
+use crate::diagnostics::{Diagnostic, Severity};
+use crate::edits::{CodeAction, TextEdit, WorkspaceEdit};
+use crate::editor::{Editor, Position};
+use crate::undo;
+
+/// Most completions offered from one response.
+const MAX_COMPLETIONS: usize = 200;
+const URI_ESCAPES: &AsciiSet = &CONTROLS.add(b' ').add(b'"').add(b'#').add(b'%').add(b'?').add(b'<').add(b'>');
+
+/// A language server started without any configuration for the files it
+/// covers, in the project folder found from them.
+pub struct ServerPreset {
+    pub name: &'static str,
+    pub command: &'static str,
+    pub language_id: &'static str,
+    pub extensions: &'static [&'static str],
+    /// A file that marks a project's folder.
+    pub root_marker: &'static str,
+    /// Text in a root marker that makes it the root of a larger project,
+    /// which a marker in a folder further in belongs to.
+    pub workspace_marker: &'static str,
+}
+
+pub const PRESETS: [ServerPreset; 1] = [ServerPreset {
+    name: "rust-analyzer",
+    command: "rust-analyzer",
+    language_id: "rust",
+    extensions: &["rs"],
+    root_marker: "Cargo.toml",
+    workspace_marker: "[workspace]",
+}];
+
+impl ServerPreset {
+    pub fn handles(&self, path: &Path) -> bool {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| self.extensions.contains(&ext))
+    }
+
+    /// The project `path` belongs to: the nearest folder above it with a
+    /// root marker, or a folder further out whose marker declares a
+    /// workspace, as Cargo does.
+    pub fn root_for(&self, path: &Path) -> Option<PathBuf> {
+        let mut markers = path.ancestors().skip(1).filter(|dir| dir.join(self.root_marker).is_file());
+        let nearest = markers.next()?;
+        let workspace = markers.find(|dir| {
+            fs::read_to_string(dir.join(self.root_marker)).is_ok_and(|text| text.contains(self.workspace_marker))
+        });
+        Some(workspace.unwrap_or(nearest).to_path_buf())
+    }
+}
+
+/// Something a language server sent that the app acts on.
+pub enum LspEvent {
+    /// A file's diagnostics, replacing the ones published before.
+    Diagnostics(PathBuf, Vec<Diagnostic>),
+    /// Completions at the primary cursor of the buffer with this path and
+    /// version.
+    Completions { path: PathBuf, version: u64, items: Vec<String> },
+    /// Where the symbol at the cursor is defined; empty if nowhere known.
+    Definition(Vec<(PathBuf, Position)>),
+    /// Actions that edit files, offered at `pos` in the buffer with this
+    /// path and version.
+    CodeActions { path: PathBuf, version: u64, pos: Position, actions: Vec<CodeAction> },
+    /// The server exited before it was ready, with the first thing it logged.
+    Failed { server: String, message: String },
+}
+
+/// How a server counts columns in positions.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PositionEncoding {
+    /// UTF-16 code units, the protocol's default.
+    Utf16,
+    /// Characters, as the editor counts them.
+    Utf32,
+}
+
+impl PositionEncoding {
+    /// The `positionEncoding` a server's capabilities name; a server that
+    /// names none uses UTF-16.
+    fn from_capabilities(capabilities: &Value) -> Self {
+        match capabilities["positionEncoding"].as_str() {
+            Some("utf-32") => PositionEncoding::Utf32,
+            _ => PositionEncoding::Utf16,
+        }
+    }
+
+    /// The server's column for char column `col` of `line`.
+    pub fn to_server(self, line: impl IntoIterator<Item = char>, col: usize) -> usize {
+        match self {
+            PositionEncoding::Utf32 => col,
+            PositionEncoding::Utf16 => line.into_iter().take(col).map(char::len_utf16).sum(),
+        }
+    }
+
+    /// The char column of the server's column `units` in `line`. A column
+    /// inside a surrogate pair lands after the character.
+    pub fn from_server(self, line: impl IntoIterator<Item = char>, units: usize) -> usize {
+        match self {
+            PositionEncoding::Utf32 => units,
+            PositionEncoding::Utf16 => {
+                let mut counted = 0;
+                line.into_iter()
+                    .take_while(|c| {
+                        let before = counted;
+                        counted += c.len_utf16();
+                        before < units
+                    })
+                    .count()
+            }
+        }
+    }
+}
+
+/// The change from `old` to `new` as one `contentChanges` entry that
+/// replaces a range, or None if the text is the same.
+pub fn incremental_change(old: &Rope, new: &Rope, encoding: PositionEncoding) -> Option<Value> {
+    let (start, old_end, new_end) = undo::changed_chars(old, new);
+    if start == old_end && start == new_end {
+        return None;
+    }
+    let position = |char_idx: usize| {
+        let line = old.char_to_line(char_idx);
+        let col = char_idx - old.line_to_char(line);
+        json!({ "line": line, "character": encoding.to_server(old.line(line).chars(), col) })
+    };
+    Some(json!({
+        "range": { "start": position(start), "end": position(old_end) },
+        "text": new.slice(start..new_end).to_string(),
+    }))
+}
+
+/// What a server was last sent of an open document.
+struct Document {
+    version: u64,
+    text: Rope,
+}
+
+/// What a request was for, to know what to do with its response.
+enum Pending {
+    Initialize,
+    Completion { path: PathBuf, version: u64 },
+    Definition,
+    CodeActions { path: PathBuf, version: u64, pos: Position },
+}
+
+/// A running language server for one project, spoken to over the Language
+/// Server Protocol on its standard input and output. Open buffers are sent
+/// to it whenever they change: only the changed range when it accepts
+/// that, otherwise in full.
+pub struct LanguageServer {
+    pub preset: &'static ServerPreset,
+    pub root: PathBuf,
+    child: Child,
+    stdin: ChildStdin,
+    events: Receiver<Incoming>,
+    seq: i64,
+    pending: HashMap<i64, Pending>,
+    /// The server has answered `initialize`, so documents can be sent.
+    ready: bool,
+    ended: bool,
+    /// The first line the server wrote to standard error while starting,
+    /// which says why if it fails to.
+    start_error: String,
+    /// Open documents, as last sent.
+    documents: HashMap<PathBuf, Document>,
+    /// How the server counts columns, from its answer to `initialize`.
+    encoding: PositionEncoding,
+    /// The server takes changed ranges rather than whole documents.
+    incremental: bool,
+    /// Diagnostics as last published for each file, which code action
+    /// requests send back.
+    published: HashMap<PathBuf, Vec<Value>>,
+}
+
+impl LanguageServer {
+    /// Start the preset's server for the project at `root`. Every message
+    /// wakes `ctx`.
+    pub fn start(preset: &'static ServerPreset, root: PathBuf, ctx: egui::Context) -> io::Result<Self> {
+        let mut child = Command::new(preset.command)
+            .current_dir(&root)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let (Some(stdin), Some(stdout), Some(stderr)) = (child.stdin.take(), child.stdout.take(), child.stderr.take())
+        else {
+            let _ = child.kill();
+            return Err(io::Error::other("the language server has no standard streams"));
+        };
+        tracing::info!(server = preset.name, root = %root.display(), "started language server");
+
+        let (tx, events) = mpsc::channel();
+        base_protocol::read_messages(stdout, tx.clone(), ctx.clone());
+        base_protocol::read_stderr(stderr, tx, ctx);
+        let mut server = Self {
+            preset,
+            root,
+            child,
+            stdin,
+            events,
+            seq: 0,
+            pending: HashMap::new(),
+            ready: false,
+            ended: false,
+            start_error: String::new(),
+            documents: HashMap::new(),
+            encoding: PositionEncoding::Utf16,
+            incremental: false,
+            published: HashMap::new(),
+        };
+        let root_uri = path_to_uri(&server.root);
+        let name = server.root.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        server.request(
+            Pending::Initialize,
+            "initialize",
+            json!({
+                "processId": std::process::id(),
+                "clientInfo": { "name": "Lux Editor" },
+                "rootUri": root_uri,
+                "workspaceFolders": [{ "uri": root_uri, "name": name }],
+                "capabilities": {
+                    "general": { "positionEncodings": ["utf-32", "utf-16"] },
+                    "textDocument": {
+                        "synchronization": { "didSave": true },
+                        "publishDiagnostics": {},
+                        "completion": { "completionItem": { "snippetSupport": false } },
+                        "definition": { "linkSupport": true },
+                        "codeAction": {
+                            "codeActionLiteralSupport": {
+                                "codeActionKind": { "valueSet": ["", "quickfix", "refactor", "source"] },
+                            },
+                        },
+                    },
+                    "workspace": { "workspaceEdit": { "documentChanges": true } },
+                },
+            }),
+        );
+        Ok(server)
+    }
+
+    pub fn is_ended(&self) -> bool {
+        self.ended
+    }
+
+    /// Whether the server covers the file at `path`.
+    pub fn covers(&self, path: &Path) -> bool {
+        self.preset.handles(path) && path.starts_with(&self.root)
+    }
+
+    /// Handle the messages that arrived since the last call.
+    pub fn poll(&mut self) -> Vec<LspEvent> {
+        let incoming: Vec<Incoming> = self.events.try_iter().collect();
+        let mut events = Vec::new();
+        for item in incoming {
+            match item {
+                Incoming::Message(message) => events.extend(self.handle(message)),
+                Incoming::Stderr(line) => {
+                    tracing::debug!(server = self.preset.name, "{}", line);
+                    if !self.ready && self.start_error.is_empty() {
+                        self.start_error = line;
+                    }
+                }
+                Incoming::Closed => {
+                    if !self.ended {
+                        tracing::warn!(server = self.preset.name, root = %self.root.display(), "language server exited");
+                    }
+                    if !self.ready && !self.ended {
+                        events.push(LspEvent::Failed {
+                            server: self.preset.name.to_string(),
+                            message: std::mem::take(&mut self.start_error),
+                        });
+                    }
+                    self.ended = true;
+                }
+            }
+        }
+        events
+    }
+
+    /// Open, update and close documents to match the buffers it covers.
+    pub fn sync(&mut self, editors: &[Editor]) {
+        if !self.ready || self.ended {
+            return;
+        }
+        let mut open = HashSet::new();
+        for editor in editors {
+            let Some(path) = editor.file_path.as_ref().filter(|p| self.covers(p)) else {
+                continue;
+            };
+            open.insert(path.clone());
+            let sent = self.documents.get(path);
+            if sent.is_some_and(|d| d.version == editor.version) {
+                continue;
+            }
+            let uri = path_to_uri(path);
+            match sent {
+                Some(document) => {
+                    let changes: Vec<Value> = if self.incremental {
+                        incremental_change(&document.text, &editor.rope, self.encoding).into_iter().collect()
+                    } else {
+                        vec![json!({ "text": editor.rope.to_string() })]
+                    };
+                    self.notify(
+                        "textDocument/didChange",
+                        json!({
+                            "textDocument": { "uri": uri, "version": editor.version },
+                            "contentChanges": changes,
+                        }),
+                    );
+                }
+                None => {
+                    self.notify(
+                        "textDocument/didOpen",
+                        json!({
+                            "textDocument": {
+                                "uri": uri,
+                                "languageId": self.preset.language_id,
+                                "version": editor.version,
+                                "text": editor.rope.to_string(),
+                            },
+                        }),
+                    );
+                }
+            }
+            // Clones of a rope share its text
+            self.documents.insert(path.clone(), Document { version: editor.version, text: editor.rope.clone() });
+        }
+        let closed: Vec<PathBuf> = self.documents.keys().filter(|p| !open.contains(*p)).cloned().collect();
+        for path in closed {
+            self.documents.remove(&path);
+            self.notify("textDocument/didClose", json!({ "textDocument": { "uri": path_to_uri(&path) } }));
+        }
+    }
+
+    pub fn did_save(&mut self, path: &Path) {
+        if self.documents.contains_key(path) {
+            self.notify("textDocument/didSave", json!({ "textDocument": { "uri": path_to_uri(path) } }));
+        }
+    }
+
+    /// Ask for completions at the editor's primary cursor. Returns false if
+    /// the server can't take requests for it yet.
+    pub fn complete(&mut self, editor: &Editor) -> bool {
+        let Some(params) = self.position_params(editor) else {
+            return false;
+        };
+        let path = editor.file_path.clone().unwrap_or_default();
+        self.request(Pending::Completion { path, version: editor.version }, "textDocument/completion", params);
+        true
+    }
+
+    /// Ask where the symbol at the editor's primary cursor is defined.
+    /// Returns false if the server can't take requests for it yet.
+    pub fn definition(&mut self, editor: &Editor) -> bool {
+        let Some(params) = self.position_params(editor) else {
+            return false;
+        };
+        self.request(Pending::Definition, "textDocument/definition", params);
+        true
+    }
+
+    /// Ask for the actions available at the editor's primary cursor, or on
+    /// its selection, passing the diagnostics there. Returns false if the
+    /// server can't take requests for it yet.
+    pub fn code_actions(&mut self, editor: &Editor) -> bool {
+        let Some(mut params) = self.position_params(editor) else {
+            return false;
+        };
+        let cursor = &editor.cursors[0];
+        let (start, end) = cursor.selection_ordered().unwrap_or((cursor.pos, cursor.pos));
+        let path = editor.file_path.clone().unwrap_or_default();
+        let diagnostics: Vec<&Value> = self
+            .published
+            .get(&path)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .filter(|d| {
+                let (from, to) = (parse_position(&d["range"]["start"]), parse_position(&d["range"]["end"]));
+                from.line <= end.line && start.line <= to.line
+            })
+            .collect();
+        params["range"] = json!({
+            "start": self.position_json(&editor.rope, start),
+            "end": self.position_json(&editor.rope, end),
+        });
+        params["context"] = json!({ "diagnostics": diagnostics });
+        let pending = Pending::CodeActions { path, version: editor.version, pos: cursor.pos };
+        self.request(pending, "textDocument/codeAction", params);
+        true
+    }
+
+    /// The document and primary cursor of `editor`, once the server has
+    /// its current version.
+    fn position_params(&self, editor: &Editor) -> Option<Value> {
+        let path = editor.file_path.as_ref()?;
+        if !self.ready || self.ended || self.documents.get(path).map(|d| d.version) != Some(editor.version) {
+            return None;
+        }
+        Some(json!({
+            "textDocument": { "uri": path_to_uri(path) },
+            "position": self.position_json(&editor.rope, editor.cursors[0].pos),
+        }))
+    }
+
+    /// `pos` in `text`, with its column counted the server's way.
+    fn position_json(&self, text: &Rope, pos: Position) -> Value {
+        let col = match text.get_line(pos.line) {
+            Some(line) => self.encoding.to_server(line.chars(), pos.col),
+            None => pos.col,
+        };
+        json!({ "line": pos.line, "character": col })
+    }
+
+    /// A position the server sent for the file at `path`, with its column
+    /// in chars. Files not open are read into `files` once.
+    fn char_position(&self, files: &mut HashMap<PathBuf, Option<Rope>>, path: &Path, pos: Position) -> Position {
+        if self.encoding == PositionEncoding::Utf32 {
+            return pos;
+        }
+        let text = match self.documents.get(path) {
+            Some(document) => Some(&document.text),
+            None => files
+                .entry(path.to_path_buf())
+                .or_insert_with(|| Editor::from_file(path.to_path_buf()).ok().map(|e| e.rope))
+                .as_ref(),
+        };
+        let line: Option<RopeSlice> = text.and_then(|text| text.get_line(pos.line));
+        match line {
+            Some(line) => Position::new(pos.line, self.encoding.from_server(line.chars(), pos.col)),
+            None => pos,
+        }
+    }
+
+    /// The edits of `edit` with their columns in chars.
+    fn edit_from_server(&self, mut edit: WorkspaceEdit) -> WorkspaceEdit {
+        let mut files = HashMap::new();
+        for (path, edits) in edit.iter_mut() {
+            for text_edit in edits {
+                text_edit.start = self.char_position(&mut files, path, text_edit.start);
+                text_edit.end = self.char_position(&mut files, path, text_edit.end);
+            }
+        }
+        edit
+    }
+
+    fn request(&mut self, pending: Pending, method: &str, params: Value) {
+        self.seq += 1;
+        self.pending.insert(self.seq, pending);
+        self.send(json!({ "jsonrpc": "2.0", "id": self.seq, "method": method, "params": params }));
+    }
+
+    fn notify(&mut self, method: &str, params: Value) {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+    }
+
+    fn send(&mut self, message: Value) {
+        if let Err(e) = base_protocol::write_message(&mut self.stdin, &message) {
+            tracing::warn!(server = self.preset.name, error = %e, "failed to write to language server");
+        }
+    }
+
+    fn handle(&mut self, message: Value) -> Option<LspEvent> {
+        let method = message["method"].as_str();
+        match (method, message.get("id")) {
+            // A request from the server: answer the ones it waits on
+            (Some(method), Some(id)) => {
+                let result = match method {
+                    "workspace/configuration" => {
+                        let items = message["params"]["items"].as_array().map_or(0, Vec::len);
+                        Value::Array(vec![Value::Null; items])
+                    }
+                    _ => Value::Null,
+                };
+                self.send(json!({ "jsonrpc": "2.0", "id": id, "result": result }));
+                None
+            }
+            (Some("textDocument/publishDiagnostics"), None) => {
+                let params = &message["params"];
+                let path = uri_to_path(params["uri"].as_str()?)?;
+                let published = params["diagnostics"].as_array().cloned().unwrap_or_default();
+                let mut files = HashMap::new();
+                let diagnostics = published
+                    .iter()
+                    .map(|d| {
+                        let mut diagnostic = parse_diagnostic(d, self.preset.name);
+                        diagnostic.start = self.char_position(&mut files, &path, diagnostic.start);
+                        diagnostic.end = self.char_position(&mut files, &path, diagnostic.end);
+                        diagnostic
+                    })
+                    .collect();
+                self.published.insert(path.clone(), published);
+                Some(LspEvent::Diagnostics(path, diagnostics))
+            }
+            (Some(_), None) => None,
+            (None, Some(id)) => {
+                let pending = self.pending.remove(&id.as_i64()?)?;
+                if let Some(error) = message.get("error") {
+                    tracing::debug!(server = self.preset.name, error = %error, "language server request failed");
+                    return None;
+                }
+                self.responded(pending, &message["result"])
+            }
+            (None, None) => None,
+        }
+    }
+
+    fn responded(&mut self, pending: Pending, result: &Value) -> Option<LspEvent> {
+        match pending {
+            Pending::Initialize => {
+                let capabilities = &result["capabilities"];
+                self.encoding = PositionEncoding::from_capabilities(capabilities);
+                // A number, or an object with the number as `change`
+                let sync = &capabilities["textDocumentSync"];
+                self.incremental = sync.as_u64().or(sync["change"].as_u64()) == Some(2);
+                tracing::debug!(
+                    server = self.preset.name,
+                    encoding = ?self.encoding,
+                    incremental = self.incremental,
+                    "language server initialized"
+                );
+                self.ready = true;
+                self.notify("initialized", json!({}));
+                None
+            }
+            Pending::Completion { path, version } => {
+                // Either a plain list or a CompletionList
+                let items = result.as_array().or(result["items"].as_array())?;
+                let mut texts: Vec<String> = Vec::new();
+                for item in items {
+                    let text = item["textEdit"]["newText"]
+                        .as_str()
+                        .or(item["insertText"].as_str())
+                        .or(item["label"].as_str())
+                        .unwrap_or_default()
+                        .to_string();
+                    if !text.is_empty() && !texts.contains(&text) {
+                        texts.push(text);
+                    }
+                    if texts.len() == MAX_COMPLETIONS {
+                        break;
+                    }
+                }
+                Some(LspEvent::Completions { path, version, items: texts })
+            }
+            Pending::Definition => {
+                // A Location, a list of them, or a list of LocationLinks
+                let list = match result {
+                    Value::Array(list) => list.clone(),
+                    Value::Null => Vec::new(),
+                    location => vec![location.clone()],
+                };
+                let mut files = HashMap::new();
+                let locations = list
+                    .iter()
+                    .filter_map(|location| {
+                        let uri = location["uri"].as_str().or(location["targetUri"].as_str())?;
+                        let range = if location["targetSelectionRange"].is_object() {
+                            &location["targetSelectionRange"]
+                        } else {
+                            &location["range"]
+                        };
+                        let path = uri_to_path(uri)?;
+                        let start = self.char_position(&mut files, &path, parse_position(&range["start"]));
+                        Some((path, start))
+                    })
+                    .collect();
+                Some(LspEvent::Definition(locations))
+            }
+            Pending::CodeActions { path, version, pos } => {
+                // Commands run on the server are left out: only edits are applied
+                let actions = result
+                    .as_array()
+                    .map(Vec::as_slice)
+                    .unwrap_or_default()
+                    .iter()
+                    .filter(|action| action["disabled"].is_null())
+                    .filter_map(|action| {
+                        Some(CodeAction {
+                            title: action["title"].as_str()?.to_string(),
+                            edit: self.edit_from_server(parse_workspace_edit(action.get("edit")?)?),
+                            source: self.preset.name.to_string(),
+                        })
+                    })
+                    .collect();
+                Some(LspEvent::CodeActions { path, version, pos, actions })
+            }
+        }
+    }
+}
+
+impl Drop for LanguageServer {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// The language servers of the open files, started as files they cover
+/// are opened.
+#[derive(Default)]
+pub struct LanguageServers {
+    servers: Vec<LanguageServer>,
+    /// Project roots found for files, so each file's folders are searched
+    /// only once.
+    roots: HashMap<PathBuf, Option<PathBuf>>,
+    /// Servers that could not be started, by preset name and root.
+    failed: HashSet<(&'static str, PathBuf)>,
+}
+
+impl LanguageServers {
+    /// Start servers for buffers a preset covers that have none yet, and
+    /// bring the running ones up to date with the buffers. Returns the
+    /// servers that failed to start, with why.
+    pub fn sync(&mut self, editors: &[Editor], ctx: &egui::Context) -> Vec<(String, io::Error)> {
+        let mut errors = Vec::new();
+        for path in editors.iter().filter_map(|e| e.file_path.as_ref()) {
+            for preset in PRESETS.iter().filter(|p| p.handles(path)) {
+                if self.servers.iter().any(|s| s.covers(path)) {
+                    continue;
+                }
+                let root = self.roots.entry(path.clone()).or_insert_with(|| preset.root_for(path));
+                let Some(root) = root.clone() else {
+                    continue;
+                };
+                if !self.failed.insert((preset.name, root.clone())) {
+                    continue;
+                }
+                match LanguageServer::start(preset, root.clone(), ctx.clone()) {
+                    Ok(server) => {
+                        self.failed.remove(&(preset.name, root));
+                        self.servers.push(server);
+                    }
+                    Err(e) => {
+                        tracing::warn!(server = preset.name, error = %e, "failed to start language server");
+                        errors.push((preset.name.to_string(), e));
+                    }
+                }
+            }
+        }
+        for server in &mut self.servers {
+            server.sync(editors);
+        }
+        errors
+    }
+
+    /// Events from every server since the last call.
+    pub fn poll(&mut self) -> Vec<LspEvent> {
+        self.servers.iter_mut().flat_map(LanguageServer::poll).collect()
+    }
+
+    /// The running server that covers `path`.
+    pub fn for_path(&mut self, path: &Path) -> Option<&mut LanguageServer> {
+        self.servers.iter_mut().find(|s| !s.is_ended() && s.covers(path))
+    }
+
+    pub fn did_save(&mut self, path: &Path) {
+        if let Some(server) = self.for_path(path) {
+            server.did_save(path);
+        }
+    }
+
+    /// Stop every server, e.g. when they are turned off.
+    pub fn stop_all(&mut self) {
+        self.servers.clear();
+        self.failed.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.servers.is_empty()
+    }
+}
+
+fn parse_position(position: &Value) -> Position {
+    let field = |key: &str| position[key].as_u64().unwrap_or_default() as usize;
+    Position::new(field("line"), field("character"))
+}
+
+fn parse_diagnostic(diagnostic: &Value, server: &str) -> Diagnostic {
+    let severity = match diagnostic["severity"].as_u64() {
+        Some(1) => Severity::Error,
+        Some(2) => Severity::Warning,
+        Some(3) => Severity::Info,
+        Some(4) => Severity::Hint,
+        _ => Severity::Error,
+    };
+    Diagnostic {
+        start: parse_position(&diagnostic["range"]["start"]),
+        end: parse_position(&diagnostic["range"]["end"]),
+        severity,
+        message: diagnostic["message"].as_str().unwrap_or_default().to_string(),
+        source: diagnostic["source"].as_str().unwrap_or(server).to_string(),
+        fixes: Vec::new(),
+    }
+}
+
+/// The text edits of a WorkspaceEdit, given as `changes` by document or
+/// as `documentChanges`. None if it also creates, renames or deletes files.
+fn parse_workspace_edit(edit: &Value) -> Option<WorkspaceEdit> {
+    let mut files = WorkspaceEdit::new();
+    let mut add = |uri: &Value, edits: &Value| -> Option<()> {
+        let list = files.entry(uri_to_path(uri.as_str()?)?).or_default();
+        for edit in edits.as_array()? {
+            list.push(TextEdit {
+                start: parse_position(&edit["range"]["start"]),
+                end: parse_position(&edit["range"]["end"]),
+                text: edit["newText"].as_str()?.to_string(),
+                old: None,
+            });
+        }
+        Some(())
+    };
+    if let Some(changes) = edit["documentChanges"].as_array() {
+        for change in changes {
+            if change.get("kind").is_some() {
+                return None;
+            }
+            add(&change["textDocument"]["uri"], &change["edits"])?;
+        }
+    } else {
+        for (uri, edits) in edit["changes"].as_object()? {
+            add(&Value::from(uri.as_str()), edits)?;
+        }
+    }
+    (!files.is_empty()).then_some(files)
+}
+
+/// A `file://` URI for an absolute path.
+pub fn path_to_uri(path: &Path) -> String {
+    let text = path.to_string_lossy().replace('\\', "/");
+    // Windows drive paths need a slash before the drive letter
+    let text = if text.starts_with('/') { text } else { format!("/{}", text) };
+    format!("file://{}", utf8_percent_encode(&text, URI_ESCAPES))
+}
+
+/// The path of a `file://` URI.
+pub fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?;
+    let path = percent_decode_str(path).decode_utf8().ok()?;
+    if cfg!(windows) {
+        Some(PathBuf::from(path.trim_start_matches('/')))
+    } else {
+        Some(PathBuf::from(path.as_ref()))
+    }
+}
@@ -1,12 +1,35 @@
-mod app;
-mod editor;
-mod syntax;
-mod ui;
-
-use app::LuxApp;
 use eframe::egui;
+use lux_edit::app::LuxApp;
+use lux_edit::{conflicts, crash, diff, logging, wait};
 
 fn main() -> eframe::Result<()> {
+    logging::init();
+    let args: Vec<String> = std::env::args().collect();
+    let recover = args.iter().any(|a| a == crash::RECOVER_FLAG);
+    let merge = args
+        .iter()
+        .position(|a| a == conflicts::MERGE_FLAG)
+        .and_then(|i| args.get(i + 1))
+        .map(|p| std::path::absolute(p).unwrap_or_else(|_| p.into()));
+    // The two files after --diff; other flags such as --wait may sit between
+    let diff = args.iter().position(|a| a == diff::DIFF_FLAG).and_then(|i| {
+        let mut paths = args[i + 1..]
+            .iter()
+            .filter(|a| !a.starts_with("--"))
+            .map(|p| std::path::absolute(p).unwrap_or_else(|_| p.into()));
+        Some((paths.next()?, paths.next()?))
+    });
+    // With --diff, --wait just keeps the process until the window closes,
+    // which it does anyway
+    let wait = args
+        .iter()
+        .position(|a| a == wait::WAIT_FLAG)
+        .and_then(|i| args[i + 1..].iter().find(|a| !a.starts_with("--")))
+        .filter(|_| diff.is_none())
+        .map(|p| std::path::absolute(p).unwrap_or_else(|_| p.into()));
+    crash::install_panic_hook();
+    tracing::info!(version = env!("CARGO_PKG_VERSION"), recover, merge = ?merge, diff = ?diff, wait = ?wait, "starting");
+
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_inner_size([1200.0, 800.0])
@@ -17,6 +40,11 @@ fn main() -> eframe::Result<()> {
     eframe::run_native(
         "Lux Editor",
         options,
-        Box::new(|cc| Ok(Box::new(LuxApp::new(cc)))),
-    )
+        Box::new(move |cc| Ok(Box::new(LuxApp::new(cc, recover, merge, diff, wait)))),
+    )?;
+    // Tell whoever waited that the edit was abandoned
+    if wait::failed() {
+        std::process::exit(1);
+    }
+    Ok(())
 }
@@ -0,0 +1,157 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use ropey::Rope;
+use serde::{Deserialize, Serialize};
+
+use crate::crash;
+
+/// How far a marker looks for its line after the text around it changes.
+const RELOCATE_WINDOW: usize = 200;
+
+/// Kinds of marker the user places on a line and that are kept between
+/// sessions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MarkerType {
+    Bookmark,
+    Breakpoint,
+}
+
+/// A marker on a line. It remembers the line's text, so it can find the
+/// line again after edits above it or changes to the file on disk.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LineMarker {
+    pub line: usize,
+    pub kind: MarkerType,
+    pub text: String,
+}
+
+fn line_text(rope: &Rope, line: usize) -> String {
+    let text: String = rope.line(line).chars().collect();
+    text.trim_end_matches(['\n', '\r']).to_string()
+}
+
+/// Put `kind` on `line`, or take it off if it is there. Returns whether
+/// the marker was added.
+pub fn toggle(markers: &mut Vec<LineMarker>, rope: &Rope, line: usize, kind: MarkerType) -> bool {
+    if let Some(i) = markers.iter().position(|m| m.line == line && m.kind == kind) {
+        markers.remove(i);
+        return false;
+    }
+    markers.push(LineMarker { line, kind, text: line_text(rope, line) });
+    markers.sort_by_key(|m| m.line);
+    true
+}
+
+/// Move markers whose line no longer has their text to the nearest line
+/// that does; a marker whose own line was edited stays and takes the new
+/// text. Returns whether any marker changed.
+pub fn relocate(markers: &mut Vec<LineMarker>, rope: &Rope) -> bool {
+    let lines = rope.len_lines();
+    let mut changed = false;
+    for marker in markers.iter_mut() {
+        let line = marker.line.min(lines - 1);
+        if line == marker.line && line_text(rope, line) == marker.text {
+            continue;
+        }
+        let found = (1..RELOCATE_WINDOW)
+            .flat_map(|d| [line.checked_add(d), line.checked_sub(d)])
+            .flatten()
+            .filter(|&l| l < lines)
+            .find(|&l| line_text(rope, l) == marker.text);
+        marker.line = found.unwrap_or(line);
+        marker.text = line_text(rope, marker.line);
+        changed = true;
+    }
+    if changed {
+        markers.sort_by_key(|m| m.line);
+        markers.dedup_by(|a, b| a.line == b.line && a.kind == b.kind);
+    }
+    changed
+}
+
+/// The line of the next marker of `kind` after `line`, or before it going
+/// backwards, wrapping around the file.
+pub fn next(markers: &[LineMarker], kind: MarkerType, line: usize, forward: bool) -> Option<usize> {
+    let lines = lines_of(markers, kind);
+    if forward {
+        lines.iter().find(|&&l| l > line).or(lines.first()).copied()
+    } else {
+        lines.iter().rev().find(|&&l| l < line).or(lines.last()).copied()
+    }
+}
+
+/// The lines with a marker of `kind`.
+pub fn lines_of(markers: &[LineMarker], kind: MarkerType) -> Vec<usize> {
+    markers.iter().filter(|m| m.kind == kind).map(|m| m.line).collect()
+}
+
+/// Markers of every file in one project, saved in the app data directory
+/// under a name derived from the project's root. Files opened without a
+/// project share one set.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProjectMarkers {
+    files: BTreeMap<PathBuf, Vec<LineMarker>>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl ProjectMarkers {
+    /// The saved markers for the project at `root`; empty if there are none
+    /// or they can't be read.
+    pub fn load(root: Option<&Path>) -> Self {
+        let path = store_path(root);
+        let mut markers: ProjectMarkers = fs::read(&path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default();
+        markers.path = path;
+        markers
+    }
+
+    pub fn get(&self, file: &Path) -> Vec<LineMarker> {
+        self.files.get(file).cloned().unwrap_or_default()
+    }
+
+    /// The lines with a marker of `kind`, by file.
+    pub fn lines(&self, kind: MarkerType) -> BTreeMap<PathBuf, Vec<usize>> {
+        self.files
+            .iter()
+            .map(|(file, markers)| (file.clone(), lines_of(markers, kind)))
+            .filter(|(_, lines)| !lines.is_empty())
+            .collect()
+    }
+
+    /// Replace a file's markers and write the project's file.
+    pub fn set(&mut self, file: &Path, markers: &[LineMarker]) -> io::Result<()> {
+        if markers.is_empty() {
+            self.files.remove(file);
+        } else {
+            self.files.insert(file.to_path_buf(), markers.to_vec());
+        }
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, serde_json::to_vec_pretty(self).unwrap_or_default())
+    }
+}
+
+fn store_path(root: Option<&Path>) -> PathBuf {
+    let name = match root {
+        Some(root) => {
+            let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+            // FNV-1a, so the name is the same across runs and builds
+            let hash = root
+                .to_string_lossy()
+                .bytes()
+                .fold(0xcbf2_9ce4_8422_2325u64, |h, b| (h ^ b as u64).wrapping_mul(0x0100_0000_01b3));
+            format!("{:016x}.json", hash)
+        }
+        None => "no-project.json".to_string(),
+    };
+    crash::app_data_dir().join("markers").join(name)
+}
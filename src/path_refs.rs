@@ -0,0 +1,256 @@
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use ropey::Rope;
+
+use crate::editor::{Editor, Position};
+use crate::edits::{TextEdit, WorkspaceEdit};
+
+/// Files larger than this are not scanned for references.
+const MAX_SCAN_BYTES: u64 = 1024 * 1024;
+/// Directories never worth scanning for source references.
+const SKIP_DIRS: &[&str] = &["target", "node_modules"];
+
+/// A single line that refers to a moved file and how it should be rewritten.
+#[derive(Clone, Debug)]
+pub struct ReferenceEdit {
+    pub path: PathBuf,
+    pub line: usize,
+    pub old_line: String,
+    pub new_line: String,
+}
+
+/// Directories marking the root of a version-controlled checkout.
+const VCS_DIRS: &[&str] = &[".git", ".hg", ".svn", ".jj"];
+
+/// Nearest ancestor that is the root of a checkout (containing `.git` or
+/// another VCS directory), or the file's own directory.
+pub fn workspace_root_for(file: &Path) -> Option<PathBuf> {
+    let dir = file.parent()?;
+    dir.ancestors()
+        .find(|d| VCS_DIRS.iter().any(|vcs| d.join(vcs).exists()))
+        .or(Some(dir))
+        .map(Path::to_path_buf)
+}
+
+/// Relative path from directory `from` to `to`, using forward slashes.
+pub fn relative_path(from: &Path, to: &Path) -> String {
+    let from: Vec<Component> = from.components().collect();
+    let to: Vec<Component> = to.components().collect();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+
+    let mut parts: Vec<String> = Vec::new();
+    for _ in common..from.len() {
+        parts.push("..".into());
+    }
+    for c in &to[common..] {
+        parts.push(c.as_os_str().to_string_lossy().into_owned());
+    }
+    parts.join("/")
+}
+
+fn strip_extension(rel: &str) -> Option<&str> {
+    let name_start = rel.rfind('/').map_or(0, |i| i + 1);
+    let dot = rel[name_start..].rfind('.')?;
+    if dot == 0 {
+        return None;
+    }
+    Some(&rel[..name_start + dot])
+}
+
+/// One way a file could spell the path to the moved file, and its new
+/// spelling.
+struct Spelling {
+    old: String,
+    new: String,
+    /// Only rewritten on lines that import something, because the spelling
+    /// could as well be a package name, like `require("util")`.
+    import_only: bool,
+}
+
+/// The spellings a file in `dir` could use to refer to the moved file.
+fn candidate_spellings(dir: &Path, old: &Path, new: &Path) -> Vec<Spelling> {
+    let old_rel = relative_path(dir, old);
+    let new_rel = relative_path(dir, new);
+    // Explicit `./` prefix for same-directory references
+    let dotted = |rel: &str| if rel.starts_with('.') { rel.to_string() } else { format!("./{}", rel) };
+    let mut spellings = vec![Spelling { old: dotted(&old_rel), new: dotted(&new_rel), import_only: false }];
+    if !old_rel.starts_with('.') {
+        spellings.push(Spelling { old: old_rel.clone(), new: new_rel.clone(), import_only: false });
+    }
+    // Extension-less module imports (`import x from './util'`)
+    if let (Some(o), Some(n)) = (strip_extension(&old_rel), strip_extension(&new_rel)) {
+        spellings.push(Spelling { old: dotted(o), new: dotted(n), import_only: true });
+    }
+    spellings
+}
+
+/// Whether `line` imports or includes something, like `import`, `from`,
+/// `require(…)` or `#include`.
+fn imports(line: &str) -> bool {
+    const WORDS: &[&str] = &["import", "from", "require", "include", "export"];
+    line.split(|c: char| !c.is_alphanumeric() && c != '_').any(|w| WORDS.contains(&w))
+}
+
+/// Rewrite quoted occurrences (`"…"`, `'…'`, `<…>`) of any candidate spelling.
+fn rewrite_line(line: &str, spellings: &[Spelling]) -> Option<String> {
+    let mut out = line.to_string();
+    let imports = imports(line);
+    for spelling in spellings.iter().filter(|s| imports || !s.import_only) {
+        for (open, close) in [('"', '"'), ('\'', '\''), ('<', '>')] {
+            let needle = format!("{}{}{}", open, spelling.old, close);
+            if out.contains(&needle) {
+                out = out.replace(&needle, &format!("{}{}{}", open, spelling.new, close));
+            }
+        }
+    }
+    (out != line).then_some(out)
+}
+
+/// `path` with `.` and `..` components resolved without the file system.
+fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                out.pop();
+            }
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+/// Whether a relative reference to `target` finds a file: the path itself,
+/// or for an extension-less module import, a file of that name with any
+/// extension.
+fn resolves(target: &Path, module: bool) -> bool {
+    if target.exists() {
+        return true;
+    }
+    let (Some(dir), Some(stem)) = (target.parent(), target.file_name()) else {
+        return false;
+    };
+    module
+        && fs::read_dir(dir).is_ok_and(|entries| {
+            entries.flatten().any(|e| Path::new(&e.file_name()).file_stem() == Some(stem))
+        })
+}
+
+/// Rewrite the moved file's own relative references on `line`, made from
+/// `old_dir`, so they point at the same files from `new_dir`. Only quoted
+/// paths starting with `./` or `../`, or any quoted path on an import line,
+/// that lead to an existing file are changed.
+fn retarget_line(line: &str, old_dir: &Path, new_dir: &Path) -> Option<String> {
+    let imports = imports(line);
+    let mut out = String::new();
+    let mut rest = line;
+    while let Some(open) = rest.find(['"', '\'']) {
+        let quote = rest[open..].chars().next().unwrap_or('"');
+        let Some(len) = rest[open + 1..].find(quote) else {
+            break;
+        };
+        let spec = &rest[open + 1..open + 1 + len];
+        let relative = spec.starts_with("./") || spec.starts_with("../");
+        let target = normalize(&old_dir.join(spec));
+        let new_spec = (!spec.is_empty() && (relative || imports) && resolves(&target, relative))
+            .then(|| relative_path(new_dir, &target))
+            .map(|rel| if relative && !rel.starts_with('.') { format!("./{}", rel) } else { rel });
+        out.push_str(&rest[..=open]);
+        out.push_str(new_spec.as_deref().unwrap_or(spec));
+        out.push(quote);
+        rest = &rest[open + len + 2..];
+    }
+    out.push_str(rest);
+    (out != line).then_some(out)
+}
+
+/// Every scannable file under `dir`: hidden entries, build output and large
+/// files are skipped.
+pub fn collect_files(dir: &Path, out: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with('.') {
+            continue;
+        }
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        if file_type.is_dir() {
+            if !SKIP_DIRS.contains(&name.as_str()) {
+                collect_files(&path, out);
+            }
+        } else if file_type.is_file()
+            && entry.metadata().is_ok_and(|m| m.len() <= MAX_SCAN_BYTES)
+        {
+            out.push(path);
+        }
+    }
+}
+
+/// Find lines in `files` that reference `old` by a relative path and
+/// compute their rewritten form pointing at `new`, and the lines of the
+/// moved file, now at `new`, whose relative references must change with
+/// its folder. Files in `open`, with their buffers' text, are read as they
+/// are in the buffer rather than on disk.
+pub fn find_references(files: &[PathBuf], open: &[(PathBuf, Rope)], old: &Path, new: &Path) -> Vec<ReferenceEdit> {
+    let (old_dir, new_dir) = (old.parent().unwrap_or(Path::new("")), new.parent().unwrap_or(Path::new("")));
+    let mut edits = Vec::new();
+    for file in files {
+        let moved = file == new;
+        if moved && old_dir == new_dir {
+            continue;
+        }
+        let loaded;
+        let rope = match open.iter().find(|(path, _)| path == file) {
+            Some((_, rope)) => rope,
+            None => {
+                // Read as opening the file would, so lines match the buffer
+                // the edits are applied to
+                let Ok(editor) = Editor::from_file(file.clone()) else {
+                    continue; // binary or unreadable
+                };
+                loaded = editor.rope;
+                &loaded
+            }
+        };
+        let spellings = candidate_spellings(file.parent().unwrap_or(old_dir), old, new);
+        for (line_idx, line) in rope.lines().enumerate() {
+            let line = line.to_string();
+            let line = line.trim_end_matches(['\n', '\r']);
+            let new_line = match moved {
+                true => retarget_line(line, old_dir, new_dir),
+                false => rewrite_line(line, &spellings),
+            };
+            if let Some(new_line) = new_line {
+                edits.push(ReferenceEdit {
+                    path: file.clone(),
+                    line: line_idx,
+                    old_line: line.to_string(),
+                    new_line,
+                });
+            }
+        }
+    }
+    edits
+}
+
+/// The workspace edit making `edits`, each replacing its whole line if the
+/// line still reads as it did.
+pub fn workspace_edit(edits: &[ReferenceEdit]) -> WorkspaceEdit {
+    let mut edit = WorkspaceEdit::new();
+    for reference in edits {
+        edit.entry(reference.path.clone()).or_default().push(TextEdit {
+            start: Position::new(reference.line, 0),
+            end: Position::new(reference.line, reference.old_line.chars().count()),
+            text: reference.new_line.clone(),
+            old: Some(reference.old_line.clone()),
+        });
+    }
+    edit
+}
@@ -0,0 +1,75 @@
+/// Comment and quote markers kept at the start of every reflowed line,
+/// longest first so `///` isn't taken for `//`.
+const MARKERS: [&str; 6] = ["///", "//!", "//", "--", "#", ">"];
+
+/// The prefix of `line` kept when reflowing: its indentation, then a
+/// comment or quote marker and the spaces after it, if any. Nested quotes
+/// like `> > ` are one prefix.
+pub fn prefix(line: &str) -> &str {
+    let mut end = line.len() - line.trim_start().len();
+    loop {
+        let rest = &line[end..];
+        let Some(marker) = MARKERS.iter().find(|m| rest.starts_with(**m)) else {
+            break;
+        };
+        end += marker.len();
+        let rest = &line[end..];
+        end += rest.len() - rest.trim_start_matches([' ', '\t']).len();
+        if !marker.starts_with('>') {
+            break;
+        }
+    }
+    &line[..end]
+}
+
+/// Whether `line` belongs to a paragraph with the given `prefix`: it starts
+/// with the same marker and has text after it.
+pub fn continues(line: &str, prefix: &str) -> bool {
+    let own = self::prefix(line);
+    own.trim_end() == prefix.trim_end() && !line[own.len()..].trim().is_empty()
+}
+
+/// `lines` rewrapped to at most `width` characters, each starting with the
+/// first line's prefix. Words longer than the width get a line of their own.
+/// Lines without text (or with only a marker) separate paragraphs and are
+/// kept.
+pub fn reflow(lines: &[&str], width: usize) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let prefix = prefix(lines[i]);
+        if lines[i][prefix.len()..].trim().is_empty() {
+            out.push(lines[i].to_string());
+            i += 1;
+            continue;
+        }
+        let mut words = Vec::new();
+        while i < lines.len() && continues(lines[i], prefix) {
+            words.extend(lines[i][self::prefix(lines[i]).len()..].split_whitespace());
+            i += 1;
+        }
+        fill(&mut out, prefix, &words, width);
+    }
+    out
+}
+
+/// Greedily fill lines of `width` with `words`, each line after `prefix`.
+fn fill(out: &mut Vec<String>, prefix: &str, words: &[&str], width: usize) {
+    let prefix_len = prefix.chars().count();
+    let mut line = String::from(prefix);
+    let mut len = prefix_len;
+    for word in words {
+        let word_len = word.chars().count();
+        if len > prefix_len && len + 1 + word_len > width {
+            out.push(std::mem::replace(&mut line, String::from(prefix)));
+            len = prefix_len;
+        }
+        if len > prefix_len {
+            line.push(' ');
+            len += 1;
+        }
+        line.push_str(word);
+        len += word_len;
+    }
+    out.push(line);
+}
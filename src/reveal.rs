@@ -0,0 +1,56 @@
+use std::io;
+use std::path::Path;
+use std::process::Command;
+
+/// Label for Reveal commands, named after the platform's file manager.
+pub const LABEL: &str = if cfg!(target_os = "macos") {
+    "Reveal in Finder"
+} else if cfg!(windows) {
+    "Reveal in File Explorer"
+} else {
+    "Reveal in File Manager"
+};
+
+/// Show `path` selected in Finder.
+#[cfg(target_os = "macos")]
+pub fn reveal(path: &Path) -> io::Result<()> {
+    Command::new("open").arg("-R").arg(path).spawn().map(drop)
+}
+
+/// Show `path` selected in Explorer.
+#[cfg(windows)]
+pub fn reveal(path: &Path) -> io::Result<()> {
+    use std::os::windows::process::CommandExt;
+
+    // Explorer parses its own command line and wants the path quoted
+    // after the comma, which the standard quoting doesn't do
+    Command::new("explorer").raw_arg(format!("/select,\"{}\"", path.display())).spawn().map(drop)
+}
+
+/// Show `path` selected in whatever file manager implements the
+/// freedesktop `FileManager1` interface, or else open its folder.
+#[cfg(not(any(target_os = "macos", windows)))]
+pub fn reveal(path: &Path) -> io::Result<()> {
+    use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+
+    const URI_ESCAPES: &AsciiSet = &CONTROLS.add(b' ').add(b'"').add(b'#').add(b'%').add(b'?').add(b'<').add(b'>');
+
+    let uri = format!("file://{}", utf8_percent_encode(&path.to_string_lossy(), URI_ESCAPES));
+    let shown = Command::new("dbus-send")
+        .args([
+            "--session",
+            "--dest=org.freedesktop.FileManager1",
+            "--type=method_call",
+            "/org/freedesktop/FileManager1",
+            "org.freedesktop.FileManager1.ShowItems",
+        ])
+        .arg(format!("array:string:{}", uri))
+        .arg("string:")
+        .status()
+        .is_ok_and(|status| status.success());
+    if shown {
+        return Ok(());
+    }
+    let folder = path.parent().unwrap_or(path);
+    Command::new("xdg-open").arg(folder).spawn().map(drop)
+}
@@ -0,0 +1,245 @@
+use std::fmt;
+use std::ops::Range;
+
+use regex_automata::meta::Regex;
+use regex_automata::util::syntax;
+use ropey::iter::{Chunks, Lines};
+use ropey::Rope;
+
+/// How a query is matched.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SearchOptions {
+    pub ignore_case: bool,
+    /// The query is a regular expression. Matches don't span lines.
+    pub regex: bool,
+    /// Only matches with no word character on either side.
+    pub whole_word: bool,
+}
+
+/// A regular expression that failed to compile.
+#[derive(Debug)]
+pub struct SearchError {
+    pub message: String,
+}
+
+impl fmt::Display for SearchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Non-overlapping occurrences of a needle in a rope, as byte offsets.
+/// Works chunk by chunk, so the rope is never copied into one string; a
+/// match spanning chunk boundaries is found through a small window that
+/// keeps the tail of the previous chunk.
+pub struct Matches<'a> {
+    chunks: Chunks<'a>,
+    needle: &'a [u8],
+    /// Unsearched bytes: the carried-over tail plus the current chunk.
+    window: Vec<u8>,
+    /// Byte offset of `window[0]` in the rope.
+    window_start: usize,
+    /// Where the next search in `window` begins.
+    pos: usize,
+    /// No match starts at or after this byte.
+    end: usize,
+}
+
+impl Iterator for Matches<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        let n = self.needle.len();
+        if n == 0 {
+            return None;
+        }
+        loop {
+            if let Some(i) = find_bytes(&self.window[self.pos.min(self.window.len())..], self.needle) {
+                let start = self.pos + i;
+                self.pos = start + n;
+                return Some(self.window_start + start).filter(|&b| b < self.end);
+            }
+            if self.window_start + self.window.len() >= self.end.saturating_add(n) {
+                return None;
+            }
+            // Keep only what could still begin a match that ends in the next chunk
+            let keep_from = self.pos.max(self.window.len().saturating_sub(n - 1)).min(self.window.len());
+            self.window.drain(..keep_from);
+            self.window_start += keep_from;
+            self.pos = self.pos.saturating_sub(keep_from);
+            self.window.extend_from_slice(self.chunks.next()?.as_bytes());
+        }
+    }
+}
+
+/// Occurrences of `needle` starting at or after byte `from`.
+pub fn matches_from<'a>(rope: &'a Rope, needle: &'a str, from: usize) -> Matches<'a> {
+    let from = from.min(rope.len_bytes());
+    let (mut chunks, chunk_start, _, _) = rope.chunks_at_byte(from);
+    let window = chunks.next().map(|c| c.as_bytes().to_vec()).unwrap_or_default();
+    Matches {
+        chunks,
+        needle: needle.as_bytes(),
+        window,
+        window_start: chunk_start,
+        pos: from - chunk_start,
+        end: usize::MAX,
+    }
+}
+
+/// Copy of `rope` with every occurrence of `find` replaced, built chunk by
+/// chunk between matches.
+pub fn replace_all(rope: &Rope, find: &str, replace: &str) -> Rope {
+    let mut out = ropey::RopeBuilder::new();
+    let mut last = 0;
+    for start in matches_from(rope, find, 0) {
+        for chunk in rope.byte_slice(last..start).chunks() {
+            out.append(chunk);
+        }
+        out.append(replace);
+        last = start + find.len();
+    }
+    for chunk in rope.byte_slice(last..).chunks() {
+        out.append(chunk);
+    }
+    out.finish()
+}
+
+/// Matches of a query in a rope as char ranges, in order, computed lazily
+/// from a starting point.
+pub struct SearchIter<'a> {
+    rope: &'a Rope,
+    matcher: Matcher<'a>,
+    whole_word: bool,
+}
+
+enum Matcher<'a> {
+    /// Case-sensitive text, matched across chunks.
+    Literal { matches: Matches<'a>, chars: usize },
+    /// Everything else runs a regex over one line at a time; a line is only
+    /// copied if it spans rope chunks.
+    Pattern {
+        regex: Regex,
+        lines: Lines<'a>,
+        /// Next line to search.
+        line: usize,
+        /// Only matches starting at or after this char.
+        from: usize,
+        /// Lines starting at or after this char are not searched.
+        end: usize,
+        /// Matches left on the line searched last.
+        pending: std::vec::IntoIter<Range<usize>>,
+    },
+}
+
+impl SearchIter<'_> {
+    /// Stop before matches starting at or after char `end`, without
+    /// scanning the rest of the rope for them.
+    pub fn until(mut self, end: usize) -> Self {
+        match &mut self.matcher {
+            Matcher::Literal { matches, .. } => matches.end = self.rope.char_to_byte(end.min(self.rope.len_chars())),
+            Matcher::Pattern { end: limit, .. } => *limit = end,
+        }
+        self
+    }
+}
+
+impl Iterator for SearchIter<'_> {
+    type Item = Range<usize>;
+
+    fn next(&mut self) -> Option<Range<usize>> {
+        loop {
+            let found = match &mut self.matcher {
+                Matcher::Literal { matches, chars } => {
+                    let start = self.rope.byte_to_char(matches.next()?);
+                    start..start + *chars
+                }
+                Matcher::Pattern { regex, lines, line, from, end, pending } => match pending.next() {
+                    Some(range) => range,
+                    None => {
+                        if *line >= self.rope.len_lines() || self.rope.line_to_char(*line) >= *end {
+                            return None;
+                        }
+                        let text = lines.next()?;
+                        let start_byte = self.rope.line_to_byte(*line);
+                        *line += 1;
+                        let text: std::borrow::Cow<str> = text.into();
+                        let text = text.trim_end_matches(['\n', '\r']);
+                        let found: Vec<Range<usize>> = regex
+                            .find_iter(text)
+                            .filter(|m| !m.is_empty())
+                            .map(|m| {
+                                self.rope.byte_to_char(start_byte + m.start())..self.rope.byte_to_char(start_byte + m.end())
+                            })
+                            .filter(|r| r.start >= *from && r.start < *end)
+                            .collect();
+                        *pending = found.into_iter();
+                        continue;
+                    }
+                },
+            };
+            if !self.whole_word || is_whole_word(self.rope, &found) {
+                return Some(found);
+            }
+        }
+    }
+}
+
+/// Search `rope` for `query` from char `from` on.
+pub fn search<'a>(rope: &'a Rope, query: &'a str, options: SearchOptions, from: usize) -> Result<SearchIter<'a>, SearchError> {
+    let from = from.min(rope.len_chars());
+    let matcher = if !options.regex && !options.ignore_case {
+        Matcher::Literal {
+            matches: matches_from(rope, query, rope.char_to_byte(from)),
+            chars: query.chars().count(),
+        }
+    } else {
+        let pattern = if options.regex {
+            query.to_string()
+        } else {
+            regex_syntax::escape(query)
+        };
+        let regex = Regex::builder()
+            .syntax(syntax::Config::new().case_insensitive(options.ignore_case).multi_line(true))
+            .build(&pattern)
+            .map_err(|e| SearchError { message: e.to_string() })?;
+        let line = rope.char_to_line(from);
+        Matcher::Pattern {
+            regex,
+            lines: rope.lines_at(line),
+            line,
+            from,
+            end: usize::MAX,
+            pending: Vec::new().into_iter(),
+        }
+    };
+    Ok(SearchIter {
+        rope,
+        matcher,
+        whole_word: options.whole_word,
+    })
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn is_whole_word(rope: &Rope, range: &Range<usize>) -> bool {
+    let before = range.start > 0 && is_word_char(rope.char(range.start - 1));
+    let after = range.end < rope.len_chars() && is_word_char(rope.char(range.end));
+    !before && !after
+}
+
+fn find_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    let first = needle[0];
+    let mut offset = 0;
+    while offset + needle.len() <= haystack.len() {
+        let i = haystack[offset..=haystack.len() - needle.len()].iter().position(|&b| b == first)?;
+        let start = offset + i;
+        if &haystack[start..start + needle.len()] == needle {
+            return Some(start);
+        }
+        offset = start + 1;
+    }
+    None
+}
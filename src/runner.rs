@@ -0,0 +1,156 @@
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Stdio};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use eframe::egui;
+
+use crate::workspace::{shell, shell_quote};
+
+/// How often the waiting thread checks whether the process has exited.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The run command for `path` from the `[run]` settings table, keyed by
+/// file extension (e.g. `py = "python %f"`), with `%f` replaced by the
+/// file's path and `%d` by its directory.
+pub fn command_for(commands: &BTreeMap<String, String>, path: &Path) -> Option<String> {
+    let ext = path.extension()?.to_str()?.to_lowercase();
+    let template = commands.get(&ext)?;
+    let dir = path.parent().unwrap_or(Path::new(""));
+    Some(template.replace("%f", &shell_quote(path)).replace("%d", &shell_quote(dir)))
+}
+
+/// Something a running process did.
+pub enum RunEvent {
+    Stdout(String),
+    Stderr(String),
+    /// The exit code, or None if it was killed by a signal.
+    Exited(Option<i32>),
+    /// The process could not be started.
+    Failed(String),
+}
+
+/// A command running in the background, streaming its output line by line.
+pub struct Run {
+    pub command: String,
+    pub cwd: PathBuf,
+    child: Arc<Mutex<Option<Child>>>,
+    events: Receiver<RunEvent>,
+}
+
+impl Run {
+    /// Start `command` through the platform shell in `cwd`. Every event
+    /// wakes `ctx` so the output shows up without waiting for input.
+    pub fn start(command: String, cwd: PathBuf, ctx: egui::Context) -> Self {
+        let (tx, events) = mpsc::channel();
+        let child = Arc::new(Mutex::new(None));
+        let spawned = shell(&command)
+            .current_dir(&cwd)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn();
+        match spawned {
+            Ok(mut process) => {
+                tracing::info!(command = %command, cwd = %cwd.display(), "started run");
+                if let Some(out) = process.stdout.take() {
+                    forward(out, tx.clone(), ctx.clone(), RunEvent::Stdout);
+                }
+                if let Some(err) = process.stderr.take() {
+                    forward(err, tx.clone(), ctx.clone(), RunEvent::Stderr);
+                }
+                *child.lock().unwrap() = Some(process);
+                wait(child.clone(), tx, ctx);
+            }
+            Err(e) => {
+                tracing::warn!(command = %command, error = %e, "failed to start run");
+                let _ = tx.send(RunEvent::Failed(e.to_string()));
+            }
+        }
+        Self { command, cwd, child, events }
+    }
+
+    /// Events since the last call.
+    pub fn poll(&self) -> Vec<RunEvent> {
+        self.events.try_iter().collect()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.child.lock().unwrap().is_some()
+    }
+
+    /// Kill the process; its exit arrives as an event like any other.
+    pub fn stop(&self) {
+        if let Some(child) = self.child.lock().unwrap().as_mut() {
+            tracing::info!(command = %self.command, "stopping run");
+            let _ = child.kill();
+        }
+    }
+}
+
+impl Drop for Run {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Send each line read from `stream` until it closes.
+fn forward(
+    stream: impl Read + Send + 'static,
+    tx: Sender<RunEvent>,
+    ctx: egui::Context,
+    event: fn(String) -> RunEvent,
+) {
+    std::thread::spawn(move || {
+        let mut reader = BufReader::new(stream);
+        let mut line = Vec::new();
+        // Lines may not be UTF-8, so read bytes and convert lossily
+        while reader.read_until(b'\n', &mut line).is_ok_and(|n| n > 0) {
+            let text = String::from_utf8_lossy(&line);
+            let text = text.trim_end_matches(['\n', '\r']).to_string();
+            line.clear();
+            if tx.send(event(text)).is_err() {
+                break;
+            }
+            ctx.request_repaint();
+        }
+    });
+}
+
+/// Poll the child until it exits, without holding the lock in between so
+/// `Run::stop` can kill it.
+fn wait(child: Arc<Mutex<Option<Child>>>, tx: Sender<RunEvent>, ctx: egui::Context) {
+    std::thread::spawn(move || loop {
+        let status = {
+            let mut guard = child.lock().unwrap();
+            let Some(process) = guard.as_mut() else {
+                return;
+            };
+            let status = match process.try_wait() {
+                Ok(Some(status)) => Some(Ok(status.code())),
+                Ok(None) => None,
+                Err(e) => Some(Err(e)),
+            };
+            if status.is_some() {
+                *guard = None;
+            }
+            status
+        };
+        match status {
+            Some(Ok(code)) => {
+                let _ = tx.send(RunEvent::Exited(code));
+                ctx.request_repaint();
+                return;
+            }
+            Some(Err(e)) => {
+                let _ = tx.send(RunEvent::Failed(e.to_string()));
+                ctx.request_repaint();
+                return;
+            }
+            None => std::thread::sleep(POLL_INTERVAL),
+        }
+    });
+}
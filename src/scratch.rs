@@ -0,0 +1,72 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::crash;
+
+/// Scratch buffers are stored as `<name>.txt`.
+const EXTENSION: &str = "txt";
+
+fn scratch_dir() -> PathBuf {
+    crash::app_data_dir().join("scratch")
+}
+
+fn scratch_path(name: &str) -> PathBuf {
+    scratch_dir().join(format!("{}.{}", name, EXTENSION))
+}
+
+/// Make `name` usable as a file name: path separators and characters some
+/// file systems reject become dashes.
+pub fn sanitize(name: &str) -> String {
+    let name: String = name
+        .trim()
+        .chars()
+        .map(|c| if c.is_control() || "/\\:*?\"<>|".contains(c) { '-' } else { c })
+        .collect();
+    name.trim_matches('.').to_string()
+}
+
+/// Names of all scratch buffers, sorted.
+pub fn list() -> Vec<String> {
+    let Ok(entries) = fs::read_dir(scratch_dir()) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            if path.extension()? != EXTENSION {
+                return None;
+            }
+            Some(path.file_stem()?.to_string_lossy().into_owned())
+        })
+        .collect();
+    names.sort();
+    names
+}
+
+/// First "Scratch N" not taken yet.
+pub fn next_name() -> String {
+    let taken = list();
+    (1..)
+        .map(|n| format!("Scratch {}", n))
+        .find(|name| !taken.contains(name))
+        .unwrap_or_default()
+}
+
+/// Contents of a scratch buffer; empty if it doesn't exist yet.
+pub fn load(name: &str) -> io::Result<String> {
+    match fs::read_to_string(scratch_path(name)) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(String::new()),
+        other => other,
+    }
+}
+
+pub fn save(name: &str, text: &str) -> io::Result<()> {
+    fs::create_dir_all(scratch_dir())?;
+    fs::write(scratch_path(name), text)
+}
+
+pub fn delete(name: &str) -> io::Result<()> {
+    fs::remove_file(scratch_path(name))
+}
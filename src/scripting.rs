@@ -0,0 +1,339 @@
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use rhai::{Array, Dynamic, Engine, EvalAltResult, FnPtr, Map, NativeCallContext, Scope, AST};
+
+use crate::config::{self, ConfigError};
+use crate::editor::{Editor, Position};
+use crate::rope_search::SearchOptions;
+
+/// Runs when the user's init script is (re)loaded; may register hooks.
+pub const INIT_SCRIPT: &str = "init.rhai";
+/// Stops runaway scripts (an endless loop would freeze the window).
+const MAX_OPERATIONS: u64 = 50_000_000;
+/// Lines kept in the console.
+const MAX_OUTPUT: usize = 1000;
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+thread_local! {
+    /// The buffer the running script acts on. Scripts only run on the UI
+    /// thread, and the editor is moved in here for the duration of a run.
+    static BUFFER: RefCell<Option<Editor>> = const { RefCell::new(None) };
+}
+
+/// Run `f` with `editor` available to the buffer functions.
+fn with_editor<T>(editor: &mut Editor, f: impl FnOnce() -> T) -> T {
+    BUFFER.with(|b| *b.borrow_mut() = Some(std::mem::take(editor)));
+    let result = f();
+    if let Some(e) = BUFFER.with(|b| b.borrow_mut().take()) {
+        *editor = e;
+    }
+    result
+}
+
+/// Call `f` on the buffer of the running script.
+fn buffer<T>(f: impl FnOnce(&mut Editor) -> T) -> ScriptResult<T> {
+    BUFFER.with(|b| match b.borrow_mut().as_mut() {
+        Some(editor) => Ok(f(editor)),
+        None => Err("no buffer is available here".into()),
+    })
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Hook {
+    Open,
+    Save,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputKind {
+    /// A command typed into the console.
+    Input,
+    Output,
+    Error,
+}
+
+pub struct OutputLine {
+    pub kind: OutputKind,
+    pub text: String,
+}
+
+/// A hook function and the script it was defined in, which it needs to run.
+struct Registered {
+    hook: Hook,
+    function: FnPtr,
+    ast: Rc<AST>,
+}
+
+/// The embedded Rhai engine, the console's variables and functions, and
+/// the hooks scripts have registered.
+pub struct ScriptHost {
+    engine: Engine,
+    /// Variables defined in the console, kept between commands.
+    scope: Scope<'static>,
+    /// Functions defined in the console so far.
+    functions: AST,
+    hooks: Vec<Registered>,
+    /// Hooks registered by the script running now, waiting for its AST.
+    new_hooks: Rc<RefCell<Vec<(Hook, FnPtr)>>>,
+    output: Rc<RefCell<Vec<OutputLine>>>,
+}
+
+impl Default for ScriptHost {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScriptHost {
+    pub fn new() -> Self {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+
+        let output: Rc<RefCell<Vec<OutputLine>>> = Rc::default();
+        let out = output.clone();
+        engine.on_print(move |text| push_line(&out, OutputKind::Output, text.to_string()));
+        let out = output.clone();
+        engine.on_debug(move |text, _, pos| push_line(&out, OutputKind::Output, format!("{} {}", pos, text)));
+
+        let new_hooks: Rc<RefCell<Vec<(Hook, FnPtr)>>> = Rc::default();
+        let hooks = new_hooks.clone();
+        engine.register_fn("on_open", move |f: FnPtr| hooks.borrow_mut().push((Hook::Open, f)));
+        let hooks = new_hooks.clone();
+        engine.register_fn("on_save", move |f: FnPtr| hooks.borrow_mut().push((Hook::Save, f)));
+
+        register_buffer_api(&mut engine);
+
+        Self {
+            engine,
+            scope: Scope::new(),
+            functions: AST::empty(),
+            hooks: Vec::new(),
+            new_hooks,
+            output,
+        }
+    }
+
+    pub fn output(&self) -> std::cell::Ref<'_, Vec<OutputLine>> {
+        self.output.borrow()
+    }
+
+    pub fn clear_output(&mut self) {
+        self.output.borrow_mut().clear();
+    }
+
+    /// Evaluate a console command against `editor`, logging the command,
+    /// its value (unless unit) and any error. Functions and variables it
+    /// defines stay available to later commands.
+    pub fn eval(&mut self, command: &str, editor: &mut Editor) {
+        self.log(OutputKind::Input, format!("> {}", command));
+        let ast = match self.engine.compile_with_scope(&self.scope, command) {
+            Ok(ast) => self.functions.merge(&ast),
+            Err(e) => return self.log(OutputKind::Error, e.to_string()),
+        };
+        let result = with_editor(editor, || self.engine.eval_ast_with_scope::<Dynamic>(&mut self.scope, &ast));
+        self.functions = ast.clone_functions_only();
+        self.adopt_hooks(&ast);
+        match result {
+            Ok(value) if value.is_unit() => {}
+            Ok(value) => self.log(OutputKind::Output, value.to_string()),
+            Err(e) => self.log(OutputKind::Error, e.to_string()),
+        }
+    }
+
+    /// Run the init script from the config directory, replacing every hook
+    /// registered so far. A missing script is not an error.
+    pub fn load_init(&mut self) -> Result<(), ConfigError> {
+        let path = config::config_dir().join(INIT_SCRIPT);
+        self.hooks.clear();
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(script_error(path, None, e.to_string())),
+        };
+        let ast = self
+            .engine
+            .compile(&text)
+            .map_err(|e| script_error(path.clone(), location(e.position()), e.to_string()))?;
+        let result = self.engine.run_ast(&ast);
+        self.adopt_hooks(&ast);
+        result.map_err(|e| script_error(path, location(e.position()), e.to_string()))
+    }
+
+    pub fn has_hooks(&self, hook: Hook) -> bool {
+        self.hooks.iter().any(|h| h.hook == hook)
+    }
+
+    /// Run every `hook` function against `editor`, in the order they were
+    /// registered. Returns the first error; later hooks still run.
+    pub fn run_hooks(&mut self, hook: Hook, editor: &mut Editor) -> Result<(), String> {
+        let mut first_error = None;
+        for registered in self.hooks.iter().filter(|h| h.hook == hook) {
+            let result = with_editor(editor, || {
+                registered.function.call::<Dynamic>(&self.engine, &registered.ast, ())
+            });
+            if let Err(e) = result {
+                let message = format!("{} hook failed: {}", hook_name(hook), e);
+                push_line(&self.output, OutputKind::Error, message.clone());
+                first_error.get_or_insert(message);
+            }
+        }
+        self.adopt_hooks(&AST::empty());
+        first_error.map_or(Ok(()), Err)
+    }
+
+    /// Keep the hooks the last script registered, with the functions they
+    /// may call.
+    fn adopt_hooks(&mut self, ast: &AST) {
+        let new: Vec<(Hook, FnPtr)> = self.new_hooks.borrow_mut().drain(..).collect();
+        if new.is_empty() {
+            return;
+        }
+        let ast = Rc::new(ast.clone_functions_only());
+        self.hooks.extend(new.into_iter().map(|(hook, function)| Registered {
+            hook,
+            function,
+            ast: ast.clone(),
+        }));
+    }
+
+    fn log(&mut self, kind: OutputKind, text: String) {
+        push_line(&self.output, kind, text);
+    }
+}
+
+fn push_line(output: &RefCell<Vec<OutputLine>>, kind: OutputKind, text: String) {
+    let mut output = output.borrow_mut();
+    if output.len() == MAX_OUTPUT {
+        output.remove(0);
+    }
+    output.push(OutputLine { kind, text });
+}
+
+fn hook_name(hook: Hook) -> &'static str {
+    match hook {
+        Hook::Open => "on_open",
+        Hook::Save => "on_save",
+    }
+}
+
+fn location(pos: rhai::Position) -> Option<(usize, usize)> {
+    Some((pos.line()?, pos.position().unwrap_or(1)))
+}
+
+fn script_error(path: PathBuf, location: Option<(usize, usize)>, message: String) -> ConfigError {
+    ConfigError { path, location, message }
+}
+
+fn position_map(pos: Position) -> Map {
+    let mut map = Map::new();
+    map.insert("line".into(), (pos.line as i64).into());
+    map.insert("col".into(), (pos.col as i64).into());
+    map
+}
+
+/// A script's line or column number; negative ones count as 0.
+fn index(n: i64) -> usize {
+    n.max(0) as usize
+}
+
+/// Functions scripts use to read and edit the current buffer. Lines and
+/// columns are 0-based and count characters.
+fn register_buffer_api(engine: &mut Engine) {
+    engine.register_fn("text", || buffer(|e| e.rope.to_string()));
+    engine.register_fn("set_text", |text: &str| buffer(|e| e.set_text(text)));
+    engine.register_fn("path", || {
+        buffer(|e| match &e.file_path {
+            Some(path) => Dynamic::from(path.display().to_string()),
+            None => Dynamic::UNIT,
+        })
+    });
+    engine.register_fn("line_count", || buffer(|e| e.line_count() as i64));
+    engine.register_fn("line", |n: i64| buffer(|e| e.line_text(index(n))));
+    engine.register_fn("set_line", |n: i64, text: &str| {
+        buffer(|e| {
+            if index(n) < e.line_count() {
+                e.set_line_text(index(n), text);
+            }
+        })
+    });
+    engine.register_fn("insert", |text: &str| buffer(|e| e.insert_text(text)));
+
+    engine.register_fn("cursor", || buffer(|e| position_map(e.cursors[0].pos)));
+    engine.register_fn("cursors", || {
+        buffer(|e| e.cursors.iter().map(|c| Dynamic::from(position_map(c.pos))).collect::<Array>())
+    });
+    engine.register_fn("set_cursor", |line: i64, col: i64| {
+        buffer(|e| {
+            e.cursors.clear();
+            e.add_cursor_at(index(line), index(col));
+        })
+    });
+    engine.register_fn("add_cursor", |line: i64, col: i64| buffer(|e| e.add_cursor_at(index(line), index(col))));
+    engine.register_fn("select", |line: i64, col: i64, to_line: i64, to_col: i64| {
+        buffer(|e| {
+            e.cursors.clear();
+            e.add_cursor_at(index(line), index(col));
+            let anchor = e.cursors[0].pos;
+            e.cursors.clear();
+            e.add_cursor_at(index(to_line), index(to_col));
+            e.cursors[0].anchor = Some(anchor);
+        })
+    });
+    engine.register_fn("selections", || {
+        buffer(|e| {
+            e.cursors
+                .iter()
+                .map(|c| {
+                    let text = match c.selection_ordered() {
+                        Some((start, end)) => e.text_between(start, end),
+                        None => String::new(),
+                    };
+                    Dynamic::from(text)
+                })
+                .collect::<Array>()
+        })
+    });
+    engine.register_fn("replace_selections", replace_selections);
+    engine.register_fn("find", |query: &str| {
+        buffer(|e| {
+            let found = e.search_iter(query, SearchOptions::default()).is_ok_and(|mut m| m.next().is_some());
+            if found {
+                let _ = e.find_and_select(query, SearchOptions::default());
+            }
+            found
+        })
+    });
+    engine.register_fn("replace_all", |find: &str, replace: &str| {
+        buffer(|e| {
+            let count = e.search_iter(find, SearchOptions::default()).map_or(0, |m| m.count());
+            let _ = e.replace_all(find, replace, SearchOptions::default());
+            count as i64
+        })
+    });
+}
+
+/// Replace each selection (or word under a cursor) with `f(text)`. The
+/// buffer is taken out of reach while `f` runs, so `f` can't edit it too.
+fn replace_selections(context: NativeCallContext, f: FnPtr) -> ScriptResult<()> {
+    let Some(mut editor) = BUFFER.with(|b| b.borrow_mut().take()) else {
+        return Err("no buffer is available here".into());
+    };
+    let error = RefCell::new(None);
+    editor.transform_selections(|text| {
+        if error.borrow().is_some() {
+            return text.to_string();
+        }
+        match f.call_within_context::<Dynamic>(&context, (text.to_string(),)) {
+            Ok(value) => value.to_string(),
+            Err(e) => {
+                *error.borrow_mut() = Some(e);
+                text.to_string()
+            }
+        }
+    });
+    BUFFER.with(|b| *b.borrow_mut() = Some(editor));
+    error.into_inner().map_or(Ok(()), Err)
+}
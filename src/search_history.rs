@@ -0,0 +1,83 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::crash;
+
+/// Queries kept per field; older ones are forgotten.
+const MAX_ENTRIES: usize = 50;
+
+fn history_path() -> PathBuf {
+    crash::app_data_dir().join("search_history.json")
+}
+
+/// Recent search and replace inputs, most recent first.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchHistory {
+    pub searches: Vec<String>,
+    pub replacements: Vec<String>,
+}
+
+impl SearchHistory {
+    /// The saved history; empty if there is none or it can't be read.
+    pub fn load() -> SearchHistory {
+        fs::read(history_path())
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        fs::create_dir_all(crash::app_data_dir())?;
+        fs::write(history_path(), serde_json::to_vec_pretty(self).unwrap_or_default())
+    }
+}
+
+/// Put `entry` first in `entries`, dropping an earlier copy of it. Returns
+/// whether anything changed.
+pub fn push(entries: &mut Vec<String>, entry: &str) -> bool {
+    if entry.is_empty() || entries.first().is_some_and(|e| e == entry) {
+        return false;
+    }
+    entries.retain(|e| e != entry);
+    entries.insert(0, entry.to_string());
+    entries.truncate(MAX_ENTRIES);
+    true
+}
+
+/// Where Up and Down have got to in a field's history. Going past the most
+/// recent entry brings back what was typed before recalling.
+#[derive(Default)]
+pub struct Recall {
+    index: Option<usize>,
+    draft: String,
+}
+
+impl Recall {
+    /// Replace `input` with the next older entry, or the next newer one.
+    pub fn step(&mut self, entries: &[String], input: &mut String, older: bool) {
+        let next = match (self.index, older) {
+            (None, true) if !entries.is_empty() => {
+                self.draft = input.clone();
+                Some(0)
+            }
+            (None, _) => return,
+            (Some(i), true) => Some((i + 1).min(entries.len() - 1)),
+            (Some(0), false) => None,
+            (Some(i), false) => Some(i - 1),
+        };
+        self.index = next;
+        *input = match next {
+            Some(i) => entries[i].clone(),
+            None => std::mem::take(&mut self.draft),
+        };
+    }
+
+    /// Forget the position, e.g. after the field was edited by hand.
+    pub fn reset(&mut self) {
+        self.index = None;
+    }
+}
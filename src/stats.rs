@@ -0,0 +1,122 @@
+/// Words per minute behind the reading time estimate.
+const READING_SPEED: usize = 230;
+
+/// Counts for a piece of text, as a writer would want them.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Stats {
+    pub chars: usize,
+    pub chars_no_spaces: usize,
+    pub words: usize,
+    pub lines: usize,
+    /// Runs of non-blank lines.
+    pub paragraphs: usize,
+}
+
+impl Stats {
+    pub fn of(text: impl IntoIterator<Item = char>) -> Stats {
+        let mut stats = Stats::default();
+        let mut words = WordCounter::default();
+        let mut line_blank = true;
+        let mut prev_line_blank = true;
+        for c in text {
+            stats.chars += 1;
+            words.push(c);
+            if c == '\n' {
+                stats.lines += 1;
+                prev_line_blank = line_blank;
+                line_blank = true;
+            } else if !c.is_whitespace() {
+                stats.chars_no_spaces += 1;
+                if line_blank && prev_line_blank {
+                    stats.paragraphs += 1;
+                }
+                line_blank = false;
+            }
+        }
+        // The last line has no newline after it
+        if stats.chars > 0 {
+            stats.lines += 1;
+        }
+        stats.words = words.finish();
+        stats
+    }
+
+    /// Minutes to read the text at an average pace, rounded up.
+    pub fn reading_minutes(&self) -> usize {
+        self.words.div_ceil(READING_SPEED)
+    }
+
+    pub fn add(&mut self, other: &Stats) {
+        self.chars += other.chars;
+        self.chars_no_spaces += other.chars_no_spaces;
+        self.words += other.words;
+        self.lines += other.lines;
+        self.paragraphs += other.paragraphs;
+    }
+}
+
+/// Words in `text`. A whitespace-separated run with a letter or digit in
+/// it counts once, while Chinese characters and Japanese kana count one
+/// each, since those languages aren't written with spaces.
+pub fn count_words(text: impl IntoIterator<Item = char>) -> usize {
+    let mut words = WordCounter::default();
+    for c in text {
+        words.push(c);
+    }
+    words.finish()
+}
+
+#[derive(Default)]
+struct WordCounter {
+    words: usize,
+    /// Inside a whitespace-separated run.
+    in_run: bool,
+    /// The current run has a letter or digit, so it is a word.
+    run_has_word: bool,
+}
+
+impl WordCounter {
+    fn push(&mut self, c: char) {
+        if c.is_whitespace() || is_cjk_punctuation(c) {
+            self.end_run();
+        } else if is_cjk(c) {
+            self.end_run();
+            self.words += 1;
+        } else {
+            self.in_run = true;
+            self.run_has_word |= c.is_alphanumeric();
+        }
+    }
+
+    fn end_run(&mut self) {
+        if self.in_run && self.run_has_word {
+            self.words += 1;
+        }
+        self.in_run = false;
+        self.run_has_word = false;
+    }
+
+    fn finish(mut self) -> usize {
+        self.end_run();
+        self.words
+    }
+}
+
+/// Han characters, hiragana and katakana. Korean is written with spaces,
+/// so Hangul counts like any other letter.
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30ff}'     // Hiragana, Katakana
+        | '\u{31f0}'..='\u{31ff}'   // Katakana phonetic extensions
+        | '\u{3400}'..='\u{4dbf}'   // CJK extension A
+        | '\u{4e00}'..='\u{9fff}'   // CJK unified ideographs
+        | '\u{f900}'..='\u{faff}'   // CJK compatibility ideographs
+        | '\u{ff66}'..='\u{ff9f}'   // Halfwidth katakana
+        | '\u{20000}'..='\u{2fa1f}' // CJK extensions B and later
+    )
+}
+
+/// Ideographic spaces, brackets and full stops, which separate words.
+fn is_cjk_punctuation(c: char) -> bool {
+    matches!(c, '\u{3000}'..='\u{303f}' | '\u{ff01}'..='\u{ff0f}' | '\u{ff1a}'..='\u{ff20}' | '\u{ff5b}'..='\u{ff65}')
+}
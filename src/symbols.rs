@@ -0,0 +1,142 @@
+/// A named definition found in a buffer, for `@` navigation.
+#[derive(Clone, Debug)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: &'static str,
+    /// Zero-based line.
+    pub line: usize,
+}
+
+/// Modifiers skipped before looking for a definition keyword.
+const MODIFIERS: &[&str] = &[
+    "pub", "pub(crate)", "pub(super)", "export", "default", "async", "unsafe", "static",
+    "public", "private", "protected", "abstract", "final", "extern",
+];
+
+/// Keywords that introduce a definition, with the kind shown in the palette.
+const KEYWORDS: &[(&str, &str)] = &[
+    ("fn", "function"),
+    ("def", "function"),
+    ("func", "function"),
+    ("function", "function"),
+    ("struct", "struct"),
+    ("enum", "enum"),
+    ("union", "union"),
+    ("trait", "trait"),
+    ("interface", "interface"),
+    ("impl", "impl"),
+    ("class", "class"),
+    ("mod", "module"),
+    ("module", "module"),
+    ("type", "type"),
+    ("const", "constant"),
+    ("macro_rules!", "macro"),
+];
+
+/// Definitions in `text`, found by keyword on each line. This is a heuristic
+/// that works across languages without a parser; Markdown headings count too.
+pub fn document_symbols(text: &str) -> Vec<Symbol> {
+    text.lines()
+        .enumerate()
+        .filter_map(|(line, content)| line_symbol(content).map(|(kind, name)| Symbol { name, kind, line }))
+        .collect()
+}
+
+fn line_symbol(content: &str) -> Option<(&'static str, String)> {
+    let trimmed = content.trim_start();
+    if let Some(heading) = trimmed.strip_prefix('#') {
+        let title = heading.trim_start_matches('#');
+        // "#include" and "#[attr]" are not headings
+        if title.starts_with(' ') && !title.trim().is_empty() {
+            return Some(("heading", title.trim().to_string()));
+        }
+        return None;
+    }
+
+    // Skip modifiers, including `const` in `const fn`
+    let mut rest = trimmed;
+    let keyword = loop {
+        let (word, after) = rest.split_once(char::is_whitespace)?;
+        rest = after.trim_start();
+        let const_fn = word == "const" && rest.starts_with("fn ");
+        if !MODIFIERS.contains(&word) && !const_fn {
+            // `impl<T>` has no space before its generics
+            break word.split('<').next().unwrap_or(word);
+        }
+    };
+    let &(_, kind) = KEYWORDS.iter().find(|(k, _)| *k == keyword)?;
+
+    let name = if keyword == "impl" {
+        // `impl<T> Trait for Type` reads best in full
+        rest.split('{').next().unwrap_or("").trim().to_string()
+    } else {
+        rest.chars()
+            .take_while(|c| c.is_alphanumeric() || *c == '_' || *c == '$')
+            .collect()
+    };
+    (!name.is_empty()).then_some((kind, name))
+}
+
+/// Lines of the definitions and headings that enclose `line`, outermost
+/// first. Code scopes end where indentation returns to the definition's
+/// level (a closing `}`, `)`, `]` or `end` at that level still belongs to
+/// it); a heading lasts until the next heading of the same or higher level.
+pub fn enclosing_scopes(text: &str, line: usize) -> Vec<usize> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut scopes = Vec::new();
+    for symbol in document_symbols(text) {
+        if symbol.line >= line {
+            break;
+        }
+        let encloses = if symbol.kind == "heading" {
+            let level = heading_level(lines[symbol.line]);
+            !lines[symbol.line + 1..=line.min(lines.len() - 1)]
+                .iter()
+                .any(|l| line_symbol(l).is_some_and(|(k, _)| k == "heading") && heading_level(l) <= level)
+        } else {
+            scope_end(&lines, symbol.line) >= line
+        };
+        if encloses {
+            scopes.push(symbol.line);
+        }
+    }
+    scopes
+}
+
+/// Last line of the indentation scope opened on `start`.
+fn scope_end(lines: &[&str], start: usize) -> usize {
+    let level = indent_width(lines[start]);
+    let mut end = start;
+    for (i, content) in lines.iter().enumerate().skip(start + 1) {
+        let trimmed = content.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        if indent_width(content) > level {
+            end = i;
+            continue;
+        }
+        let closes = trimmed.starts_with(['}', ')', ']']) || trimmed == "end" || trimmed.starts_with("end ");
+        if !closes {
+            break;
+        }
+        end = i;
+        // `) {` or `) -> T {` continues a multi-line signature
+        if !trimmed.ends_with('{') {
+            break;
+        }
+    }
+    end
+}
+
+fn indent_width(content: &str) -> usize {
+    content
+        .chars()
+        .take_while(|c| c.is_whitespace())
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum()
+}
+
+fn heading_level(content: &str) -> usize {
+    content.trim_start().chars().take_while(|&c| c == '#').count()
+}
@@ -1,13 +1,103 @@
 use eframe::egui::Color32;
+use serde::{Deserialize, Serialize};
+use ropey::Rope;
+use std::borrow::Cow;
 use std::path::Path;
 use syntect::easy::HighlightLines;
 use syntect::highlighting::{ThemeSet, Style};
-use syntect::parsing::{SyntaxSet, SyntaxReference};
-use syntect::util::LinesWithEndings;
+use syntect::parsing::{ParseState, Scope, ScopeStack, SyntaxSet, SyntaxReference};
 
 pub struct SyntaxHighlighter {
     syntax_set: SyntaxSet,
     theme_set: ThemeSet,
+    pub palette: SyntaxPalette,
+    /// Minimum WCAG contrast ratio enforced against `background`, if any.
+    pub min_contrast: Option<f32>,
+    /// Editor background the contrast is measured against.
+    pub background: Color32,
+    /// Use the light syntect theme instead of base16-eighties.
+    pub light: bool,
+}
+
+/// Alternative token colors for color vision deficiencies.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyntaxPalette {
+    Default,
+    Deuteranopia,
+    Protanopia,
+}
+
+/// Lines longer than this, in chars, are not highlighted.
+pub const MAX_HIGHLIGHT_CHARS: usize = 10_000;
+
+/// WCAG AA contrast for normal text.
+pub const DEFAULT_MIN_CONTRAST: f32 = 4.5;
+
+// base16-eighties accent colors, in order: red, orange, yellow, green, cyan, blue, purple
+const EIGHTIES_ACCENTS: [(u8, u8, u8); 7] = [
+    (0xf2, 0x77, 0x7a),
+    (0xf9, 0x91, 0x57),
+    (0xff, 0xcc, 0x66),
+    (0x99, 0xcc, 0x99),
+    (0x66, 0xcc, 0xcc),
+    (0x66, 0x99, 0xcc),
+    (0xcc, 0x99, 0xcc),
+];
+
+// Replacements based on the Okabe-Ito palette, lightened for a dark background.
+// Red/green pairs are moved onto the blue/orange axis that stays distinct.
+const DEUTERANOPIA_ACCENTS: [(u8, u8, u8); 7] = [
+    (0xe6, 0x9f, 0x00),
+    (0xf0, 0xe4, 0x42),
+    (0xff, 0xe8, 0xa8),
+    (0x56, 0xb4, 0xe9),
+    (0xa6, 0xdc, 0xef),
+    (0x7f, 0x9f, 0xff),
+    (0xcc, 0x79, 0xa7),
+];
+
+// Protanopes see long wavelengths darkened, so warm colors are kept bright.
+const PROTANOPIA_ACCENTS: [(u8, u8, u8); 7] = [
+    (0xff, 0xb0, 0x00),
+    (0xf0, 0xe4, 0x42),
+    (0xff, 0xf2, 0xb8),
+    (0x56, 0xb4, 0xe9),
+    (0xb0, 0xe0, 0xf0),
+    (0x8f, 0xa8, 0xff),
+    (0xdd, 0xaa, 0xdd),
+];
+
+impl SyntaxPalette {
+    pub const ALL: [SyntaxPalette; 3] = [
+        SyntaxPalette::Default,
+        SyntaxPalette::Deuteranopia,
+        SyntaxPalette::Protanopia,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SyntaxPalette::Default => "Default",
+            SyntaxPalette::Deuteranopia => "Deuteranopia",
+            SyntaxPalette::Protanopia => "Protanopia",
+        }
+    }
+
+    fn remap(self, color: Color32) -> Color32 {
+        let replacements = match self {
+            SyntaxPalette::Default => return color,
+            SyntaxPalette::Deuteranopia => &DEUTERANOPIA_ACCENTS,
+            SyntaxPalette::Protanopia => &PROTANOPIA_ACCENTS,
+        };
+        let rgb = (color.r(), color.g(), color.b());
+        match EIGHTIES_ACCENTS.iter().position(|&c| c == rgb) {
+            Some(i) => {
+                let (r, g, b) = replacements[i];
+                Color32::from_rgb(r, g, b)
+            }
+            None => color,
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -16,11 +106,30 @@ pub struct StyledToken {
     pub color: Color32,
 }
 
+impl Default for SyntaxHighlighter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl SyntaxHighlighter {
     pub fn new() -> Self {
         Self {
             syntax_set: SyntaxSet::load_defaults_newlines(),
             theme_set: ThemeSet::load_defaults(),
+            palette: SyntaxPalette::Default,
+            min_contrast: None,
+            background: Color32::from_rgb(30, 30, 30),
+            light: false,
+        }
+    }
+
+    /// Final token color after palette substitution and contrast enforcement.
+    fn token_color(&self, style: Style) -> Color32 {
+        let color = self.palette.remap(syntect_to_egui(style));
+        match self.min_contrast {
+            Some(ratio) => enforce_contrast(color, self.background, ratio),
+            None => color,
         }
     }
 
@@ -41,38 +150,173 @@ impl SyntaxHighlighter {
     }
 
     /// Highlight a range of lines. Returns a Vec of line token lists.
+    /// Lines are read straight from the rope; only those up to `last_line`
+    /// are touched, and a line is copied only if it spans rope chunks.
     pub fn highlight_lines(
         &self,
-        full_text: &str,
+        rope: &Rope,
         file_path: Option<&Path>,
         first_line: usize,
         last_line: usize,
     ) -> Vec<Vec<StyledToken>> {
         let syntax = self.find_syntax(file_path);
-        let theme = &self.theme_set.themes["base16-eighties.dark"];
+        let theme = &self.theme_set.themes[if self.light { "base16-ocean.light" } else { "base16-eighties.dark" }];
         let mut highlighter = HighlightLines::new(syntax, theme);
+        let plain = self.token_color(Style {
+            foreground: theme.settings.foreground.unwrap_or(syntect::highlighting::Color::WHITE),
+            background: theme.settings.background.unwrap_or(syntect::highlighting::Color::BLACK),
+            font_style: syntect::highlighting::FontStyle::empty(),
+        });
 
         let mut result = Vec::new();
-        for (i, line) in LinesWithEndings::from(full_text).enumerate() {
-            let regions = highlighter.highlight_line(line, &self.syntax_set).unwrap_or_default();
+        for (i, line) in rope.lines().enumerate() {
+            if i >= last_line {
+                break;
+            }
+            // Too long to highlight every frame: shown plain, and left out
+            // of the parse state as if it weren't there
+            if line.len_chars() > MAX_HIGHLIGHT_CHARS {
+                if i >= first_line {
+                    let text = line.to_string();
+                    let text = text.trim_end_matches('\n').trim_end_matches('\r').to_string();
+                    result.push(vec![StyledToken { text, color: plain }]);
+                }
+                continue;
+            }
+            let line: Cow<str> = line.into();
+            let regions = highlighter.highlight_line(&line, &self.syntax_set).unwrap_or_default();
             if i >= first_line && i < last_line {
                 let tokens: Vec<StyledToken> = regions
                     .iter()
                     .map(|(style, text)| StyledToken {
                         text: text.trim_end_matches('\n').trim_end_matches('\r').to_string(),
-                        color: syntect_to_egui(*style),
+                        color: self.token_color(*style),
                     })
                     .filter(|t| !t.text.is_empty())
                     .collect();
                 result.push(tokens);
             }
-            if i >= last_line {
-                break;
-            }
         }
 
         result
     }
+
+    /// Whether the character at `col` of `line` (or the line break, at its
+    /// end) is part of a comment, going by the scopes the file's grammar
+    /// gives it. Plain text has no comments.
+    pub fn in_comment(&self, rope: &Rope, file_path: Option<&Path>, line: usize, col: usize) -> bool {
+        let syntax = self.find_syntax(file_path);
+        let mut state = ParseState::new(syntax);
+        let mut stack = ScopeStack::new();
+        for (i, text) in rope.lines().enumerate().take(line + 1) {
+            // Skipped like highlight_lines skips it
+            if text.len_chars() > MAX_HIGHLIGHT_CHARS {
+                if i == line {
+                    return false;
+                }
+                continue;
+            }
+            let mut text = String::from(text);
+            if !text.ends_with('\n') {
+                text.push('\n');
+            }
+            let Ok(ops) = state.parse_line(&text, &self.syntax_set) else {
+                return false;
+            };
+            let end = if i == line {
+                text.char_indices().nth(col).map_or(text.len(), |(byte, _)| byte)
+            } else {
+                usize::MAX
+            };
+            for (offset, op) in &ops {
+                if *offset > end {
+                    break;
+                }
+                let _ = stack.apply(op);
+            }
+        }
+        let comment = Scope::new("comment").expect("valid scope");
+        stack.as_slice().iter().any(|scope| comment.is_prefix_of(*scope))
+    }
+
+    /// Languages the highlighter knows with their file extensions, by name,
+    /// for file dialog filters. The language of `first`, if known, comes
+    /// first.
+    pub fn file_types(&self, first: Option<&Path>) -> Vec<(String, Vec<String>)> {
+        let first = first.map(|path| self.find_syntax(Some(path)).name.as_str());
+        let mut types: Vec<(String, Vec<String>)> = self
+            .syntax_set
+            .syntaxes()
+            .iter()
+            .filter(|syntax| !syntax.file_extensions.is_empty())
+            .map(|syntax| (syntax.name.clone(), syntax.file_extensions.clone()))
+            .collect();
+        types.sort_by_key(|(name, _)| (Some(name.as_str()) != first, name.to_lowercase()));
+        types.dedup_by(|a, b| a.0 == b.0);
+        types
+    }
+}
+
+/// Tokens of a highlighted line range cut down to the columns between
+/// `start_col` on the first line and `end_col` on the last.
+pub fn slice_tokens(lines: Vec<Vec<StyledToken>>, start_col: usize, end_col: usize) -> Vec<Vec<StyledToken>> {
+    let last = lines.len().saturating_sub(1);
+    lines
+        .into_iter()
+        .enumerate()
+        .map(|(i, tokens)| {
+            let from = if i == 0 { start_col } else { 0 };
+            let to = if i == last { end_col } else { usize::MAX };
+            let mut col = 0;
+            let mut sliced = Vec::new();
+            for token in tokens {
+                let len = token.text.chars().count();
+                let (a, b) = (from.max(col), to.min(col + len));
+                if a < b {
+                    sliced.push(StyledToken {
+                        text: token.text.chars().skip(a - col).take(b - a).collect(),
+                        color: token.color,
+                    });
+                }
+                col += len;
+            }
+            sliced
+        })
+        .collect()
+}
+
+/// An HTML fragment with inline colors, so highlighted code keeps its
+/// colors when pasted into documents, slides or mail.
+pub fn tokens_to_html(lines: &[Vec<StyledToken>], background: Color32, foreground: Color32) -> String {
+    let mut html = format!(
+        "<pre style=\"background-color:{};color:{};font-family:monospace;padding:8px\">",
+        css_color(background),
+        css_color(foreground)
+    );
+    for (i, tokens) in lines.iter().enumerate() {
+        if i > 0 {
+            html.push('\n');
+        }
+        for token in tokens {
+            html.push_str(&format!("<span style=\"color:{}\">", css_color(token.color)));
+            for c in token.text.chars() {
+                match c {
+                    '&' => html.push_str("&amp;"),
+                    '<' => html.push_str("&lt;"),
+                    '>' => html.push_str("&gt;"),
+                    '"' => html.push_str("&quot;"),
+                    _ => html.push(c),
+                }
+            }
+            html.push_str("</span>");
+        }
+    }
+    html.push_str("</pre>");
+    html
+}
+
+fn css_color(color: Color32) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r(), color.g(), color.b())
 }
 
 fn syntect_to_egui(style: Style) -> Color32 {
@@ -82,3 +326,145 @@ fn syntect_to_egui(style: Style) -> Color32 {
         style.foreground.b,
     )
 }
+
+fn relative_luminance(c: Color32) -> f32 {
+    let channel = |v: u8| {
+        let v = v as f32 / 255.0;
+        if v <= 0.03928 {
+            v / 12.92
+        } else {
+            ((v + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * channel(c.r()) + 0.7152 * channel(c.g()) + 0.0722 * channel(c.b())
+}
+
+/// WCAG contrast ratio between two colors (1.0 ..= 21.0).
+pub fn contrast_ratio(a: Color32, b: Color32) -> f32 {
+    let (la, lb) = (relative_luminance(a), relative_luminance(b));
+    let (hi, lo) = if la > lb { (la, lb) } else { (lb, la) };
+    (hi + 0.05) / (lo + 0.05)
+}
+
+/// Blend `fg` toward white (dark backgrounds) or black (light backgrounds)
+/// until it reaches `ratio` against `bg`.
+pub fn enforce_contrast(fg: Color32, bg: Color32, ratio: f32) -> Color32 {
+    if contrast_ratio(fg, bg) >= ratio {
+        return fg;
+    }
+    let target = if relative_luminance(bg) < 0.5 {
+        Color32::WHITE
+    } else {
+        Color32::BLACK
+    };
+    let mix = |a: u8, b: u8, t: f32| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+    for step in 1..=20 {
+        let t = step as f32 / 20.0;
+        let c = Color32::from_rgb(
+            mix(fg.r(), target.r(), t),
+            mix(fg.g(), target.g(), t),
+            mix(fg.b(), target.b(), t),
+        );
+        if contrast_ratio(c, bg) >= ratio {
+            return c;
+        }
+    }
+    target
+}
+
+const PROSE_EXTENSIONS: &[&str] = &["txt", "md", "markdown", "rst", "adoc", "org", "tex"];
+
+/// Whether a file holds prose rather than code, by extension. Untitled
+/// buffers count as prose.
+pub fn is_prose(path: Option<&Path>) -> bool {
+    match path {
+        Some(path) => path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| PROSE_EXTENSIONS.contains(&e.to_lowercase().as_str())),
+        None => true,
+    }
+}
+
+/// Line comment prefix for a file, by extension. Untitled and unknown files
+/// fall back to `//`.
+pub fn line_comment_for(path: Option<&Path>) -> &'static str {
+    let ext = path
+        .and_then(|p| p.extension())
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "py" | "rb" | "sh" | "bash" | "zsh" | "fish" | "pl" | "r" | "toml" | "yaml" | "yml"
+        | "ini" | "conf" | "cmake" | "mk" | "ps1" | "nix" | "ex" | "exs" | "jl" => "#",
+        "sql" | "lua" | "hs" | "elm" | "ada" => "--",
+        "tex" | "sty" | "erl" => "%",
+        "lisp" | "el" | "clj" | "scm" | "asm" | "s" => ";",
+        "vim" => "\"",
+        "bat" | "cmd" => "REM",
+        _ => "//",
+    }
+}
+
+/// Indentation a language requires, whatever the settings or the file's
+/// current content say.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IndentPolicy {
+    /// Makefile recipes must start with a tab, and gofmt indents with tabs.
+    Tabs,
+    /// YAML, Elm and Nim reject tabs.
+    Spaces,
+}
+
+/// The indentation policy for a file, by name and extension; None when the
+/// language leaves it to the user.
+pub fn indent_policy_for(path: Option<&Path>) -> Option<IndentPolicy> {
+    let path = path?;
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    if matches!(name, "Makefile" | "makefile" | "GNUmakefile") {
+        return Some(IndentPolicy::Tabs);
+    }
+    let ext = path.extension().and_then(|e| e.to_str()).map(|e| e.to_lowercase()).unwrap_or_default();
+    match ext.as_str() {
+        "mk" | "mak" | "go" => Some(IndentPolicy::Tabs),
+        "yaml" | "yml" | "elm" | "nim" => Some(IndentPolicy::Spaces),
+        _ => None,
+    }
+}
+
+/// How a language's blocks open and close, for auto-indent.
+pub struct IndentRules {
+    /// Last words of a line that open a block, like `do` or `then`, besides
+    /// `{`, `(`, `[` and `:`.
+    pub openers: &'static [&'static str],
+    /// Words that close or continue a block; alone on a line they move it
+    /// back a level once the word is complete, when something other than a
+    /// word character follows it or Enter is pressed.
+    pub closers: &'static [&'static str],
+    /// For languages whose block words end their line with a character,
+    /// like Python's `else:`, the closers dedent only when it is typed.
+    pub closer_end: Option<char>,
+    /// First words of a statement that ends its block, so the line after it
+    /// is dedented.
+    pub block_enders: &'static [&'static str],
+}
+
+/// Auto-indent rules for a file, by extension.
+pub fn indent_rules_for(path: Option<&Path>) -> IndentRules {
+    let ext = path
+        .and_then(|p| p.extension())
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+    let closer_end = matches!(ext.as_str(), "py" | "pyw").then_some(':');
+    let (openers, closers, block_enders): (&[&str], &[&str], &[&str]) = match ext.as_str() {
+        "py" | "pyw" => (&[], &["else", "elif", "except", "finally"], &["return", "pass", "raise", "break", "continue"]),
+        "rb" => (&["do", "then"], &["end", "else", "elsif", "when", "rescue", "ensure"], &[]),
+        "lua" => (&["do", "then"], &["end", "else", "elseif", "until"], &[]),
+        "jl" => (&["do"], &["end", "else", "elseif", "catch", "finally"], &[]),
+        "ex" | "exs" => (&["do", "->"], &["end", "else", "rescue", "after", "catch"], &[]),
+        "sh" | "bash" | "zsh" => (&["do", "then"], &["fi", "done", "esac", "else", "elif"], &[]),
+        _ => (&[], &["else"], &[]),
+    };
+    IndentRules { openers, closers, closer_end, block_enders }
+}
@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use ropey::Rope;
+
+use crate::editor::Editor;
+
+/// Words that mark a comment as work left to do.
+pub const TAGS: [&str; 4] = ["TODO", "FIXME", "HACK", "XXX"];
+/// Workspace files larger than this are not scanned.
+const MAX_SCAN_BYTES: u64 = 1024 * 1024;
+/// Longest note kept for the list, in chars.
+const MAX_TEXT_CHARS: usize = 200;
+
+/// One `TODO`-style note. Line and column are 0-based.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Todo {
+    pub line: usize,
+    pub col: usize,
+    pub tag: &'static str,
+    /// What follows the tag, without the `:` after it.
+    pub text: String,
+}
+
+/// Where a group of notes lives: an open tab without a file, or a file.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TodoSource {
+    Tab(usize),
+    File(PathBuf),
+}
+
+/// The notes in one buffer or file.
+pub struct TodoGroup {
+    pub source: TodoSource,
+    pub name: String,
+    pub todos: Arc<Vec<Todo>>,
+}
+
+/// The first tag on a line, as a whole upper-case word.
+fn scan_line(line: &str, index: usize) -> Option<Todo> {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    for (byte, _) in line.char_indices() {
+        let rest = &line[byte..];
+        let Some(tag) = TAGS.iter().find(|t| rest.starts_with(**t)) else {
+            continue;
+        };
+        let before = line[..byte].chars().next_back();
+        let after = rest[tag.len()..].chars().next();
+        if before.is_some_and(is_word) || after.is_some_and(is_word) {
+            continue;
+        }
+        let text = rest[tag.len()..].trim_start_matches(':').trim();
+        return Some(Todo {
+            line: index,
+            col: line[..byte].chars().count(),
+            tag,
+            text: text.chars().take(MAX_TEXT_CHARS).collect(),
+        });
+    }
+    None
+}
+
+/// The notes in a buffer, one per line at most.
+pub fn scan_rope(rope: &Rope) -> Vec<Todo> {
+    rope.lines()
+        .enumerate()
+        .filter_map(|(i, line)| {
+            let line = line.to_string();
+            scan_line(line.trim_end_matches(['\n', '\r']), i)
+        })
+        .collect()
+}
+
+fn scan_text(text: &str) -> Vec<Todo> {
+    text.lines().enumerate().filter_map(|(i, line)| scan_line(line, i)).collect()
+}
+
+/// Notes in workspace files, with the modification time each file was read at.
+pub type WorkspaceTodos = HashMap<PathBuf, (SystemTime, Arc<Vec<Todo>>)>;
+
+/// Scan `files`, reusing `previous` results for files that haven't been
+/// modified since. Large and non-UTF-8 files are skipped.
+pub fn scan_workspace(files: &[PathBuf], previous: &WorkspaceTodos) -> WorkspaceTodos {
+    let mut scanned = HashMap::new();
+    for path in files {
+        let Ok(meta) = fs::metadata(path) else {
+            continue;
+        };
+        if meta.len() > MAX_SCAN_BYTES {
+            continue;
+        }
+        let modified = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+        let todos = match previous.get(path) {
+            Some((time, todos)) if *time == modified => todos.clone(),
+            _ => match fs::read_to_string(path) {
+                Ok(text) => Arc::new(scan_text(&text)),
+                Err(_) => continue,
+            },
+        };
+        if !todos.is_empty() {
+            scanned.insert(path.clone(), (modified, todos));
+        }
+    }
+    scanned
+}
+
+/// `TODO`, `FIXME`, `HACK` and `XXX` notes in the open buffers and, when
+/// asked for, the rest of the workspace. A buffer is scanned again only
+/// when its content changes.
+#[derive(Default)]
+pub struct TodoIndex {
+    /// Notes per buffer version; versions are unique across buffers.
+    buffers: HashMap<u64, Arc<Vec<Todo>>>,
+    /// Notes in workspace files, from the last scan.
+    pub workspace: Arc<WorkspaceTodos>,
+    /// Index generation the last workspace scan started at.
+    pub scanned_generation: Option<u64>,
+    /// A workspace scan is running.
+    pub scanning: bool,
+    /// List workspace files as well as open buffers.
+    pub include_workspace: bool,
+}
+
+impl TodoIndex {
+    /// Notes grouped by buffer, then by workspace file, each sorted by
+    /// line. Open buffers replace what the workspace scan found in their
+    /// files, since they may have unsaved edits.
+    pub fn groups(&mut self, editors: &[Editor], root: Option<&Path>) -> Vec<TodoGroup> {
+        let versions: Vec<u64> = editors.iter().map(|e| e.version).collect();
+        self.buffers.retain(|version, _| versions.contains(version));
+
+        let mut groups = Vec::new();
+        for (i, editor) in editors.iter().enumerate() {
+            let todos = self
+                .buffers
+                .entry(editor.version)
+                .or_insert_with(|| Arc::new(scan_rope(&editor.rope)))
+                .clone();
+            if todos.is_empty() {
+                continue;
+            }
+            let source = match &editor.file_path {
+                Some(path) => TodoSource::File(path.clone()),
+                None => TodoSource::Tab(i),
+            };
+            let name = match &editor.file_path {
+                Some(path) => display_name(path, root),
+                None => editor.title.clone(),
+            };
+            groups.push(TodoGroup { source, name, todos });
+        }
+
+        if self.include_workspace {
+            let mut files: Vec<(&PathBuf, &Arc<Vec<Todo>>)> = self
+                .workspace
+                .iter()
+                .filter(|(path, _)| !editors.iter().any(|e| e.file_path.as_ref() == Some(*path)))
+                .map(|(path, (_, todos))| (path, todos))
+                .collect();
+            files.sort_by(|a, b| a.0.cmp(b.0));
+            for (path, todos) in files {
+                groups.push(TodoGroup {
+                    source: TodoSource::File(path.clone()),
+                    name: display_name(path, root),
+                    todos: todos.clone(),
+                });
+            }
+        }
+        groups
+    }
+
+    /// Drop the workspace notes when the folder closes or changes.
+    pub fn forget_workspace(&mut self) {
+        self.workspace = Arc::default();
+        self.scanned_generation = None;
+    }
+}
+
+fn display_name(path: &Path, root: Option<&Path>) -> String {
+    root.and_then(|r| path.strip_prefix(r).ok()).unwrap_or(path).display().to_string()
+}
@@ -0,0 +1,106 @@
+use std::ops::Range;
+
+use eframe::egui::{self, accesskit, Rect};
+
+use crate::editor::{Editor, Position};
+use crate::ui::editor_view::EditorMetrics;
+
+/// Describe the editor to screen readers through AccessKit: a multi-line
+/// text input with one text run per line, and the primary selection in
+/// terms of those runs. Only the lines in view and the cursor's line are
+/// listed, so a large file costs no more than a small one. Does nothing
+/// unless a screen reader has turned AccessKit on.
+pub fn describe(ctx: &egui::Context, id: egui::Id, editor: &Editor, text_rect: Rect, lines: Range<usize>, metrics: &EditorMetrics) {
+    let cursor = &editor.cursors[0];
+    let anchor = cursor.anchor.unwrap_or(cursor.pos);
+    let described = ctx.accesskit_node_builder(id, |builder| {
+        builder.set_role(accesskit::Role::MultilineTextInput);
+        builder.set_name(editor.title.clone());
+        builder.set_default_action_verb(accesskit::DefaultActionVerb::Focus);
+        builder.set_text_selection(accesskit::TextSelection {
+            anchor: text_position(id, anchor),
+            focus: text_position(id, cursor.pos),
+        });
+    });
+    if described.is_none() {
+        return;
+    }
+
+    let mut listed: Vec<usize> = lines.collect();
+    for line in [anchor.line, cursor.pos.line] {
+        if !listed.contains(&line) {
+            listed.push(line);
+        }
+    }
+    listed.sort_unstable();
+
+    ctx.with_accessibility_parent(id, || {
+        for line in listed {
+            ctx.accesskit_node_builder(line_id(id, line), |builder| {
+                let text = editor.line_text(line);
+                let top = text_rect.top() + line as f32 * metrics.line_height - editor.scroll_y;
+                let left = text_rect.left() - editor.scroll_x;
+                let width = text.chars().count() as f32 * metrics.char_width;
+                builder.set_role(accesskit::Role::InlineTextBox);
+                builder.set_text_direction(accesskit::TextDirection::LeftToRight);
+                builder.set_bounds(accesskit::Rect {
+                    x0: left.into(),
+                    y0: top.into(),
+                    x1: (left + width).into(),
+                    y1: (top + metrics.line_height).into(),
+                });
+
+                // Every character is one cell wide; words run up to the
+                // start of the next word, as egui's own text fields do
+                let mut value = String::with_capacity(text.len() + 1);
+                let mut lengths = Vec::new();
+                let mut positions = Vec::new();
+                let mut widths = Vec::new();
+                let mut word_lengths = Vec::new();
+                let (mut word_start, mut after_word) = (0, false);
+                for (col, c) in text.chars().enumerate() {
+                    let word_char = c.is_alphanumeric() || c == '_';
+                    if word_char && after_word {
+                        word_lengths.push(saturate(col - word_start));
+                        word_start = col;
+                    }
+                    after_word = !word_char;
+                    value.push(c);
+                    lengths.push(c.len_utf8() as u8);
+                    positions.push(col as f32 * metrics.char_width);
+                    widths.push(metrics.char_width);
+                }
+                if line + 1 < editor.line_count() {
+                    value.push('\n');
+                    lengths.push(1);
+                    positions.push(width);
+                    widths.push(0.0);
+                }
+                word_lengths.push(saturate(lengths.len() - word_start));
+
+                builder.set_value(value);
+                builder.set_character_lengths(lengths);
+                builder.set_character_positions(positions);
+                builder.set_character_widths(widths);
+                builder.set_word_lengths(word_lengths);
+            });
+        }
+    });
+}
+
+/// AccessKit counts word lengths in a byte; longer words are cut short.
+fn saturate(len: usize) -> u8 {
+    u8::try_from(len).unwrap_or(u8::MAX)
+}
+
+fn line_id(editor_id: egui::Id, line: usize) -> egui::Id {
+    editor_id.with(("accesskit_line", line))
+}
+
+fn text_position(editor_id: egui::Id, pos: Position) -> accesskit::TextPosition {
+    accesskit::TextPosition {
+        // egui's node ids are its widget ids
+        node: accesskit::NodeId(line_id(editor_id, pos.line).value()),
+        character_index: pos.col,
+    }
+}
@@ -0,0 +1,139 @@
+use eframe::egui;
+
+use crate::collab::{self, Role, Session};
+
+/// What the user asked the collaboration window to do.
+pub enum CollabRequest {
+    /// With `public`, other computers may join, not just this one.
+    Host { name: String, port: u16, public: bool },
+    Join { name: String, url: String },
+    /// Keep the view on a collaborator's cursor, or stop with None.
+    Follow(Option<u64>),
+    Leave,
+}
+
+/// Window to host or join a collaboration session, and to see who is in
+/// the current one.
+pub struct CollabWindow {
+    pub open: bool,
+    name: String,
+    port: String,
+    /// Accept guests from other computers when hosting.
+    public: bool,
+    url: String,
+}
+
+impl Default for CollabWindow {
+    fn default() -> Self {
+        Self {
+            open: false,
+            name: collab::default_name(),
+            port: collab::DEFAULT_PORT.to_string(),
+            public: false,
+            url: String::new(),
+        }
+    }
+}
+
+impl CollabWindow {
+    pub fn show(&mut self, ctx: &egui::Context, session: Option<&Session>) -> Option<CollabRequest> {
+        if !self.open {
+            return None;
+        }
+        let mut request = None;
+        let mut open = true;
+        egui::Window::new("Collaboration")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| match session {
+                Some(session) => request = self.show_session(ui, session),
+                None => request = self.show_start(ui),
+            });
+        if !open {
+            self.open = false;
+        }
+        request
+    }
+
+    fn show_start(&mut self, ui: &mut egui::Ui) -> Option<CollabRequest> {
+        let mut request = None;
+        egui::Grid::new("collab_start").num_columns(2).spacing([8.0, 6.0]).show(ui, |ui| {
+            ui.label("Your name");
+            ui.text_edit_singleline(&mut self.name);
+            ui.end_row();
+        });
+        let name = self.name.trim().to_string();
+        ui.separator();
+
+        ui.strong("Host the current file");
+        ui.horizontal(|ui| {
+            ui.label("Port");
+            ui.add(egui::TextEdit::singleline(&mut self.port).desired_width(60.0));
+            let port = self.port.trim().parse::<u16>().ok();
+            if ui.add_enabled(port.is_some() && !name.is_empty(), egui::Button::new("Host")).clicked() {
+                request = port.map(|port| CollabRequest::Host { name: name.clone(), port, public: self.public });
+            }
+        });
+        ui.checkbox(&mut self.public, "Allow other computers to join")
+            .on_hover_text("Connections are not encrypted; anyone on the network who has the session link can edit");
+        ui.add_space(6.0);
+
+        ui.strong("Join a session");
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.url).desired_width(220.0).hint_text("Session link from the host"));
+            let url = self.url.trim().to_string();
+            let valid = url.starts_with("ws://") || url.starts_with("wss://");
+            if ui.add_enabled(valid && !name.is_empty(), egui::Button::new("Join")).clicked() {
+                request = Some(CollabRequest::Join { name: name.clone(), url });
+            }
+        });
+        request
+    }
+
+    fn show_session(&mut self, ui: &mut egui::Ui, session: &Session) -> Option<CollabRequest> {
+        let mut request = None;
+        match &session.role {
+            Role::Host { port, public, .. } => {
+                let (address, whom) = if *public { ("<your address>", "Others") } else { ("localhost", "Others on this computer") };
+                let link = session.role.join_url(address).unwrap_or_default();
+                ui.label(format!("Hosting on port {}. {} join with this link:", port, whom));
+                ui.horizontal(|ui| {
+                    ui.monospace(&link);
+                    if ui.small_button("Copy").clicked() {
+                        ui.ctx().copy_text(link.clone());
+                    }
+                });
+            }
+            Role::Guest { url } if session.connected => {
+                ui.label(format!("Connected to {}", url));
+            }
+            Role::Guest { url } => {
+                ui.label(format!("Connecting to {}\u{2026}", url));
+            }
+        }
+        ui.add_space(4.0);
+        ui.strong("People");
+        ui.label(format!("{} (you)", session.name));
+        for presence in session.collaborators.values() {
+            ui.horizontal(|ui| {
+                let (rect, _) = ui.allocate_exact_size(egui::vec2(10.0, 10.0), egui::Sense::hover());
+                ui.painter().rect_filled(rect, 2.0, presence.color());
+                ui.label(&presence.name);
+                let following = session.following == Some(presence.peer);
+                if ui.selectable_label(following, "Follow").on_hover_text("Keep their cursor in view").clicked() {
+                    request = Some(CollabRequest::Follow((!following).then_some(presence.peer)));
+                }
+            });
+        }
+        if session.collaborators.is_empty() {
+            ui.weak("Nobody else yet.");
+        }
+        ui.add_space(8.0);
+        if ui.button("Leave Session").clicked() {
+            request = Some(CollabRequest::Leave);
+        }
+        request
+    }
+}
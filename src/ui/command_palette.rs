@@ -1,5 +1,20 @@
 use eframe::egui::{self, Sense};
 
+use crate::calc;
+use crate::commands::{self, CommandId};
+use crate::config::Chrome;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::activity::ActivityHandle;
+use crate::editor::Editor;
+use crate::index::FileIndex;
+use crate::path_refs;
+use crate::scratch;
+use crate::symbols::{self, Symbol};
+use crate::workspace::Workspace;
+use crate::workspace_search::{self, TextHit};
+
 #[derive(Clone, Debug)]
 pub struct Command {
     pub name: String,
@@ -7,18 +22,94 @@ pub struct Command {
     pub id: CommandId,
 }
 
+/// Palette commands remembered for the recently-used list.
+const HISTORY_LEN: usize = 20;
+/// Workspace text search (`#`) stops after this many hits.
+const MAX_TEXT_HITS: usize = 200;
+/// Shortest `#` query worth scanning the workspace for.
+const MIN_TEXT_QUERY: usize = 2;
+/// Quick-open lists at most this many files.
+const MAX_FILE_RESULTS: usize = 200;
+
+/// What the palette lists.
 #[derive(Clone, Debug, PartialEq)]
-pub enum CommandId {
-    NewTab,
-    OpenFile,
-    SaveFile,
-    SaveFileAs,
-    CloseTab,
-    Find,
-    GoToLine,
-    SelectAll,
-    Undo,
-    Redo,
+enum Mode {
+    /// Commands, `:`/`@`/`#` navigation or an `=` calculation.
+    Commands,
+    /// Workspace files (quick-open).
+    Files,
+    /// Completions for a word prefix.
+    Completions(Vec<String>),
+    /// Titles of the fixes available at the cursor.
+    CodeActions(Vec<String>),
+    /// The name of a new scratch buffer.
+    ScratchName,
+}
+
+/// Hits of a `#` search, filled in by a job as they are found.
+#[derive(Default)]
+struct SearchProgress {
+    hits: Vec<TextHit>,
+    done: bool,
+}
+
+/// A `#` search for the app to run on its job pool.
+pub struct TextSearch {
+    query: String,
+    root: PathBuf,
+    /// The workspace's indexed files; without an index the job walks `root`.
+    files: Option<Arc<Vec<PathBuf>>>,
+    progress: Arc<Mutex<SearchProgress>>,
+}
+
+impl TextSearch {
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    /// Search the files, handing over hits as they are found, until the
+    /// limit or until `activity` is cancelled.
+    pub fn run(self, activity: &ActivityHandle, ctx: &egui::Context) -> usize {
+        let files = self.files.unwrap_or_else(|| {
+            let mut files = Vec::new();
+            path_refs::collect_files(&self.root, &mut files);
+            files.sort();
+            Arc::new(files)
+        });
+        let needle = self.query.to_lowercase();
+        let mut found = 0;
+        for file in files.iter() {
+            if activity.is_cancelled() || found >= MAX_TEXT_HITS {
+                break;
+            }
+            let Some(mut hits) = workspace_search::search_file(file, &needle) else {
+                continue; // binary or unreadable
+            };
+            if hits.is_empty() {
+                continue;
+            }
+            hits.truncate(MAX_TEXT_HITS - found);
+            found += hits.len();
+            lock(&self.progress).hits.extend(hits);
+            ctx.request_repaint();
+        }
+        lock(&self.progress).done = true;
+        ctx.request_repaint();
+        found
+    }
+}
+
+fn lock(progress: &Mutex<SearchProgress>) -> std::sync::MutexGuard<'_, SearchProgress> {
+    progress.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// What the palette can search besides its commands, and the colors to
+/// draw it in.
+pub struct PaletteContext<'a> {
+    pub editor: &'a Editor,
+    pub workspace: Option<&'a Workspace>,
+    pub index: Option<&'a FileIndex>,
+    pub chrome: Chrome,
 }
 
 pub struct CommandPalette {
@@ -26,6 +117,45 @@ pub struct CommandPalette {
     pub input: String,
     pub selected: usize,
     commands: Vec<Command>,
+    /// Tasks of the open workspace, listed after the commands.
+    task_commands: Vec<Command>,
+    debug_commands: Vec<Command>,
+    /// Commands run from the palette, most recent first, without duplicates.
+    history: Vec<CommandId>,
+    mode: Mode,
+    /// Files walked directly when there is no index, until the palette closes.
+    file_cache: Option<(PathBuf, Arc<Vec<PathBuf>>)>,
+    /// Symbols of the active buffer, keyed by its version.
+    symbol_cache: Option<(u64, Vec<Symbol>)>,
+    /// The latest `#` search: its query, the folder paths are shown
+    /// relative to, and its hits so far.
+    text_search: Option<(String, PathBuf, Arc<Mutex<SearchProgress>>)>,
+    /// A `#` search waiting for the app to start it.
+    search_request: Option<TextSearch>,
+    /// Scratch buffer names, read once per opening.
+    scratch_cache: Option<Vec<String>>,
+    /// The entries last listed, until the input or what they came from
+    /// changes, so the list isn't filtered and ranked again every frame.
+    ranked: Option<(RankedFor, Arc<Vec<Command>>)>,
+}
+
+/// What listed entries were made from, besides the palette's own state,
+/// which drops them whenever it changes.
+#[derive(PartialEq)]
+struct RankedFor {
+    input: String,
+    /// The active buffer's id and version, for symbols and undo labels.
+    editor: (u64, u64),
+    /// The workspace, and its index's generation once it has one.
+    workspace: Option<(PathBuf, Option<u64>)>,
+    /// `#` hits found so far.
+    hits: usize,
+}
+
+impl Default for CommandPalette {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl CommandPalette {
@@ -34,66 +164,402 @@ impl CommandPalette {
             visible: false,
             input: String::new(),
             selected: 0,
-            commands: vec![
-                Command {
-                    name: "New Tab".into(),
-                    shortcut: "Ctrl+N".into(),
-                    id: CommandId::NewTab,
-                },
-                Command {
-                    name: "Open File".into(),
-                    shortcut: "Ctrl+O".into(),
-                    id: CommandId::OpenFile,
-                },
-                Command {
-                    name: "Save File".into(),
-                    shortcut: "Ctrl+S".into(),
-                    id: CommandId::SaveFile,
-                },
-                Command {
-                    name: "Save File As...".into(),
-                    shortcut: "Ctrl+Shift+S".into(),
-                    id: CommandId::SaveFileAs,
-                },
-                Command {
-                    name: "Close Tab".into(),
-                    shortcut: "Ctrl+W".into(),
-                    id: CommandId::CloseTab,
-                },
-                Command {
-                    name: "Find".into(),
-                    shortcut: "Ctrl+F".into(),
-                    id: CommandId::Find,
-                },
-                Command {
-                    name: "Go to Line".into(),
-                    shortcut: "Ctrl+G".into(),
-                    id: CommandId::GoToLine,
-                },
-                Command {
-                    name: "Select All".into(),
-                    shortcut: "Ctrl+A".into(),
-                    id: CommandId::SelectAll,
-                },
-            ],
+            history: Vec::new(),
+            mode: Mode::Commands,
+            file_cache: None,
+            symbol_cache: None,
+            text_search: None,
+            search_request: None,
+            scratch_cache: None,
+            ranked: None,
+            task_commands: Vec::new(),
+            debug_commands: Vec::new(),
+            commands: commands::all()
+                .iter()
+                .map(|c| Command {
+                    name: c.name.clone(),
+                    shortcut: c.shortcut_label(),
+                    id: c.id.clone(),
+                })
+                .collect(),
         }
     }
 
     pub fn toggle(&mut self) {
         self.visible = !self.visible;
+        self.ranked = None;
         if self.visible {
             self.input.clear();
             self.selected = 0;
+            self.mode = Mode::Commands;
+            // Files may have changed since the last search
+            self.text_search = None;
+            self.file_cache = None;
+            self.scratch_cache = None;
         }
     }
 
+    /// Open the palette listing workspace files.
+    pub fn open_files(&mut self) {
+        self.toggle();
+        if self.visible {
+            self.mode = Mode::Files;
+        }
+    }
+
+    /// Open the palette asking for the name of a new scratch buffer.
+    pub fn open_scratch_name(&mut self) {
+        self.toggle();
+        if self.visible {
+            self.mode = Mode::ScratchName;
+        }
+    }
+
+    /// Open the palette offering `words` to complete the word at the cursor.
+    pub fn open_completions(&mut self, words: Vec<String>) {
+        self.visible = true;
+        self.input.clear();
+        self.selected = 0;
+        self.mode = Mode::Completions(words);
+        self.ranked = None;
+    }
+
+    /// Open the palette offering the fixes titled `titles`.
+    pub fn open_code_actions(&mut self, titles: Vec<String>) {
+        self.visible = true;
+        self.input.clear();
+        self.selected = 0;
+        self.mode = Mode::CodeActions(titles);
+        self.ranked = None;
+    }
+
     pub fn close(&mut self) {
         self.visible = false;
         self.input.clear();
+        self.mode = Mode::Commands;
+        self.ranked = None;
+    }
+
+    /// Remember a command run from the palette.
+    pub fn record(&mut self, id: &CommandId) {
+        if matches!(
+            id,
+            CommandId::RepeatLastCommand
+                | CommandId::ShowCommandPalette
+                | CommandId::JumpToLine(_)
+                | CommandId::OpenLocation(..)
+                | CommandId::OpenPath(_)
+                | CommandId::OpenScratch(_)
+                | CommandId::InsertCompletion(_)
+                | CommandId::ApplyCodeAction(_)
+        ) {
+            return;
+        }
+        self.history.retain(|h| h != id);
+        self.history.insert(0, id.clone());
+        self.history.truncate(HISTORY_LEN);
+        self.ranked = None;
+    }
+
+    /// The most recent command run from the palette.
+    pub fn last_command(&self) -> Option<CommandId> {
+        self.history.first().cloned()
+    }
+
+    /// Pick up shortcut changes after the keymap was reloaded.
+    pub fn refresh_shortcuts(&mut self) {
+        for cmd in &mut self.commands {
+            if let Some(c) = commands::all().iter().find(|c| c.id == cmd.id) {
+                cmd.shortcut = c.shortcut_label();
+            }
+        }
+        self.ranked = None;
+    }
+
+    /// Replace the workspace task entries.
+    pub fn set_tasks<'a>(&mut self, names: impl IntoIterator<Item = &'a str>) {
+        self.task_commands = names
+            .into_iter()
+            .map(|name| Command {
+                name: format!("Tasks: Run {}", name),
+                shortcut: String::new(),
+                id: CommandId::RunTask(name.to_string()),
+            })
+            .collect();
+        self.ranked = None;
+    }
+
+    /// Replace the workspace debug target entries.
+    pub fn set_debug_targets<'a>(&mut self, names: impl IntoIterator<Item = &'a str>) {
+        self.debug_commands = names
+            .into_iter()
+            .map(|name| Command {
+                name: format!("Debug: Start {}", name),
+                shortcut: String::new(),
+                id: CommandId::StartDebugging(name.to_string()),
+            })
+            .collect();
+        self.ranked = None;
+    }
+
+    fn history_rank(&self, id: &CommandId) -> Option<usize> {
+        self.history.iter().position(|h| h == id)
+    }
+
+    /// The `#` search to start, if the query changed.
+    pub fn take_search_request(&mut self) -> Option<TextSearch> {
+        self.search_request.take()
+    }
+
+    /// Whether a `#` search is still looking for more hits.
+    pub fn is_searching(&self) -> bool {
+        self.visible && self.text_search.as_ref().is_some_and(|(_, _, progress)| !lock(progress).done)
+    }
+
+    /// Indexed workspace files and their root, when a workspace is open.
+    fn indexed_files(cx: &PaletteContext) -> Option<(PathBuf, Arc<Vec<PathBuf>>)> {
+        let (ws, index) = (cx.workspace?, cx.index?);
+        let files = index.files();
+        let files = files.iter().filter(|f| !ws.is_excluded(f)).cloned().collect();
+        Some((ws.root.clone(), Arc::new(files)))
+    }
+
+    /// The folder searched without a workspace: the active file's checkout.
+    fn search_root(cx: &PaletteContext) -> Option<PathBuf> {
+        cx.editor.file_path.as_deref().and_then(path_refs::workspace_root_for)
+    }
+
+    /// Root and file list for quick-open: the index when a workspace is
+    /// open, otherwise a direct walk around the active file.
+    fn workspace_files(&mut self, cx: &PaletteContext) -> Option<(PathBuf, Arc<Vec<PathBuf>>)> {
+        if let Some(indexed) = Self::indexed_files(cx) {
+            return Some(indexed);
+        }
+        if self.file_cache.is_none() {
+            let root = Self::search_root(cx)?;
+            let mut files = Vec::new();
+            path_refs::collect_files(&root, &mut files);
+            files.sort();
+            self.file_cache = Some((root, Arc::new(files)));
+        }
+        self.file_cache.clone()
+    }
+
+    fn scratch_names(&mut self) -> &[String] {
+        self.scratch_cache.get_or_insert_with(scratch::list)
+    }
+
+    /// Quick-open entries: matching scratch buffers, then workspace files.
+    fn file_entries(&mut self, cx: &PaletteContext) -> Vec<Command> {
+        let query = self.input.trim().to_lowercase();
+        let mut entries: Vec<Command> = self
+            .scratch_names()
+            .iter()
+            .filter(|name| fuzzy_rank(name, &query).is_some())
+            .map(|name| Command {
+                name: name.clone(),
+                shortcut: "scratch".into(),
+                id: CommandId::OpenScratch(name.clone()),
+            })
+            .collect();
+        let Some((root, files)) = self.workspace_files(cx) else {
+            return entries;
+        };
+        let mut matches: Vec<(usize, String, &PathBuf)> = files
+            .iter()
+            .filter_map(|path| {
+                let rel = path_refs::relative_path(&root, path);
+                fuzzy_rank(&rel, &query).map(|rank| (rank, rel, path))
+            })
+            .collect();
+        matches.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.cmp(&b.1)));
+        entries.extend(matches.into_iter().take(MAX_FILE_RESULTS).map(|(_, rel, path)| {
+            let dir = Path::new(&rel).parent().map(|p| p.display().to_string()).unwrap_or_default();
+            Command {
+                name: path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or(rel),
+                shortcut: dir,
+                id: CommandId::OpenPath(path.clone()),
+            }
+        }));
+        entries
+    }
+
+    /// The single entry of the scratch name prompt.
+    fn scratch_name_entry(&mut self) -> Vec<Command> {
+        let name = match scratch::sanitize(&self.input) {
+            name if name.is_empty() => scratch::next_name(),
+            name => name,
+        };
+        let exists = self.scratch_names().contains(&name);
+        vec![Command {
+            name: if exists {
+                format!("Open scratch buffer \"{}\"", name)
+            } else {
+                format!("Create scratch buffer \"{}\"", name)
+            },
+            shortcut: String::new(),
+            id: CommandId::OpenScratch(name),
+        }]
+    }
+
+    /// Entries for the current input, as last ranked if nothing they come
+    /// from has changed.
+    fn entries(&mut self, cx: &PaletteContext) -> Arc<Vec<Command>> {
+        let hits = self.text_search.as_ref().map_or(0, |(_, _, progress)| lock(progress).hits.len());
+        let key = RankedFor {
+            input: self.input.clone(),
+            editor: (cx.editor.id, cx.editor.version),
+            workspace: cx.workspace.map(|ws| (ws.root.clone(), cx.index.map(FileIndex::generation))),
+            hits,
+        };
+        if let Some((ranked_for, entries)) = &self.ranked {
+            if *ranked_for == key {
+                return entries.clone();
+            }
+        }
+        let entries = Arc::new(self.rank_entries(cx));
+        self.ranked = Some((key, entries.clone()));
+        entries
+    }
+
+    /// Entries for the current input. Besides commands the input understands
+    /// `:line`, `@symbol` in the active buffer, `#text` across the workspace
+    /// and `=expression` to calculate.
+    fn rank_entries(&mut self, cx: &PaletteContext) -> Vec<Command> {
+        let editor = cx.editor;
+        match &self.mode {
+            Mode::Commands => {}
+            Mode::Files => return self.file_entries(cx),
+            Mode::ScratchName => return self.scratch_name_entry(),
+            Mode::Completions(words) => {
+                let query = self.input.to_lowercase();
+                return words
+                    .iter()
+                    .filter(|w| w.to_lowercase().contains(&query))
+                    .map(|w| Command {
+                        name: w.clone(),
+                        shortcut: String::new(),
+                        id: CommandId::InsertCompletion(w.clone()),
+                    })
+                    .collect();
+            }
+            Mode::CodeActions(titles) => {
+                let query = self.input.to_lowercase();
+                return titles
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, t)| t.to_lowercase().contains(&query))
+                    .map(|(i, t)| Command { name: t.clone(), shortcut: String::new(), id: CommandId::ApplyCodeAction(i) })
+                    .collect();
+            }
+        }
+
+        if let Some(line) = self.input.strip_prefix(':') {
+            return match line.trim().parse::<usize>() {
+                Ok(n) if n > 0 => vec![Command {
+                    name: format!("Go to line {}", n),
+                    shortcut: format!("of {}", editor.line_count()),
+                    id: CommandId::JumpToLine(n),
+                }],
+                _ => Vec::new(),
+            };
+        }
+
+        if let Some(expr) = self.input.strip_prefix('=') {
+            return match calc::evaluate(expr) {
+                Ok(value) => vec![Command {
+                    name: format!("= {}", calc::format(value)),
+                    shortcut: "Copy".to_string(),
+                    id: CommandId::CopyText(calc::format(value)),
+                }],
+                Err(_) => Vec::new(),
+            };
+        }
+
+        if let Some(query) = self.input.strip_prefix('@') {
+            let query = query.trim().to_lowercase();
+            if self.symbol_cache.as_ref().is_none_or(|(v, _)| *v != editor.version) {
+                let text = editor.rope.to_string();
+                self.symbol_cache = Some((editor.version, symbols::document_symbols(&text)));
+            }
+            let symbols = self.symbol_cache.as_ref().map(|(_, s)| s.as_slice()).unwrap_or(&[]);
+            return symbols
+                .iter()
+                .filter(|s| s.name.to_lowercase().contains(&query))
+                .map(|s| Command {
+                    name: s.name.clone(),
+                    shortcut: format!("{}  line {}", s.kind, s.line + 1),
+                    id: CommandId::JumpToLine(s.line + 1),
+                })
+                .collect();
+        }
+
+        if let Some(query) = self.input.strip_prefix('#') {
+            let query = query.trim().to_string();
+            if query.chars().count() < MIN_TEXT_QUERY {
+                self.text_search = None;
+                return Vec::new();
+            }
+            if self.text_search.as_ref().is_none_or(|(searched, _, _)| *searched != query) {
+                self.text_search = None;
+                let (root, files) = match Self::indexed_files(cx) {
+                    Some((root, files)) => (root, Some(files)),
+                    None => match Self::search_root(cx) {
+                        Some(root) => (root, None),
+                        None => return Vec::new(),
+                    },
+                };
+                let progress = Arc::new(Mutex::new(SearchProgress::default()));
+                self.text_search = Some((query.clone(), root.clone(), progress.clone()));
+                self.search_request = Some(TextSearch { query, root, files, progress });
+            }
+            let Some((_, root, progress)) = &self.text_search else {
+                return Vec::new();
+            };
+            return lock(progress)
+                .hits
+                .iter()
+                .map(|hit| Command {
+                    name: hit.text.chars().take(80).collect(),
+                    shortcut: format!("{}:{}", path_refs::relative_path(root, &hit.path), hit.line + 1),
+                    id: CommandId::OpenLocation(hit.path.clone(), hit.line + 1),
+                })
+                .collect();
+        }
+
+        let query = self.input.to_lowercase();
+        let mut filtered: Vec<Command> = self
+            .commands
+            .iter()
+            .chain(&self.task_commands)
+            .chain(&self.debug_commands)
+            .cloned()
+            .map(|mut c| {
+                // "Undo Replace All" when a command made the change
+                let label = match c.id {
+                    CommandId::Undo => editor.undo_label(),
+                    CommandId::Redo => editor.redo_label(),
+                    _ => None,
+                };
+                if let Some(label) = label {
+                    c.name = format!("{} {}", c.name, label);
+                }
+                c
+            })
+            .filter(|c| query.is_empty() || c.name.to_lowercase().contains(&query))
+            .collect();
+        // Recently used first when browsing; the sort is stable
+        if query.is_empty() {
+            filtered.sort_by_key(|c| self.history_rank(&c.id).unwrap_or(usize::MAX));
+        }
+        filtered
     }
 
     /// Show the command palette overlay. Returns the selected CommandId if one was chosen.
-    pub fn show(&mut self, ctx: &egui::Context) -> Option<CommandId> {
+    pub fn show(&mut self, ctx: &egui::Context, cx: &PaletteContext) -> Option<CommandId> {
         if !self.visible {
             return None;
         }
@@ -122,9 +588,9 @@ impl CommandPalette {
             .order(egui::Order::Foreground)
             .show(ctx, |ui| {
                 egui::Frame::none()
-                    .fill(egui::Color32::from_rgb(40, 40, 40))
+                    .fill(cx.chrome.popup)
                     .rounding(egui::Rounding::same(8.0))
-                    .stroke(egui::Stroke::new(1.0, egui::Color32::from_rgb(70, 70, 70)))
+                    .stroke(egui::Stroke::new(1.0, cx.chrome.border))
                     .inner_margin(egui::Margin::same(8.0))
                     .show(ui, |ui| {
                         ui.set_width(palette_width);
@@ -133,28 +599,26 @@ impl CommandPalette {
                             egui::TextEdit::singleline(&mut self.input)
                                 .desired_width(palette_width - 16.0)
                                 .font(egui::FontId::monospace(14.0))
-                                .text_color(egui::Color32::WHITE)
-                                .hint_text("Type a command..."),
+                                .text_color(cx.chrome.text)
+                                .hint_text(match self.mode {
+                                    Mode::Commands => "Type a command, :line, @symbol, #text or =math...",
+                                    Mode::Files if cx.index.is_some_and(|i| !i.is_ready()) => "Indexing workspace...",
+                                    Mode::Files => "Type a file name...",
+                                    Mode::Completions(_) => "Filter completions...",
+                                    Mode::CodeActions(_) => "Filter fixes...",
+                                    Mode::ScratchName => "Name the scratch buffer...",
+                                }),
                         );
                         input_response.request_focus();
 
                         ui.add_space(4.0);
 
-                        // Collect filtered commands as owned data to avoid borrow conflicts
-                        let query = self.input.to_lowercase();
-                        let filtered: Vec<Command> = self
-                            .commands
-                            .iter()
-                            .filter(|c| query.is_empty() || c.name.to_lowercase().contains(&query))
-                            .cloned()
-                            .collect();
+                        // Collect entries as owned data to avoid borrow conflicts
+                        let browsing = self.input.is_empty() && self.mode == Mode::Commands;
+                        let filtered = self.entries(cx);
                         let count = filtered.len();
 
-                        // Keyboard navigation
-                        if ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
-                            should_close = true;
-                            return;
-                        }
+                        // Keyboard navigation; the app closes the palette on Escape
                         if ctx.input(|i| i.key_pressed(egui::Key::ArrowDown)) && count > 0 {
                             self.selected = (self.selected + 1) % count;
                         }
@@ -172,6 +636,13 @@ impl CommandPalette {
                         if self.selected >= count && count > 0 {
                             self.selected = count - 1;
                         }
+                        if self.is_searching() {
+                            ui.label(
+                                egui::RichText::new(format!("Searching\u{2026} {} found", count))
+                                    .color(cx.chrome.weak)
+                                    .size(11.0),
+                            );
+                        }
 
                         // Command list
                         egui::ScrollArea::vertical()
@@ -179,8 +650,9 @@ impl CommandPalette {
                             .show(ui, |ui| {
                                 for (i, cmd) in filtered.iter().enumerate() {
                                     let is_selected = i == self.selected;
+                                    let recent = browsing && self.history_rank(&cmd.id).is_some();
                                     let bg = if is_selected {
-                                        egui::Color32::from_rgb(55, 55, 75)
+                                        cx.chrome.selected
                                     } else {
                                         egui::Color32::TRANSPARENT
                                     };
@@ -193,7 +665,7 @@ impl CommandPalette {
                                             ui.horizontal(|ui| {
                                                 ui.label(
                                                     egui::RichText::new(&cmd.name)
-                                                        .color(egui::Color32::WHITE)
+                                                        .color(cx.chrome.text)
                                                         .size(13.0),
                                                 );
                                                 ui.with_layout(
@@ -201,9 +673,16 @@ impl CommandPalette {
                                                     |ui| {
                                                         ui.label(
                                                             egui::RichText::new(&cmd.shortcut)
-                                                                .color(egui::Color32::from_rgb(120, 120, 120))
+                                                                .color(cx.chrome.weak)
                                                                 .size(11.0),
                                                         );
+                                                        if recent {
+                                                            ui.label(
+                                                                egui::RichText::new("recently used  ")
+                                                                    .color(cx.chrome.accent)
+                                                                    .size(11.0),
+                                                            );
+                                                        }
                                                     },
                                                 );
                                             });
@@ -222,7 +701,38 @@ impl CommandPalette {
         if should_close {
             self.close();
         }
+        if let Some(id) = &result {
+            self.record(id);
+        }
 
         result
     }
 }
+
+/// Rank `text` against a fuzzy `query` whose characters must appear in order;
+/// lower is better. Matches inside the file name rank ahead of path matches.
+pub fn fuzzy_rank(text: &str, query: &str) -> Option<usize> {
+    if query.is_empty() {
+        return Some(0);
+    }
+    let lower = text.to_lowercase();
+    let name_start = lower.rfind('/').map_or(0, |i| i + 1);
+    if let Some(pos) = lower[name_start..].find(query) {
+        return Some(pos);
+    }
+    if let Some(pos) = lower.find(query) {
+        return Some(1000 + pos);
+    }
+    let mut chars = lower.chars();
+    let mut gaps = 0;
+    for q in query.chars() {
+        loop {
+            let c = chars.next()?;
+            if c == q {
+                break;
+            }
+            gaps += 1;
+        }
+    }
+    Some(10_000 + gaps)
+}
@@ -0,0 +1,153 @@
+use std::path::Path;
+
+use eframe::egui;
+
+use crate::debugger::{DebugSession, DebugState, Variable};
+
+const ENDED_COLOR: egui::Color32 = egui::Color32::from_rgb(240, 110, 100);
+/// How deep expanded variables are shown.
+const MAX_DEPTH: usize = 8;
+
+/// Window with the debug session's controls, call stack, variables and
+/// program output. Controls act on the session directly; a selected frame
+/// is left in its `reveal` for the app to show.
+pub fn show(ctx: &egui::Context, open: &mut bool, session: Option<&mut DebugSession>, root: Option<&Path>) {
+    egui::Window::new("Debug")
+        .open(open)
+        .default_width(420.0)
+        .default_height(480.0)
+        .anchor(egui::Align2::RIGHT_TOP, [-12.0, 48.0])
+        .show(ctx, |ui| {
+            let Some(session) = session else {
+                ui.weak("No debug session. Add a [[debug]] target to .lux-edit/settings.toml, then run Debug: Start from the palette.");
+                return;
+            };
+            controls(ui, session);
+            ui.separator();
+
+            egui::CollapsingHeader::new("Call Stack").default_open(true).show(ui, |ui| {
+                if session.frames.is_empty() {
+                    ui.weak(if session.is_stopped() { "Loading..." } else { "Not stopped." });
+                }
+                let mut selected = None;
+                for (i, frame) in session.frames.iter().enumerate() {
+                    let location = match &frame.path {
+                        Some(path) => {
+                            let name = root.and_then(|r| path.strip_prefix(r).ok()).unwrap_or(path);
+                            format!("{}:{}", name.display(), frame.line + 1)
+                        }
+                        None => "unknown source".to_string(),
+                    };
+                    let text = format!("{}  {}", frame.name, location);
+                    if ui.selectable_label(i == session.selected_frame, text).clicked() {
+                        selected = Some(i);
+                    }
+                }
+                if let Some(i) = selected {
+                    session.select_frame(i);
+                }
+            });
+
+            egui::CollapsingHeader::new("Variables").default_open(true).show(ui, |ui| {
+                let mut expand = Vec::new();
+                egui::ScrollArea::vertical().id_salt("debug_variables").max_height(220.0).show(ui, |ui| {
+                    if session.scopes.is_empty() {
+                        ui.weak(if session.is_stopped() { "Loading..." } else { "Not stopped." });
+                    }
+                    for (i, scope) in session.scopes.iter().enumerate() {
+                        egui::CollapsingHeader::new(&scope.name)
+                            .id_salt(("debug_scope", scope.reference))
+                            .default_open(i == 0)
+                            .show(ui, |ui| variables(ui, session, scope.reference, 0, &mut expand));
+                    }
+                });
+                for reference in expand {
+                    session.expand(reference);
+                }
+            });
+
+            egui::CollapsingHeader::new("Output").default_open(true).show(ui, |ui| {
+                egui::ScrollArea::vertical()
+                    .id_salt("debug_output")
+                    .max_height(160.0)
+                    .auto_shrink([false, true])
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        for line in &session.output {
+                            ui.label(egui::RichText::new(line).monospace());
+                        }
+                    });
+            });
+        });
+}
+
+fn controls(ui: &mut egui::Ui, session: &mut DebugSession) {
+    ui.horizontal(|ui| {
+        ui.strong(&session.name);
+        match &session.state {
+            DebugState::Starting => {
+                ui.spinner();
+            }
+            DebugState::Running => {
+                ui.weak("running");
+            }
+            DebugState::Stopped { reason, .. } => {
+                ui.label(format!("paused on {}", reason));
+            }
+            DebugState::Ended(reason) => {
+                ui.colored_label(ENDED_COLOR, format!("ended: {}", reason));
+            }
+        }
+    });
+    let stopped = session.is_stopped();
+    let running = session.state == DebugState::Running;
+    ui.horizontal(|ui| {
+        if ui.add_enabled(stopped, egui::Button::new("Continue")).on_hover_text("F8").clicked() {
+            session.resume();
+        }
+        if ui.add_enabled(running, egui::Button::new("Pause")).on_hover_text("F6").clicked() {
+            session.pause();
+        }
+        if ui.add_enabled(stopped, egui::Button::new("Step Over")).on_hover_text("F10").clicked() {
+            session.step_over();
+        }
+        if ui.add_enabled(stopped, egui::Button::new("Step Into")).on_hover_text("F11").clicked() {
+            session.step_into();
+        }
+        if ui.add_enabled(stopped, egui::Button::new("Step Out")).on_hover_text("Shift+F11").clicked() {
+            session.step_out();
+        }
+        if ui.add_enabled(!session.is_ended(), egui::Button::new("Stop")).on_hover_text("Shift+F5").clicked() {
+            session.stop();
+        }
+    });
+}
+
+/// The children of `reference`, collecting the references of expanded
+/// variables whose children haven't been fetched.
+fn variables(ui: &mut egui::Ui, session: &DebugSession, reference: i64, depth: usize, expand: &mut Vec<i64>) {
+    let Some(children) = session.variables.get(&reference) else {
+        expand.push(reference);
+        ui.spinner();
+        return;
+    };
+    if children.is_empty() {
+        ui.weak("(none)");
+    }
+    for var in children {
+        if var.reference > 0 && depth < MAX_DEPTH {
+            egui::CollapsingHeader::new(label(var))
+                .id_salt(("debug_variable", reference, &var.name))
+                .show(ui, |ui| variables(ui, session, var.reference, depth + 1, expand));
+        } else {
+            let response = ui.label(label(var));
+            if let Some(type_name) = &var.type_name {
+                response.on_hover_text(type_name);
+            }
+        }
+    }
+}
+
+fn label(var: &Variable) -> egui::RichText {
+    egui::RichText::new(format!("{} = {}", var.name, var.value)).monospace()
+}
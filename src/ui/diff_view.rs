@@ -0,0 +1,192 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use eframe::egui::{self, Color32, FontId, Rect, Sense, Vec2};
+
+use crate::diff::{self, DiffRow};
+
+const REMOVED_BACKGROUND: Color32 = Color32::from_rgba_premultiplied(70, 25, 25, 90);
+const ADDED_BACKGROUND: Color32 = Color32::from_rgba_premultiplied(25, 60, 25, 90);
+const MISSING_BACKGROUND: Color32 = Color32::from_rgba_premultiplied(40, 40, 40, 60);
+const ERROR_COLOR: Color32 = Color32::from_rgb(240, 110, 100);
+
+/// Two files side by side, with changed lines colored and paired up, and
+/// buttons to step through the changes.
+pub struct DiffView {
+    pub open: bool,
+    old_path: PathBuf,
+    new_path: PathBuf,
+    rows: Vec<DiffRow>,
+    /// Rows where each run of changed lines starts.
+    changes: Vec<usize>,
+    /// Index into `changes` of the change last moved to.
+    current: Option<usize>,
+    /// Row to scroll to on the next frame.
+    scroll_to: Option<usize>,
+    /// A file couldn't be read.
+    error: Option<String>,
+}
+
+impl Default for DiffView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DiffView {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            old_path: PathBuf::new(),
+            new_path: PathBuf::new(),
+            rows: Vec::new(),
+            changes: Vec::new(),
+            current: None,
+            scroll_to: None,
+            error: None,
+        }
+    }
+
+    pub fn open_for(&mut self, old: PathBuf, new: PathBuf) {
+        self.old_path = old;
+        self.new_path = new;
+        self.open = true;
+        self.reload();
+        // Start at the first change
+        self.step(true);
+    }
+
+    /// Read both files again and compare them.
+    fn reload(&mut self) {
+        let read = |path: &Path| fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e));
+        let (old, new) = match (read(&self.old_path), read(&self.new_path)) {
+            (Ok(old), Ok(new)) => (old, new),
+            (Err(e), _) | (_, Err(e)) => {
+                tracing::warn!(error = %e, "failed to read file to compare");
+                self.error = Some(e);
+                self.rows.clear();
+                self.changes.clear();
+                return;
+            }
+        };
+        self.error = None;
+        self.rows = diff::side_by_side(&diff::line_diff(&old, &new));
+        self.changes = (0..self.rows.len())
+            .filter(|&i| self.rows[i].changed && (i == 0 || !self.rows[i - 1].changed))
+            .collect();
+        self.current = None;
+    }
+
+    /// Move to the next change, or the previous one.
+    fn step(&mut self, forward: bool) {
+        if self.changes.is_empty() {
+            return;
+        }
+        let last = self.changes.len() - 1;
+        let next = match (self.current, forward) {
+            (None, true) => 0,
+            (None, false) => last,
+            (Some(i), true) => (i + 1).min(last),
+            (Some(i), false) => i.saturating_sub(1),
+        };
+        self.current = Some(next);
+        self.scroll_to = Some(self.changes[next]);
+    }
+
+    /// Show the view. Returns a file to open in a tab when its Open button
+    /// is clicked.
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<PathBuf> {
+        if !self.open {
+            return None;
+        }
+        let mut open_file = None;
+        let mut open = self.open;
+        let size = ctx.screen_rect().size() * 0.9;
+        egui::Window::new("Compare Files")
+            .open(&mut open)
+            .default_size(size)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    let changes = match self.changes.len() {
+                        0 if self.error.is_none() => "The files are identical".to_string(),
+                        1 => "1 change".to_string(),
+                        n => format!("{} changes", n),
+                    };
+                    ui.label(changes);
+                    ui.separator();
+                    let any = !self.changes.is_empty();
+                    if ui.add_enabled(any, egui::Button::new("\u{2191} Previous")).clicked() {
+                        self.step(false);
+                    }
+                    if ui.add_enabled(any, egui::Button::new("\u{2193} Next")).clicked() {
+                        self.step(true);
+                    }
+                    if ui.button("Reload").clicked() {
+                        self.reload();
+                    }
+                });
+                if let Some(error) = &self.error {
+                    ui.colored_label(ERROR_COLOR, error);
+                }
+                ui.separator();
+
+                let half = (ui.available_width() - ui.spacing().item_spacing.x) / 2.0;
+                ui.horizontal(|ui| {
+                    for path in [&self.old_path, &self.new_path] {
+                        ui.allocate_ui(Vec2::new(half, ui.spacing().interact_size.y), |ui| {
+                            ui.set_width(half);
+                            ui.horizontal(|ui| {
+                                if ui.small_button("Open").on_hover_text("Open in a tab").clicked() {
+                                    open_file = Some(path.clone());
+                                }
+                                ui.label(path.display().to_string());
+                            });
+                        });
+                    }
+                });
+                ui.separator();
+                self.show_rows(ui, half);
+            });
+        self.open = open;
+        open_file
+    }
+
+    fn show_rows(&mut self, ui: &mut egui::Ui, half: f32) {
+        let font = FontId::monospace(12.0);
+        let row_height = ui.fonts(|f| f.row_height(&font));
+        let digits = self.rows.len().max(1).ilog10() as usize + 1;
+        let number_width = ui.fonts(|f| f.glyph_width(&font, '0')) * (digits + 1) as f32;
+        let mut scroll = egui::ScrollArea::vertical().auto_shrink([false, false]);
+        if let Some(row) = self.scroll_to.take() {
+            // A little context above the change
+            scroll = scroll.vertical_scroll_offset(row.saturating_sub(3) as f32 * row_height);
+        }
+        scroll.show_rows(ui, row_height, self.rows.len(), |ui, range| {
+            for row in &self.rows[range] {
+                let gap = ui.spacing().item_spacing.x;
+                let (rect, _) = ui.allocate_exact_size(Vec2::new(half * 2.0 + gap, row_height), Sense::hover());
+                let left = Rect::from_min_size(rect.min, Vec2::new(half, row_height));
+                let right = Rect::from_min_size(rect.min + Vec2::new(half + gap, 0.0), Vec2::new(half, row_height));
+                for (side, rect, background) in [(&row.old, left, REMOVED_BACKGROUND), (&row.new, right, ADDED_BACKGROUND)] {
+                    let painter = ui.painter_at(rect);
+                    match side {
+                        None => {
+                            painter.rect_filled(rect, 0.0, MISSING_BACKGROUND);
+                        }
+                        Some((line, text)) => {
+                            if row.changed {
+                                painter.rect_filled(rect, 0.0, background);
+                            }
+                            let weak = ui.visuals().weak_text_color();
+                            painter.text(rect.left_center(), egui::Align2::LEFT_CENTER, (line + 1).to_string(), font.clone(), weak);
+                            let text_pos = rect.left_center() + Vec2::new(number_width, 0.0);
+                            let color = ui.visuals().text_color();
+                            painter.text(text_pos, egui::Align2::LEFT_CENTER, text.replace('\t', "    "), font.clone(), color);
+                        }
+                    }
+                }
+            }
+        });
+    }
+}
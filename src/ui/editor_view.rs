@@ -1,19 +1,38 @@
+use std::ops::Range;
+
 use eframe::egui::{self, Color32, FontId, Pos2, Rect, Sense, Stroke, Vec2};
 
-use crate::editor::{Editor, LINE_HEIGHT};
-use crate::syntax::SyntaxHighlighter;
+use crate::calc;
+use crate::clipboard::ClipboardContent;
+use crate::codec;
+use crate::collab::Presence;
+use crate::color_literals::{self, ColorLiteral};
+use crate::commands::{self, CommandId, Scope};
+use crate::config::{Theme, DEFAULT_FONT_SIZE};
+use crate::conflicts::{Conflict, Resolution};
+use crate::diagnostics::Diagnostic;
+use crate::editor::{Editor, Position, LINE_HEIGHT};
+use crate::rope_search::SearchOptions;
+use crate::symbols;
+use crate::syntax::{self, StyledToken, SyntaxHighlighter};
+use crate::ui::{accessibility, gutter, jump};
 use arboard::Clipboard;
 
-const BG_COLOR: Color32 = Color32::from_rgb(30, 30, 30);
-const TEXT_COLOR: Color32 = Color32::from_rgb(212, 212, 212);
-const CURSOR_COLOR: Color32 = Color32::from_rgb(248, 248, 240);
-const SELECTION_BG: Color32 = Color32::from_rgba_premultiplied(60, 100, 150, 120);
-const LINE_NUM_COLOR: Color32 = Color32::from_rgb(90, 90, 90);
-const LINE_NUM_ACTIVE_COLOR: Color32 = Color32::from_rgb(180, 180, 180);
-const GUTTER_BG: Color32 = Color32::from_rgb(37, 37, 37);
-const ACTIVE_LINE_BG: Color32 = Color32::from_rgb(40, 40, 40);
-const FONT_SIZE: f32 = 14.0;
+const OVERWRITE_CURSOR_BG: Color32 = Color32::from_rgba_premultiplied(150, 150, 140, 150);
 const GUTTER_PADDING: f32 = 16.0;
+/// How long the cursor rests in a word before its other occurrences light up.
+const WORD_HIGHLIGHT_DELAY: f64 = 0.3;
+/// Seconds the cursor stays on, then off, while blinking.
+const BLINK_INTERVAL: f64 = 0.5;
+/// Most enclosing definitions pinned by sticky scroll.
+const MAX_STICKY_LINES: usize = 3;
+const JUMP_LABEL_BG: Color32 = Color32::from_rgb(255, 200, 60);
+const CONFLICT_CURRENT_BG: Color32 = Color32::from_rgba_premultiplied(20, 60, 40, 60);
+const CONFLICT_INCOMING_BG: Color32 = Color32::from_rgba_premultiplied(20, 45, 80, 60);
+const CONFLICT_BASE_BG: Color32 = Color32::from_rgba_premultiplied(40, 40, 40, 60);
+const CONFLICT_MARKER_BG: Color32 = Color32::from_rgba_premultiplied(50, 50, 70, 110);
+const EXECUTION_LINE_BG: Color32 = Color32::from_rgba_premultiplied(70, 60, 10, 70);
+const CONFLICT_ACTION: Color32 = Color32::from_rgb(140, 170, 220);
 
 pub struct EditorMetrics {
     pub char_width: f32,
@@ -23,51 +42,201 @@ pub struct EditorMetrics {
 }
 
 impl EditorMetrics {
-    pub fn compute(ui: &egui::Ui, line_count: usize) -> Self {
-        let font_id = FontId::monospace(FONT_SIZE);
-        let char_width = ui.fonts(|f| {
-            let galley = f.layout_no_wrap("M".to_string(), font_id.clone(), TEXT_COLOR);
-            galley.size().x
-        });
+    pub fn compute(ui: &egui::Ui, line_count: usize, line_numbers: bool, font_size: f32) -> Self {
+        let font_id = FontId::monospace(font_size);
+        let char_width = cell_width(ui.ctx(), &font_id);
         let digits = format!("{}", line_count).len().max(3);
-        let gutter_width = char_width * digits as f32 + GUTTER_PADDING * 2.0;
+        let gutter_width = if line_numbers {
+            char_width * digits as f32 + GUTTER_PADDING * 2.0
+        } else {
+            gutter::LANES_WIDTH
+        };
 
+        // Whole physical pixels, so lines stay sharp at fractional scales
+        let pixels_per_point = ui.ctx().pixels_per_point();
         Self {
             char_width,
-            line_height: LINE_HEIGHT,
+            line_height: (LINE_HEIGHT * font_size / DEFAULT_FONT_SIZE * pixels_per_point).round() / pixels_per_point,
             gutter_width,
             font_id,
         }
     }
 }
 
+/// Width of a monospace cell, kept per font and UI scale so the layout
+/// behind it runs once rather than for every editor on every frame.
+#[derive(Clone, Copy)]
+struct CellWidth(f32);
+
+fn cell_width(ctx: &egui::Context, font_id: &FontId) -> f32 {
+    let id = egui::Id::new(("cell_width", font_id, ctx.pixels_per_point().to_bits()));
+    if let Some(CellWidth(width)) = ctx.data(|d| d.get_temp(id)) {
+        return width;
+    }
+    let width = ctx.fonts(|f| f.layout_no_wrap("M".to_string(), font_id.clone(), Color32::WHITE).size().x);
+    ctx.data_mut(|d| d.insert_temp(id, CellWidth(width)));
+    width
+}
+
+/// Drop the measured cell widths, after a zoom or theme change.
+pub fn forget_metrics(ctx: &egui::Context) {
+    ctx.data_mut(|d| d.remove_by_type::<CellWidth>());
+}
+
+/// How the editor area is drawn this frame.
+pub struct ViewOptions<'a> {
+    /// Take keyboard focus without a click (no overlay is open).
+    pub auto_focus: bool,
+    /// The window is unfocused or minimized: the cursor stops blinking.
+    pub idle: bool,
+    pub line_numbers: bool,
+    pub sticky_scroll: bool,
+    /// Columns with a vertical ruler.
+    pub rulers: &'a [usize],
+    pub scroll_past_end: bool,
+    /// Lines kept visible above and below the cursor when it moves.
+    pub scroll_margin: usize,
+    /// Highlight the cursor's line only while the editor has keyboard focus.
+    pub active_line_focus_only: bool,
+    /// Draw a `¶` where each line ends.
+    pub show_line_ends: bool,
+    /// Draw a swatch after lines with color literals.
+    pub color_swatches: bool,
+    pub font_size: f32,
+    /// Carry comments on to the next line on Enter.
+    pub continue_comments: bool,
+    /// Re-indent multi-line pastes to the cursor's line.
+    pub reindent_paste: bool,
+    /// Expand Emmet abbreviations on Tab.
+    pub emmet: bool,
+    /// Query of the open find bar; its matches are highlighted.
+    pub search: Option<(&'a str, SearchOptions)>,
+    /// Other people editing this buffer; their cursors are drawn in their colors.
+    pub collaborators: &'a [Presence],
+    /// A followed collaborator's cursor, kept in view instead of our own.
+    pub follow: Option<Position>,
+    pub theme: &'a Theme,
+}
+
+/// A click while drawing the lines that changes the buffer.
+enum LineAction {
+    Resolve(Conflict, Resolution),
+    /// Replace a color literal on `line` with `color`. `join` folds it into
+    /// the previous change from the same picker.
+    Recolor { line: usize, literal: ColorLiteral, color: Color32, join: bool },
+}
+
+/// The color picker opened from a swatch, by where its literal starts.
+#[derive(Clone, Copy)]
+struct ColorPicker {
+    line: usize,
+    col: usize,
+    /// Below the swatch, where the picker was last shown.
+    at: Pos2,
+    /// Frame it opened on, so the opening click doesn't close it.
+    opened: u64,
+    /// Whether it has changed the literal yet.
+    edited: bool,
+}
+
+/// What `show` works out about the editor before drawing it.
+struct FrameState<'a> {
+    /// Lines pinned at the top by sticky scroll.
+    sticky: &'a [usize],
+    /// Keyboard focus in a window that has it: only then does the cursor
+    /// blink, and the current line may be highlighted only then.
+    focused: bool,
+}
+
 /// Renders the editor area and handles input. Returns true if content changed.
-pub fn show(ui: &mut egui::Ui, editor: &mut Editor, clipboard: &mut Option<Clipboard>, highlighter: &SyntaxHighlighter, auto_focus: bool) -> bool {
+pub fn show(ui: &mut egui::Ui, editor: &mut Editor, clipboard: &mut Option<Clipboard>, highlighter: &SyntaxHighlighter, opts: &ViewOptions) -> bool {
     let mut changed = false;
-    let metrics = EditorMetrics::compute(ui, editor.line_count());
+    let metrics = EditorMetrics::compute(ui, editor.line_count(), opts.line_numbers, opts.font_size);
+    editor.line_height = metrics.line_height;
+    editor.reindent_paste = opts.reindent_paste;
     let available = ui.available_rect_before_wrap();
+    editor.view_height = available.height();
 
     // Background
     ui.painter()
-        .rect_filled(available, 0.0, BG_COLOR);
+        .rect_filled(available, 0.0, opts.theme.background);
 
     // Allocate the full area as an interactive region
     let response = ui.allocate_rect(available, Sense::click_and_drag());
 
     // Request focus on click/drag, or automatically when no overlay is active
-    if response.clicked() || response.dragged() || auto_focus {
+    if response.clicked() || response.dragged() || opts.auto_focus {
         ui.memory_mut(|m| m.request_focus(response.id));
     }
 
     let has_focus = ui.memory(|m| m.has_focus(response.id));
+    editor.relocate_markers();
+
+    let sticky = if opts.sticky_scroll {
+        // Recomputed only when the buffer changes or it scrolls by a line
+        let first_line = (editor.scroll_y / metrics.line_height).floor() as usize;
+        let key = (editor.version, first_line);
+        let id = response.id.with("sticky_lines");
+        match ui.data(|d| d.get_temp::<((u64, usize), Vec<usize>)>(id)) {
+            Some((cached, lines)) if cached == key => lines,
+            _ => {
+                let lines = sticky_lines(editor, first_line);
+                ui.data_mut(|d| d.insert_temp(id, (key, lines.clone())));
+                lines
+            }
+        }
+    } else {
+        Vec::new()
+    };
+
+    // A click on a sticky line jumps to that definition
+    let sticky_bottom = available.top() + sticky.len() as f32 * metrics.line_height;
+    let sticky_click = response
+        .interact_pointer_pos()
+        .filter(|p| response.clicked() && p.y < sticky_bottom)
+        .map(|p| sticky[((p.y - available.top()) / metrics.line_height) as usize]);
+    if let Some(line) = sticky_click {
+        editor.goto_line(line + 1);
+    }
+
+    // Before a click or key can collapse it
+    editor.note_selection();
+
+    // A second press in quick succession, as in a double-click, makes a
+    // drag select whole words
+    let word_drag_id = response.id.with("word_drag");
+    if response.is_pointer_button_down_on() && ui.input(|i| i.pointer.primary_pressed()) {
+        if let Some(pos) = response.interact_pointer_pos() {
+            let press_id = response.id.with("last_press");
+            let time = ui.input(|i| i.time);
+            let delay = ui.ctx().options(|o| o.input_options.max_double_click_delay);
+            let last = ui.data(|d| d.get_temp::<f64>(press_id));
+            let (line, col) = screen_to_editor_pos(pos, &available, &metrics, editor);
+            let word = editor.word_bounds_at(Position::new(line, col));
+            ui.data_mut(|d| {
+                d.insert_temp(press_id, time);
+                if last.is_some_and(|last| time - last < delay) {
+                    d.insert_temp(word_drag_id, word);
+                } else {
+                    d.remove::<(Position, Position)>(word_drag_id);
+                }
+            });
+        }
+    }
 
     // Handle mouse click -> set cursor position
-    if response.clicked() {
+    if response.clicked() && sticky_click.is_none() {
+        editor.jump_query = None;
         if let Some(pos) = response.interact_pointer_pos() {
             let (line, col) = screen_to_editor_pos(pos, &available, &metrics, editor);
             let ctrl = ui.input(|i| i.modifiers.command);
-            if ctrl {
-                editor.add_cursor_at(line, col);
+            if gutter::in_lanes(pos, available.left()) {
+                gutter::click(editor, line, pos.x - available.left());
+            } else if ctrl {
+                // On another occurrence of a selected word, select it too
+                if !editor.add_occurrence_at(Position::new(line, col)) {
+                    editor.add_cursor_at(line, col);
+                }
             } else {
                 editor.cursors.truncate(1);
                 editor.cursors[0].pos = crate::editor::Position::new(line, col);
@@ -93,41 +262,158 @@ pub fn show(ui: &mut egui::Ui, editor: &mut Editor, clipboard: &mut Option<Clipb
     if response.dragged() {
         if let Some(pos) = response.interact_pointer_pos() {
             let (line, col) = screen_to_editor_pos(pos, &available, &metrics, editor);
+            let word_drag = ui.data(|d| d.get_temp::<(Position, Position)>(word_drag_id));
+            let word = editor.word_bounds_at(Position::new(line, col));
             let cursor = &mut editor.cursors[0];
-            if cursor.anchor.is_none() {
-                cursor.anchor = Some(cursor.pos);
+            if let Some((start, end)) = word_drag {
+                // Keep the first word selected, extended by whole words
+                if word.0 < start {
+                    cursor.anchor = Some(end);
+                    cursor.pos = word.0;
+                } else {
+                    cursor.anchor = Some(start);
+                    cursor.pos = word.1.max(end);
+                }
+            } else {
+                if cursor.anchor.is_none() {
+                    cursor.anchor = Some(cursor.pos);
+                }
+                cursor.pos = crate::editor::Position::new(line, col);
             }
-            cursor.pos = crate::editor::Position::new(line, col);
-            cursor.desired_col = col;
+            cursor.desired_col = cursor.pos.col;
         }
     }
 
-    // Handle scroll
-    let scroll_delta = ui.input(|i| i.smooth_scroll_delta.y);
-    if scroll_delta != 0.0 {
-        editor.scroll_y = (editor.scroll_y - scroll_delta).max(0.0);
-        let max_scroll = (editor.line_count() as f32 * metrics.line_height - available.height())
-            .max(0.0);
-        editor.scroll_y = editor.scroll_y.min(max_scroll);
+    // Scroll on both axes with pixel precision, so trackpads pan freely. A
+    // zoom gesture (pinch or Ctrl+wheel) is left to the app's font zoom.
+    let scroll_delta = ui.input(|i| if i.zoom_delta() != 1.0 { Vec2::ZERO } else { i.smooth_scroll_delta });
+    editor.scroll_x -= scroll_delta.x;
+    editor.scroll_y -= scroll_delta.y;
+
+    // Handle keyboard input. Tab and the arrows edit the buffer, so egui must
+    // not also use them to move focus to the next widget.
+    if has_focus {
+        ui.memory_mut(|m| {
+            m.set_focus_lock_filter(
+                response.id,
+                egui::EventFilter { tab: true, horizontal_arrows: true, vertical_arrows: true, escape: false },
+            )
+        });
+        if editor.jump_query.is_some() {
+            jump::handle_input(ui, editor, visible_lines(&available, &metrics, editor));
+        } else {
+            changed = handle_keyboard(ui, editor, clipboard, highlighter, opts);
+        }
+    }
+
+    // Bring the primary (or followed) cursor into view when it moves,
+    // keeping `scroll_margin` lines around it unless the mouse put it there
+    let cursor_id = response.id.with("last_cursor");
+    let pos = opts.follow.unwrap_or(editor.cursors[0].pos);
+    let last_pos = ui.data(|d| d.get_temp::<Position>(cursor_id));
+    ui.data_mut(|d| d.insert_temp(cursor_id, pos));
+    if last_pos != Some(pos) {
+        let visible = (available.height() / metrics.line_height) as usize;
+        let margin = if response.clicked() || response.dragged() {
+            0
+        } else {
+            opts.scroll_margin.min(visible.saturating_sub(1) / 2)
+        };
+        let top = pos.line.saturating_sub(margin) as f32 * metrics.line_height;
+        let bottom = (pos.line + 1 + margin) as f32 * metrics.line_height;
+        if top < editor.scroll_y {
+            editor.scroll_y = top;
+        } else if bottom > editor.scroll_y + available.height() {
+            editor.scroll_y = bottom - available.height();
+        }
+
+        let text_width = available.width() - metrics.gutter_width - 4.0;
+        let left = pos.col as f32 * metrics.char_width;
+        let right = left + metrics.char_width * 2.0;
+        if left < editor.scroll_x {
+            editor.scroll_x = left;
+        } else if right > editor.scroll_x + text_width {
+            editor.scroll_x = right - text_width;
+        }
     }
 
-    // Handle keyboard input
+    let content_height = if opts.scroll_past_end {
+        editor.line_count().saturating_sub(1) as f32 * metrics.line_height + available.height()
+    } else {
+        editor.line_count() as f32 * metrics.line_height
+    };
+    let max_scroll = (content_height - available.height()).max(0.0);
+    editor.scroll_y = editor.scroll_y.clamp(0.0, max_scroll);
+    if editor.scroll_x != 0.0 {
+        // Only measured while scrolled sideways, and once per change: it
+        // walks every line
+        let id = response.id.with("longest_line");
+        let longest = match ui.data(|d| d.get_temp::<(u64, usize)>(id)) {
+            Some((version, longest)) if version == editor.version => longest,
+            _ => {
+                let longest = editor.rope.lines().map(|l| l.len_chars()).max().unwrap_or(0);
+                ui.data_mut(|d| d.insert_temp(id, (editor.version, longest)));
+                longest
+            }
+        };
+        let content_width = (longest + 2) as f32 * metrics.char_width;
+        let text_width = available.width() - metrics.gutter_width - 4.0;
+        editor.scroll_x = editor.scroll_x.clamp(0.0, (content_width - text_width).max(0.0));
+    }
+
+    // Tell the platform where the cursor is, which also turns IME input on
     if has_focus {
-        changed = handle_keyboard(ui, editor, clipboard);
+        let cursor = editor.cursors[0].pos;
+        let x = available.left() + metrics.gutter_width + 4.0 + cursor.col as f32 * metrics.char_width - editor.scroll_x;
+        let y = available.top() + cursor.line as f32 * metrics.line_height - editor.scroll_y;
+        let cursor_rect = Rect::from_min_size(Pos2::new(x, y), Vec2::new(2.0, metrics.line_height));
+        ui.ctx().output_mut(|o| {
+            o.ime = Some(egui::output::IMEOutput {
+                rect: available,
+                cursor_rect,
+            })
+        });
     }
 
-    // Render visible lines
-    render_lines(ui, &available, editor, &metrics, highlighter);
+    // The same text for screen readers
+    let text_rect = Rect::from_min_max(Pos2::new(available.left() + metrics.gutter_width + 4.0, available.top()), available.max);
+    accessibility::describe(ui.ctx(), response.id, editor, text_rect, visible_lines(&available, &metrics, editor), &metrics);
 
-    // Ensure cursor is visible (auto-scroll)
-    if !editor.cursors.is_empty() {
-        let primary = &editor.cursors[0];
-        let cursor_y = primary.pos.line as f32 * metrics.line_height;
+    // Render visible lines
+    let frame = FrameState {
+        sticky: &sticky,
+        focused: has_focus && !opts.idle,
+    };
+    match render_lines(ui, &available, editor, &metrics, highlighter, opts, &frame) {
+        Some(LineAction::Resolve(conflict, resolution)) => {
+            editor.resolve_conflict(&conflict, resolution);
+            changed = true;
+        }
+        Some(LineAction::Recolor { line, literal, color, join }) => {
+            // Dragging in the picker is one undo step
+            if join {
+                editor.join_next_undo();
+            }
+            let (start, end) = (Position::new(line, literal.start), Position::new(line, literal.end));
+            editor.labeled_edit("Change Color", |editor| editor.replace_span(start, end, &literal.rewrite(color)));
+            changed = true;
+        }
+        None => {}
+    }
 
-        if cursor_y < editor.scroll_y {
-            editor.scroll_y = cursor_y;
-        } else if cursor_y + metrics.line_height > editor.scroll_y + available.height() {
-            editor.scroll_y = cursor_y + metrics.line_height - available.height();
+    // Diagnostic messages under the pointer
+    if let Some(hover) = response.hover_pos() {
+        let hits = diagnostics_at(hover, &available, &metrics, editor);
+        if !hits.is_empty() && !response.dragged() {
+            egui::show_tooltip_at_pointer(ui.ctx(), ui.layer_id(), response.id.with("diagnostics"), |ui| {
+                for diag in hits {
+                    ui.horizontal(|ui| {
+                        ui.colored_label(diag.severity.color(), diag.severity.label());
+                        ui.label(&diag.message);
+                        ui.weak(&diag.source);
+                    });
+                }
+            });
         }
     }
 
@@ -153,58 +439,114 @@ fn screen_to_editor_pos(
     (line, col)
 }
 
-fn handle_keyboard(ui: &mut egui::Ui, editor: &mut Editor, clipboard: &mut Option<Clipboard>) -> bool {
+fn handle_keyboard(
+    ui: &mut egui::Ui,
+    editor: &mut Editor,
+    clipboard: &mut Option<Clipboard>,
+    highlighter: &SyntaxHighlighter,
+    opts: &ViewOptions,
+) -> bool {
     let mut changed = false;
     let events: Vec<egui::Event> = ui.input(|i| i.events.clone());
     let time = ui.input(|i| i.time);
 
     for event in &events {
+        // While an input method composes, its keystrokes belong to it
+        let composing = editor.ime_preedit.is_some();
         match event {
+            egui::Event::Ime(ime) => match ime {
+                egui::ImeEvent::Preedit(text) => {
+                    editor.ime_preedit = (!text.is_empty()).then(|| text.clone());
+                }
+                egui::ImeEvent::Commit(text) => {
+                    editor.ime_preedit = None;
+                    if !text.is_empty() {
+                        editor.type_text(text);
+                        changed = true;
+                    }
+                }
+                egui::ImeEvent::Enabled => {}
+                egui::ImeEvent::Disabled => editor.ime_preedit = None,
+            },
+            egui::Event::Text(_) | egui::Event::Key { .. } if composing => {}
             egui::Event::Text(text) => {
                 let ctrl = ui.input(|i| i.modifiers.command);
                 if !ctrl {
-                    editor.insert_text(text);
+                    editor.type_text(text);
                     changed = true;
                 }
             }
+            egui::Event::Copy => {
+                changed |= run_command(editor, clipboard, &CommandId::Copy);
+            }
+            egui::Event::Cut => {
+                changed |= run_command(editor, clipboard, &CommandId::Cut);
+            }
+            egui::Event::Paste(text) => {
+                editor.paste(&ClipboardContent::recall(text));
+                changed = true;
+            }
             egui::Event::Key {
                 key,
                 pressed: true,
+                repeat,
                 modifiers,
                 ..
             } => {
+                // Held Backspace/Delete (by character or word) continue the
+                // undo step of the first press
+                if *repeat && matches!(key, egui::Key::Backspace | egui::Key::Delete) {
+                    editor.join_next_undo();
+                }
+                if let Some(id) = commands::lookup(Scope::Editor, *key, *modifiers) {
+                    changed |= run_command(editor, clipboard, &id);
+                    continue;
+                }
+
                 let shift = modifiers.shift;
                 let ctrl = modifiers.command;
+                let alt = modifiers.alt;
+                let prose = syntax::is_prose(editor.file_path.as_deref());
 
                 match key {
-                    egui::Key::Backspace if ctrl => {
-                        editor.delete_word_backward();
-                        changed = true;
-                    }
                     egui::Key::Backspace => {
                         editor.backspace();
                         changed = true;
                     }
-                    egui::Key::Delete if ctrl => {
-                        editor.delete_word_forward();
-                        changed = true;
-                    }
                     egui::Key::Delete => {
                         editor.delete_forward();
                         changed = true;
                     }
+                    egui::Key::Enter if opts.continue_comments => {
+                        let path = editor.file_path.clone();
+                        editor.insert_newline_continuing(|rope, pos| {
+                            highlighter.in_comment(rope, path.as_deref(), pos.line, pos.col)
+                        });
+                        changed = true;
+                    }
                     egui::Key::Enter => {
                         editor.insert_newline();
                         changed = true;
                     }
-                    egui::Key::Tab => {
+                    egui::Key::Tab if !ctrl && !shift && opts.emmet && editor.expand_emmet() => {
+                        changed = true;
+                    }
+                    // Ctrl+Tab belongs to tab switching
+                    egui::Key::Tab if !ctrl => {
                         editor.insert_tab();
                         changed = true;
                     }
                     egui::Key::ArrowLeft if ctrl => editor.move_word_left(shift),
                     egui::Key::ArrowRight if ctrl => editor.move_word_right(shift),
+                    // By sentence in prose, by camelCase or snake_case part in code
+                    egui::Key::ArrowLeft if alt && prose => editor.move_sentence_backward(shift),
+                    egui::Key::ArrowRight if alt && prose => editor.move_sentence_forward(shift),
+                    egui::Key::ArrowLeft if alt => editor.move_subword_left(shift),
+                    egui::Key::ArrowRight if alt => editor.move_subword_right(shift),
                     egui::Key::ArrowLeft => editor.move_left(shift),
                     egui::Key::ArrowRight => editor.move_right(shift),
+                    egui::Key::ArrowUp if ctrl => editor.move_paragraph_up(shift),
+                    egui::Key::ArrowDown if ctrl => editor.move_paragraph_down(shift),
                     egui::Key::ArrowUp => editor.move_up(shift),
                     egui::Key::ArrowDown => editor.move_down(shift),
                     egui::Key::Home if ctrl => editor.move_to_start(shift),
@@ -212,49 +554,13 @@ fn handle_keyboard(ui: &mut egui::Ui, editor: &mut Editor, clipboard: &mut Optio
                     egui::Key::Home => editor.move_home(shift),
                     egui::Key::End => editor.move_end(shift),
                     egui::Key::PageUp => {
-                        let visible = (ui.available_height() / LINE_HEIGHT) as usize;
+                        let visible = (ui.available_height() / editor.line_height) as usize;
                         editor.move_page_up(shift, visible.max(1));
                     }
                     egui::Key::PageDown => {
-                        let visible = (ui.available_height() / LINE_HEIGHT) as usize;
+                        let visible = (ui.available_height() / editor.line_height) as usize;
                         editor.move_page_down(shift, visible.max(1));
                     }
-                    egui::Key::A if ctrl => editor.select_all(),
-                    egui::Key::D if ctrl => editor.select_next_occurrence(),
-                    egui::Key::C if ctrl => {
-                        if let Some(cb) = clipboard.as_mut() {
-                            let text = editor.copy_text();
-                            let _ = cb.set_text(&text);
-                        }
-                    }
-                    egui::Key::X if ctrl => {
-                        if let Some(cb) = clipboard.as_mut() {
-                            let text = editor.cut_text();
-                            let _ = cb.set_text(&text);
-                            changed = true;
-                        }
-                    }
-                    egui::Key::V if ctrl => {
-                        if let Some(cb) = clipboard.as_mut() {
-                            if let Ok(text) = cb.get_text() {
-                                editor.insert_text(&text);
-                                changed = true;
-                            }
-                        }
-                    }
-                    egui::Key::Z if ctrl && shift => {
-                        editor.redo();
-                        changed = true;
-                    }
-                    egui::Key::Z if ctrl => {
-                        editor.undo();
-                        changed = true;
-                    }
-                    egui::Key::Y if ctrl => {
-                        editor.redo();
-                        changed = true;
-                    }
-                    egui::Key::Escape => editor.clear_extra_cursors(),
                     _ => {}
                 }
             }
@@ -269,41 +575,331 @@ fn handle_keyboard(ui: &mut egui::Ui, editor: &mut Editor, clipboard: &mut Optio
     changed
 }
 
+/// What is drawn in place of a character that is invisible or would shift
+/// the columns after it: C0 and C1 controls, DEL, BiDi controls, zero-width
+/// characters and the byte order mark. Tabs are left alone.
+fn invisible_glyph(c: char) -> Option<char> {
+    match c {
+        '\t' => None,
+        // Control Pictures, ␀ to ␟
+        '\0'..='\u{1f}' => char::from_u32(0x2400 + c as u32),
+        '\u{7f}' => Some('\u{2421}'),
+        '\u{80}'..='\u{9f}'
+        | '\u{ad}'
+        | '\u{61c}'
+        | '\u{200b}'..='\u{200f}'
+        | '\u{202a}'..='\u{202e}'
+        | '\u{2060}'..='\u{2064}'
+        | '\u{2066}'..='\u{2069}'
+        | '\u{feff}' => Some('\u{a4}'),
+        _ => None,
+    }
+}
+
+/// Run an editor-scoped command against `editor`. Returns true if the
+/// content changed; commands outside the editor scope are ignored. The
+/// undo step of a change is named after the command.
+pub fn run_command(editor: &mut Editor, clipboard: &mut Option<Clipboard>, id: &CommandId) -> bool {
+    let changed = run_editor_command(editor, clipboard, id);
+    if changed && !matches!(id, CommandId::Undo | CommandId::Redo) {
+        if let Some(command) = commands::all().iter().find(|c| c.id == *id) {
+            editor.label_last_edit(&command.name);
+        }
+    }
+    changed
+}
+
+fn run_editor_command(editor: &mut Editor, clipboard: &mut Option<Clipboard>, id: &CommandId) -> bool {
+    match id {
+        CommandId::Copy => {
+            if let Some(cb) = clipboard.as_mut() {
+                let content = editor.copy_content();
+                content.remember();
+                let _ = cb.set_text(content.text());
+            }
+            return false;
+        }
+        CommandId::Cut => {
+            let Some(cb) = clipboard.as_mut() else {
+                return false;
+            };
+            let content = editor.cut_content();
+            content.remember();
+            let _ = cb.set_text(content.text());
+        }
+        CommandId::Paste => {
+            let Some(text) = clipboard.as_mut().and_then(|cb| cb.get_text().ok()) else {
+                return false;
+            };
+            editor.paste(&ClipboardContent::recall(&text));
+        }
+        CommandId::Undo => editor.undo(),
+        CommandId::Redo => editor.redo(),
+        CommandId::DeleteWordBackward => editor.delete_word_backward(),
+        CommandId::DeleteWordForward => editor.delete_word_forward(),
+        CommandId::DeleteSubwordBackward => editor.delete_subword_backward(),
+        CommandId::DeleteSubwordForward => editor.delete_subword_forward(),
+        CommandId::DuplicateLine | CommandId::CopyLineDown => editor.duplicate_lines(),
+        CommandId::CopyLineUp => editor.copy_lines_up(),
+        CommandId::DeleteLine => editor.delete_lines(),
+        CommandId::SelectLine => {
+            editor.select_lines();
+            return false;
+        }
+        CommandId::CenterCursor => {
+            editor.center_cursor();
+            return false;
+        }
+        CommandId::ScrollLineUp | CommandId::ScrollLineDown => {
+            editor.scroll_lines(if *id == CommandId::ScrollLineUp { -1 } else { 1 });
+            return false;
+        }
+        CommandId::CursorToViewTop | CommandId::CursorToViewBottom => {
+            editor.move_to_view_edge(*id == CommandId::CursorToViewTop, false);
+            return false;
+        }
+        CommandId::ToggleComment => {
+            let prefix = syntax::line_comment_for(editor.file_path.as_deref());
+            editor.toggle_line_comment(prefix);
+        }
+        CommandId::MoveLineUp => editor.move_lines_up(),
+        CommandId::MoveLineDown => editor.move_lines_down(),
+        CommandId::Indent => editor.indent_lines(),
+        CommandId::Outdent => editor.outdent_lines(),
+        CommandId::JoinLines => editor.join_lines(),
+        CommandId::InsertUuid => editor.insert_each(|_| uuid::Uuid::new_v4().to_string()),
+        CommandId::InsertNumbers => editor.insert_each(|n| (n + 1).to_string()),
+        CommandId::UpperCase => editor.transform_selections(|s| s.to_uppercase()),
+        CommandId::LowerCase => editor.transform_selections(|s| s.to_lowercase()),
+        CommandId::TitleCase => editor.transform_selections(title_case),
+        // Text that doesn't decode is left as it was
+        CommandId::Base64Encode => editor.transform_selections(codec::base64_encode),
+        CommandId::Base64Decode => editor.transform_selections(|s| codec::base64_decode(s).unwrap_or_else(|| s.to_string())),
+        CommandId::UrlEncode => editor.transform_selections(codec::url_encode),
+        CommandId::UrlDecode => editor.transform_selections(|s| codec::url_decode(s).unwrap_or_else(|| s.to_string())),
+        CommandId::HtmlEscape => editor.transform_selections(codec::html_escape),
+        CommandId::HtmlUnescape => editor.transform_selections(codec::html_unescape),
+        CommandId::JsonEscape => editor.transform_selections(codec::json_escape),
+        CommandId::EvaluateSelection => editor.transform_selections(|s| calc::substitute(s).unwrap_or_else(|| s.to_string())),
+        CommandId::SortLines => editor.transform_lines(|mut lines| {
+            lines.sort();
+            lines
+        }),
+        CommandId::ReverseLines => editor.transform_lines(|mut lines| {
+            lines.reverse();
+            lines
+        }),
+        CommandId::UniqueLines => editor.transform_lines(|lines| {
+            let mut seen = std::collections::HashSet::new();
+            lines.into_iter().filter(|l| seen.insert(l.clone())).collect()
+        }),
+        CommandId::TrimTrailingWhitespace => editor.trim_trailing_whitespace(),
+        CommandId::ToggleOverwrite => {
+            editor.overwrite = !editor.overwrite;
+            return false;
+        }
+        CommandId::SelectAll => {
+            editor.select_all();
+            return false;
+        }
+        CommandId::SwapSelectionEnds => {
+            editor.swap_selection_ends();
+            return false;
+        }
+        CommandId::Reselect => {
+            editor.reselect();
+            return false;
+        }
+        CommandId::SelectParagraph => {
+            editor.select_paragraph();
+            return false;
+        }
+        CommandId::SelectNextOccurrence => {
+            editor.select_next_occurrence();
+            return false;
+        }
+        CommandId::SelectAllOccurrences => {
+            editor.select_all_occurrences();
+            return false;
+        }
+        CommandId::SplitSelectionIntoLines => {
+            editor.split_selection_into_lines();
+            return false;
+        }
+        CommandId::AddCursorAbove => {
+            editor.add_cursor_vertical(false);
+            return false;
+        }
+        CommandId::AddCursorBelow => {
+            editor.add_cursor_vertical(true);
+            return false;
+        }
+        CommandId::ClearExtraCursors => {
+            editor.clear_extra_cursors();
+            return false;
+        }
+        CommandId::JumpToChar => {
+            editor.jump_query = Some(String::new());
+            return false;
+        }
+        CommandId::AcceptCurrentChange => return editor.resolve_conflict_at_cursor(Resolution::Current),
+        CommandId::AcceptIncomingChange => return editor.resolve_conflict_at_cursor(Resolution::Incoming),
+        CommandId::AcceptBothChanges => return editor.resolve_conflict_at_cursor(Resolution::Both),
+        CommandId::NextConflict => {
+            editor.goto_conflict(true);
+            return false;
+        }
+        CommandId::PrevConflict => {
+            editor.goto_conflict(false);
+            return false;
+        }
+        CommandId::ToggleBookmark => {
+            editor.toggle_bookmarks();
+            return false;
+        }
+        CommandId::NextBookmark => {
+            editor.goto_bookmark(true);
+            return false;
+        }
+        CommandId::PrevBookmark => {
+            editor.goto_bookmark(false);
+            return false;
+        }
+        CommandId::ClearBookmarks => {
+            editor.clear_bookmarks();
+            return false;
+        }
+        CommandId::ToggleBreakpoint => {
+            editor.toggle_breakpoints();
+            return false;
+        }
+        _ => return false,
+    }
+    true
+}
+
+/// Uppercase the first letter of every word and lowercase the rest.
+fn title_case(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut at_word_start = true;
+    for c in text.chars() {
+        if c.is_alphanumeric() {
+            if at_word_start {
+                out.extend(c.to_uppercase());
+            } else {
+                out.extend(c.to_lowercase());
+            }
+            at_word_start = false;
+        } else {
+            out.push(c);
+            at_word_start = true;
+        }
+    }
+    out
+}
+
+/// Draw the visible lines. Returns a conflict whose accept button was clicked.
 fn render_lines(
     ui: &egui::Ui,
     rect: &Rect,
     editor: &Editor,
     metrics: &EditorMetrics,
     highlighter: &SyntaxHighlighter,
-) {
+    opts: &ViewOptions,
+    frame: &FrameState,
+) -> Option<LineAction> {
+    let theme = opts.theme;
+    let FrameState { sticky, focused } = *frame;
     let painter = ui.painter_at(*rect);
     let time = ui.input(|i| i.time);
 
+    // A steady cursor without focus, so nothing needs repainting; otherwise
+    // wake up exactly when the blink phase flips
     let since_edit = time - editor.last_edit_time;
-    let cursor_visible = since_edit < 0.5 || ((since_edit * 2.0) as u64 % 2 == 0);
+    let cursor_visible = !focused || since_edit < BLINK_INTERVAL || ((since_edit / BLINK_INTERVAL) as u64).is_multiple_of(2);
+    if focused {
+        ui.ctx().request_repaint_after_secs((BLINK_INTERVAL - since_edit % BLINK_INTERVAL) as f32);
+    }
+    // Dimmed while the palette, search bar or another window has the keys
+    let (cursor_color, overwrite_color) = if focused {
+        (theme.cursor, OVERWRITE_CURSOR_BG)
+    } else {
+        (theme.cursor.gamma_multiply(0.4), OVERWRITE_CURSOR_BG.gamma_multiply(0.4))
+    };
 
-    let first_line = (editor.scroll_y / metrics.line_height).floor() as usize;
-    let visible_count = (rect.height() / metrics.line_height).ceil() as usize + 1;
-    let last_line = (first_line + visible_count).min(editor.line_count());
+    let Range { start: first_line, end: last_line } = visible_lines(rect, metrics, editor);
 
     // Syntax highlighting for visible lines
-    let full_text = editor.rope.to_string();
     let highlighted = highlighter.highlight_lines(
-        &full_text,
+        &editor.rope,
         editor.file_path.as_deref(),
         first_line,
         last_line,
     );
 
-    // Collect active cursor lines
-    let active_lines: Vec<usize> = editor.cursors.iter().map(|c| c.pos.line).collect();
+    let occurrences = word_occurrences(ui, editor, first_line..last_line);
+    let search_matches = match opts.search {
+        Some((query, options)) => matches_in(editor, query, options, first_line..last_line),
+        None => Vec::new(),
+    };
+
+    let jump_targets = editor
+        .jump_query
+        .as_deref()
+        .map(|q| jump::targets(editor, q, first_line..last_line))
+        .unwrap_or_default();
+
+    // Cursors, selections and highlights sorted into the visible lines
+    // once, so drawing a line only looks at what is on it
+    let visible = first_line..last_line;
+    let mut carets = LineBuckets::new(visible.clone());
+    let mut selections = LineBuckets::new(visible.clone());
+    for cursor in &editor.cursors {
+        carets.add(cursor.pos.line, cursor.pos.line, cursor.pos.col);
+        if let Some((start, end)) = cursor.selection_ordered() {
+            selections.add(start.line, end.line, (start, end));
+        }
+    }
+    let mut highlights = LineBuckets::new(visible.clone());
+    for &(start, end) in &occurrences {
+        highlights.add(start.line, end.line, (start, end));
+    }
+    let mut matches = LineBuckets::new(visible.clone());
+    for &(start, end) in &search_matches {
+        matches.add(start.line, end.line, (start, end));
+    }
+    let mut remote_carets = LineBuckets::new(visible.clone());
+    let mut remote_selections = LineBuckets::new(visible.clone());
+    for presence in opts.collaborators {
+        for (i, cursor) in presence.cursors.iter().enumerate() {
+            // Only the first cursor carries the name
+            let name = (i == 0).then_some(presence.name.as_str());
+            remote_carets.add(cursor.pos.0, cursor.pos.0, (cursor.pos.1, presence.color(), name));
+            if let Some((start, end)) = cursor.selection() {
+                remote_selections.add(start.line, end.line, (start, end, presence.color()));
+            }
+        }
+    }
+    let mut diagnostics = LineBuckets::new(visible.clone());
+    for diag in &editor.diagnostics {
+        diagnostics.add(diag.start.line, diag.end.line, diag);
+    }
+    let mut gutter_markers = LineBuckets::new(visible);
+    for marker in gutter::collect(editor, first_line..last_line) {
+        gutter_markers.add(marker.line, marker.line, marker);
+    }
+    let conflicts = conflict_blocks(ui, editor);
+    let mut action = None;
+    let stylesheet = color_literals::is_stylesheet(editor.file_path.as_deref());
+    let picker_id = ui.id().with("color_picker");
+    let mut picker = ui.data(|d| d.get_temp::<ColorPicker>(picker_id));
 
     // Draw gutter background
     let gutter_rect = Rect::from_min_size(
         rect.left_top(),
         Vec2::new(metrics.gutter_width, rect.height()),
     );
-    painter.rect_filled(gutter_rect, 0.0, GUTTER_BG);
+    painter.rect_filled(gutter_rect, 0.0, theme.gutter);
 
     // Draw separator line
     painter.line_segment(
@@ -314,109 +910,589 @@ fn render_lines(
         Stroke::new(1.0, Color32::from_rgb(50, 50, 50)),
     );
 
+    // Rulers behind the text; redrawn over the active line's background below
+    let text_left = rect.left() + metrics.gutter_width + 4.0 - editor.scroll_x;
+    let ruler_xs: Vec<f32> = opts
+        .rulers
+        .iter()
+        .map(|&col| text_left + col as f32 * metrics.char_width)
+        .filter(|&x| x > rect.left() + metrics.gutter_width)
+        .collect();
+    for &x in &ruler_xs {
+        painter.line_segment(
+            [Pos2::new(x, rect.top()), Pos2::new(x, rect.bottom())],
+            Stroke::new(1.0, theme.ruler),
+        );
+    }
+
+    // Columns in view, with one to spare on each side
+    let first_col = (editor.scroll_x / metrics.char_width) as usize;
+    let text_width = rect.width() - metrics.gutter_width - 4.0;
+    let columns = first_col.saturating_sub(1)..first_col + (text_width / metrics.char_width) as usize + 2;
+
     for line_idx in first_line..last_line {
         let y = rect.top() + (line_idx as f32) * metrics.line_height - editor.scroll_y;
 
+        let is_active = !carets.get(line_idx).is_empty();
+
         // Active line highlight
-        if active_lines.contains(&line_idx) {
+        if is_active && (focused || !opts.active_line_focus_only) {
             let line_rect = Rect::from_min_size(
                 Pos2::new(rect.left() + metrics.gutter_width, y),
                 Vec2::new(rect.width() - metrics.gutter_width, metrics.line_height),
             );
-            painter.rect_filled(line_rect, 0.0, ACTIVE_LINE_BG);
+            painter.rect_filled(line_rect, 0.0, theme.active_line);
+            for &x in &ruler_xs {
+                painter.line_segment(
+                    [Pos2::new(x, y), Pos2::new(x, y + metrics.line_height)],
+                    Stroke::new(1.0, theme.ruler),
+                );
+            }
         }
 
         // Line number
-        let ln_color = if active_lines.contains(&line_idx) {
-            LINE_NUM_ACTIVE_COLOR
-        } else {
-            LINE_NUM_COLOR
-        };
-        let ln_text = format!("{}", line_idx + 1);
-        painter.text(
-            Pos2::new(rect.left() + metrics.gutter_width - GUTTER_PADDING / 2.0, y + metrics.line_height / 2.0),
-            egui::Align2::RIGHT_CENTER,
-            &ln_text,
-            metrics.font_id.clone(),
-            ln_color,
-        );
+        if opts.line_numbers {
+            let ln_color = if is_active {
+                theme.line_number_active
+            } else {
+                theme.line_number
+            };
+            let ln_text = format!("{}", line_idx + 1);
+            painter.text(
+                Pos2::new(rect.left() + metrics.gutter_width - GUTTER_PADDING / 2.0, y + metrics.line_height / 2.0),
+                egui::Align2::RIGHT_CENTER,
+                &ln_text,
+                metrics.font_id.clone(),
+                ln_color,
+            );
+        }
+
+        // Merge conflict sides, with accept buttons after the start marker
+        let conflict = conflicts.get(conflicts.partition_point(|c| c.end < line_idx)).filter(|c| c.contains(line_idx));
+        if let Some(conflict) = conflict {
+            let line_rect = Rect::from_min_size(
+                Pos2::new(rect.left() + metrics.gutter_width, y),
+                Vec2::new(rect.width() - metrics.gutter_width, metrics.line_height),
+            );
+            let markers = [Some(conflict.start), conflict.base, Some(conflict.separator), Some(conflict.end)];
+            let fill = if markers.contains(&Some(line_idx)) {
+                CONFLICT_MARKER_BG
+            } else if conflict.current().contains(&line_idx) {
+                CONFLICT_CURRENT_BG
+            } else if conflict.incoming().contains(&line_idx) {
+                CONFLICT_INCOMING_BG
+            } else {
+                CONFLICT_BASE_BG
+            };
+            painter.rect_filled(line_rect, 0.0, fill);
+            if line_idx == conflict.start {
+                let text_end = rect.left() + metrics.gutter_width + 4.0 - editor.scroll_x
+                    + (editor.line_text(line_idx).chars().count() + 2) as f32 * metrics.char_width;
+                if let Some((conflict, resolution)) = conflict_actions(ui, &painter, conflict, text_end, y, metrics) {
+                    action = Some(LineAction::Resolve(conflict, resolution));
+                }
+            }
+        }
+
+        // The line the debugger is stopped at
+        if editor.execution_line == Some(line_idx) {
+            let line_rect = Rect::from_min_size(
+                Pos2::new(rect.left() + metrics.gutter_width, y),
+                Vec2::new(rect.width() - metrics.gutter_width, metrics.line_height),
+            );
+            painter.rect_filled(line_rect, 0.0, EXECUTION_LINE_BG);
+        }
+
+        for (start, end) in highlights.get(line_idx) {
+            if let Some(r) = selection_rect(rect, line_idx, start, end, metrics, editor) {
+                painter.rect_filled(r, 2.0, theme.word_highlight);
+            }
+        }
+        for (start, end) in matches.get(line_idx) {
+            if let Some(r) = selection_rect(rect, line_idx, start, end, metrics, editor) {
+                painter.rect_filled(r, 2.0, theme.search_match);
+            }
+        }
 
         // Selection highlighting
-        for cursor in &editor.cursors {
-            if let Some((sel_start, sel_end)) = cursor.selection_ordered() {
-                draw_selection(
-                    &painter,
-                    rect,
-                    line_idx,
-                    &sel_start,
-                    &sel_end,
-                    metrics,
-                    editor,
-                );
+        for (sel_start, sel_end) in selections.get(line_idx) {
+            if let Some(sel_rect) = selection_rect(rect, line_idx, sel_start, sel_end, metrics, editor) {
+                painter.rect_filled(sel_rect, 0.0, theme.selection);
+            }
+        }
+        for (sel_start, sel_end, color) in remote_selections.get(line_idx) {
+            if let Some(sel_rect) = selection_rect(rect, line_idx, sel_start, sel_end, metrics, editor) {
+                painter.rect_filled(sel_rect, 0.0, color.gamma_multiply(0.3));
             }
         }
 
-        // Line text (syntax highlighted)
+        // Line text (syntax highlighted), only the columns in view
         let hl_idx = line_idx - first_line;
         let text_x_base = rect.left() + metrics.gutter_width + 4.0 - editor.scroll_x;
-        if let Some(tokens) = highlighted.get(hl_idx) {
-            let mut offset_x = text_x_base;
-            for token in tokens {
-                if !token.text.is_empty() {
-                    painter.text(
-                        Pos2::new(offset_x, y + metrics.line_height / 2.0),
-                        egui::Align2::LEFT_CENTER,
-                        &token.text,
-                        metrics.font_id.clone(),
-                        token.color,
-                    );
-                    offset_x += token.text.chars().count() as f32 * metrics.char_width;
-                }
+        let plain;
+        let tokens = match highlighted.get(hl_idx) {
+            Some(tokens) => tokens.as_slice(),
+            None => {
+                plain = [StyledToken { text: editor.line_text(line_idx), color: theme.text }];
+                &plain
             }
-        } else {
-            let text = editor.line_text(line_idx);
-            if !text.is_empty() {
+        };
+        let mut col = 0;
+        let mut placeholders = Vec::new();
+        for token in tokens {
+            if col >= columns.end {
+                break;
+            }
+            // Long tokens are cut down to what's visible before layout
+            let len = token.text.chars().count();
+            let (from, to) = (columns.start.max(col), columns.end.min(col + len));
+            if from < to {
+                // Invisible characters keep their column as a space and get
+                // a placeholder drawn over it
+                let text: String = token
+                    .text
+                    .chars()
+                    .skip(from - col)
+                    .take(to - from)
+                    .enumerate()
+                    .map(|(i, c)| match invisible_glyph(c) {
+                        Some(glyph) => {
+                            placeholders.push((from + i, glyph));
+                            ' '
+                        }
+                        None => c,
+                    })
+                    .collect();
                 painter.text(
-                    Pos2::new(text_x_base, y + metrics.line_height / 2.0),
+                    Pos2::new(text_x_base + from as f32 * metrics.char_width, y + metrics.line_height / 2.0),
                     egui::Align2::LEFT_CENTER,
-                    &text,
+                    text,
                     metrics.font_id.clone(),
-                    TEXT_COLOR,
+                    token.color,
                 );
             }
+            col += len;
+        }
+        for (col, glyph) in placeholders {
+            let cell = Rect::from_min_size(
+                Pos2::new(text_x_base + col as f32 * metrics.char_width, y + 1.0),
+                Vec2::new(metrics.char_width, metrics.line_height - 2.0),
+            );
+            let glyph = if ui.fonts(|f| f.has_glyph(&metrics.font_id, glyph)) { glyph } else { '?' };
+            painter.rect_stroke(cell, 2.0, Stroke::new(1.0, theme.control_char));
+            painter.text(cell.center(), egui::Align2::CENTER_CENTER, glyph, metrics.font_id.clone(), theme.control_char);
+        }
+        if opts.show_line_ends && line_idx + 1 < editor.line_count() && columns.contains(&col) {
+            painter.text(
+                Pos2::new(text_x_base + col as f32 * metrics.char_width, y + metrics.line_height / 2.0),
+                egui::Align2::LEFT_CENTER,
+                '\u{b6}',
+                metrics.font_id.clone(),
+                theme.line_number,
+            );
+        }
+
+        // A swatch for each color on the line, after its end; clicking one
+        // opens a picker that rewrites the color
+        if opts.color_swatches && editor.rope.line(line_idx).len_chars() <= syntax::MAX_HIGHLIGHT_CHARS {
+            let text = editor.line_text(line_idx);
+            let size = (metrics.line_height * 0.6).round();
+            let end = text.chars().count() + if opts.show_line_ends { 2 } else { 1 };
+            let mut x = text_x_base + end as f32 * metrics.char_width;
+            for literal in color_literals::find(&text, stylesheet) {
+                let swatch = Rect::from_min_size(Pos2::new(x, y + (metrics.line_height - size) / 2.0), Vec2::splat(size));
+                x += size + 4.0;
+                if swatch.left() < rect.left() + metrics.gutter_width {
+                    continue;
+                }
+                let response = ui
+                    .interact(swatch, ui.id().with(("color_swatch", line_idx, literal.start)), Sense::click())
+                    .on_hover_cursor(egui::CursorIcon::PointingHand);
+                if response.hovered() {
+                    let (start, end) = (Position::new(line_idx, literal.start), Position::new(line_idx, literal.end));
+                    if let Some(r) = selection_rect(rect, line_idx, &start, &end, metrics, editor) {
+                        painter.rect_filled(r, 2.0, theme.word_highlight);
+                    }
+                }
+                painter.rect_filled(swatch, 2.0, literal.color);
+                painter.rect_stroke(swatch, 2.0, Stroke::new(1.0, theme.line_number));
+                if response.clicked() {
+                    picker = Some(ColorPicker {
+                        line: line_idx,
+                        col: literal.start,
+                        at: swatch.left_bottom(),
+                        opened: ui.ctx().cumulative_pass_nr(),
+                        edited: false,
+                    });
+                } else if let Some(open) = picker.as_mut().filter(|p| (p.line, p.col) == (line_idx, literal.start)) {
+                    open.at = swatch.left_bottom();
+                }
+            }
+        }
+
+        // Diagnostics: squiggles under each range
+        for diag in diagnostics.get(line_idx) {
+            if let Some(r) = selection_rect(rect, line_idx, &diag.start, &diag.end, metrics, editor) {
+                draw_squiggle(&painter, r.left(), r.right(), r.bottom() - 2.0, diag.severity.color());
+            }
+        }
+        gutter::draw(&painter, gutter_markers.get(line_idx), rect.left(), y, metrics.line_height);
+
+        // Jump labels over the first character of each match
+        for target in jump_targets.iter().filter(|t| t.pos.line == line_idx) {
+            let label_rect = Rect::from_min_size(
+                Pos2::new(text_x_base + target.pos.col as f32 * metrics.char_width, y + 1.0),
+                Vec2::new(metrics.char_width, metrics.line_height - 2.0),
+            );
+            painter.rect_filled(label_rect, 2.0, JUMP_LABEL_BG);
+            painter.text(
+                label_rect.center(),
+                egui::Align2::CENTER_CENTER,
+                target.label,
+                metrics.font_id.clone(),
+                Color32::BLACK,
+            );
         }
 
         // Cursors on this line
         if cursor_visible {
-            for cursor in &editor.cursors {
-                if cursor.pos.line == line_idx {
-                    let cx = rect.left()
-                        + metrics.gutter_width
-                        + 4.0
-                        + cursor.pos.col as f32 * metrics.char_width
-                        - editor.scroll_x;
+            for &col in carets.get(line_idx) {
+                let cx = rect.left()
+                    + metrics.gutter_width
+                    + 4.0
+                    + col as f32 * metrics.char_width
+                    - editor.scroll_x;
+                if editor.overwrite {
+                    // Block cursor over the character that will be replaced
+                    let cursor_rect = Rect::from_min_size(
+                        Pos2::new(cx, y + 1.0),
+                        Vec2::new(metrics.char_width, metrics.line_height - 2.0),
+                    );
+                    painter.rect_filled(cursor_rect, 0.0, overwrite_color);
+                } else {
                     let cursor_rect = Rect::from_min_size(
                         Pos2::new(cx, y + 1.0),
                         Vec2::new(2.0, metrics.line_height - 2.0),
                     );
-                    painter.rect_filled(cursor_rect, 0.0, CURSOR_COLOR);
+                    painter.rect_filled(cursor_rect, 0.0, cursor_color);
                 }
             }
         }
+
+        // Collaborators' cursors, steady, with a name flag above
+        for &(col, color, name) in remote_carets.get(line_idx) {
+            let cx = text_x_base + col as f32 * metrics.char_width;
+            painter.rect_filled(
+                Rect::from_min_size(Pos2::new(cx, y + 1.0), Vec2::new(2.0, metrics.line_height - 2.0)),
+                0.0,
+                color,
+            );
+            if let Some(name) = name {
+                let font = FontId::proportional(metrics.font_id.size * 0.75);
+                let galley = painter.layout_no_wrap(name.to_string(), font, Color32::BLACK);
+                let flag = Rect::from_min_size(Pos2::new(cx, y - galley.size().y), galley.size() + Vec2::new(6.0, 0.0));
+                painter.rect_filled(flag, 2.0, color);
+                painter.galley(flag.min + Vec2::new(3.0, 0.0), galley, Color32::BLACK);
+            }
+        }
     }
+
+    // Input method composition at the primary cursor, underlined, over the
+    // text that follows it
+    if let Some(preedit) = &editor.ime_preedit {
+        let cursor = editor.cursors[0].pos;
+        let x = rect.left() + metrics.gutter_width + 4.0 + cursor.col as f32 * metrics.char_width - editor.scroll_x;
+        let y = rect.top() + cursor.line as f32 * metrics.line_height - editor.scroll_y;
+        let galley = painter.layout_no_wrap(preedit.clone(), metrics.font_id.clone(), theme.text);
+        let width = galley.size().x;
+        painter.rect_filled(
+            Rect::from_min_size(Pos2::new(x, y), Vec2::new(width, metrics.line_height)),
+            0.0,
+            theme.active_line,
+        );
+        painter.galley(Pos2::new(x, y + (metrics.line_height - galley.size().y) / 2.0), galley, theme.text);
+        painter.line_segment(
+            [Pos2::new(x, y + metrics.line_height - 2.0), Pos2::new(x + width, y + metrics.line_height - 2.0)],
+            Stroke::new(1.0, theme.text),
+        );
+    }
+
+    // Sticky scroll: enclosing definitions over the top lines
+    for (i, &line_idx) in sticky.iter().enumerate() {
+        let y = rect.top() + i as f32 * metrics.line_height;
+        let row = Rect::from_min_size(Pos2::new(rect.left(), y), Vec2::new(rect.width(), metrics.line_height));
+        painter.rect_filled(row, 0.0, theme.background);
+        painter.rect_filled(
+            Rect::from_min_size(row.min, Vec2::new(metrics.gutter_width, metrics.line_height)),
+            0.0,
+            theme.gutter,
+        );
+        if opts.line_numbers {
+            painter.text(
+                Pos2::new(rect.left() + metrics.gutter_width - GUTTER_PADDING / 2.0, y + metrics.line_height / 2.0),
+                egui::Align2::RIGHT_CENTER,
+                format!("{}", line_idx + 1),
+                metrics.font_id.clone(),
+                theme.line_number,
+            );
+        }
+        painter.text(
+            Pos2::new(rect.left() + metrics.gutter_width + 4.0 - editor.scroll_x, y + metrics.line_height / 2.0),
+            egui::Align2::LEFT_CENTER,
+            editor.line_text(line_idx),
+            metrics.font_id.clone(),
+            theme.text,
+        );
+    }
+    if !sticky.is_empty() {
+        let bottom = rect.top() + sticky.len() as f32 * metrics.line_height;
+        painter.line_segment(
+            [Pos2::new(rect.left(), bottom), Pos2::new(rect.right(), bottom)],
+            Stroke::new(1.0, Color32::from_rgb(60, 60, 60)),
+        );
+    }
+
+    if let Some(query) = &editor.jump_query {
+        painter.text(
+            rect.right_top() + Vec2::new(-8.0, 4.0),
+            egui::Align2::RIGHT_TOP,
+            format!("Jump to: {}", query),
+            metrics.font_id.clone(),
+            JUMP_LABEL_BG,
+        );
+    }
+
+    if let Some(open) = picker {
+        match color_picker(ui, editor, stylesheet, open) {
+            Some((still_open, change)) => {
+                if let Some((literal, color)) = change {
+                    action = Some(LineAction::Recolor { line: open.line, literal, color, join: open.edited });
+                }
+                ui.data_mut(|d| d.insert_temp(picker_id, still_open));
+            }
+            None => ui.data_mut(|d| d.remove::<ColorPicker>(picker_id)),
+        }
+    }
+    action
+}
+
+/// Show the open color picker under its swatch. Returns the picker while it
+/// stays open, with the literal and its new color if it was changed.
+fn color_picker(ui: &egui::Ui, editor: &Editor, stylesheet: bool, mut open: ColorPicker) -> Option<(ColorPicker, Option<(ColorLiteral, Color32)>)> {
+    // Gone if an edit took the literal away
+    let literal = (open.line < editor.line_count())
+        .then(|| color_literals::find(&editor.line_text(open.line), stylesheet))?
+        .into_iter()
+        .find(|l| l.start == open.col)?;
+    let mut color = literal.color;
+    let area = egui::Area::new(ui.id().with("color_picker_area"))
+        .order(egui::Order::Foreground)
+        .fixed_pos(open.at + Vec2::new(0.0, 2.0))
+        .show(ui.ctx(), |ui| {
+            egui::Frame::popup(ui.style())
+                .show(ui, |ui| egui::color_picker::color_picker_color32(ui, &mut color, egui::color_picker::Alpha::OnlyBlend))
+                .inner
+        });
+    if area.response.clicked_elsewhere() && open.opened != ui.ctx().cumulative_pass_nr() {
+        return None;
+    }
+    let change = (area.inner && color != literal.color).then_some((literal, color));
+    open.edited |= change.is_some();
+    Some((open, change))
 }
 
-fn draw_selection(
+/// Conflict blocks in the buffer, found again only when it changes.
+fn conflict_blocks(ui: &egui::Ui, editor: &Editor) -> Vec<Conflict> {
+    let id = ui.id().with("conflicts");
+    match ui.data(|d| d.get_temp::<(u64, Vec<Conflict>)>(id)) {
+        Some((version, conflicts)) if version == editor.version => conflicts,
+        _ => {
+            let conflicts = editor.conflicts();
+            ui.data_mut(|d| d.insert_temp(id, (editor.version, conflicts.clone())));
+            conflicts
+        }
+    }
+}
+
+/// "Accept Current | Accept Incoming | Accept Both" starting at `x` on the
+/// start marker line. Returns the one that was clicked.
+fn conflict_actions(
+    ui: &egui::Ui,
     painter: &egui::Painter,
+    conflict: &Conflict,
+    mut x: f32,
+    y: f32,
+    metrics: &EditorMetrics,
+) -> Option<(Conflict, Resolution)> {
+    let font = FontId::proportional(metrics.font_id.size * 0.85);
+    let mut clicked = None;
+    for (i, resolution) in Resolution::ALL.into_iter().enumerate() {
+        if i > 0 {
+            let bar = painter.text(
+                Pos2::new(x, y + metrics.line_height / 2.0),
+                egui::Align2::LEFT_CENTER,
+                " | ",
+                font.clone(),
+                Color32::GRAY,
+            );
+            x = bar.right();
+        }
+        let galley = painter.layout_no_wrap(resolution.label().to_string(), font.clone(), CONFLICT_ACTION);
+        let button = Rect::from_min_size(
+            Pos2::new(x, y + (metrics.line_height - galley.size().y) / 2.0),
+            galley.size(),
+        );
+        let response = ui
+            .interact(button, ui.id().with(("conflict_action", conflict.start, i)), Sense::click())
+            .on_hover_cursor(egui::CursorIcon::PointingHand);
+        let color = if response.hovered() { Color32::WHITE } else { CONFLICT_ACTION };
+        painter.galley(button.min, galley, color);
+        if response.hovered() {
+            painter.hline(button.x_range(), button.bottom(), Stroke::new(1.0, color));
+        }
+        if response.clicked() {
+            clicked = Some((*conflict, resolution));
+        }
+        x = button.right();
+    }
+    clicked
+}
+
+/// Whole-word matches on `lines` of the word under the primary cursor, once
+/// the cursor has rested there for `WORD_HIGHLIGHT_DELAY`. The occurrence
+/// under the cursor is left out.
+fn word_occurrences(ui: &egui::Ui, editor: &Editor, lines: Range<usize>) -> Vec<(Position, Position)> {
+    let id = ui.id().with("word_highlight");
+    let now = ui.input(|i| i.time);
+    let pos = editor.cursors[0].pos;
+    let rested_since = ui.data_mut(|d| {
+        let entry = d.get_temp_mut_or_insert_with(id, || (pos, now));
+        if entry.0 != pos {
+            *entry = (pos, now);
+        }
+        entry.1
+    });
+    if now - rested_since < WORD_HIGHLIGHT_DELAY {
+        ui.ctx().request_repaint_after_secs((WORD_HIGHLIGHT_DELAY - (now - rested_since)) as f32);
+        return Vec::new();
+    }
+    let Some((word_start, word_end)) = editor.word_under_cursor() else {
+        return Vec::new();
+    };
+    let word: String = editor.line_text(word_start.line).chars().skip(word_start.col).take(word_end.col - word_start.col).collect();
+    let options = SearchOptions { whole_word: true, ..SearchOptions::default() };
+    let mut found = matches_in(editor, &word, options, lines);
+    found.retain(|(start, _)| *start != word_start);
+    found
+}
+
+/// Matches of `query` that start on `lines`.
+fn matches_in(editor: &Editor, query: &str, options: SearchOptions, lines: Range<usize>) -> Vec<(Position, Position)> {
+    let rope = &editor.rope;
+    let from = rope.line_to_char(lines.start);
+    let to = rope.line_to_char(lines.end);
+    let Ok(matches) = editor.search_iter_from(query, options, from) else {
+        return Vec::new();
+    };
+    let to_pos = |idx: usize| {
+        let line = rope.char_to_line(idx);
+        Position::new(line, idx - rope.line_to_char(line))
+    };
+    matches.until(to).map(|range| (to_pos(range.start), to_pos(range.end))).collect()
+}
+
+/// Lines of the definitions enclosing `first_line`, the top of the viewport,
+/// innermost last. Scopes are looked up below the pinned rows, since those
+/// hide the lines they cover.
+fn sticky_lines(editor: &Editor, first_line: usize) -> Vec<usize> {
+    if first_line == 0 {
+        return Vec::new();
+    }
+    // Scopes of a line only depend on the lines up to it
+    let end = (first_line + MAX_STICKY_LINES + 1).min(editor.rope.len_lines());
+    let text = editor.rope.slice(..editor.rope.line_to_char(end)).to_string();
+    let covered = symbols::enclosing_scopes(&text, first_line).len().min(MAX_STICKY_LINES);
+    let mut scopes = symbols::enclosing_scopes(&text, first_line + covered);
+    let skip = scopes.len().saturating_sub(MAX_STICKY_LINES);
+    scopes.split_off(skip)
+}
+
+/// Lines at least partly inside the editor area.
+fn visible_lines(rect: &Rect, metrics: &EditorMetrics, editor: &Editor) -> Range<usize> {
+    let first_line = (editor.scroll_y / metrics.line_height).floor() as usize;
+    let visible_count = (rect.height() / metrics.line_height).ceil() as usize + 1;
+    first_line..(first_line + visible_count).min(editor.line_count())
+}
+
+/// Items spanning line ranges, grouped by the visible line they touch.
+struct LineBuckets<T> {
+    lines: Range<usize>,
+    buckets: Vec<Vec<T>>,
+}
+
+impl<T: Clone> LineBuckets<T> {
+    fn new(lines: Range<usize>) -> Self {
+        Self {
+            buckets: vec![Vec::new(); lines.len()],
+            lines,
+        }
+    }
+
+    /// Add `item` to every visible line from `first` to `last` inclusive.
+    fn add(&mut self, first: usize, last: usize, item: T) {
+        let from = first.max(self.lines.start);
+        let to = last.saturating_add(1).min(self.lines.end);
+        for line in from..to {
+            self.buckets[line - self.lines.start].push(item.clone());
+        }
+    }
+
+    fn get(&self, line: usize) -> &[T] {
+        line.checked_sub(self.lines.start)
+            .and_then(|i| self.buckets.get(i))
+            .map_or(&[], |b| b.as_slice())
+    }
+}
+
+/// Wavy underline from `x1` to `x2` along `y`.
+fn draw_squiggle(painter: &egui::Painter, x1: f32, x2: f32, y: f32, color: Color32) {
+    const STEP: f32 = 2.0;
+    let mut points = Vec::new();
+    let mut x = x1;
+    let mut up = false;
+    while x < x2 {
+        points.push(Pos2::new(x, if up { y - 1.5 } else { y }));
+        x += STEP;
+        up = !up;
+    }
+    points.push(Pos2::new(x2, if up { y - 1.5 } else { y }));
+    painter.add(egui::Shape::line(points, Stroke::new(1.0, color)));
+}
+
+/// Diagnostics under a screen position: every one on the line when over
+/// the gutter, otherwise those whose range covers the character.
+fn diagnostics_at<'a>(pos: Pos2, rect: &Rect, metrics: &EditorMetrics, editor: &'a Editor) -> Vec<&'a Diagnostic> {
+    let line = ((pos.y - rect.top() + editor.scroll_y) / metrics.line_height).floor() as usize;
+    let on_line = editor.diagnostics.iter().filter(move |d| d.touches_line(line));
+    if pos.x < rect.left() + metrics.gutter_width {
+        return on_line.collect();
+    }
+    let rel_x = pos.x - rect.left() - metrics.gutter_width - 4.0 + editor.scroll_x;
+    let at = Position::new(line, (rel_x / metrics.char_width).floor().max(0.0) as usize);
+    on_line.filter(|d| d.start <= at && at < d.end).collect()
+}
+
+/// Screen rect of the part of a selection that lies on `line_idx`.
+fn selection_rect(
     rect: &Rect,
     line_idx: usize,
     sel_start: &crate::editor::Position,
     sel_end: &crate::editor::Position,
     metrics: &EditorMetrics,
     editor: &Editor,
-) {
+) -> Option<Rect> {
     if line_idx < sel_start.line || line_idx > sel_end.line {
-        return;
+        return None;
     }
 
     let y = rect.top() + line_idx as f32 * metrics.line_height - editor.scroll_y;
@@ -434,15 +1510,14 @@ fn draw_selection(
     };
 
     if start_col >= end_col && line_idx == sel_start.line && line_idx == sel_end.line {
-        return;
+        return None;
     }
 
     let x1 = text_x + start_col as f32 * metrics.char_width - editor.scroll_x;
     let x2 = text_x + end_col as f32 * metrics.char_width - editor.scroll_x;
 
-    let sel_rect = Rect::from_min_size(
+    Some(Rect::from_min_size(
         Pos2::new(x1, y),
         Vec2::new(x2 - x1, metrics.line_height),
-    );
-    painter.rect_filled(sel_rect, 0.0, SELECTION_BG);
+    ))
 }
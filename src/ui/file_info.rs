@@ -0,0 +1,295 @@
+use std::fs::{self, File};
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use eframe::egui;
+
+use crate::editor::Editor;
+use crate::git::Repo;
+use crate::ui::time_ago;
+
+const ERROR_COLOR: egui::Color32 = egui::Color32::from_rgb(240, 110, 100);
+
+/// What the file system and git say about a file, read when the window
+/// opens and after each change made from it.
+struct Metadata {
+    size: u64,
+    modified: Option<SystemTime>,
+    /// `rwxr-xr-x` on Unix; "read-only" or "writable" elsewhere.
+    permissions: String,
+    #[cfg(unix)]
+    mode: u32,
+    #[cfg(not(unix))]
+    readonly: bool,
+    git: Option<String>,
+}
+
+/// The active file's path, size, encoding, line count, permissions,
+/// modification time and git status, with buttons to touch it, change
+/// its permissions and copy its path.
+pub struct FileInfoWindow {
+    pub open: bool,
+    file: PathBuf,
+    metadata: Result<Metadata, String>,
+    /// Octal mode being typed for Change Mode.
+    #[cfg(unix)]
+    mode_input: String,
+    /// The mode field has the keyboard.
+    editing: bool,
+    /// The last touch or permission change failed.
+    error: Option<String>,
+}
+
+impl Default for FileInfoWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FileInfoWindow {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            file: PathBuf::new(),
+            metadata: Err(String::new()),
+            #[cfg(unix)]
+            mode_input: String::new(),
+            editing: false,
+            error: None,
+        }
+    }
+
+    pub fn open_for(&mut self, file: PathBuf) {
+        self.file = file;
+        self.open = true;
+        self.error = None;
+        self.refresh();
+    }
+
+    pub fn wants_keyboard(&self) -> bool {
+        self.open && self.editing
+    }
+
+    fn refresh(&mut self) {
+        self.metadata = read_metadata(&self.file);
+        #[cfg(unix)]
+        if let Ok(metadata) = &self.metadata {
+            self.mode_input = format!("{:o}", metadata.mode & 0o7777);
+        }
+    }
+
+    /// Show the window for `editor`. Returns text to put on the clipboard
+    /// when a copy button is clicked.
+    pub fn show(&mut self, ctx: &egui::Context, editor: &Editor) -> Option<String> {
+        if !self.open {
+            return None;
+        }
+        if editor.file_path.as_ref() != Some(&self.file) {
+            // The active tab changed; follow it
+            match editor.file_path.clone() {
+                Some(path) => self.open_for(path),
+                None => {
+                    self.open = false;
+                    return None;
+                }
+            }
+        }
+        let mut copy = None;
+        let mut open = self.open;
+        egui::Window::new("File Info")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::RIGHT_TOP, [-12.0, 48.0])
+            .show(ctx, |ui| {
+                egui::Grid::new("file_info").num_columns(2).spacing([24.0, 6.0]).striped(true).show(ui, |ui| {
+                    ui.label("Path");
+                    ui.label(self.file.display().to_string());
+                    ui.end_row();
+
+                    ui.label("Encoding");
+                    ui.label(format!("{}, {}", editor.encoding.label(), editor.line_ending.label()));
+                    ui.end_row();
+
+                    ui.label("Lines");
+                    ui.label(editor.line_count().to_string());
+                    ui.end_row();
+
+                    match &self.metadata {
+                        Ok(metadata) => {
+                            ui.label("Size on disk");
+                            ui.label(format_size(metadata.size));
+                            ui.end_row();
+
+                            ui.label("Modified");
+                            match metadata.modified {
+                                Some(time) => {
+                                    let local: chrono::DateTime<chrono::Local> = time.into();
+                                    ui.label(format!("{} ({})", local.format("%Y-%m-%d %H:%M:%S"), time_ago(time)));
+                                }
+                                None => {
+                                    ui.weak("unknown");
+                                }
+                            }
+                            ui.end_row();
+
+                            ui.label("Permissions");
+                            ui.label(&metadata.permissions);
+                            ui.end_row();
+
+                            ui.label("Git");
+                            match &metadata.git {
+                                Some(status) => ui.label(status),
+                                None => ui.weak("not in a repository"),
+                            };
+                            ui.end_row();
+                        }
+                        Err(e) => {
+                            ui.label("On disk");
+                            ui.colored_label(ERROR_COLOR, e);
+                            ui.end_row();
+                        }
+                    }
+                });
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    if ui.button("Copy Path").clicked() {
+                        copy = Some(self.file.display().to_string());
+                    }
+                    if ui.button("Copy File Name").clicked() {
+                        copy = self.file.file_name().map(|n| n.to_string_lossy().into_owned());
+                    }
+                    if ui.button("Touch").on_hover_text("Set the modification time to now").clicked() {
+                        let result = File::options().append(true).open(&self.file).and_then(|f| f.set_modified(SystemTime::now()));
+                        self.finish(result);
+                    }
+                    if ui.button("Refresh").clicked() {
+                        self.error = None;
+                        self.refresh();
+                    }
+                });
+                self.show_permissions(ui);
+                if let Some(error) = &self.error {
+                    ui.colored_label(ERROR_COLOR, error);
+                }
+            });
+        self.open = open;
+        copy
+    }
+
+    #[cfg(unix)]
+    fn show_permissions(&mut self, ui: &mut egui::Ui) {
+        use std::os::unix::fs::PermissionsExt;
+        let Ok(metadata) = &self.metadata else {
+            return;
+        };
+        let mode = metadata.mode;
+        ui.horizontal(|ui| {
+            ui.label("Mode");
+            let response = ui.add(egui::TextEdit::singleline(&mut self.mode_input).desired_width(48.0).font(egui::TextStyle::Monospace));
+            self.editing = response.has_focus();
+            let parsed = u32::from_str_radix(self.mode_input.trim(), 8).ok().filter(|m| *m <= 0o7777);
+            if ui.add_enabled(parsed.is_some(), egui::Button::new("Change Mode")).clicked() {
+                let result = fs::set_permissions(&self.file, fs::Permissions::from_mode(parsed.unwrap_or(mode)));
+                self.finish(result);
+            }
+            let executable = mode & 0o111 != 0;
+            let text = if executable { "Make Not Executable" } else { "Make Executable" };
+            if ui.button(text).clicked() {
+                // Execute permission for whoever may read the file
+                let mode = if executable { mode & !0o111 } else { mode | (mode & 0o444) >> 2 };
+                let result = fs::set_permissions(&self.file, fs::Permissions::from_mode(mode));
+                self.finish(result);
+            }
+        });
+    }
+
+    #[cfg(not(unix))]
+    fn show_permissions(&mut self, ui: &mut egui::Ui) {
+        let Ok(metadata) = &self.metadata else {
+            return;
+        };
+        let readonly = metadata.readonly;
+        let text = if readonly { "Make Writable" } else { "Make Read-Only" };
+        if ui.button(text).clicked() {
+            let result = fs::metadata(&self.file).and_then(|m| {
+                let mut permissions = m.permissions();
+                permissions.set_readonly(!readonly);
+                fs::set_permissions(&self.file, permissions)
+            });
+            self.finish(result);
+        }
+    }
+
+    /// Show what a change did by reading the file again, or why it failed.
+    fn finish(&mut self, result: std::io::Result<()>) {
+        match result {
+            Ok(()) => {
+                tracing::info!(path = %self.file.display(), "changed file metadata");
+                self.error = None;
+            }
+            Err(e) => {
+                tracing::warn!(path = %self.file.display(), error = %e, "failed to change file metadata");
+                self.error = Some(e.to_string());
+            }
+        }
+        self.refresh();
+    }
+}
+
+fn read_metadata(path: &std::path::Path) -> Result<Metadata, String> {
+    let metadata = fs::metadata(path).map_err(|e| e.to_string())?;
+    #[cfg(not(unix))]
+    let readonly = metadata.permissions().readonly();
+    #[cfg(unix)]
+    let (permissions, mode) = {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = metadata.permissions().mode();
+        (format!("{} ({:o})", mode_string(mode), mode & 0o7777), mode)
+    };
+    #[cfg(not(unix))]
+    let permissions = if readonly { "read-only" } else { "writable" }.to_string();
+    let git = Repo::discover(path).ok().and_then(|repo| repo.describe_file(path).ok());
+    Ok(Metadata {
+        size: metadata.len(),
+        modified: metadata.modified().ok(),
+        permissions,
+        #[cfg(unix)]
+        mode,
+        #[cfg(not(unix))]
+        readonly,
+        git,
+    })
+}
+
+/// `rwxr-xr--` for the owner, group and other permission bits.
+#[cfg(unix)]
+fn mode_string(mode: u32) -> String {
+    (0..9)
+        .map(|i| {
+            let bit = 0o400 >> i;
+            match (mode & bit != 0, i % 3) {
+                (false, _) => '-',
+                (true, 0) => 'r',
+                (true, 1) => 'w',
+                (true, _) => 'x',
+            }
+        })
+        .collect()
+}
+
+/// "512 bytes", "12.3 KB", "4.0 MB".
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 3] = ["KB", "MB", "GB"];
+    if bytes < 1024 {
+        return format!("{} bytes", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
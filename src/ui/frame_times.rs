@@ -0,0 +1,88 @@
+use std::collections::VecDeque;
+
+use eframe::egui::{self, Color32, Pos2, Rect, Stroke, Vec2};
+
+/// Frames kept for the graph and the average.
+const HISTORY: usize = 120;
+const GRAPH_SIZE: Vec2 = Vec2::new(240.0, 48.0);
+/// Budget for one frame at 60 Hz, drawn as a line across the graph.
+const FRAME_BUDGET_MS: f32 = 1000.0 / 60.0;
+
+/// Overlay with the CPU time of recent frames, for spotting slow paths
+/// while using the editor. Only frames that were actually drawn count:
+/// an idle editor doesn't repaint, so it records nothing.
+#[derive(Default)]
+pub struct FrameTimes {
+    pub visible: bool,
+    /// Milliseconds per frame, oldest first.
+    samples: VecDeque<f32>,
+}
+
+impl FrameTimes {
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+        self.samples.clear();
+    }
+
+    /// Record the previous frame's time, as reported by eframe.
+    pub fn record(&mut self, frame: &eframe::Frame) {
+        if !self.visible {
+            return;
+        }
+        if let Some(secs) = frame.info().cpu_usage {
+            if self.samples.len() == HISTORY {
+                self.samples.pop_front();
+            }
+            self.samples.push_back(secs * 1000.0);
+        }
+    }
+
+    pub fn show(&self, ctx: &egui::Context) {
+        if !self.visible {
+            return;
+        }
+        let count = self.samples.len().max(1) as f32;
+        let avg = self.samples.iter().sum::<f32>() / count;
+        let max = self.samples.iter().copied().fold(0.0, f32::max);
+        let last = self.samples.back().copied().unwrap_or(0.0);
+
+        egui::Area::new(egui::Id::new("frame_times"))
+            .anchor(egui::Align2::RIGHT_TOP, [-12.0, 36.0])
+            .interactable(false)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.label(
+                        egui::RichText::new(format!("{:.1} ms   avg {:.1}   max {:.1}", last, avg, max))
+                            .monospace()
+                            .small(),
+                    );
+                    let (rect, _) = ui.allocate_exact_size(GRAPH_SIZE, egui::Sense::hover());
+                    self.paint_graph(ui.painter(), rect, max);
+                });
+            });
+    }
+
+    /// One bar per frame, scaled so the frame budget line and the slowest
+    /// frame both fit.
+    fn paint_graph(&self, painter: &egui::Painter, rect: Rect, max: f32) {
+        let scale = rect.height() / max.max(FRAME_BUDGET_MS * 1.25);
+        let bar_width = rect.width() / HISTORY as f32;
+        let x0 = rect.right() - bar_width * self.samples.len() as f32;
+        for (i, &ms) in self.samples.iter().enumerate() {
+            let color = if ms > FRAME_BUDGET_MS {
+                Color32::from_rgb(230, 90, 80)
+            } else {
+                Color32::from_rgb(110, 180, 110)
+            };
+            let x = x0 + i as f32 * bar_width;
+            let top = rect.bottom() - (ms * scale).min(rect.height());
+            painter.rect_filled(
+                Rect::from_min_max(Pos2::new(x, top), Pos2::new(x + bar_width.max(1.0), rect.bottom())),
+                0.0,
+                color,
+            );
+        }
+        let y = rect.bottom() - FRAME_BUDGET_MS * scale;
+        painter.hline(rect.x_range(), y, Stroke::new(1.0, Color32::from_gray(160)));
+    }
+}
@@ -0,0 +1,194 @@
+//! Markers in the editor's gutter. Each kind of marker says which lane it
+//! sits in, how it is drawn and what clicking it does, so a feature that
+//! marks lines adds a kind here instead of painting the gutter itself.
+
+use std::ops::Range;
+
+use eframe::egui::{self, Color32, Pos2, Stroke};
+
+use crate::diagnostics::Severity;
+use crate::editor::Editor;
+use crate::markers::MarkerType;
+
+/// Width of the marker lanes at the gutter's left edge.
+pub const LANES_WIDTH: f32 = 24.0;
+const BOOKMARK_COLOR: Color32 = Color32::from_rgb(90, 160, 230);
+const BREAKPOINT_COLOR: Color32 = Color32::from_rgb(220, 60, 60);
+const EXECUTION_COLOR: Color32 = Color32::from_rgb(250, 200, 60);
+const FIXES_COLOR: Color32 = Color32::from_rgb(240, 210, 80);
+
+/// A column of the gutter holding one kind of marker.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Lane {
+    /// Breakpoints and the line the debugger is stopped at; clicking an
+    /// empty spot here places a breakpoint.
+    Debug,
+    /// Bookmarks and other markers the user places; clicking an empty spot
+    /// here places a bookmark.
+    User,
+    /// Diagnostics, by the worst severity on the line, or a lightbulb when
+    /// fixes are available at the cursor.
+    Diagnostic,
+}
+
+impl Lane {
+    /// Horizontal center of the lane, from the gutter's left edge.
+    fn center(self) -> f32 {
+        match self {
+            Lane::Debug => 5.0,
+            Lane::User => 14.0,
+            Lane::Diagnostic => 21.0,
+        }
+    }
+
+    /// The lane nearest to `x`.
+    fn at(x: f32) -> Lane {
+        [Lane::Debug, Lane::User, Lane::Diagnostic]
+            .into_iter()
+            .min_by(|a, b| (a.center() - x).abs().total_cmp(&(b.center() - x).abs()))
+            .unwrap_or(Lane::User)
+    }
+}
+
+/// What a gutter marker stands for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MarkerKind {
+    Line(MarkerType),
+    Diagnostic(Severity),
+    /// The debugger is stopped at this line.
+    Execution,
+    /// Fixes are available at the cursor, which is on this line.
+    Fixes,
+}
+
+/// Something shown in the gutter beside a line.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GutterMarker {
+    pub line: usize,
+    pub kind: MarkerKind,
+}
+
+impl MarkerKind {
+    fn lane(self) -> Lane {
+        match self {
+            MarkerKind::Line(MarkerType::Bookmark) => Lane::User,
+            MarkerKind::Line(MarkerType::Breakpoint) | MarkerKind::Execution => Lane::Debug,
+            MarkerKind::Diagnostic(_) | MarkerKind::Fixes => Lane::Diagnostic,
+        }
+    }
+
+    fn draw(self, painter: &egui::Painter, center: Pos2, line_height: f32) {
+        match self {
+            MarkerKind::Line(MarkerType::Bookmark) => {
+                // A ribbon with a notch at the bottom
+                let (w, h) = (4.0, (line_height * 0.6).min(12.0));
+                let top = center.y - h / 2.0;
+                let points = vec![
+                    Pos2::new(center.x - w, top),
+                    Pos2::new(center.x + w, top),
+                    Pos2::new(center.x + w, top + h),
+                    Pos2::new(center.x, top + h - 3.0),
+                    Pos2::new(center.x - w, top + h),
+                ];
+                painter.add(egui::Shape::convex_polygon(points, BOOKMARK_COLOR, Stroke::NONE));
+            }
+            MarkerKind::Line(MarkerType::Breakpoint) => {
+                painter.circle_filled(center, 4.0, BREAKPOINT_COLOR);
+            }
+            MarkerKind::Execution => {
+                // An arrow pointing at the line, over any breakpoint
+                let (w, h) = (4.0, 4.0);
+                let points = vec![
+                    Pos2::new(center.x - w, center.y - h),
+                    Pos2::new(center.x + w, center.y),
+                    Pos2::new(center.x - w, center.y + h),
+                ];
+                painter.add(egui::Shape::convex_polygon(points, EXECUTION_COLOR, Stroke::new(1.0, Color32::BLACK)));
+            }
+            MarkerKind::Diagnostic(severity) => {
+                painter.circle_filled(center, 3.0, severity.color());
+            }
+            MarkerKind::Fixes => {
+                // A bulb over its base
+                painter.circle_filled(center - egui::vec2(0.0, 1.5), 3.5, FIXES_COLOR);
+                let base = center + egui::vec2(0.0, 3.5);
+                painter.line_segment([base - egui::vec2(2.0, 0.0), base + egui::vec2(2.0, 0.0)], Stroke::new(1.5, FIXES_COLOR));
+            }
+        }
+    }
+
+    /// React to a click on the marker.
+    fn clicked(self, editor: &mut Editor, line: usize) {
+        match self {
+            MarkerKind::Line(kind) => editor.toggle_marker(line, kind),
+            MarkerKind::Execution => editor.toggle_marker(line, MarkerType::Breakpoint),
+            MarkerKind::Diagnostic(_) => {
+                // Select the first diagnostic on the line
+                if let Some(diag) = editor.diagnostics.iter().find(|d| d.touches_line(line)) {
+                    let (start, end) = (diag.start, diag.end);
+                    editor.cursors.truncate(1);
+                    editor.cursors[0].anchor = Some(start);
+                    editor.cursors[0].pos = end;
+                    editor.cursors[0].desired_col = end.col;
+                }
+            }
+            MarkerKind::Fixes => editor.show_code_actions = true,
+        }
+    }
+}
+
+/// The markers on `lines`, by line, one per kind and lane: for
+/// diagnostics only the worst severity on each line, and on the cursor's
+/// line a lightbulb instead when it has fixes.
+pub fn collect(editor: &Editor, lines: Range<usize>) -> Vec<GutterMarker> {
+    let mut markers: Vec<GutterMarker> = editor
+        .markers
+        .iter()
+        .filter(|m| lines.contains(&m.line))
+        .map(|m| GutterMarker { line: m.line, kind: MarkerKind::Line(m.kind) })
+        .collect();
+    if let Some(line) = editor.execution_line.filter(|l| lines.contains(l)) {
+        markers.push(GutterMarker { line, kind: MarkerKind::Execution });
+    }
+    let fixes_line = Some(editor.cursors[0].pos.line).filter(|l| lines.contains(l) && editor.has_code_actions());
+    if let Some(line) = fixes_line {
+        markers.push(GutterMarker { line, kind: MarkerKind::Fixes });
+    }
+    for line in lines.filter(|&l| Some(l) != fixes_line) {
+        let worst = editor.diagnostics.iter().filter(|d| d.touches_line(line)).map(|d| d.severity).max();
+        if let Some(severity) = worst {
+            markers.push(GutterMarker { line, kind: MarkerKind::Diagnostic(severity) });
+        }
+    }
+    markers.sort_by_key(|m| m.line);
+    markers
+}
+
+/// Draw the markers of one line, whose row starts at `y`.
+pub fn draw(painter: &egui::Painter, markers: &[GutterMarker], gutter_left: f32, y: f32, line_height: f32) {
+    for marker in markers {
+        let center = Pos2::new(gutter_left + marker.kind.lane().center(), y + line_height / 2.0);
+        marker.kind.draw(painter, center, line_height);
+    }
+}
+
+/// Handle a click at `x` (from the gutter's left edge) in the lanes beside
+/// `line`: the marker in that lane reacts, an empty debug lane gets a
+/// breakpoint and an empty user lane a bookmark.
+pub fn click(editor: &mut Editor, line: usize, x: f32) {
+    let lane = Lane::at(x);
+    let markers = collect(editor, line..line + 1);
+    match markers.iter().find(|m| m.kind.lane() == lane) {
+        Some(marker) => marker.kind.clicked(editor, line),
+        None => match lane {
+            Lane::Debug => editor.toggle_marker(line, MarkerType::Breakpoint),
+            Lane::User => editor.toggle_marker(line, MarkerType::Bookmark),
+            Lane::Diagnostic => {}
+        },
+    }
+}
+
+/// Whether a point is over the marker lanes of a gutter starting at `left`.
+pub fn in_lanes(pos: Pos2, left: f32) -> bool {
+    pos.x >= left && pos.x < left + LANES_WIDTH
+}
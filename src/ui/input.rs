@@ -0,0 +1,76 @@
+//! Keyboard routing. The app sorts out each frame's keys before any widget
+//! runs: Escape goes to the topmost open layer only, and handled shortcuts
+//! are taken out of the event queue, so the focused widget never reacts to
+//! the same keystroke as well.
+
+use eframe::egui::{self, Event, Key, Modifiers};
+
+use crate::commands::{self, CommandId, Scope};
+
+/// What has the keyboard, from the top down.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Layer {
+    /// The command palette.
+    Palette,
+    /// A modal dialog: shortcuts don't act on the window behind it.
+    Dialog,
+    /// The search, go to line or insert date bar above the editor.
+    Bar,
+    /// Nothing is open over the editor.
+    Editor,
+}
+
+/// Take a press of `key` without modifiers out of this frame's events.
+pub fn take_key(ctx: &egui::Context, key: Key) -> bool {
+    ctx.input_mut(|i| i.consume_key(Modifiers::NONE, key))
+}
+
+/// Take the first key pressed this frame out of the events, as the second
+/// key of a chord, with the text it typed and its release. Other events,
+/// like text typed after it, stay for the widgets.
+pub fn take_chord_key(ctx: &egui::Context) -> Option<Key> {
+    ctx.input_mut(|i| {
+        let at = i.events.iter().position(|e| matches!(e, Event::Key { pressed: true, .. }))?;
+        let Event::Key { key, .. } = i.events.remove(at) else {
+            return None;
+        };
+        // The text a key types comes right after its press
+        if matches!(i.events.get(at), Some(Event::Text(_))) {
+            i.events.remove(at);
+        }
+        let released = |e: &Event| matches!(e, Event::Key { key: k, pressed: false, .. } if *k == key);
+        if let Some(release) = i.events[at..].iter().position(released) {
+            i.events.remove(at + release);
+        }
+        Some(key)
+    })
+}
+
+/// Take the presses bound to global commands out of this frame's events and
+/// return their commands in order. A `Ctrl+K` starting a chord is taken too,
+/// reported by the second value. Held keys don't repeat a command.
+pub fn take_shortcuts(ctx: &egui::Context) -> (Vec<CommandId>, bool) {
+    let mut triggered = Vec::new();
+    let mut chord_started = false;
+    ctx.input_mut(|i| {
+        i.events.retain(|e| {
+            let Event::Key { key, pressed: true, repeat, modifiers, .. } = e else {
+                return true;
+            };
+            if *key == Key::K && modifiers.command && !modifiers.shift {
+                chord_started |= !repeat;
+                return false;
+            }
+            match commands::lookup(Scope::Global, *key, *modifiers) {
+                Some(id) => {
+                    if !repeat {
+                        triggered.push(id);
+                    }
+                    false
+                }
+                None => true,
+            }
+        });
+    });
+    (triggered, chord_started)
+}
@@ -0,0 +1,107 @@
+use std::ops::Range;
+
+use eframe::egui;
+
+use crate::editor::{Editor, Position};
+
+/// Label letters, home row first.
+const LABELS: &str = "asdfjklghqweruioptyzxcvbnm";
+/// Characters typed before labels are the only way forward.
+const MAX_QUERY_CHARS: usize = 2;
+
+/// A labeled match of the jump query.
+pub struct JumpTarget {
+    pub pos: Position,
+    pub label: char,
+}
+
+/// Matches of `query` on the visible `lines`, nearest to the primary cursor
+/// first, each with a label. While the query can still grow, letters that
+/// follow a match are left out of the labels so the next key is never
+/// ambiguous. Matches beyond the available letters get no label.
+pub fn targets(editor: &Editor, query: &str, lines: Range<usize>) -> Vec<JumpTarget> {
+    if query.is_empty() {
+        return Vec::new();
+    }
+    let query: Vec<char> = query.chars().flat_map(char::to_lowercase).collect();
+    let mut matches = Vec::new();
+    let mut following = Vec::new();
+    for line in lines {
+        let text: Vec<char> = editor
+            .line_text(line)
+            .chars()
+            .map(|c| c.to_lowercase().next().unwrap_or(c))
+            .collect();
+        for col in 0..text.len() {
+            if text[col..].starts_with(&query) {
+                matches.push(Position::new(line, col));
+                if let Some(&next) = text.get(col + query.len()) {
+                    following.push(next);
+                }
+            }
+        }
+    }
+
+    let cursor = editor.cursors[0].pos;
+    matches.sort_by_key(|p| (p.line.abs_diff(cursor.line), p.col.abs_diff(cursor.col)));
+
+    let can_grow = query.len() < MAX_QUERY_CHARS;
+    let labels = LABELS.chars().filter(|c| !(can_grow && following.contains(c)));
+    matches
+        .into_iter()
+        .zip(labels)
+        .map(|(pos, label)| JumpTarget { pos, label })
+        .collect()
+}
+
+/// Keyboard handling while jump mode is on: typed characters extend the
+/// query or pick a label, Enter takes the nearest match, Backspace edits the
+/// query and Escape leaves the mode.
+pub fn handle_input(ui: &egui::Ui, editor: &mut Editor, lines: Range<usize>) {
+    let events: Vec<egui::Event> = ui.input(|i| i.events.clone());
+    for event in &events {
+        let Some(query) = editor.jump_query.clone() else {
+            return;
+        };
+        match event {
+            egui::Event::Text(text) => {
+                let mut query = query;
+                for c in text.chars() {
+                    let targets = targets(editor, &query, lines.clone());
+                    if let Some(target) = targets.iter().find(|t| t.label == c) {
+                        jump_to(editor, target.pos);
+                        return;
+                    }
+                    if query.chars().count() < MAX_QUERY_CHARS {
+                        query.push(c);
+                    }
+                }
+                editor.jump_query = Some(query);
+            }
+            egui::Event::Key { key, pressed: true, .. } => match key {
+                egui::Key::Escape => editor.jump_query = None,
+                egui::Key::Backspace => {
+                    let mut query = query;
+                    query.pop();
+                    editor.jump_query = Some(query);
+                }
+                egui::Key::Enter => {
+                    if let Some(target) = targets(editor, &query, lines.clone()).first() {
+                        jump_to(editor, target.pos);
+                    }
+                    editor.jump_query = None;
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+}
+
+fn jump_to(editor: &mut Editor, pos: Position) {
+    editor.cursors.truncate(1);
+    editor.cursors[0].pos = pos;
+    editor.cursors[0].anchor = None;
+    editor.cursors[0].desired_col = pos.col;
+    editor.jump_query = None;
+}
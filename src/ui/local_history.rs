@@ -0,0 +1,159 @@
+use std::fs;
+use std::path::PathBuf;
+
+use eframe::egui::{self, Color32, FontId, RichText};
+
+use crate::diff::{self, DiffLine};
+use crate::editor::Editor;
+use crate::local_history::{self, Version};
+use crate::ui::time_ago;
+
+/// Unchanged lines shown around each change.
+const CONTEXT_LINES: usize = 2;
+
+/// Browser for the saved versions of the active file: pick one to see what
+/// changed since, and restore it into the buffer.
+pub struct LocalHistoryWindow {
+    pub open: bool,
+    file: PathBuf,
+    versions: Vec<Version>,
+    selected: Option<usize>,
+    /// Contents of the selected version.
+    text: String,
+    diff: Vec<DiffLine>,
+    /// Buffer version the diff was computed against.
+    diffed: Option<u64>,
+}
+
+impl Default for LocalHistoryWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LocalHistoryWindow {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            file: PathBuf::new(),
+            versions: Vec::new(),
+            selected: None,
+            text: String::new(),
+            diff: Vec::new(),
+            diffed: None,
+        }
+    }
+
+    pub fn open_for(&mut self, file: PathBuf) {
+        self.versions = local_history::versions(&file);
+        self.file = file;
+        self.open = true;
+        self.select(0);
+    }
+
+    fn select(&mut self, idx: usize) {
+        self.selected = self.versions.get(idx).map(|_| idx);
+        self.text = self
+            .selected
+            .and_then(|i| fs::read_to_string(&self.versions[i].path).ok())
+            .unwrap_or_default();
+        self.diffed = None;
+    }
+
+    /// Show the window for `editor`. Returns the text to restore when the
+    /// Restore button is clicked.
+    pub fn show(&mut self, ctx: &egui::Context, editor: &Editor) -> Option<String> {
+        if !self.open {
+            return None;
+        }
+        if editor.file_path.as_ref() != Some(&self.file) {
+            // The active tab changed; follow it
+            match editor.file_path.clone() {
+                Some(path) => self.open_for(path),
+                None => {
+                    self.open = false;
+                    return None;
+                }
+            }
+        }
+        if self.selected.is_some() && self.diffed != Some(editor.version) {
+            self.diff = diff::line_diff(&self.text, &editor.rope.to_string());
+            self.diffed = Some(editor.version);
+        }
+
+        let mut restore = None;
+        let mut clicked = None;
+        let mut open = self.open;
+        egui::Window::new(format!("Local History: {}", editor.title))
+            .open(&mut open)
+            .default_size([720.0, 440.0])
+            .show(ctx, |ui| {
+                if self.versions.is_empty() {
+                    ui.label("No saved versions yet. Each save of this file keeps a copy here.");
+                    return;
+                }
+                ui.horizontal_top(|ui| {
+                    ui.vertical(|ui| {
+                        ui.set_width(180.0);
+                        egui::ScrollArea::vertical().id_salt("versions").show(ui, |ui| {
+                            for (i, version) in self.versions.iter().enumerate() {
+                                let label = format!("{}  ({} bytes)", time_ago(version.time), version.size);
+                                if ui.selectable_label(self.selected == Some(i), label).clicked() {
+                                    clicked = Some(i);
+                                }
+                            }
+                        });
+                    });
+                    ui.separator();
+                    ui.vertical(|ui| {
+                        ui.horizontal(|ui| {
+                            ui.label("Changes since this version");
+                            if ui.button("Restore").clicked() {
+                                restore = Some(self.text.clone());
+                            }
+                        });
+                        ui.separator();
+                        egui::ScrollArea::both().id_salt("diff").show(ui, |ui| {
+                            show_diff(ui, &self.diff);
+                        });
+                    });
+                });
+            });
+        self.open = open;
+        if let Some(i) = clicked {
+            self.select(i);
+        }
+        restore
+    }
+}
+
+/// Changed lines with a little context; long unchanged runs are folded.
+pub(crate) fn show_diff(ui: &mut egui::Ui, diff: &[DiffLine]) {
+    if diff.iter().all(|l| matches!(l, DiffLine::Same(_))) {
+        ui.weak("Identical to the current buffer.");
+        return;
+    }
+    let near_change = |i: usize| {
+        let lo = i.saturating_sub(CONTEXT_LINES);
+        let hi = (i + CONTEXT_LINES + 1).min(diff.len());
+        diff[lo..hi].iter().any(|l| !matches!(l, DiffLine::Same(_)))
+    };
+    let font = FontId::monospace(12.0);
+    let mut folded = false;
+    for (i, line) in diff.iter().enumerate() {
+        let (prefix, text, color) = match line {
+            DiffLine::Same(_) if !near_change(i) => {
+                if !folded {
+                    ui.weak("\u{22ef}");
+                    folded = true;
+                }
+                continue;
+            }
+            DiffLine::Same(t) => (" ", t, Color32::from_gray(170)),
+            DiffLine::Removed(t) => ("-", t, Color32::from_rgb(230, 110, 110)),
+            DiffLine::Added(t) => ("+", t, Color32::from_rgb(120, 200, 120)),
+        };
+        folded = false;
+        ui.label(RichText::new(format!("{} {}", prefix, text)).font(font.clone()).color(color));
+    }
+}
@@ -1,3 +1,40 @@
 pub mod editor_view;
+pub mod frame_times;
+pub mod gutter;
+pub mod accessibility;
+pub mod collab_window;
 pub mod command_palette;
+pub mod debug_panel;
+pub mod diff_view;
+pub mod file_info;
+pub mod input;
+pub mod jump;
+pub mod local_history;
+pub mod output_panel;
+pub mod problems;
+pub mod rename;
+pub mod script_console;
+pub mod settings_window;
+pub mod source_control;
+pub mod statistics;
 pub mod status_bar;
+pub mod toasts;
+pub mod todo_list;
+pub mod undo_history;
+
+use std::time::{Duration, SystemTime};
+
+/// "just now", "12 s ago", "3 min ago", "2 h ago" or "4 d ago".
+pub fn time_ago(time: SystemTime) -> String {
+    let secs = SystemTime::now()
+        .duration_since(time)
+        .unwrap_or(Duration::ZERO)
+        .as_secs();
+    match secs {
+        0..=4 => "just now".into(),
+        5..=59 => format!("{} s ago", secs),
+        60..=3599 => format!("{} min ago", secs / 60),
+        3600..=86399 => format!("{} h ago", secs / 3600),
+        _ => format!("{} d ago", secs / 86400),
+    }
+}
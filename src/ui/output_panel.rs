@@ -0,0 +1,125 @@
+use std::collections::VecDeque;
+
+use eframe::egui;
+
+use crate::runner::{Run, RunEvent};
+
+/// Lines kept from a run; older ones scroll away.
+const MAX_LINES: usize = 10_000;
+const STDERR_COLOR: egui::Color32 = egui::Color32::from_rgb(240, 110, 100);
+
+enum Status {
+    Running,
+    Exited(Option<i32>),
+    Failed(String),
+}
+
+/// Panel under the editor showing the output of the last run command.
+#[derive(Default)]
+pub struct OutputPanel {
+    pub open: bool,
+    run: Option<Run>,
+    status: Option<Status>,
+    /// Output lines, true for standard error.
+    lines: VecDeque<(bool, String)>,
+}
+
+impl OutputPanel {
+    /// Show the panel for a new run, replacing (and stopping) the last one.
+    pub fn start(&mut self, run: Run) {
+        self.lines.clear();
+        self.status = Some(Status::Running);
+        self.run = Some(run);
+        self.open = true;
+    }
+
+    pub fn stop(&self) {
+        if let Some(run) = &self.run {
+            run.stop();
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.run.as_ref().is_some_and(Run::is_running)
+    }
+
+    /// Take in the output that arrived since the last frame.
+    fn poll(&mut self) {
+        let Some(run) = &self.run else {
+            return;
+        };
+        for event in run.poll() {
+            match event {
+                RunEvent::Stdout(line) => self.push(false, line),
+                RunEvent::Stderr(line) => self.push(true, line),
+                RunEvent::Exited(code) => self.status = Some(Status::Exited(code)),
+                RunEvent::Failed(e) => self.status = Some(Status::Failed(e)),
+            }
+        }
+    }
+
+    fn push(&mut self, stderr: bool, line: String) {
+        if self.lines.len() == MAX_LINES {
+            self.lines.pop_front();
+        }
+        self.lines.push_back((stderr, line));
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context) {
+        self.poll();
+        if !self.open {
+            return;
+        }
+        egui::TopBottomPanel::bottom("output_panel")
+            .resizable(true)
+            .default_height(180.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.strong("Output");
+                    if let Some(run) = &self.run {
+                        ui.label(egui::RichText::new(&run.command).monospace())
+                            .on_hover_text(run.cwd.display().to_string());
+                    }
+                    match &self.status {
+                        Some(Status::Running) => {
+                            ui.spinner();
+                        }
+                        Some(Status::Exited(Some(0))) => {
+                            ui.weak("finished");
+                        }
+                        Some(Status::Exited(Some(code))) => {
+                            ui.colored_label(STDERR_COLOR, format!("exited with code {}", code));
+                        }
+                        Some(Status::Exited(None)) => {
+                            ui.colored_label(STDERR_COLOR, "stopped");
+                        }
+                        Some(Status::Failed(e)) => {
+                            ui.colored_label(STDERR_COLOR, format!("failed to start: {}", e));
+                        }
+                        None => {}
+                    }
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui.button("Close").clicked() {
+                            self.open = false;
+                        }
+                        if ui.button("Clear").clicked() {
+                            self.lines.clear();
+                        }
+                        if ui.add_enabled(self.is_running(), egui::Button::new("Stop")).clicked() {
+                            self.stop();
+                        }
+                    });
+                });
+                ui.separator();
+                egui::ScrollArea::both()
+                    .auto_shrink([false, false])
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        for (stderr, line) in &self.lines {
+                            let text = egui::RichText::new(line).monospace();
+                            ui.label(if *stderr { text.color(STDERR_COLOR) } else { text });
+                        }
+                    });
+            });
+    }
+}
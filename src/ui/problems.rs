@@ -0,0 +1,59 @@
+use std::path::{Path, PathBuf};
+
+use eframe::egui;
+
+use crate::diagnostics::{Problem, Severity};
+
+/// Bottom window listing the problems build tasks and linters reported,
+/// grouped by file. Returns the file, line and column of a clicked row.
+pub fn show(
+    ctx: &egui::Context,
+    open: &mut bool,
+    problems: &[Problem],
+    root: Option<&Path>,
+) -> Option<(PathBuf, usize, usize)> {
+    let mut jump = None;
+    egui::Window::new("Problems")
+        .open(open)
+        .default_width(640.0)
+        .default_height(240.0)
+        .anchor(egui::Align2::LEFT_BOTTOM, [12.0, -36.0])
+        .show(ctx, |ui| {
+            let count = |severity| problems.iter().filter(|p| p.severity == severity).count();
+            ui.label(format!(
+                "{} errors, {} warnings, {} other",
+                count(Severity::Error),
+                count(Severity::Warning),
+                problems.len() - count(Severity::Error) - count(Severity::Warning),
+            ));
+            ui.separator();
+            egui::ScrollArea::vertical().auto_shrink([false, false]).show(ui, |ui| {
+                if problems.is_empty() {
+                    ui.weak("No problems reported.");
+                }
+                for (i, problem) in problems.iter().enumerate() {
+                    if i == 0 || problems[i - 1].path != problem.path {
+                        let name = root
+                            .and_then(|r| problem.path.strip_prefix(r).ok())
+                            .unwrap_or(&problem.path);
+                        ui.strong(name.display().to_string());
+                    }
+                    ui.horizontal(|ui| {
+                        ui.add_space(12.0);
+                        ui.colored_label(problem.severity.color(), problem.severity.label());
+                        let text = format!(
+                            "{}:{}  {}  [{}]",
+                            problem.line + 1,
+                            problem.col + 1,
+                            problem.message,
+                            problem.source
+                        );
+                        if ui.selectable_label(false, text).clicked() {
+                            jump = Some((problem.path.clone(), problem.line, problem.col));
+                        }
+                    });
+                }
+            });
+        });
+    jump
+}
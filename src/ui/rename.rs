@@ -0,0 +1,158 @@
+use std::path::PathBuf;
+
+use eframe::egui::{self, Color32, RichText};
+
+use crate::edits::{TextEdit, WorkspaceEdit};
+use crate::path_refs;
+use crate::workspace_search::WordHit;
+
+const REMOVED_COLOR: Color32 = Color32::from_rgb(220, 110, 110);
+const ADDED_COLOR: Color32 = Color32::from_rgb(110, 200, 110);
+
+/// Asks for a symbol's new name and lists every occurrence it would change,
+/// each of which can be left out.
+pub struct RenameWindow {
+    pub open: bool,
+    old: String,
+    new: String,
+    hits: Vec<(bool, WordHit)>,
+    /// The search stopped at its limit, so some occurrences are missing.
+    truncated: bool,
+    /// Paths are shown relative to this.
+    root: Option<PathBuf>,
+    focus: bool,
+}
+
+impl Default for RenameWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RenameWindow {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            old: String::new(),
+            new: String::new(),
+            hits: Vec::new(),
+            truncated: false,
+            root: None,
+            focus: false,
+        }
+    }
+
+    pub fn open_for(&mut self, old: String, hits: Vec<WordHit>, truncated: bool, root: Option<PathBuf>) {
+        self.new = old.clone();
+        self.old = old;
+        self.hits = hits.into_iter().map(|hit| (true, hit)).collect();
+        self.truncated = truncated;
+        self.root = root;
+        self.open = true;
+        self.focus = true;
+    }
+
+    /// Show the window. Returns the edit renaming the checked occurrences
+    /// when Rename is clicked.
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<WorkspaceEdit> {
+        if !self.open {
+            return None;
+        }
+        let mut rename = false;
+        let mut cancel = false;
+        egui::Window::new("Rename Symbol")
+            .collapsible(false)
+            .resizable(true)
+            .default_width(600.0)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                let problem = self.problem();
+                ui.horizontal(|ui| {
+                    ui.label(format!("Rename '{}' to", self.old));
+                    let response = ui.text_edit_singleline(&mut self.new);
+                    if std::mem::take(&mut self.focus) {
+                        response.request_focus();
+                    }
+                    if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) && problem.is_none() {
+                        rename = true;
+                    }
+                });
+                let files = {
+                    let mut paths: Vec<&PathBuf> = self.hits.iter().map(|(_, hit)| &hit.path).collect();
+                    paths.dedup();
+                    paths.len()
+                };
+                ui.label(format!(
+                    "{} occurrence(s) in {} file(s), matching whole words and case{}:",
+                    self.hits.len(),
+                    files,
+                    if self.truncated { " (stopped at the limit)" } else { "" },
+                ));
+                ui.add_space(4.0);
+                egui::ScrollArea::vertical().max_height(320.0).show(ui, |ui| {
+                    for (enabled, hit) in &mut self.hits {
+                        let path = match &self.root {
+                            Some(root) => path_refs::relative_path(root, &hit.path),
+                            None => hit.path.display().to_string(),
+                        };
+                        ui.checkbox(enabled, format!("{}:{}", path, hit.start.line + 1));
+                        let (before, after) = split_at_char(&hit.line_text, hit.start.col, hit.end.col);
+                        ui.label(RichText::new(format!("- {}", hit.line_text.trim())).monospace().color(REMOVED_COLOR));
+                        let renamed = format!("{}{}{}", before, self.new, after);
+                        ui.label(RichText::new(format!("+ {}", renamed.trim())).monospace().color(ADDED_COLOR));
+                        ui.add_space(4.0);
+                    }
+                });
+                ui.add_space(8.0);
+                ui.horizontal(|ui| {
+                    let any = self.hits.iter().any(|(enabled, _)| *enabled);
+                    if ui.add_enabled(problem.is_none() && any, egui::Button::new("Rename")).clicked() {
+                        rename = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        cancel = true;
+                    }
+                    if let Some(problem) = problem {
+                        ui.weak(problem);
+                    }
+                });
+            });
+
+        if cancel {
+            self.close();
+        }
+        if !rename {
+            return None;
+        }
+        // Found in snapshots, so each edit checks the word is still there
+        let mut edit = WorkspaceEdit::new();
+        for (_, hit) in self.hits.iter().filter(|(enabled, _)| *enabled) {
+            let (text, old) = (self.new.clone(), Some(self.old.clone()));
+            edit.entry(hit.path.clone()).or_default().push(TextEdit { start: hit.start, end: hit.end, text, old });
+        }
+        self.close();
+        Some(edit)
+    }
+
+    pub fn close(&mut self) {
+        self.open = false;
+        self.hits.clear();
+    }
+
+    /// Why the new name can't be used yet.
+    fn problem(&self) -> Option<&'static str> {
+        if self.new == self.old {
+            Some("Type the new name.")
+        } else if self.new.is_empty() || !self.new.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            Some("The name must be a single word.")
+        } else {
+            None
+        }
+    }
+}
+
+/// The parts of `line` before char `start` and after char `end`.
+fn split_at_char(line: &str, start: usize, end: usize) -> (&str, &str) {
+    let byte = |col: usize| line.char_indices().nth(col).map_or(line.len(), |(i, _)| i);
+    (&line[..byte(start)], &line[byte(end)..])
+}
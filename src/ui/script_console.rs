@@ -0,0 +1,102 @@
+use eframe::egui;
+
+use crate::editor::Editor;
+use crate::scripting::{OutputKind, ScriptHost};
+
+const ERROR_COLOR: egui::Color32 = egui::Color32::from_rgb(240, 110, 100);
+
+/// Window for typing Rhai commands against the active buffer, with the
+/// output of everything scripts printed.
+#[derive(Default)]
+pub struct ScriptConsole {
+    pub open: bool,
+    input: String,
+    /// Commands run so far, oldest first.
+    history: Vec<String>,
+    /// Position while stepping through `history` with the arrow keys.
+    history_pos: Option<usize>,
+    focus_input: bool,
+}
+
+impl ScriptConsole {
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.focus_input = self.open;
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, host: &mut ScriptHost, editor: &mut Editor) {
+        let mut open = self.open;
+        let mut run = None;
+        egui::Window::new("Script Console")
+            .open(&mut open)
+            .default_width(520.0)
+            .default_height(280.0)
+            .anchor(egui::Align2::RIGHT_BOTTOM, [-12.0, -36.0])
+            .show(ctx, |ui| {
+                let input_height = ui.spacing().interact_size.y + 8.0;
+                egui::ScrollArea::vertical()
+                    .auto_shrink([false, false])
+                    .max_height(ui.available_height() - input_height)
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        for line in host.output().iter() {
+                            let text = egui::RichText::new(&line.text).monospace();
+                            let text = match line.kind {
+                                OutputKind::Input => text.weak(),
+                                OutputKind::Output => text,
+                                OutputKind::Error => text.color(ERROR_COLOR),
+                            };
+                            ui.add(egui::Label::new(text).wrap());
+                        }
+                    });
+                ui.separator();
+                ui.horizontal(|ui| {
+                    let response = ui.add(
+                        egui::TextEdit::singleline(&mut self.input)
+                            .font(egui::TextStyle::Monospace)
+                            .hint_text("e.g. replace_selections(|s| s.to_upper())")
+                            .desired_width(ui.available_width() - 60.0),
+                    );
+                    if std::mem::take(&mut self.focus_input) {
+                        response.request_focus();
+                    }
+                    if response.has_focus() {
+                        self.step_history(ui);
+                    }
+                    if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                        run = Some(std::mem::take(&mut self.input));
+                        self.focus_input = true;
+                    }
+                    if ui.button("Clear").clicked() {
+                        host.clear_output();
+                    }
+                });
+            });
+        if !open {
+            self.open = false;
+        }
+        if let Some(command) = run.filter(|c| !c.trim().is_empty()) {
+            if self.history.last() != Some(&command) {
+                self.history.push(command.clone());
+            }
+            self.history_pos = None;
+            editor.labeled_edit("Script Console", |editor| host.eval(&command, editor));
+        }
+    }
+
+    /// Up and Down recall earlier commands.
+    fn step_history(&mut self, ui: &egui::Ui) {
+        let (up, down) = ui.input(|i| (i.key_pressed(egui::Key::ArrowUp), i.key_pressed(egui::Key::ArrowDown)));
+        if !up && !down || self.history.is_empty() {
+            return;
+        }
+        let last = self.history.len() - 1;
+        self.history_pos = match (self.history_pos, up) {
+            (None, true) => Some(last),
+            (Some(p), true) => Some(p.saturating_sub(1)),
+            (Some(p), false) if p < last => Some(p + 1),
+            (_, false) => None,
+        };
+        self.input = self.history_pos.map(|p| self.history[p].clone()).unwrap_or_default();
+    }
+}
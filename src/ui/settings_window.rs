@@ -0,0 +1,424 @@
+use eframe::egui;
+
+use crate::commands::{self, KeyBinding};
+use crate::config::{self, ColorScheme, Keymap, Settings, Theme, DEFAULT_DATE_FORMAT, FONT_SIZE_RANGE, UI_SCALE_RANGE};
+use crate::editor::LineEnding;
+use crate::syntax::{SyntaxPalette, DEFAULT_MIN_CONTRAST};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Section {
+    Editor,
+    Appearance,
+    Keybindings,
+    Files,
+}
+
+impl Section {
+    const ALL: [Section; 4] = [
+        Section::Editor,
+        Section::Appearance,
+        Section::Keybindings,
+        Section::Files,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            Section::Editor => "Editor",
+            Section::Appearance => "Appearance",
+            Section::Keybindings => "Keybindings",
+            Section::Files => "Files",
+        }
+    }
+}
+
+/// One row of the keybinding editor.
+struct BindingRow {
+    keymap_name: String,
+    name: String,
+    /// Comma-separated shortcuts as edited.
+    text: String,
+    default_text: String,
+}
+
+impl BindingRow {
+    fn parse(&self) -> Option<Vec<KeyBinding>> {
+        self.text
+            .split(',')
+            .map(str::trim)
+            .filter(|k| !k.is_empty())
+            .map(KeyBinding::parse)
+            .collect()
+    }
+}
+
+/// Edits copies of the settings, theme and keymap; nothing changes until Save.
+pub struct SettingsWindow {
+    pub open: bool,
+    section: Section,
+    settings: Settings,
+    theme: Theme,
+    bindings: Vec<BindingRow>,
+    binding_filter: String,
+    /// Ruler columns as edited, comma-separated.
+    rulers: String,
+}
+
+/// Everything the window saves at once.
+pub struct SavedConfig {
+    pub settings: Settings,
+    pub theme: Theme,
+    pub keymap: Keymap,
+}
+
+fn bindings_text(bindings: &[KeyBinding]) -> String {
+    bindings.iter().map(|b| b.label()).collect::<Vec<_>>().join(", ")
+}
+
+impl Default for SettingsWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SettingsWindow {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            section: Section::Editor,
+            settings: Settings::default(),
+            theme: Theme::default(),
+            bindings: Vec::new(),
+            binding_filter: String::new(),
+            rulers: String::new(),
+        }
+    }
+
+    /// Open the window with drafts of the current configuration.
+    pub fn open_with(&mut self, settings: &Settings, theme: &Theme) {
+        self.open = true;
+        self.settings = settings.clone();
+        self.theme = theme.clone();
+        self.binding_filter.clear();
+        self.rulers = settings.rulers.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(", ");
+        self.bindings = commands::all()
+            .iter()
+            .filter(|c| c.chord.is_none())
+            .map(|c| BindingRow {
+                keymap_name: c.keymap_name(),
+                name: c.name.clone(),
+                text: bindings_text(&c.active_bindings()),
+                default_text: bindings_text(&c.bindings),
+            })
+            .collect();
+    }
+
+    /// Show the window. Returns the edited configuration when Save is clicked.
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<SavedConfig> {
+        if !self.open {
+            return None;
+        }
+        let mut saved = None;
+        let mut open = self.open;
+
+        egui::Window::new("Settings")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(true)
+            .default_size([560.0, 420.0])
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    for section in Section::ALL {
+                        ui.selectable_value(&mut self.section, section, section.label());
+                    }
+                });
+                ui.separator();
+
+                egui::ScrollArea::vertical()
+                    .max_height(320.0)
+                    .auto_shrink([false, true])
+                    .show(ui, |ui| match self.section {
+                        Section::Editor => self.show_editor(ui),
+                        Section::Appearance => self.show_appearance(ui),
+                        Section::Keybindings => self.show_keybindings(ui),
+                        Section::Files => self.show_files(ui),
+                    });
+
+                ui.separator();
+                let invalid = self.bindings.iter().any(|r| r.parse().is_none());
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(!invalid, egui::Button::new("Save")).clicked() {
+                        saved = Some(self.collect());
+                    }
+                    if ui.button("Cancel").clicked() {
+                        self.open = false;
+                    }
+                    if invalid {
+                        ui.colored_label(egui::Color32::from_rgb(230, 100, 100), "Fix the highlighted shortcuts to save");
+                    }
+                });
+                ui.label(
+                    egui::RichText::new(format!("Saved to {}", config::config_dir().display()))
+                        .small()
+                        .weak(),
+                );
+            });
+
+        self.open = self.open && open && saved.is_none();
+        saved
+    }
+
+    fn collect(&self) -> SavedConfig {
+        // Only shortcuts that differ from the defaults go into keymap.toml;
+        // overrides for commands not listed here are kept as they are
+        let mut keymap = commands::keymap();
+        for row in &self.bindings {
+            match row.parse() {
+                Some(bindings) if row.text.trim() != row.default_text => {
+                    keymap.insert(row.keymap_name.clone(), bindings);
+                }
+                _ => {
+                    keymap.remove(&row.keymap_name);
+                }
+            }
+        }
+        let mut settings = self.settings.clone();
+        settings.rulers = self
+            .rulers
+            .split(',')
+            .filter_map(|c| c.trim().parse().ok())
+            .collect();
+        SavedConfig {
+            settings,
+            theme: self.theme.clone(),
+            keymap,
+        }
+    }
+
+    fn show_editor(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("settings_editor").num_columns(2).spacing([24.0, 8.0]).show(ui, |ui| {
+            ui.label("Font size");
+            ui.add(egui::Slider::new(&mut self.settings.font_size, FONT_SIZE_RANGE).step_by(1.0));
+            ui.end_row();
+
+            ui.label("Tab width");
+            ui.add(egui::Slider::new(&mut self.settings.tab_width, 1..=8));
+            ui.end_row();
+
+            ui.label("Indentation");
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut self.settings.use_tabs, false, "Spaces");
+                ui.radio_value(&mut self.settings.use_tabs, true, "Tabs");
+            });
+            ui.end_row();
+
+            ui.label("Detect indentation");
+            ui.checkbox(&mut self.settings.detect_indentation, "")
+                .on_hover_text("Follow the indentation each file already uses");
+            ui.end_row();
+
+            ui.label("Scroll past end");
+            ui.checkbox(&mut self.settings.scroll_past_end, "");
+            ui.end_row();
+
+            ui.label("Scroll margin (lines)");
+            ui.add(egui::Slider::new(&mut self.settings.scroll_margin, 0..=10));
+            ui.end_row();
+
+            ui.label("Continue comments");
+            ui.checkbox(&mut self.settings.continue_comments, "")
+                .on_hover_text("Enter on a comment line starts the next one with the same prefix");
+            ui.end_row();
+
+            ui.label("Re-indent pasted lines");
+            ui.checkbox(&mut self.settings.reindent_paste, "")
+                .on_hover_text("Multi-line pastes take the indentation of the line they are pasted into");
+            ui.end_row();
+
+            ui.label("Emmet abbreviations");
+            ui.checkbox(&mut self.settings.emmet, "")
+                .on_hover_text("Tab expands abbreviations like ul>li*3 in HTML and CSS files");
+            ui.end_row();
+
+            ui.label("Rulers");
+            ui.add(egui::TextEdit::singleline(&mut self.rulers).hint_text("e.g. 80, 100, 120"));
+            ui.end_row();
+
+            ui.label("Undo memory per buffer (MB)");
+            ui.add(egui::Slider::new(&mut self.settings.undo_memory_mb, 1..=1024).logarithmic(true));
+            ui.end_row();
+
+            ui.label("Reflow width");
+            ui.add(egui::Slider::new(&mut self.settings.text_width, 20..=200));
+            ui.end_row();
+
+            ui.label("Date/time format");
+            ui.add(egui::TextEdit::singleline(&mut self.settings.date_format).hint_text(DEFAULT_DATE_FORMAT));
+            ui.end_row();
+        });
+    }
+
+    fn show_appearance(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("settings_appearance").num_columns(2).spacing([24.0, 8.0]).show(ui, |ui| {
+            ui.label("Interface scale");
+            ui.add(egui::Slider::new(&mut self.settings.ui_scale, UI_SCALE_RANGE).step_by(0.05))
+                .on_hover_text("Applied on top of the display's scaling, which is followed as the window moves between screens");
+            ui.end_row();
+
+            ui.label("Line numbers");
+            ui.checkbox(&mut self.settings.line_numbers, "");
+            ui.end_row();
+
+            ui.label("Status bar");
+            ui.checkbox(&mut self.settings.status_bar, "");
+            ui.end_row();
+
+            ui.label("Statistics in status bar");
+            ui.checkbox(&mut self.settings.status_statistics, "");
+            ui.end_row();
+
+            ui.label("Sticky scroll");
+            ui.checkbox(&mut self.settings.sticky_scroll, "");
+            ui.end_row();
+
+            ui.label("Current line only when focused");
+            ui.checkbox(&mut self.settings.active_line_focus_only, "")
+                .on_hover_text("Highlight the cursor's line only while the editor has keyboard focus");
+            ui.end_row();
+
+            ui.label("Show line ends");
+            ui.checkbox(&mut self.settings.show_line_ends, "");
+            ui.end_row();
+
+            ui.label("Color swatches");
+            ui.checkbox(&mut self.settings.color_swatches, "")
+                .on_hover_text("Show the colors written in the text, and click one to change it");
+            ui.end_row();
+
+            ui.label("Color scheme");
+            egui::ComboBox::from_id_salt("settings_scheme")
+                .selected_text(self.settings.color_scheme.name())
+                .show_ui(ui, |ui| {
+                    for scheme in ColorScheme::ALL {
+                        ui.selectable_value(&mut self.settings.color_scheme, scheme, scheme.name());
+                    }
+                });
+            ui.end_row();
+
+            ui.label("Syntax palette");
+            egui::ComboBox::from_id_salt("settings_palette")
+                .selected_text(self.settings.syntax_palette.name())
+                .show_ui(ui, |ui| {
+                    for p in SyntaxPalette::ALL {
+                        ui.selectable_value(&mut self.settings.syntax_palette, p, p.name());
+                    }
+                });
+            ui.end_row();
+
+            ui.label("Minimum contrast");
+            ui.horizontal(|ui| {
+                let mut enabled = self.settings.min_contrast.is_some();
+                if ui.checkbox(&mut enabled, "").changed() {
+                    self.settings.min_contrast = enabled.then_some(DEFAULT_MIN_CONTRAST);
+                }
+                if let Some(ratio) = &mut self.settings.min_contrast {
+                    ui.add(egui::Slider::new(ratio, 1.0..=7.0).step_by(0.5));
+                }
+            });
+            ui.end_row();
+
+            ui.label("Dark scheme colors").on_hover_text("Saved to theme.toml");
+            ui.end_row();
+
+            let theme = &mut self.theme;
+            for (label, color) in [
+                ("Background", &mut theme.background),
+                ("Text", &mut theme.text),
+                ("Cursor", &mut theme.cursor),
+                ("Selection", &mut theme.selection),
+                ("Gutter", &mut theme.gutter),
+                ("Line numbers", &mut theme.line_number),
+                ("Current line number", &mut theme.line_number_active),
+                ("Current line", &mut theme.active_line),
+                ("Word highlight", &mut theme.word_highlight),
+                ("Search matches", &mut theme.search_match),
+                ("Control characters", &mut theme.control_char),
+                ("Rulers", &mut theme.ruler),
+            ] {
+                ui.label(label);
+                ui.color_edit_button_srgba(color);
+                ui.end_row();
+            }
+        });
+        if ui.button("Reset Colors").clicked() {
+            self.theme = Theme::default();
+        }
+    }
+
+    fn show_keybindings(&mut self, ui: &mut egui::Ui) {
+        ui.add(
+            egui::TextEdit::singleline(&mut self.binding_filter)
+                .hint_text("Filter commands...")
+                .desired_width(f32::INFINITY),
+        );
+        ui.label(egui::RichText::new("Separate several shortcuts with commas; leave empty to unbind.").small().weak());
+        ui.add_space(4.0);
+
+        let filter = self.binding_filter.to_lowercase();
+        egui::Grid::new("settings_keys").num_columns(3).striped(true).show(ui, |ui| {
+            for row in self
+                .bindings
+                .iter_mut()
+                .filter(|r| r.name.to_lowercase().contains(&filter))
+            {
+                ui.label(&row.name);
+                let valid = row
+                    .text
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|k| !k.is_empty())
+                    .all(|k| KeyBinding::parse(k).is_some());
+                let mut edit = egui::TextEdit::singleline(&mut row.text).desired_width(160.0);
+                if !valid {
+                    edit = edit.text_color(egui::Color32::from_rgb(230, 100, 100));
+                }
+                ui.add(edit);
+                if ui
+                    .add_enabled(row.text != row.default_text, egui::Button::new("Reset").small())
+                    .clicked()
+                {
+                    row.text = row.default_text.clone();
+                }
+                ui.end_row();
+            }
+        });
+    }
+
+    fn show_files(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("settings_files").num_columns(2).spacing([24.0, 8.0]).show(ui, |ui| {
+            ui.label("Trim trailing whitespace on save");
+            ui.checkbox(&mut self.settings.trim_trailing_whitespace_on_save, "");
+            ui.end_row();
+
+            ui.label("Ensure final newline on save");
+            ui.checkbox(&mut self.settings.ensure_final_newline, "");
+            ui.end_row();
+
+            ui.label("Language servers (rust-analyzer)");
+            ui.checkbox(&mut self.settings.language_servers, "");
+            ui.end_row();
+
+            ui.label("Open files from Go to File in a preview tab");
+            ui.checkbox(&mut self.settings.preview_tabs, "");
+            ui.end_row();
+
+            ui.label("Line ending for new files");
+            ui.horizontal(|ui| {
+                for ending in [LineEnding::Lf, LineEnding::CrLf] {
+                    ui.radio_value(&mut self.settings.default_line_ending, ending, ending.label());
+                }
+            });
+            ui.end_row();
+        });
+    }
+}
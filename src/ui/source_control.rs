@@ -0,0 +1,274 @@
+use std::path::{Path, PathBuf};
+
+use eframe::egui::{self, RichText};
+
+use crate::diff::{self, DiffLine};
+use crate::git::{Change, FileStatus, Remote, Repo};
+use crate::ui::local_history::show_diff;
+
+const ERROR_COLOR: egui::Color32 = egui::Color32::from_rgb(240, 110, 100);
+
+/// Something the panel needs the app to do.
+pub enum ScmAction {
+    Open(PathBuf),
+    Remote(Remote),
+    /// A commit was made; the short id.
+    Committed(String),
+}
+
+/// Side panel with the workspace repository's changed files: stage and
+/// unstage them, preview their diff, commit, push and pull.
+#[derive(Default)]
+pub struct SourceControl {
+    pub open: bool,
+    repo: Option<Repo>,
+    branch: Option<String>,
+    files: Vec<FileStatus>,
+    /// Why there is no repository, or the last operation failed.
+    error: Option<String>,
+    message: String,
+    /// The file whose diff is shown, and whether it's the staged side.
+    selected: Option<(PathBuf, bool)>,
+    diff: Vec<DiffLine>,
+    /// A push or pull is running.
+    pub busy: bool,
+    /// The commit message box has the keyboard.
+    editing: bool,
+}
+
+impl SourceControl {
+    /// Show the panel for the repository containing `root`.
+    pub fn open_for(&mut self, root: Option<&Path>) {
+        self.open = true;
+        let same = match (&self.repo, root) {
+            (Some(repo), Some(root)) => root.starts_with(&repo.root),
+            _ => false,
+        };
+        if !same {
+            self.repo = None;
+            self.selected = None;
+            self.error = match root.map(Repo::discover) {
+                Some(Ok(repo)) => {
+                    self.repo = Some(repo);
+                    None
+                }
+                Some(Err(e)) => Some(format!("No git repository: {}", e.message())),
+                None => Some("Open a folder to use source control.".to_string()),
+            };
+        }
+        self.refresh();
+    }
+
+    /// Forget the repository, e.g. when the workspace closes.
+    pub fn close(&mut self) {
+        *self = Self::default();
+    }
+
+    /// True while the commit message box has the keyboard.
+    pub fn wants_keyboard(&self) -> bool {
+        self.open && self.editing
+    }
+
+    /// Re-read the status and the shown diff, after anything that may have
+    /// changed them.
+    pub fn refresh(&mut self) {
+        let Some(repo) = &self.repo else {
+            return;
+        };
+        self.branch = repo.branch();
+        match repo.status() {
+            Ok(files) => self.files = files,
+            Err(e) => self.error = Some(e.message().to_string()),
+        }
+        // Keep showing the file if it moved between staged and unstaged
+        if let Some((path, staged)) = self.selected.clone() {
+            let side = |staged: bool| {
+                self.files
+                    .iter()
+                    .any(|f| f.path == path && if staged { f.staged.is_some() } else { f.unstaged.is_some() })
+            };
+            self.selected = if side(staged) {
+                Some((path, staged))
+            } else if side(!staged) {
+                Some((path, !staged))
+            } else {
+                None
+            };
+        }
+        self.load_diff();
+    }
+
+    fn load_diff(&mut self) {
+        self.diff.clear();
+        let (Some(repo), Some((path, staged))) = (&self.repo, &self.selected) else {
+            return;
+        };
+        match repo.diff_texts(path, *staged) {
+            Ok((old, new)) => self.diff = diff::line_diff(&old, &new),
+            Err(e) => self.error = Some(e.message().to_string()),
+        }
+    }
+
+    /// Run a repository operation, then show its error or the new status.
+    fn apply(&mut self, f: impl FnOnce(&Repo) -> Result<(), git2::Error>) {
+        let Some(repo) = &self.repo else {
+            return;
+        };
+        self.error = f(repo).err().map(|e| e.message().to_string());
+        self.refresh();
+    }
+
+    fn commit(&mut self) -> Option<ScmAction> {
+        let repo = self.repo.as_ref()?;
+        match repo.commit(self.message.trim()) {
+            Ok(id) => {
+                tracing::info!(commit = %id, "committed");
+                self.message.clear();
+                self.error = None;
+                self.refresh();
+                Some(ScmAction::Committed(id))
+            }
+            Err(e) => {
+                self.error = Some(e.message().to_string());
+                None
+            }
+        }
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<ScmAction> {
+        if !self.open {
+            self.editing = false;
+            return None;
+        }
+        let mut action = None;
+        let mut stage = None;
+        let mut unstage = None;
+        let mut select = None;
+        let mut commit = false;
+        egui::SidePanel::right("source_control")
+            .resizable(true)
+            .default_width(300.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.strong("Source Control");
+                    if let Some(branch) = &self.branch {
+                        ui.weak(format!("\u{2387} {}", branch));
+                    }
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui.button("\u{2715}").on_hover_text("Close").clicked() {
+                            self.open = false;
+                        }
+                        if self.repo.is_none() {
+                            return;
+                        }
+                        if ui.button("\u{27f3}").on_hover_text("Refresh").clicked() {
+                            self.refresh();
+                        }
+                        if self.busy {
+                            ui.spinner();
+                        } else {
+                            if ui.button("Push").clicked() {
+                                action = Some(ScmAction::Remote(Remote::Push));
+                            }
+                            if ui.button("Pull").clicked() {
+                                action = Some(ScmAction::Remote(Remote::Pull));
+                            }
+                        }
+                    });
+                });
+                if let Some(error) = &self.error {
+                    ui.add(egui::Label::new(RichText::new(error).color(ERROR_COLOR)).wrap());
+                }
+                if self.repo.is_none() {
+                    return;
+                }
+                ui.separator();
+
+                let response = ui.add(
+                    egui::TextEdit::multiline(&mut self.message)
+                        .hint_text("Commit message")
+                        .desired_rows(3)
+                        .desired_width(f32::INFINITY),
+                );
+                self.editing = response.has_focus();
+                let any_staged = self.files.iter().any(|f| f.staged.is_some());
+                let can_commit = any_staged && !self.message.trim().is_empty();
+                let shortcut = response.has_focus() && ui.input(|i| i.modifiers.command && i.key_pressed(egui::Key::Enter));
+                if ui.add_enabled(can_commit, egui::Button::new("Commit")).on_hover_text("Ctrl+Enter").clicked()
+                    || (shortcut && can_commit)
+                {
+                    commit = true;
+                }
+                ui.separator();
+
+                egui::ScrollArea::vertical()
+                    .id_salt("scm_files")
+                    .max_height(ui.available_height() * 0.45)
+                    .auto_shrink([false, true])
+                    .show(ui, |ui| {
+                        for staged in [true, false] {
+                            let files: Vec<&FileStatus> = self
+                                .files
+                                .iter()
+                                .filter(|f| if staged { f.staged.is_some() } else { f.unstaged.is_some() })
+                                .collect();
+                            if staged && files.is_empty() {
+                                continue;
+                            }
+                            let title = if staged { "Staged Changes" } else { "Changes" };
+                            ui.strong(format!("{} ({})", title, files.len()));
+                            if !staged && files.is_empty() {
+                                ui.weak("No changes.");
+                            }
+                            for file in files {
+                                let change = if staged { file.staged } else { file.unstaged };
+                                ui.horizontal(|ui| {
+                                    let (label, hover) = if staged { ("\u{2212}", "Unstage") } else { ("+", "Stage") };
+                                    if ui.small_button(label).on_hover_text(hover).clicked() {
+                                        if staged {
+                                            unstage = Some(file.path.clone());
+                                        } else {
+                                            stage = Some(file.path.clone());
+                                        }
+                                    }
+                                    ui.monospace(change.map_or(" ", Change::letter));
+                                    let selected = self.selected.as_ref() == Some(&(file.path.clone(), staged));
+                                    let name = file.path.display().to_string();
+                                    if ui.selectable_label(selected, name).clicked() {
+                                        select = Some((file.path.clone(), staged));
+                                    }
+                                });
+                            }
+                        }
+                    });
+
+                if let (Some(repo), Some((path, staged))) = (&self.repo, &self.selected) {
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        ui.label(if *staged { "Staged diff" } else { "Working tree diff" });
+                        if ui.small_button("Open File").clicked() {
+                            action = Some(ScmAction::Open(repo.root.join(path)));
+                        }
+                    });
+                    egui::ScrollArea::both().id_salt("scm_diff").auto_shrink([false, false]).show(ui, |ui| {
+                        show_diff(ui, &self.diff);
+                    });
+                }
+            });
+
+        if let Some(path) = stage {
+            self.apply(|repo| repo.stage(&path));
+        }
+        if let Some(path) = unstage {
+            self.apply(|repo| repo.unstage(&path));
+        }
+        if let Some(selected) = select {
+            self.selected = Some(selected);
+            self.load_diff();
+        }
+        if commit {
+            action = self.commit().or(action);
+        }
+        action
+    }
+}
@@ -0,0 +1,75 @@
+use eframe::egui;
+
+use crate::editor::{Editor, Position};
+use crate::stats::Stats;
+
+type SelectionKey = (u64, Vec<(Position, Option<Position>)>);
+/// A row of the statistics window: its label and how to read the value.
+type Row = (&'static str, fn(&Stats) -> String);
+
+/// Statistics of the whole buffer, recomputed only when its version changes.
+pub fn document(ctx: &egui::Context, editor: &Editor) -> Stats {
+    let id = egui::Id::new("document_statistics");
+    if let Some((version, stats)) = ctx.data(|d| d.get_temp::<(u64, Stats)>(id)) {
+        if version == editor.version {
+            return stats;
+        }
+    }
+    let stats = editor.statistics();
+    ctx.data_mut(|d| d.insert_temp(id, (editor.version, stats)));
+    stats
+}
+
+/// Statistics of the selections, recomputed only when they or the buffer
+/// change.
+pub fn selection(ctx: &egui::Context, editor: &Editor) -> Option<Stats> {
+    let id = egui::Id::new("selection_statistics");
+    let key: SelectionKey = (editor.version, editor.cursors.iter().map(|c| (c.pos, c.anchor)).collect());
+    if let Some((cached, stats)) = ctx.data(|d| d.get_temp::<(SelectionKey, Option<Stats>)>(id)) {
+        if cached == key {
+            return stats;
+        }
+    }
+    let stats = editor.selection_statistics();
+    ctx.data_mut(|d| d.insert_temp(id, (key, stats)));
+    stats
+}
+
+/// Window with character, word, line and paragraph counts for the buffer,
+/// and for the selection when there is one.
+pub fn show(ctx: &egui::Context, open: &mut bool, editor: &Editor) {
+    let document = document(ctx, editor);
+    let selection = selection(ctx, editor);
+    egui::Window::new("Document Statistics")
+        .open(open)
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::RIGHT_TOP, [-12.0, 48.0])
+        .show(ctx, |ui| {
+            egui::Grid::new("statistics").num_columns(3).spacing([24.0, 6.0]).striped(true).show(ui, |ui| {
+                ui.label("");
+                ui.strong("Document");
+                if selection.is_some() {
+                    ui.strong("Selection");
+                }
+                ui.end_row();
+
+                let rows: [Row; 6] = [
+                    ("Characters", |s| s.chars.to_string()),
+                    ("Characters (no spaces)", |s| s.chars_no_spaces.to_string()),
+                    ("Words", |s| s.words.to_string()),
+                    ("Lines", |s| s.lines.to_string()),
+                    ("Paragraphs", |s| s.paragraphs.to_string()),
+                    ("Reading time", |s| format!("{} min", s.reading_minutes())),
+                ];
+                for (label, value) in rows {
+                    ui.label(label);
+                    ui.label(value(&document));
+                    if let Some(selection) = &selection {
+                        ui.label(value(selection));
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+}
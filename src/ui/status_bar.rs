@@ -1,12 +1,25 @@
 use eframe::egui;
 
-use crate::editor::Editor;
+use crate::activity::Activities;
+use crate::editor::{Editor, Encoding, LineEnding};
+use crate::commands::CommandId;
+use crate::syntax;
+use crate::ui::statistics;
 
 const BAR_HEIGHT: f32 = 24.0;
 const BAR_BG: egui::Color32 = egui::Color32::from_rgb(0, 122, 204);
+const BAR_HOVER_BG: egui::Color32 = egui::Color32::from_rgb(30, 142, 224);
 const BAR_TEXT: egui::Color32 = egui::Color32::WHITE;
 
-pub fn show(ui: &mut egui::Ui, editor: &Editor) {
+fn segment_text(text: impl Into<String>) -> egui::RichText {
+    egui::RichText::new(text).color(BAR_TEXT).size(12.0)
+}
+
+/// Renders the status bar. Segments with pickers change the editor directly;
+/// segments bound to a command return it. With `statistics` every file
+/// shows its counts, otherwise only prose shows a word count. Background
+/// `activities` show with a spinner, and a cancel button where they allow it.
+pub fn show(ui: &mut egui::Ui, editor: &mut Editor, statistics: bool, activities: &Activities) -> Option<CommandId> {
     let rect = ui.available_rect_before_wrap();
     let bar_rect = egui::Rect::from_min_size(
         egui::Pos2::new(rect.left(), rect.bottom() - BAR_HEIGHT),
@@ -16,7 +29,20 @@ pub fn show(ui: &mut egui::Ui, editor: &Editor) {
     ui.painter().rect_filled(bar_rect, 0.0, BAR_BG);
     ui.allocate_rect(bar_rect, egui::Sense::hover());
 
-    let primary = &editor.cursors[0];
+    let mut command = None;
+    let mut bar = ui.new_child(
+        egui::UiBuilder::new()
+            .max_rect(bar_rect.shrink2(egui::vec2(8.0, 0.0)))
+            .layout(egui::Layout::left_to_right(egui::Align::Center)),
+    );
+    // Frameless segments that light up on hover
+    let widgets = &mut bar.style_mut().visuals.widgets;
+    widgets.inactive.weak_bg_fill = egui::Color32::TRANSPARENT;
+    widgets.inactive.bg_stroke = egui::Stroke::NONE;
+    widgets.hovered.weak_bg_fill = BAR_HOVER_BG;
+    widgets.hovered.bg_stroke = egui::Stroke::NONE;
+    widgets.active.weak_bg_fill = BAR_HOVER_BG;
+    widgets.open.weak_bg_fill = BAR_HOVER_BG;
 
     // Left side: file info
     let file_info = if let Some(path) = &editor.file_path {
@@ -24,34 +50,140 @@ pub fn show(ui: &mut egui::Ui, editor: &Editor) {
     } else {
         "Untitled".into()
     };
-
     let modified_marker = if editor.modified { " [Modified]" } else { "" };
+    bar.label(segment_text(format!("{}{}", file_info, modified_marker)));
+    if editor.deleted_on_disk {
+        bar.label(segment_text("[Deleted on Disk]"))
+            .on_hover_text("Save to write it again, or Save As to put it somewhere else");
+    }
+    if let Some(encryption) = &editor.encryption {
+        bar.label(segment_text(format!("\u{1f512} {}", encryption.kind.name())))
+            .on_hover_text("Encrypted: decrypted in memory only, encrypted again on save");
+    }
 
-    ui.painter().text(
-        egui::Pos2::new(bar_rect.left() + 12.0, bar_rect.center().y),
-        egui::Align2::LEFT_CENTER,
-        format!("{}{}", file_info, modified_marker),
-        egui::FontId::proportional(12.0),
-        BAR_TEXT,
-    );
+    // Right side, laid out right to left
+    bar.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+        let mixed = editor.has_mixed_line_endings();
+        let label = if mixed { "Mixed" } else { editor.line_ending.label() };
+        let endings = ui.menu_button(segment_text(label), |ui| {
+            for ending in [LineEnding::Lf, LineEnding::CrLf] {
+                if ui
+                    .selectable_label(!mixed && editor.line_ending == ending, ending.label())
+                    .clicked()
+                {
+                    editor.set_line_ending(ending);
+                    ui.close_menu();
+                }
+            }
+        });
+        if mixed {
+            endings.response.on_hover_text("Each line keeps its LF or CRLF ending; pick one to use it for all");
+        }
 
-    // Right side: cursor position + cursor count
-    let cursor_info = if editor.cursors.len() > 1 {
-        format!(
-            "Ln {}, Col {} ({} cursors)",
-            primary.pos.line + 1,
-            primary.pos.col + 1,
-            editor.cursors.len()
-        )
-    } else {
-        format!("Ln {}, Col {}", primary.pos.line + 1, primary.pos.col + 1)
-    };
+        ui.menu_button(segment_text(editor.encoding.label()), |ui| {
+            for encoding in [Encoding::Utf8, Encoding::Utf8Bom] {
+                if ui
+                    .selectable_label(editor.encoding == encoding, encoding.label())
+                    .clicked()
+                {
+                    if editor.encoding != encoding {
+                        editor.encoding = encoding;
+                        editor.modified = true;
+                    }
+                    ui.close_menu();
+                }
+            }
+        });
 
-    ui.painter().text(
-        egui::Pos2::new(bar_rect.right() - 12.0, bar_rect.center().y),
-        egui::Align2::RIGHT_CENTER,
-        cursor_info,
-        egui::FontId::proportional(12.0),
-        BAR_TEXT,
-    );
+        let indent = ui.menu_button(segment_text(editor.indent.label()), |ui| {
+            let before = editor.indent;
+            ui.radio_value(&mut editor.indent.use_tabs, false, "Indent Using Spaces");
+            ui.radio_value(&mut editor.indent.use_tabs, true, "Indent Using Tabs");
+            ui.separator();
+            for width in [2, 3, 4, 8] {
+                if ui
+                    .selectable_label(editor.indent.width == width, format!("Width: {}", width))
+                    .clicked()
+                {
+                    editor.indent.width = width;
+                    ui.close_menu();
+                }
+            }
+            if editor.indent != before {
+                editor.indent_detected = false;
+            }
+        });
+        if editor.indent_detected {
+            indent.response.on_hover_text("Detected from the file's contents");
+        }
+
+        if editor.overwrite
+            && ui
+                .button(segment_text("OVR"))
+                .on_hover_text("Overwrite mode (Insert to toggle)")
+                .clicked()
+        {
+            editor.overwrite = false;
+        }
+
+        let primary = editor.cursors[0].pos;
+        let cursor_info = if editor.cursors.len() > 1 {
+            format!(
+                "Ln {}, Col {} ({} cursors)",
+                primary.line + 1,
+                primary.col + 1,
+                editor.cursors.len()
+            )
+        } else {
+            format!("Ln {}, Col {}", primary.line + 1, primary.col + 1)
+        };
+        if ui.button(segment_text(cursor_info)).clicked() {
+            command = Some(CommandId::GoToLine);
+        }
+
+        if statistics {
+            let stats = statistics::document(ui.ctx(), editor);
+            let text = format!("{} words, {} chars, {} lines", stats.words, stats.chars, stats.lines);
+            if ui.button(segment_text(text)).on_hover_text("Document Statistics").clicked() {
+                command = Some(CommandId::ShowStatistics);
+            }
+        } else if syntax::is_prose(editor.file_path.as_deref()) {
+            // Word counts only make sense for prose
+            let words = statistics::document(ui.ctx(), editor).words;
+            ui.label(segment_text(format!("{} words", words)));
+        }
+
+        if statistics {
+            if let Some(stats) = statistics::selection(ui.ctx(), editor) {
+                let text = format!("{} words, {} chars selected", stats.words, stats.chars);
+                ui.label(segment_text(text));
+            }
+        } else if let Some((count, chars, lines)) = editor.selection_stats() {
+            let text = if count > 1 {
+                format!("{} selections ({} chars)", count, chars)
+            } else if lines > 1 {
+                format!("{} chars, {} lines selected", chars, lines)
+            } else {
+                format!("{} selected", chars)
+            };
+            ui.label(segment_text(text));
+        }
+
+        // The oldest background activity, and how many more are running
+        let running = activities.running();
+        if let Some(first) = running.first() {
+            if first.cancellable && ui.button(segment_text("\u{2715}")).on_hover_text("Cancel").clicked() {
+                activities.cancel(first.id);
+            }
+            let text = match running.len() {
+                1 => first.label.clone(),
+                n => format!("{} (+{} more)", first.label, n - 1),
+            };
+            let all: Vec<&str> = running.iter().map(|a| a.label.as_str()).collect();
+            ui.label(segment_text(text)).on_hover_text(all.join("\n"));
+            ui.add(egui::Spinner::new().size(12.0).color(BAR_TEXT));
+        }
+    });
+
+    command
 }
@@ -0,0 +1,132 @@
+use eframe::egui;
+
+/// Seconds an info toast stays on screen. Errors stay until dismissed.
+const INFO_TIMEOUT: f64 = 4.0;
+const TOAST_WIDTH: f32 = 320.0;
+const MAX_VISIBLE: usize = 5;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ToastLevel {
+    Info,
+    Error,
+}
+
+pub struct Toast {
+    pub level: ToastLevel,
+    pub message: String,
+    pub details: Option<String>,
+    /// Frame time when the toast was first shown.
+    created: Option<f64>,
+    expanded: bool,
+}
+
+#[derive(Default)]
+pub struct Toasts {
+    items: Vec<Toast>,
+}
+
+impl Toasts {
+    pub fn info(&mut self, message: impl Into<String>) {
+        self.push(ToastLevel::Info, message.into(), None);
+    }
+
+    pub fn error(&mut self, message: impl Into<String>, details: impl ToString) {
+        self.push(ToastLevel::Error, message.into(), Some(details.to_string()));
+    }
+
+    fn push(&mut self, level: ToastLevel, message: String, details: Option<String>) {
+        self.items.push(Toast {
+            level,
+            message,
+            details,
+            created: None,
+            expanded: false,
+        });
+        if self.items.len() > MAX_VISIBLE {
+            self.items.remove(0);
+        }
+    }
+
+    /// Render the notification stack in the bottom-right corner.
+    pub fn show(&mut self, ctx: &egui::Context) {
+        if self.items.is_empty() {
+            return;
+        }
+        let now = ctx.input(|i| i.time);
+        for toast in &mut self.items {
+            toast.created.get_or_insert(now);
+        }
+        self.items.retain(|t| {
+            t.level == ToastLevel::Error || now - t.created.unwrap_or(now) < INFO_TIMEOUT
+        });
+        // Wake up to take the next info toast down
+        let next_expiry = self
+            .items
+            .iter()
+            .filter(|t| t.level == ToastLevel::Info)
+            .filter_map(|t| t.created)
+            .map(|created| created + INFO_TIMEOUT - now)
+            .reduce(f64::min);
+        if let Some(secs) = next_expiry {
+            ctx.request_repaint_after_secs(secs as f32);
+        }
+
+        let mut dismiss: Option<usize> = None;
+        egui::Area::new(egui::Id::new("toasts"))
+            .anchor(egui::Align2::RIGHT_BOTTOM, [-12.0, -36.0])
+            .order(egui::Order::Foreground)
+            .show(ctx, |ui| {
+                ui.set_width(TOAST_WIDTH);
+                for (i, toast) in self.items.iter_mut().enumerate() {
+                    let accent = match toast.level {
+                        ToastLevel::Info => egui::Color32::from_rgb(0, 122, 204),
+                        ToastLevel::Error => egui::Color32::from_rgb(204, 60, 60),
+                    };
+                    egui::Frame::none()
+                        .fill(egui::Color32::from_rgb(40, 40, 40))
+                        .rounding(egui::Rounding::same(6.0))
+                        .stroke(egui::Stroke::new(1.0, accent))
+                        .inner_margin(egui::Margin::same(8.0))
+                        .show(ui, |ui| {
+                            ui.set_width(TOAST_WIDTH - 16.0);
+                            ui.horizontal(|ui| {
+                                ui.label(
+                                    egui::RichText::new(&toast.message)
+                                        .color(egui::Color32::WHITE)
+                                        .size(13.0),
+                                );
+                                ui.with_layout(
+                                    egui::Layout::right_to_left(egui::Align::Center),
+                                    |ui| {
+                                        if ui.small_button("\u{2715}").clicked() {
+                                            dismiss = Some(i);
+                                        }
+                                        if toast.details.is_some() {
+                                            let label = if toast.expanded { "Less" } else { "Details" };
+                                            if ui.small_button(label).clicked() {
+                                                toast.expanded = !toast.expanded;
+                                            }
+                                        }
+                                    },
+                                );
+                            });
+                            if toast.expanded {
+                                if let Some(details) = &toast.details {
+                                    ui.label(
+                                        egui::RichText::new(details)
+                                            .monospace()
+                                            .color(egui::Color32::from_rgb(180, 180, 180))
+                                            .size(11.0),
+                                    );
+                                }
+                            }
+                        });
+                    ui.add_space(6.0);
+                }
+            });
+
+        if let Some(i) = dismiss {
+            self.items.remove(i);
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use eframe::egui;
+
+use crate::todos::{TodoGroup, TodoSource};
+
+fn tag_color(tag: &str) -> egui::Color32 {
+    match tag {
+        "FIXME" | "XXX" => egui::Color32::from_rgb(230, 110, 100),
+        "HACK" => egui::Color32::from_rgb(230, 170, 60),
+        _ => egui::Color32::from_rgb(100, 160, 220),
+    }
+}
+
+/// Bottom window listing `TODO`, `FIXME`, `HACK` and `XXX` comments,
+/// grouped by file. `include_workspace` is its checkbox for files that
+/// aren't open. Returns where a clicked row points.
+pub fn show(
+    ctx: &egui::Context,
+    open: &mut bool,
+    groups: &[TodoGroup],
+    include_workspace: &mut bool,
+    scanning: bool,
+) -> Option<(TodoSource, usize, usize)> {
+    let mut jump = None;
+    egui::Window::new("TODOs")
+        .open(open)
+        .default_width(640.0)
+        .default_height(240.0)
+        .anchor(egui::Align2::RIGHT_BOTTOM, [-12.0, -36.0])
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let count: usize = groups.iter().map(|g| g.todos.len()).sum();
+                ui.label(format!("{} in {} files", count, groups.len()));
+                ui.checkbox(include_workspace, "Workspace files");
+                if scanning {
+                    ui.spinner();
+                }
+            });
+            ui.separator();
+            egui::ScrollArea::vertical().auto_shrink([false, false]).show(ui, |ui| {
+                if groups.is_empty() {
+                    ui.weak("No TODO comments found.");
+                }
+                for group in groups {
+                    ui.strong(&group.name);
+                    for todo in group.todos.iter() {
+                        ui.horizontal(|ui| {
+                            ui.add_space(12.0);
+                            ui.colored_label(tag_color(todo.tag), todo.tag);
+                            let text = format!("{}  {}", todo.line + 1, todo.text);
+                            if ui.selectable_label(false, text).clicked() {
+                                jump = Some((group.source.clone(), todo.line, todo.col));
+                            }
+                        });
+                    }
+                }
+            });
+        });
+    jump
+}
@@ -0,0 +1,44 @@
+use eframe::egui;
+
+use crate::editor::Editor;
+use crate::ui::time_ago;
+
+/// Side window listing every undo state of `editor`, newest first. States
+/// off the current undo/redo path (abandoned branches) are dimmed; clicking
+/// any row restores it. Returns true if the buffer changed.
+pub fn show(ctx: &egui::Context, open: &mut bool, editor: &mut Editor) -> bool {
+    let mut restore = None;
+    egui::Window::new("Undo History")
+        .open(open)
+        .default_width(340.0)
+        .default_height(360.0)
+        .anchor(egui::Align2::RIGHT_TOP, [-12.0, 48.0])
+        .show(ctx, |ui| {
+            let history = editor.undo_history();
+            let current = history.current();
+            if current.is_none() {
+                ui.weak("Unsaved edits since the last state; picking one keeps them as a branch.");
+                ui.separator();
+            }
+            egui::ScrollArea::vertical().auto_shrink([false, false]).show(ui, |ui| {
+                for (id, node) in history.nodes().iter().enumerate().rev() {
+                    let label = format!("{}  {}", time_ago(node.time), history.describe(id));
+                    let text = if history.on_active_branch(id) {
+                        egui::RichText::new(label)
+                    } else {
+                        egui::RichText::new(format!("\u{2387} {}", label)).weak()
+                    };
+                    if ui.selectable_label(current == Some(id), text).clicked() {
+                        restore = Some(id);
+                    }
+                }
+            });
+        });
+    match restore {
+        Some(id) => {
+            editor.restore_undo_state(id);
+            true
+        }
+        None => false,
+    }
+}
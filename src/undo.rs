@@ -0,0 +1,481 @@
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::SystemTime;
+
+use ropey::iter::Chunks;
+use ropey::Rope;
+
+use crate::editor::Cursor;
+
+/// Memory a buffer's undo history may use before the oldest states go.
+pub const DEFAULT_MEMORY_BUDGET: usize = 64 * 1024 * 1024;
+/// The newest states keep their text uncompressed, since undo and redo
+/// mostly step through those.
+const KEEP_UNCOMPRESSED: usize = 50;
+/// Changes smaller than this are not worth compressing.
+const MIN_COMPRESS_BYTES: usize = 256;
+
+static MEMORY_BUDGET: AtomicUsize = AtomicUsize::new(DEFAULT_MEMORY_BUDGET);
+
+/// Set the per-buffer undo memory budget in bytes.
+pub fn set_memory_budget(bytes: usize) {
+    MEMORY_BUDGET.store(bytes, Ordering::Relaxed);
+}
+
+/// Buffer state at one point of the undo history.
+#[derive(Clone)]
+pub struct Snapshot {
+    pub rope: Rope,
+    pub cursors: Vec<Cursor>,
+}
+
+/// Text removed or inserted by a change, deflated once it gets old.
+enum Text {
+    Plain(String),
+    Compressed(Vec<u8>),
+}
+
+impl Text {
+    fn get(&self) -> Cow<'_, str> {
+        match self {
+            Text::Plain(s) => Cow::Borrowed(s),
+            Text::Compressed(data) => {
+                let bytes = miniz_oxide::inflate::decompress_to_vec(data).unwrap_or_default();
+                Cow::Owned(String::from_utf8(bytes).unwrap_or_default())
+            }
+        }
+    }
+
+    fn bytes(&self) -> usize {
+        match self {
+            Text::Plain(s) => s.len(),
+            Text::Compressed(data) => data.len(),
+        }
+    }
+
+    fn compress(&mut self) {
+        if let Text::Plain(s) = self {
+            if s.len() >= MIN_COMPRESS_BYTES {
+                *self = Text::Compressed(miniz_oxide::deflate::compress_to_vec(s.as_bytes(), 6));
+            }
+        }
+    }
+}
+
+/// Replacement of `removed` by `inserted` at char `start`.
+struct Delta {
+    start: usize,
+    removed: Text,
+    inserted: Text,
+}
+
+impl Delta {
+    fn apply(&self, rope: &mut Rope) {
+        replace(rope, self.start, &self.removed.get(), &self.inserted.get());
+    }
+
+    fn revert(&self, rope: &mut Rope) {
+        replace(rope, self.start, &self.inserted.get(), &self.removed.get());
+    }
+}
+
+fn replace(rope: &mut Rope, start: usize, old: &str, new: &str) {
+    rope.remove(start..start + old.chars().count());
+    rope.insert(start, new);
+}
+
+/// One recorded state, stored as the change from its parent. Parents
+/// always come before their children.
+pub struct UndoNode {
+    pub parent: Option<usize>,
+    pub time: SystemTime,
+    /// Child that redo returns to: the newest, or the one last undone from.
+    last_child: Option<usize>,
+    /// Empty at the root.
+    delta: Delta,
+    cursors: Vec<Cursor>,
+    /// Preview of the change for the history panel.
+    summary: String,
+    /// Name of the command that made the change, for one made by a command.
+    label: Option<String>,
+}
+
+impl UndoNode {
+    fn bytes(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + self.delta.removed.bytes()
+            + self.delta.inserted.bytes()
+            + self.cursors.len() * std::mem::size_of::<Cursor>()
+            + self.summary.len()
+            + self.label.as_ref().map_or(0, String::len)
+    }
+}
+
+/// Undo history as a tree: editing after an undo starts a new branch
+/// instead of discarding the states that were undone. Only the current
+/// state's text is kept whole; other states are reached by replaying the
+/// changes along the path to them.
+pub struct UndoTree {
+    nodes: Vec<UndoNode>,
+    /// Node the buffer was at when last recorded or restored.
+    current: usize,
+    /// Text of `current`.
+    text: Rope,
+    /// The buffer has been edited since `current` was recorded.
+    pending: bool,
+    /// Memory used by `nodes`.
+    bytes: usize,
+    /// Label for the pending change, given to its node once recorded.
+    pending_label: Option<String>,
+}
+
+impl UndoTree {
+    pub fn new(initial: Snapshot) -> Self {
+        let root = UndoNode {
+            parent: None,
+            time: SystemTime::now(),
+            last_child: None,
+            delta: Delta {
+                start: 0,
+                removed: Text::Plain(String::new()),
+                inserted: Text::Plain(String::new()),
+            },
+            cursors: initial.cursors,
+            summary: String::new(),
+            label: None,
+        };
+        Self {
+            bytes: root.bytes(),
+            nodes: vec![root],
+            current: 0,
+            text: initial.rope,
+            pending: false,
+            pending_label: None,
+        }
+    }
+
+    /// Called before an edit with the buffer's state at that moment.
+    pub fn record(&mut self, state: Snapshot) {
+        if self.pending {
+            self.push(state);
+        }
+        self.pending = true;
+    }
+
+    /// True if the buffer has changed since the last recorded state.
+    pub fn has_pending_edit(&self) -> bool {
+        self.pending
+    }
+
+    /// Name the pending change, like "Replace All".
+    pub fn label_pending(&mut self, label: &str) {
+        if self.pending {
+            self.pending_label = Some(label.to_string());
+        }
+    }
+
+    /// Forget the pending change if `buffer` is still the recorded text,
+    /// so a command that changed nothing leaves no empty step behind.
+    pub fn drop_unchanged(&mut self, buffer: &Rope) {
+        let (start, old_end, new_end) = changed_chars(&self.text, buffer);
+        if self.pending && start == old_end && start == new_end {
+            self.pending = false;
+            self.pending_label = None;
+        }
+    }
+
+    /// Label of the change undo would revert, if a command made it.
+    pub fn undo_label(&self) -> Option<&str> {
+        if self.pending {
+            return self.pending_label.as_deref();
+        }
+        self.nodes[self.current].parent?;
+        self.nodes[self.current].label.as_deref()
+    }
+
+    /// Label of the change redo would make again, if a command made it.
+    pub fn redo_label(&self) -> Option<&str> {
+        if self.pending {
+            return None;
+        }
+        let child = self.nodes[self.current].last_child?;
+        self.nodes[child].label.as_deref()
+    }
+
+    /// Step back one state. `buffer` is the current state, kept so redo (or
+    /// the history panel) can return to it.
+    pub fn undo(&mut self, buffer: Snapshot) -> Option<Snapshot> {
+        if self.pending {
+            self.push(buffer);
+            self.pending = false;
+        } else {
+            let parent = self.nodes[self.current].parent?;
+            self.nodes[parent].last_child = Some(self.current);
+        }
+        self.nodes[self.current].parent?;
+        self.step_up();
+        Some(self.snapshot())
+    }
+
+    /// Step forward along the branch last undone from.
+    pub fn redo(&mut self) -> Option<Snapshot> {
+        if self.pending {
+            return None;
+        }
+        let child = self.nodes[self.current].last_child?;
+        self.step_down(child);
+        Some(self.snapshot())
+    }
+
+    /// Go straight to any recorded state, on any branch.
+    pub fn jump_to(&mut self, id: usize, buffer: Snapshot) -> Option<Snapshot> {
+        if id >= self.nodes.len() {
+            return None;
+        }
+        if self.pending {
+            self.push(buffer);
+            self.pending = false;
+        }
+        // Path from the target up to the root; point redo along it
+        let mut path = vec![id];
+        while let Some(parent) = self.nodes[*path.last().unwrap()].parent {
+            self.nodes[parent].last_child = Some(*path.last().unwrap());
+            path.push(parent);
+        }
+        // Climb to the first common ancestor, then walk down to the target
+        while !path.contains(&self.current) {
+            self.step_up();
+        }
+        let common = path.iter().position(|&n| n == self.current).unwrap_or(0);
+        for &node in path[..common].iter().rev() {
+            self.step_down(node);
+        }
+        Some(self.snapshot())
+    }
+
+    pub fn nodes(&self) -> &[UndoNode] {
+        &self.nodes
+    }
+
+    /// The node the buffer is at, unless it has been edited since.
+    pub fn current(&self) -> Option<usize> {
+        (!self.pending).then_some(self.current)
+    }
+
+    /// True if `id` is on the path undo and redo walk: an ancestor of the
+    /// current node or on its redo chain.
+    pub fn on_active_branch(&self, id: usize) -> bool {
+        let mut node = Some(self.current);
+        while let Some(n) = node {
+            if n == id {
+                return true;
+            }
+            node = self.nodes[n].parent;
+        }
+        let mut node = self.nodes[self.current].last_child;
+        while let Some(n) = node {
+            if n == id {
+                return true;
+            }
+            node = self.nodes[n].last_child;
+        }
+        false
+    }
+
+    /// What changed from a node's parent: the command that changed it, if
+    /// any, and the first changed line.
+    pub fn describe(&self, id: usize) -> String {
+        let node = &self.nodes[id];
+        match (node.parent, &node.label) {
+            (None, _) => "Original".into(),
+            (Some(_), Some(label)) => format!("{} \u{2014} {}", label, node.summary),
+            (Some(_), None) => node.summary.clone(),
+        }
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            rope: self.text.clone(),
+            cursors: self.nodes[self.current].cursors.clone(),
+        }
+    }
+
+    fn step_up(&mut self) {
+        let node = &self.nodes[self.current];
+        if let Some(parent) = node.parent {
+            node.delta.revert(&mut self.text);
+            self.current = parent;
+        }
+    }
+
+    fn step_down(&mut self, child: usize) {
+        self.nodes[child].delta.apply(&mut self.text);
+        self.current = child;
+    }
+
+    fn push(&mut self, state: Snapshot) {
+        let delta = diff(&self.text, &state.rope);
+        let line = state.rope.char_to_line(delta.start.min(state.rope.len_chars()));
+        let preview: String = state.rope.line(line).to_string().trim().chars().take(48).collect();
+        let node = UndoNode {
+            parent: Some(self.current),
+            time: SystemTime::now(),
+            last_child: None,
+            delta,
+            cursors: state.cursors,
+            summary: format!("Line {}: {}", line + 1, preview),
+            label: self.pending_label.take(),
+        };
+        self.bytes += node.bytes();
+        self.nodes.push(node);
+        let id = self.nodes.len() - 1;
+        self.nodes[self.current].last_child = Some(id);
+        self.current = id;
+        self.text = state.rope;
+
+        if let Some(old) = id.checked_sub(KEEP_UNCOMPRESSED) {
+            let node = &mut self.nodes[old];
+            let before = node.bytes();
+            node.delta.removed.compress();
+            node.delta.inserted.compress();
+            self.bytes = self.bytes - before + node.bytes();
+        }
+        let budget = MEMORY_BUDGET.load(Ordering::Relaxed);
+        while self.bytes > budget && self.drop_oldest() {}
+    }
+
+    /// Re-root the tree at the root's child toward the current node,
+    /// dropping the old root and every branch off it. Returns false if the
+    /// current node is the root.
+    fn drop_oldest(&mut self) -> bool {
+        let mut new_root = self.current;
+        while let Some(parent) = self.nodes[new_root].parent {
+            if parent == 0 {
+                break;
+            }
+            new_root = parent;
+        }
+        if new_root == 0 {
+            return false;
+        }
+        // Parents precede children, so one forward pass finds the subtree
+        let mut remap = vec![None; self.nodes.len()];
+        let mut next = 0;
+        for i in new_root..self.nodes.len() {
+            let keep = i == new_root || self.nodes[i].parent.is_some_and(|p| remap[p].is_some());
+            if keep {
+                remap[i] = Some(next);
+                next += 1;
+            }
+        }
+        let nodes = std::mem::take(&mut self.nodes);
+        self.nodes = nodes
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| remap[*i].is_some())
+            .map(|(i, mut node)| {
+                if i == new_root {
+                    // Its change was relative to the dropped root
+                    node.parent = None;
+                    node.delta = Delta {
+                        start: 0,
+                        removed: Text::Plain(String::new()),
+                        inserted: Text::Plain(String::new()),
+                    };
+                } else {
+                    node.parent = node.parent.and_then(|p| remap[p]);
+                }
+                node.last_child = node.last_child.and_then(|c| remap[c]);
+                node
+            })
+            .collect();
+        self.current = remap[self.current].unwrap_or(0);
+        self.bytes = self.nodes.iter().map(UndoNode::bytes).sum();
+        true
+    }
+}
+
+/// The change turning `old` into `new`: everything between their common
+/// prefix and suffix.
+fn diff(old: &Rope, new: &Rope) -> Delta {
+    let (start, old_end, new_end) = changed_chars(old, new);
+    Delta {
+        start,
+        removed: Text::Plain(old.slice(start..old_end).to_string()),
+        inserted: Text::Plain(new.slice(start..new_end).to_string()),
+    }
+}
+
+/// The char range that differs between `old` and `new`, as its start and
+/// its end in each. Chunks the two ropes still share are skipped by
+/// address, so an edit costs a pass over chunk boundaries, not the text.
+pub fn changed_chars(old: &Rope, new: &Rope) -> (usize, usize, usize) {
+    let prefix = common_bytes(old.chunks(), new.chunks(), false);
+    let max_suffix = old.len_bytes().min(new.len_bytes()) - prefix;
+    let suffix = common_bytes(
+        reversed(old.chunks_at_byte(old.len_bytes()).0),
+        reversed(new.chunks_at_byte(new.len_bytes()).0),
+        true,
+    )
+    .min(max_suffix);
+
+    // Snap to char boundaries: the prefix ends at the char containing its
+    // last common byte, the suffix starts at the next char start
+    let start = old.byte_to_char(prefix);
+    let end_char = |rope: &Rope| {
+        let end = rope.len_bytes() - suffix;
+        let c = rope.byte_to_char(end);
+        if rope.char_to_byte(c) == end { c } else { c + 1 }
+    };
+    (start, end_char(old).max(start), end_char(new).max(start))
+}
+
+/// Chunks from the end of the rope backwards.
+fn reversed(mut chunks: Chunks<'_>) -> impl Iterator<Item = &str> {
+    std::iter::from_fn(move || chunks.prev())
+}
+
+/// Length in bytes of the common prefix of two chunk sequences, or of the
+/// common suffix when the chunks come in reverse.
+fn common_bytes<'a>(mut a: impl Iterator<Item = &'a str>, mut b: impl Iterator<Item = &'a str>, from_end: bool) -> usize {
+    let (mut ca, mut cb) = (a.next().unwrap_or("").as_bytes(), b.next().unwrap_or("").as_bytes());
+    let mut common = 0;
+    loop {
+        if ca.is_empty() {
+            match a.next() {
+                Some(c) => ca = c.as_bytes(),
+                None => return common,
+            }
+            continue;
+        }
+        if cb.is_empty() {
+            match b.next() {
+                Some(c) => cb = c.as_bytes(),
+                None => return common,
+            }
+            continue;
+        }
+        let n = ca.len().min(cb.len());
+        let (sa, sb) = if from_end {
+            (&ca[ca.len() - n..], &cb[cb.len() - n..])
+        } else {
+            (&ca[..n], &cb[..n])
+        };
+        if !std::ptr::eq(sa, sb) && sa != sb {
+            let same = if from_end {
+                sa.iter().rev().zip(sb.iter().rev()).take_while(|(x, y)| x == y).count()
+            } else {
+                sa.iter().zip(sb).take_while(|(x, y)| x == y).count()
+            };
+            return common + same;
+        }
+        common += n;
+        if from_end {
+            ca = &ca[..ca.len() - n];
+            cb = &cb[..cb.len() - n];
+        } else {
+            ca = &ca[n..];
+            cb = &cb[n..];
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::editor::Editor;
+
+/// Command-line flag that opens a file and quits once its tab is closed,
+/// e.g. as `$EDITOR` or git's `core.editor`.
+pub const WAIT_FLAG: &str = "--wait";
+
+/// The file given with --wait was closed, or the editor quit, without its
+/// latest changes written to it.
+static FAILED: AtomicBool = AtomicBool::new(false);
+
+/// Whether waiting for the file given with --wait failed, for the process
+/// to exit with a failure code.
+pub fn failed() -> bool {
+    FAILED.load(Ordering::Relaxed)
+}
+
+pub fn set_failed(failed: bool) {
+    FAILED.store(failed, Ordering::Relaxed);
+}
+
+/// The tab holding the file given with --wait. It is followed by editor
+/// id, so Save As or moving the file doesn't lose it, but only writes to
+/// the original path count as saving it.
+pub struct WaitedFile {
+    path: PathBuf,
+    editor: u64,
+    /// The file held the tab's text when the tab was last seen.
+    saved: bool,
+}
+
+impl WaitedFile {
+    /// Wait for `editor`, just opened on `path`.
+    pub fn new(path: PathBuf, editor: &Editor) -> Self {
+        Self { path, editor: editor.id, saved: true }
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    pub fn is_tab(&self, editor: &Editor) -> bool {
+        editor.id == self.editor
+    }
+
+    /// Note the state of the waited tab. Returns false once it is no
+    /// longer among `editors`. Call before the tab is closed, too, so a
+    /// save made while closing it counts.
+    pub fn observe(&mut self, editors: &[Editor]) -> bool {
+        let Some(editor) = editors.iter().find(|e| self.is_tab(e)) else {
+            return false;
+        };
+        // After Save As the file keeps whatever it held when the tab left it
+        if editor.file_path.as_ref() == Some(&self.path) {
+            self.saved = !editor.modified;
+        }
+        true
+    }
+
+    /// Whether the file is missing the tab's latest text: it has unsaved
+    /// changes, was never created, or was renamed or moved away.
+    pub fn discarded(&self) -> bool {
+        !self.saved || !self.path.exists()
+    }
+}
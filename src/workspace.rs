@@ -0,0 +1,268 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde::Deserialize;
+
+use crate::editor::Editor;
+
+/// Project settings file, relative to the workspace root.
+pub const SETTINGS_FILE: &str = ".lux-edit/settings.toml";
+
+/// Overrides read from `.lux-edit/settings.toml`. Every field is optional.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProjectSettings {
+    pub tab_width: Option<usize>,
+    pub use_tabs: Option<bool>,
+    /// Globs relative to the root, e.g. `"dist/**"` or `"*.min.js"`.
+    pub exclude: Vec<String>,
+    pub tasks: Vec<Task>,
+    pub linters: Vec<Linter>,
+    pub debug: Vec<DebugTarget>,
+}
+
+/// A shell command runnable from the palette.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Task {
+    pub name: String,
+    pub command: String,
+    /// Working directory relative to the root; defaults to the root.
+    pub cwd: Option<String>,
+}
+
+impl Task {
+    /// Run the task through the platform shell and wait for it to finish.
+    pub fn run(&self, root: &Path) -> TaskOutput {
+        let cwd = match &self.cwd {
+            Some(dir) => root.join(dir),
+            None => root.to_path_buf(),
+        };
+        match shell(&self.command).current_dir(cwd).output() {
+            Ok(out) => TaskOutput {
+                name: self.name.clone(),
+                success: out.status.success(),
+                output: format!(
+                    "{}{}",
+                    String::from_utf8_lossy(&out.stdout),
+                    String::from_utf8_lossy(&out.stderr)
+                ),
+            },
+            Err(e) => TaskOutput {
+                name: self.name.clone(),
+                success: false,
+                output: e.to_string(),
+            },
+        }
+    }
+}
+
+/// `command` run through the platform shell.
+pub(crate) fn shell(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut c = Command::new("cmd");
+        c.arg("/C").arg(command);
+        c
+    } else {
+        let mut c = Command::new("sh");
+        c.arg("-c").arg(command);
+        c
+    }
+}
+
+/// A program to debug, through a debug adapter that speaks the Debug
+/// Adapter Protocol on its standard input and output.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DebugTarget {
+    pub name: String,
+    /// Shell command starting the adapter, e.g. `"lldb-dap"`.
+    pub adapter: String,
+    #[serde(default)]
+    pub request: DebugRequest,
+    /// Arguments of the launch or attach request, passed on as they are;
+    /// which ones apply depends on the adapter.
+    #[serde(default)]
+    pub arguments: toml::Table,
+    /// Working directory of the adapter relative to the root; defaults to
+    /// the root.
+    pub cwd: Option<String>,
+}
+
+/// Whether the adapter starts the program or attaches to a running one.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DebugRequest {
+    #[default]
+    Launch,
+    Attach,
+}
+
+/// A linter run in the background after saving a file it covers.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Linter {
+    pub name: String,
+    /// Globs for the files it checks, relative to the root, e.g. `["*.rs"]`.
+    pub files: Vec<String>,
+    /// Shell command. `{file}` is replaced by the saved file's path, which
+    /// limits the results to that file.
+    pub command: String,
+    #[serde(default)]
+    pub format: LintFormat,
+    /// Working directory relative to the root; defaults to the root.
+    pub cwd: Option<String>,
+}
+
+/// How a linter reports problems.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LintFormat {
+    /// `file:line:col: message` or rustc-style lines, as for tasks.
+    #[default]
+    Text,
+    /// `cargo ... --message-format=json`.
+    CargoJson,
+    /// `eslint -f json`.
+    EslintJson,
+}
+
+impl Linter {
+    /// True if the linter covers `path`, a file under `root`.
+    pub fn matches(&self, root: &Path, path: &Path) -> bool {
+        let Ok(rel) = path.strip_prefix(root) else {
+            return false;
+        };
+        self.files
+            .iter()
+            .filter_map(|pattern| Glob::new(pattern).ok())
+            .any(|glob| glob.compile_matcher().is_match(rel))
+    }
+
+    /// Run the linter after `file` was saved and wait for it to finish.
+    pub fn run(&self, root: &Path, file: &Path) -> LintOutput {
+        let cwd = match &self.cwd {
+            Some(dir) => root.join(dir),
+            None => root.to_path_buf(),
+        };
+        let scoped = self.command.contains("{file}");
+        let command = self.command.replace("{file}", &shell_quote(file));
+        let (success, output, errors) = match shell(&command).current_dir(&cwd).output() {
+            Ok(out) => (
+                out.status.success(),
+                String::from_utf8_lossy(&out.stdout).into_owned(),
+                String::from_utf8_lossy(&out.stderr).into_owned(),
+            ),
+            Err(e) => (false, String::new(), e.to_string()),
+        };
+        LintOutput {
+            linter: self.name.clone(),
+            format: self.format,
+            cwd,
+            file: scoped.then(|| file.to_path_buf()),
+            success,
+            output,
+            errors,
+        }
+    }
+}
+
+/// `path` quoted for the platform shell.
+pub(crate) fn shell_quote(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    if cfg!(windows) {
+        format!("\"{}\"", path)
+    } else {
+        format!("'{}'", path.replace('\'', "'\\''"))
+    }
+}
+
+/// Outcome of a linter run.
+pub struct LintOutput {
+    pub linter: String,
+    pub format: LintFormat,
+    /// Directory it ran in; relative paths in its output start here.
+    pub cwd: PathBuf,
+    /// The file it checked, if it was given one; otherwise it checked the
+    /// whole project.
+    pub file: Option<PathBuf>,
+    pub success: bool,
+    /// Standard output, where the problems are reported.
+    pub output: String,
+    /// Standard error, to explain a failed run.
+    pub errors: String,
+}
+
+/// Outcome of a finished task.
+pub struct TaskOutput {
+    pub name: String,
+    pub success: bool,
+    pub output: String,
+}
+
+/// A folder opened as a project.
+pub struct Workspace {
+    pub root: PathBuf,
+    pub settings: ProjectSettings,
+    excluded: GlobSet,
+}
+
+impl Workspace {
+    /// Open `root`, reading its settings file if there is one. A missing file
+    /// means defaults; a malformed one is an error.
+    pub fn open(root: PathBuf) -> Result<Self, String> {
+        let settings_path = root.join(SETTINGS_FILE);
+        let settings = match fs::read_to_string(&settings_path) {
+            Ok(text) => toml::from_str::<ProjectSettings>(&text)
+                .map_err(|e| format!("{}: {}", settings_path.display(), e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => ProjectSettings::default(),
+            Err(e) => return Err(format!("{}: {}", settings_path.display(), e)),
+        };
+
+        let mut builder = GlobSetBuilder::new();
+        for pattern in &settings.exclude {
+            let glob = Glob::new(pattern).map_err(|e| format!("exclude pattern {:?}: {}", pattern, e))?;
+            builder.add(glob);
+        }
+        let excluded = builder.build().map_err(|e| e.to_string())?;
+
+        Ok(Self {
+            root,
+            settings,
+            excluded,
+        })
+    }
+
+    /// Folder name, for the window title.
+    pub fn name(&self) -> String {
+        self.root
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| self.root.display().to_string())
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        path.starts_with(&self.root)
+    }
+
+    /// True if `path` matches one of the project's exclude globs.
+    pub fn is_excluded(&self, path: &Path) -> bool {
+        path.strip_prefix(&self.root)
+            .is_ok_and(|rel| self.excluded.is_match(rel))
+    }
+
+    /// Apply indentation overrides to an editor for a file in this project.
+    pub fn apply_to(&self, editor: &mut Editor) {
+        if !editor.file_path.as_deref().is_some_and(|p| self.contains(p)) {
+            return;
+        }
+        if let Some(width) = self.settings.tab_width {
+            editor.indent.width = width.max(1);
+        }
+        if let Some(use_tabs) = self.settings.use_tabs {
+            editor.indent.use_tabs = use_tabs;
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ropey::Rope;
+
+use crate::editor::{Editor, Position};
+use crate::rope_search::{self, SearchOptions};
+
+/// A line in a workspace file containing the searched text.
+#[derive(Clone, Debug)]
+pub struct TextHit {
+    pub path: PathBuf,
+    /// Zero-based line.
+    pub line: usize,
+    pub text: String,
+}
+
+/// Lines of `file` containing `needle`, which must be lowercase; the
+/// search ignores case. None if the file is binary or unreadable.
+pub fn search_file(file: &Path, needle: &str) -> Option<Vec<TextHit>> {
+    let content = fs::read_to_string(file).ok()?;
+    let hits = content
+        .lines()
+        .enumerate()
+        .filter(|(_, text)| text.to_lowercase().contains(needle))
+        .map(|(line, text)| TextHit { path: file.to_path_buf(), line, text: text.trim().to_string() })
+        .collect();
+    Some(hits)
+}
+
+/// An occurrence of a whole word in a workspace file.
+#[derive(Clone, Debug)]
+pub struct WordHit {
+    pub path: PathBuf,
+    pub start: Position,
+    pub end: Position,
+    /// The line it is on.
+    pub line_text: String,
+}
+
+/// Whole-word, case-sensitive occurrences of `word` in `files`, stopping
+/// after `limit` hits. Files in `open`, with their buffers' text, are
+/// searched as they are in the buffer rather than on disk.
+pub fn find_word(files: &[PathBuf], open: &[(PathBuf, Rope)], word: &str, limit: usize) -> Vec<WordHit> {
+    let options = SearchOptions { whole_word: true, ..SearchOptions::default() };
+    let mut hits = Vec::new();
+    for file in files {
+        let loaded;
+        let rope = match open.iter().find(|(path, _)| path == file) {
+            Some((_, rope)) => rope,
+            None => {
+                // Read as opening the file would, so positions match the
+                // buffer the edits are applied to (no BOM, no '\r')
+                let Ok(editor) = Editor::from_file(file.clone()) else {
+                    continue; // binary or unreadable
+                };
+                loaded = editor.rope;
+                &loaded
+            }
+        };
+        let Ok(matches) = rope_search::search(rope, word, options, 0) else {
+            continue;
+        };
+        for range in matches {
+            let position = |idx: usize| {
+                let line = rope.char_to_line(idx);
+                Position::new(line, idx - rope.line_to_char(line))
+            };
+            let (start, end) = (position(range.start), position(range.end));
+            let line_text = rope.line(start.line).to_string().trim_end_matches(['\n', '\r']).to_string();
+            hits.push(WordHit { path: file.clone(), start, end, line_text });
+            if hits.len() >= limit {
+                return hits;
+            }
+        }
+    }
+    hits
+}
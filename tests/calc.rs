@@ -0,0 +1,65 @@
+//! Evaluate Selection's calculator: precedence, errors, and the limit on
+//! how deeply an expression may nest.
+
+use lux_edit::calc::{self, evaluate};
+
+#[test]
+fn precedence_and_associativity() {
+    assert_eq!(evaluate("1 + 2 * 3"), Ok(7.0));
+    assert_eq!(evaluate("(1 + 2) * 3"), Ok(9.0));
+    assert_eq!(evaluate("10 - 4 - 3"), Ok(3.0));
+    assert_eq!(evaluate("24 / 4 / 2"), Ok(3.0));
+    assert_eq!(evaluate("7 % 4 * 2"), Ok(6.0));
+    // Powers are right associative and bind tighter than signs
+    assert_eq!(evaluate("2 ^ 3 ^ 2"), Ok(512.0));
+    assert_eq!(evaluate("2 ** 3 * 2"), Ok(16.0));
+    assert_eq!(evaluate("-2 ^ 2"), Ok(-4.0));
+    assert_eq!(evaluate("2 ^ -1"), Ok(0.5));
+    assert_eq!(evaluate("--3"), Ok(3.0));
+}
+
+#[test]
+fn numbers_constants_and_functions() {
+    assert_eq!(evaluate("0xff + 0b101 + 0o10"), Ok(268.0));
+    assert_eq!(evaluate("1.5e3"), Ok(1500.0));
+    assert_eq!(evaluate("max(1, sqrt(16), 3)"), Ok(4.0));
+    assert_eq!(evaluate("pow(2, 10)"), Ok(1024.0));
+    assert_eq!(evaluate("PI"), Ok(std::f64::consts::PI));
+}
+
+#[test]
+fn errors() {
+    for (expr, message) in [
+        ("1 / 0", "division by zero"),
+        ("5 % 0", "division by zero"),
+        ("(1 + 2", "missing ')'"),
+        ("1 +", "unexpected end of expression"),
+        ("1 2", "unexpected '2'"),
+        ("foo", "unknown name 'foo'"),
+        ("foo(1)", "unknown function 'foo'"),
+        ("sqrt(1, 2)", "sqrt() takes one argument"),
+        ("min()", "min() needs at least one argument"),
+        ("0xg", "invalid number '0x'"),
+        ("10 ^ 400", "the result is not a finite number"),
+    ] {
+        assert_eq!(evaluate(expr), Err(message.to_string()), "{}", expr);
+    }
+}
+
+#[test]
+fn deep_nesting_is_an_error() {
+    let nested = |depth: usize| format!("{}1{}", "(".repeat(depth), ")".repeat(depth));
+    assert_eq!(evaluate(&nested(100)), Ok(1.0));
+    let too_deep = Err("the expression is nested too deeply".to_string());
+    assert_eq!(evaluate(&nested(100_000)), too_deep);
+    assert_eq!(evaluate(&"-".repeat(100_000)), too_deep);
+    assert_eq!(evaluate(&format!("{}1", "2^".repeat(100_000))), too_deep);
+    assert_eq!(evaluate(&format!("{}1)", "sqrt(".repeat(100_000))), too_deep);
+}
+
+#[test]
+fn substitute_keeps_the_equation() {
+    assert_eq!(calc::substitute("2 + 2 ="), Some("2 + 2 = 4".to_string()));
+    assert_eq!(calc::substitute("0.1 + 0.2"), Some("0.3".to_string()));
+    assert_eq!(calc::substitute("hello"), None);
+}
@@ -0,0 +1,9 @@
+//! Helpers shared by the integration tests.
+
+use tempfile::TempDir;
+
+/// A fresh directory under the system temp directory, removed with
+/// everything in it when the guard is dropped, even by a failing test.
+pub fn temp_dir() -> TempDir {
+    tempfile::Builder::new().prefix("lux-edit-").tempdir().unwrap()
+}
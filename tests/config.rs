@@ -0,0 +1,72 @@
+//! Saving from the settings window edits the config files in place.
+
+#![cfg(not(any(target_os = "macos", windows)))]
+
+mod common;
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use common::temp_dir;
+use lux_edit::config::{self, Settings};
+
+/// The config directory comes from the environment, shared by all tests.
+static CONFIG_DIR: Mutex<()> = Mutex::new(());
+
+/// Run `test` with a fresh, empty config directory.
+fn with_config_dir(test: impl FnOnce(PathBuf)) {
+    let _guard = CONFIG_DIR.lock().unwrap_or_else(|e| e.into_inner());
+    let home = temp_dir();
+    std::env::set_var("XDG_CONFIG_HOME", home.path());
+    let dir = config::config_dir();
+    fs::create_dir_all(&dir).unwrap();
+    test(dir);
+}
+
+#[test]
+fn keeps_comments_and_formatting() {
+    with_config_dir(|dir| {
+        let path = dir.join(config::SETTINGS_FILE);
+        fs::write(&path, "# My settings\nfont_size = 16.0 # big\n\n# Rulers\nrulers = [80, 100]\n").unwrap();
+        let mut settings = config::load_settings().unwrap();
+        settings.font_size = 18.0;
+
+        assert!(config::save_settings(&settings).unwrap());
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(text.starts_with("# My settings\nfont_size = 18.0 # big\n\n# Rulers\nrulers = [80, 100]\n"), "{}", text);
+        assert_eq!(config::load_settings().unwrap(), settings);
+    });
+}
+
+#[test]
+fn unchanged_settings_are_not_written() {
+    with_config_dir(|dir| {
+        let path = dir.join(config::SETTINGS_FILE);
+        assert!(config::save_settings(&Settings::default()).unwrap());
+        let written = fs::read_to_string(&path).unwrap();
+        assert!(!config::save_settings(&Settings::default()).unwrap());
+        assert_eq!(fs::read_to_string(&path).unwrap(), written);
+        // Other files aren't created by saving settings
+        assert!(!dir.join(config::THEME_FILE).exists());
+    });
+}
+
+#[test]
+fn keeps_unknown_keys_and_drops_unset_options() {
+    with_config_dir(|dir| {
+        let path = dir.join(config::SETTINGS_FILE);
+        fs::write(&path, "from_a_newer_version = true\nmin_contrast = 4.5\n\n[run]\npy = \"python3 %f\"\n").unwrap();
+        // Contrast turned off, and the run commands replaced
+        let mut settings = Settings::default();
+        settings.run.clear();
+        settings.run.insert("rb".into(), "ruby %f".into());
+
+        config::save_settings(&settings).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(text.contains("from_a_newer_version = true"), "{}", text);
+        assert!(!text.contains("min_contrast"), "{}", text);
+        assert!(!text.contains("py ="), "{}", text);
+        assert!(text.contains("rb = \"ruby %f\""), "{}", text);
+    });
+}
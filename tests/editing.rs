@@ -0,0 +1,352 @@
+//! Random sequences of editing operations, checked against invariants that
+//! must hold after every step: cursors stay inside the buffer, the rope's
+//! index conversions agree with its text, and undo/redo round-trip.
+
+use lux_edit::clipboard::ClipboardContent;
+use lux_edit::editor::{Cursor, Editor, Position};
+use proptest::prelude::*;
+
+#[derive(Clone, Debug)]
+enum Op {
+    Type(&'static str),
+    Backspace,
+    DeleteForward,
+    Newline,
+    CommentNewline,
+    Tab,
+    DeleteWordBackward,
+    DeleteWordForward,
+    DeleteSubwordBackward,
+    DeleteSubwordForward,
+    Move(Motion, bool),
+    AddCursor(usize, usize),
+    AddOccurrence(usize, usize),
+    AddCursorVertical(bool),
+    SelectNextOccurrence,
+    SelectAllOccurrences,
+    SplitSelectionIntoLines,
+    ClearExtraCursors,
+    SelectAll,
+    Cut,
+    Paste(&'static str, bool),
+    CopyPaste,
+    DuplicateLines,
+    MoveLinesUp,
+    MoveLinesDown,
+    Indent,
+    Outdent,
+    ToggleComment,
+    JoinLines,
+    Undo,
+    Redo,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Motion {
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    WordLeft,
+    WordRight,
+    SubwordLeft,
+    SubwordRight,
+    DocStart,
+    DocEnd,
+    PageUp,
+    PageDown,
+}
+
+/// Text mixing ASCII, multi-byte and astral characters, newlines and tabs.
+const SNIPPETS: &[&str] = &["a", "xy", "é", "日本", "🙂", " ", "\n", "\t", "foo bar", "a\nb\n", "  // c", "getHTTPServer_id"];
+
+fn motion() -> impl Strategy<Value = Motion> {
+    prop_oneof![
+        Just(Motion::Left),
+        Just(Motion::Right),
+        Just(Motion::Up),
+        Just(Motion::Down),
+        Just(Motion::Home),
+        Just(Motion::End),
+        Just(Motion::WordLeft),
+        Just(Motion::WordRight),
+        Just(Motion::SubwordLeft),
+        Just(Motion::SubwordRight),
+        Just(Motion::DocStart),
+        Just(Motion::DocEnd),
+        Just(Motion::PageUp),
+        Just(Motion::PageDown),
+    ]
+}
+
+/// Operations that change the text, plus motions and cursor management.
+fn edit_op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        6 => proptest::sample::select(SNIPPETS).prop_map(Op::Type),
+        3 => Just(Op::Backspace),
+        2 => Just(Op::DeleteForward),
+        2 => Just(Op::Newline),
+        1 => Just(Op::CommentNewline),
+        1 => Just(Op::Tab),
+        1 => Just(Op::DeleteWordBackward),
+        1 => Just(Op::DeleteWordForward),
+        1 => Just(Op::DeleteSubwordBackward),
+        1 => Just(Op::DeleteSubwordForward),
+        6 => (motion(), any::<bool>()).prop_map(|(m, select)| Op::Move(m, select)),
+        2 => (0..12usize, 0..12usize).prop_map(|(line, col)| Op::AddCursor(line, col)),
+        1 => (0..12usize, 0..12usize).prop_map(|(line, col)| Op::AddOccurrence(line, col)),
+        1 => any::<bool>().prop_map(Op::AddCursorVertical),
+        1 => Just(Op::SelectNextOccurrence),
+        1 => Just(Op::SelectAllOccurrences),
+        1 => Just(Op::SplitSelectionIntoLines),
+        1 => Just(Op::ClearExtraCursors),
+        1 => Just(Op::SelectAll),
+        1 => Just(Op::Cut),
+        1 => (proptest::sample::select(SNIPPETS), any::<bool>()).prop_map(|(text, linewise)| Op::Paste(text, linewise)),
+        1 => Just(Op::CopyPaste),
+        1 => Just(Op::DuplicateLines),
+        1 => Just(Op::MoveLinesUp),
+        1 => Just(Op::MoveLinesDown),
+        1 => Just(Op::Indent),
+        1 => Just(Op::Outdent),
+        1 => Just(Op::ToggleComment),
+        1 => Just(Op::JoinLines),
+    ]
+}
+
+fn any_op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        8 => edit_op(),
+        1 => Just(Op::Undo),
+        1 => Just(Op::Redo),
+    ]
+}
+
+fn initial_text() -> impl Strategy<Value = String> {
+    proptest::collection::vec(proptest::sample::select(SNIPPETS), 0..8).prop_map(|parts| parts.concat())
+}
+
+fn apply(editor: &mut Editor, op: &Op) {
+    match *op {
+        Op::Type(text) => editor.type_text(text),
+        Op::Backspace => editor.backspace(),
+        Op::DeleteForward => editor.delete_forward(),
+        Op::Newline => editor.insert_newline(),
+        // As if every line were inside a comment
+        Op::CommentNewline => editor.insert_newline_continuing(|_, _| true),
+        Op::Tab => editor.insert_tab(),
+        Op::DeleteWordBackward => editor.delete_word_backward(),
+        Op::DeleteWordForward => editor.delete_word_forward(),
+        Op::DeleteSubwordBackward => editor.delete_subword_backward(),
+        Op::DeleteSubwordForward => editor.delete_subword_forward(),
+        Op::Move(motion, select) => match motion {
+            Motion::Left => editor.move_left(select),
+            Motion::Right => editor.move_right(select),
+            Motion::Up => editor.move_up(select),
+            Motion::Down => editor.move_down(select),
+            Motion::Home => editor.move_home(select),
+            Motion::End => editor.move_end(select),
+            Motion::WordLeft => editor.move_word_left(select),
+            Motion::WordRight => editor.move_word_right(select),
+            Motion::SubwordLeft => editor.move_subword_left(select),
+            Motion::SubwordRight => editor.move_subword_right(select),
+            Motion::DocStart => editor.move_to_start(select),
+            Motion::DocEnd => editor.move_to_end(select),
+            Motion::PageUp => editor.move_page_up(select, 3),
+            Motion::PageDown => editor.move_page_down(select, 3),
+        },
+        Op::AddCursor(line, col) => editor.add_cursor_at(line, col),
+        Op::AddOccurrence(line, col) => {
+            editor.add_occurrence_at(Position::new(line, col));
+        }
+        Op::AddCursorVertical(below) => editor.add_cursor_vertical(below),
+        Op::SelectNextOccurrence => editor.select_next_occurrence(),
+        Op::SelectAllOccurrences => editor.select_all_occurrences(),
+        Op::SplitSelectionIntoLines => editor.split_selection_into_lines(),
+        Op::ClearExtraCursors => editor.clear_extra_cursors(),
+        Op::SelectAll => editor.select_all(),
+        Op::Cut => {
+            editor.cut_content();
+        }
+        Op::Paste(text, linewise) => {
+            editor.paste(&ClipboardContent { parts: vec![text.to_string()], linewise });
+        }
+        Op::CopyPaste => {
+            let content = editor.copy_content();
+            editor.paste(&content);
+        }
+        Op::DuplicateLines => editor.duplicate_lines(),
+        Op::MoveLinesUp => editor.move_lines_up(),
+        Op::MoveLinesDown => editor.move_lines_down(),
+        Op::Indent => editor.indent_lines(),
+        Op::Outdent => editor.outdent_lines(),
+        Op::ToggleComment => editor.toggle_line_comment("//"),
+        Op::JoinLines => editor.join_lines(),
+        Op::Undo => editor.undo(),
+        Op::Redo => editor.redo(),
+    }
+}
+
+/// Length of a line in chars, not counting its line break.
+fn line_len(editor: &Editor, line: usize) -> usize {
+    let text = editor.rope.line(line).to_string();
+    text.trim_end_matches(['\n', '\r']).chars().count()
+}
+
+fn check_position(editor: &Editor, pos: Position, what: &str) -> Result<(), TestCaseError> {
+    prop_assert!(
+        pos.line < editor.rope.len_lines(),
+        "{} line {} past the last line {}",
+        what,
+        pos.line,
+        editor.rope.len_lines() - 1
+    );
+    let len = line_len(editor, pos.line);
+    prop_assert!(pos.col <= len, "{} col {} past the end of line {} ({} chars)", what, pos.col, pos.line, len);
+    Ok(())
+}
+
+fn check_cursors(editor: &Editor) -> Result<(), TestCaseError> {
+    prop_assert!(!editor.cursors.is_empty(), "no cursors left");
+    for Cursor { pos, anchor, .. } in &editor.cursors {
+        check_position(editor, *pos, "cursor")?;
+        if let Some(anchor) = anchor {
+            check_position(editor, *anchor, "anchor")?;
+        }
+    }
+    Ok(())
+}
+
+/// The rope's counts and index conversions agree with its plain text.
+fn check_rope(editor: &Editor) -> Result<(), TestCaseError> {
+    let rope = &editor.rope;
+    let text = rope.to_string();
+    prop_assert_eq!(rope.len_bytes(), text.len());
+    prop_assert_eq!(rope.len_chars(), text.chars().count());
+    prop_assert_eq!(rope.len_lines(), text.matches('\n').count() + 1);
+    for (char_idx, (byte_idx, _)) in text.char_indices().enumerate() {
+        prop_assert_eq!(rope.char_to_byte(char_idx), byte_idx);
+        prop_assert_eq!(rope.byte_to_char(byte_idx), char_idx);
+    }
+    let mut line_start = 0;
+    for (line, part) in text.split('\n').enumerate() {
+        prop_assert_eq!(rope.line_to_char(line), line_start);
+        line_start += part.chars().count() + 1;
+    }
+    Ok(())
+}
+
+/// Undo until nothing changes, bounded so a stuck undo fails rather than hangs.
+fn undo_all(editor: &mut Editor, limit: usize) {
+    for _ in 0..limit {
+        let version = editor.version;
+        editor.undo();
+        if editor.version == version {
+            return;
+        }
+    }
+}
+
+fn redo_all(editor: &mut Editor, limit: usize) {
+    for _ in 0..limit {
+        let version = editor.version;
+        editor.redo();
+        if editor.version == version {
+            return;
+        }
+    }
+}
+
+// 256 cases each by default; set PROPTEST_CASES for a longer run
+proptest! {
+    #[test]
+    fn edits_keep_cursors_and_rope_consistent(
+        text in initial_text(),
+        ops in proptest::collection::vec(any_op(), 1..40),
+    ) {
+        let mut editor = Editor::from_text(None, &text);
+        for op in &ops {
+            apply(&mut editor, op);
+            check_cursors(&editor)?;
+            check_rope(&editor)?;
+        }
+    }
+
+    #[test]
+    fn undo_and_redo_round_trip(
+        text in initial_text(),
+        ops in proptest::collection::vec(edit_op(), 1..40),
+    ) {
+        let mut editor = Editor::from_text(None, &text);
+        for op in &ops {
+            apply(&mut editor, op);
+        }
+        let edited = editor.rope.to_string();
+        let limit = ops.len() + 2;
+
+        undo_all(&mut editor, limit);
+        prop_assert_eq!(editor.rope.to_string(), text.clone());
+        check_cursors(&editor)?;
+
+        redo_all(&mut editor, limit);
+        prop_assert_eq!(editor.rope.to_string(), edited.clone());
+        check_cursors(&editor)?;
+
+        // A second round trip goes through the same states
+        undo_all(&mut editor, limit);
+        prop_assert_eq!(editor.rope.to_string(), text);
+        redo_all(&mut editor, limit);
+        prop_assert_eq!(editor.rope.to_string(), edited);
+    }
+
+    #[test]
+    fn labeled_edits_undo_in_one_step(
+        text in initial_text(),
+        ops in proptest::collection::vec(edit_op(), 0..10),
+        group in proptest::collection::vec(edit_op(), 1..10),
+    ) {
+        let mut editor = Editor::from_text(None, &text);
+        for op in &ops {
+            apply(&mut editor, op);
+        }
+        let before = editor.rope.to_string();
+        editor.labeled_edit("Group", |editor| {
+            for op in &group {
+                apply(editor, op);
+            }
+        });
+        let after = editor.rope.to_string();
+        // A group that changed nothing leaves no step to undo
+        prop_assume!(after != before);
+        prop_assert_eq!(editor.undo_label(), Some("Group"));
+
+        editor.undo();
+        prop_assert_eq!(editor.rope.to_string(), before);
+        check_cursors(&editor)?;
+        prop_assert_eq!(editor.redo_label(), Some("Group"));
+        editor.redo();
+        prop_assert_eq!(editor.rope.to_string(), after);
+    }
+
+    #[test]
+    fn undo_history_states_are_restorable(
+        text in initial_text(),
+        ops in proptest::collection::vec(any_op(), 1..40),
+        picks in proptest::collection::vec(any::<prop::sample::Index>(), 1..10),
+    ) {
+        let mut editor = Editor::from_text(None, &text);
+        for op in &ops {
+            apply(&mut editor, op);
+        }
+        // Every state reached through the history panel is a valid buffer
+        for pick in picks {
+            let id = pick.index(editor.undo_history().nodes().len());
+            editor.restore_undo_state(id);
+            prop_assert_eq!(editor.undo_history().current(), Some(id));
+            check_cursors(&editor)?;
+            check_rope(&editor)?;
+        }
+    }
+}
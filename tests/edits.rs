@@ -0,0 +1,163 @@
+//! Workspace edits, as Rename Symbol and code actions make them: every
+//! file changes or none does.
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use common::temp_dir;
+use lux_edit::editor::{Editor, Position};
+use lux_edit::edits::{self, TextEdit, WorkspaceEdit};
+
+fn replace(start: (usize, usize), end: (usize, usize), text: &str) -> TextEdit {
+    TextEdit {
+        start: Position::new(start.0, start.1),
+        end: Position::new(end.0, end.1),
+        text: text.to_string(),
+        old: None,
+    }
+}
+
+fn read(path: &Path) -> String {
+    fs::read_to_string(path).unwrap()
+}
+
+#[test]
+fn applies_to_closed_files() {
+    let temp = temp_dir();
+    let dir = temp.path();
+    let (a, b) = (dir.join("a.txt"), dir.join("b.txt"));
+    fs::write(&a, "let foo = foo;\n").unwrap();
+    fs::write(&b, "foo\r\nbar\r\n").unwrap();
+    let mut edit = WorkspaceEdit::new();
+    // Out of order, and all against the text as it was
+    edit.insert(a.clone(), vec![replace((0, 10), (0, 13), "quux"), replace((0, 4), (0, 7), "quux")]);
+    edit.insert(b.clone(), vec![replace((1, 0), (1, 3), "baz")]);
+
+    let report = edits::apply(&edit, "Rename", &mut []).unwrap();
+    assert_eq!(read(&a), "let quux = quux;\n");
+    assert_eq!(read(&b), "foo\r\nbaz\r\n");
+    assert_eq!(report.summary(), "Made 3 changes in 2 files");
+}
+
+#[test]
+fn open_buffers_change_instead_of_their_files() {
+    let temp = temp_dir();
+    let dir = temp.path();
+    let path = dir.join("open.txt");
+    fs::write(&path, "one two\n").unwrap();
+    let mut editors = vec![Editor::from_file(path.clone()).unwrap()];
+    let mut edit = WorkspaceEdit::new();
+    edit.insert(path.clone(), vec![replace((0, 4), (0, 7), "three")]);
+
+    let report = edits::apply(&edit, "Rename", &mut editors).unwrap();
+    assert_eq!(report.summary(), "Made 1 change in 1 file (unsaved)");
+    assert_eq!(editors[0].rope.to_string(), "one three\n");
+    assert!(editors[0].modified);
+    assert_eq!(read(&path), "one two\n");
+    // One undo step takes it all back
+    editors[0].undo();
+    assert_eq!(editors[0].rope.to_string(), "one two\n");
+}
+
+#[test]
+fn overlapping_edits_change_nothing() {
+    let temp = temp_dir();
+    let dir = temp.path();
+    let (a, b) = (dir.join("a.txt"), dir.join("b.txt"));
+    fs::write(&a, "alpha\n").unwrap();
+    fs::write(&b, "beta gamma\n").unwrap();
+    let mut editors = vec![Editor::from_file(a.clone()).unwrap()];
+    let mut edit = WorkspaceEdit::new();
+    edit.insert(a.clone(), vec![replace((0, 0), (0, 5), "ALPHA")]);
+    edit.insert(b.clone(), vec![replace((0, 0), (0, 6), "x"), replace((0, 5), (0, 10), "y")]);
+
+    let error = edits::apply(&edit, "Rename", &mut editors).unwrap_err();
+    assert_eq!(error.path, b);
+    assert_eq!(error.message, "edits overlap on line 1");
+    assert_eq!(editors[0].rope.to_string(), "alpha\n");
+    assert!(!editors[0].modified);
+    assert_eq!(read(&b), "beta gamma\n");
+}
+
+#[test]
+fn edits_past_the_text_change_nothing() {
+    let temp = temp_dir();
+    let dir = temp.path();
+    let (a, b) = (dir.join("a.txt"), dir.join("b.txt"));
+    fs::write(&a, "short\n").unwrap();
+    fs::write(&b, "one line\n").unwrap();
+    let mut edit = WorkspaceEdit::new();
+    edit.insert(a.clone(), vec![replace((0, 0), (0, 5), "long")]);
+    edit.insert(b.clone(), vec![replace((4, 0), (4, 1), "x")]);
+
+    let error = edits::apply(&edit, "Rename", &mut []).unwrap_err();
+    assert_eq!(error.message, "line 5 has changed since the edit was made");
+    assert_eq!(read(&a), "short\n");
+}
+
+#[test]
+fn edits_made_against_other_text_change_nothing() {
+    let temp = temp_dir();
+    let dir = temp.path();
+    let (a, b) = (dir.join("a.txt"), dir.join("b.txt"));
+    fs::write(&a, "foo\n").unwrap();
+    // `foo` was renamed to `bar` after the edit was made, same length
+    fs::write(&b, "let bar = 1;\n").unwrap();
+    let mut editors = vec![Editor::from_file(a.clone()).unwrap()];
+    let mut edit = WorkspaceEdit::new();
+    edit.insert(a.clone(), vec![TextEdit { old: Some("foo".into()), ..replace((0, 0), (0, 3), "quux") }]);
+    edit.insert(b.clone(), vec![TextEdit { old: Some("foo".into()), ..replace((0, 4), (0, 7), "quux") }]);
+
+    let error = edits::apply(&edit, "Rename", &mut editors).unwrap_err();
+    assert_eq!(error.path, b);
+    assert_eq!(error.message, "line 1 has changed since the edit was made");
+    assert_eq!(editors[0].rope.to_string(), "foo\n");
+    assert_eq!(read(&b), "let bar = 1;\n");
+
+    // Matching text is replaced
+    fs::write(&b, "let foo = 1;\n").unwrap();
+    edits::apply(&edit, "Rename", &mut editors).unwrap();
+    assert_eq!(editors[0].rope.to_string(), "quux\n");
+    assert_eq!(read(&b), "let quux = 1;\n");
+}
+
+#[test]
+fn failed_write_rolls_back() {
+    let temp = temp_dir();
+    let dir = temp.path();
+    let (a, b) = (dir.join("a.txt"), dir.join("b.txt"));
+    fs::write(&a, "a\n").unwrap();
+    fs::write(&b, "b\n").unwrap();
+    // Something in the way of b's temporary file
+    fs::create_dir(dir.join(".b.txt.lux-edit-tmp")).unwrap();
+    let mut edit = WorkspaceEdit::new();
+    edit.insert(a.clone(), vec![replace((0, 0), (0, 1), "A")]);
+    edit.insert(b.clone(), vec![replace((0, 0), (0, 1), "B")]);
+
+    let error = edits::apply(&edit, "Rename", &mut []).unwrap_err();
+    assert_eq!(error.path, b);
+    assert_eq!(read(&a), "a\n");
+    assert_eq!(read(&b), "b\n");
+    assert!(!dir.join(".a.txt.lux-edit-tmp").exists());
+}
+
+#[test]
+fn closed_encrypted_files_are_refused() {
+    let temp = temp_dir();
+    let dir = temp.path();
+    let (plain, secret) = (dir.join("plain.txt"), dir.join("secret.txt.age"));
+    let armored = "-----BEGIN AGE ENCRYPTED FILE-----\nYWdlLWVuY3J5cHRpb24ub3JnL3YxCg==\n-----END AGE ENCRYPTED FILE-----\n";
+    fs::write(&plain, "plain\n").unwrap();
+    fs::write(&secret, armored).unwrap();
+    let mut edit = WorkspaceEdit::new();
+    edit.insert(plain.clone(), vec![replace((0, 0), (0, 5), "PLAIN")]);
+    edit.insert(secret.clone(), vec![replace((1, 0), (1, 4), "junk")]);
+
+    let error = edits::apply(&edit, "Rename", &mut []).unwrap_err();
+    assert_eq!(error.path, secret);
+    assert_eq!(error.message, "the file is encrypted; open it to edit it");
+    assert_eq!(read(&secret), armored);
+    assert_eq!(read(&plain), "plain\n");
+}
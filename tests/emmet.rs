@@ -0,0 +1,95 @@
+//! Emmet abbreviations: how they parse into nested elements, and the limit
+//! on how much one expansion may write.
+
+use lux_edit::emmet::{self, Expansion, Syntax};
+
+/// Expand the whole of `abbr` as HTML, indenting nested lines by two spaces.
+fn html(abbr: &str) -> Option<Expansion> {
+    emmet::expand(Syntax::Html, abbr, abbr.chars().count(), "  ")
+}
+
+fn html_text(abbr: &str) -> String {
+    html(abbr).unwrap_or_else(|| panic!("{} didn't expand", abbr)).text
+}
+
+#[test]
+fn element_with_id_classes_and_attributes() {
+    let expansion = html("a#top.nav.main[title=Home]").unwrap();
+    assert_eq!(expansion.start, 0);
+    assert_eq!(expansion.text, "<a id=\"top\" class=\"nav main\" title=\"Home\" href=\"\"></a>");
+    // The cursor goes into the first empty value
+    assert_eq!(expansion.cursor, expansion.text.find("href=\"").unwrap() + 6);
+}
+
+#[test]
+fn children_siblings_and_climbing() {
+    assert_eq!(html_text("ul>li"), "<ul>\n  <li></li>\n</ul>");
+    assert_eq!(html_text("p+p"), "<p></p>\n<p></p>");
+    assert_eq!(html_text("div>p^span"), "<div>\n  <p></p>\n</div>\n<span></span>");
+    // Climbing past the top still adds a sibling there
+    assert_eq!(html_text("div>p^^^span"), "<div>\n  <p></p>\n</div>\n<span></span>");
+}
+
+#[test]
+fn counts_number_their_copies() {
+    assert_eq!(
+        html_text("ul>li.item$*3"),
+        "<ul>\n  <li class=\"item1\"></li>\n  <li class=\"item2\"></li>\n  <li class=\"item3\"></li>\n</ul>"
+    );
+    assert_eq!(html_text("li{$$}*2"), "<li>01</li>\n<li>02</li>");
+}
+
+#[test]
+fn groups_repeat_together() {
+    assert_eq!(html_text("(dt+dd)*2"), "<dt></dt>\n<dd></dd>\n<dt></dt>\n<dd></dd>");
+    // `>` after a group nests in its last element
+    assert_eq!(html_text("(div+p)>span"), "<div></div>\n<p>\n  <span></span>\n</p>");
+}
+
+#[test]
+fn implicit_names_follow_the_parent() {
+    assert_eq!(html_text("ul>.x"), "<ul>\n  <li class=\"x\"></li>\n</ul>");
+    assert_eq!(html_text(".x"), "<div class=\"x\"></div>");
+}
+
+#[test]
+fn malformed_abbreviations_do_not_expand() {
+    for abbr in ["(div", "div)", "div*", "div*0", "div*1001", "div[title", "notatag"] {
+        assert_eq!(html(abbr), None, "{}", abbr);
+    }
+}
+
+#[test]
+fn keeps_the_line_indentation() {
+    let expansion = emmet::expand(Syntax::Html, "    ul>li", 9, "\t").unwrap();
+    assert_eq!(expansion.start, 4);
+    assert_eq!(expansion.text, "<ul>\n    \t<li></li>\n    </ul>");
+}
+
+#[test]
+fn nested_counts_are_capped_overall() {
+    // Each count is allowed, but together they'd write a million elements
+    assert_eq!(html("(div*100)*100"), None);
+    assert_eq!(html("ul*100>li*100>a*100"), None);
+    assert_eq!(html("((((p*10)*10)*10)*10)*10"), None);
+    // Up to the cap still expands
+    assert_eq!(html_text("(p*50)*100").matches("<p>").count(), 5000);
+    assert_eq!(html_text("ul*10>li*10").matches("<li>").count(), 100);
+}
+
+#[test]
+fn deep_nesting_is_refused() {
+    // Too deep to expand, whether by children or by groups
+    assert_eq!(html("div>".repeat(100_000).trim_end_matches('>')), None);
+    assert_eq!(html(&format!("{}p{}", "(".repeat(100_000), ")".repeat(100_000))), None);
+    // Reasonable nesting still expands
+    assert_eq!(html_text("b>".repeat(20).trim_end_matches('>')).matches("<b>").count(), 20);
+    assert_eq!(html_text("((((p))))"), "<p></p>");
+}
+
+#[test]
+fn css_properties() {
+    let expansion = emmet::expand(Syntax::Css, "  m10", 5, "  ").unwrap();
+    assert_eq!(expansion.start, 2);
+    assert_eq!(expansion.text, "margin: 10px;");
+}
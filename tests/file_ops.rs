@@ -0,0 +1,62 @@
+//! Renaming over an existing file must never lose it when the move fails.
+
+mod common;
+
+use std::fs;
+
+use common::temp_dir;
+use lux_edit::file_ops;
+
+#[test]
+fn failed_rename_keeps_the_existing_file() {
+    let temp = temp_dir();
+    let dir = temp.path();
+    let to = dir.join("kept.txt");
+    fs::write(&to, "keep me").unwrap();
+
+    assert!(file_ops::rename(&dir.join("missing.txt"), &to).is_err());
+    assert_eq!(fs::read_to_string(&to).unwrap(), "keep me");
+    // Nothing staged is left behind either
+    assert_eq!(fs::read_dir(dir).unwrap().count(), 1);
+}
+
+#[test]
+fn rename_to_a_free_name() {
+    let temp = temp_dir();
+    let dir = temp.path();
+    let (from, to) = (dir.join("a.txt"), dir.join("b.txt"));
+    fs::write(&from, "text").unwrap();
+
+    file_ops::rename(&from, &to).unwrap();
+    assert!(!from.exists());
+    assert_eq!(fs::read_to_string(&to).unwrap(), "text");
+}
+
+#[test]
+fn rename_into_a_missing_folder_fails_cleanly() {
+    let temp = temp_dir();
+    let dir = temp.path();
+    let from = dir.join("a.txt");
+    fs::write(&from, "text").unwrap();
+
+    assert!(file_ops::rename(&from, &dir.join("nowhere").join("a.txt")).is_err());
+    assert_eq!(fs::read_to_string(&from).unwrap(), "text");
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+#[test]
+fn rename_over_a_file_trashes_it() {
+    let temp = temp_dir();
+    let dir = temp.path();
+    // Keep the trash inside the test directory
+    std::env::set_var("XDG_DATA_HOME", dir.join("data"));
+    let (from, to) = (dir.join("new.txt"), dir.join("old.txt"));
+    fs::write(&from, "new").unwrap();
+    fs::write(&to, "old").unwrap();
+
+    file_ops::rename(&from, &to).unwrap();
+    assert!(!from.exists());
+    assert_eq!(fs::read_to_string(&to).unwrap(), "new");
+    let trashed = dir.join("data").join("Trash").join("files").join("old.txt");
+    assert_eq!(fs::read_to_string(trashed).unwrap(), "old");
+}
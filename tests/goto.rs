@@ -0,0 +1,63 @@
+//! What Go to Line accepts, and where each form puts the cursor.
+
+use lux_edit::editor::{Editor, Position};
+
+/// A 100-line buffer with the cursor on line 50, column 1.
+fn editor() -> Editor {
+    let mut editor = Editor::new();
+    let text: String = (1..=100).map(|n| format!("line {}\n", n)).collect();
+    editor.set_text(text.trim_end());
+    editor.goto_line(50);
+    editor
+}
+
+/// Where `target` moves the cursor, as 1-based line and column, or None if
+/// it is rejected.
+fn goto(target: &str) -> Option<(usize, usize)> {
+    let mut editor = editor();
+    editor.goto(target).then(|| {
+        let Position { line, col } = editor.cursors[0].pos;
+        (line + 1, col + 1)
+    })
+}
+
+#[test]
+fn line_and_column() {
+    assert_eq!(goto("42"), Some((42, 1)));
+    assert_eq!(goto(" 42 "), Some((42, 1)));
+    assert_eq!(goto("42:7"), Some((42, 7)));
+    // Past the end lands on the last line, and the column on its end
+    assert_eq!(goto("1000"), Some((100, 1)));
+    assert_eq!(goto("3:99"), Some((3, 7)));
+}
+
+#[test]
+fn relative_lines() {
+    assert_eq!(goto("+10"), Some((60, 1)));
+    assert_eq!(goto("-10"), Some((40, 1)));
+    assert_eq!(goto("+ 3"), Some((53, 1)));
+    assert_eq!(goto("-80"), Some((1, 1)));
+    assert_eq!(goto("+5:3"), Some((55, 3)));
+}
+
+#[test]
+fn percentages() {
+    assert_eq!(goto("0%"), Some((1, 1)));
+    assert_eq!(goto("100%"), Some((100, 1)));
+    assert_eq!(goto("50%"), Some((51, 1)));
+    assert_eq!(goto("250%"), Some((100, 1)));
+}
+
+#[test]
+fn invalid_input_is_rejected() {
+    for target in ["", "abc", "++2", "+-", "--2", "+", "4 2", "42:", "42:x", "42:+7", "nan%", "inf%", "%", "1.5"] {
+        assert_eq!(goto(target), None, "{:?}", target);
+    }
+}
+
+#[test]
+fn rejected_input_leaves_the_cursor() {
+    let mut editor = editor();
+    assert!(!editor.goto("++2"));
+    assert_eq!(editor.cursors[0].pos, Position::new(49, 0));
+}
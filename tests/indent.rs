@@ -0,0 +1,72 @@
+//! Auto-indent as text is typed: Enter between brackets, and block-closing
+//! words moving back a level once they are complete.
+
+use lux_edit::editor::{Cursor, Editor, Position};
+
+/// A buffer for a file named `name` holding `text`, with the cursor at its
+/// end.
+fn editor(name: &str, text: &str) -> Editor {
+    let mut editor = Editor::from_text(Some(name.into()), text);
+    editor.move_to_end(false);
+    editor
+}
+
+/// Type `text` a character at a time, pressing Enter for `\n`.
+fn type_keys(editor: &mut Editor, text: &str) {
+    for c in text.chars() {
+        if c == '\n' {
+            editor.insert_newline();
+        } else {
+            editor.type_text(&c.to_string());
+        }
+    }
+}
+
+fn typed(name: &str, text: &str, keys: &str) -> String {
+    let mut editor = editor(name, text);
+    type_keys(&mut editor, keys);
+    editor.rope.to_string()
+}
+
+#[test]
+fn enter_between_brackets_opens_an_indented_line() {
+    let mut editor = editor("a.rs", "fn main() {}");
+    editor.cursors = vec![Cursor::new(0, 11)];
+    editor.insert_newline();
+    assert_eq!(editor.rope.to_string(), "fn main() {\n    \n}");
+    assert_eq!(editor.cursors[0].pos, Position::new(1, 4));
+}
+
+#[test]
+fn closing_bracket_goes_back_to_its_opener() {
+    assert_eq!(typed("a.rs", "fn main() {\n    x();\n    ", "}"), "fn main() {\n    x();\n}");
+}
+
+#[test]
+fn closer_words_dedent_once_complete() {
+    let script = "if true; then\n    echo yes\n    ";
+    assert_eq!(typed("a.sh", script, "fi\n"), "if true; then\n    echo yes\nfi\n");
+    assert_eq!(typed("a.sh", script, "fi;"), "if true; then\n    echo yes\nfi;");
+    assert_eq!(typed("a.sh", script, "else "), "if true; then\n    echo yes\nelse ");
+    let ruby = "def f\n  x\n  ";
+    assert_eq!(typed("a.rb", ruby, "end\n"), "def f\n  x\nend\n");
+}
+
+#[test]
+fn words_starting_with_a_closer_keep_their_indentation() {
+    let script = "if true; then\n    ";
+    assert_eq!(typed("a.sh", script, "file=a"), "if true; then\n    file=a");
+    assert_eq!(typed("a.rb", "def f\n  ", "endpoint = 1"), "def f\n  endpoint = 1");
+    assert_eq!(typed("a.lua", "if x then\n    ", "ending()"), "if x then\n    ending()");
+    assert_eq!(typed("a.c", "if (x) {\n    y();\n    ", "elsewhere();"), "if (x) {\n    y();\n    elsewhere();");
+}
+
+#[test]
+fn python_closers_dedent_at_the_colon() {
+    let code = "try:\n    f()\n    ";
+    assert_eq!(typed("a.py", code, "exception = 1"), "try:\n    f()\n    exception = 1");
+    // Nothing moves until the statement is complete
+    assert_eq!(typed("a.py", code, "except "), "try:\n    f()\n    except ");
+    assert_eq!(typed("a.py", code, "except ValueError:"), "try:\n    f()\nexcept ValueError:");
+    assert_eq!(typed("a.py", "if x:\n    y()\n    ", "else:\n"), "if x:\n    y()\nelse:\n    ");
+}
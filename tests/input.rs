@@ -0,0 +1,52 @@
+//! The second key of a chord is taken out of the frame's events without
+//! losing anything else typed in the same frame.
+
+use eframe::egui::{self, Event, Key, Modifiers};
+use lux_edit::ui::input;
+
+fn key(key: Key, pressed: bool) -> Event {
+    Event::Key { key, physical_key: None, pressed, repeat: false, modifiers: Modifiers::NONE }
+}
+
+/// Run one frame with `events`, taking the chord key, and return it with
+/// the events left for widgets.
+fn take_chord_key(events: Vec<Event>) -> (Option<Key>, Vec<Event>) {
+    let ctx = egui::Context::default();
+    let mut taken = None;
+    let mut left = Vec::new();
+    let _ = ctx.run(egui::RawInput { events, ..Default::default() }, |ctx| {
+        taken = input::take_chord_key(ctx);
+        left = ctx.input(|i| i.events.clone());
+    });
+    (taken, left)
+}
+
+#[test]
+fn takes_the_chord_key_and_its_text() {
+    let events = vec![
+        key(Key::W, true),
+        Event::Text("w".into()),
+        key(Key::W, false),
+        key(Key::X, true),
+        Event::Text("x".into()),
+    ];
+    let (taken, left) = take_chord_key(events);
+    assert_eq!(taken, Some(Key::W));
+    assert_eq!(left, vec![key(Key::X, true), Event::Text("x".into())]);
+}
+
+#[test]
+fn keeps_events_before_the_chord_key() {
+    let events = vec![Event::Text("a".into()), key(Key::Escape, true), key(Key::B, true), Event::Text("b".into())];
+    let (taken, left) = take_chord_key(events);
+    assert_eq!(taken, Some(Key::Escape));
+    assert_eq!(left, vec![Event::Text("a".into()), key(Key::B, true), Event::Text("b".into())]);
+}
+
+#[test]
+fn nothing_pressed_takes_nothing() {
+    let events = vec![Event::Text("a".into()), key(Key::A, false)];
+    let (taken, left) = take_chord_key(events.clone());
+    assert_eq!(taken, None);
+    assert_eq!(left, events);
+}
@@ -0,0 +1,67 @@
+//! The job pool delivers a panicking job's failure in place of its result
+//! and keeps running other jobs.
+
+use std::time::{Duration, Instant};
+
+use eframe::egui;
+use lux_edit::activity::Activities;
+use lux_edit::jobs::{JobPanic, Jobs};
+
+#[derive(Debug)]
+enum Done {
+    Value(u32),
+    Panicked(JobPanic),
+}
+
+impl From<JobPanic> for Done {
+    fn from(panic: JobPanic) -> Self {
+        Done::Panicked(panic)
+    }
+}
+
+/// Wait for `count` results.
+fn collect(jobs: &Jobs<Done>, count: usize) -> Vec<Done> {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let mut done = Vec::new();
+    while done.len() < count && Instant::now() < deadline {
+        done.extend(jobs.finished());
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    done
+}
+
+#[test]
+fn panicking_job_reports_to_its_owner() {
+    let ctx = egui::Context::default();
+    let activities = Activities::new(ctx.clone());
+    let mut jobs = Jobs::new(ctx);
+
+    jobs.spawn(activities.start("Exploding"), |_| panic!("boom"));
+    let done = collect(&jobs, 1);
+    let [Done::Panicked(panic)] = done.as_slice() else {
+        panic!("expected a panic, got {:?}", done);
+    };
+    assert_eq!(panic.label, "Exploding");
+    assert_eq!(panic.message, "boom");
+
+    // The worker that caught it still takes jobs
+    jobs.spawn(activities.start("Counting"), |_| Done::Value(7));
+    let done = collect(&jobs, 1);
+    assert!(matches!(done.as_slice(), [Done::Value(7)]), "{:?}", done);
+}
+
+#[test]
+fn cancelled_job_delivers_nothing() {
+    let ctx = egui::Context::default();
+    let activities = Activities::new(ctx.clone());
+    let mut jobs = Jobs::new(ctx);
+
+    let activity = activities.start_cancellable("Cancelled");
+    activities.cancel(activity.id());
+    jobs.spawn(activity, |_| Done::Value(1));
+    jobs.spawn(activities.start("Kept"), |_| Done::Value(2));
+    let done = collect(&jobs, 1);
+    std::thread::sleep(Duration::from_millis(50));
+    assert!(jobs.finished().is_empty());
+    assert!(matches!(done.as_slice(), [Done::Value(2)]), "{:?}", done);
+}
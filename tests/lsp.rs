@@ -0,0 +1,72 @@
+//! Positions and document changes as language servers are sent them.
+
+use lux_edit::lsp::{incremental_change, PositionEncoding};
+use ropey::Rope;
+use serde_json::json;
+
+#[test]
+fn utf16_columns_count_surrogate_pairs() {
+    let line = "a😀b";
+    let utf16 = PositionEncoding::Utf16;
+    assert_eq!(utf16.to_server(line.chars(), 0), 0);
+    assert_eq!(utf16.to_server(line.chars(), 1), 1);
+    assert_eq!(utf16.to_server(line.chars(), 2), 3);
+    assert_eq!(utf16.to_server(line.chars(), 3), 4);
+    assert_eq!(utf16.from_server(line.chars(), 3), 2);
+    assert_eq!(utf16.from_server(line.chars(), 4), 3);
+    // Inside the pair
+    assert_eq!(utf16.from_server(line.chars(), 2), 2);
+    // Past the end stops there
+    assert_eq!(utf16.from_server(line.chars(), 10), 3);
+}
+
+#[test]
+fn utf32_columns_are_chars() {
+    let utf32 = PositionEncoding::Utf32;
+    assert_eq!(utf32.to_server("😀😀".chars(), 2), 2);
+    assert_eq!(utf32.from_server("😀😀".chars(), 1), 1);
+}
+
+#[test]
+fn incremental_change_covers_only_what_changed() {
+    let old = Rope::from_str("fn main() {\n    let x = 1;\n}\n");
+    let new = Rope::from_str("fn main() {\n    let xy = 1;\n}\n");
+    assert_eq!(
+        incremental_change(&old, &new, PositionEncoding::Utf16),
+        Some(json!({
+            "range": { "start": { "line": 1, "character": 9 }, "end": { "line": 1, "character": 9 } },
+            "text": "y",
+        }))
+    );
+}
+
+#[test]
+fn incremental_change_across_lines() {
+    let old = Rope::from_str("one\ntwo\nthree\n");
+    let new = Rope::from_str("one\nthree\n");
+    let change = incremental_change(&old, &new, PositionEncoding::Utf32).unwrap();
+    // The shortest change: "two\nthree" loses "wo\nt"
+    assert_eq!(change["range"]["start"], json!({ "line": 1, "character": 1 }));
+    assert_eq!(change["range"]["end"], json!({ "line": 2, "character": 1 }));
+    assert_eq!(change["text"], "");
+}
+
+#[test]
+fn incremental_change_counts_utf16_columns() {
+    let old = Rope::from_str("😀 = 1");
+    let new = Rope::from_str("😀 = 2");
+    let change = incremental_change(&old, &new, PositionEncoding::Utf16).unwrap();
+    assert_eq!(change["range"]["start"], json!({ "line": 0, "character": 5 }));
+    assert_eq!(change["range"]["end"], json!({ "line": 0, "character": 6 }));
+    assert_eq!(change["text"], "2");
+}
+
+#[test]
+fn repeated_text_is_not_counted_twice() {
+    // The common prefix and suffix overlap in "aaa" -> "aaaa"
+    let change = incremental_change(&Rope::from_str("aaa"), &Rope::from_str("aaaa"), PositionEncoding::Utf32).unwrap();
+    assert_eq!(change["range"]["start"], json!({ "line": 0, "character": 3 }));
+    assert_eq!(change["range"]["end"], json!({ "line": 0, "character": 3 }));
+    assert_eq!(change["text"], "a");
+    assert_eq!(incremental_change(&Rope::from_str("same"), &Rope::from_str("same"), PositionEncoding::Utf32), None);
+}
@@ -0,0 +1,85 @@
+//! Rewriting references after a file moves: which quoted paths count as
+//! references, and the moved file's own relative imports.
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use common::temp_dir;
+use lux_edit::edits;
+use lux_edit::path_refs::{self, ReferenceEdit};
+
+/// Write `files`, relative to `dir`, then move `from` to `to` and find the
+/// references the move breaks.
+fn move_file(dir: &Path, files: &[(&str, &str)], from: &str, to: &str) -> Vec<ReferenceEdit> {
+    for (name, content) in files {
+        let path = dir.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+    let (old, new) = (dir.join(from), dir.join(to));
+    fs::create_dir_all(new.parent().unwrap()).unwrap();
+    fs::rename(&old, &new).unwrap();
+    let mut found = Vec::new();
+    path_refs::collect_files(dir, &mut found);
+    found.sort();
+    path_refs::find_references(&found, &[], &old, &new)
+}
+
+fn lines(edits: &[ReferenceEdit]) -> Vec<(&str, &str)> {
+    edits.iter().map(|e| (e.old_line.as_str(), e.new_line.as_str())).collect()
+}
+
+#[test]
+fn rewrites_paths_to_the_moved_file() {
+    let temp = temp_dir();
+    let dir = temp.path();
+    let main = "import u from './util';\n#include \"util.js\"\nconst name = \"./util\";\nconst os = require(\"util\");\n";
+    let edits = move_file(dir, &[("main.js", main), ("util.js", "")], "util.js", "lib/util.js");
+    assert_eq!(
+        lines(&edits),
+        vec![
+            ("import u from './util';", "import u from './lib/util';"),
+            ("#include \"util.js\"", "#include \"lib/util.js\""),
+        ]
+    );
+}
+
+#[test]
+fn moved_file_keeps_its_own_imports_working() {
+    let temp = temp_dir();
+    let dir = temp.path();
+    let a = "import b from './b';\nimport c from '../c.js';\nimport React from 'react';\nconst s = './nothing';\n";
+    let files = [("src/a.js", a), ("src/b.ts", ""), ("c.js", "")];
+    let edits = move_file(dir, &files, "src/a.js", "src/sub/a.js");
+    assert!(edits.iter().all(|e| e.path == dir.join("src/sub/a.js")));
+    assert_eq!(
+        lines(&edits),
+        vec![
+            ("import b from './b';", "import b from '../b';"),
+            ("import c from '../c.js';", "import c from '../../c.js';"),
+        ]
+    );
+}
+
+#[test]
+fn rewrites_apply_all_or_nothing() {
+    let temp = temp_dir();
+    let dir = temp.path();
+    let files = [("a.js", "import u from './util';\n"), ("b.js", "import u from './util.js';\n"), ("util.js", "")];
+    let edits = move_file(dir, &files, "util.js", "lib/util.js");
+    assert_eq!(edits.len(), 2);
+    let edit = path_refs::workspace_edit(&edits);
+
+    // a.js changed after the references were found
+    fs::write(dir.join("a.js"), "// gone\n").unwrap();
+    assert!(edits::apply(&edit, "Update References", &mut []).is_err());
+    assert_eq!(fs::read_to_string(dir.join("b.js")).unwrap(), "import u from './util.js';\n");
+
+    fs::write(dir.join("a.js"), "import u from './util';\n").unwrap();
+    let report = edits::apply(&edit, "Update References", &mut []).unwrap();
+    assert_eq!(report.edit_count(), 2);
+    assert_eq!(fs::read_to_string(dir.join("a.js")).unwrap(), "import u from './lib/util';\n");
+    assert_eq!(fs::read_to_string(dir.join("b.js")).unwrap(), "import u from './lib/util.js';\n");
+}
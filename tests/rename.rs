@@ -0,0 +1,92 @@
+//! Rename Symbol on files that aren't open: the occurrences found on disk
+//! must line up with the text the edits are applied to, whatever the
+//! file's byte-order mark and line endings.
+
+mod common;
+
+use std::fs;
+use std::path::PathBuf;
+
+use common::temp_dir;
+use lux_edit::editor::Editor;
+use lux_edit::edits::{self, TextEdit, WorkspaceEdit};
+use lux_edit::workspace_search;
+
+/// The edit renaming every whole-word `old` in `path` to `new`, checked
+/// against the word as the rename window makes it.
+fn rename_edit(path: &PathBuf, old: &str, new: &str) -> WorkspaceEdit {
+    let hits = workspace_search::find_word(std::slice::from_ref(path), &[], old, 100);
+    let mut edit = WorkspaceEdit::new();
+    for hit in hits {
+        let (text, old) = (new.to_string(), Some(old.to_string()));
+        edit.entry(hit.path).or_default().push(TextEdit { start: hit.start, end: hit.end, text, old });
+    }
+    edit
+}
+
+/// Rename every whole-word `old` in the closed file holding `content` to
+/// `new`, and return what the file holds afterwards.
+fn rename_in_file(content: &str, old: &str, new: &str) -> String {
+    let temp = temp_dir();
+    let dir = temp.path();
+    let path = dir.join("file.txt");
+    fs::write(&path, content).unwrap();
+
+    let edit = rename_edit(&path, old, new);
+    edits::apply(&edit, "Rename Symbol", &mut []).unwrap();
+
+    fs::read_to_string(&path).unwrap()
+}
+
+#[test]
+fn rename_keeps_byte_order_mark() {
+    let renamed = rename_in_file("\u{FEFF}let foo = 1;\nfoo();\n", "foo", "bar");
+    assert_eq!(renamed, "\u{FEFF}let bar = 1;\nbar();\n");
+}
+
+#[test]
+fn rename_keeps_crlf_endings() {
+    let renamed = rename_in_file("let foo = 1;\r\nfoo(foo);\r\n", "foo", "bar");
+    assert_eq!(renamed, "let bar = 1;\r\nbar(bar);\r\n");
+}
+
+#[test]
+fn rename_in_bom_crlf_file() {
+    let renamed = rename_in_file("\u{FEFF}foo\r\n  foo.x\r\n", "foo", "quux");
+    assert_eq!(renamed, "\u{FEFF}quux\r\n  quux.x\r\n");
+}
+
+#[test]
+fn finds_words_in_open_buffer_rather_than_on_disk() {
+    let temp = temp_dir();
+    let dir = temp.path();
+    let path = dir.join("open.txt");
+    fs::write(&path, "foo\n").unwrap();
+    let open = vec![(path.clone(), ropey::Rope::from_str("x\nfoo foo\n"))];
+
+    let hits = workspace_search::find_word(std::slice::from_ref(&path), &open, "foo", 100);
+    let positions: Vec<(usize, usize)> = hits.iter().map(|h| (h.start.line, h.start.col)).collect();
+    assert_eq!(positions, vec![(1, 0), (1, 4)]);
+}
+
+#[test]
+fn rename_keeps_mixed_line_endings() {
+    let renamed = rename_in_file("foo\r\nb\nfoo\n\r\nc", "foo", "bar");
+    assert_eq!(renamed, "bar\r\nb\nbar\n\r\nc");
+}
+
+#[test]
+fn rename_refuses_text_changed_since_the_search() {
+    let temp = temp_dir();
+    let dir = temp.path();
+    let path = dir.join("file.txt");
+    fs::write(&path, "foo();\nfoo();\n").unwrap();
+    let edit = rename_edit(&path, "foo", "bar");
+    // Edited before Rename was clicked
+    let mut editors = vec![Editor::from_file(path.clone()).unwrap()];
+    editors[0].set_text("foo();\nqux();\n");
+
+    let error = edits::apply(&edit, "Rename Symbol", &mut editors).unwrap_err();
+    assert_eq!(error.message, "line 2 has changed since the edit was made");
+    assert_eq!(editors[0].rope.to_string(), "foo();\nqux();\n");
+}
@@ -0,0 +1,87 @@
+//! Waiting for the file given with --wait: the tab is followed through Save
+//! As and moves, and only a save to the original path counts.
+
+mod common;
+
+use std::fs;
+
+use common::temp_dir;
+use lux_edit::editor::Editor;
+use tempfile::TempDir;
+use lux_edit::wait::WaitedFile;
+
+/// A waited-for editor on a `COMMIT_EDITMSG` in a fresh directory.
+fn open_waited() -> (TempDir, Vec<Editor>, WaitedFile) {
+    let dir = temp_dir();
+    let path = dir.path().join("COMMIT_EDITMSG");
+    fs::write(&path, "\n# Please enter the commit message\n").unwrap();
+    let editors = vec![Editor::from_file(path.clone()).unwrap()];
+    let waited = WaitedFile::new(path, &editors[0]);
+    (dir, editors, waited)
+}
+
+#[test]
+fn closing_saved_file_succeeds() {
+    let (_dir, mut editors, mut waited) = open_waited();
+    editors[0].insert_text("Fix the thing");
+    assert!(waited.observe(&editors));
+    assert!(waited.discarded());
+
+    editors[0].save().unwrap();
+    assert!(waited.observe(&editors));
+    editors.clear();
+    assert!(!waited.observe(&editors));
+    assert!(!waited.discarded());
+}
+
+#[test]
+fn closing_with_unsaved_changes_fails() {
+    let (_dir, mut editors, mut waited) = open_waited();
+    editors[0].insert_text("Fix the thing");
+    waited.observe(&editors);
+    editors.clear();
+    assert!(!waited.observe(&editors));
+    assert!(waited.discarded());
+}
+
+#[test]
+fn save_as_elsewhere_leaves_file_unwritten() {
+    let (dir, mut editors, mut waited) = open_waited();
+    editors[0].insert_text("Fix the thing");
+    waited.observe(&editors);
+    editors[0].save_as(dir.path().join("elsewhere.txt")).unwrap();
+
+    // Still the waited tab, though it holds another file now
+    assert!(waited.observe(&editors));
+    assert!(waited.is_tab(&editors[0]));
+    editors.clear();
+    assert!(!waited.observe(&editors));
+    assert!(waited.discarded());
+}
+
+#[test]
+fn save_then_save_as_keeps_saved_file() {
+    let (dir, mut editors, mut waited) = open_waited();
+    editors[0].insert_text("Fix the thing");
+    editors[0].save().unwrap();
+    waited.observe(&editors);
+    editors[0].save_as(dir.path().join("copy.txt")).unwrap();
+    editors[0].insert_text(" and more");
+    waited.observe(&editors);
+    assert!(!waited.discarded());
+}
+
+#[test]
+fn moving_file_away_fails() {
+    let (dir, mut editors, mut waited) = open_waited();
+    editors[0].insert_text("Fix the thing");
+    editors[0].save().unwrap();
+    waited.observe(&editors);
+    editors[0].rename_file(dir.path().join("moved.txt")).unwrap();
+
+    assert!(waited.observe(&editors));
+    assert!(waited.discarded());
+    editors.clear();
+    assert!(!waited.observe(&editors));
+    assert!(waited.discarded());
+}